@@ -0,0 +1,62 @@
+use std::path::{Path, PathBuf};
+use forge::conformance::{Case, find_cases};
+
+/// Run every case in the directories (or the files given directly), reporting each case whose output or errors differ
+/// from those its annotations expect. Returns whether all of the cases passed.
+pub fn run(paths: Vec<String>, fail_fast: bool) -> bool {
+    let mut files: Vec<PathBuf> = Vec::new();
+    for path in &paths {
+        if Path::new(path).is_dir() {
+            match find_cases(path) {
+                Ok(cases) => files.extend(cases),
+                Err(err) => {
+                    println!("Could not read directory '{}': {}", path, err);
+                    return false;
+                },
+            }
+        } else {
+            files.push(PathBuf::from(path));
+        }
+    }
+
+    let mut failures = Vec::new();
+    let mut passed = 0;
+    println!("running {} case{}", files.len(), if files.len() == 1 { "" } else { "s" });
+    for file in &files {
+        let case = match Case::load(file) {
+            Ok(case) => case,
+            Err(_) => {
+                println!("Could not open file '{}'", file.display());
+                return false;
+            },
+        };
+        let mismatches = case.run();
+        if mismatches.is_empty() {
+            println!("case {} ... ok", file.display());
+            passed += 1;
+        } else {
+            println!("case {} ... FAILED", file.display());
+            failures.push((file, mismatches));
+            if fail_fast {
+                break;
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (file, mismatches) in &failures {
+            println!("\n---- {} ----", file.display());
+            for mismatch in mismatches {
+                print!("{}", mismatch);
+            }
+        }
+    }
+    println!(
+        "\ngrammar result: {}. {} passed; {} failed.",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len(),
+    );
+    failures.is_empty()
+}
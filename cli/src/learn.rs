@@ -0,0 +1,199 @@
+use std::{
+    cell::RefCell,
+    rc::Rc,
+};
+use forge::{DefaultIo, Engine, ExecResult, ForgeResult, Io, Value};
+use crate::repl::{self, Entry, Input};
+
+/// A step of the tutorial: something to read, then a task to do at the prompt.
+struct Lesson {
+    title: &'static str,
+    text: &'static str,
+    task: &'static str,
+    /// Run before the lesson starts, for tasks that work with values that they are given.
+    setup: &'static str,
+    /// Run after each input, failing (through `expect`) until the task has been done. The lines printed during the
+    /// lesson are in `printed`.
+    check: &'static str,
+    /// Input that passes the check, shown when asked for.
+    answer: &'static str,
+}
+
+const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Printing",
+        text: "Statements end with a semicolon. `print` shows the value of an expression, such as the text in a string.",
+        task: "Print the string \"Hello, world!\".",
+        setup: "",
+        check: r#"expect(printed).to_contain("Hello, world!");"#,
+        answer: r#"print "Hello, world!";"#,
+    },
+    Lesson {
+        title: "Variables",
+        text: "`var` declares a variable and gives it a value. Typing an expression on its own shows its value.",
+        task: "Declare a variable named `age` that holds 30.",
+        setup: "",
+        check: "expect(age).to_equal(30);",
+        answer: "var age = 30;",
+    },
+    Lesson {
+        title: "Arithmetic",
+        text: "Numbers can be added with `+`, subtracted with `-`, multiplied with `*` and divided with `/`. Parentheses \
+            group what should be worked out first.",
+        task: "Declare `minutes` as the number of minutes in a week, working it out with `*` rather than by hand.",
+        setup: "",
+        check: "expect(minutes).to_equal(10080);",
+        answer: "var minutes = 7 * 24 * 60;",
+    },
+    Lesson {
+        title: "Strings",
+        text: "An expression in braces within a string is replaced by its value, so \"{1 + 1}\" is the string \"2\".",
+        task: "The variable `name` holds a name. Declare `greeting` as \"Hello, \" followed by the name and then \"!\".",
+        setup: r#"var name = "Ada";"#,
+        check: r#"expect(greeting).to_equal("Hello, Ada!");"#,
+        answer: r#"var greeting = "Hello, {name}!";"#,
+    },
+    Lesson {
+        title: "Lists",
+        text: "A list holds values in order, written between brackets. Items are read by their index, starting at 0, as \
+            in `list[0]`.",
+        task: "Declare `primes` as a list of the first four prime numbers: 2, 3, 5 and 7.",
+        setup: "",
+        check: "expect(primes).to_equal([2, 3, 5, 7]);",
+        answer: "var primes = [2, 3, 5, 7];",
+    },
+    Lesson {
+        title: "Conditions",
+        text: "`if` runs a block only when its condition is true, and `else` runs another block when it is not. \
+            Comparisons such as `<` and `==` produce true or false.",
+        task: "The variable `n` holds a number. Declare `sign`, then set it to \"negative\" if `n` is below 0 and to \
+            \"positive\" otherwise.",
+        setup: "var n = -4;",
+        check: r#"expect(sign).to_equal("negative");"#,
+        answer: r#"var sign = "positive"; if n < 0 { sign = "negative"; }"#,
+    },
+    Lesson {
+        title: "Loops",
+        text: "`for` runs a block once for each item of a list or range. The range `1..5` holds the numbers from 1 up \
+            to, but not including, 5. `+=` adds to a variable.",
+        task: "The variable `total` holds 0. Use a `for` loop to add the numbers from 1 to 10 to it.",
+        setup: "var total = 0;",
+        check: "expect(total).to_equal(55);",
+        answer: "for i in 1..11 { total += i; }",
+    },
+    Lesson {
+        title: "Functions",
+        text: "`fn` declares a function with the given parameters. `return` ends the function and produces its result.",
+        task: "Declare a function named `square` that produces its argument multiplied by itself.",
+        setup: "",
+        check: "expect(square(4)).to_equal(16);\nexpect(square(-3)).to_equal(9);",
+        answer: "fn square(x) { return x * x; }",
+    },
+    Lesson {
+        title: "Lambdas",
+        text: "Functions are values too. `|x| { ... }` makes a function without a name, which can be stored in a \
+            variable or passed to another function.",
+        task: "Declare `twice` as a function that takes a function `f` and a value `x`, and produces `f(f(x))`.",
+        setup: "",
+        check: "expect(twice(|x| { return x + 3; }, 1)).to_equal(7);",
+        answer: "var twice = |f, x| { return f(f(x)); };",
+    },
+    Lesson {
+        title: "Maps",
+        text: "A map holds values by their keys, written as `[\"key\": value]`. `map.key` reads the same item as \
+            `map[\"key\"]`.",
+        task: "Declare `book` as a map whose `title` is \"Dune\" and whose `year` is 1965.",
+        setup: "",
+        check: "expect(book.title).to_equal(\"Dune\");\nexpect(book.year).to_equal(1965);",
+        answer: r#"var book = ["title": "Dune", "year": 1965];"#,
+    },
+    Lesson {
+        title: "Classes",
+        text: "A class groups methods that share a `self`. `Name.new(...)` makes an instance, calling the class's \
+            `new` method with the instance as `self`, and `value is Name` checks whether a value is an instance.",
+        task: "Declare a class `Counter` whose `new` method sets `self.count` to 0, and whose `add` method adds 1 to it.",
+        setup: "",
+        check: "var tally = Counter.new();\ntally.add();\ntally.add();\nexpect(tally.count).to_equal(2);",
+        answer: "class Counter { fn new() { self.count = 0; } fn add() { self.count += 1; } }",
+    },
+];
+
+const COMMANDS: &str = "Type :check to see what is still missing, :answer to see an answer, :skip to move on or :quit to \
+    stop.";
+
+/// Shows printed lines as usual, and also keeps them so that lessons can check what was printed.
+struct Recorder(Rc<RefCell<Vec<String>>>);
+
+impl Io for Recorder {
+    fn input(&mut self, s: String) -> ExecResult<String> {
+        DefaultIo.input(s)
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.0.borrow_mut().push(s.clone());
+        DefaultIo.print(s)
+    }
+}
+
+fn check(engine: &mut Engine, printed: &Rc<RefCell<Vec<String>>>, lesson: &Lesson) -> ForgeResult<()> {
+    let lines = printed.borrow().iter().cloned().map(Value::from).collect::<Vec<_>>();
+    engine.set("printed", lines);
+    engine.safe_exec(lesson.check)
+}
+
+/// Walk through the lessons, starting at the given one. Answers are run in an engine that may not touch the files,
+/// environment or network of the machine, and lessons build upon the variables that earlier ones declared.
+pub fn run(start: usize) {
+    println!("Welcome to the Forge tutorial. {}", COMMANDS);
+
+    let printed = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::build().with_io(Recorder(printed.clone())).finish();
+    #[cfg(unix)]
+    crate::signal::forward_interrupts(engine.interrupt_handle());
+
+    let mut input = Input::open();
+    for (i, lesson) in LESSONS.iter().enumerate().skip(start) {
+        println!("\nLesson {} of {}: {}\n", i + 1, LESSONS.len(), lesson.title);
+        println!("{}\n", lesson.text);
+        println!("Task: {}", lesson.task);
+        printed.borrow_mut().clear();
+        if let Err(err) = engine.safe_exec(lesson.setup) {
+            repl::show(&engine, &Err(err));
+        }
+
+        loop {
+            match input.read(&mut engine) {
+                None => return,
+                Some(Entry::Ran(result)) => {
+                    repl::show(&engine, &result);
+                    if result.is_ok() && check(&mut engine, &printed, lesson).is_ok() {
+                        println!("Well done!");
+                        break;
+                    }
+                },
+                Some(Entry::Command(command)) => match command.as_str() {
+                    ":check" => match check(&mut engine, &printed, lesson) {
+                        Ok(()) => {
+                            println!("Well done!");
+                            break;
+                        },
+                        Err(err) => repl::show(&engine, &Err(err)),
+                    },
+                    ":answer" => println!("{}", lesson.answer),
+                    ":skip" => break,
+                    ":quit" => return,
+                    _ => println!("Unknown command '{}'. {}", command, COMMANDS),
+                },
+            }
+        }
+    }
+    println!("\nThat was the last lesson. Run `forge` on its own for a prompt, or `forge file.fg` to run a script.");
+}
+
+/// The number of the lesson to start at, as given to `forge learn`, counting from 1.
+pub fn parse_start(arg: &str) -> Option<usize> {
+    match arg.parse::<usize>() {
+        Ok(n) if n >= 1 && n <= LESSONS.len() => Some(n - 1),
+        _ => None,
+    }
+}
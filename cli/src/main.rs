@@ -2,48 +2,247 @@ use std::{
     env,
     fs,
     io::prelude::*,
+    path::Path,
+    process,
 };
-use forge::Engine;
-use rustyline::Editor;
+use forge::{Engine, EngineBuilder, ExecMode, ForgeError, DiagnosticStyle, Recording};
+use share::Snippet;
 
-fn prompt() {
-    println!("Welcome to the Forge prompt.");
-
-    let mut engine = Engine::default();
+#[cfg(feature = "repl")]
+mod repl;
+#[cfg(feature = "repl")]
+mod learn;
+mod grammar;
+mod runner;
+mod share;
+#[cfg(unix)]
+mod signal;
 
-    let mut rl = Editor::<()>::new();
-    while let Ok(line) = rl.readline(">> ") {
-        rl.add_history_entry(line.clone());
+/// Errors are shown in color when printed to a terminal, unless the user has asked for no color with `NO_COLOR`.
+fn diagnostic_style() -> DiagnosticStyle {
+    #[cfg(unix)]
+    let is_tty = unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 };
+    #[cfg(not(unix))]
+    let is_tty = false;
 
-        let _ = engine.prompt(&line)
-            .map(|val| val.map(|val| {
-                println!("{}", val.get_display_text().unwrap_or("<value cannot be displayed>".to_string()))
-            }))
-            .map_err(|err| print!("{}", err));
+    if is_tty && env::var_os("NO_COLOR").is_none() {
+        DiagnosticStyle::Colored
+    } else {
+        DiagnosticStyle::Plain
     }
 }
 
-fn exec(fname: &str) {
+#[derive(Copy, Clone, PartialEq)]
+enum ErrorFormat {
+    Human,
+    Json,
+}
+
+/// Read a script, printing an error if it cannot be opened.
+fn read_script(fname: &str) -> Option<String> {
     let mut code = String::new();
-    match fs::File::open(fname) {
-        Ok(mut file) => { file.read_to_string(&mut code).unwrap(); },
-        Err(_) => println!("Could not open file '{}'", fname),
+    match fs::File::open(fname).and_then(|mut file| file.read_to_string(&mut code)) {
+        Ok(_) => Some(code),
+        Err(_) => {
+            println!("Could not open file '{}'", fname);
+            None
+        },
     }
+}
+
+fn builder_for(fname: &str) -> EngineBuilder {
+    // Modules imported by the file are found relative to it
+    let root = Path::new(fname).parent().unwrap_or(Path::new("."));
+    Engine::build()
+        .with_module_root(root)
+        .allow_fs()
+        .allow_env()
+        .allow_net()
+        .allow_clock()
+}
+
+fn start(builder: EngineBuilder) -> Engine {
+    let engine = builder.finish();
+    #[cfg(unix)]
+    signal::forward_interrupts(engine.interrupt_handle());
+    engine
+}
 
-    let mut engine = Engine::default();
+fn report(err: &ForgeError, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Human => print!("{}", err.display_with(diagnostic_style())),
+        // Kept apart from the script's own output so that tools can read it
+        ErrorFormat::Json => eprintln!("{}", err.to_json()),
+    }
+}
 
+fn exec(fname: &str, error_format: ErrorFormat, record: Option<&str>, replay: Option<&str>) {
+    let code = read_script(fname).unwrap_or_default();
+    let mut builder = builder_for(fname);
+    if let Some(path) = replay {
+        match Recording::load(path) {
+            Ok(recording) => builder = builder.replay(recording),
+            Err(err) => return println!("Could not read recording '{}': {}", path, err),
+        }
+    }
+    if record.is_some() {
+        builder = builder.record();
+    }
+    let mut engine = start(builder);
     let _ = engine.exec(&code)
-        .map_err(|err| print!("{}", err));
+        .map_err(|err| report(&err, error_format));
+
+    // The recording is kept whether or not the script failed, since failures are what it is most useful for
+    if let (Some(path), Some(recording)) = (record, engine.recording()) {
+        if let Err(err) = recording.save(path) {
+            println!("Could not write recording '{}': {}", path, err);
+        }
+    }
+}
+
+/// Print a script as a snippet, which can be run elsewhere with `forge run --snippet`.
+fn share_file(fname: &str, exec_mode: ExecMode, seed: Option<u64>) {
+    if let Some(code) = read_script(fname) {
+        println!("{}", Snippet { code, exec_mode, seed }.encode());
+    }
+}
+
+fn run_snippet(snippet: &str, error_format: ErrorFormat) {
+    match Snippet::decode(snippet) {
+        Ok(snippet) => {
+            let mut engine = start(snippet.builder());
+            let _ = engine.exec(&snippet.code)
+                .map_err(|err| report(&err, error_format));
+        },
+        Err(msg) => {
+            println!("{}", msg);
+            process::exit(1);
+        },
+    }
 }
 
 fn usage() {
-    println!("Usage: forge [file]");
+    println!("Usage: forge [--error-format=human|json] [--record=path | --replay=path] [file]");
+    println!("       forge test [--error-format=human|json] [--filter text] [--fail-fast] [--update-snapshots] [file...]");
+    println!("       forge test-grammar [--fail-fast] dir...");
+    println!("       forge learn [lesson]");
+    println!("       forge share [--exec-mode=interpret|bytecode] [--seed=n] file");
+    println!("       forge run [--error-format=human|json] --snippet snippet");
+}
+
+#[cfg(feature = "repl")]
+fn prompt() {
+    repl::run();
+}
+
+#[cfg(not(feature = "repl"))]
+fn prompt() {
+    println!("This build of Forge does not include the interactive prompt (enable the 'repl' feature).");
+    usage();
+}
+
+#[cfg(feature = "repl")]
+fn learn(lesson: Option<&str>) {
+    match lesson.map(learn::parse_start) {
+        None => learn::run(0),
+        Some(Some(start)) => learn::run(start),
+        Some(None) => usage(),
+    }
+}
+
+// The tutorial is worked through at the interactive prompt
+#[cfg(not(feature = "repl"))]
+fn learn(_lesson: Option<&str>) {
+    prompt();
 }
 
 fn main() {
-    match &env::args().nth(1) {
-        None => prompt(),
-        Some(arg) if env::args().count() == 2 => exec(arg),
-        Some(_) => usage(),
+    let mut args = env::args().skip(1).peekable();
+    let test = args.peek().map(|arg| arg == "test").unwrap_or(false);
+    let test_grammar = args.peek().map(|arg| arg == "test-grammar").unwrap_or(false);
+    let share = args.peek().map(|arg| arg == "share").unwrap_or(false);
+    let run = args.peek().map(|arg| arg == "run").unwrap_or(false);
+    if test || test_grammar || share || run {
+        args.next();
+    }
+    let script = !(test || test_grammar || share || run);
+    if args.peek().map(|arg| arg == "learn").unwrap_or(false) {
+        args.next();
+        let lesson = args.next();
+        return match args.next() {
+            None => learn(lesson.as_deref()),
+            Some(_) => usage(),
+        };
+    }
+
+    let mut error_format = ErrorFormat::Human;
+    let mut options = runner::Options::default();
+    let mut files = Vec::new();
+    let (mut exec_mode, mut seed, mut snippet) = (ExecMode::Interpret, None, None);
+    let (mut record, mut replay) = (None, None);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--error-format=human" => error_format = ErrorFormat::Human,
+            "--error-format=json" => error_format = ErrorFormat::Json,
+            "--filter" if test => match args.next() {
+                Some(filter) => options.filter = Some(filter),
+                None => return usage(),
+            },
+            "--fail-fast" if test || test_grammar => options.fail_fast = true,
+            "--update-snapshots" if test => options.update_snapshots = true,
+            "--exec-mode=interpret" if share => exec_mode = ExecMode::Interpret,
+            "--exec-mode=bytecode" if share => exec_mode = ExecMode::Bytecode,
+            _ if share && arg.starts_with("--seed=") => match arg["--seed=".len()..].parse() {
+                Ok(n) => seed = Some(n),
+                Err(_) => return usage(),
+            },
+            "--snippet" if run => match args.next() {
+                Some(text) => snippet = Some(text),
+                None => return usage(),
+            },
+            _ if script && arg.starts_with("--record=") => record = Some(arg["--record=".len()..].to_string()),
+            _ if script && arg.starts_with("--replay=") => replay = Some(arg["--replay=".len()..].to_string()),
+            _ if test && arg.starts_with("--filter=") => options.filter = Some(arg["--filter=".len()..].to_string()),
+            _ if arg.starts_with("--") => return usage(),
+            _ => files.push(arg),
+        }
+    }
+
+    if test_grammar {
+        if files.is_empty() {
+            return usage();
+        }
+        if !grammar::run(files, options.fail_fast) {
+            process::exit(1);
+        }
+        return;
+    }
+
+    if share {
+        return match files.as_slice() {
+            [fname] => share_file(fname, exec_mode, seed),
+            _ => usage(),
+        };
+    }
+
+    if run {
+        return match (snippet, files.is_empty()) {
+            (Some(snippet), true) => run_snippet(&snippet, error_format),
+            _ => usage(),
+        };
+    }
+
+    if test {
+        if !runner::run(files, error_format, &options) {
+            process::exit(1);
+        }
+        return;
+    }
+
+    match files.as_slice() {
+        [] if record.is_some() || replay.is_some() => usage(),
+        [] => prompt(),
+        [fname] if record.is_none() || replay.is_none() => exec(fname, error_format, record.as_deref(), replay.as_deref()),
+        _ => usage(),
     }
 }
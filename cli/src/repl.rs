@@ -0,0 +1,106 @@
+use std::{
+    env,
+    path::PathBuf,
+};
+use forge::{Engine, ForgeResult, Value};
+use rustyline::{Editor, error::ReadlineError};
+
+// History is kept between sessions in the user's home directory
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(".forge_history"))
+}
+
+/// A line of input, or several that together form complete statements or expressions.
+pub enum Entry {
+    /// Input that was run, producing this.
+    Ran(ForgeResult<Option<Value>>),
+    /// A line starting with `:`, which is not run but left for the caller to act upon.
+    Command(String),
+}
+
+/// Reads input from the user with line editing and history.
+pub struct Input {
+    rl: Editor<()>,
+}
+
+impl Input {
+    pub fn open() -> Self {
+        let mut rl = Editor::<()>::new();
+        if let Some(path) = history_path() {
+            let _ = rl.load_history(&path);
+        }
+        Self { rl }
+    }
+
+    /// Read lines until they form complete statements or expressions, then run them in the engine. Produces `None` once
+    /// the user has ended their input.
+    pub fn read(&mut self, engine: &mut Engine) -> Option<Entry> {
+        let mut input = String::new();
+        loop {
+            let line = match self.rl.readline(if input.is_empty() { ">> " } else { ".. " }) {
+                Ok(line) => line,
+                // Ctrl-C abandons the current input, but not the session
+                Err(ReadlineError::Interrupted) => {
+                    input.clear();
+                    continue;
+                },
+                Err(_) => return None,
+            };
+            if input.is_empty() && line.trim_start().starts_with(':') {
+                self.rl.add_history_entry(line.clone());
+                return Some(Entry::Command(line.trim().to_string()));
+            }
+            if !input.is_empty() {
+                input.push('\n');
+            }
+            input += &line;
+
+            let result = engine.safe_prompt(&input);
+            match &result {
+                // An empty line forces incomplete input to be run anyway, so that its errors are shown
+                Err(err) if err.is_incomplete() && !line.trim().is_empty() => continue,
+                _ => {},
+            }
+            if !input.trim().is_empty() {
+                self.rl.add_history_entry(input.clone());
+            }
+            return Some(Entry::Ran(result));
+        }
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if let Some(path) = history_path() {
+            let _ = self.rl.save_history(&path);
+        }
+    }
+}
+
+/// Print the value or error that running some input produced.
+pub fn show(engine: &Engine, result: &ForgeResult<Option<Value>>) {
+    match result {
+        Ok(Some(val)) => println!("{}", engine.display(val).unwrap_or("<value cannot be displayed>".to_string())),
+        Ok(None) => {},
+        Err(err) => print!("{}", err.display_with(crate::diagnostic_style())),
+    }
+}
+
+pub fn run() {
+    println!("Welcome to the Forge prompt.");
+
+    let mut engine = Engine::build().allow_fs().allow_env().allow_net().allow_clock().finish();
+    #[cfg(unix)]
+    crate::signal::forward_interrupts(engine.interrupt_handle());
+
+    let mut input = Input::open();
+    while let Some(entry) = input.read(&mut engine) {
+        match entry {
+            Entry::Ran(result) => show(&engine, &result),
+            Entry::Command(command) => match command.as_str() {
+                ":mem" => print!("{}", engine.heap_snapshot()),
+                _ => println!("Unknown command '{}'. Type :mem to see what is using memory.", command),
+            },
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use std::{
+    fs,
+    path::Path,
+};
+use forge::{Engine, ForgeError, Value};
+use crate::{ErrorFormat, builder_for, read_script, report, start};
+
+// Scripts are looked for here when `forge test` is given no files
+const TEST_DIR: &str = "tests";
+
+// The snapshots of `tests/math.fg` are kept in `tests/snapshots/math/`
+const SNAPSHOT_DIR: &str = "snapshots";
+
+#[derive(Default)]
+pub struct Options {
+    /// Only tests whose full names (such as `math::test_add`) contain this are run.
+    pub filter: Option<String>,
+    /// Stop at the first failure rather than running the remaining tests.
+    pub fail_fast: bool,
+    /// Record the values passed to `expect_snapshot` as the new snapshots, rather than failing when they differ.
+    pub update_snapshots: bool,
+}
+
+fn test_files() -> Vec<String> {
+    let mut files = fs::read_dir(TEST_DIR)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().map(|ext| ext == "fg").unwrap_or(false))
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect::<Vec<_>>())
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+fn engine_for(fname: &str, options: &Options) -> Engine {
+    let path = Path::new(fname);
+    let dir = path
+        .parent()
+        .unwrap_or(Path::new("."))
+        .join(SNAPSHOT_DIR)
+        .join(path.file_stem().unwrap_or_default());
+    let builder = builder_for(fname).with_snapshot_dir(dir).allow_mocks();
+    start(if options.update_snapshots { builder.update_snapshots() } else { builder })
+}
+
+fn is_fn(engine: &Engine, name: &str) -> bool {
+    match engine.get(name) {
+        Some(Value::Fn(_, _, _)) => true,
+        _ => false,
+    }
+}
+
+// Hooks are optional, so calling one that the file does not define does nothing
+fn call_hook(engine: &mut Engine, name: &str) -> Result<(), ForgeError> {
+    if is_fn(engine, name) {
+        engine.eval(&format!("{}()", name))?;
+    }
+    Ok(())
+}
+
+/// Each test runs in an engine of its own, in which the file has just been run, so tests cannot see the globals that
+/// other tests changed. `setup` is called before the test and `teardown` after it, even if the test failed.
+fn run_test(fname: &str, code: &str, name: &str, options: &Options) -> Result<(), ForgeError> {
+    let mut engine = engine_for(fname, options);
+    engine.exec(code)?;
+    call_hook(&mut engine, "setup")?;
+    let result = engine.eval(&format!("{}()", name)).map(|_| ());
+    let teardown = call_hook(&mut engine, "teardown");
+    result.and(teardown)
+}
+
+/// Run every function whose name starts with `test_` in each of the files, after running the rest of the file. Returns
+/// whether all of the tests passed.
+pub fn run(files: Vec<String>, error_format: ErrorFormat, options: &Options) -> bool {
+    let files = if files.is_empty() { test_files() } else { files };
+    let mut failures: Vec<(String, ForgeError)> = Vec::new();
+    let (mut passed, mut filtered) = (0, 0);
+
+    'files: for fname in &files {
+        let code = match read_script(fname) {
+            Some(code) => code,
+            None => return false,
+        };
+        let mut engine = engine_for(fname, options);
+        if let Err(err) = engine.exec(&code) {
+            println!("error in {}", fname);
+            failures.push((fname.clone(), err));
+            if options.fail_fast {
+                break;
+            }
+            continue;
+        }
+
+        let stem = Path::new(fname).file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+        let mut tests = engine
+            .global_names()
+            .into_iter()
+            .filter(|name| name.starts_with("test_") && is_fn(&engine, name))
+            .map(|name| (format!("{}::{}", stem, name), name))
+            .collect::<Vec<_>>();
+        let total = tests.len();
+        if let Some(filter) = &options.filter {
+            tests.retain(|(test, _)| test.contains(filter.as_str()));
+        }
+        filtered += total - tests.len();
+        if tests.is_empty() && total > 0 {
+            continue;
+        }
+        tests.sort();
+
+        println!("running {} test{} in {}", tests.len(), if tests.len() == 1 { "" } else { "s" }, fname);
+        for (test, name) in tests {
+            match run_test(fname, &code, &name, options) {
+                Ok(()) => {
+                    println!("test {} ... ok", test);
+                    passed += 1;
+                },
+                Err(err) => {
+                    println!("test {} ... FAILED", test);
+                    failures.push((test, err));
+                    if options.fail_fast {
+                        break 'files;
+                    }
+                },
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (test, err) in &failures {
+            println!("\n---- {} ----", test);
+            report(err, error_format);
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} filtered out.",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len(),
+        filtered,
+    );
+    failures.is_empty()
+}
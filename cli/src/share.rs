@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+use forge::{Engine, EngineBuilder, ExecMode};
+
+// Marks the text as a snippet, and the version of the format that it was made with
+const PREFIX: &str = "forge1:";
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+// Repeated text is replaced by references back to where it last appeared, up to this far back
+const WINDOW: usize = 4096;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = MIN_MATCH + 15;
+// How many earlier occurrences are tried when looking for the longest repeat
+const MAX_TRIES: usize = 64;
+
+/// A script along with the options it should be run with, which can be shared as a single line of text so that others
+/// can run exactly the same thing (`forge share` and `forge run --snippet`).
+///
+/// Only the script itself is included, not the modules that it imports.
+pub struct Snippet {
+    pub code: String,
+    pub exec_mode: ExecMode,
+    pub seed: Option<u64>,
+}
+
+impl Snippet {
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        if self.exec_mode == ExecMode::Bytecode {
+            text += "mode=bytecode\n";
+        }
+        if let Some(seed) = self.seed {
+            text += &format!("seed={}\n", seed);
+        }
+        text += "\n";
+        text += &self.code;
+        format!("{}{}", PREFIX, to_base64(&compress(text.as_bytes())))
+    }
+
+    pub fn decode(snippet: &str) -> Result<Self, String> {
+        let damaged = || String::from("The snippet is damaged or incomplete.");
+        let data = snippet
+            .trim()
+            .strip_prefix(PREFIX)
+            .ok_or_else(|| String::from("This is not a Forge snippet, or was made by a newer version of Forge."))?;
+        let text = from_base64(data)
+            .and_then(|bytes| decompress(&bytes))
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+            .ok_or_else(damaged)?;
+
+        // The options come first, one to a line, and end at the first empty line
+        let (header, code) = match text.strip_prefix('\n') {
+            Some(code) => ("", code),
+            None => text.split_once("\n\n").ok_or_else(damaged)?,
+        };
+        let mut snippet = Self { code: code.to_string(), exec_mode: ExecMode::Interpret, seed: None };
+        for line in header.lines() {
+            match line.split_once('=') {
+                Some(("mode", "interpret")) => snippet.exec_mode = ExecMode::Interpret,
+                Some(("mode", "bytecode")) => snippet.exec_mode = ExecMode::Bytecode,
+                Some(("seed", seed)) => snippet.seed = Some(seed.parse().map_err(|_| damaged())?),
+                _ => return Err(format!("The snippet uses the option '{}', which this version of Forge does not know.", line)),
+            }
+        }
+        Ok(snippet)
+    }
+
+    /// Snippets come from other people, so they may not touch the files, environment or network of the machine.
+    pub fn builder(&self) -> EngineBuilder {
+        let builder = Engine::build().with_exec_mode(self.exec_mode);
+        match self.seed {
+            Some(seed) => builder.with_seed(seed),
+            None => builder,
+        }
+    }
+}
+
+// LZSS: each flag byte says which of the (up to) 8 items after it are literal bytes and which are references back to
+// earlier output, given as 12 bits of distance and 4 bits of length. The length of the input comes first.
+fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = (input.len() as u32).to_le_bytes().to_vec();
+    let mut seen: HashMap<&[u8], Vec<usize>> = HashMap::new();
+    let (mut i, mut flags_at, mut nitems) = (0, 0, 8);
+    while i < input.len() {
+        if nitems == 8 {
+            flags_at = out.len();
+            out.push(0);
+            nitems = 0;
+        }
+
+        let mut best = (0, 0);
+        if let Some(starts) = input.get(i..i + MIN_MATCH).and_then(|key| seen.get(key)) {
+            for &start in starts.iter().rev().take(MAX_TRIES).take_while(|&&start| i - start <= WINDOW) {
+                let len = input[start..]
+                    .iter()
+                    .zip(&input[i..])
+                    .take(MAX_MATCH)
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best.1 {
+                    best = (i - start, len);
+                }
+            }
+        }
+
+        let len = if best.1 >= MIN_MATCH {
+            let item = ((best.0 - 1) << 4 | (best.1 - MIN_MATCH)) as u16;
+            out.extend_from_slice(&item.to_le_bytes());
+            out[flags_at] |= 1 << nitems;
+            best.1
+        } else {
+            out.push(input[i]);
+            1
+        };
+        for j in i..i + len {
+            if let Some(key) = input.get(j..j + MIN_MATCH) {
+                seen.entry(key).or_insert_with(Vec::new).push(j);
+            }
+        }
+        i += len;
+        nitems += 1;
+    }
+    out
+}
+
+fn decompress(input: &[u8]) -> Option<Vec<u8>> {
+    let len = u32::from_le_bytes([*input.get(0)?, *input.get(1)?, *input.get(2)?, *input.get(3)?]) as usize;
+    let mut out = Vec::with_capacity(len.min(input.len() * MAX_MATCH));
+    let mut bytes = input[4..].iter().copied();
+    while out.len() < len {
+        let flags = bytes.next()?;
+        for bit in 0..8 {
+            if out.len() >= len {
+                break;
+            }
+            if flags & (1 << bit) == 0 {
+                out.push(bytes.next()?);
+            } else {
+                let item = u16::from_le_bytes([bytes.next()?, bytes.next()?]) as usize;
+                let dist = (item >> 4) + 1;
+                let start = out.len().checked_sub(dist)?;
+                for j in start..start + (item & 0xF) + MIN_MATCH {
+                    out.push(out[j]);
+                }
+            }
+        }
+    }
+    if out.len() == len && bytes.next().is_none() {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+fn to_base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - i * 8));
+        for i in 0..chunk.len() + 1 {
+            out.push(BASE64[(n >> (18 - i * 6)) as usize & 63] as char);
+        }
+    }
+    out
+}
+
+fn from_base64(text: &str) -> Option<Vec<u8>> {
+    let digits = text
+        .bytes()
+        .map(|c| BASE64.iter().position(|d| *d == c).map(|d| d as u32))
+        .collect::<Option<Vec<_>>>()?;
+    let mut out = Vec::new();
+    for chunk in digits.chunks(4) {
+        if chunk.len() < 2 {
+            return None;
+        }
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, d)| n | d << (18 - i * 6));
+        for i in 0..chunk.len() - 1 {
+            out.push((n >> (16 - i * 8)) as u8);
+        }
+    }
+    Some(out)
+}
@@ -0,0 +1,22 @@
+use std::sync::OnceLock;
+use forge::InterruptHandle;
+
+// The handle of the engine that Ctrl-C interrupts
+static TARGET: OnceLock<InterruptHandle> = OnceLock::new();
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    match TARGET.get() {
+        // Pressing Ctrl-C again before the engine has noticed the first interrupt kills the process as usual
+        Some(handle) if !handle.is_pending() => handle.interrupt(),
+        _ => unsafe { libc::_exit(130) },
+    }
+}
+
+/// Make Ctrl-C interrupt the code running in the engine with the given handle rather than killing the process.
+pub fn forward_interrupts(handle: InterruptHandle) {
+    if TARGET.set(handle).is_ok() {
+        unsafe {
+            libc::signal(libc::SIGINT, on_sigint as extern "C" fn(libc::c_int) as libc::sighandler_t);
+        }
+    }
+}
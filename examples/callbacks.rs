@@ -1,15 +1,17 @@
-use forge::Engine;
+use forge::{Engine, Value};
 
 fn main() {
     let mut engine = Engine::build()
         .with_global("say_hello", || println!("Hello, world!"))
         .with_global("get_meaning_of_life", || 42)
+        .with_fn("greet", 1, |args| Ok(Value::from(format!("Hello, {}!", args[0]))))
         .finish();
 
     engine
         .exec(r#"
         say_hello();
         print "The meaning of life is " + get_meaning_of_life();
+        print greet("Forge");
         "#)
         .unwrap();
 }
@@ -1,3 +1,4 @@
+use std::convert::TryFrom;
 use forge::Engine;
 
 fn main() {
@@ -23,4 +24,9 @@ fn main() {
             print "I've not finished shopping yet";
         }
     "#).unwrap();
+
+    // Values can be extracted from the engine too
+    engine.exec(r#"var heaviest = bag_weight * 2;"#).unwrap();
+    let heaviest = f64::try_from(engine.get("heaviest").unwrap()).unwrap();
+    println!("My bag could hold up to {} Kg", heaviest);
 }
@@ -0,0 +1,930 @@
+use std::{
+    fmt,
+    mem,
+    ops::Range,
+    rc::{Rc, Weak},
+};
+use hashbrown::{HashMap, HashSet};
+use crate::{
+    exec::{
+        Value,
+        Type,
+        UnaryOpRef,
+        BinaryOpRef,
+    },
+    parser::{
+        SrcRef,
+        ast::{
+            Node,
+            Expr,
+            LVal,
+            Stmt,
+            Block,
+            Args,
+            StringPart,
+            Pattern,
+        },
+    },
+};
+
+#[derive(Copy, Clone, Debug)]
+pub enum UnaryOp {
+    Not,
+    Neg,
+    BitNot,
+    Clone,
+    Mirror,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub enum BinaryOp {
+    Mul,
+    Div,
+    Rem,
+    Add,
+    Sub,
+    Shl,
+    Shr,
+    BitAnd,
+    BitXor,
+    BitOr,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Eq,
+    NotEq,
+    Xor,
+    Range,
+    Is,
+}
+
+#[derive(Debug)]
+pub enum Instr {
+    Const(Value),
+    String(String), // Strings are mutable, so each evaluation must produce a fresh value
+    Pop,
+    Dup(usize), // Copies the top values, keeping their order
+    Rot(usize), // Moves the top value beneath the ones under it
+
+    Load(String, SrcRef),
+    Store(String, SrcRef),
+    Declare(String),
+    LoadLocal(usize),
+    StoreLocal(usize), // Declares the local as well as assigning to it
+    StoreIndex(SrcRef, SrcRef, SrcRef), // Container, index, rvalue
+    StoreMember(String, SrcRef, SrcRef), // Container, member
+
+    Unary(UnaryOp, UnaryOpRef),
+    Binary(BinaryOp, BinaryOpRef),
+    As(Type, BinaryOpRef),
+    Index(SrcRef, SrcRef), // Container, index
+    Member(String, SrcRef),
+    Input(SrcRef, SrcRef), // Operator, operand
+    Import(String, SrcRef),
+
+    Display(SrcRef),
+    Concat(usize),
+    MakeList(usize, SrcRef),
+    MakeListClone(SrcRef, SrcRef), // Item, number
+    MakeMap(usize, SrcRef),
+    MakeFn(Rc<String>, Rc<(Node<Args>, Node<Block>, Option<String>)>), // Captures the current environment
+    Call(usize, SrcRef, SrcRef), // Arg count, callee, args
+    CallNamed(usize, Vec<(String, SrcRef)>, SrcRef, SrcRef), // Positional arg count, names of the args after them, callee, args
+    CallMethod(usize, Vec<(String, SrcRef)>, SrcRef, SrcRef), // As with `CallNamed`, with the receiver beneath the method
+    TailCall(usize, Vec<(String, SrcRef)>, bool, SrcRef, SrcRef), // As with `CallMethod`, if there is a receiver
+
+    PushScope,
+    PopScope,
+    Jump(usize),
+    JumpIfFalse(usize, SrcRef),
+    JumpIfFalsy(usize), // Keeps the value if it jumps, and pops it otherwise
+    JumpIfTruthy(usize), // Keeps the value if it jumps, and pops it otherwise
+    JumpIfNull(usize), // Keeps the value whether or not it jumps
+    JumpIfNotNull(usize), // Keeps the value if it jumps, and pops it otherwise
+    IterStart(SrcRef),
+    IterStartPairs(SrcRef),
+    IterNext(usize), // Pushes the key and then the value when iterating over pairs
+    TryStart(usize), // Catch block
+    TryEnd,
+
+    Print(SrcRef),
+    Return(usize), // Keeps the value being returned
+}
+
+/// A flat sequence of instructions produced from a statement list or expression.
+#[derive(Debug, Default)]
+pub struct Chunk {
+    pub code: Vec<Instr>,
+    // The number of slots that the locals of the code are kept in
+    pub locals: usize,
+    // The instructions that evaluate an expression whose errors are reported at its location, innermost first
+    pub located: Vec<(Range<usize>, SrcRef)>,
+}
+
+impl Chunk {
+    /// Where the error of an instruction happened: the location of the innermost expression that it is part of,
+    /// among those with locations of their own.
+    pub fn location_of(&self, at: usize) -> Option<SrcRef> {
+        self.located.iter().find(|(range, _)| range.contains(&at)).map(|(_, r)| *r)
+    }
+}
+
+/// A function literal: its parameters, its body and its name, if it has one.
+pub type FnDef = (Node<Args>, Node<Block>, Option<String>);
+
+/// Raised when the compiler meets a construct that it cannot lower. Callers are expected to fall back to the
+/// tree-walking interpreter when this happens.
+#[derive(Debug)]
+pub enum CompileError {
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for CompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompileError::Unsupported(what) => write!(f, "cannot compile {} to bytecode", what),
+        }
+    }
+}
+
+pub type CompileResult<T> = Result<T, CompileError>;
+
+pub fn compile_stmts(stmts: &[Node<Stmt>]) -> CompileResult<Chunk> {
+    let mut compiler = Compiler { captured: Captures::in_stmts(stmts), ..Compiler::default() };
+    for stmt in stmts {
+        // Returning from the top level only abandons the current statement, mirroring the interpreter
+        let start = compiler.here();
+        compiler.stmt(&stmt.0)?;
+        if compiler.chunk.code[start..].iter().any(|instr| matches!(instr, Instr::Return(_))) {
+            let to_end = compiler.emit(Instr::Jump(0));
+            let target = compiler.emit(Instr::Pop);
+            compiler.patch(to_end);
+            compiler.patch_returns(start, target);
+        }
+    }
+    Ok(compiler.chunk)
+}
+
+pub fn compile_expr(expr: &Expr) -> CompileResult<Chunk> {
+    let mut compiler = Compiler { captured: Captures::in_expr(expr), ..Compiler::default() };
+    compiler.expr(expr)?;
+    Ok(compiler.chunk)
+}
+
+/// Compile the body of a function, which runs within the frame that its arguments have been declared in and leaves
+/// the value that the function returns.
+pub fn compile_fn(f: &FnDef) -> CompileResult<Chunk> {
+    let (params, body) = (&(f.0).0, &(f.1).0);
+    let mut compiler = Compiler {
+        scopes: vec![Vec::new()],
+        captured: Captures::in_fn(params, body),
+        in_fn: true,
+        ..Compiler::default()
+    };
+    // Parameters that no function refers to are copied into slots, which are quicker to get at than the frame
+    for name in params.0.iter().map(|param| &param.name).chain(&params.1) {
+        if !compiler.captured.contains(&name.0) {
+            compiler.emit(Instr::Load(name.0.clone(), name.1));
+            compiler.declare(&name.0);
+        }
+    }
+    for stmt in &body.0 {
+        compiler.stmt(&stmt.0)?;
+    }
+    compiler.emit(Instr::Const(Value::Null));
+    let end = compiler.here();
+    compiler.patch_returns(0, end);
+    Ok(compiler.chunk)
+}
+
+/// The bytecode of each function that has been called, compiled when it is first called. Functions that cannot be
+/// compiled are remembered as such, to be interpreted.
+#[derive(Default)]
+pub struct FnCache {
+    fns: HashMap<usize, (Weak<FnDef>, Option<Rc<Chunk>>)>,
+    // How many functions were remembered when those that have since been freed were last forgotten
+    live: usize,
+}
+
+impl FnCache {
+    pub fn get(&mut self, f: &Rc<FnDef>) -> Option<Rc<Chunk>> {
+        let id = Rc::as_ptr(f) as usize;
+        // A function that is still alive cannot share its address with another
+        if let Some((def, chunk)) = self.fns.get(&id) {
+            if def.strong_count() > 0 {
+                return chunk.clone();
+            }
+        }
+        if self.fns.len() >= self.live * 2 + 64 {
+            self.fns.retain(|_, (def, _)| def.strong_count() > 0);
+            self.live = self.fns.len();
+        }
+        let chunk = compile_fn(f).ok().map(Rc::new);
+        self.fns.insert(id, (Rc::downgrade(f), chunk.clone()));
+        chunk
+    }
+}
+
+// Where a variable is kept: in a slot of its own, or by name in the frame of the block that declared it
+#[derive(Copy, Clone)]
+enum Local {
+    Slot(usize),
+    Named,
+}
+
+#[derive(Default)]
+struct Compiler {
+    chunk: Chunk,
+    // The variables declared by each block being compiled, innermost last. Variables declared outside of any block
+    // are those of the scope that the code runs in, and so are kept by name.
+    scopes: Vec<Vec<(String, Local)>>,
+    // The names that functions within the code refer to, whose variables must be kept by name for the functions to
+    // find them
+    captured: HashSet<String>,
+    // Whether the code is the body of a function, which may leave the call it ends with to its caller
+    in_fn: bool,
+    // The number of `try` blocks that the code being compiled is within
+    tries: usize,
+}
+
+impl Compiler {
+    fn emit(&mut self, instr: Instr) -> usize {
+        self.chunk.code.push(instr);
+        self.chunk.code.len() - 1
+    }
+
+    fn here(&self) -> usize {
+        self.chunk.code.len()
+    }
+
+    fn patch(&mut self, at: usize) {
+        let here = self.here();
+        match &mut self.chunk.code[at] {
+            Instr::Jump(target)
+            | Instr::JumpIfFalse(target, _)
+            | Instr::JumpIfFalsy(target)
+            | Instr::JumpIfTruthy(target)
+            | Instr::JumpIfNull(target)
+            | Instr::JumpIfNotNull(target)
+            | Instr::IterNext(target)
+            | Instr::TryStart(target) => *target = here,
+            _ => unreachable!(),
+        }
+    }
+
+    fn patch_returns(&mut self, from: usize, target: usize) {
+        for instr in &mut self.chunk.code[from..] {
+            if let Instr::Return(to) = instr {
+                *to = target;
+            }
+        }
+    }
+
+    // Declare a variable with the value on top of the stack
+    fn declare(&mut self, name: &str) {
+        match self.scopes.last_mut() {
+            Some(scope) if !self.captured.contains(name) => {
+                let slot = self.chunk.locals;
+                self.chunk.locals += 1;
+                scope.push((name.to_string(), Local::Slot(slot)));
+                self.chunk.code.push(Instr::StoreLocal(slot));
+            },
+            scope => {
+                if let Some(scope) = scope {
+                    scope.push((name.to_string(), Local::Named));
+                }
+                self.chunk.code.push(Instr::Declare(name.to_string()));
+            },
+        }
+    }
+
+    // The slot of the variable that a name refers to here, unless it is kept by name
+    fn resolve(&self, name: &str) -> Option<usize> {
+        self.scopes
+            .iter()
+            .rev()
+            .flat_map(|scope| scope.iter().rev())
+            .find(|(other, _)| other == name)
+            .and_then(|(_, local)| match local {
+                Local::Slot(slot) => Some(*slot),
+                Local::Named => None,
+            })
+    }
+
+    fn load(&mut self, name: &Node<String>) {
+        match self.resolve(&name.0) {
+            Some(slot) => self.emit(Instr::LoadLocal(slot)),
+            None => self.emit(Instr::Load(name.0.clone(), name.1)),
+        };
+    }
+
+    fn store(&mut self, name: &Node<String>) {
+        match self.resolve(&name.0) {
+            Some(slot) => self.emit(Instr::StoreLocal(slot)),
+            None => self.emit(Instr::Store(name.0.clone(), name.1)),
+        };
+    }
+
+    // Enter a block that declares the given variables. Its frame is only needed for those kept by name.
+    fn enter(&mut self, names: &[&str]) -> bool {
+        let framed = names.iter().any(|name| self.captured.contains(*name));
+        if framed {
+            self.emit(Instr::PushScope);
+        }
+        self.scopes.push(Vec::new());
+        framed
+    }
+
+    fn leave(&mut self, framed: bool) {
+        self.scopes.pop();
+        if framed {
+            self.emit(Instr::PopScope);
+        }
+    }
+
+    // An expression whose errors are reported as having happened at `r`, wherever the interpreter reports them so
+    fn expr_at(&mut self, expr: &Expr, r: SrcRef) -> CompileResult<()> {
+        let start = self.here();
+        self.expr(expr)?;
+        self.chunk.located.push((start..self.here(), r));
+        Ok(())
+    }
+
+    fn unary(&mut self, op: UnaryOp, r: SrcRef, expr: &Node<Expr>) -> CompileResult<()> {
+        self.expr(&expr.0)?;
+        self.emit(Instr::Unary(op, UnaryOpRef { op: r, expr: expr.1 }));
+        Ok(())
+    }
+
+    fn binary(&mut self, op: BinaryOp, r: SrcRef, left: &Node<Expr>, right: &Node<Expr>) -> CompileResult<()> {
+        self.expr(&left.0)?;
+        self.expr(&right.0)?;
+        self.emit(Instr::Binary(op, BinaryOpRef { op: r, left: left.1, right: right.1 }));
+        Ok(())
+    }
+
+    fn compound_assign(&mut self, op: BinaryOp, r: SrcRef, lvalue: &Node<LVal>, expr: &Node<Expr>) -> CompileResult<()> {
+        match &lvalue.0 {
+            LVal::Local(ident) => {
+                self.load(ident);
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Binary(op, BinaryOpRef { op: r, left: lvalue.1, right: expr.1 }));
+                self.store(ident);
+                self.emit(Instr::Const(Value::Null));
+                Ok(())
+            },
+            // The container and index are evaluated once, then kept on the stack for the store
+            LVal::Index(container, index) => {
+                self.expr_at(&container.0, container.1)?;
+                self.expr_at(&index.0, index.1)?;
+                self.emit(Instr::Dup(2));
+                self.emit(Instr::Index(container.1, index.1));
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Binary(op, BinaryOpRef { op: r, left: lvalue.1, right: expr.1 }));
+                self.emit(Instr::Rot(3));
+                self.emit(Instr::StoreIndex(container.1, index.1, expr.1));
+                self.emit(Instr::Const(Value::Null));
+                Ok(())
+            },
+            LVal::Member(container, name) => {
+                self.expr_at(&container.0, container.1)?;
+                self.emit(Instr::Dup(1));
+                self.emit(Instr::Member(name.0.clone(), name.1));
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Binary(op, BinaryOpRef { op: r, left: lvalue.1, right: expr.1 }));
+                self.emit(Instr::Rot(2));
+                self.emit(Instr::StoreMember(name.0.clone(), container.1, name.1));
+                self.emit(Instr::Const(Value::Null));
+                Ok(())
+            },
+        }
+    }
+
+    // A call, which a function may leave to its caller if it is the last thing that the function does
+    fn call(&mut self, expr: &Node<Expr>, params: &Node<Vec<Node<Expr>>>, named: &[(Node<String>, Node<Expr>)], tail: bool) -> CompileResult<()> {
+        // `x?.f()` is null, rather than calling null, when `x` is null
+        // The receiver of a method call is kept beneath the method, to be passed to it as `self`
+        let (to_end, is_method) = match &expr.0 {
+            Expr::OptionalAccess(_, recv, name) => {
+                self.expr_at(&recv.0, recv.1)?;
+                let to_end = self.emit(Instr::JumpIfNull(0));
+                self.emit(Instr::Dup(1));
+                self.emit(Instr::Member(name.0.clone(), name.1));
+                (Some(to_end), true)
+            },
+            Expr::DotAccess(_, recv, name) => {
+                self.expr_at(&recv.0, recv.1)?;
+                self.emit(Instr::Dup(1));
+                self.emit(Instr::Member(name.0.clone(), name.1));
+                (None, true)
+            },
+            callee => {
+                self.expr_at(callee, expr.1)?;
+                (None, false)
+            },
+        };
+        for param in &params.0 {
+            self.expr(&param.0)?;
+        }
+        for (_, param) in named {
+            self.expr(&param.0)?;
+        }
+        let names = named.iter().map(|(name, _)| (name.0.clone(), name.1)).collect();
+        if tail {
+            self.emit(Instr::TailCall(params.0.len(), names, is_method, expr.1, params.1));
+        } else if is_method {
+            self.emit(Instr::CallMethod(params.0.len(), names, expr.1, params.1));
+        } else if named.is_empty() {
+            self.emit(Instr::Call(params.0.len(), expr.1, params.1));
+        } else {
+            self.emit(Instr::CallNamed(params.0.len(), names, expr.1, params.1));
+        }
+        if let Some(to_end) = to_end {
+            self.patch(to_end);
+        }
+        Ok(())
+    }
+
+    fn expr(&mut self, expr: &Expr) -> CompileResult<()> {
+        match expr {
+            Expr::None | Expr::LiteralNull => { self.emit(Instr::Const(Value::Null)); },
+            Expr::LiteralInt(x) => { self.emit(Instr::Const(Value::Int(*x))); },
+            Expr::LiteralFloat(x) => { self.emit(Instr::Const(Value::Float(*x))); },
+            Expr::LiteralString(s) => { self.emit(Instr::String(s.clone())); },
+            Expr::LiteralChar(c) => { self.emit(Instr::Const(Value::Char(*c))); },
+            Expr::LiteralBoolean(b) => { self.emit(Instr::Const(Value::Boolean(*b))); },
+            Expr::InterpolatedString(parts) => {
+                for part in parts {
+                    match part {
+                        StringPart::Literal(s) => { self.emit(Instr::String(s.clone())); },
+                        StringPart::Expr(expr) => {
+                            self.expr_at(&expr.0, expr.1)?;
+                            self.emit(Instr::Display(expr.1));
+                        },
+                    }
+                }
+                self.emit(Instr::Concat(parts.len()));
+            },
+            Expr::Ident(name) => self.load(name),
+            Expr::List(items) => {
+                for item in &items.0 {
+                    self.expr_at(&item.0, item.1)?;
+                }
+                self.emit(Instr::MakeList(items.0.len(), items.1));
+            },
+            Expr::ListClone(item, num) => {
+                self.expr_at(&item.0, item.1)?;
+                self.expr_at(&num.0, num.1)?;
+                self.emit(Instr::MakeListClone(item.1, num.1));
+            },
+            Expr::Map(maps) => {
+                for (key, val) in &maps.0 {
+                    self.expr_at(&key.0, key.1)?;
+                    self.expr_at(&val.0, val.1)?;
+                }
+                self.emit(Instr::MakeMap(maps.0.len(), maps.1));
+            },
+            Expr::Call(_, expr, params, named) => self.call(expr, params, named, false)?,
+            Expr::DotAccess(_, expr, name) => {
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Member(name.0.clone(), name.1));
+            },
+            Expr::OptionalAccess(_, expr, name) => {
+                self.expr_at(&expr.0, expr.1)?;
+                let to_end = self.emit(Instr::JumpIfNull(0));
+                self.emit(Instr::Member(name.0.clone(), name.1));
+                self.patch(to_end);
+            },
+            Expr::Index(_, expr, index) => {
+                self.expr_at(&expr.0, expr.1)?;
+                self.expr_at(&index.0, expr.1)?;
+                self.emit(Instr::Index(expr.1, index.1));
+            },
+
+            Expr::UnaryNot(r, expr) => self.unary(UnaryOp::Not, *r, expr)?,
+            Expr::UnaryNeg(r, expr) => self.unary(UnaryOp::Neg, *r, expr)?,
+            Expr::UnaryBitNot(r, expr) => self.unary(UnaryOp::BitNot, *r, expr)?,
+            Expr::UnaryClone(r, expr) => self.unary(UnaryOp::Clone, *r, expr)?,
+            Expr::UnaryMirror(r, expr) => self.unary(UnaryOp::Mirror, *r, expr)?,
+            Expr::UnaryInput(r, expr) => {
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Input(*r, expr.1));
+            },
+
+            Expr::BinaryMul(r, left, right) => self.binary(BinaryOp::Mul, *r, left, right)?,
+            Expr::BinaryDiv(r, left, right) => self.binary(BinaryOp::Div, *r, left, right)?,
+            Expr::BinaryRem(r, left, right) => self.binary(BinaryOp::Rem, *r, left, right)?,
+            Expr::BinaryAdd(r, left, right) => self.binary(BinaryOp::Add, *r, left, right)?,
+            Expr::BinarySub(r, left, right) => self.binary(BinaryOp::Sub, *r, left, right)?,
+            Expr::BinaryShl(r, left, right) => self.binary(BinaryOp::Shl, *r, left, right)?,
+            Expr::BinaryShr(r, left, right) => self.binary(BinaryOp::Shr, *r, left, right)?,
+            Expr::BinaryBitAnd(r, left, right) => self.binary(BinaryOp::BitAnd, *r, left, right)?,
+            Expr::BinaryBitXor(r, left, right) => self.binary(BinaryOp::BitXor, *r, left, right)?,
+            Expr::BinaryBitOr(r, left, right) => self.binary(BinaryOp::BitOr, *r, left, right)?,
+            Expr::BinaryGreater(r, left, right) => self.binary(BinaryOp::Greater, *r, left, right)?,
+            Expr::BinaryGreaterEq(r, left, right) => self.binary(BinaryOp::GreaterEq, *r, left, right)?,
+            Expr::BinaryLess(r, left, right) => self.binary(BinaryOp::Less, *r, left, right)?,
+            Expr::BinaryLessEq(r, left, right) => self.binary(BinaryOp::LessEq, *r, left, right)?,
+            Expr::BinaryEq(r, left, right) => self.binary(BinaryOp::Eq, *r, left, right)?,
+            Expr::BinaryNotEq(r, left, right) => self.binary(BinaryOp::NotEq, *r, left, right)?,
+            Expr::BinaryAnd(_, left, right) => {
+                self.expr(&left.0)?;
+                let to_end = self.emit(Instr::JumpIfFalsy(0));
+                self.expr(&right.0)?;
+                self.patch(to_end);
+            },
+            Expr::BinaryOr(_, left, right) => {
+                self.expr(&left.0)?;
+                let to_end = self.emit(Instr::JumpIfTruthy(0));
+                self.expr(&right.0)?;
+                self.patch(to_end);
+            },
+            Expr::BinaryCoalesce(_, left, right) => {
+                self.expr(&left.0)?;
+                let to_end = self.emit(Instr::JumpIfNotNull(0));
+                self.expr(&right.0)?;
+                self.patch(to_end);
+            },
+            Expr::BinaryXor(r, left, right) => self.binary(BinaryOp::Xor, *r, left, right)?,
+            Expr::BinaryRange(r, left, right) => self.binary(BinaryOp::Range, *r, left, right)?,
+            Expr::BinaryIs(r, left, right) => self.binary(BinaryOp::Is, *r, left, right)?,
+            Expr::BinaryAs(r, left, right) => {
+                let ty = match &right.0 {
+                    Expr::Ident(name) => Type::from_name(&name.0),
+                    _ => None,
+                }.ok_or(CompileError::Unsupported("non-type cast target"))?;
+                self.expr(&left.0)?;
+                self.emit(Instr::As(ty, BinaryOpRef { op: *r, left: left.1, right: right.1 }));
+            },
+
+            Expr::Ternary(_, cond, a, b) => {
+                self.expr(&cond.0)?;
+                let to_else = self.emit(Instr::JumpIfFalse(0, cond.1));
+                self.expr(&a.0)?;
+                let to_end = self.emit(Instr::Jump(0));
+                self.patch(to_else);
+                self.expr(&b.0)?;
+                self.patch(to_end);
+            },
+            Expr::BinaryAssign(_, lvalue, rvalue) => match &lvalue.0 {
+                LVal::Local(ident) => {
+                    self.expr_at(&rvalue.0, rvalue.1)?;
+                    self.store(ident);
+                    self.emit(Instr::Const(Value::Null));
+                },
+                LVal::Index(expr, index) => {
+                    self.expr_at(&rvalue.0, rvalue.1)?;
+                    self.expr_at(&expr.0, expr.1)?;
+                    self.expr_at(&index.0, index.1)?;
+                    self.emit(Instr::StoreIndex(expr.1, index.1, rvalue.1));
+                    self.emit(Instr::Const(Value::Null));
+                },
+                LVal::Member(expr, name) => {
+                    self.expr_at(&rvalue.0, rvalue.1)?;
+                    self.expr_at(&expr.0, expr.1)?;
+                    self.emit(Instr::StoreMember(name.0.clone(), expr.1, name.1));
+                    self.emit(Instr::Const(Value::Null));
+                },
+            },
+            Expr::BinaryAddAssign(r, lvalue, expr) => self.compound_assign(BinaryOp::Add, *r, lvalue, expr)?,
+            Expr::BinarySubAssign(r, lvalue, expr) => self.compound_assign(BinaryOp::Sub, *r, lvalue, expr)?,
+            Expr::BinaryMulAssign(r, lvalue, expr) => self.compound_assign(BinaryOp::Mul, *r, lvalue, expr)?,
+            Expr::BinaryDivAssign(r, lvalue, expr) => self.compound_assign(BinaryOp::Div, *r, lvalue, expr)?,
+            Expr::BinaryRemAssign(r, lvalue, expr) => self.compound_assign(BinaryOp::Rem, *r, lvalue, expr)?,
+
+            Expr::Fn(code, rc) => { self.emit(Instr::MakeFn(code.clone(), rc.clone())); },
+        }
+        Ok(())
+    }
+
+    fn block(&mut self, block: &Block) -> CompileResult<()> {
+        let framed = self.enter(&declared_in(block));
+        self.stmts(block)?;
+        self.leave(framed);
+        Ok(())
+    }
+
+    fn stmts(&mut self, block: &Block) -> CompileResult<()> {
+        for stmt in &block.0 {
+            self.stmt(&stmt.0)?;
+        }
+        Ok(())
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> CompileResult<()> {
+        match stmt {
+            Stmt::Expr(expr) => {
+                self.expr(&expr.0)?;
+                self.emit(Instr::Pop);
+            },
+            Stmt::Print(expr) => {
+                self.expr(&expr.0)?;
+                self.emit(Instr::Print(expr.1));
+            },
+            Stmt::If(expr, block) => {
+                self.expr(&expr.0)?;
+                let skip = self.emit(Instr::JumpIfFalse(0, expr.1));
+                self.block(&block.0)?;
+                self.patch(skip);
+            },
+            Stmt::IfElse(expr, true_block, false_block) => {
+                self.expr(&expr.0)?;
+                let to_else = self.emit(Instr::JumpIfFalse(0, expr.1));
+                self.block(&true_block.0)?;
+                let to_end = self.emit(Instr::Jump(0));
+                self.patch(to_else);
+                self.block(&false_block.0)?;
+                self.patch(to_end);
+            },
+            Stmt::IfElseIf(expr, true_block, else_stmt) => {
+                self.expr(&expr.0)?;
+                let to_else = self.emit(Instr::JumpIfFalse(0, expr.1));
+                self.block(&true_block.0)?;
+                let to_end = self.emit(Instr::Jump(0));
+                self.patch(to_else);
+                self.stmt(&else_stmt.0)?;
+                self.patch(to_end);
+            },
+            Stmt::While(expr, block) => {
+                let start = self.here();
+                self.expr(&expr.0)?;
+                let to_end = self.emit(Instr::JumpIfFalse(0, expr.1));
+                self.block(&block.0)?;
+                self.emit(Instr::Jump(start));
+                self.patch(to_end);
+            },
+            Stmt::For(ident, val_ident, expr, block) => {
+                self.expr(&expr.0)?;
+                self.emit(match val_ident {
+                    Some(_) => Instr::IterStartPairs(expr.1),
+                    None => Instr::IterStart(expr.1),
+                });
+                let start = self.emit(Instr::IterNext(0));
+                let mut names = declared_in(&block.0);
+                names.extend(val_ident.iter().chain(Some(ident)).map(|name| name.0.as_str()));
+                let framed = self.enter(&names);
+                if let Some(val_ident) = val_ident {
+                    self.declare(&val_ident.0);
+                }
+                self.declare(&ident.0);
+                self.stmts(&block.0)?;
+                self.leave(framed);
+                self.emit(Instr::Jump(start));
+                self.patch(start);
+            },
+            Stmt::TryCatch(try_block, ident, catch_block) => {
+                let to_catch = self.emit(Instr::TryStart(0));
+                // Calls within the `try` block are not the last thing that the function does, since their errors are caught
+                self.tries += 1;
+                self.block(&try_block.0)?;
+                self.tries -= 1;
+                self.emit(Instr::TryEnd);
+                let to_end = self.emit(Instr::Jump(0));
+                // The VM pushes the caught error before jumping here
+                self.patch(to_catch);
+                let mut names = declared_in(&catch_block.0);
+                names.push(&ident.0);
+                let framed = self.enter(&names);
+                self.declare(&ident.0);
+                self.stmts(&catch_block.0)?;
+                self.leave(framed);
+                self.patch(to_end);
+            },
+            Stmt::Match(_, _) => return Err(CompileError::Unsupported("match statement")),
+            Stmt::With(_, _, _) => return Err(CompileError::Unsupported("with statement")),
+            Stmt::Class(_, _) => return Err(CompileError::Unsupported("class declaration")),
+            Stmt::DeclPattern(_, _) => return Err(CompileError::Unsupported("destructuring declaration")),
+            Stmt::Import(path, ident) => {
+                self.emit(Instr::Import(path.0.clone(), path.1));
+                self.declare(&ident.0);
+            },
+            Stmt::Decl(ident, expr) => {
+                self.expr(&expr.0)?;
+                self.declare(&ident.0);
+            },
+            // A call that a function ends with is left for the function's caller to make, once the function has returned
+            Stmt::Return(Node(Expr::Call(_, callee, params, named), r)) if self.in_fn && self.tries == 0 => {
+                let start = self.here();
+                self.call(callee, params, named, true)?;
+                self.chunk.located.push((start..self.here(), *r));
+                self.emit(Instr::Return(0));
+            },
+            Stmt::Return(expr) => {
+                self.expr_at(&expr.0, expr.1)?;
+                self.emit(Instr::Return(0));
+            },
+        }
+        Ok(())
+    }
+}
+
+// The variables that the statements of a block declare directly, rather than within blocks of their own
+fn declared_in(block: &Block) -> Vec<&str> {
+    block.0
+        .iter()
+        .filter_map(|stmt| match &stmt.0 {
+            Stmt::Decl(ident, _) | Stmt::Import(_, ident) => Some(ident.0.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+// Finds the names that the functions within some code refer to. A variable of the code with one of these names might
+// be the one that a function refers to, so it must be kept where the function can find it. This errs on the side of
+// finding too many, since any variable of the same name is kept by name.
+#[derive(Default)]
+struct Captures {
+    names: HashSet<String>,
+    // Whether the code being looked at is within a function
+    in_fn: bool,
+}
+
+impl Captures {
+    fn in_stmts(stmts: &[Node<Stmt>]) -> HashSet<String> {
+        let mut this = Self::default();
+        stmts.iter().for_each(|stmt| this.stmt(&stmt.0));
+        this.names
+    }
+
+    fn in_expr(expr: &Expr) -> HashSet<String> {
+        let mut this = Self::default();
+        this.expr(expr);
+        this.names
+    }
+
+    // The defaults of the parameters are evaluated in the function's frame, and so may refer to its parameters
+    fn in_fn(params: &Args, body: &Block) -> HashSet<String> {
+        let mut this = Self::default();
+        this.params(params, true);
+        body.0.iter().for_each(|stmt| this.stmt(&stmt.0));
+        this.names
+    }
+
+    fn name(&mut self, name: &str) {
+        if self.in_fn {
+            self.names.insert(name.to_string());
+        }
+    }
+
+    fn params(&mut self, params: &Args, in_fn: bool) {
+        let outer = mem::replace(&mut self.in_fn, in_fn);
+        params.0.iter().filter_map(|param| param.default.as_ref()).for_each(|default| self.expr(&default.0));
+        self.in_fn = outer;
+    }
+
+    fn lvalue(&mut self, lvalue: &LVal) {
+        match lvalue {
+            LVal::Local(ident) => self.name(&ident.0),
+            LVal::Index(expr, index) => {
+                self.expr(&expr.0);
+                self.expr(&index.0);
+            },
+            LVal::Member(expr, _) => self.expr(&expr.0),
+        }
+    }
+
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::None
+            | Expr::LiteralInt(_)
+            | Expr::LiteralFloat(_)
+            | Expr::LiteralString(_)
+            | Expr::LiteralChar(_)
+            | Expr::LiteralBoolean(_)
+            | Expr::LiteralNull => {},
+            Expr::InterpolatedString(parts) => for part in parts {
+                if let StringPart::Expr(expr) = part {
+                    self.expr(&expr.0);
+                }
+            },
+            Expr::Ident(name) => self.name(&name.0),
+            Expr::List(items) => items.0.iter().for_each(|item| self.expr(&item.0)),
+            Expr::Map(items) => for (key, val) in &items.0 {
+                self.expr(&key.0);
+                self.expr(&val.0);
+            },
+            Expr::Call(_, expr, params, named) => {
+                self.expr(&expr.0);
+                params.0.iter().for_each(|param| self.expr(&param.0));
+                named.iter().for_each(|(_, param)| self.expr(&param.0));
+            },
+            Expr::DotAccess(_, expr, _)
+            | Expr::OptionalAccess(_, expr, _)
+            | Expr::UnaryNot(_, expr)
+            | Expr::UnaryNeg(_, expr)
+            | Expr::UnaryBitNot(_, expr)
+            | Expr::UnaryInput(_, expr)
+            | Expr::UnaryClone(_, expr)
+            | Expr::UnaryMirror(_, expr) => self.expr(&expr.0),
+            Expr::ListClone(left, right)
+            | Expr::Index(_, left, right)
+            | Expr::BinaryMul(_, left, right)
+            | Expr::BinaryDiv(_, left, right)
+            | Expr::BinaryRem(_, left, right)
+            | Expr::BinaryAdd(_, left, right)
+            | Expr::BinarySub(_, left, right)
+            | Expr::BinaryShl(_, left, right)
+            | Expr::BinaryShr(_, left, right)
+            | Expr::BinaryBitAnd(_, left, right)
+            | Expr::BinaryBitXor(_, left, right)
+            | Expr::BinaryBitOr(_, left, right)
+            | Expr::BinaryGreater(_, left, right)
+            | Expr::BinaryGreaterEq(_, left, right)
+            | Expr::BinaryLess(_, left, right)
+            | Expr::BinaryLessEq(_, left, right)
+            | Expr::BinaryEq(_, left, right)
+            | Expr::BinaryNotEq(_, left, right)
+            | Expr::BinaryAnd(_, left, right)
+            | Expr::BinaryOr(_, left, right)
+            | Expr::BinaryCoalesce(_, left, right)
+            | Expr::BinaryXor(_, left, right)
+            | Expr::BinaryRange(_, left, right)
+            | Expr::BinaryAs(_, left, right)
+            | Expr::BinaryIs(_, left, right) => {
+                self.expr(&left.0);
+                self.expr(&right.0);
+            },
+            Expr::Ternary(_, cond, a, b) => {
+                self.expr(&cond.0);
+                self.expr(&a.0);
+                self.expr(&b.0);
+            },
+            Expr::BinaryAssign(_, lvalue, expr)
+            | Expr::BinaryAddAssign(_, lvalue, expr)
+            | Expr::BinarySubAssign(_, lvalue, expr)
+            | Expr::BinaryMulAssign(_, lvalue, expr)
+            | Expr::BinaryDivAssign(_, lvalue, expr)
+            | Expr::BinaryRemAssign(_, lvalue, expr) => {
+                self.lvalue(&lvalue.0);
+                self.expr(&expr.0);
+            },
+            Expr::Fn(_, f) => {
+                self.params(&(f.0).0, true);
+                let outer = mem::replace(&mut self.in_fn, true);
+                self.block(&(f.1).0);
+                self.in_fn = outer;
+            },
+        }
+    }
+
+    fn block(&mut self, block: &Block) {
+        block.0.iter().for_each(|stmt| self.stmt(&stmt.0));
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Wildcard | Pattern::Binding(_) => {},
+            Pattern::Literal(expr) => self.expr(&expr.0),
+            Pattern::Range(from, to) => {
+                self.expr(&from.0);
+                self.expr(&to.0);
+            },
+            Pattern::List(items, rest) => {
+                items.iter().for_each(|item| self.pattern(&item.0));
+                if let Some(rest) = rest {
+                    self.pattern(&rest.0);
+                }
+            },
+            Pattern::Map(items) => items.iter().for_each(|(_, item)| self.pattern(&item.0)),
+            Pattern::Typed(inner, _) => self.pattern(&inner.0),
+        }
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Expr(expr) | Stmt::Print(expr) | Stmt::Return(expr) | Stmt::Decl(_, expr) => self.expr(&expr.0),
+            Stmt::If(expr, block) | Stmt::While(expr, block) | Stmt::For(_, _, expr, block) | Stmt::With(expr, _, block) => {
+                self.expr(&expr.0);
+                self.block(&block.0);
+            },
+            Stmt::IfElse(expr, a, b) => {
+                self.expr(&expr.0);
+                self.block(&a.0);
+                self.block(&b.0);
+            },
+            Stmt::IfElseIf(expr, block, else_stmt) => {
+                self.expr(&expr.0);
+                self.block(&block.0);
+                self.stmt(&else_stmt.0);
+            },
+            Stmt::TryCatch(a, _, b) => {
+                self.block(&a.0);
+                self.block(&b.0);
+            },
+            Stmt::Match(expr, arms) => {
+                self.expr(&expr.0);
+                for (pattern, block) in arms {
+                    self.pattern(&pattern.0);
+                    self.block(&block.0);
+                }
+            },
+            Stmt::Class(_, methods) => methods.iter().for_each(|(_, method)| self.expr(&method.0)),
+            Stmt::DeclPattern(pattern, expr) => {
+                self.pattern(&pattern.0);
+                self.expr(&expr.0);
+            },
+            Stmt::Import(_, _) => {},
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! A runner for a corpus of `.fg` files that are annotated with the output and errors they should produce, so that
+//! changes to the language can be checked against every case at once.
+//!
+//! Annotations are line comments, either on lines of their own or after code:
+//!
+//! ```text
+//! print 1 + 2;        # expect: 3
+//! print "a" * 2;      # expect-error: Cannot multiply
+//! ```
+//!
+//! Each `# expect:` is one line of output, in order. A case with an `# expect-error:` must fail with an error whose
+//! message contains the given text, after printing its expected output. A case without one must run without error.
+//! Every case is run both by the interpreter and as bytecode, and must behave the same in each.
+
+use std::{
+    cell::RefCell,
+    fmt,
+    fs,
+    io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+use crate::{Engine, ExecMode, ExecResult, Io, output::Json};
+
+const EXPECT_OUTPUT: &str = "# expect:";
+const EXPECT_ERROR: &str = "# expect-error:";
+
+// Cases are run with a fixed seed, so that those using `random` behave the same on every run
+const SEED: u64 = 0;
+
+/// Collects printed lines so that they can be compared with those expected.
+struct CaptureIo(Rc<RefCell<Vec<String>>>);
+
+impl Io for CaptureIo {
+    // Cases have no one to answer prompts
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(String::new())
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.0.borrow_mut().extend(s.split('\n').map(String::from));
+        Ok(())
+    }
+}
+
+/// A script from the corpus, along with what it is expected to do.
+pub struct Case {
+    path: PathBuf,
+    code: String,
+    output: Vec<String>,
+    error: Option<String>,
+}
+
+impl Case {
+    /// Read the case from a file. Modules that it imports are found relative to it.
+    pub fn load<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        let path = path.into();
+        let code = fs::read_to_string(&path)?;
+        Ok(Self::parse(path, code))
+    }
+
+    /// Read the expectations from the annotations in the code of a case.
+    pub fn parse<P: Into<PathBuf>>(path: P, code: String) -> Self {
+        let mut output = Vec::new();
+        let mut error = None;
+        for line in code.lines() {
+            if let Some(i) = line.find(EXPECT_OUTPUT) {
+                output.push(line[i + EXPECT_OUTPUT.len()..].trim().to_string());
+            } else if let Some(i) = line.find(EXPECT_ERROR) {
+                error = Some(line[i + EXPECT_ERROR.len()..].trim().to_string());
+            }
+        }
+        Self { path: path.into(), code, output, error }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Run the case in each mode, producing the ways in which it did not do what it was expected to.
+    pub fn run(&self) -> Vec<Mismatch> {
+        [ExecMode::Interpret, ExecMode::Bytecode]
+            .iter()
+            .filter_map(|&mode| self.run_in(mode))
+            .collect()
+    }
+
+    fn run_in(&self, mode: ExecMode) -> Option<Mismatch> {
+        let printed = Rc::new(RefCell::new(Vec::new()));
+        let mut engine = Engine::build()
+            .with_io(CaptureIo(printed.clone()))
+            .with_exec_mode(mode)
+            .with_module_root(self.path.parent().unwrap_or(Path::new(".")))
+            .with_seed(SEED)
+            .finish();
+        let result = engine.exec(&self.code);
+        let output = printed.borrow().clone();
+        let mismatch = |problem| Some(Mismatch { mode, problem });
+
+        if let (None, Err(err)) = (&self.error, &result) {
+            return mismatch(Problem::UnexpectedError(err.to_string()));
+        }
+        if output != self.output {
+            // Each line is ended rather than separated, so that no output at all differs from a single empty line
+            let text = |lines: &[String]| lines.iter().map(|line| format!("{}\n", line)).collect::<String>();
+            return mismatch(Problem::Output(crate::exec::changed_lines(&text(&self.output), &text(&output))));
+        }
+        match (&self.error, result) {
+            (Some(expected), Ok(())) => mismatch(Problem::MissingError(expected.clone())),
+            (Some(expected), Err(err)) => {
+                let mut messages = Vec::new();
+                collect_messages(&err.to_json_value(), &mut messages);
+                if messages.iter().any(|message| message.contains(expected.as_str())) {
+                    None
+                } else {
+                    mismatch(Problem::WrongError(expected.clone(), err.to_string()))
+                }
+            },
+            (None, _) => None,
+        }
+    }
+}
+
+// Expected errors are looked for in the messages alone, since the code that errors point to includes the annotations
+fn collect_messages(json: &Json, messages: &mut Vec<String>) {
+    match json {
+        Json::Object(fields) => for (name, field) in fields {
+            match (*name, field) {
+                ("message", Json::Str(message)) => messages.push(message.clone()),
+                (_, field) => collect_messages(field, messages),
+            }
+        },
+        Json::Array(items) => items.iter().for_each(|item| collect_messages(item, messages)),
+        _ => {},
+    }
+}
+
+/// The ways in which a case can fail to do what it was expected to.
+#[derive(Debug)]
+pub enum Problem {
+    /// The lines that differ between the expected and actual output, prefixed with `-` and `+` respectively.
+    Output(Vec<String>),
+    /// The case failed with this error, although none was expected.
+    UnexpectedError(String),
+    /// The case ran without error, although one containing this text was expected.
+    MissingError(String),
+    /// The case failed with an error that does not contain the expected text. Expected, found.
+    WrongError(String, String),
+}
+
+/// A way in which a case did not do what it was expected to, when run in some mode.
+#[derive(Debug)]
+pub struct Mismatch {
+    pub mode: ExecMode,
+    pub problem: Problem,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mode = match self.mode {
+            ExecMode::Interpret => "interpreted",
+            ExecMode::Bytecode => "run as bytecode",
+        };
+        match &self.problem {
+            Problem::Output(diff) => {
+                writeln!(f, "When {}, the output differs (-expected +actual):", mode)?;
+                diff.iter().try_for_each(|line| writeln!(f, "{}", line))
+            },
+            Problem::UnexpectedError(found) => {
+                writeln!(f, "When {}, the case failed unexpectedly:", mode)?;
+                write!(f, "{}", found)
+            },
+            Problem::MissingError(expected) => writeln!(f, "When {}, the case did not fail with the expected error: {}", mode, expected),
+            Problem::WrongError(expected, found) => {
+                writeln!(f, "When {}, the case failed with an error other than the expected one: {}", mode, expected)?;
+                write!(f, "{}", found)
+            },
+        }
+    }
+}
+
+/// Every `.fg` file in the directory and those within it, in order of their paths. Files in directories named
+/// `modules` are left out, since they are imported by cases rather than being cases of their own.
+pub fn find_cases<P: AsRef<Path>>(dir: P) -> io::Result<Vec<PathBuf>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if path.file_name().map(|name| name != "modules").unwrap_or(true) {
+                cases.extend(find_cases(&path)?);
+            }
+        } else if path.extension().map(|ext| ext == "fg").unwrap_or(false) {
+            cases.push(path);
+        }
+    }
+    cases.sort();
+    Ok(cases)
+}
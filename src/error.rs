@@ -1,7 +1,8 @@
 use std::fmt;
 use crate::{
-    parser::ParseError,
+    parser::{ParseError, SrcRef},
     exec::ExecError,
+    output::{self, DiagnosticStyle, Json},
 };
 
 #[derive(Debug)]
@@ -9,6 +10,9 @@ pub enum ForgeError {
     Parse(ParseError),
     Exec(ExecError),
     InSrc(String, Box<ForgeError>),
+    /// Forge itself panicked while running the code, with the given message. This is always a bug in Forge, and is
+    /// only produced by the `Engine::safe_*` methods, which catch the panic rather than letting it reach the host.
+    Internal(String),
 }
 
 pub type ForgeResult<T> = Result<T, ForgeError>;
@@ -26,20 +30,81 @@ impl From<ExecError> for ForgeError {
 }
 
 impl ForgeError {
-    fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, _depth: usize) -> fmt::Result {
+    /// Whether the code failed to parse only because it ended part-way through a construct (see
+    /// `ParseError::is_incomplete`).
+    pub fn is_incomplete(&self) -> bool {
         match self {
-            ForgeError::Parse(err) => err.fmt_nice(f, src, 0, vec![]),
-            ForgeError::Exec(err) => err.fmt_nice(f, src, None, 0),
+            ForgeError::Parse(err) => err.is_incomplete(),
+            ForgeError::InSrc(_, err) => err.is_incomplete(),
+            ForgeError::Exec(_) => false,
+            ForgeError::Internal(_) => false,
+        }
+    }
+
+    /// Display the error in the given style. Displaying the error directly uses `DiagnosticStyle::Plain`.
+    pub fn display_with(&self, style: DiagnosticStyle) -> Diagnostic<'_> {
+        Diagnostic { err: self, style }
+    }
+
+    /// Describe the error as a JSON document, for tools that run Forge code and need to read its errors.
+    ///
+    /// The document is an object with an `errors` array (several parse errors may be reported at once). Each error
+    /// has a `kind` (`"parse"`, `"runtime"` or `"internal"`), a `message`, and a `location` giving the `start` and
+    /// exclusive `end` of the code it refers to as a `line` and `col`. Parse errors also list what was `expected` and
+    /// `found`, the constructs being parsed (`while_parsing`, outermost first), and whether the error was only caused by
+    /// the input ending early (`incomplete`). Runtime errors list secondary `labels`, a `trace` of the function calls
+    /// that the error passed through (innermost first), and the `cause` of a failed import. Internal errors have no
+    /// location.
+    pub fn to_json(&self) -> String {
+        self.to_json_value().to_string()
+    }
+
+    pub(crate) fn to_json_value(&self) -> Json {
+        let mut errors = Vec::new();
+        match self {
+            ForgeError::Parse(err) => err.to_json(SrcRef::empty(), &mut Vec::new(), false, &mut errors),
+            ForgeError::Exec(err) => errors.push(err.to_json()),
+            ForgeError::InSrc(_, err) => return err.to_json_value(),
+            ForgeError::Internal(msg) => errors.push(Json::Object(vec![
+                ("kind", Json::from(String::from("internal"))),
+                ("message", Json::from(internal_message(msg))),
+                ("location", Json::Null),
+            ])),
+        }
+        Json::Object(vec![("errors", Json::Array(errors))])
+    }
+
+    fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, _depth: usize, style: DiagnosticStyle) -> fmt::Result {
+        match self {
+            ForgeError::Parse(err) => err.fmt_nice(f, src, 0, style, vec![]),
+            ForgeError::Exec(err) => err.fmt_nice(f, src, None, 0, style),
+            ForgeError::Internal(msg) => writeln!(f, "{} {}", output::ErrorTag(style), internal_message(msg)),
             _ => Ok(()),
         }
     }
 }
 
+fn internal_message(msg: &str) -> String {
+    format!("Forge failed unexpectedly: {}. This is a bug in Forge rather than in the code being run.", msg)
+}
+
 impl fmt::Display for ForgeError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            ForgeError::InSrc(src, err) => err.fmt_nice(f, Some(src), 0),
-            err => err.fmt_nice(f, None, 0),
+        write!(f, "{}", self.display_with(DiagnosticStyle::Plain))
+    }
+}
+
+/// An error paired with the style in which it should be displayed, created with `ForgeError::display_with`.
+pub struct Diagnostic<'a> {
+    err: &'a ForgeError,
+    style: DiagnosticStyle,
+}
+
+impl<'a> fmt::Display for Diagnostic<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.err {
+            ForgeError::InSrc(src, err) => err.fmt_nice(f, Some(src), 0, self.style),
+            err => err.fmt_nice(f, None, 0, self.style),
         }
     }
 }
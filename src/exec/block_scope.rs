@@ -1,56 +1,28 @@
-use hashbrown::HashMap;
 use super::{
-    ExecError,
-    ExecResult,
+    Env,
     Scope,
-    Value,
 };
 
-pub struct BlockScope<'a> {
-    vars: HashMap<String, Value>,
-    parent: &'a mut dyn Scope,
+pub struct BlockScope {
+    env: Env,
 }
 
-impl<'a> BlockScope<'a> {
-    pub fn new(parent: &'a mut dyn Scope) -> Self {
+impl BlockScope {
+    pub fn new(parent: &dyn Scope) -> Self {
+        Self::within(parent.env())
+    }
+
+    /// Create a scope for a function body, nested within the environment that the function captured.
+    pub fn within(env: &Env) -> Self {
         Self {
-            vars: HashMap::new(),
-            parent,
+            env: env.child(),
         }
     }
 }
 
-impl<'a> Scope for BlockScope<'a> {
-    fn get_var(&self, name: &str) -> ExecResult<Value> {
-        self.vars
-            .get(name)
-            .cloned()
-            .ok_or(ExecError::NoSuchItem(name.to_string()))
-            .or_else(|_| self.parent.get_var(name))
-    }
-
-    fn take_var(&mut self, name: &str) -> Option<Value> {
-        self.vars
-            .remove(name)
-            .or_else(|| self.parent.take_var(name))
-    }
-
-    fn declare_var(&mut self, name: String, val: Value) {
-        self.vars.insert(name, val);
-    }
-
-    fn assign_var(&mut self, name: &str, val: Value) -> ExecResult<()> {
-        self.vars
-            .get_mut(name)
-            .map(|v| *v = val.clone())
-            .ok_or(ExecError::NoSuchItem(name.to_string()))
-            .or_else(|_| self.parent.assign_var(name, val))
-    }
-
-    fn list(&self) {
-        for (name, val) in &self.vars {
-            println!("{} = {:?}", name, val);
-        }
+impl Scope for BlockScope {
+    fn env(&self) -> &Env {
+        &self.env
     }
 
     fn as_scope_mut(&mut self) -> &mut dyn Scope {
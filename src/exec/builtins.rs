@@ -0,0 +1,462 @@
+use std::{
+    env,
+    rc::Rc,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Obj,
+    Value,
+    heap::Heap,
+    check,
+    config,
+    crypto,
+    diff,
+    expect,
+    format,
+    fs,
+    markup,
+    math,
+    memo,
+    method,
+    mock,
+    prompt,
+    reflect,
+    semver,
+    stats,
+    sys,
+    term,
+    url,
+    weak,
+    time::{Duration, DateTime},
+};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use super::compress;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+use super::serial;
+#[cfg(feature = "regex")]
+use super::regex;
+#[cfg(feature = "sqlite")]
+use super::sql;
+#[cfg(feature = "net")]
+use super::ws;
+
+pub(super) type BuiltinFn = fn(Vec<Value>, &mut ExecCtx, &Rc<String>, SrcRef, SrcRef) -> ExecResult<Value>;
+
+/// A function that is available to all Forge code without being declared, such as `heap`.
+///
+/// Builtins are looked up after every other scope, so declaring a variable of the same name shadows them.
+pub struct Builtin {
+    name: &'static str,
+    // The namespace that the builtin is a member of, if any
+    namespace: Option<&'static str>,
+    f: BuiltinFn,
+}
+
+impl Builtin {
+    pub(super) fn new(name: &'static str, namespace: Option<&'static str>, f: BuiltinFn) -> Self {
+        Self { name, namespace, f }
+    }
+}
+
+impl Obj for Builtin {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<builtin function '{}'>", self.name))
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.name.to_string())
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let result = match mock::find(ctx, self.namespace, self.name) {
+            Some(mock) => mock.call(args, ctx, src, r_caller, r_args, |args, ctx| (self.f)(args, ctx, src, r_caller, r_args)),
+            None => (self.f)(args, ctx, src, r_caller, r_args),
+        };
+        result.map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))
+    }
+}
+
+/// A group of related builtins accessed as members, such as `datetime.now`, along with any constants that belong with
+/// them, such as `math.pi`.
+pub struct Namespace {
+    name: &'static str,
+    members: &'static [(&'static str, BuiltinFn)],
+    consts: &'static [(&'static str, Value)],
+}
+
+impl Obj for Namespace {
+    fn get_type_name(&self) -> String {
+        String::from("namespace")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<namespace '{}'>", self.name))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        if let Some((_, val)) = self.consts.iter().find(|(member, _)| *member == name) {
+            return Ok(val.clone());
+        }
+        self.members
+            .iter()
+            .find(|(member, _)| *member == name)
+            .map(|(member, f)| Value::from(Builtin { name: member, namespace: Some(self.name), f: *f }))
+            .ok_or_else(|| ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string()))))
+    }
+}
+
+const DATETIME: &[(&str, BuiltinFn)] = &[
+    ("now", datetime_now),
+    ("parse", datetime_parse),
+    ("from_unix", datetime_from_unix),
+];
+
+const TIME: &[(&str, BuiltinFn)] = &[
+    ("now", datetime_now),
+    ("unix", time_unix),
+    ("elapsed", time_elapsed),
+    ("format", time_format),
+];
+
+const DURATION: &[(&str, BuiltinFn)] = &[
+    ("days", |args, _, _, _, r_args| duration_in(args, r_args, 86_400_000_000_000)),
+    ("hours", |args, _, _, _, r_args| duration_in(args, r_args, 3_600_000_000_000)),
+    ("minutes", |args, _, _, _, r_args| duration_in(args, r_args, 60_000_000_000)),
+    ("seconds", |args, _, _, _, r_args| duration_in(args, r_args, 1_000_000_000)),
+    ("millis", |args, _, _, _, r_args| duration_in(args, r_args, 1_000_000)),
+];
+
+const UUID: &[(&str, BuiltinFn)] = &[
+    ("v4", uuid_v4),
+];
+
+const RANDOM: &[(&str, BuiltinFn)] = &[
+    ("token", random_token),
+];
+
+const ENV: &[(&str, BuiltinFn)] = &[
+    ("get", env_get),
+    ("set", env_set),
+    ("cwd", env_cwd),
+    ("set_cwd", env_set_cwd),
+];
+
+pub(crate) fn find_builtin(name: &str) -> Option<Value> {
+    let (name, f): (_, BuiltinFn) = match name {
+        "heap" => ("heap", builtin_heap),
+        "weak" => ("weak", weak::builtin_weak),
+        "on_interrupt" => ("on_interrupt", builtin_on_interrupt),
+        "check" => ("check", check::builtin_check),
+        "expect" => ("expect", expect::builtin_expect),
+        "expect_snapshot" => ("expect_snapshot", expect::builtin_expect_snapshot),
+        "mock" => ("mock", mock::builtin_mock),
+        "spy" => ("spy", mock::builtin_spy),
+        "partial" => ("partial", method::builtin_partial),
+        "compose" => ("compose", method::builtin_compose),
+        "memo" => ("memo", memo::builtin_memo),
+        "rand" => ("rand", builtin_rand),
+        "rand_range" => ("rand_range", builtin_rand_range),
+        "shuffle" => ("shuffle", builtin_shuffle),
+        "sortedmap" => ("sortedmap", builtin_sortedmap),
+        "defaultmap" => ("defaultmap", builtin_defaultmap),
+        "format" => ("format", format::builtin_format),
+        "group_by" => ("group_by", method::builtin_group_by),
+        "count_by" => ("count_by", method::builtin_count_by),
+        "unique" => ("unique", method::builtin_unique),
+        "datetime" => return Some(Value::from(Namespace { name: "datetime", members: DATETIME, consts: &[] })),
+        "duration" => return Some(Value::from(Namespace { name: "duration", members: DURATION, consts: &[] })),
+        "time" => return Some(Value::from(Namespace { name: "time", members: TIME, consts: &[] })),
+        "uuid" => return Some(Value::from(Namespace { name: "uuid", members: UUID, consts: &[] })),
+        "random" => return Some(Value::from(Namespace { name: "random", members: RANDOM, consts: &[] })),
+        "config" => return Some(Value::from(Namespace { name: "config", members: config::CONFIG, consts: &[] })),
+        "fs" => return Some(Value::from(Namespace { name: "fs", members: fs::FS, consts: &[] })),
+        "env" => return Some(Value::from(Namespace { name: "env", members: ENV, consts: &[] })),
+        "reflect" => return Some(Value::from(Namespace { name: "reflect", members: reflect::REFLECT, consts: &[] })),
+        "math" => return Some(Value::from(Namespace { name: "math", members: math::MATH, consts: math::MATH_CONSTS })),
+        "stats" => return Some(Value::from(Namespace { name: "stats", members: stats::STATS, consts: &[] })),
+        "prompt" => return Some(Value::from(Namespace { name: "prompt", members: prompt::PROMPT, consts: &[] })),
+        "url" => return Some(Value::from(Namespace { name: "url", members: url::URL, consts: &[] })),
+        "term" => return Some(Value::from(Namespace { name: "term", members: term::TERM, consts: &[] })),
+        "crypto" => return Some(Value::from(Namespace { name: "crypto", members: crypto::CRYPTO, consts: &[] })),
+        "diff" => return Some(Value::from(Namespace { name: "diff", members: diff::DIFF, consts: &[] })),
+        "semver" => return Some(Value::from(Namespace { name: "semver", members: semver::SEMVER, consts: &[] })),
+        "xml" => return Some(Value::from(Namespace { name: "xml", members: markup::XML, consts: &[] })),
+        "html" => return Some(Value::from(Namespace { name: "html", members: markup::HTML, consts: &[] })),
+        #[cfg(feature = "msgpack")]
+        "msgpack" => return Some(Value::from(Namespace { name: "msgpack", members: serial::MSGPACK, consts: &[] })),
+        #[cfg(feature = "cbor")]
+        "cbor" => return Some(Value::from(Namespace { name: "cbor", members: serial::CBOR, consts: &[] })),
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        "compress" => return Some(Value::from(Namespace { name: "compress", members: compress::COMPRESS, consts: &[] })),
+        #[cfg(feature = "regex")]
+        "regex" => return Some(Value::from(Namespace { name: "regex", members: regex::REGEX, consts: &[] })),
+        #[cfg(feature = "sqlite")]
+        "sql" => return Some(Value::from(Namespace { name: "sql", members: sql::SQL, consts: &[] })),
+        #[cfg(feature = "net")]
+        "ws" => return Some(Value::from(Namespace { name: "ws", members: ws::WS, consts: &[] })),
+        _ => return None,
+    };
+    Some(Value::from(Builtin { name, namespace: None, f }))
+}
+
+/// Builtins that describe the engine that the code is running in, and so are found after those that do not.
+pub(crate) fn find_engine_builtin(name: &str, ctx: &ExecCtx) -> Option<Value> {
+    match name {
+        "sys" => Some(sys::sys(ctx)),
+        _ => None,
+    }
+}
+
+pub(super) fn number_arg(val: &Value, r_args: SrcRef) -> ExecResult<f64> {
+    match val {
+        Value::Int(x) => Ok(*x as f64),
+        Value::Float(x) => Ok(*x),
+        val => Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("number"), val.get_type_name())))),
+    }
+}
+
+pub(super) fn string_arg(val: &Value, r_args: SrcRef) -> ExecResult<String> {
+    val.as_string()
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string"), val.get_type_name()))))
+}
+
+// Bytes are represented as a list of ints from 0 to 255
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "gzip", feature = "zstd"))]
+pub(super) fn bytes_arg(val: &Value, r_args: SrcRef) -> ExecResult<Vec<u8>> {
+    let err = || ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list of bytes"), val.get_type_name())));
+    match val {
+        Value::List(l) => l
+            .borrow()
+            .iter()
+            .map(|b| match b {
+                Value::Int(x) if *x >= 0 && *x <= 255 => Ok(*x as u8),
+                _ => Err(err()),
+            })
+            .collect(),
+        _ => Err(err()),
+    }
+}
+
+#[cfg(any(feature = "msgpack", feature = "cbor", feature = "gzip", feature = "zstd"))]
+pub(super) fn bytes_value(bytes: &[u8]) -> Value {
+    Value::from(bytes.iter().map(|b| Value::Int(*b as i64)).collect::<Vec<_>>())
+}
+
+fn builtin_heap(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 1, r_args)?;
+    Ok(Value::from(Heap::new(args.into_iter().next())))
+}
+
+// `on_interrupt(fn)` calls `fn` instead of failing when the engine is interrupted, and `on_interrupt(null)` restores
+// the default behaviour
+fn builtin_on_interrupt(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    ctx.on_interrupt = match args.into_iter().next().unwrap() {
+        Value::Null => None,
+        handler => Some(handler),
+    };
+    Ok(Value::Null)
+}
+
+fn datetime_now(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    ctx.now().map(Value::from).map_err(|err| ExecError::At(r_caller, Box::new(err)))
+}
+
+// Seconds since the start of 1970 (UTC), with a fraction
+fn time_unix(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    ctx.now()
+        .map(|now| Value::Float(now.unix_nanos() as f64 / 1e9))
+        .map_err(|err| ExecError::At(r_caller, Box::new(err)))
+}
+
+// How long the engine has been running, for timing code with `time.elapsed() - start`. Unlike the difference between
+// two calls to `time.now()`, this is not thrown off by the clock being changed in between.
+fn time_elapsed(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    ctx.elapsed().map(Value::from).map_err(|err| ExecError::At(r_caller, Box::new(err)))
+}
+
+// `time.format(pattern)` is `time.now().format(pattern)`
+fn time_format(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let pattern = string_arg(&args[0], r_args)?;
+    let now = ctx.now().map_err(|err| ExecError::At(r_caller, Box::new(err)))?;
+    now.format(&pattern)
+        .map(Value::from)
+        .map_err(|c| ExecError::At(r_args, Box::new(ExecError::Custom(format!("Unknown format specifier '%{}'.", c)))))
+}
+
+fn datetime_parse(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let text = match &args[0] {
+        Value::String(s) => s.borrow().clone(),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string"), val.get_type_name())))),
+    };
+    DateTime::parse_rfc3339(&text)
+        .map(Value::from)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::CouldNotParse(text))))
+}
+
+fn datetime_from_unix(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let secs = number_arg(&args[0], r_args)?;
+    Ok(Value::from(DateTime::from_unix_nanos(Duration::from_secs_f64(secs).as_nanos())))
+}
+
+// Ints are multiplied exactly, so that durations of many days are not rounded to the precision of a float
+fn duration_in(args: Vec<Value>, r_args: SrcRef, unit_nanos: i128) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let nanos = match &args[0] {
+        Value::Int(x) => *x as i128 * unit_nanos,
+        val => (number_arg(val, r_args)? * unit_nanos as f64).round() as i128,
+    };
+    Ok(Value::from(Duration::from_nanos(nanos)))
+}
+
+fn uuid_v4(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    let mut bytes = [0; 16];
+    ctx.rng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+
+    let hex = bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+    Ok(Value::from(format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32])))
+}
+
+// `random.token(n)` produces `n` hex digits, and `random.token(n, "alphanumeric")` draws from letters and digits too
+fn random_token(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    const HEX: &[u8] = b"0123456789abcdef";
+    const ALPHANUMERIC: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let len = number_arg(&args[0], r_args)?;
+    if len < 0.0 || len.fract() != 0.0 {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Token length must be a whole number.")))));
+    }
+    let alphabet = match args.get(1).map(|kind| kind.as_string()) {
+        None => HEX,
+        Some(Some(ref kind)) if kind == "hex" => HEX,
+        Some(Some(ref kind)) if kind == "alphanumeric" => ALPHANUMERIC,
+        Some(_) => return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Token kind must be \"hex\" or \"alphanumeric\"."))))),
+    };
+
+    Ok(Value::from((0..len as usize)
+        .map(|_| alphabet[ctx.rng.below(alphabet.len() as u64) as usize] as char)
+        .collect::<String>()))
+}
+
+fn builtin_rand(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Float(ctx.rng.next_f64()))
+}
+
+// Ints produce an int from `a` to `b` inclusive, like the faces of a die, while floats produce a float below `b`
+fn builtin_rand_range(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let empty = || ExecError::At(r_args, Box::new(ExecError::Custom(String::from(
+        "The end of the range must not be less than its start.",
+    ))));
+    match (&args[0], &args[1]) {
+        (Value::Int(a), Value::Int(b)) if b < a => Err(empty()),
+        (Value::Int(a), Value::Int(b)) => {
+            // A span of zero means that the range covers every int
+            let span = (*b as u64).wrapping_sub(*a as u64).wrapping_add(1);
+            let offset = if span == 0 { ctx.rng.next_u64() } else { ctx.rng.below(span) };
+            Ok(Value::Int(a.wrapping_add(offset as i64)))
+        },
+        (a, b) => {
+            let (a, b) = (number_arg(a, r_args)?, number_arg(b, r_args)?);
+            if !(a <= b) || !(b - a).is_finite() {
+                return Err(empty());
+            }
+            Ok(Value::Float((a + (b - a) * ctx.rng.next_f64()).min(b)))
+        },
+    }
+}
+
+// Shuffles the list in place, as `sort` sorts it
+fn builtin_shuffle(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    match &args[0] {
+        Value::List(l) => l.borrow_mut().modify(|items| {
+            for i in (1..items.len()).rev() {
+                items.swap(i, ctx.rng.below(i as u64 + 1) as usize);
+            }
+        }),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    }
+    Ok(Value::Null)
+}
+
+// `sortedmap()` makes an empty map that keeps its items in the order of their keys, and `sortedmap(map)` makes one
+// with the items of another map
+fn builtin_sortedmap(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 1, r_args)?;
+    let mut map = Map::new_sorted();
+    match args.get(0) {
+        Some(Value::Map(m)) => map.extend(m.borrow().iter().map(|(key, val)| (key.clone(), val.clone()))),
+        Some(val) => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("map"), val.get_type_name())))),
+        None => {},
+    }
+    Ok(Value::new_map(map))
+}
+
+// `defaultmap(f)` makes an empty map in which indexing a missing key stores and produces `f()`, so that
+// `counts[word] += 1` needs no check for whether `word` has been counted before
+fn builtin_defaultmap(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let default = args.into_iter().next().unwrap();
+    if !default.is_function() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::CannotCall(default.get_type_name()))));
+    }
+    Ok(Value::new_map(Map::with_default(default)))
+}
+
+// Produces null for variables that are not set (or that are not valid unicode)
+fn env_get(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    ctx.permissions.require_env().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(env::var(string_arg(&args[0], r_args)?).ok().map(Value::from).into())
+}
+
+// Setting a variable to null removes it
+fn env_set(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    ctx.permissions.require_env().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let name = string_arg(&args[0], r_args)?;
+    if name.is_empty() || name.contains(|c| c == '=' || c == '\0') {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a valid environment variable name.", name)))));
+    }
+    match &args[1] {
+        Value::Null => env::remove_var(name),
+        val => env::set_var(name, val.get_display_text().map_err(|err| ExecError::At(r_args, Box::new(err)))?),
+    }
+    Ok(Value::Null)
+}
+
+fn env_cwd(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    ctx.permissions.require_env().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let cwd = env::current_dir().map_err(|err| ExecError::At(r_args, Box::new(ExecError::Io(err))))?;
+    Ok(Value::from(cwd.to_string_lossy().into_owned()))
+}
+
+// The working directory is shared by the whole process, so this affects relative paths everywhere, including in the
+// host application
+fn env_set_cwd(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    ctx.permissions.require_env().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    env::set_current_dir(string_arg(&args[0], r_args)?).map_err(|err| ExecError::At(r_args, Box::new(ExecError::Io(err))))?;
+    Ok(Value::Null)
+}
@@ -0,0 +1,261 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Rng,
+    Value,
+    method,
+};
+
+// The number of random inputs tried unless another is given
+const DEFAULT_RUNS: usize = 100;
+// Inputs grow with each run, up to this size
+const MAX_SIZE: u64 = 100;
+// Shrinking gives up after this many calls, so that slow properties still finish
+const MAX_SHRINK_CALLS: usize = 1000;
+// Ints at the edges of their range are tried every so often, since they are where overflows and sign errors hide
+const EDGE_INTS: [i64; 5] = [0, 1, -1, i64::max_value(), i64::min_value()];
+const EDGE_FLOATS: [f64; 4] = [0.0, 0.5, -1.5, 1e10];
+// Chars outside of ASCII are tried every so often, since they are where byte and char indices differ
+const UNUSUAL_CHARS: [char; 4] = ['é', 'ß', '中', '😀'];
+
+/// The types of parameter that `check` can generate values for.
+#[derive(Copy, Clone)]
+enum Gen {
+    Int,
+    Float,
+    Num,
+    Str,
+    Char,
+    Bool,
+    List,
+}
+
+impl Gen {
+    fn from_type(ty: &str) -> Option<Self> {
+        match ty {
+            "int" => Some(Gen::Int),
+            "float" => Some(Gen::Float),
+            "num" => Some(Gen::Num),
+            "str" => Some(Gen::Str),
+            "char" => Some(Gen::Char),
+            "bool" => Some(Gen::Bool),
+            "list" => Some(Gen::List),
+            _ => None,
+        }
+    }
+
+    fn generate(self, rng: &mut Rng, size: u64) -> Value {
+        match self {
+            Gen::Int => Value::Int(gen_int(rng, size)),
+            Gen::Float => Value::Float(gen_float(rng, size)),
+            Gen::Num if rng.below(2) == 0 => Value::Int(gen_int(rng, size)),
+            Gen::Num => Value::Float(gen_float(rng, size)),
+            Gen::Str => Value::from((0..rng.below(size / 4 + 1)).map(|_| gen_char(rng)).collect::<String>()),
+            Gen::Char => Value::Char(gen_char(rng)),
+            Gen::Bool => Value::Boolean(rng.below(2) == 0),
+            Gen::List => Value::from((0..rng.below(size / 4 + 1)).map(|_| Value::Int(gen_int(rng, size))).collect::<Vec<_>>()),
+        }
+    }
+}
+
+fn gen_int(rng: &mut Rng, size: u64) -> i64 {
+    if rng.below(10) == 0 {
+        EDGE_INTS[rng.below(EDGE_INTS.len() as u64) as usize]
+    } else {
+        rng.below(2 * size + 1) as i64 - size as i64
+    }
+}
+
+fn gen_float(rng: &mut Rng, size: u64) -> f64 {
+    if rng.below(10) == 0 {
+        EDGE_FLOATS[rng.below(EDGE_FLOATS.len() as u64) as usize]
+    } else {
+        // 53 random bits make a float between 0 and 1
+        let unit = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        (unit * 2.0 - 1.0) * size as f64
+    }
+}
+
+fn gen_char(rng: &mut Rng) -> char {
+    if rng.below(20) == 0 {
+        UNUSUAL_CHARS[rng.below(UNUSUAL_CHARS.len() as u64) as usize]
+    } else {
+        (b' ' + rng.below(95) as u8) as char
+    }
+}
+
+/// Simpler values to try in place of one for which a property failed, simplest first.
+fn shrink(val: &Value) -> Vec<Value> {
+    let mut candidates = match val {
+        Value::Int(0) => Vec::new(),
+        Value::Int(x) => {
+            let mut candidates = vec![Value::Int(0)];
+            if *x < 0 && *x != i64::min_value() {
+                candidates.push(Value::Int(-x));
+            }
+            candidates.push(Value::Int(x / 2));
+            candidates.push(Value::Int(x - x.signum()));
+            candidates
+        },
+        Value::Float(x) if *x == 0.0 => Vec::new(),
+        Value::Float(x) => {
+            let mut candidates = vec![Value::Float(0.0), Value::Float(x.trunc())];
+            if x.abs() >= 1.0 {
+                candidates.push(Value::Float(x / 2.0));
+            }
+            candidates
+        },
+        Value::Char('a') => Vec::new(),
+        Value::Char(_) => vec![Value::Char('a')],
+        Value::Boolean(true) => vec![Value::Boolean(false)],
+        Value::String(s) => {
+            let chars = s.borrow().chars().collect::<Vec<_>>();
+            let mut candidates = halves(&chars)
+                .into_iter()
+                .map(|chars| Value::from(chars.into_iter().collect::<String>()))
+                .collect::<Vec<_>>();
+            for (i, c) in chars.iter().enumerate() {
+                if *c != 'a' {
+                    let mut chars = chars.clone();
+                    chars[i] = 'a';
+                    candidates.push(Value::from(chars.into_iter().collect::<String>()));
+                }
+            }
+            candidates
+        },
+        Value::List(l) => {
+            let items = l.borrow().to_vec();
+            let mut candidates = halves(&items).into_iter().map(Value::from).collect::<Vec<_>>();
+            for (i, item) in items.iter().enumerate() {
+                for smaller in shrink(item) {
+                    let mut items = items.clone();
+                    items[i] = smaller;
+                    candidates.push(Value::from(items));
+                }
+            }
+            candidates
+        },
+        _ => Vec::new(),
+    };
+    candidates.retain(|candidate| candidate != val);
+    candidates.dedup();
+    candidates
+}
+
+// The empty sequence, each half, and the sequence without each of its items
+fn halves<T: Clone>(items: &[T]) -> Vec<Vec<T>> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+    let mut candidates = vec![Vec::new()];
+    if items.len() > 2 {
+        candidates.push(items[..items.len() / 2].to_vec());
+        candidates.push(items[items.len() / 2..].to_vec());
+    }
+    for i in 0..items.len() {
+        let mut items = items.to_vec();
+        items.remove(i);
+        candidates.push(items);
+    }
+    candidates
+}
+
+// Strings and lists are shared, so each call is given copies that the property may change freely
+fn fresh(val: &Value) -> Value {
+    match val {
+        Value::String(s) => Value::from(s.borrow().clone()),
+        Value::List(l) => Value::from(l.borrow().iter().map(fresh).collect::<Vec<_>>()),
+        val => val.clone(),
+    }
+}
+
+/// Call the property, producing the reason that it failed, if it did. Properties pass unless they return `false` or
+/// produce an error, so they may use `expect` rather than returning a bool.
+fn falsify(property: &Value, args: &[Value], ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Option<String>> {
+    match property.call(args.iter().map(fresh).collect(), ctx, src, r_caller, r_args) {
+        Ok(Value::Boolean(false)) => Ok(Some(String::from("The property returned false."))),
+        Ok(_) => Ok(None),
+        Err(err) if err.is_interrupt() => Err(err),
+        Err(err) => Ok(Some(format!("The property failed: {}", err.message()))),
+    }
+}
+
+/// Call a property with random values of the types its parameters are annotated with, and fail with the simplest
+/// inputs that can be found for which it does not hold.
+pub(super) fn builtin_check(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let error = |msg: String| ExecError::At(r_args, Box::new(ExecError::Custom(msg)));
+    let property = &args[0];
+    let params = match property {
+        Value::Fn(_, f, _) => ((f.0).0).0
+            .iter()
+            .map(|param| match &param.ty {
+                Some(ty) => Gen::from_type(&ty.0).map(|gen| (param.name.0.clone(), gen)).ok_or_else(|| error(format!(
+                    "Cannot generate values of type '{}' for parameter '{}'. Use int, float, num, str, char, bool or list.",
+                    ty.0, param.name.0,
+                ))),
+                None => Err(error(format!(
+                    "Parameter '{}' needs a type, such as '{}: int', so that values can be generated for it.",
+                    param.name.0, param.name.0,
+                ))),
+            })
+            .collect::<ExecResult<Vec<_>>>()?,
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("function"), val.get_type_name())))),
+    };
+    let runs = match args.get(1) {
+        Some(Value::Int(runs)) if *runs > 0 => *runs as usize,
+        Some(runs) => return Err(error(format!("The number of runs must be a positive int, not {}.", runs.get_preview_text().unwrap_or_default()))),
+        None => DEFAULT_RUNS,
+    };
+
+    for run in 0..runs {
+        let size = (1 + run as u64 * MAX_SIZE / runs as u64).min(MAX_SIZE);
+        let mut inputs = params.iter().map(|(_, gen)| gen.generate(&mut ctx.rng, size)).collect::<Vec<_>>();
+        let mut reason = match falsify(property, &inputs, ctx, src, r_caller, r_args)? {
+            Some(reason) => reason,
+            None => continue,
+        };
+
+        // Replace the inputs with simpler ones for as long as the property still fails
+        let (mut calls, mut shrinks) = (0, 0);
+        'shrink: while calls < MAX_SHRINK_CALLS {
+            for i in 0..inputs.len() {
+                for candidate in shrink(&inputs[i]) {
+                    if calls >= MAX_SHRINK_CALLS {
+                        break 'shrink;
+                    }
+                    calls += 1;
+                    let mut trial = inputs.clone();
+                    trial[i] = candidate;
+                    if let Some(new_reason) = falsify(property, &trial, ctx, src, r_caller, r_args)? {
+                        inputs = trial;
+                        reason = new_reason;
+                        shrinks += 1;
+                        continue 'shrink;
+                    }
+                }
+            }
+            break;
+        }
+
+        let mut differences = params
+            .iter()
+            .zip(&inputs)
+            .map(|((name, _), val)| format!("{} = {}", name, val.get_preview_text().unwrap_or_else(|| format!("<{}>", val.get_type_name()))))
+            .collect::<Vec<_>>();
+        differences.push(reason);
+        return Err(ExecError::ExpectationFailed {
+            message: format!(
+                "The property does not hold, found after {} run{} and simplified {} time{}:",
+                run + 1, if run == 0 { "" } else { "s" }, shrinks, if shrinks == 1 { "" } else { "s" },
+            ),
+            differences,
+            r_actual: r_args,
+            r_expected: None,
+        });
+    }
+    Ok(Value::Null)
+}
@@ -0,0 +1,66 @@
+use std::{
+    rc::{Rc, Weak},
+    cell::RefCell,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Obj,
+    Value,
+    method,
+};
+
+/// The `new` member of a class, which makes instances of it.
+///
+/// A class is a map of its methods. Its instances are maps too, which refer to the class apart from their items, and
+/// members that an instance lacks are looked for in its class. Making an instance calls the `new` method that the class declared,
+/// if any, with the instance as `self`. The class is held weakly, since it holds its constructor.
+pub(crate) struct Constructor {
+    name: String,
+    class: Weak<RefCell<Map>>,
+    init: Option<Value>,
+}
+
+impl Constructor {
+    pub(crate) fn new(name: String, class: &Rc<RefCell<Map>>, init: Option<Value>) -> Self {
+        Self { name, class: Rc::downgrade(class), init }
+    }
+}
+
+impl Obj for Constructor {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<constructor of '{}'>", self.name))
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let class = self.class.upgrade().ok_or_else(|| ExecError::At(r_caller, Box::new(ExecError::Custom(format!(
+            "The class '{}' has been freed, so no more instances of it can be made.",
+            self.name,
+        )))))?;
+        let instance = Value::new_map(Map::with_class(class));
+        match &self.init {
+            Some(init) => { init.call_method(Some(instance.clone()), args, vec![], ctx, src, r_caller, r_args)?; },
+            None => method::check_arg_num(&args, 0, 0, r_args)?,
+        }
+        Ok(instance)
+    }
+}
+
+/// The class of an instance, if it is one.
+pub(crate) fn class_of(val: &Value) -> Option<Rc<RefCell<Map>>> {
+    match val {
+        Value::Map(m) => m.borrow().class().cloned(),
+        _ => None,
+    }
+}
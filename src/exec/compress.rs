@@ -0,0 +1,288 @@
+use std::{
+    rc::Rc,
+    ffi::CStr,
+    os::raw::c_char,
+};
+#[cfg(feature = "gzip")]
+use std::os::raw::c_int;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::{BuiltinFn, bytes_arg, bytes_value, number_arg},
+    method,
+};
+
+pub(super) const COMPRESS: &[(&str, BuiltinFn)] = &[
+    #[cfg(feature = "gzip")]
+    ("gzip", compress_gzip),
+    #[cfg(feature = "gzip")]
+    ("gunzip", compress_gunzip),
+    #[cfg(feature = "zstd")]
+    ("zstd", compress_zstd),
+    #[cfg(feature = "zstd")]
+    ("unzstd", compress_unzstd),
+];
+
+// Decompressed output is produced in pieces of this size
+const CHUNK: usize = 64 * 1024;
+
+// Text is compressed as its UTF-8 encoding
+fn input_arg(val: &Value, r_args: SrcRef) -> ExecResult<Vec<u8>> {
+    match val {
+        Value::String(s) => Ok(s.borrow().as_bytes().to_vec()),
+        _ => bytes_arg(val, r_args),
+    }
+}
+
+fn level_arg(args: &[Value], min: i32, max: i32, default: i32, r_args: SrcRef) -> ExecResult<i32> {
+    match args.get(1) {
+        Some(level) => {
+            let level = number_arg(level, r_args)?;
+            if level.fract() == 0.0 && level >= min as f64 && level <= max as f64 {
+                Ok(level as i32)
+            } else {
+                Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!(
+                    "Compression level must be a whole number from {} to {}, not {}.", min, max, level,
+                )))))
+            }
+        },
+        None => Ok(default),
+    }
+}
+
+fn error(format: &str, msg: &str) -> ExecError {
+    ExecError::Custom(format!("Invalid {} data: {}.", format, msg))
+}
+
+unsafe fn c_msg(msg: *const c_char) -> String {
+    if msg.is_null() {
+        String::from("unknown error")
+    } else {
+        CStr::from_ptr(msg).to_string_lossy().into_owned()
+    }
+}
+
+// The parts of the zlib C API that are used here, linked against the system library
+#[cfg(feature = "gzip")]
+mod zlib {
+    use std::os::raw::{c_char, c_int, c_uint, c_ulong, c_void};
+
+    #[repr(C)]
+    pub struct Stream {
+        pub next_in: *const u8,
+        pub avail_in: c_uint,
+        pub total_in: c_ulong,
+        pub next_out: *mut u8,
+        pub avail_out: c_uint,
+        pub total_out: c_ulong,
+        pub msg: *const c_char,
+        pub state: *mut c_void,
+        // Null allocators select zlib's defaults
+        pub zalloc: Option<extern "C" fn(*mut c_void, c_uint, c_uint) -> *mut c_void>,
+        pub zfree: Option<extern "C" fn(*mut c_void, *mut c_void)>,
+        pub opaque: *mut c_void,
+        pub data_type: c_int,
+        pub adler: c_ulong,
+        pub reserved: c_ulong,
+    }
+
+    pub const OK: c_int = 0;
+    pub const STREAM_END: c_int = 1;
+    pub const BUF_ERROR: c_int = -5;
+    pub const NO_FLUSH: c_int = 0;
+    pub const FINISH: c_int = 4;
+    pub const DEFLATED: c_int = 8;
+    // 15 bits of window, plus 16 to use a gzip header and trailer rather than a zlib one
+    pub const GZIP_WINDOW_BITS: c_int = 15 + 16;
+
+    #[link(name = "z")]
+    extern "C" {
+        pub fn zlibVersion() -> *const c_char;
+        pub fn deflateInit2_(strm: *mut Stream, level: c_int, method: c_int, window_bits: c_int, mem_level: c_int, strategy: c_int, version: *const c_char, size: c_int) -> c_int;
+        pub fn deflate(strm: *mut Stream, flush: c_int) -> c_int;
+        pub fn deflateEnd(strm: *mut Stream) -> c_int;
+        pub fn inflateInit2_(strm: *mut Stream, window_bits: c_int, version: *const c_char, size: c_int) -> c_int;
+        pub fn inflate(strm: *mut Stream, flush: c_int) -> c_int;
+        pub fn inflateEnd(strm: *mut Stream) -> c_int;
+    }
+
+    impl Stream {
+        pub fn new(input: &[u8]) -> Self {
+            Self {
+                next_in: input.as_ptr(),
+                avail_in: input.len() as c_uint,
+                total_in: 0,
+                next_out: std::ptr::null_mut(),
+                avail_out: 0,
+                total_out: 0,
+                msg: std::ptr::null(),
+                state: std::ptr::null_mut(),
+                zalloc: None,
+                zfree: None,
+                opaque: std::ptr::null_mut(),
+                data_type: 0,
+                adler: 0,
+                reserved: 0,
+            }
+        }
+    }
+}
+
+// Run a zlib stream to completion, calling `step` until it reports the end of the stream
+#[cfg(feature = "gzip")]
+fn run_zlib(strm: &mut zlib::Stream, step: impl Fn(&mut zlib::Stream) -> c_int) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    loop {
+        let mut buf = vec![0u8; CHUNK];
+        strm.next_out = buf.as_mut_ptr();
+        strm.avail_out = CHUNK as _;
+        let code = step(strm);
+        out.extend_from_slice(&buf[..CHUNK - strm.avail_out as usize]);
+        match code {
+            zlib::STREAM_END => return Ok(out),
+            // No progress is possible once all of the input has been consumed and there is still room for output
+            zlib::BUF_ERROR if strm.avail_in == 0 && strm.avail_out > 0 => return Err(String::from("unexpected end of data")),
+            zlib::OK | zlib::BUF_ERROR => {},
+            _ => return Err(unsafe { c_msg(strm.msg) }),
+        }
+    }
+}
+
+/// `compress.gzip(data, level?)` compresses a string or list of bytes in the gzip format, with a level from 0 (none)
+/// to 9 (smallest). The default is 6.
+#[cfg(feature = "gzip")]
+fn compress_gzip(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let input = input_arg(&args[0], r_args)?;
+    let level = level_arg(&args, 0, 9, 6, r_args)?;
+    let mut strm = zlib::Stream::new(&input);
+    let out = unsafe {
+        let size = std::mem::size_of::<zlib::Stream>() as c_int;
+        if zlib::deflateInit2_(&mut strm, level, zlib::DEFLATED, zlib::GZIP_WINDOW_BITS, 8, 0, zlib::zlibVersion(), size) != zlib::OK {
+            return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Could not start gzip compression.")))));
+        }
+        let out = run_zlib(&mut strm, |strm| zlib::deflate(strm, zlib::FINISH));
+        zlib::deflateEnd(&mut strm);
+        out
+    };
+    out
+        .map(|out| bytes_value(&out))
+        .map_err(|msg| ExecError::At(r_args, Box::new(ExecError::Custom(format!("Could not compress with gzip: {}.", msg)))))
+}
+
+/// `compress.gunzip(bytes)` decompresses gzip data into a list of bytes.
+#[cfg(feature = "gzip")]
+fn compress_gunzip(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let input = bytes_arg(&args[0], r_args)?;
+    let mut strm = zlib::Stream::new(&input);
+    let out = unsafe {
+        let size = std::mem::size_of::<zlib::Stream>() as c_int;
+        if zlib::inflateInit2_(&mut strm, zlib::GZIP_WINDOW_BITS, zlib::zlibVersion(), size) != zlib::OK {
+            return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Could not start gzip decompression.")))));
+        }
+        let out = run_zlib(&mut strm, |strm| zlib::inflate(strm, zlib::NO_FLUSH));
+        let out = out.and_then(|out| match strm.avail_in {
+            0 => Ok(out),
+            _ => Err(String::from("unexpected bytes after the end of the data")),
+        });
+        zlib::inflateEnd(&mut strm);
+        out
+    };
+    out
+        .map(|out| bytes_value(&out))
+        .map_err(|msg| ExecError::At(r_args, Box::new(error("gzip", &msg))))
+}
+
+// The parts of the Zstandard C API that are used here, linked against the system library
+#[cfg(feature = "zstd")]
+mod zstd {
+    use std::os::raw::{c_char, c_int, c_uint, c_void};
+
+    pub enum DCtx {}
+
+    #[repr(C)]
+    pub struct InBuffer {
+        pub src: *const c_void,
+        pub size: usize,
+        pub pos: usize,
+    }
+
+    #[repr(C)]
+    pub struct OutBuffer {
+        pub dst: *mut c_void,
+        pub size: usize,
+        pub pos: usize,
+    }
+
+    #[link(name = "zstd")]
+    extern "C" {
+        pub fn ZSTD_compressBound(src_size: usize) -> usize;
+        pub fn ZSTD_compress(dst: *mut c_void, dst_cap: usize, src: *const c_void, src_size: usize, level: c_int) -> usize;
+        pub fn ZSTD_createDCtx() -> *mut DCtx;
+        pub fn ZSTD_freeDCtx(dctx: *mut DCtx) -> usize;
+        pub fn ZSTD_decompressStream(dctx: *mut DCtx, output: *mut OutBuffer, input: *mut InBuffer) -> usize;
+        pub fn ZSTD_isError(code: usize) -> c_uint;
+        pub fn ZSTD_getErrorName(code: usize) -> *const c_char;
+    }
+}
+
+#[cfg(feature = "zstd")]
+fn zstd_error(code: usize) -> Option<String> {
+    unsafe {
+        if zstd::ZSTD_isError(code) != 0 {
+            Some(c_msg(zstd::ZSTD_getErrorName(code)).to_lowercase())
+        } else {
+            None
+        }
+    }
+}
+
+/// `compress.zstd(data, level?)` compresses a string or list of bytes in the Zstandard format, with a level from 1 to
+/// 22 (smallest). The default is 3.
+#[cfg(feature = "zstd")]
+fn compress_zstd(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let input = input_arg(&args[0], r_args)?;
+    let level = level_arg(&args, 1, 22, 3, r_args)?;
+    let mut out = vec![0u8; unsafe { zstd::ZSTD_compressBound(input.len()) }];
+    let len = unsafe { zstd::ZSTD_compress(out.as_mut_ptr() as _, out.len(), input.as_ptr() as _, input.len(), level) };
+    if let Some(msg) = zstd_error(len) {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("Could not compress with Zstandard: {}.", msg)))));
+    }
+    Ok(bytes_value(&out[..len]))
+}
+
+/// `compress.unzstd(bytes)` decompresses Zstandard data, which may hold several frames, into a list of bytes.
+#[cfg(feature = "zstd")]
+fn compress_unzstd(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let input = bytes_arg(&args[0], r_args)?;
+    let dctx = unsafe { zstd::ZSTD_createDCtx() };
+    let mut in_buf = zstd::InBuffer { src: input.as_ptr() as _, size: input.len(), pos: 0 };
+    let mut out = Vec::new();
+    let res = loop {
+        let mut buf = vec![0u8; CHUNK];
+        let mut out_buf = zstd::OutBuffer { dst: buf.as_mut_ptr() as _, size: CHUNK, pos: 0 };
+        // Zero once a frame has been decoded and flushed completely
+        let remaining = unsafe { zstd::ZSTD_decompressStream(dctx, &mut out_buf, &mut in_buf) };
+        if let Some(msg) = zstd_error(remaining) {
+            break Err(msg);
+        }
+        out.extend_from_slice(&buf[..out_buf.pos]);
+        if in_buf.pos == in_buf.size {
+            if remaining == 0 {
+                break Ok(out);
+            } else if out_buf.pos < out_buf.size {
+                break Err(String::from("unexpected end of data"));
+            }
+        }
+    };
+    unsafe { zstd::ZSTD_freeDCtx(dctx); }
+    res
+        .map(|out| bytes_value(&out))
+        .map_err(|msg| ExecError::At(r_args, Box::new(error("Zstandard", &msg))))
+}
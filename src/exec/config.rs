@@ -0,0 +1,404 @@
+use std::{
+    env,
+    fs,
+    rc::Rc,
+    path::Path,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Permissions,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+    truncate_preview,
+};
+
+pub(super) const CONFIG: &[(&str, BuiltinFn)] = &[
+    ("load_env", config_load_env),
+    ("load", config_load),
+];
+
+const TYPES: &[&str] = &["string", "number", "bool", "list", "any"];
+
+/// A setting read from a configuration file. Settings within tables have a path of several keys.
+#[derive(Clone)]
+struct Setting {
+    path: Vec<String>,
+    val: Value,
+    line: usize,
+}
+
+impl Setting {
+    fn name(&self) -> String {
+        self.path.join(".")
+    }
+}
+
+fn read_file(permissions: &Permissions, path: &str) -> ExecResult<String> {
+    fs::read_to_string(permissions.resolve_path(Path::new(path))?).map_err(|err| ExecError::Custom(format!("Could not read '{}': {}.", path, err)))
+}
+
+// Files named like `.env` or `app.env` are read as dotenv files, and anything else as TOML
+fn is_dotenv(path: &str) -> bool {
+    let name = Path::new(path).file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+    name.starts_with(".env") || name.ends_with(".env")
+}
+
+/// Read the variables in a dotenv file into the environment of the process, returning them as a map. Variables that
+/// are already set are left as they are, so that the real environment takes precedence over the file.
+fn config_load_env(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 1, r_args)?;
+    ctx.permissions.require_fs_read()
+        .and_then(|_| ctx.permissions.require_env())
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let path = match args.first() {
+        Some(path) => string_arg(path, r_args)?,
+        None => String::from(".env"),
+    };
+
+    let settings = read_file(&ctx.permissions, &path)
+        .and_then(|text| parse_dotenv(&text).map_err(|(line, msg)| parse_error(&path, line, msg)))
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    for setting in &settings {
+        if env::var_os(&setting.path[0]).is_none() {
+            env::set_var(&setting.path[0], setting.val.as_string().unwrap_or_default());
+        }
+    }
+    Ok(to_map(&settings))
+}
+
+/// Read a TOML (or dotenv) file. Given a schema, a map of keys to types (or to maps, for tables), only the settings in
+/// the schema are returned, converted to their types. Settings missing from the file are reported together, unless
+/// their type ends with `?`, in which case they are null.
+fn config_load(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    ctx.permissions.require_fs_read().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let path = string_arg(&args[0], r_args)?;
+
+    let text = read_file(&ctx.permissions, &path).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let settings = if is_dotenv(&path) { parse_dotenv(&text) } else { parse_toml(&text) }
+        .map_err(|(line, msg)| ExecError::At(r_args, Box::new(parse_error(&path, line, msg))))?;
+
+    match args.get(1) {
+        Some(schema) => {
+            let mut missing = Vec::new();
+            let config = apply_schema(schema, &[], &settings, &path, &mut missing)
+                .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+            missing.sort();
+            if missing.is_empty() {
+                Ok(config)
+            } else {
+                Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is missing {} {}.",
+                    path,
+                    if missing.len() == 1 { "the setting" } else { "the settings" },
+                    missing.iter().map(|name| format!("'{}'", name)).collect::<Vec<_>>().join(", "),
+                )))))
+            }
+        },
+        None => Ok(to_map(&settings)),
+    }
+}
+
+fn parse_error(path: &str, line: usize, msg: String) -> ExecError {
+    ExecError::Custom(format!("Could not parse line {} of '{}': {}.", line, path, msg))
+}
+
+/// Build nested maps from settings, with a map for each table.
+fn to_map(settings: &[Setting]) -> Value {
+    let mut map = Map::new();
+    let mut tables: Vec<&str> = Vec::new();
+    for setting in settings {
+        if setting.path.len() == 1 {
+            map.insert(Value::from(setting.path[0].clone()), setting.val.clone());
+        } else if !tables.contains(&setting.path[0].as_str()) {
+            tables.push(&setting.path[0]);
+        }
+    }
+    for table in tables {
+        let inner = settings
+            .iter()
+            .filter(|setting| setting.path.len() > 1 && setting.path[0] == table)
+            .map(|setting| Setting { path: setting.path[1..].to_vec(), ..setting.clone() })
+            .collect::<Vec<_>>();
+        map.insert(Value::from(table), to_map(&inner));
+    }
+    Value::from(map)
+}
+
+fn apply_schema(schema: &Value, prefix: &[String], settings: &[Setting], path: &str, missing: &mut Vec<String>) -> ExecResult<Value> {
+    // The settings are given in the order of the schema
+    let schema = match schema {
+        Value::Map(m) => m.borrow().clone(),
+        schema => return Err(ExecError::WrongArgType(String::from("map"), schema.get_type_name())),
+    };
+    let mut config = Map::new();
+    for (key, ty) in schema {
+        let key_path = prefix.iter().cloned().chain(Some(key.get_display_text()?)).collect::<Vec<_>>();
+        let val = if let Value::Map(_) = ty {
+            apply_schema(&ty, &key_path, settings, path, missing)?
+        } else {
+            let ty = ty.as_string().ok_or_else(|| ExecError::WrongArgType(String::from("string"), ty.get_type_name()))?;
+            let (ty, optional) = match ty.ends_with('?') {
+                true => (&ty[..ty.len() - 1], true),
+                false => (&ty[..], false),
+            };
+            if !TYPES.contains(&ty) {
+                return Err(ExecError::Custom(format!("'{}' is not a setting type. Expected one of {}.",
+                    ty,
+                    TYPES.iter().map(|ty| format!("'{}'", ty)).collect::<Vec<_>>().join(", "),
+                )));
+            }
+            match settings.iter().rev().find(|setting| setting.path == key_path) {
+                Some(setting) => coerce(&setting.val, ty).ok_or_else(|| ExecError::Custom(format!(
+                    "The setting '{}' on line {} of '{}' should be of type '{}', but is {} of type '{}'.",
+                    setting.name(),
+                    setting.line,
+                    path,
+                    ty,
+                    truncate_preview(setting.val.get_display_text().unwrap_or_default()),
+                    setting.val.get_type_name(),
+                )))?,
+                None if optional => Value::Null,
+                None => {
+                    missing.push(key_path.join("."));
+                    Value::Null
+                },
+            }
+        };
+        config.insert(key, val);
+    }
+    Ok(Value::from(config))
+}
+
+// Numbers without a fractional part or exponent are ints
+fn parse_number(s: &str) -> Option<Value> {
+    if s.is_empty() {
+        None
+    } else if let Ok(x) = s.parse::<i64>() {
+        Some(Value::Int(x))
+    } else {
+        s.parse::<f64>().ok().map(Value::Float)
+    }
+}
+
+// Settings are converted to the type the schema asks for where this is unambiguous, since dotenv files (and
+// carelessly written TOML files) only contain strings
+fn coerce(val: &Value, ty: &str) -> Option<Value> {
+    match (ty, val) {
+        ("any", val) => Some(val.clone()),
+        ("string", Value::String(_)) => Some(val.clone()),
+        ("string", Value::Int(_)) | ("string", Value::Float(_)) | ("string", Value::Boolean(_)) => val.get_display_text().ok().map(Value::from),
+        ("number", Value::Int(_)) | ("number", Value::Float(_)) => Some(val.clone()),
+        ("number", Value::String(s)) => parse_number(s.borrow().trim()),
+        ("bool", Value::Boolean(_)) => Some(val.clone()),
+        ("bool", Value::String(s)) => match s.borrow().trim().to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(Value::Boolean(true)),
+            "false" | "no" | "off" | "0" => Some(Value::Boolean(false)),
+            _ => None,
+        },
+        ("list", Value::List(_)) => Some(val.clone()),
+        ("list", Value::String(s)) => Some(Value::from(s
+            .borrow()
+            .split(',')
+            .map(|item| item.trim())
+            .filter(|item| !item.is_empty())
+            .map(Value::from)
+            .collect::<Vec<_>>())),
+        _ => None,
+    }
+}
+
+type ConfigResult<T> = Result<T, (usize, String)>;
+
+/// Parse lines of `KEY=value`, optionally preceded by `export`. Values may be quoted, with escapes being decoded
+/// within double quotes. Unquoted values end at a `#` comment.
+fn parse_dotenv(text: &str) -> ConfigResult<Vec<Setting>> {
+    let mut settings = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_num = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_start_matches("export ").trim_start();
+        let eq = line.find('=').ok_or((line_num, String::from("expected 'KEY=value'")))?;
+        let key = line[..eq].trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '.') {
+            return Err((line_num, format!("'{}' is not a valid variable name", key)));
+        }
+        let raw = line[eq + 1..].trim();
+        let val = if raw.starts_with('"') || raw.starts_with('\'') {
+            let (val, rest) = parse_string(raw).map_err(|msg| (line_num, msg))?;
+            expect_end(rest).map_err(|msg| (line_num, msg))?;
+            val
+        } else {
+            Value::from(raw.splitn(2, " #").next().unwrap_or("").trim())
+        };
+        settings.push(Setting { path: vec![key.to_string()], val, line: line_num });
+    }
+    Ok(settings)
+}
+
+/// Parse the commonly used subset of TOML: tables, dotted keys, strings, numbers, booleans and arrays of these
+/// (which may span several lines). Inline tables, arrays of tables and dates are not supported.
+fn parse_toml(text: &str) -> ConfigResult<Vec<Setting>> {
+    let mut settings = Vec::new();
+    let mut table = Vec::new();
+    let mut lines = text.lines().enumerate();
+    while let Some((i, line)) = lines.next() {
+        let line_num = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with("[[") {
+            return Err((line_num, String::from("arrays of tables are not supported")));
+        } else if line.starts_with('[') {
+            let end = line.find(']').ok_or((line_num, String::from("expected ']' to close the table name")))?;
+            expect_end(&line[end + 1..]).map_err(|msg| (line_num, msg))?;
+            table = parse_key(&line[1..end]).map_err(|msg| (line_num, msg))?;
+            continue;
+        }
+
+        let eq = line.find('=').ok_or((line_num, String::from("expected 'key = value'")))?;
+        let path = table.iter().cloned().chain(parse_key(&line[..eq]).map_err(|msg| (line_num, msg))?).collect::<Vec<_>>();
+        // Arrays continue onto the following lines until they are closed
+        let mut raw = line[eq + 1..].to_string();
+        let val = loop {
+            match parse_value(&raw) {
+                Ok((val, rest)) => {
+                    expect_end(rest).map_err(|msg| (line_num, msg))?;
+                    break val;
+                },
+                Err(ValueError::Unterminated) => match lines.next() {
+                    Some((_, next)) if raw.trim_start().starts_with('[') => {
+                        raw.push('\n');
+                        raw.push_str(next);
+                    },
+                    _ => return Err((line_num, String::from("the value is not closed"))),
+                },
+                Err(ValueError::Invalid(msg)) => return Err((line_num, msg)),
+            }
+        };
+        if settings.iter().any(|setting: &Setting| setting.path == path) {
+            return Err((line_num, format!("'{}' is set more than once", path.join("."))));
+        }
+        settings.push(Setting { path, val, line: line_num });
+    }
+    Ok(settings)
+}
+
+enum ValueError {
+    // The value ended before it was closed, so may continue on the next line
+    Unterminated,
+    Invalid(String),
+}
+
+fn parse_key(s: &str) -> Result<Vec<String>, String> {
+    s.split('.')
+        .map(|part| part.trim())
+        .map(|part| {
+            let part = part.trim_matches('"');
+            if !part.is_empty() && part.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
+                Ok(part.to_string())
+            } else {
+                Err(format!("'{}' is not a valid key", s.trim()))
+            }
+        })
+        .collect()
+}
+
+// Only whitespace and comments may follow a value
+fn expect_end(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected '{}' after the value", rest))
+    }
+}
+
+fn skip_space(mut s: &str) -> &str {
+    loop {
+        s = s.trim_start();
+        if s.starts_with('#') {
+            s = s.find('\n').map(|i| &s[i..]).unwrap_or("");
+        } else {
+            return s;
+        }
+    }
+}
+
+fn parse_string(s: &str) -> Result<(Value, &str), String> {
+    let quote = s.chars().next().unwrap_or('"');
+    let mut val = String::new();
+    let mut chars = s.char_indices().skip(1);
+    while let Some((i, c)) = chars.next() {
+        match c {
+            c if c == quote => return Ok((Value::from(val), &s[i + 1..])),
+            '\\' if quote == '"' => match chars.next().map(|(_, c)| c) {
+                Some('n') => val.push('\n'),
+                Some('t') => val.push('\t'),
+                Some('r') => val.push('\r'),
+                Some('"') => val.push('"'),
+                Some('\\') => val.push('\\'),
+                Some(c) => return Err(format!("invalid escape sequence '\\{}'", c)),
+                None => break,
+            },
+            '\n' => break,
+            c => val.push(c),
+        }
+    }
+    Err(String::from("the string is not closed"))
+}
+
+fn parse_value(s: &str) -> Result<(Value, &str), ValueError> {
+    let s = skip_space(s);
+    if s.starts_with('"') || s.starts_with('\'') {
+        parse_string(s).map_err(ValueError::Invalid)
+    } else if s.starts_with('[') {
+        let mut items = Vec::new();
+        let mut rest = skip_space(&s[1..]);
+        loop {
+            if rest.is_empty() {
+                return Err(ValueError::Unterminated);
+            } else if rest.starts_with(']') {
+                return Ok((Value::from(items), &rest[1..]));
+            }
+            let (item, after) = parse_value(rest)?;
+            items.push(item);
+            rest = skip_space(after);
+            if rest.starts_with(',') {
+                rest = skip_space(&rest[1..]);
+            } else if !rest.starts_with(']') && !rest.is_empty() {
+                return Err(ValueError::Invalid(String::from("expected ',' or ']' in the array")));
+            }
+        }
+    } else if s.starts_with("true") {
+        Ok((Value::Boolean(true), &s[4..]))
+    } else if s.starts_with("false") {
+        Ok((Value::Boolean(false), &s[5..]))
+    } else {
+        let end = s.find(|c: char| !(c.is_alphanumeric() || "+-._".contains(c))).unwrap_or(s.len());
+        let num = s[..end].replace('_', "");
+        let val = match num.get(0..2) {
+            Some("0x") => i64::from_str_radix(&num[2..], 16).ok().map(Value::Int),
+            Some("0o") => i64::from_str_radix(&num[2..], 8).ok().map(Value::Int),
+            Some("0b") => i64::from_str_radix(&num[2..], 2).ok().map(Value::Int),
+            _ => parse_number(&num),
+        };
+        match val {
+            Some(val) => Ok((val, &s[end..])),
+            None => match s.chars().next() {
+                None => Err(ValueError::Invalid(String::from("expected a value"))),
+                Some(c) if end == 0 => Err(ValueError::Invalid(format!("unexpected '{}'", c))),
+                Some(_) => Err(ValueError::Invalid(format!("'{}' is not a valid value", &s[..end]))),
+            },
+        }
+    }
+}
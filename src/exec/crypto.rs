@@ -0,0 +1,553 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::BuiltinFn,
+    method,
+};
+
+pub(super) const CRYPTO: &[(&str, BuiltinFn)] = &[
+    ("sha256", crypto_sha256),
+    ("hmac_sha256", crypto_hmac_sha256),
+    ("verify_ed25519", crypto_verify_ed25519),
+    ("equal", crypto_equal),
+];
+
+fn wrong_type(val: &Value, r_args: SrcRef) -> ExecError {
+    ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string or list of bytes"), val.get_type_name())))
+}
+
+fn list_bytes(val: &Value, r_args: SrcRef) -> ExecResult<Vec<u8>> {
+    match val {
+        Value::List(l) => l
+            .borrow()
+            .iter()
+            .map(|b| match b {
+                Value::Int(x) if *x >= 0 && *x <= 255 => Ok(*x as u8),
+                _ => Err(wrong_type(val, r_args)),
+            })
+            .collect(),
+        _ => Err(wrong_type(val, r_args)),
+    }
+}
+
+// Messages and HMAC keys that are strings are used as their UTF-8 encoding
+fn data_arg(val: &Value, r_args: SrcRef) -> ExecResult<Vec<u8>> {
+    match val {
+        Value::String(s) => Ok(s.borrow().as_bytes().to_vec()),
+        _ => list_bytes(val, r_args),
+    }
+}
+
+// Public keys and signatures are always binary, so strings are read as hexadecimal
+fn binary_arg(val: &Value, len: usize, what: &str, r_args: SrcRef) -> ExecResult<Vec<u8>> {
+    let bytes = match val {
+        Value::String(s) => {
+            let s = s.borrow();
+            let digits = s.chars().map(|c| c.to_digit(16)).collect::<Option<Vec<_>>>();
+            match digits {
+                Some(digits) if digits.len() % 2 == 0 => digits.chunks(2).map(|d| (d[0] * 16 + d[1]) as u8).collect(),
+                _ => return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("The {} is not valid hexadecimal.", what))))),
+            }
+        },
+        _ => list_bytes(val, r_args)?,
+    };
+    if bytes.len() == len {
+        Ok(bytes)
+    } else {
+        Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("The {} must be {} bytes long, not {}.", what, len, bytes.len())))))
+    }
+}
+
+fn hex(bytes: &[u8]) -> Value {
+    Value::from(bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+}
+
+/// `crypto.sha256(data)` produces the SHA-256 digest of a string or list of bytes, as lowercase hexadecimal.
+fn crypto_sha256(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    Ok(hex(&sha256(&data_arg(&args[0], r_args)?)))
+}
+
+/// `crypto.hmac_sha256(key, data)` produces the HMAC-SHA256 of `data` (RFC 2104), as lowercase hexadecimal. This is
+/// the signature that most webhook providers send.
+fn crypto_hmac_sha256(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let key = data_arg(&args[0], r_args)?;
+    let data = data_arg(&args[1], r_args)?;
+    Ok(hex(&hmac_sha256(&key, &data)))
+}
+
+/// `crypto.verify_ed25519(pub, sig, data)` checks that `sig` is an Ed25519 signature of `data` (RFC 8032) made with
+/// the private key of `pub`. Keys and signatures may be lists of bytes or hexadecimal strings.
+fn crypto_verify_ed25519(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 3, 3, r_args)?;
+    let public = binary_arg(&args[0], 32, "public key", r_args)?;
+    let sig = binary_arg(&args[1], 64, "signature", r_args)?;
+    let data = data_arg(&args[2], r_args)?;
+    Ok(Value::Boolean(ed25519::verify(&public, &sig, &data)))
+}
+
+/// `crypto.equal(a, b)` compares two strings or lists of bytes in an amount of time that does not depend on where
+/// they differ, so comparing a secret (such as an expected signature) does not reveal how much of it was guessed.
+fn crypto_equal(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let a = data_arg(&args[0], r_args)?;
+    let b = data_arg(&args[1], r_args)?;
+    Ok(Value::Boolean(a.len() == b.len() && a.iter().zip(&b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0))
+}
+
+// Pad a message into whole blocks, ending with its length in bits
+fn pad(data: &[u8], block: usize) -> Vec<u8> {
+    let len_bytes = block / 8;
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % block != block - len_bytes {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u128 * 8).to_be_bytes()[16 - len_bytes..]);
+    msg
+}
+
+const K256: [u32; 64] = [
+    0x428a_2f98, 0x7137_4491, 0xb5c0_fbcf, 0xe9b5_dba5, 0x3956_c25b, 0x59f1_11f1, 0x923f_82a4, 0xab1c_5ed5,
+    0xd807_aa98, 0x1283_5b01, 0x2431_85be, 0x550c_7dc3, 0x72be_5d74, 0x80de_b1fe, 0x9bdc_06a7, 0xc19b_f174,
+    0xe49b_69c1, 0xefbe_4786, 0x0fc1_9dc6, 0x240c_a1cc, 0x2de9_2c6f, 0x4a74_84aa, 0x5cb0_a9dc, 0x76f9_88da,
+    0x983e_5152, 0xa831_c66d, 0xb003_27c8, 0xbf59_7fc7, 0xc6e0_0bf3, 0xd5a7_9147, 0x06ca_6351, 0x1429_2967,
+    0x27b7_0a85, 0x2e1b_2138, 0x4d2c_6dfc, 0x5338_0d13, 0x650a_7354, 0x766a_0abb, 0x81c2_c92e, 0x9272_2c85,
+    0xa2bf_e8a1, 0xa81a_664b, 0xc24b_8b70, 0xc76c_51a3, 0xd192_e819, 0xd699_0624, 0xf40e_3585, 0x106a_a070,
+    0x19a4_c116, 0x1e37_6c08, 0x2748_774c, 0x34b0_bcb5, 0x391c_0cb3, 0x4ed8_aa4a, 0x5b9c_ca4f, 0x682e_6ff3,
+    0x748f_82ee, 0x78a5_636f, 0x84c8_7814, 0x8cc7_0208, 0x90be_fffa, 0xa450_6ceb, 0xbef9_a3f7, 0xc671_78f2,
+];
+
+fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09_e667, 0xbb67_ae85, 0x3c6e_f372, 0xa54f_f53a, 0x510e_527f, 0x9b05_688c, 0x1f83_d9ab, 0x5be0_cd19,
+    ];
+    for block in pad(data, 64).chunks(64) {
+        let mut w = [0u32; 64];
+        for i in 0..64 {
+            w[i] = if i < 16 {
+                u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]])
+            } else {
+                let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+                let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+                w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1)
+            };
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K256[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (h, x) in h.iter_mut().zip(&[a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(*x);
+        }
+    }
+    let mut out = [0; 32];
+    for (i, h) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> [u8; 32] {
+    // Keys longer than a block are hashed first
+    let mut block = [0u8; 64];
+    if key.len() > 64 {
+        block[..32].copy_from_slice(&sha256(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = block.iter().map(|b| b ^ 0x36).collect::<Vec<_>>();
+    inner.extend_from_slice(data);
+    let mut outer = block.iter().map(|b| b ^ 0x5c).collect::<Vec<_>>();
+    outer.extend_from_slice(&sha256(&inner));
+    sha256(&outer)
+}
+
+const K512: [u64; 80] = [
+    0x428a_2f98_d728_ae22, 0x7137_4491_23ef_65cd, 0xb5c0_fbcf_ec4d_3b2f, 0xe9b5_dba5_8189_dbbc,
+    0x3956_c25b_f348_b538, 0x59f1_11f1_b605_d019, 0x923f_82a4_af19_4f9b, 0xab1c_5ed5_da6d_8118,
+    0xd807_aa98_a303_0242, 0x1283_5b01_4570_6fbe, 0x2431_85be_4ee4_b28c, 0x550c_7dc3_d5ff_b4e2,
+    0x72be_5d74_f27b_896f, 0x80de_b1fe_3b16_96b1, 0x9bdc_06a7_25c7_1235, 0xc19b_f174_cf69_2694,
+    0xe49b_69c1_9ef1_4ad2, 0xefbe_4786_384f_25e3, 0x0fc1_9dc6_8b8c_d5b5, 0x240c_a1cc_77ac_9c65,
+    0x2de9_2c6f_592b_0275, 0x4a74_84aa_6ea6_e483, 0x5cb0_a9dc_bd41_fbd4, 0x76f9_88da_8311_53b5,
+    0x983e_5152_ee66_dfab, 0xa831_c66d_2db4_3210, 0xb003_27c8_98fb_213f, 0xbf59_7fc7_beef_0ee4,
+    0xc6e0_0bf3_3da8_8fc2, 0xd5a7_9147_930a_a725, 0x06ca_6351_e003_826f, 0x1429_2967_0a0e_6e70,
+    0x27b7_0a85_46d2_2ffc, 0x2e1b_2138_5c26_c926, 0x4d2c_6dfc_5ac4_2aed, 0x5338_0d13_9d95_b3df,
+    0x650a_7354_8baf_63de, 0x766a_0abb_3c77_b2a8, 0x81c2_c92e_47ed_aee6, 0x9272_2c85_1482_353b,
+    0xa2bf_e8a1_4cf1_0364, 0xa81a_664b_bc42_3001, 0xc24b_8b70_d0f8_9791, 0xc76c_51a3_0654_be30,
+    0xd192_e819_d6ef_5218, 0xd699_0624_5565_a910, 0xf40e_3585_5771_202a, 0x106a_a070_32bb_d1b8,
+    0x19a4_c116_b8d2_d0c8, 0x1e37_6c08_5141_ab53, 0x2748_774c_df8e_eb99, 0x34b0_bcb5_e19b_48a8,
+    0x391c_0cb3_c5c9_5a63, 0x4ed8_aa4a_e341_8acb, 0x5b9c_ca4f_7763_e373, 0x682e_6ff3_d6b2_b8a3,
+    0x748f_82ee_5def_b2fc, 0x78a5_636f_4317_2f60, 0x84c8_7814_a1f0_ab72, 0x8cc7_0208_1a64_39ec,
+    0x90be_fffa_2363_1e28, 0xa450_6ceb_de82_bde9, 0xbef9_a3f7_b2c6_7915, 0xc671_78f2_e372_532b,
+    0xca27_3ece_ea26_619c, 0xd186_b8c7_21c0_c207, 0xeada_7dd6_cde0_eb1e, 0xf57d_4f7f_ee6e_d178,
+    0x06f0_67aa_7217_6fba, 0x0a63_7dc5_a2c8_98a6, 0x113f_9804_bef9_0dae, 0x1b71_0b35_131c_471b,
+    0x28db_77f5_2304_7d84, 0x32ca_ab7b_40c7_2493, 0x3c9e_be0a_15c9_bebc, 0x431d_67c4_9c10_0d4c,
+    0x4cc5_d4be_cb3e_42b6, 0x597f_299c_fc65_7e2a, 0x5fcb_6fab_3ad6_faec, 0x6c44_198c_4a47_5817,
+];
+
+// Ed25519 hashes with SHA-512
+fn sha512(data: &[u8]) -> [u8; 64] {
+    let mut h: [u64; 8] = [
+        0x6a09_e667_f3bc_c908, 0xbb67_ae85_84ca_a73b, 0x3c6e_f372_fe94_f82b, 0xa54f_f53a_5f1d_36f1,
+        0x510e_527f_ade6_82d1, 0x9b05_688c_2b3e_6c1f, 0x1f83_d9ab_fb41_bd6b, 0x5be0_cd19_137e_2179,
+    ];
+    for block in pad(data, 128).chunks(128) {
+        let mut w = [0u64; 80];
+        for i in 0..80 {
+            w[i] = if i < 16 {
+                let mut word = [0; 8];
+                word.copy_from_slice(&block[i * 8..i * 8 + 8]);
+                u64::from_be_bytes(word)
+            } else {
+                let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+                let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+                w[i - 16].wrapping_add(s0).wrapping_add(w[i - 7]).wrapping_add(s1)
+            };
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for i in 0..80 {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh.wrapping_add(s1).wrapping_add(ch).wrapping_add(K512[i]).wrapping_add(w[i]);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            hh = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+        for (h, x) in h.iter_mut().zip(&[a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(*x);
+        }
+    }
+    let mut out = [0; 64];
+    for (i, h) in h.iter().enumerate() {
+        out[i * 8..i * 8 + 8].copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
+
+/// Ed25519 signature verification, following the reference implementation in TweetNaCl. Only public values are
+/// involved, so none of this needs to run in constant time.
+mod ed25519 {
+    use super::sha512;
+
+    // An element of the field of integers modulo 2^255 - 19, as sixteen 16-bit limbs
+    type Gf = [i64; 16];
+    // A point on the curve in extended coordinates (X, Y, Z, T)
+    type Point = [Gf; 4];
+
+    const GF0: Gf = [0; 16];
+    const GF1: Gf = [1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    // The curve constant d = -121665 / 121666
+    const D: Gf = [
+        0x78a3, 0x1359, 0x4dca, 0x75eb, 0xd8ab, 0x4141, 0x0a4d, 0x0070,
+        0xe898, 0x7779, 0x4079, 0x8cc7, 0xfe73, 0x2b6f, 0x6cee, 0x5203,
+    ];
+    const D2: Gf = [
+        0xf159, 0x26b2, 0x9b94, 0xebd6, 0xb156, 0x8283, 0x149a, 0x00e0,
+        0xd130, 0xeef3, 0x80f2, 0x198e, 0xfce7, 0x56df, 0xd9dc, 0x2406,
+    ];
+    // The base point
+    const X: Gf = [
+        0xd51a, 0x8f25, 0x2d60, 0xc956, 0xa7b2, 0x9525, 0xc760, 0x692c,
+        0xdc5c, 0xfdd6, 0xe231, 0xc0a4, 0x53fe, 0xcd6e, 0x36d3, 0x2169,
+    ];
+    const Y: Gf = [
+        0x6658, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+        0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666, 0x6666,
+    ];
+    // A square root of -1
+    const I: Gf = [
+        0xa0b0, 0x4a0e, 0x1b27, 0xc4ee, 0xe478, 0xad2f, 0x1806, 0x2f43,
+        0xd7a7, 0x3dfb, 0x0099, 0x2b4d, 0xdf0b, 0x4fc1, 0x2480, 0x2b83,
+    ];
+    // The order of the base point, little-endian
+    const L: [i64; 32] = [
+        0xed, 0xd3, 0xf5, 0x5c, 0x1a, 0x63, 0x12, 0x58, 0xd6, 0x9c, 0xf7, 0xa2, 0xde, 0xf9, 0xde, 0x14,
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x10,
+    ];
+
+    fn carry(o: &mut Gf) {
+        for i in 0..16 {
+            o[i] += 1 << 16;
+            let c = o[i] >> 16;
+            if i < 15 {
+                o[i + 1] += c - 1;
+            } else {
+                // 2^256 is 38 modulo the field's prime
+                o[0] += 38 * (c - 1);
+            }
+            o[i] -= c << 16;
+        }
+    }
+
+    fn add(a: &Gf, b: &Gf) -> Gf {
+        let mut o = GF0;
+        for i in 0..16 {
+            o[i] = a[i] + b[i];
+        }
+        o
+    }
+
+    fn sub(a: &Gf, b: &Gf) -> Gf {
+        let mut o = GF0;
+        for i in 0..16 {
+            o[i] = a[i] - b[i];
+        }
+        o
+    }
+
+    fn mul(a: &Gf, b: &Gf) -> Gf {
+        let mut t = [0i64; 31];
+        for i in 0..16 {
+            for j in 0..16 {
+                t[i + j] += a[i] * b[j];
+            }
+        }
+        for i in 0..15 {
+            t[i] += 38 * t[i + 16];
+        }
+        let mut o = GF0;
+        o.copy_from_slice(&t[..16]);
+        carry(&mut o);
+        carry(&mut o);
+        o
+    }
+
+    fn square(a: &Gf) -> Gf {
+        mul(a, a)
+    }
+
+    // a^(p - 2), the inverse of a
+    fn invert(a: &Gf) -> Gf {
+        let mut c = *a;
+        for i in (0..=253).rev() {
+            c = square(&c);
+            if i != 2 && i != 4 {
+                c = mul(&c, a);
+            }
+        }
+        c
+    }
+
+    // a^((p - 5) / 8), used to find square roots
+    fn pow2523(a: &Gf) -> Gf {
+        let mut c = *a;
+        for i in (0..=250).rev() {
+            c = square(&c);
+            if i != 1 {
+                c = mul(&c, a);
+            }
+        }
+        c
+    }
+
+    fn pack_gf(n: &Gf) -> [u8; 32] {
+        let mut t = *n;
+        carry(&mut t);
+        carry(&mut t);
+        carry(&mut t);
+        // Subtract the prime (twice, as the value may still be up to twice it) unless doing so would underflow
+        for _ in 0..2 {
+            let mut m = GF0;
+            m[0] = t[0] - 0xffed;
+            for i in 1..15 {
+                m[i] = t[i] - 0xffff - ((m[i - 1] >> 16) & 1);
+                m[i - 1] &= 0xffff;
+            }
+            m[15] = t[15] - 0x7fff - ((m[14] >> 16) & 1);
+            let underflow = (m[15] >> 16) & 1;
+            m[14] &= 0xffff;
+            if underflow == 0 {
+                t = m;
+            }
+        }
+        let mut o = [0; 32];
+        for i in 0..16 {
+            o[2 * i] = t[i] as u8;
+            o[2 * i + 1] = (t[i] >> 8) as u8;
+        }
+        o
+    }
+
+    fn unpack_gf(n: &[u8]) -> Gf {
+        let mut o = GF0;
+        for i in 0..16 {
+            o[i] = n[2 * i] as i64 + ((n[2 * i + 1] as i64) << 8);
+        }
+        o[15] &= 0x7fff;
+        o
+    }
+
+    fn neq(a: &Gf, b: &Gf) -> bool {
+        pack_gf(a) != pack_gf(b)
+    }
+
+    fn parity(a: &Gf) -> u8 {
+        pack_gf(a)[0] & 1
+    }
+
+    fn point_add(p: &mut Point, q: &Point) {
+        let a = mul(&sub(&p[1], &p[0]), &sub(&q[1], &q[0]));
+        let b = mul(&add(&p[0], &p[1]), &add(&q[0], &q[1]));
+        let c = mul(&mul(&p[3], &q[3]), &D2);
+        let d = mul(&p[2], &q[2]);
+        let d = add(&d, &d);
+        let e = sub(&b, &a);
+        let f = sub(&d, &c);
+        let g = add(&d, &c);
+        let h = add(&b, &a);
+        p[0] = mul(&e, &f);
+        p[1] = mul(&h, &g);
+        p[2] = mul(&g, &f);
+        p[3] = mul(&e, &h);
+    }
+
+    fn pack_point(p: &Point) -> [u8; 32] {
+        let zi = invert(&p[2]);
+        let x = mul(&p[0], &zi);
+        let y = mul(&p[1], &zi);
+        let mut o = pack_gf(&y);
+        o[31] ^= parity(&x) << 7;
+        o
+    }
+
+    // The scalar s is little-endian
+    fn scalar_mul(q: &Point, s: &[u8]) -> Point {
+        let mut p = [GF0, GF1, GF1, GF0];
+        let mut q = *q;
+        for i in (0..256).rev() {
+            let bit = (s[i / 8] >> (i & 7)) & 1 == 1;
+            if bit {
+                std::mem::swap(&mut p, &mut q);
+            }
+            point_add(&mut q, &p);
+            let double = p;
+            point_add(&mut p, &double);
+            if bit {
+                std::mem::swap(&mut p, &mut q);
+            }
+        }
+        p
+    }
+
+    fn scalar_base(s: &[u8]) -> Point {
+        scalar_mul(&[X, Y, GF1, mul(&X, &Y)], s)
+    }
+
+    // Decode a point and negate it, or return `None` if the bytes do not encode a point on the curve
+    fn unpack_neg(p: &[u8]) -> Option<Point> {
+        let y = unpack_gf(p);
+        let num = square(&y);
+        let den = mul(&num, &D);
+        let num = sub(&num, &GF1);
+        let den = add(&GF1, &den);
+
+        let den2 = square(&den);
+        let den4 = square(&den2);
+        let den6 = mul(&den4, &den2);
+        let t = mul(&mul(&den6, &num), &den);
+        let t = mul(&mul(&pow2523(&t), &num), &den);
+        let mut x = mul(&mul(&t, &den), &den);
+
+        if neq(&mul(&square(&x), &den), &num) {
+            x = mul(&x, &I);
+        }
+        if neq(&mul(&square(&x), &den), &num) {
+            return None;
+        }
+        if parity(&x) == p[31] >> 7 {
+            x = sub(&GF0, &x);
+        }
+        let t = mul(&x, &y);
+        Some([x, y, GF1, t])
+    }
+
+    // Reduce a 512-bit little-endian number modulo L
+    fn reduce(r: &[u8; 64]) -> [u8; 32] {
+        let mut x = [0i64; 64];
+        for i in 0..64 {
+            x[i] = r[i] as i64;
+        }
+        for i in (32..64).rev() {
+            let mut c = 0;
+            for j in i - 32..i - 12 {
+                x[j] += c - 16 * x[i] * L[j - (i - 32)];
+                c = (x[j] + 128) >> 8;
+                x[j] -= c << 8;
+            }
+            x[i - 12] += c;
+            x[i] = 0;
+        }
+        let mut c = 0;
+        for j in 0..32 {
+            x[j] += c - (x[31] >> 4) * L[j];
+            c = x[j] >> 8;
+            x[j] &= 255;
+        }
+        for j in 0..32 {
+            x[j] -= c * L[j];
+        }
+        let mut o = [0; 32];
+        for i in 0..32 {
+            x[i + 1] += x[i] >> 8;
+            o[i] = (x[i] & 255) as u8;
+        }
+        o
+    }
+
+    // Whether a little-endian scalar is less than L, as RFC 8032 requires of signatures so that they are not malleable
+    fn is_canonical(s: &[u8]) -> bool {
+        for i in (0..32).rev() {
+            if (s[i] as i64) != L[i] {
+                return (s[i] as i64) < L[i];
+            }
+        }
+        false
+    }
+
+    pub(super) fn verify(public: &[u8], sig: &[u8], data: &[u8]) -> bool {
+        let (r, s) = sig.split_at(32);
+        if !is_canonical(s) {
+            return false;
+        }
+        let neg_a = match unpack_neg(public) {
+            Some(neg_a) => neg_a,
+            None => return false,
+        };
+        let mut hashed = r.to_vec();
+        hashed.extend_from_slice(public);
+        hashed.extend_from_slice(data);
+        let k = reduce(&sha512(&hashed));
+
+        // The signature is valid if [s]B - [k]A is R
+        let mut p = scalar_mul(&neg_a, &k);
+        point_add(&mut p, &scalar_base(s));
+        pack_point(&p)[..] == *r
+    }
+}
@@ -0,0 +1,390 @@
+use std::{
+    env,
+    rc::Rc,
+    path::{Component, Path, PathBuf},
+    time::Instant,
+};
+use crate::{
+    compile::FnCache,
+    parser::SrcRef,
+};
+use super::{
+    DateTime,
+    Duration,
+    ExecError,
+    ExecResult,
+    InterruptHandle,
+    Io,
+    ModuleLoader,
+    Rng,
+    Value,
+    expect::Snapshots,
+    replay::Replay,
+    gc,
+    mock::Mocks,
+    Resource,
+    Resources,
+    TailCall,
+};
+
+/// How ints are written when displayed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IntFormat {
+    /// `1234567`
+    Plain,
+    /// `1,234,567`
+    Grouped,
+    /// `0x12d687`
+    Hex,
+}
+
+/// Controls how values are turned into text by `print` and the REPL.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DisplayOptions {
+    /// The number of decimal places to show for floats (`None` shows as many as needed).
+    pub float_precision: Option<usize>,
+    /// The format used for ints.
+    pub int_format: IntFormat,
+    /// The maximum number of items shown for lists and maps before the rest are elided.
+    pub max_list_len: Option<usize>,
+    /// The maximum nesting depth of lists and maps shown before inner containers are elided.
+    pub max_depth: Option<usize>,
+}
+
+impl Default for DisplayOptions {
+    fn default() -> Self {
+        Self {
+            float_precision: None,
+            int_format: IntFormat::Plain,
+            max_list_len: None,
+            max_depth: Some(32),
+        }
+    }
+}
+
+impl DisplayOptions {
+    pub fn fmt_int(&self, x: i64) -> String {
+        let (sign, n) = (if x < 0 { "-" } else { "" }, x.unsigned_abs());
+        match self.int_format {
+            IntFormat::Plain => format!("{}", x),
+            IntFormat::Grouped => {
+                let digits = n.to_string();
+                let mut s = String::from(sign);
+                for (i, c) in digits.chars().enumerate() {
+                    if i != 0 && (digits.len() - i) % 3 == 0 {
+                        s.push(',');
+                    }
+                    s.push(c);
+                }
+                s
+            },
+            IntFormat::Hex => format!("{}0x{:x}", sign, n),
+        }
+    }
+
+    /// Floats always show a decimal point, so that they can be told apart from ints.
+    pub fn fmt_float(&self, x: f64) -> String {
+        match self.float_precision {
+            Some(prec) => format!("{:.*}", prec, x),
+            None if x.fract() == 0.0 && x.is_finite() => format!("{:.1}", x),
+            None => format!("{}", x),
+        }
+    }
+
+    // The bounds and steps of float ranges are shown as ints when they are integral
+    pub fn fmt_number(&self, x: f64) -> String {
+        if x.fract() == 0.0 && x.is_finite() && x.abs() < 1e18 {
+            self.fmt_int(x as i64)
+        } else {
+            self.fmt_float(x)
+        }
+    }
+}
+
+/// Capabilities that must be granted to an engine before the code it runs may affect the world outside of it. All
+/// of them are denied by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Permissions {
+    /// Reading files and listing directories.
+    pub fs_read: bool,
+    /// Creating, modifying and removing files and directories.
+    pub fs_write: bool,
+    /// Reading and changing environment variables and the working directory of the process.
+    pub env: bool,
+    /// Connecting to other machines over the network.
+    pub net: bool,
+    /// Reading the time from the host's clock, whether the date and time of day or how long the engine has been
+    /// running.
+    pub clock: bool,
+    /// The directory that files must be within, if they are confined to one. Relative paths are taken to be relative
+    /// to it rather than to the working directory of the process.
+    pub fs_root: Option<PathBuf>,
+}
+
+impl Permissions {
+    pub fn all() -> Self {
+        Self {
+            fs_read: true,
+            fs_write: true,
+            env: true,
+            net: true,
+            clock: true,
+            fs_root: None,
+        }
+    }
+
+    pub(crate) fn require(&self, granted: bool, name: &'static str) -> ExecResult<()> {
+        if granted {
+            Ok(())
+        } else {
+            Err(ExecError::PermissionDenied(name))
+        }
+    }
+
+    pub(crate) fn require_fs_read(&self) -> ExecResult<()> {
+        self.require(self.fs_read, "fs_read")
+    }
+
+    pub(crate) fn require_fs_write(&self) -> ExecResult<()> {
+        self.require(self.fs_write, "fs_write")
+    }
+
+    pub(crate) fn require_env(&self) -> ExecResult<()> {
+        self.require(self.env, "env")
+    }
+
+    /// Where a path given by the code refers to on the host. When files are confined to a directory, paths that lead
+    /// out of it (whether with `..`, or through a symbolic link) are refused.
+    pub(crate) fn resolve_path(&self, path: &Path) -> ExecResult<PathBuf> {
+        let root = match &self.fs_root {
+            Some(root) => normalize(&env::current_dir().map_err(ExecError::Io)?.join(root)),
+            None => return Ok(path.to_path_buf()),
+        };
+        let resolved = normalize(&root.join(path));
+        let outside = || ExecError::Custom(format!(
+            "'{}' is outside of the directory that files are confined to.",
+            path.display(),
+        ));
+        if !resolved.starts_with(&root) {
+            return Err(outside());
+        }
+
+        // Only the part of the path that exists can be followed through links
+        let mut existing = resolved.as_path();
+        while !existing.exists() {
+            existing = match existing.parent() {
+                Some(parent) => parent,
+                None => return Ok(resolved),
+            };
+        }
+        match (existing.canonicalize(), root.canonicalize()) {
+            (Ok(real), Ok(real_root)) if !real.starts_with(&real_root) => Err(outside()),
+            _ => Ok(resolved),
+        }
+    }
+
+    pub(crate) fn require_clock(&self) -> ExecResult<()> {
+        self.require(self.clock, "clock")
+    }
+
+    #[cfg(feature = "net")]
+    pub(crate) fn require_net(&self) -> ExecResult<()> {
+        self.require(self.net, "net")
+    }
+}
+
+// Remove the `.` and `..` components of an absolute path, without looking at the filesystem
+fn normalize(path: &Path) -> PathBuf {
+    let mut normal = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {},
+            Component::ParentDir => { normal.pop(); },
+            component => normal.push(component),
+        }
+    }
+    normal
+}
+
+/// The number of function calls that may be underway at once, unless the engine is configured otherwise.
+///
+/// This is only a ceiling. Each call also uses the host's stack, and calls fail as nested too deeply once too little of
+/// it is left: after a few hundred calls on an 8 MiB stack in a release build, and a few dozen in a debug build. Only
+/// engines running on threads with much larger stacks reach this limit. Tail calls count towards neither.
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 10_000;
+
+/// State shared by all code executing within an engine.
+pub struct ExecCtx {
+    pub(crate) io: Box<dyn Io>,
+    pub(crate) display: DisplayOptions,
+    pub(crate) modules: ModuleLoader,
+    pub(crate) rng: Rng,
+    pub(crate) permissions: Permissions,
+    pub(crate) interrupt: InterruptHandle,
+    // The function registered with `on_interrupt`, if any
+    pub(crate) on_interrupt: Option<Value>,
+    // The number of containers allocated between automatic cycle collections (`None` disables them)
+    pub(crate) gc_threshold: Option<usize>,
+    // Where `expect_snapshot` keeps its snapshots (`None` disables it)
+    pub(crate) snapshots: Option<Snapshots>,
+    // The functions replaced by `mock` or watched by `spy` (`None` disables both)
+    pub(crate) mocks: Option<Mocks>,
+    // The resources opened by the code, which are closed when the engine is dropped
+    pub(crate) resources: Resources,
+    // Whether `return f(x);` may leave the call to the caller of the function it is in, which it may not outside of
+    // functions or within `try` and `with` statements, since they have something left to do once the call returns
+    pub(crate) tail_calls: bool,
+    // The call that a function left to its caller, until the caller makes it
+    pub(crate) tail_call: Option<TailCall>,
+    // The number of function calls that are underway, not counting those left to callers as tail calls
+    pub(crate) call_depth: usize,
+    // The number of calls that may be underway at once (`None` leaves only the stack to limit them)
+    pub(crate) max_call_depth: Option<usize>,
+    // The bytecode of the functions that have been called, when functions are run as bytecode (`None` when they are
+    // interpreted)
+    pub(crate) compiled_fns: Option<FnCache>,
+    // How many more expressions (or instructions) may be run before the code fails (`None` for no limit)
+    pub(crate) fuel: Option<u64>,
+    // The recording that the code's inputs are kept in or taken from, if any
+    pub(crate) replay: Option<Replay>,
+    // When the engine was made, which `time.elapsed()` counts from
+    pub(crate) started: Instant,
+}
+
+impl ExecCtx {
+    pub fn new(io: Box<dyn Io>, display: DisplayOptions) -> Self {
+        Self {
+            io,
+            display,
+            modules: ModuleLoader::default(),
+            rng: Rng::from_entropy(),
+            permissions: Permissions::default(),
+            interrupt: InterruptHandle::default(),
+            on_interrupt: None,
+            gc_threshold: Some(gc::DEFAULT_THRESHOLD),
+            snapshots: None,
+            mocks: None,
+            resources: Resources::default(),
+            tail_calls: false,
+            tail_call: None,
+            call_depth: 0,
+            max_call_depth: Some(DEFAULT_MAX_CALL_DEPTH),
+            compiled_fns: None,
+            fuel: None,
+            replay: None,
+            started: Instant::now(),
+        }
+    }
+
+    /// Produce a value for a newly opened resource, which will be closed when the engine is dropped if it has not been
+    /// closed by then.
+    pub fn open_resource<T: Resource>(&mut self, res: T) -> Value {
+        self.resources.open(res)
+    }
+
+    pub fn io(&mut self) -> &mut dyn Io {
+        &mut *self.io
+    }
+
+    pub fn display(&self) -> &DisplayOptions {
+        &self.display
+    }
+
+    pub fn modules(&self) -> &ModuleLoader {
+        &self.modules
+    }
+
+    pub fn rng(&mut self) -> &mut Rng {
+        &mut self.rng
+    }
+
+    pub fn permissions(&self) -> &Permissions {
+        &self.permissions
+    }
+
+    pub fn interrupt_handle(&self) -> &InterruptHandle {
+        &self.interrupt
+    }
+
+    /// Read a line of input, as `input` does, from the recording when replaying one.
+    pub(crate) fn input(&mut self, prompt: String) -> ExecResult<String> {
+        let io = &mut self.io;
+        match &mut self.replay {
+            Some(replay) => replay.input(|| io.input(prompt)),
+            None => io.input(prompt),
+        }
+    }
+
+    /// Read a line of input without showing what is typed, from the recording when replaying one.
+    pub(crate) fn input_hidden(&mut self, prompt: String) -> ExecResult<String> {
+        let io = &mut self.io;
+        match &mut self.replay {
+            Some(replay) => replay.input(|| io.input_hidden(prompt)),
+            None => io.input_hidden(prompt),
+        }
+    }
+
+    /// The current time, or the time that the code read at the same point of the recording when replaying one.
+    pub(crate) fn now(&mut self) -> ExecResult<DateTime> {
+        self.permissions.require_clock()?;
+        match &mut self.replay {
+            Some(replay) => replay.time(|| DateTime::now().unix_nanos()).map(DateTime::from_unix_nanos),
+            None => Ok(DateTime::now()),
+        }
+    }
+
+    /// How long the engine has been running, measured by a clock that never goes backwards, or the time that the code
+    /// read at the same point of the recording when replaying one.
+    pub(crate) fn elapsed(&mut self) -> ExecResult<Duration> {
+        self.permissions.require_clock()?;
+        let started = self.started;
+        let elapsed = || started.elapsed().as_nanos() as i128;
+        match &mut self.replay {
+            Some(replay) => replay.time(elapsed).map(Duration::from_nanos),
+            None => Ok(Duration::from_nanos(elapsed())),
+        }
+    }
+
+    /// Use up a unit of fuel, failing once there is none left. Code that has run out cannot even handle the error,
+    /// since handling it would take more fuel.
+    #[inline(always)]
+    pub(crate) fn burn_fuel(&mut self) -> ExecResult<()> {
+        match &mut self.fuel {
+            Some(0) => Err(ExecError::OutOfFuel),
+            Some(fuel) => {
+                *fuel -= 1;
+                Ok(())
+            },
+            None => Ok(()),
+        }
+    }
+
+    /// Respond to a pending interrupt, if there is one, by calling the script's interrupt handler or failing.
+    ///
+    /// This is called at every loop iteration and function call, which is also when cycles are collected automatically.
+    pub(crate) fn poll_interrupt(&mut self, src: &Rc<String>, r: SrcRef) -> ExecResult<()> {
+        // Loops with empty bodies evaluate nothing else, so they must use fuel here to be stopped by it
+        self.burn_fuel().map_err(|err| ExecError::WithSrc(src.clone(), Box::new(ExecError::At(r, Box::new(err)))))?;
+        gc::maybe_collect(self.gc_threshold);
+        if !self.interrupt.is_pending() || !self.interrupt.take() {
+            return Ok(());
+        }
+        // The handler is unregistered while it runs, so a second interrupt stops it instead of calling it again
+        match self.on_interrupt.take() {
+            Some(handler) => {
+                let res = handler.call(Vec::new(), self, src, r, r);
+                if self.on_interrupt.is_none() {
+                    self.on_interrupt = Some(handler);
+                }
+                res.map(|_| ())
+            },
+            None => Err(ExecError::Interrupted),
+        }
+    }
+}
+
+impl Drop for ExecCtx {
+    fn drop(&mut self) {
+        self.resources.close_all();
+    }
+}
@@ -0,0 +1,268 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, number_arg, string_arg},
+    method,
+};
+
+pub(super) const DIFF: &[(&str, BuiltinFn)] = &[
+    ("lines", diff_lines),
+    ("apply", diff_apply),
+];
+
+const DEFAULT_CONTEXT: usize = 3;
+
+#[derive(Copy, Clone, PartialEq)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// The shortest edit script that turns `a` into `b`, found with the linear space variant of Myers' algorithm.
+fn edit_script(a: &[&str], b: &[&str], ops: &mut Vec<Op>) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[prefix..], &b[prefix..]);
+    let suffix = a.iter().rev().zip(b.iter().rev()).take_while(|(x, y)| x == y).count();
+    let (a, b) = (&a[..a.len() - suffix], &b[..b.len() - suffix]);
+
+    ops.extend((0..prefix).map(|_| Op::Equal));
+    if a.is_empty() || b.is_empty() {
+        ops.extend((0..a.len()).map(|_| Op::Delete));
+        ops.extend((0..b.len()).map(|_| Op::Insert));
+    } else {
+        let (x, y) = middle_snake(a, b);
+        edit_script(&a[..x], &b[..y], ops);
+        edit_script(&a[x..], &b[y..], ops);
+    }
+    ops.extend((0..suffix).map(|_| Op::Equal));
+}
+
+/// A point on a shortest edit path, found where searches from either end meet.
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = (n + m + 1) / 2;
+    let off = max_d;
+    let delta = n - m;
+    // If the difference in length is odd, the forward search will be the one to meet the reverse search
+    let front = delta % 2 != 0;
+    let mut v1 = vec![-1isize; 2 * max_d as usize + 2];
+    let mut v2 = v1.clone();
+    v1[off as usize + 1] = 0;
+    v2[off as usize + 1] = 0;
+    // Diagonals that have run off the edge of the grid are trimmed from later rounds
+    let (mut k1_start, mut k1_end, mut k2_start, mut k2_end) = (0, 0, 0, 0);
+
+    for d in 0..max_d {
+        for k1 in (-d + k1_start..=d - k1_end).step_by(2) {
+            let k1_off = (off + k1) as usize;
+            let mut x1 = if k1 == -d || (k1 != d && v1[k1_off - 1] < v1[k1_off + 1]) {
+                v1[k1_off + 1]
+            } else {
+                v1[k1_off - 1] + 1
+            };
+            let mut y1 = x1 - k1;
+            while x1 < n && y1 < m && a[x1 as usize] == b[y1 as usize] {
+                x1 += 1;
+                y1 += 1;
+            }
+            v1[k1_off] = x1;
+            if x1 > n {
+                k1_end += 2;
+            } else if y1 > m {
+                k1_start += 2;
+            } else if front {
+                let k2_off = off + delta - k1;
+                if k2_off >= 0 && (k2_off as usize) < v2.len() && v2[k2_off as usize] != -1 && x1 >= n - v2[k2_off as usize] {
+                    return (x1 as usize, y1 as usize);
+                }
+            }
+        }
+
+        for k2 in (-d + k2_start..=d - k2_end).step_by(2) {
+            let k2_off = (off + k2) as usize;
+            let mut x2 = if k2 == -d || (k2 != d && v2[k2_off - 1] < v2[k2_off + 1]) {
+                v2[k2_off + 1]
+            } else {
+                v2[k2_off - 1] + 1
+            };
+            let mut y2 = x2 - k2;
+            while x2 < n && y2 < m && a[(n - x2 - 1) as usize] == b[(m - y2 - 1) as usize] {
+                x2 += 1;
+                y2 += 1;
+            }
+            v2[k2_off] = x2;
+            if x2 > n {
+                k2_end += 2;
+            } else if y2 > m {
+                k2_start += 2;
+            } else if !front {
+                let k1_off = off + delta - k2;
+                if k1_off >= 0 && (k1_off as usize) < v1.len() && v1[k1_off as usize] != -1 {
+                    let x1 = v1[k1_off as usize];
+                    let y1 = off + x1 - k1_off;
+                    if x1 >= n - x2 {
+                        return (x1 as usize, y1 as usize);
+                    }
+                }
+            }
+        }
+    }
+
+    // Only reachable if the texts have nothing in common, in which case any split will do
+    (a.len(), 0)
+}
+
+/// The lines that differ between two texts, in order, as lines of `a` prefixed with `-` and lines of `b` prefixed with
+/// `+`.
+pub(crate) fn changed_lines(a: &str, b: &str) -> Vec<String> {
+    let (a, b) = (a.split('\n').collect::<Vec<_>>(), b.split('\n').collect::<Vec<_>>());
+    let mut ops = Vec::new();
+    edit_script(&a, &b, &mut ops);
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    for op in ops {
+        match op {
+            Op::Equal => {
+                i += 1;
+                j += 1;
+            },
+            Op::Delete => {
+                lines.push(format!("-{}", a[i]));
+                i += 1;
+            },
+            Op::Insert => {
+                lines.push(format!("+{}", b[j]));
+                j += 1;
+            },
+        }
+    }
+    lines
+}
+
+fn diff_lines(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 3, r_args)?;
+    let a = string_arg(&args[0], r_args)?;
+    let b = string_arg(&args[1], r_args)?;
+    let context = match args.get(2) {
+        Some(context) => match number_arg(context, r_args)? {
+            context if context >= 0.0 && context.fract() == 0.0 => context as usize,
+            context => return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("Context must be a whole number of lines, not {}.", context))))),
+        },
+        None => DEFAULT_CONTEXT,
+    };
+    let (a, b) = (a.split('\n').collect::<Vec<_>>(), b.split('\n').collect::<Vec<_>>());
+    let mut ops = Vec::new();
+    edit_script(&a, &b, &mut ops);
+
+    // Changes that are separated by no more than twice the context share a hunk
+    let changes = ops.iter().enumerate().filter(|(_, op)| **op != Op::Equal).map(|(i, _)| i).collect::<Vec<_>>();
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for i in changes {
+        match groups.last_mut() {
+            Some((_, last)) if i - *last - 1 <= 2 * context => *last = i,
+            _ => groups.push((i, i)),
+        }
+    }
+
+    // The position in each text that every operation starts at
+    let mut positions = Vec::with_capacity(ops.len() + 1);
+    let (mut old, mut new) = (0, 0);
+    for op in &ops {
+        positions.push((old, new));
+        match op {
+            Op::Equal => { old += 1; new += 1; },
+            Op::Delete => old += 1,
+            Op::Insert => new += 1,
+        }
+    }
+    positions.push((old, new));
+
+    let hunks = groups
+        .into_iter()
+        .map(|(first, last)| {
+            let start = first.saturating_sub(context);
+            let end = (last + 1 + context).min(ops.len());
+            let lines = (start..end)
+                .map(|i| {
+                    let (old, new) = positions[i];
+                    Value::from(match ops[i] {
+                        Op::Equal => format!(" {}", a[old]),
+                        Op::Delete => format!("-{}", a[old]),
+                        Op::Insert => format!("+{}", b[new]),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut hunk = Map::new();
+            hunk.insert(Value::from("old_start"), Value::from(positions[start].0 + 1));
+            hunk.insert(Value::from("old_count"), Value::from(positions[end].0 - positions[start].0));
+            hunk.insert(Value::from("new_start"), Value::from(positions[start].1 + 1));
+            hunk.insert(Value::from("new_count"), Value::from(positions[end].1 - positions[start].1));
+            hunk.insert(Value::from("lines"), Value::from(lines));
+            Value::from(hunk)
+        })
+        .collect::<Vec<_>>();
+    Ok(Value::from(hunks))
+}
+
+fn diff_apply(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let text = string_arg(&args[0], r_args)?;
+    let hunks = args[1]
+        .as_list()
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), args[1].get_type_name()))))?;
+    let error = |msg: String| ExecError::At(r_args, Box::new(ExecError::Custom(msg)));
+
+    let old = text.split('\n').collect::<Vec<_>>();
+    let mut new: Vec<String> = Vec::with_capacity(old.len());
+    let mut cursor = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let invalid = || error(format!("Hunk {} is not a hunk produced by 'diff.lines'.", n + 1));
+        let hunk = hunk.as_map().ok_or_else(invalid)?;
+        let start = match hunk.get(&Value::from("old_start")) {
+            Some(Value::Int(start)) if *start >= 1 => *start as usize - 1,
+            _ => return Err(invalid()),
+        };
+        let lines = hunk
+            .get(&Value::from("lines"))
+            .and_then(|lines| lines.as_list())
+            .ok_or_else(invalid)?;
+
+        if start < cursor || start > old.len() {
+            return Err(error(format!("Hunk {} does not apply at line {}.", n + 1, start + 1)));
+        }
+        new.extend(old[cursor..start].iter().map(|line| line.to_string()));
+        cursor = start;
+
+        for line in lines {
+            let line = line.as_string().ok_or_else(invalid)?;
+            let (op, line) = line.split_at(line.chars().next().map(char::len_utf8).unwrap_or(0));
+            let expect = |cursor: usize| match old.get(cursor) {
+                Some(old_line) if *old_line == line => Ok(()),
+                _ => Err(error(format!("Hunk {} does not match line {}.", n + 1, cursor + 1))),
+            };
+            match op {
+                " " => {
+                    expect(cursor)?;
+                    new.push(line.to_string());
+                    cursor += 1;
+                },
+                "-" => {
+                    expect(cursor)?;
+                    cursor += 1;
+                },
+                "+" => new.push(line.to_string()),
+                _ => return Err(invalid()),
+            }
+        }
+    }
+    new.extend(old[cursor..].iter().map(|line| line.to_string()));
+    Ok(Value::from(new.join("\n")))
+}
@@ -0,0 +1,105 @@
+use std::{
+    fmt,
+    rc::Rc,
+    cell::RefCell,
+};
+use hashbrown::HashMap;
+use super::{
+    Value,
+    gc,
+};
+
+pub(super) struct Frame {
+    pub(super) vars: RefCell<HashMap<String, Value>>,
+    pub(super) parent: Option<Env>,
+}
+
+/// A chain of variable frames, innermost first.
+///
+/// Frames are reference-counted rather than owned by the code that created them, so a function captures the frames
+/// it was created in and continues to see (and change) their variables after those scopes have ended. A function
+/// stored in a frame that it captured forms a reference cycle, which is freed by the cycle collector once nothing
+/// else refers to it.
+#[derive(Clone)]
+pub struct Env(Rc<Frame>);
+
+impl Env {
+    fn new(parent: Option<Env>) -> Self {
+        let frame = Rc::new(Frame {
+            vars: RefCell::new(HashMap::new()),
+            parent,
+        });
+        gc::track_frame(&frame);
+        Env(frame)
+    }
+
+    pub fn root() -> Self {
+        Self::new(None)
+    }
+
+    /// Create a new, empty frame within this one.
+    pub fn child(&self) -> Self {
+        Self::new(Some(self.clone()))
+    }
+
+    pub fn parent(&self) -> Option<&Env> {
+        self.0.parent.as_ref()
+    }
+
+    pub fn ptr_eq(&self, other: &Env) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    // Identifies the frame to the cycle collector
+    pub(super) fn id(&self) -> usize {
+        Rc::as_ptr(&self.0) as *const () as usize
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.0.vars.borrow().get(name) {
+            Some(val) => Some(val.clone()),
+            None => self.parent()?.get(name),
+        }
+    }
+
+    pub fn declare(&self, name: String, val: Value) {
+        self.0.vars.borrow_mut().insert(name, val);
+    }
+
+    /// Assign to the innermost variable with the given name, returning `false` if there is none.
+    pub fn assign(&self, name: &str, val: Value) -> bool {
+        match self.0.vars.borrow_mut().get_mut(name) {
+            Some(var) => {
+                *var = val;
+                return true;
+            },
+            None => {},
+        }
+        self.parent().map(|parent| parent.assign(name, val)).unwrap_or(false)
+    }
+
+    pub fn take(&self, name: &str) -> Option<Value> {
+        match self.0.vars.borrow_mut().remove(name) {
+            Some(val) => Some(val),
+            None => self.parent()?.take(name),
+        }
+    }
+
+    /// The names and values of the variables declared in this frame, excluding its parents.
+    pub fn vars(&self) -> Vec<(String, Value)> {
+        self.0.vars
+            .borrow()
+            .iter()
+            .map(|(name, val)| (name.clone(), val.clone()))
+            .collect()
+    }
+}
+
+// Frames may contain functions that capture them, so only the names of their variables are shown
+impl fmt::Debug for Env {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.vars.borrow().keys())
+            .finish()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::parser::SrcRef;
+use super::{
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+};
+
+/// The value bound to the variable of a `catch` block, describing the error that was caught.
+pub struct ErrorValue {
+    message: String,
+    r: Option<SrcRef>,
+}
+
+impl ErrorValue {
+    pub fn from_error(err: &ExecError) -> Self {
+        Self {
+            message: err.message(),
+            r: err.src_ref(),
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn src_ref(&self) -> Option<SrcRef> {
+        self.r
+    }
+}
+
+impl Obj for ErrorValue {
+    fn get_type_name(&self) -> String {
+        String::from("error")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(self.message.clone())
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let pos = self.r.and_then(|r| r.start().pos());
+        match name {
+            "message" => Ok(Value::from(self.message.clone())),
+            "line" => Ok(pos.map(|(line, _)| Value::Int(line as i64)).unwrap_or(Value::Null)),
+            "col" => Ok(pos.map(|(_, col)| Value::Int(col as i64)).unwrap_or(Value::Null)),
+            _ => Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        }
+    }
+}
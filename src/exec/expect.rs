@@ -0,0 +1,321 @@
+use std::{
+    fs,
+    path::PathBuf,
+    rc::Rc,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    builtins::string_arg,
+    diff,
+    method::{self, Method, MethodFn},
+};
+
+// Beyond this, a failed expectation only says how many more differences there are
+const MAX_DIFFERENCES: usize = 10;
+
+// Snapshots are stored as `<name>.snap` in the snapshot directory
+const SNAPSHOT_EXT: &str = "snap";
+
+/// Where `expect_snapshot` keeps its snapshots, and whether it replaces them with the values it is given.
+#[derive(Clone, Debug)]
+pub(crate) struct Snapshots {
+    pub dir: PathBuf,
+    pub update: bool,
+}
+
+/// The value passed to `expect`, which its methods check against what a test expects of it.
+#[derive(Clone)]
+pub struct Expectation {
+    actual: Value,
+    r_actual: SrcRef,
+}
+
+impl Obj for Expectation {
+    fn get_type_name(&self) -> String {
+        String::from("expectation")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(String::from("<expectation>"))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<Expectation>) = match name {
+            "to_equal" => ("to_equal", expect_to_equal),
+            "not_to_equal" => ("not_to_equal", expect_not_to_equal),
+            "to_contain" => ("to_contain", expect_to_contain),
+            "to_be_truthy" => ("to_be_truthy", expect_to_be_truthy),
+            "to_be_falsy" => ("to_be_falsy", expect_to_be_falsy),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+}
+
+pub(super) fn builtin_expect(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let actual = args.into_iter().next().unwrap_or(Value::Null);
+    Ok(Value::from(Expectation { actual, r_actual: r_args }))
+}
+
+fn preview(val: &Value) -> String {
+    val.get_preview_text().unwrap_or_else(|| format!("<{}>", val.get_type_name()))
+}
+
+// String keys that could be written as members are shown as such
+fn key_path(key: &Value) -> String {
+    match key {
+        Value::String(s) if s.borrow().chars().next().map(|c| c.is_alphabetic() || c == '_').unwrap_or(false)
+            && s.borrow().chars().all(|c| c.is_alphanumeric() || c == '_') => format!(".{}", s.borrow()),
+        key => format!("[{}]", preview(key)),
+    }
+}
+
+/// Compare two values structurally, recording where they differ. Lists and maps are compared by their contents rather
+/// than by identity.
+///
+/// `parents` holds the pairs of containers currently being compared, so that self-referential values do not recurse
+/// forever.
+fn compare(path: &str, expected: &Value, actual: &Value, parents: &mut Vec<(*const (), *const ())>, differences: &mut Vec<String>) {
+    let at = |path: &str| if path.is_empty() { String::new() } else { format!("At {}: ", path) };
+    let addrs = match (expected, actual) {
+        (Value::List(x), Value::List(y)) => Some((x.as_ptr() as *const (), y.as_ptr() as *const ())),
+        (Value::Map(x), Value::Map(y)) => Some((x.as_ptr() as *const (), y.as_ptr() as *const ())),
+        _ => None,
+    };
+    if let Some(addrs) = addrs {
+        if addrs.0 == addrs.1 || parents.contains(&addrs) {
+            return;
+        }
+        parents.push(addrs);
+    }
+
+    match (expected.as_list(), actual.as_list(), expected.as_map(), actual.as_map()) {
+        (Some(expected), Some(actual), _, _) => {
+            for i in 0..expected.len().max(actual.len()) {
+                let path = format!("{}[{}]", path, i);
+                match (expected.get(i), actual.get(i)) {
+                    (Some(x), Some(y)) => compare(&path, x, y, parents, differences),
+                    (Some(x), None) => differences.push(format!("{}expected {}, but the list ends.", at(&path), preview(x))),
+                    (None, Some(y)) => differences.push(format!("{}found {}, which was not expected.", at(&path), preview(y))),
+                    (None, None) => {},
+                }
+            }
+        },
+        (_, _, Some(expected), Some(actual)) => {
+            // Keys are visited in a fixed order so that the differences are always listed the same way
+            let mut keys = expected.keys().chain(actual.keys().filter(|key| !expected.contains_key(key))).collect::<Vec<_>>();
+            keys.sort_by_cached_key(|key| preview(key));
+            for key in keys {
+                let path = format!("{}{}", path, key_path(key));
+                match (expected.get(key), actual.get(key)) {
+                    (Some(x), Some(y)) => compare(&path, x, y, parents, differences),
+                    (Some(x), None) => differences.push(format!("{}expected {}, but the key is missing.", at(&path), preview(x))),
+                    (None, Some(y)) => differences.push(format!("{}found {}, which was not expected.", at(&path), preview(y))),
+                    (None, None) => {},
+                }
+            }
+        },
+        _ if expected != actual => differences.push(format!("{}expected {}, found {}.", at(path), preview(expected), preview(actual))),
+        _ => {},
+    }
+
+    if addrs.is_some() {
+        parents.pop();
+    }
+}
+
+fn differences(expected: &Value, actual: &Value) -> Vec<String> {
+    let mut differences = Vec::new();
+    compare("", expected, actual, &mut Vec::new(), &mut differences);
+    differences
+}
+
+fn truncate_differences(mut differences: Vec<String>) -> Vec<String> {
+    if differences.len() > MAX_DIFFERENCES {
+        let more = differences.len() - MAX_DIFFERENCES;
+        differences.truncate(MAX_DIFFERENCES);
+        differences.push(format!("...and {} more.", more));
+    }
+    differences
+}
+
+/// Write a value across several lines, with one item of each list or map on each line. Map entries are sorted by key so
+/// that the same value is always written the same way.
+fn pretty(val: &Value, depth: usize, parents: &mut Vec<*const ()>) -> String {
+    let addr = match val {
+        Value::List(l) => l.as_ptr() as *const (),
+        Value::Map(m) => m.as_ptr() as *const (),
+        Value::String(s) => return format!("{:?}", s.borrow()),
+        Value::Char(c) => return format!("{:?}", c),
+        val => return val.get_display_text().unwrap_or_else(|_| format!("<{}>", val.get_type_name())),
+    };
+    if parents.contains(&addr) {
+        return String::from("<cycle>");
+    }
+
+    parents.push(addr);
+    let mut items = match val {
+        Value::List(l) => l.borrow().iter().map(|item| pretty(item, depth + 1, parents)).collect::<Vec<_>>(),
+        _ => {
+            let mut entries = val
+                .as_map()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(key, val)| format!("{}: {}", pretty(&key, depth + 1, parents), pretty(&val, depth + 1, parents)))
+                .collect::<Vec<_>>();
+            entries.sort();
+            entries
+        },
+    };
+    parents.pop();
+
+    if items.is_empty() {
+        return String::from("[]");
+    }
+    let indent = "    ".repeat(depth + 1);
+    for item in &mut items {
+        *item = format!("{}{},\n", indent, item);
+    }
+    format!("[\n{}{}]", items.concat(), "    ".repeat(depth))
+}
+
+// Names become file names, so they may not contain anything that would lead outside of the snapshot directory
+fn valid_snapshot_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.')
+}
+
+/// Compare the pretty-printed form of a value against the snapshot of the given name, or record it as the snapshot when
+/// snapshots are being updated.
+pub(super) fn builtin_expect_snapshot(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let name = string_arg(&args[0], r_args)?;
+    let error = |msg: String| ExecError::At(r_args, Box::new(ExecError::Custom(msg)));
+    if !valid_snapshot_name(&name) {
+        return Err(error(format!(
+            "'{}' is not a valid snapshot name. Names may only contain letters, digits, '_', '-' and '.', and may not start with '.'.",
+            name,
+        )));
+    }
+    let snapshots = ctx.snapshots
+        .clone()
+        .ok_or_else(|| error(String::from("Snapshots are not enabled. Run the script with 'forge test' to use them.")))?;
+
+    let path = snapshots.dir.join(format!("{}.{}", name, SNAPSHOT_EXT));
+    let actual = pretty(&args[1], 0, &mut Vec::new()) + "\n";
+    let stored = fs::read_to_string(&path).ok();
+    if stored.as_ref() == Some(&actual) {
+        return Ok(Value::Null);
+    }
+
+    if snapshots.update {
+        fs::create_dir_all(&snapshots.dir)
+            .and_then(|_| fs::write(&path, &actual))
+            .map_err(|err| ExecError::At(r_args, Box::new(ExecError::Io(err))))?;
+        return Ok(Value::Null);
+    }
+
+    let (message, differences) = match stored {
+        Some(stored) => (
+            format!("The value does not match the snapshot '{}'. Update the snapshots to accept it.", name),
+            diff::changed_lines(stored.trim_end_matches('\n'), actual.trim_end_matches('\n')),
+        ),
+        None => (
+            format!("There is no snapshot named '{}' yet. Update the snapshots to record it.", name),
+            actual.trim_end_matches('\n').split('\n').map(|line| format!("+{}", line)).collect(),
+        ),
+    };
+    Err(ExecError::ExpectationFailed {
+        message,
+        differences: truncate_differences(differences),
+        r_actual: r_args,
+        r_expected: None,
+    })
+}
+
+fn expect_to_equal(this: &Expectation, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let differences = differences(&args[0], &this.actual);
+    if differences.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    Err(ExecError::ExpectationFailed {
+        message: String::from("Expected the values to be equal."),
+        differences: truncate_differences(differences),
+        r_actual: this.r_actual,
+        r_expected: Some(r_args),
+    })
+}
+
+fn expect_not_to_equal(this: &Expectation, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    if differences(&args[0], &this.actual).is_empty() {
+        Err(ExecError::ExpectationFailed {
+            message: format!("Expected the values to differ, but both are {}.", preview(&this.actual)),
+            differences: Vec::new(),
+            r_actual: this.r_actual,
+            r_expected: Some(r_args),
+        })
+    } else {
+        Ok(Value::Null)
+    }
+}
+
+fn expect_to_contain(this: &Expectation, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let item = &args[0];
+    // Lists are searched for an equal item, maps for the key, and strings for the text
+    let contains = match (&this.actual, item) {
+        (Value::List(list), item) => list.borrow().iter().any(|x| differences(item, x).is_empty()),
+        (Value::Map(map), key) => map.borrow().contains_key(key),
+        (Value::String(s), Value::String(sub)) => s.borrow().contains(sub.borrow().as_str()),
+        (Value::String(s), Value::Char(c)) => s.borrow().contains(*c),
+        (actual, _) => return Err(ExecError::At(this.r_actual, Box::new(ExecError::NotIterable(actual.get_type_name())))),
+    };
+    if contains {
+        Ok(Value::Null)
+    } else {
+        Err(ExecError::ExpectationFailed {
+            message: format!("Expected {} to contain {}.", preview(&this.actual), preview(item)),
+            differences: Vec::new(),
+            r_actual: this.r_actual,
+            r_expected: Some(r_args),
+        })
+    }
+}
+
+fn expect_to_be_truthy(this: &Expectation, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    if this.actual.is_truthy() {
+        Ok(Value::Null)
+    } else {
+        Err(ExecError::ExpectationFailed {
+            message: format!("Expected a truthy value, found {}.", preview(&this.actual)),
+            differences: Vec::new(),
+            r_actual: this.r_actual,
+            r_expected: None,
+        })
+    }
+}
+
+fn expect_to_be_falsy(this: &Expectation, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    if this.actual.is_truthy() {
+        Err(ExecError::ExpectationFailed {
+            message: format!("Expected a falsy value, found {}.", preview(&this.actual)),
+            differences: Vec::new(),
+            r_actual: this.r_actual,
+            r_expected: None,
+        })
+    } else {
+        Ok(Value::Null)
+    }
+}
@@ -0,0 +1,207 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::string_arg,
+};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+// The widest that a value may be padded to, and the most digits it may be given after the point, which keep the text
+// that a placeholder produces to a reasonable size
+const MAX_WIDTH: usize = u16::MAX as usize;
+
+// What follows the `:` of a placeholder, such as the `>8.2` of `{:>8.2}`
+struct Spec {
+    fill: char,
+    align: Option<Align>,
+    plus: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    kind: Option<char>,
+}
+
+fn align_of(c: char) -> Option<Align> {
+    match c {
+        '<' => Some(Align::Left),
+        '^' => Some(Align::Center),
+        '>' => Some(Align::Right),
+        _ => None,
+    }
+}
+
+impl Spec {
+    // `[[fill]align][+][0][width][.precision][type]`, as in Rust, with `x`, `X`, `o`, `b` and `e` as the types
+    fn parse(spec: &str) -> Result<Option<Self>, String> {
+        let chars = spec.chars().collect::<Vec<_>>();
+        let mut i = 0;
+        let mut this = Spec { fill: ' ', align: None, plus: false, zero: false, width: 0, precision: None, kind: None };
+
+        if let Some(align) = chars.get(1).copied().and_then(align_of) {
+            this.fill = chars[0];
+            this.align = Some(align);
+            i = 2;
+        } else if let Some(align) = chars.get(0).copied().and_then(align_of) {
+            this.align = Some(align);
+            i = 1;
+        }
+        if chars.get(i) == Some(&'+') {
+            this.plus = true;
+            i += 1;
+        }
+        if chars.get(i) == Some(&'0') {
+            this.zero = true;
+            i += 1;
+        }
+        let read_num = |i: &mut usize| {
+            let from = *i;
+            while chars.get(*i).map(|c| c.is_ascii_digit()).unwrap_or(false) {
+                *i += 1;
+            }
+            let digits = chars[from..*i].iter().collect::<String>();
+            match digits.parse::<usize>() {
+                Ok(n) if n <= MAX_WIDTH => Ok(Some(n)),
+                Err(_) if digits.is_empty() => Ok(None),
+                _ => Err(format!("Widths and precisions may be at most {}, found {}.", MAX_WIDTH, digits)),
+            }
+        };
+        this.width = read_num(&mut i)?.unwrap_or(0);
+        if chars.get(i) == Some(&'.') {
+            i += 1;
+            this.precision = match read_num(&mut i)? {
+                Some(precision) => Some(precision),
+                None => return Ok(None),
+            };
+        }
+        if let Some(kind) = chars.get(i).filter(|c| "xXobe".contains(**c)) {
+            this.kind = Some(*kind);
+            i += 1;
+        }
+        Ok(if i == chars.len() { Some(this) } else { None })
+    }
+
+    fn render(&self, val: &Value) -> Result<String, String> {
+        let body = match (self.kind, val) {
+            (Some('x'), Value::Int(x)) => format!("{:x}", x),
+            (Some('X'), Value::Int(x)) => format!("{:X}", x),
+            (Some('o'), Value::Int(x)) => format!("{:o}", x),
+            (Some('b'), Value::Int(x)) => format!("{:b}", x),
+            (Some('e'), val) if val.as_number().is_some() => match self.precision {
+                Some(precision) => format!("{:.*e}", precision, val.as_number().unwrap()),
+                None => format!("{:e}", val.as_number().unwrap()),
+            },
+            (Some(kind), val) => return Err(format!(
+                "Cannot format a value of type '{}' as '{}'.", val.get_type_name(), kind,
+            )),
+            // A precision makes ints into floats, so that `{:.2}` works for any number
+            (None, val) if val.as_number().is_some() && self.precision.is_some() => {
+                format!("{:.*}", self.precision.unwrap(), val.as_number().unwrap())
+            },
+            (None, val) => {
+                let text = val.get_display_text().map_err(|err| err.message())?;
+                match self.precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                }
+            },
+        };
+
+        let is_number = val.as_number().is_some();
+        let (sign, digits) = match body.strip_prefix('-') {
+            Some(digits) if is_number => ("-", digits),
+            _ if is_number && self.plus => ("+", body.as_str()),
+            _ => ("", body.as_str()),
+        };
+        let len = sign.len() + digits.chars().count();
+        let pad = self.width.saturating_sub(len);
+        let fill = |n: usize| std::iter::repeat(self.fill).take(n).collect::<String>();
+
+        Ok(match self.align {
+            // Zeros go between the sign and the digits, unless an alignment is given
+            None if self.zero && is_number => format!("{}{}{}", sign, "0".repeat(pad), digits),
+            Some(Align::Left) => format!("{}{}{}", sign, digits, fill(pad)),
+            Some(Align::Center) => format!("{}{}{}{}", fill(pad / 2), sign, digits, fill(pad - pad / 2)),
+            Some(Align::Right) => format!("{}{}{}", fill(pad), sign, digits),
+            // Numbers are aligned to the right by default, and everything else to the left
+            None if is_number => format!("{}{}{}", fill(pad), sign, digits),
+            None => format!("{}{}{}", sign, digits, fill(pad)),
+        })
+    }
+}
+
+// Fills in the placeholders of a format string, each of which uses the next argument unless given the index of one
+fn format(fmt: &str, args: &[Value]) -> Result<String, String> {
+    let mut out = String::new();
+    let mut used = vec![false; args.len()];
+    let mut next = 0;
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            },
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            },
+            '}' => return Err(String::from("Found '}' without a placeholder to close; use '}}' for a literal '}'.")),
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(String::from("Expected '}' to close the placeholder; use '{{' for a literal '{'.")),
+                    }
+                }
+                let (index, spec) = match placeholder.find(':') {
+                    Some(i) => (&placeholder[..i], Some(&placeholder[i + 1..])),
+                    None => (placeholder.as_str(), None),
+                };
+                let index = match index {
+                    "" => {
+                        next += 1;
+                        next - 1
+                    },
+                    index => index.parse::<usize>().map_err(|_| format!("Invalid placeholder '{{{}}}'.", placeholder))?,
+                };
+                let spec = Spec::parse(spec.unwrap_or(""))?
+                    .ok_or_else(|| format!("Invalid format specifier in '{{{}}}'.", placeholder))?;
+                let val = args.get(index).ok_or_else(|| format!(
+                    "The placeholder '{{{}}}' refers to argument {}, but only {} were given.", placeholder, index + 1, args.len(),
+                ))?;
+                used[index] = true;
+                out.push_str(&spec.render(val)?);
+            },
+            c => out.push(c),
+        }
+    }
+    match used.iter().position(|used| !used) {
+        Some(index) => Err(format!("Argument {} is not used by the format string.", index + 1)),
+        None => Ok(out),
+    }
+}
+
+/// `format(fmt, ...)` fills in the `{}` placeholders of a string with its other arguments, in the style of Rust's
+/// `format!`, as in `format("{:>6.2}", x)`.
+pub(super) fn builtin_format(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    let fmt = match args.first() {
+        Some(fmt) => string_arg(fmt, r_args)?,
+        None => return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from(
+            "Expected a format string.",
+        ))))),
+    };
+    format(&fmt, &args[1..])
+        .map(Value::from)
+        .map_err(|msg| ExecError::At(r_args, Box::new(ExecError::Custom(msg))))
+}
@@ -0,0 +1,332 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    ForgeIter,
+    Obj,
+    Permissions,
+    Resource,
+    Value,
+    builtins::BuiltinFn,
+    method::{self, Method, MethodFn},
+};
+
+pub(super) const FS: &[(&str, BuiltinFn)] = &[
+    ("read", fs_read),
+    ("write", fs_write),
+    ("exists", fs_exists),
+    ("open", fs_open),
+    ("list_dir", fs_list_dir),
+    ("glob", fs_glob),
+    ("walk", fs_walk),
+];
+
+enum Handle {
+    Read(BufReader<File>),
+    Write(BufWriter<File>),
+    Closed,
+}
+
+/// An open file, created with `fs.open(path, mode)`.
+///
+/// Files opened with mode `"r"` are read a line at a time, either with `read_line()` or by iterating over the file
+/// with a `for` loop. Files opened with mode `"w"` (truncating) or `"a"` (appending) are written to with `write()`.
+/// Writes are buffered until the file is closed, either explicitly with `close()`, at the end of a `with` statement, or
+/// when the last reference to it goes away.
+#[derive(Clone)]
+pub struct FileHandle {
+    path: String,
+    handle: Rc<RefCell<Handle>>,
+}
+
+impl FileHandle {
+    fn read_line(&self) -> ExecResult<Option<String>> {
+        let mut handle = self.handle.borrow_mut();
+        let reader = match &mut *handle {
+            Handle::Read(reader) => reader,
+            Handle::Write(_) => return Err(ExecError::Custom(format!("File '{}' was not opened for reading.", self.path))),
+            Handle::Closed => return Err(ExecError::Custom(format!("File '{}' has been closed.", self.path))),
+        };
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(ExecError::Io)? == 0 {
+            return Ok(None);
+        }
+        if line.ends_with('\n') {
+            line.pop();
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+        Ok(Some(line))
+    }
+}
+
+impl Obj for FileHandle {
+    fn get_type_name(&self) -> String {
+        String::from("file")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<file '{}'>", self.path))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<FileHandle>) = match name {
+            "read_line" => ("read_line", file_read_line),
+            "write" => ("write", file_write),
+            "close" => ("close", file_close),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+
+    // Iteration yields the remaining lines and stops early if the file cannot be read
+    fn eval_iter(&self, r: SrcRef) -> ExecResult<Box<dyn ForgeIter>> {
+        if let Handle::Read(_) = &*self.handle.borrow() {
+            let file = self.clone();
+            Ok(Box::new(std::iter::from_fn(move || file.read_line().ok().and_then(|line| line.map(Value::from)))))
+        } else {
+            Err(ExecError::At(r, Box::new(ExecError::NotIterable(self.get_type_name()))))
+        }
+    }
+
+    fn as_resource(&self) -> Option<&dyn Resource> {
+        Some(self)
+    }
+}
+
+impl Resource for FileHandle {
+    // The file is left open if its buffered writes cannot be flushed, so that closing it can be tried again
+    fn close(&self) -> ExecResult<()> {
+        let mut handle = self.handle.borrow_mut();
+        if let Handle::Write(writer) = &mut *handle {
+            writer.flush().map_err(ExecError::Io)?;
+        }
+        *handle = Handle::Closed;
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        matches!(&*self.handle.borrow(), Handle::Closed)
+    }
+}
+
+// The whole of a file, which must be text
+fn fs_read(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let path = path_arg(&args[0], r_args)?;
+    ctx.permissions.require_fs_read()
+        .and_then(|_| ctx.permissions.resolve_path(&path))
+        .and_then(|path| fs::read_to_string(path).map_err(ExecError::Io))
+        .map(Value::from)
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))
+}
+
+// Replaces the contents of the file (creating it if need be) with the text of the value, as `print` would show it
+fn fs_write(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let path = path_arg(&args[0], r_args)?;
+    ctx.permissions.require_fs_write()
+        .and_then(|_| args[1].get_display_text())
+        .and_then(|text| ctx.permissions.resolve_path(&path).map(|path| (path, text)))
+        .and_then(|(path, text)| fs::write(path, text).map_err(ExecError::Io))
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Null)
+}
+
+// Paths outside of the directory that files are confined to do not exist, as far as the code can tell
+fn fs_exists(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let path = path_arg(&args[0], r_args)?;
+    ctx.permissions.require_fs_read().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Boolean(ctx.permissions.resolve_path(&path).map(|path| path.exists()).unwrap_or(false)))
+}
+
+fn fs_open(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let path = path_arg(&args[0], r_args)?;
+    let mode = args[1].as_string().unwrap_or_default();
+    let host_path = ctx.permissions.resolve_path(&path).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+
+    let file = match mode.as_str() {
+        "r" => ctx.permissions.require_fs_read()
+            .and_then(|_| File::open(&host_path).map_err(ExecError::Io))
+            .map(|file| Handle::Read(BufReader::new(file))),
+        "w" | "a" => ctx.permissions.require_fs_write()
+            .and_then(|_| OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(mode == "w")
+                .append(mode == "a")
+                .open(&host_path)
+                .map_err(ExecError::Io))
+            .map(|file| Handle::Write(BufWriter::new(file))),
+        _ => Err(ExecError::Custom(String::from("File mode must be \"r\", \"w\" or \"a\"."))),
+    }.map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+
+    Ok(ctx.open_resource(FileHandle {
+        path: path.to_string_lossy().into_owned(),
+        handle: Rc::new(RefCell::new(file)),
+    }))
+}
+
+// Produces the next line without its line ending, or null at the end of the file
+fn file_read_line(file: &FileHandle, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(file.read_line().map_err(|err| ExecError::At(r_args, Box::new(err)))?.map(Value::from).unwrap_or(Value::Null))
+}
+
+fn file_write(file: &FileHandle, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let text = args[0].get_display_text().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    match &mut *file.handle.borrow_mut() {
+        Handle::Write(writer) => writer.write_all(text.as_bytes()).map_err(ExecError::Io),
+        Handle::Read(_) => Err(ExecError::Custom(format!("File '{}' was not opened for writing.", file.path))),
+        Handle::Closed => Err(ExecError::Custom(format!("File '{}' has been closed.", file.path))),
+    }.map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Null)
+}
+
+// Closing a file more than once does nothing
+fn file_close(file: &FileHandle, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    file.close().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Null)
+}
+
+fn path_arg(val: &Value, r_args: SrcRef) -> ExecResult<PathBuf> {
+    val.as_string()
+        .map(PathBuf::from)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string"), val.get_type_name()))))
+}
+
+fn path_value(path: &Path) -> Value {
+    Value::from(path.to_string_lossy().into_owned())
+}
+
+// An empty path refers to the current directory, but `read_dir` does not accept it
+fn read_dir_sorted(permissions: &Permissions, dir: &Path) -> ExecResult<Vec<fs::DirEntry>> {
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    let mut entries = fs::read_dir(permissions.resolve_path(dir)?)
+        .and_then(|entries| entries.collect::<Result<Vec<_>, _>>())
+        .map_err(ExecError::Io)?;
+    entries.sort_by_key(|entry| entry.file_name());
+    Ok(entries)
+}
+
+// Symbolic links are not followed into, so that links to a parent directory cannot cause endless recursion
+fn is_dir(entry: &fs::DirEntry) -> bool {
+    entry.file_type().map(|ty| ty.is_dir()).unwrap_or(false)
+}
+
+fn fs_list_dir(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    ctx.permissions.require_fs_read().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let dir = path_arg(&args[0], r_args)?;
+    let entries = read_dir_sorted(&ctx.permissions, &dir).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::from(entries
+        .iter()
+        .map(|entry| Value::from(entry.file_name().to_string_lossy().into_owned()))
+        .collect::<Vec<_>>()))
+}
+
+/// Match a file name against one component of a glob pattern, where `*` matches any run of characters and `?` any
+/// single character. As in shells, wildcards do not match the leading `.` of hidden files.
+fn matches_component(pattern: &[char], name: &[char]) -> bool {
+    if name.first() == Some(&'.') && pattern.first() != Some(&'.') {
+        return false;
+    }
+    fn matches(pattern: &[char], name: &[char]) -> bool {
+        match (pattern.first(), name.first()) {
+            (None, None) => true,
+            (Some('*'), _) => matches(&pattern[1..], name) || (!name.is_empty() && matches(pattern, &name[1..])),
+            (Some('?'), Some(_)) => matches(&pattern[1..], &name[1..]),
+            (Some(p), Some(n)) if p == n => matches(&pattern[1..], &name[1..]),
+            _ => false,
+        }
+    }
+    matches(pattern, name)
+}
+
+fn glob_in(permissions: &Permissions, dir: &Path, components: &[&str], found: &mut Vec<PathBuf>) -> ExecResult<()> {
+    let (component, rest) = match components.split_first() {
+        Some(split) => split,
+        None => {
+            found.push(dir.to_path_buf());
+            return Ok(());
+        },
+    };
+
+    if *component == "**" {
+        // `**` matches any number of directories, including none
+        glob_in(permissions, dir, rest, found)?;
+        for entry in read_dir_sorted(permissions, dir)? {
+            if is_dir(&entry) && !entry.file_name().to_string_lossy().starts_with('.') {
+                glob_in(permissions, &dir.join(entry.file_name()), components, found)?;
+            }
+        }
+    } else if component.contains(|c| c == '*' || c == '?') {
+        let pattern = component.chars().collect::<Vec<_>>();
+        for entry in read_dir_sorted(permissions, dir)? {
+            let name = entry.file_name().to_string_lossy().chars().collect::<Vec<_>>();
+            if matches_component(&pattern, &name) && (rest.is_empty() || is_dir(&entry)) {
+                glob_in(permissions, &dir.join(entry.file_name()), rest, found)?;
+            }
+        }
+    } else {
+        let path = dir.join(component);
+        if permissions.resolve_path(&path)?.exists() {
+            glob_in(permissions, &path, rest, found)?;
+        }
+    }
+    Ok(())
+}
+
+// `fs.glob("src/**/*.fg")` produces the sorted paths of the files and directories that match the pattern
+fn fs_glob(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    ctx.permissions.require_fs_read().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let pattern = path_arg(&args[0], r_args)?.to_string_lossy().into_owned();
+
+    let (base, pattern) = match pattern.strip_prefix('/') {
+        Some(pattern) => (PathBuf::from("/"), pattern),
+        None => (PathBuf::new(), pattern.as_str()),
+    };
+    let components = pattern.split('/').filter(|c| !c.is_empty() && *c != ".").collect::<Vec<_>>();
+
+    let mut found = Vec::new();
+    glob_in(&ctx.permissions, &base, &components, &mut found).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    found.sort();
+    found.dedup();
+    Ok(Value::from(found.iter().map(|path| path_value(path)).collect::<Vec<_>>()))
+}
+
+fn walk_in(dir: &Path, f: &Value, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<()> {
+    let entries = read_dir_sorted(&ctx.permissions, dir).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    for entry in entries {
+        let path = dir.join(entry.file_name());
+        let descend = f.call(vec![path_value(&path)], ctx, src, r_caller, r_args)?;
+        if is_dir(&entry) && !matches!(descend, Value::Boolean(false)) {
+            walk_in(&path, f, ctx, src, r_caller, r_args)?;
+        }
+    }
+    Ok(())
+}
+
+// `fs.walk(dir, fn)` calls `fn` with the path of everything beneath `dir`, depth-first and in name order. Returning
+// `false` for a directory skips its contents.
+fn fs_walk(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    ctx.permissions.require_fs_read().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let dir = path_arg(&args[0], r_args)?;
+    walk_in(&dir, &args[1], ctx, src, r_caller, r_args)?;
+    Ok(Value::Null)
+}
@@ -0,0 +1,366 @@
+use std::{
+    rc::{Rc, Weak},
+    cell::{Cell, RefCell},
+    fmt,
+    mem,
+};
+use hashbrown::{HashMap, HashSet};
+use crate::parser::SrcRef;
+use super::{
+    List,
+    Map,
+    Value,
+    env::Frame,
+};
+
+/// The number of containers allocated between automatic collections, unless the engine is configured otherwise.
+pub const DEFAULT_THRESHOLD: usize = 10_000;
+
+// A container that can hold values, and so can be part of a reference cycle
+enum Tracked {
+    List(Weak<RefCell<List>>),
+    Map(Weak<RefCell<Map>>),
+    Frame(Weak<Frame>),
+}
+
+enum Live {
+    List(Rc<RefCell<List>>),
+    Map(Rc<RefCell<Map>>),
+    Frame(Rc<Frame>),
+}
+
+impl Tracked {
+    fn upgrade(&self) -> Option<Live> {
+        match self {
+            Tracked::List(l) => l.upgrade().map(Live::List),
+            Tracked::Map(m) => m.upgrade().map(Live::Map),
+            Tracked::Frame(f) => f.upgrade().map(Live::Frame),
+        }
+    }
+}
+
+impl Live {
+    fn id(&self) -> usize {
+        match self {
+            Live::List(l) => Rc::as_ptr(l) as *const () as usize,
+            Live::Map(m) => Rc::as_ptr(m) as *const () as usize,
+            Live::Frame(f) => Rc::as_ptr(f) as *const () as usize,
+        }
+    }
+
+    fn downgrade(&self) -> Tracked {
+        match self {
+            Live::List(l) => Tracked::List(Rc::downgrade(l)),
+            Live::Map(m) => Tracked::Map(Rc::downgrade(m)),
+            Live::Frame(f) => Tracked::Frame(Rc::downgrade(f)),
+        }
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Live::List(_) => "list",
+            Live::Map(_) => "map",
+            Live::Frame(_) => "scope",
+        }
+    }
+
+    // The memory used by the container and the strings that it holds, but not by the other containers that it holds
+    fn bytes(&self) -> usize {
+        match self {
+            Live::List(l) => mem::size_of::<RefCell<List>>() + l.try_borrow().map(|l| l
+                .iter()
+                .map(|item| mem::size_of::<Value>() + string_bytes(item))
+                .sum()).unwrap_or(0),
+            Live::Map(m) => mem::size_of::<RefCell<Map>>() + m.try_borrow().map(|m| {
+                m.capacity_bytes() + m.iter().map(|(k, v)| string_bytes(k) + string_bytes(v)).sum::<usize>()
+            }).unwrap_or(0),
+            Live::Frame(frame) => mem::size_of::<Frame>() + frame.vars.try_borrow().map(|vars| {
+                vars.capacity() * mem::size_of::<(String, Value)>() + vars.iter().map(|(name, val)| name.capacity() + string_bytes(val)).sum::<usize>()
+            }).unwrap_or(0),
+        }
+    }
+
+    fn strong_count(&self) -> usize {
+        match self {
+            Live::List(l) => Rc::strong_count(l),
+            Live::Map(m) => Rc::strong_count(m),
+            Live::Frame(f) => Rc::strong_count(f),
+        }
+    }
+
+    /// Call `f` with the identity of every tracked container directly referenced by this one, returning `false` if
+    /// the contents could not be inspected.
+    fn for_each_child(&self, mut f: impl FnMut(usize)) -> bool {
+        match self {
+            Live::List(l) => match l.try_borrow() {
+                // Items shared with another list or an iterator are referenced from outside of this container
+                Ok(l) if !l.is_shared() => l.iter().for_each(|item| child_id(item, &mut f)),
+                _ => return false,
+            },
+            Live::Map(m) => match m.try_borrow() {
+                Ok(m) => {
+                    m.iter().for_each(|(k, v)| {
+                        child_id(k, &mut f);
+                        child_id(v, &mut f);
+                    });
+                    if let Some(default) = m.default_fn() {
+                        child_id(default, &mut f);
+                    }
+                    if let Some(class) = m.class() {
+                        f(Rc::as_ptr(class) as *const () as usize);
+                    }
+                },
+                Err(_) => return false,
+            },
+            Live::Frame(frame) => match frame.vars.try_borrow() {
+                Ok(vars) => {
+                    vars.values().for_each(|val| child_id(val, &mut f));
+                    if let Some(parent) = &frame.parent {
+                        f(parent.id());
+                    }
+                },
+                Err(_) => return false,
+            },
+        }
+        true
+    }
+
+    // Empty the container, handing back its contents so that they are dropped once every container has been emptied
+    fn clear(&self, dead: &mut Vec<Value>) {
+        match self {
+            Live::List(l) => if let Ok(mut l) = l.try_borrow_mut() {
+                dead.extend(mem::replace(&mut *l, List::new()).iter().cloned());
+            },
+            Live::Map(m) => if let Ok(mut m) = m.try_borrow_mut() {
+                dead.extend(m.take_default_fn());
+                dead.extend(m.take_class().map(Value::Map));
+                dead.extend(mem::take(&mut *m).into_iter().flat_map(|(k, v)| vec![k, v]));
+            },
+            Live::Frame(frame) => if let Ok(mut vars) = frame.vars.try_borrow_mut() {
+                dead.extend(mem::take(&mut *vars).into_iter().map(|(_, val)| val));
+            },
+        }
+    }
+}
+
+fn string_bytes(val: &Value) -> usize {
+    match val {
+        Value::String(s) => s.try_borrow().map(|s| s.capacity()).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn child_id(val: &Value, f: &mut impl FnMut(usize)) {
+    match val {
+        Value::List(l) => f(Rc::as_ptr(l) as *const () as usize),
+        Value::Map(m) => f(Rc::as_ptr(m) as *const () as usize),
+        Value::Fn(_, _, env) => f(env.id()),
+        // Custom objects are not traced, so anything they hold is treated as reachable
+        _ => {},
+    }
+}
+
+#[derive(Default)]
+struct Registry {
+    // Each container, along with where it was allocated
+    tracked: Vec<(Tracked, SrcRef)>,
+    // Containers allocated since the last collection
+    allocated: usize,
+    // The number of containers alive after the last collection or pruning
+    survivors: usize,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<Registry> = RefCell::new(Registry::default());
+    static COLLECTING: Cell<bool> = Cell::new(false);
+    static SITE: Cell<SrcRef> = Cell::new(SrcRef::empty());
+}
+
+/// Attributes the containers allocated until it is dropped to a place in the source (see `enter_site`).
+pub(crate) struct SiteGuard(SrcRef);
+
+impl Drop for SiteGuard {
+    fn drop(&mut self) {
+        SITE.with(|site| site.set(self.0));
+    }
+}
+
+/// Say that the containers allocated from now on, until the guard is dropped, were allocated by the code at this
+/// location, as reported by `heap_snapshot`.
+pub(crate) fn enter_site(r: SrcRef) -> SiteGuard {
+    SiteGuard(SITE.with(|site| site.replace(r)))
+}
+
+fn track(tracked: Tracked) {
+    REGISTRY.with(|reg| {
+        let mut reg = reg.borrow_mut();
+        // Entries for containers that have already been freed are pruned as they accumulate, even if collection is
+        // disabled
+        if reg.tracked.len() >= (reg.survivors * 2).max(DEFAULT_THRESHOLD) * 2 {
+            reg.tracked.retain(|(t, _)| t.upgrade().is_some());
+            reg.survivors = reg.tracked.len();
+        }
+        reg.tracked.push((tracked, SITE.with(|site| site.get())));
+        reg.allocated += 1;
+    });
+}
+
+pub(crate) fn track_list(l: &Rc<RefCell<List>>) {
+    track(Tracked::List(Rc::downgrade(l)));
+}
+
+pub(crate) fn track_map(m: &Rc<RefCell<Map>>) {
+    track(Tracked::Map(Rc::downgrade(m)));
+}
+
+pub(crate) fn track_frame(frame: &Rc<Frame>) {
+    track(Tracked::Frame(Rc::downgrade(frame)));
+}
+
+/// Collect if at least `threshold` containers have been allocated since the last collection. The threshold grows with
+/// the number of containers that survived the last collection, so large heaps are not traced over and over.
+pub(crate) fn maybe_collect(threshold: Option<usize>) {
+    let due = threshold.map_or(false, |threshold| REGISTRY.with(|reg| {
+        let reg = reg.borrow();
+        reg.allocated >= threshold.max(reg.survivors)
+    }));
+    if due {
+        collect();
+    }
+}
+
+/// Free every list, map and variable frame that is only reachable through reference cycles, returning the number
+/// freed.
+///
+/// Containers that are referenced from outside of the runtime heap (by the host, the call stack or a custom object)
+/// are roots. Each container starts with its reference count, from which the references held by other containers are
+/// subtracted; those left with references, and everything reachable from them, are alive.
+pub fn collect() -> usize {
+    // Dropping the contents of freed containers may run code (such as a `Drop` impl of a custom object) that allocates
+    if COLLECTING.with(|c| c.replace(true)) {
+        return 0;
+    }
+    let live = REGISTRY.with(|reg| {
+        let mut reg = reg.borrow_mut();
+        reg.allocated = 0;
+        let live = reg.tracked
+            .iter()
+            .filter_map(|(t, site)| t.upgrade().map(|live| (live, *site)))
+            .collect::<Vec<_>>();
+        reg.tracked = live.iter().map(|(live, site)| (live.downgrade(), *site)).collect();
+        live.into_iter().map(|(live, _)| live).collect::<Vec<_>>()
+    });
+
+    let index = live.iter().enumerate().map(|(i, live)| (live.id(), i)).collect::<HashMap<_, _>>();
+    // The reference held by `live` itself is not counted
+    let mut refs = live.iter().map(|live| live.strong_count() as isize - 1).collect::<Vec<_>>();
+    let mut roots = HashSet::new();
+    for (i, container) in live.iter().enumerate() {
+        let traced = container.for_each_child(|id| if let Some(&child) = index.get(&id) {
+            refs[child] -= 1;
+        });
+        if !traced {
+            roots.insert(i);
+        }
+    }
+
+    let mut reachable = vec![false; live.len()];
+    let mut stack = (0..live.len()).filter(|i| refs[*i] > 0 || roots.contains(i)).collect::<Vec<_>>();
+    while let Some(i) = stack.pop() {
+        if mem::replace(&mut reachable[i], true) {
+            continue;
+        }
+        live[i].for_each_child(|id| if let Some(&child) = index.get(&id) {
+            if !reachable[child] {
+                stack.push(child);
+            }
+        });
+    }
+
+    let mut dead = Vec::new();
+    let mut freed = 0;
+    for (i, container) in live.iter().enumerate() {
+        if !reachable[i] {
+            container.clear(&mut dead);
+            freed += 1;
+        }
+    }
+    REGISTRY.with(|reg| reg.borrow_mut().survivors = live.len() - freed);
+    drop(live);
+    drop(dead);
+    COLLECTING.with(|c| c.set(false));
+    freed
+}
+
+/// The lists, maps and scopes (frames of variables) that are alive, grouped by type and by where they were allocated,
+/// largest first. Produced by `Engine::heap_snapshot`.
+///
+/// Strings are counted towards the containers that hold them, while numbers and other small values take up only
+/// their container's slot for them. Custom objects are not counted. Containers are allocated at the list or map
+/// literal that made them or, for the scopes of functions and the lists and maps made by builtins, at the call that
+/// made them.
+#[derive(Clone, Debug, Default)]
+pub struct HeapSnapshot {
+    pub groups: Vec<HeapGroup>,
+}
+
+/// Live containers of the same type that were allocated at the same place.
+#[derive(Clone, Debug)]
+pub struct HeapGroup {
+    pub type_name: &'static str,
+    /// The line and column where they were allocated, if known.
+    pub site: Option<(usize, usize)>,
+    pub count: usize,
+    /// Roughly how much memory they use.
+    pub bytes: usize,
+}
+
+impl HeapSnapshot {
+    pub fn count(&self) -> usize {
+        self.groups.iter().map(|group| group.count).sum()
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.groups.iter().map(|group| group.bytes).sum()
+    }
+}
+
+impl fmt::Display for HeapSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{:>10} {:>12}  {:<6} {}", "count", "bytes", "type", "allocated at")?;
+        for group in &self.groups {
+            let site = match group.site {
+                Some((line, col)) => format!("{}:{}", line, col),
+                None => String::from("?"),
+            };
+            writeln!(f, "{:>10} {:>12}  {:<6} {}", group.count, group.bytes, group.type_name, site)?;
+        }
+        writeln!(f, "{:>10} {:>12}  total", self.count(), self.bytes())
+    }
+}
+
+/// Take a snapshot of the containers that are alive on this thread.
+pub fn snapshot() -> HeapSnapshot {
+    let live = REGISTRY.with(|reg| reg
+        .borrow()
+        .tracked
+        .iter()
+        .filter_map(|(t, site)| t.upgrade().map(|live| (live, *site)))
+        .collect::<Vec<_>>());
+
+    let mut groups: HashMap<(&'static str, Option<(usize, usize)>), HeapGroup> = HashMap::new();
+    for (live, site) in &live {
+        let site = site.start().pos();
+        let group = groups.entry((live.type_name(), site)).or_insert_with(|| HeapGroup {
+            type_name: live.type_name(),
+            site,
+            count: 0,
+            bytes: 0,
+        });
+        group.count += 1;
+        group.bytes += live.bytes();
+    }
+    let mut groups = groups.into_iter().map(|(_, group)| group).collect::<Vec<_>>();
+    groups.sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.site.cmp(&b.site)).then(a.type_name.cmp(b.type_name)));
+    HeapSnapshot { groups }
+}
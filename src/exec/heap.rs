@@ -0,0 +1,163 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    cmp::Ordering,
+};
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    method::{self, Method, MethodFn},
+};
+
+struct Entry {
+    key: Value,
+    // Breaks ties between equal keys so that items with the same priority are popped in the order they were pushed
+    seq: u64,
+    item: Value,
+}
+
+struct HeapState {
+    entries: Vec<Entry>,
+    key_fn: Option<Value>,
+    next_seq: u64,
+}
+
+/// A binary min-heap, created with `heap()` or `heap(key_fn)`.
+///
+/// Items are ordered by the `<` operator, applied either to the items themselves or to the keys produced for them by
+/// the key function when they are pushed.
+#[derive(Clone)]
+pub struct Heap {
+    state: Rc<RefCell<HeapState>>,
+}
+
+impl Heap {
+    pub fn new(key_fn: Option<Value>) -> Self {
+        Self {
+            state: Rc::new(RefCell::new(HeapState {
+                entries: Vec::new(),
+                key_fn,
+                next_seq: 0,
+            })),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.borrow().entries.len()
+    }
+}
+
+impl Obj for Heap {
+    fn get_type_name(&self) -> String {
+        String::from("heap")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<heap with {} items>", self.len()))
+    }
+
+    fn eval_truth(&self, _r: SrcRef) -> ExecResult<bool> {
+        Ok(self.len() > 0)
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<Heap>) = match name {
+            "push" => ("push", heap_push),
+            "pop_min" => ("pop_min", heap_pop_min),
+            "peek" => ("peek", heap_peek),
+            "len" => ("len", heap_len),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+}
+
+fn cmp_entries(a: &Entry, b: &Entry, refs: BinaryOpRef) -> ExecResult<Ordering> {
+    Ok(method::natural_cmp(&a.key, &b.key, refs)?.then(a.seq.cmp(&b.seq)))
+}
+
+fn sift_up(entries: &mut [Entry], mut idx: usize, refs: BinaryOpRef) -> ExecResult<()> {
+    while idx > 0 {
+        let parent = (idx - 1) / 2;
+        if cmp_entries(&entries[idx], &entries[parent], refs)? != Ordering::Less {
+            break;
+        }
+        entries.swap(idx, parent);
+        idx = parent;
+    }
+    Ok(())
+}
+
+fn sift_down(entries: &mut [Entry], mut idx: usize, refs: BinaryOpRef) -> ExecResult<()> {
+    loop {
+        let mut smallest = idx;
+        for child in &[idx * 2 + 1, idx * 2 + 2] {
+            if *child < entries.len() && cmp_entries(&entries[*child], &entries[smallest], refs)? == Ordering::Less {
+                smallest = *child;
+            }
+        }
+        if smallest == idx {
+            return Ok(());
+        }
+        entries.swap(idx, smallest);
+        idx = smallest;
+    }
+}
+
+fn heap_push(heap: &Heap, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let item = args.into_iter().next().unwrap();
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+
+    // The key function is called before borrowing the heap so that it may freely access it
+    let key_fn = heap.state.borrow().key_fn.clone();
+    let key = match key_fn {
+        Some(f) => f.call(vec![item.clone()], ctx, src, r_caller, r_args)?,
+        None => item.clone(),
+    };
+
+    let mut state = heap.state.borrow_mut();
+    let seq = state.next_seq;
+    state.next_seq += 1;
+    state.entries.push(Entry { key, seq, item });
+    let last = state.entries.len() - 1;
+    if let Err(err) = sift_up(&mut state.entries, last, refs) {
+        // Keys that cannot be compared are rejected, leaving the heap as it was
+        state.entries.pop();
+        return Err(err);
+    }
+    Ok(Value::Null)
+}
+
+fn heap_pop_min(heap: &Heap, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+
+    let mut state = heap.state.borrow_mut();
+    if state.entries.is_empty() {
+        return Ok(Value::Null);
+    }
+    let min = state.entries.swap_remove(0);
+    sift_down(&mut state.entries, 0, refs)?;
+    Ok(min.item)
+}
+
+fn heap_peek(heap: &Heap, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(heap.state
+        .borrow()
+        .entries
+        .get(0)
+        .map(|entry| entry.item.clone())
+        .unwrap_or(Value::Null))
+}
+
+fn heap_len(heap: &Heap, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Int(heap.len() as i64))
+}
@@ -0,0 +1,32 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Lets a host stop the code running in an engine, typically from a Ctrl-C signal handler or another thread.
+///
+/// Running code notices an interrupt at the next loop iteration or function call. If the code has registered a
+/// handler with `on_interrupt(fn)`, the handler is called and execution continues, allowing it to shut down
+/// gracefully. Otherwise, an error is raised that may be caught with `try`/`catch`. An interrupt that arrives while
+/// no code is running takes effect when code next runs.
+#[derive(Clone, Debug, Default)]
+pub struct InterruptHandle {
+    flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// Request that the running code be interrupted. This only sets a flag, so it is safe to call from a signal
+    /// handler.
+    pub fn interrupt(&self) {
+        self.flag.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether an interrupt has been requested but not yet noticed by the running code.
+    pub fn is_pending(&self) -> bool {
+        self.flag.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn take(&self) -> bool {
+        self.flag.swap(false, Ordering::SeqCst)
+    }
+}
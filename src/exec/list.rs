@@ -0,0 +1,130 @@
+use std::{
+    rc::Rc,
+    fmt,
+    ops::{Deref, Range},
+    mem,
+};
+use super::{Value, value};
+
+/// The storage behind a list value.
+///
+/// Slicing a list produces a view that shares the same items. The items are only copied when either the list or one of
+/// its views is mutated while they are still shared (copy-on-write), so slicing is cheap regardless of list length.
+#[derive(Clone)]
+pub struct List {
+    items: Rc<Vec<Value>>,
+    range: Range<usize>,
+}
+
+impl List {
+    pub fn new() -> Self {
+        Vec::new().into()
+    }
+
+    /// Produce a view over part of this list without copying its items.
+    pub fn slice(&self, range: Range<usize>) -> Option<Self> {
+        if range.start <= range.end && range.end <= self.len() {
+            Some(Self {
+                items: self.items.clone(),
+                range: self.range.start + range.start..self.range.start + range.end,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Mutate the items, copying them first if they are shared with another list or view.
+    pub fn modify<R>(&mut self, f: impl FnOnce(&mut Vec<Value>) -> R) -> R {
+        if self.range != (0..self.items.len()) {
+            self.items = Rc::new(self.items[self.range.clone()].to_vec());
+        }
+        let res = f(Rc::make_mut(&mut self.items));
+        self.range = 0..self.items.len();
+        res
+    }
+
+    pub fn push(&mut self, item: Value) {
+        self.modify(|items| items.push(item))
+    }
+
+    pub fn set(&mut self, idx: usize, item: Value) -> Option<()> {
+        if idx < self.len() {
+            self.modify(|items| items[idx] = item);
+            Some(())
+        } else {
+            None
+        }
+    }
+
+    /// Replace the items within a range, returning the items that were removed.
+    pub fn splice(&mut self, range: Range<usize>, with: Vec<Value>) -> Vec<Value> {
+        self.modify(|items| items.splice(range, with).collect())
+    }
+
+    // Whether the items are shared with another list, view or iterator
+    pub(crate) fn is_shared(&self) -> bool {
+        Rc::strong_count(&self.items) > 1
+    }
+
+    // Empty the list, returning its items unless they are shared with another list or view, which is left to drop them
+    pub(super) fn take_items(&mut self) -> Vec<Value> {
+        self.range = 0..0;
+        Rc::get_mut(&mut self.items).map(mem::take).unwrap_or_default()
+    }
+
+    pub fn iter_owned(&self) -> ListIter {
+        ListIter {
+            items: self.items.clone(),
+            range: self.range.clone(),
+        }
+    }
+}
+
+impl Drop for List {
+    fn drop(&mut self) {
+        value::drop_items(self.take_items());
+    }
+}
+
+impl Deref for List {
+    type Target = [Value];
+
+    fn deref(&self) -> &[Value] {
+        &self.items[self.range.clone()]
+    }
+}
+
+impl From<Vec<Value>> for List {
+    fn from(items: Vec<Value>) -> Self {
+        Self {
+            range: 0..items.len(),
+            items: Rc::new(items),
+        }
+    }
+}
+
+impl std::iter::FromIterator<Value> for List {
+    fn from_iter<I: IntoIterator<Item=Value>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl fmt::Debug for List {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// Iterates over the items of a list as they were when iteration began, without copying them.
+pub struct ListIter {
+    items: Rc<Vec<Value>>,
+    range: Range<usize>,
+}
+
+impl Iterator for ListIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        self.range.next().map(|idx| self.items[idx].clone())
+    }
+}
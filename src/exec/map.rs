@@ -0,0 +1,239 @@
+use std::{
+    fmt,
+    rc::Rc,
+    cell::RefCell,
+    cmp::Ordering,
+    iter::FromIterator,
+};
+use hashbrown::HashMap;
+use super::{Value, value};
+
+/// The storage behind a map value.
+///
+/// Items are kept in the order that their keys were first inserted, so iterating over a map (or displaying it) gives
+/// the same result every time. Assigning to a key that is already present leaves it where it was. Sorted maps, as made
+/// by `sortedmap()`, keep their items in the order of their keys instead.
+#[derive(Clone, Default)]
+pub struct Map {
+    // Where the item of each key is in `entries`
+    indices: HashMap<Value, usize>,
+    // Removed items leave a gap, until there are enough gaps that it is worth closing them
+    entries: Vec<Option<(Value, Value)>>,
+    sorted: bool,
+    // The function that produces the item of a key that the map does not contain, as given to `defaultmap(f)`
+    default: Option<Value>,
+    // The class of an instance, which is kept apart from the items so that it is not one of them
+    class: Option<Rc<RefCell<Map>>>,
+}
+
+impl Map {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn new_sorted() -> Self {
+        let mut map = Self::default();
+        map.sorted = true;
+        map
+    }
+
+    pub fn with_default(default: Value) -> Self {
+        let mut map = Self::default();
+        map.default = Some(default);
+        map
+    }
+
+    pub(crate) fn with_class(class: Rc<RefCell<Map>>) -> Self {
+        let mut map = Self::default();
+        map.class = Some(class);
+        map
+    }
+
+    pub(crate) fn class(&self) -> Option<&Rc<RefCell<Map>>> {
+        self.class.as_ref()
+    }
+
+    pub(crate) fn take_class(&mut self) -> Option<Rc<RefCell<Map>>> {
+        self.class.take()
+    }
+
+    pub fn default_fn(&self) -> Option<&Value> {
+        self.default.as_ref()
+    }
+
+    pub(crate) fn take_default_fn(&mut self) -> Option<Value> {
+        self.default.take()
+    }
+
+    pub fn len(&self) -> usize {
+        self.indices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    pub fn get(&self, key: &Value) -> Option<&Value> {
+        let idx = *self.indices.get(key)?;
+        self.entries[idx].as_ref().map(|(_, val)| val)
+    }
+
+    pub fn get_mut(&mut self, key: &Value) -> Option<&mut Value> {
+        let idx = *self.indices.get(key)?;
+        self.entries[idx].as_mut().map(|(_, val)| val)
+    }
+
+    pub fn contains_key(&self, key: &Value) -> bool {
+        self.indices.contains_key(key)
+    }
+
+    /// Set the value of a key, returning the value that it replaced.
+    pub fn insert(&mut self, key: Value, val: Value) -> Option<Value> {
+        match self.indices.get(&key) {
+            Some(&idx) => self.entries[idx].as_mut().map(|(_, old)| std::mem::replace(old, val)),
+            None if self.sorted => {
+                if self.entries.len() > self.indices.len() {
+                    self.compact();
+                }
+                let idx = self.entries
+                    .binary_search_by(|entry| entry.as_ref().map(|(other, _)| key_cmp(other, &key)).unwrap_or(Ordering::Less))
+                    .unwrap_or_else(|idx| idx);
+                self.entries.insert(idx, Some((key.clone(), val)));
+                for entry in &self.entries[idx + 1..] {
+                    if let Some((key, _)) = entry {
+                        *self.indices.get_mut(key).unwrap() += 1;
+                    }
+                }
+                self.indices.insert(key, idx);
+                None
+            },
+            None => {
+                self.indices.insert(key.clone(), self.entries.len());
+                self.entries.push(Some((key, val)));
+                None
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: &Value) -> Option<Value> {
+        let idx = self.indices.remove(key)?;
+        let (_, val) = self.entries[idx].take()?;
+        if self.entries.len() > 8 && self.entries.len() > self.indices.len() * 2 {
+            self.compact();
+        }
+        Some(val)
+    }
+
+    pub fn clear(&mut self) {
+        self.indices.clear();
+        self.entries.clear();
+    }
+
+    fn compact(&mut self) {
+        self.entries.retain(|entry| entry.is_some());
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if let Some((key, _)) = entry {
+                self.indices.insert(key.clone(), idx);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&Value, &Value)> {
+        self.entries.iter().filter_map(|entry| entry.as_ref().map(|(key, val)| (key, val)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (&Value, &mut Value)> {
+        self.entries.iter_mut().filter_map(|entry| entry.as_mut().map(|(key, val)| (&*key, val)))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(key, _)| key)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &Value> {
+        self.iter().map(|(_, val)| val)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Value> {
+        self.iter_mut().map(|(_, val)| val)
+    }
+
+    // Empty the map, returning its keys, items, default function and class
+    pub(super) fn take_items(&mut self) -> Vec<Value> {
+        // The keys are also held by the entries, so clearing the indices drops none of them
+        self.indices.clear();
+        self.entries
+            .drain(..)
+            .flatten()
+            .flat_map(|(key, val)| vec![key, val])
+            .chain(self.default.take())
+            .chain(self.class.take().map(Value::Map))
+            .collect()
+    }
+
+    // Roughly how much memory the items take up, not counting what they refer to
+    pub(crate) fn capacity_bytes(&self) -> usize {
+        self.entries.capacity() * std::mem::size_of::<Option<(Value, Value)>>()
+            + self.indices.capacity() * (std::mem::size_of::<Value>() + std::mem::size_of::<usize>())
+    }
+}
+
+// The keys of a sorted map are ordered by value where they can be compared, as numbers, strings, chars or bools, and
+// otherwise by their type
+fn key_cmp(x: &Value, y: &Value) -> Ordering {
+    let rank = |val: &Value| match val {
+        Value::Null => 0,
+        Value::Boolean(_) => 1,
+        Value::Int(_) | Value::Float(_) => 2,
+        Value::Char(_) => 3,
+        Value::String(_) => 4,
+        _ => 5,
+    };
+    match (x, y) {
+        (Value::Boolean(x), Value::Boolean(y)) => x.cmp(y),
+        (Value::Int(x), Value::Int(y)) => x.cmp(y),
+        (Value::Char(x), Value::Char(y)) => x.cmp(y),
+        (Value::String(x), Value::String(y)) => x.borrow().as_str().cmp(y.borrow().as_str()),
+        (x, y) => match (x.as_number(), y.as_number()) {
+            (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+            _ => rank(x).cmp(&rank(y)),
+        },
+    }
+}
+
+impl Drop for Map {
+    fn drop(&mut self) {
+        value::drop_items(self.take_items());
+    }
+}
+
+impl FromIterator<(Value, Value)> for Map {
+    fn from_iter<I: IntoIterator<Item = (Value, Value)>>(iter: I) -> Self {
+        let mut map = Self::new();
+        map.extend(iter);
+        map
+    }
+}
+
+impl Extend<(Value, Value)> for Map {
+    fn extend<I: IntoIterator<Item = (Value, Value)>>(&mut self, iter: I) {
+        for (key, val) in iter {
+            self.insert(key, val);
+        }
+    }
+}
+
+impl IntoIterator for Map {
+    type Item = (Value, Value);
+    type IntoIter = std::iter::Flatten<std::vec::IntoIter<Option<(Value, Value)>>>;
+
+    fn into_iter(mut self) -> Self::IntoIter {
+        std::mem::take(&mut self.entries).into_iter().flatten()
+    }
+}
+
+impl fmt::Debug for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
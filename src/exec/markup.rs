@@ -0,0 +1,553 @@
+use std::{
+    iter::Peekable,
+    rc::Rc,
+    str::Chars,
+};
+use hashbrown::HashMap;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+};
+
+pub(super) const XML: &[(&str, BuiltinFn)] = &[
+    ("parse", xml_parse),
+];
+
+pub(super) const HTML: &[(&str, BuiltinFn)] = &[
+    ("parse", html_parse),
+    ("select", html_select),
+];
+
+// Elements that never have content, so they need no closing tag
+const VOID_TAGS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr"];
+// Elements whose content is text up to the closing tag, even if it looks like markup
+const RAW_TAGS: &[&str] = &["script", "style", "textarea", "title"];
+// Elements that are closed by the start of a sibling, such as the `li` before another `li`
+const SIBLING_TAGS: &[&[&str]] = &[&["li"], &["p"], &["option"], &["tr"], &["td", "th"], &["dt", "dd"]];
+
+enum Node {
+    Element(Element),
+    Text(String),
+}
+
+struct Element {
+    tag: String,
+    attrs: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(tag: String) -> Self {
+        Element { tag, attrs: Vec::new(), children: Vec::new() }
+    }
+
+    /// Convert the element into a map, along with the text that it contains.
+    fn into_value(self) -> (Value, String) {
+        let mut text = String::new();
+        let children = self.children
+            .into_iter()
+            .map(|child| match child {
+                Node::Element(elem) => {
+                    let (val, inner) = elem.into_value();
+                    text += &inner;
+                    val
+                },
+                Node::Text(s) => {
+                    text += &s;
+                    Value::from(s)
+                },
+            })
+            .collect::<Vec<_>>();
+
+        let mut node = Map::new();
+        node.insert(Value::from("tag"), Value::from(self.tag));
+        node.insert(Value::from("attrs"), Value::from(self.attrs
+            .into_iter()
+            .map(|(name, val)| (Value::from(name), Value::from(val)))
+            .collect::<Map>()));
+        node.insert(Value::from("children"), Value::from(children));
+        node.insert(Value::from("text"), Value::from(text.clone()));
+        (Value::from(node), text)
+    }
+}
+
+/// A forgiving parser for HTML, or a strict one for XML.
+struct Parser<'a> {
+    s: &'a str,
+    pos: usize,
+    html: bool,
+}
+
+type ParseResult<T> = Result<T, String>;
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.s[self.pos..]
+    }
+
+    fn line(&self) -> usize {
+        self.s[..self.pos].matches('\n').count() + 1
+    }
+
+    fn skip_space(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Skip past the next occurrence of `end`, or to the end of the text if HTML leaves it out.
+    fn skip_past(&mut self, end: &str, what: &str) -> ParseResult<&'a str> {
+        match self.rest().find(end) {
+            Some(i) => {
+                let skipped = &self.rest()[..i];
+                self.pos += i + end.len();
+                Ok(skipped)
+            },
+            None if self.html => {
+                let skipped = self.rest();
+                self.pos = self.s.len();
+                Ok(skipped)
+            },
+            None => Err(format!("{} is never closed", what)),
+        }
+    }
+
+    fn read_name(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/' || c == '<')
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn fold_case(&self, name: &str) -> String {
+        if self.html { name.to_lowercase() } else { name.to_string() }
+    }
+
+    fn read_attrs(&mut self) -> ParseResult<Vec<(String, String)>> {
+        let mut attrs = Vec::new();
+        loop {
+            self.skip_space();
+            let rest = self.rest();
+            if rest.is_empty() || rest.starts_with('>') || rest.starts_with("/>") {
+                return Ok(attrs);
+            }
+
+            let name = self.read_name();
+            if name.is_empty() {
+                if self.html {
+                    self.pos += rest.chars().next().map(char::len_utf8).unwrap_or(0);
+                    continue;
+                } else {
+                    return Err(format!("expected an attribute name, found '{}'", rest.chars().next().unwrap_or(' ')));
+                }
+            }
+            let name = self.fold_case(name);
+
+            self.skip_space();
+            if !self.rest().starts_with('=') {
+                if self.html {
+                    attrs.push((name, String::new()));
+                    continue;
+                } else {
+                    return Err(format!("attribute '{}' has no value", name));
+                }
+            }
+            self.pos += 1;
+            self.skip_space();
+
+            let val = match self.rest().chars().next() {
+                Some(quote) if quote == '"' || quote == '\'' => {
+                    self.pos += 1;
+                    self.skip_past(&quote.to_string(), &format!("The value of attribute '{}'", name))?
+                },
+                _ if self.html => {
+                    let rest = self.rest();
+                    let len = rest.find(|c: char| c.is_whitespace() || c == '>').unwrap_or(rest.len());
+                    self.pos += len;
+                    &rest[..len]
+                },
+                _ => return Err(format!("the value of attribute '{}' is not quoted", name)),
+            };
+            attrs.push((name, decode_entities(val)));
+        }
+    }
+
+    fn parse(&mut self) -> ParseResult<Vec<Node>> {
+        let mut root = Vec::new();
+        let mut open: Vec<Element> = Vec::new();
+
+        fn append(root: &mut Vec<Node>, open: &mut Vec<Element>, node: Node) {
+            match open.last_mut() {
+                Some(parent) => parent.children.push(node),
+                None => root.push(node),
+            }
+        }
+
+        fn close(root: &mut Vec<Node>, open: &mut Vec<Element>) {
+            if let Some(elem) = open.pop() {
+                append(root, open, Node::Element(elem));
+            }
+        }
+
+        while self.pos < self.s.len() {
+            let rest = self.rest();
+            if rest.starts_with("<!--") {
+                self.pos += 4;
+                self.skip_past("-->", "A comment")?;
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += 9;
+                let text = self.skip_past("]]>", "A CDATA section")?;
+                append(&mut root, &mut open, Node::Text(text.to_string()));
+            } else if rest.starts_with("<?") {
+                self.pos += 2;
+                self.skip_past("?>", "A processing instruction")?;
+            } else if rest.starts_with("<!") {
+                self.pos += 2;
+                self.skip_past(">", "A declaration")?;
+            } else if rest.starts_with("</") {
+                self.pos += 2;
+                let tag = self.skip_past(">", "A closing tag")?.trim();
+                let tag = self.fold_case(tag);
+                match open.iter().rposition(|elem| elem.tag == tag) {
+                    Some(i) if self.html || i == open.len() - 1 => while open.len() > i {
+                        close(&mut root, &mut open);
+                    },
+                    // HTML is full of stray closing tags, so they are ignored
+                    _ if self.html => {},
+                    _ => return Err(match open.last() {
+                        Some(elem) => format!("expected '</{}>', found '</{}>'", elem.tag, tag),
+                        None => format!("'</{}>' has no opening tag", tag),
+                    }),
+                }
+            } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_alphabetic() || c == '_') {
+                self.pos += 1;
+                let tag = self.read_name();
+                let tag = self.fold_case(tag);
+                let mut elem = Element::new(tag);
+                elem.attrs = self.read_attrs()?;
+
+                let self_closing = if self.rest().starts_with("/>") {
+                    self.pos += 2;
+                    true
+                } else if self.rest().starts_with('>') {
+                    self.pos += 1;
+                    false
+                } else {
+                    return Err(format!("'<{}>' is never closed", elem.tag));
+                };
+
+                if self.html {
+                    if let Some(siblings) = SIBLING_TAGS.iter().find(|tags| tags.contains(&elem.tag.as_str())) {
+                        if open.last().map(|top| siblings.contains(&top.tag.as_str())).unwrap_or(false) {
+                            close(&mut root, &mut open);
+                        }
+                    }
+                }
+
+                if self_closing || (self.html && VOID_TAGS.contains(&elem.tag.as_str())) {
+                    append(&mut root, &mut open, Node::Element(elem));
+                } else if self.html && RAW_TAGS.contains(&elem.tag.as_str()) {
+                    let end = self.rest().to_ascii_lowercase().find(&format!("</{}", elem.tag)).unwrap_or(self.rest().len());
+                    let text = &self.rest()[..end];
+                    if !text.trim().is_empty() {
+                        // Scripts and styles are code, but the text of titles and text areas may still contain entities
+                        elem.children.push(Node::Text(match elem.tag.as_str() {
+                            "script" | "style" => text.to_string(),
+                            _ => decode_entities(text),
+                        }));
+                    }
+                    self.pos += end;
+                    if self.pos < self.s.len() {
+                        self.skip_past(">", "A closing tag")?;
+                    }
+                    append(&mut root, &mut open, Node::Element(elem));
+                } else {
+                    open.push(elem);
+                }
+            } else {
+                // A `<` that starts nothing is just text
+                let first = rest.chars().next().map(char::len_utf8).unwrap_or(0);
+                let len = rest[first..].find('<').map(|i| i + first).unwrap_or(rest.len());
+                let text = &rest[..len];
+                self.pos += len;
+                if !text.trim().is_empty() {
+                    append(&mut root, &mut open, Node::Text(decode_entities(text)));
+                }
+            }
+        }
+
+        match open.last() {
+            Some(elem) if !self.html => Err(format!("'<{}>' is never closed", elem.tag)),
+            _ => {
+                while !open.is_empty() {
+                    close(&mut root, &mut open);
+                }
+                Ok(root)
+            },
+        }
+    }
+}
+
+fn decode_entities(s: &str) -> String {
+    let mut decoded = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        decoded += &rest[..i];
+        rest = &rest[i..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|entity| match entity {
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "amp" => Some('&'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some('\u{a0}'),
+            _ if entity.starts_with("#x") || entity.starts_with("#X") => u32::from_str_radix(&entity[2..], 16).ok().and_then(std::char::from_u32),
+            _ if entity.starts_with('#') => entity[1..].parse().ok().and_then(std::char::from_u32),
+            _ => None,
+        });
+        match (c, entity) {
+            (Some(c), Some(entity)) => {
+                decoded.push(c);
+                rest = &rest[entity.len() + 2..];
+            },
+            // Unknown entities are left as they are
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            },
+        }
+    }
+    decoded + rest
+}
+
+fn xml_parse(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let text = string_arg(&args[0], r_args)?;
+    let mut parser = Parser { s: &text, pos: 0, html: false };
+    let error = |msg: String| ExecError::At(r_args, Box::new(ExecError::Custom(msg)));
+
+    let nodes = parser.parse().map_err(|msg| error(format!("Could not parse line {} of XML: {}.", parser.line(), msg)))?;
+    let mut elems = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Element(elem) => elems.push(elem),
+            Node::Text(_) => return Err(error(String::from("XML text must be within the root element."))),
+        }
+    }
+    match elems.len() {
+        1 => Ok(elems.remove(0).into_value().0),
+        0 => Err(error(String::from("XML must have a root element."))),
+        _ => Err(error(String::from("XML must have only one root element."))),
+    }
+}
+
+fn html_parse(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let text = string_arg(&args[0], r_args)?;
+    let mut parser = Parser { s: &text, pos: 0, html: true };
+    let mut doc = Element::new(String::from("#document"));
+    doc.children = parser.parse().map_err(|msg| ExecError::At(r_args, Box::new(ExecError::Custom(format!("Could not parse HTML: {}.", msg)))))?;
+    Ok(doc.into_value().0)
+}
+
+// Selectors
+
+#[derive(Default)]
+struct Compound {
+    tag: Option<String>,
+    ids: Vec<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<(char, String)>)>,
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+// Each compound selector is paired with how it relates to the one before it
+type Selector = Vec<(Combinator, Compound)>;
+
+fn parse_selectors(s: &str) -> Option<Vec<Selector>> {
+    let mut selectors = Vec::new();
+    let mut chars = s.chars().peekable();
+    let mut selector: Selector = Vec::new();
+    let mut combinator = Combinator::Descendant;
+
+    fn read_ident(chars: &mut Peekable<Chars>) -> Option<String> {
+        let mut ident = String::new();
+        while let Some(c) = chars.peek().copied().filter(|c| c.is_alphanumeric() || *c == '-' || *c == '_') {
+            ident.push(c);
+            chars.next();
+        }
+        Some(ident).filter(|ident| !ident.is_empty())
+    }
+
+    loop {
+        match chars.peek().copied() {
+            Some(c) if c.is_whitespace() => { chars.next(); },
+            Some('>') if !selector.is_empty() && combinator == Combinator::Descendant => {
+                chars.next();
+                combinator = Combinator::Child;
+            },
+            Some(',') | None => {
+                if selector.is_empty() || combinator == Combinator::Child {
+                    return None;
+                }
+                selectors.push(std::mem::replace(&mut selector, Vec::new()));
+                if chars.next().is_none() {
+                    return Some(selectors);
+                }
+            },
+            Some(_) => {
+                let mut compound = Compound::default();
+                let universal = chars.peek() == Some(&'*');
+                if universal {
+                    chars.next();
+                } else {
+                    compound.tag = read_ident(&mut chars);
+                }
+                loop {
+                    match chars.peek().copied() {
+                        Some('#') => { chars.next(); compound.ids.push(read_ident(&mut chars)?); },
+                        Some('.') => { chars.next(); compound.classes.push(read_ident(&mut chars)?); },
+                        Some('[') => {
+                            chars.next();
+                            let inner = chars.by_ref().take_while(|c| *c != ']').collect::<String>();
+                            let attr = match inner.find('=') {
+                                Some(i) => {
+                                    let (name, op) = match inner[..i].chars().last() {
+                                        Some(op) if "~^$*|".contains(op) => (&inner[..i - 1], op),
+                                        _ => (&inner[..i], '='),
+                                    };
+                                    let val = inner[i + 1..].trim();
+                                    let val = val.trim_matches(|c| c == '"' || c == '\'');
+                                    (name.trim().to_string(), Some((op, val.to_string())))
+                                },
+                                None => (inner.trim().to_string(), None),
+                            };
+                            if attr.0.is_empty() {
+                                return None;
+                            }
+                            compound.attrs.push(attr);
+                        },
+                        _ => break,
+                    }
+                }
+                match chars.peek() {
+                    None => {},
+                    Some(c) if c.is_whitespace() || *c == '>' || *c == ',' => {},
+                    Some(_) => return None,
+                }
+                let empty = compound.tag.is_none() && compound.ids.is_empty() && compound.classes.is_empty() && compound.attrs.is_empty();
+                if empty && !universal {
+                    return None;
+                }
+                selector.push((combinator, compound));
+                combinator = Combinator::Descendant;
+            },
+        }
+    }
+}
+
+/// The parts of a node that selectors look at.
+struct Elem {
+    tag: String,
+    attrs: HashMap<String, String>,
+}
+
+impl Elem {
+    fn from_value(val: &Value) -> Option<Self> {
+        let node = val.as_map()?;
+        let tag = node.get(&Value::from("tag"))?.as_string()?;
+        let attrs = node.get(&Value::from("attrs"))
+            .and_then(|attrs| attrs.as_map())
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(name, val)| Some((name.as_string()?, val.get_display_text().ok()?)))
+            .collect();
+        Some(Elem { tag, attrs })
+    }
+
+    fn matches(&self, compound: &Compound) -> bool {
+        let attr_matches = |(name, test): &(String, Option<(char, String)>)| match (self.attrs.get(name), test) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(val), Some(('=', expected))) => val == expected,
+            (Some(val), Some(('~', expected))) => val.split_whitespace().any(|word| word == expected),
+            (Some(val), Some(('^', expected))) => val.starts_with(expected.as_str()),
+            (Some(val), Some(('$', expected))) => val.ends_with(expected.as_str()),
+            (Some(val), Some(('*', expected))) => val.contains(expected.as_str()),
+            (Some(val), Some((_, expected))) => val == expected || val.starts_with(&format!("{}-", expected)),
+        };
+        compound.tag.as_ref().map(|tag| tag.eq_ignore_ascii_case(&self.tag)).unwrap_or(!self.tag.starts_with('#'))
+            && compound.ids.iter().all(|id| self.attrs.get("id") == Some(id))
+            && compound.classes.iter().all(|class| self.attrs
+                .get("class")
+                .map(|classes| classes.split_whitespace().any(|c| c == class))
+                .unwrap_or(false))
+            && compound.attrs.iter().all(attr_matches)
+    }
+}
+
+// Whether the last compound of `selector` matches `elem`, and the ones before it match its ancestors
+fn matches(selector: &[(Combinator, Compound)], elem: &Elem, ancestors: &[Elem]) -> bool {
+    let ((combinator, compound), before) = match selector.split_last() {
+        Some(split) => split,
+        None => return true,
+    };
+    if !elem.matches(compound) {
+        return false;
+    }
+    if before.is_empty() {
+        return true;
+    }
+    match combinator {
+        Combinator::Child => match ancestors.split_last() {
+            Some((parent, ancestors)) => matches(before, parent, ancestors),
+            None => false,
+        },
+        Combinator::Descendant => (0..ancestors.len())
+            .rev()
+            .any(|i| matches(before, &ancestors[i], &ancestors[..i])),
+    }
+}
+
+fn select(node: &Value, selectors: &[Selector], ancestors: &mut Vec<Elem>, found: &mut Vec<Value>) {
+    let children = node
+        .as_map()
+        .and_then(|node| node.get(&Value::from("children")).and_then(|children| children.as_list()))
+        .unwrap_or_default();
+    for child in children {
+        if let Some(elem) = Elem::from_value(&child) {
+            if selectors.iter().any(|selector| matches(selector, &elem, ancestors)) {
+                found.push(child.clone());
+            }
+            ancestors.push(elem);
+            select(&child, selectors, ancestors, found);
+            ancestors.pop();
+        }
+    }
+}
+
+fn html_select(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let root = Elem::from_value(&args[0])
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("node"), args[0].get_type_name()))))?;
+    let s = string_arg(&args[1], r_args)?;
+    let selectors = parse_selectors(&s)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a valid selector.", s)))))?;
+
+    let mut found = Vec::new();
+    select(&args[0], &selectors, &mut vec![root], &mut found);
+    Ok(Value::from(found))
+}
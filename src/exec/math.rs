@@ -0,0 +1,107 @@
+use std::{
+    rc::Rc,
+    cmp::Ordering,
+    f64::consts,
+};
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::{BuiltinFn, number_arg},
+    method,
+    value,
+};
+
+pub(super) const MATH: &[(&str, BuiltinFn)] = &[
+    ("sqrt", |args, _, _, _, r_args| float_fn(args, r_args, f64::sqrt)),
+    ("abs", math_abs),
+    ("floor", |args, _, _, _, r_args| round_fn(args, r_args, f64::floor)),
+    ("ceil", |args, _, _, _, r_args| round_fn(args, r_args, f64::ceil)),
+    ("round", |args, _, _, _, r_args| round_fn(args, r_args, f64::round)),
+    ("sin", |args, _, _, _, r_args| float_fn(args, r_args, f64::sin)),
+    ("cos", |args, _, _, _, r_args| float_fn(args, r_args, f64::cos)),
+    ("tan", |args, _, _, _, r_args| float_fn(args, r_args, f64::tan)),
+    ("log", math_log),
+    ("exp", |args, _, _, _, r_args| float_fn(args, r_args, f64::exp)),
+    ("pow", math_pow),
+    ("min", |args, _, _, r_caller, r_args| extreme(args, r_caller, r_args, Ordering::Less)),
+    ("max", |args, _, _, r_caller, r_args| extreme(args, r_caller, r_args, Ordering::Greater)),
+];
+
+pub(super) const MATH_CONSTS: &[(&str, Value)] = &[
+    ("pi", Value::Float(consts::PI)),
+    ("e", Value::Float(consts::E)),
+];
+
+fn float_fn(args: Vec<Value>, r_args: SrcRef, f: fn(f64) -> f64) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    Ok(Value::Float(f(number_arg(&args[0], r_args)?)))
+}
+
+// Rounding produces an int, unless the result is too large for one (or is not a number at all)
+fn round_fn(args: Vec<Value>, r_args: SrcRef, f: fn(f64) -> f64) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    match &args[0] {
+        Value::Int(x) => Ok(Value::Int(*x)),
+        val => {
+            let x = f(number_arg(val, r_args)?);
+            if x >= i64::MIN as f64 && x < i64::MAX as f64 {
+                Ok(Value::Int(x as i64))
+            } else {
+                Ok(Value::Float(x))
+            }
+        },
+    }
+}
+
+fn math_abs(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    match &args[0] {
+        Value::Int(x) => x.checked_abs().map(Value::Int).ok_or_else(|| value::overflow(r_args)),
+        val => Ok(Value::Float(number_arg(val, r_args)?.abs())),
+    }
+}
+
+// The natural logarithm, or the logarithm in the base given after the number
+fn math_log(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let x = number_arg(&args[0], r_args)?;
+    match args.get(1) {
+        Some(base) => Ok(Value::Float(x.log(number_arg(base, r_args)?))),
+        None => Ok(Value::Float(x.ln())),
+    }
+}
+
+// Ints raised to a power that is a positive int stay ints, as they do when multiplied
+fn math_pow(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    match (&args[0], &args[1]) {
+        (Value::Int(x), Value::Int(y)) if *y >= 0 => {
+            let y = if *y > u32::MAX as i64 { u32::MAX } else { *y as u32 };
+            x.checked_pow(y).map(Value::Int).ok_or_else(|| value::overflow(r_args))
+        },
+        (x, y) => Ok(Value::Float(number_arg(x, r_args)?.powf(number_arg(y, r_args)?))),
+    }
+}
+
+// The least or greatest of the arguments, or of the items of a list given alone, compared as `<` compares them
+fn extreme(args: Vec<Value>, r_caller: SrcRef, r_args: SrcRef, want: Ordering) -> ExecResult<Value> {
+    let items = match args.as_slice() {
+        [Value::List(l)] => l.borrow().to_vec(),
+        _ => args,
+    };
+    let refs = BinaryOpRef { op: r_caller, left: r_args, right: r_args };
+    let mut items = items.into_iter();
+    let mut best = items.next().ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(String::from(
+        "Expected at least one value to choose from.",
+    )))))?;
+    for item in items {
+        if method::natural_cmp(&item, &best, refs)? == want {
+            best = item;
+        }
+    }
+    Ok(best)
+}
@@ -0,0 +1,170 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    collections::{VecDeque, hash_map::DefaultHasher},
+    hash::{Hash, Hasher},
+};
+use hashbrown::HashMap;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    method,
+};
+
+// How many results are kept when `memo` is not told otherwise
+const DEFAULT_LIMIT: usize = 1024;
+
+// Arguments nested deeper than this (or that contain themselves) are not worth remembering
+const MAX_KEY_DEPTH: usize = 32;
+
+/// A copy of the arguments of a call, which compares lists, maps and strings by what they contain at the time of the
+/// call rather than by what they refer to, so that changing them afterwards does not change what was remembered.
+pub(super) enum Key {
+    Value(Value),
+    String(String),
+    List(Vec<Key>),
+    Map(Vec<(Key, Key)>),
+}
+
+impl Key {
+    // Objects compare by identity alone and may change in ways that cannot be seen, so calls involving them are not
+    // remembered
+    fn of(val: &Value, depth: usize) -> Option<Self> {
+        if depth > MAX_KEY_DEPTH {
+            return None;
+        }
+        Some(match val {
+            Value::String(s) => Key::String(s.borrow().clone()),
+            Value::List(l) => Key::List(l.borrow().iter().map(|item| Self::of(item, depth + 1)).collect::<Option<_>>()?),
+            Value::Map(m) => Key::Map(m
+                .borrow()
+                .iter()
+                .map(|(key, val)| Some((Self::of(key, depth + 1)?, Self::of(val, depth + 1)?)))
+                .collect::<Option<_>>()?),
+            Value::Custom(_) => return None,
+            val => Key::Value(val.clone()),
+        })
+    }
+
+    // The key of a value compared by what it contains where it can be, and by identity where it cannot, as when
+    // grouping items
+    pub(super) fn structural(val: &Value) -> Self {
+        Self::of(val, 0).unwrap_or_else(|| Key::Value(val.clone()))
+    }
+}
+
+impl PartialEq for Key {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Key::Value(x), Key::Value(y)) => x == y,
+            (Key::String(x), Key::String(y)) => x == y,
+            (Key::List(x), Key::List(y)) => x == y,
+            // Maps that hold the same items in a different order are equal
+            (Key::Map(x), Key::Map(y)) => x.len() == y.len() && x.iter().all(|item| y.contains(item)),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Key {}
+
+impl Hash for Key {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            // Strings must hash as they do in a value, since they are only kept apart to take a copy
+            Key::Value(x) => x.hash(state),
+            Key::String(x) => Value::from(x.as_str()).hash(state),
+            Key::List(x) => x.hash(state),
+            Key::Map(x) => {
+                // Combined so that the order of the items makes no difference
+                x.len().hash(state);
+                x.iter()
+                    .map(|item| {
+                        let mut hasher = DefaultHasher::new();
+                        item.hash(&mut hasher);
+                        hasher.finish()
+                    })
+                    .fold(0u64, u64::wrapping_add)
+                    .hash(state);
+            },
+        }
+    }
+}
+
+/// A function that remembers what it produced for the arguments it was given, as made by `memo(f)`. Calls with
+/// arguments equal to an earlier call's produce the same value without calling the function again.
+pub struct Memo {
+    f: Value,
+    limit: usize,
+    results: RefCell<HashMap<Rc<Vec<Key>>, Value>>,
+    // The oldest result is forgotten first once there are too many
+    order: RefCell<VecDeque<Rc<Vec<Key>>>>,
+}
+
+impl Obj for Memo {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<memo {}>", self.f.get_display_text()?.trim_start_matches('<').trim_end_matches('>')))
+    }
+
+    fn arity(&self) -> Option<usize> {
+        self.f.arity()
+    }
+
+    fn name(&self) -> Option<String> {
+        self.f.fn_name()
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let key = match args.iter().map(|arg| Key::of(arg, 0)).collect::<Option<Vec<_>>>() {
+            Some(key) => Rc::new(key),
+            None => return self.f.call(args, ctx, src, r_caller, r_args),
+        };
+        if let Some(val) = self.results.borrow().get(&key) {
+            return Ok(val.clone());
+        }
+
+        // Nothing is borrowed during the call, since the function may well call itself through this. Errors are not
+        // remembered.
+        let val = self.f.call(args, ctx, src, r_caller, r_args)?;
+
+        let mut results = self.results.borrow_mut();
+        let mut order = self.order.borrow_mut();
+        if results.insert(key.clone(), val.clone()).is_none() {
+            order.push_back(key);
+            while results.len() > self.limit {
+                match order.pop_front() {
+                    Some(oldest) => results.remove(&oldest),
+                    None => break,
+                };
+            }
+        }
+        Ok(val)
+    }
+}
+
+/// `memo(f)` wraps a function so that it is called at most once for each set of arguments, up to a number of results
+/// (1024, unless given after the function) beyond which the oldest are forgotten.
+pub(super) fn builtin_memo(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let f = args[0].clone();
+    if !f.is_function() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::CannotCall(f.get_type_name()))));
+    }
+    let limit = match args.get(1) {
+        None => DEFAULT_LIMIT,
+        Some(Value::Int(limit)) if *limit > 0 => *limit as usize,
+        Some(Value::Int(_)) => return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from(
+            "The number of results to remember must be at least 1.",
+        ))))),
+        Some(val) => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("int"), val.get_type_name())))),
+    };
+    Ok(Value::from(Memo { f, limit, results: RefCell::new(HashMap::new()), order: RefCell::new(VecDeque::new()) }))
+}
@@ -0,0 +1,629 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    cmp::Ordering,
+};
+use hashbrown::{HashMap, HashSet};
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    List,
+    Map,
+    Obj,
+    Value,
+    builtins::string_arg,
+    memo::Key,
+    value::resolve_index,
+};
+
+pub(super) type MethodFn<R = Value> = fn(&R, Vec<Value>, &mut ExecCtx, &Rc<String>, SrcRef, SrcRef) -> ExecResult<Value>;
+
+/// A built-in method bound to the value it was accessed on, such as `list.sort`.
+pub struct Method<R = Value> {
+    name: &'static str,
+    recv: R,
+    f: MethodFn<R>,
+}
+
+impl<R: 'static> Method<R> {
+    pub(super) fn new(name: &'static str, recv: R, f: MethodFn<R>) -> Self {
+        Self { name, recv, f }
+    }
+}
+
+impl<R: 'static> Obj for Method<R> {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<method '{}'>", self.name))
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.name.to_string())
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        (self.f)(&self.recv, args, ctx, src, r_caller, r_args)
+            .map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))
+    }
+}
+
+/// A function with its leading arguments fixed, as made by `f.bind(1, 2)` or `partial(f, 1, 2)`. Calling it calls the
+/// function with those arguments followed by the ones it was given.
+pub struct Bound {
+    f: Value,
+    args: Vec<Value>,
+}
+
+impl Obj for Bound {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<bound {}>", self.f.get_display_text()?.trim_start_matches('<').trim_end_matches('>')))
+    }
+
+    fn arity(&self) -> Option<usize> {
+        self.f.arity().map(|arity| arity.saturating_sub(self.args.len()))
+    }
+
+    fn name(&self) -> Option<String> {
+        self.f.fn_name()
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let mut all = self.args.clone();
+        all.extend(args);
+        self.f.call(all, ctx, src, r_caller, r_args)
+    }
+}
+
+/// Two functions applied one after the other, as made by `f >> g` or `compose(f, g)`. Calling it calls the first with
+/// the arguments it was given, then the second with what the first produced.
+pub struct Composed {
+    first: Value,
+    then: Value,
+}
+
+impl Composed {
+    pub(super) fn new(first: Value, then: Value) -> Self {
+        Self { first, then }
+    }
+}
+
+impl Obj for Composed {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("{} >> {}", self.first.get_display_text()?, self.then.get_display_text()?))
+    }
+
+    fn arity(&self) -> Option<usize> {
+        self.first.arity()
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let val = self.first.call(args, ctx, src, r_caller, r_args)?;
+        self.then.call(vec![val], ctx, src, r_caller, r_args)
+    }
+}
+
+pub(crate) fn find_method(recv: &Value, name: &str) -> Option<Value> {
+    let (name, f): (_, MethodFn) = match (recv, name) {
+        (Value::List(_), "sort") => ("sort", list_sort),
+        (Value::List(_), "binary_search") => ("binary_search", list_binary_search),
+        (Value::List(_), "dedup") => ("dedup", list_dedup),
+        (Value::List(_), "push") => ("push", list_push),
+        (Value::List(_), "pop") => ("pop", list_pop),
+        (Value::List(_), "insert") => ("insert", list_insert),
+        (Value::List(_), "remove") => ("remove", list_remove),
+        (Value::List(_), "len") => ("len", list_len),
+        (Value::List(_), "sort_by") => ("sort_by", list_sort_by),
+        (Value::List(_), "map") => ("map", list_map),
+        (Value::List(_), "filter") => ("filter", list_filter),
+        (Value::List(_), "fold") => ("fold", list_fold),
+        (Value::List(_), "reverse") => ("reverse", list_reverse),
+        (Value::List(_), "contains") => ("contains", list_contains),
+        (Value::List(_), "join") => ("join", list_join),
+        (Value::Map(_), "keys") => ("keys", map_keys),
+        (Value::Map(_), "values") => ("values", map_values),
+        (Value::Map(_), "len") => ("len", map_len),
+        (Value::Map(_), "contains_key") => ("contains_key", map_contains_key),
+        (Value::Map(_), "remove") => ("remove", map_remove),
+        (Value::Map(_), "merge") => ("merge", map_merge),
+        (Value::Map(_), "get") => ("get", map_get),
+        (Value::Fn(..), "bind") => ("bind", fn_bind),
+        (f, "apply") if f.is_function() => ("apply", fn_apply),
+        (Value::String(_), "len") => ("len", string_len),
+        (Value::String(_), "upper") => ("upper", string_upper),
+        (Value::String(_), "lower") => ("lower", string_lower),
+        (Value::String(_), "trim") => ("trim", string_trim),
+        (Value::String(_), "split") => ("split", string_split),
+        (Value::String(_), "contains") => ("contains", string_contains),
+        (Value::String(_), "starts_with") => ("starts_with", string_starts_with),
+        (Value::String(_), "replace") => ("replace", string_replace),
+        (Value::String(_), "chars") => ("chars", string_chars),
+        (Value::String(_), "find") => ("find", string_find),
+        _ => return None,
+    };
+    Some(Value::from(Method::new(name, recv.clone(), f)))
+}
+
+pub(super) fn check_arg_num(args: &[Value], min: usize, max: usize, r_args: SrcRef) -> ExecResult<()> {
+    if args.len() < min || args.len() > max {
+        Err(ExecError::At(r_args, Box::new(ExecError::WrongArgNum(None, if args.len() < min { min } else { max }, args.len()))))
+    } else {
+        Ok(())
+    }
+}
+
+// The ordering used when no comparison function is given, as determined by the `<` operator
+pub(super) fn natural_cmp(a: &Value, b: &Value, refs: BinaryOpRef) -> ExecResult<Ordering> {
+    if a.eval_less(b, refs)?.eval_truth(refs.op)? {
+        Ok(Ordering::Less)
+    } else if b.eval_less(a, refs)?.eval_truth(refs.op)? {
+        Ok(Ordering::Greater)
+    } else {
+        Ok(Ordering::Equal)
+    }
+}
+
+// Comparison functions may return a number (negative, zero or positive) or a bool (whether the first argument should
+// come before the second)
+fn custom_cmp(f: &Value, a: &Value, b: &Value, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Ordering> {
+    match f.call(vec![a.clone(), b.clone()], ctx, src, r_caller, r_args)? {
+        Value::Int(x) => Ok(x.cmp(&0)),
+        Value::Float(x) => Ok(x.partial_cmp(&0.0).unwrap_or(Ordering::Equal)),
+        Value::Boolean(true) => Ok(Ordering::Less),
+        Value::Boolean(false) => match f.call(vec![b.clone(), a.clone()], ctx, src, r_caller, r_args)? {
+            Value::Boolean(true) => Ok(Ordering::Greater),
+            _ => Ok(Ordering::Equal),
+        },
+        val => Err(ExecError::At(r_args, Box::new(ExecError::NotNumeric(val.get_type_name())))),
+    }
+}
+
+fn list_sort(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 1, r_args)?;
+    let l = match recv {
+        Value::List(l) => l,
+        _ => unreachable!(),
+    };
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+
+    // Sort a copy so that the comparison function may freely access the list
+    let mut items = l.borrow().to_vec();
+    let mut error = None;
+    // `sort_by` is a stable sort
+    items.sort_by(|a, b| if error.is_some() {
+        Ordering::Equal
+    } else {
+        match args.get(0) {
+            Some(f) => custom_cmp(f, a, b, ctx, src, r_caller, r_args),
+            None => natural_cmp(a, b, refs),
+        }.unwrap_or_else(|err| {
+            error = Some(err);
+            Ordering::Equal
+        })
+    });
+    if let Some(err) = error {
+        return Err(err);
+    }
+
+    l.borrow_mut().modify(|old| *old = items);
+    Ok(Value::Null)
+}
+
+fn list_binary_search(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let l = match recv {
+        Value::List(l) => l.borrow(),
+        _ => unreachable!(),
+    };
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+
+    let (mut lo, mut hi) = (0, l.len());
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        match natural_cmp(&l[mid], &args[0], refs)? {
+            Ordering::Less => lo = mid + 1,
+            Ordering::Greater => hi = mid,
+            Ordering::Equal => return Ok(Value::Int(mid as i64)),
+        }
+    }
+    Ok(Value::Null)
+}
+
+fn list_dedup(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    match recv {
+        Value::List(l) => l.borrow_mut().modify(|items| items.dedup_by(|a, b| Key::structural(a) == Key::structural(b))),
+        _ => unreachable!(),
+    }
+    Ok(Value::Null)
+}
+
+fn fn_bind(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, _r_args: SrcRef) -> ExecResult<Value> {
+    Ok(Value::from(Bound { f: recv.clone(), args }))
+}
+
+// `f.apply([a, b])` calls `f(a, b)`, for when the arguments were put together as a list
+fn fn_apply(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    match &args[0] {
+        Value::List(l) => {
+            let args = l.borrow().to_vec();
+            recv.call(args, ctx, src, r_caller, r_args)
+        },
+        val => Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    }
+}
+
+// Unlike `bind`, this also works for builtins and for functions that are already bound
+pub(super) fn builtin_partial(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, usize::MAX, r_args)?;
+    let mut args = args.into_iter();
+    let f = args.next().unwrap();
+    if !f.is_function() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::CannotCall(f.get_type_name()))));
+    }
+    Ok(Value::from(Bound { f, args: args.collect() }))
+}
+
+// `compose(f, g, h)` applies `f`, then `g`, then `h`
+pub(super) fn builtin_compose(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, usize::MAX, r_args)?;
+    if let Some(f) = args.iter().find(|f| !f.is_function()) {
+        return Err(ExecError::At(r_args, Box::new(ExecError::CannotCall(f.get_type_name()))));
+    }
+    let mut args = args.into_iter();
+    let first = args.next().unwrap();
+    Ok(args.fold(first, |first, then| Value::from(Composed::new(first, then))))
+}
+
+fn list_recv(recv: &Value) -> &Rc<RefCell<List>> {
+    match recv {
+        Value::List(l) => l,
+        _ => unreachable!(),
+    }
+}
+
+fn list_push(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    list_recv(recv).borrow_mut().push(args.into_iter().next().unwrap());
+    Ok(Value::Null)
+}
+
+// The last item, which is removed, or null if the list is empty
+fn list_pop(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(list_recv(recv).borrow_mut().modify(|items| items.pop()).unwrap_or(Value::Null))
+}
+
+// Indices may count back from the end, as when indexing. Inserting at the length of the list adds to the end.
+fn list_insert(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 2, 2, r_args)?;
+    let mut l = list_recv(recv).borrow_mut();
+    let idx = match &args[0] {
+        Value::Int(i) => resolve_index(*i, l.len()).filter(|i| *i <= l.len()),
+        _ => None,
+    }.ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::InvalidIndex(recv.get_type_name(), args[0].clone()))))?;
+    l.modify(|items| items.insert(idx, args[1].clone()));
+    Ok(Value::Null)
+}
+
+fn list_remove(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let mut l = list_recv(recv).borrow_mut();
+    let idx = match &args[0] {
+        Value::Int(i) => resolve_index(*i, l.len()).filter(|i| *i < l.len()),
+        _ => None,
+    }.ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::InvalidIndex(recv.get_type_name(), args[0].clone()))))?;
+    Ok(l.modify(|items| items.remove(idx)))
+}
+
+fn list_len(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Int(list_recv(recv).borrow().len() as i64))
+}
+
+// Sorts by the keys that the function produces for each item, which are compared as `sort` compares items
+fn list_sort_by(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let l = list_recv(recv);
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+
+    // Each key is found once, before sorting, so that the function is called once per item
+    let items = l.borrow().to_vec();
+    let mut keyed = items
+        .into_iter()
+        .map(|item| Ok((args[0].call(vec![item.clone()], ctx, src, r_caller, r_args)?, item)))
+        .collect::<ExecResult<Vec<_>>>()?;
+    let mut error = None;
+    keyed.sort_by(|(a, _), (b, _)| if error.is_some() {
+        Ordering::Equal
+    } else {
+        natural_cmp(a, b, refs).unwrap_or_else(|err| {
+            error = Some(err);
+            Ordering::Equal
+        })
+    });
+    if let Some(err) = error {
+        return Err(err);
+    }
+
+    l.borrow_mut().modify(|old| *old = keyed.into_iter().map(|(_, item)| item).collect());
+    Ok(Value::Null)
+}
+
+// The functions given to `map`, `filter` and `fold` see a copy of the items, so they may freely change the list
+
+fn list_map(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let items = list_recv(recv).borrow().to_vec();
+    let mapped = items
+        .into_iter()
+        .map(|item| args[0].call(vec![item], ctx, src, r_caller, r_args))
+        .collect::<ExecResult<Vec<_>>>()?;
+    Ok(Value::from(mapped))
+}
+
+fn list_filter(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let items = list_recv(recv).borrow().to_vec();
+    let mut kept = Vec::new();
+    for item in items {
+        if args[0].call(vec![item.clone()], ctx, src, r_caller, r_args)?.eval_truth(r_args)? {
+            kept.push(item);
+        }
+    }
+    Ok(Value::from(kept))
+}
+
+fn list_arg(val: &Value, r_args: SrcRef) -> ExecResult<Vec<Value>> {
+    match val {
+        Value::List(l) => Ok(l.borrow().to_vec()),
+        val => Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    }
+}
+
+// The first of the keys seen so far that has the same contents as this one, so that lists and maps with equal items
+// are grouped together even though they are different values
+fn first_equal(firsts: &mut HashMap<Key, Value>, key: Value) -> Value {
+    firsts.entry(Key::structural(&key)).or_insert(key).clone()
+}
+
+// `group_by(xs, f)` makes a map from each `f(x)` to the items that produced it, in the order they came in
+pub(super) fn builtin_group_by(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 2, 2, r_args)?;
+    let mut groups = Map::new();
+    let mut firsts = HashMap::new();
+    for item in list_arg(&args[0], r_args)? {
+        let key = args[1].call(vec![item.clone()], ctx, src, r_caller, r_args)?;
+        let key = first_equal(&mut firsts, key);
+        match groups.get_mut(&key) {
+            Some(Value::List(group)) => group.borrow_mut().push(item),
+            _ => { groups.insert(key, Value::from(vec![item])); },
+        }
+    }
+    Ok(Value::new_map(groups))
+}
+
+// `count_by(xs, f)` makes a map from each `f(x)` to how many items produced it
+pub(super) fn builtin_count_by(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 2, 2, r_args)?;
+    let mut counts = Map::new();
+    let mut firsts = HashMap::new();
+    for item in list_arg(&args[0], r_args)? {
+        let key = args[1].call(vec![item], ctx, src, r_caller, r_args)?;
+        let key = first_equal(&mut firsts, key);
+        match counts.get_mut(&key) {
+            Some(Value::Int(count)) => *count += 1,
+            _ => { counts.insert(key, Value::Int(1)); },
+        }
+    }
+    Ok(Value::new_map(counts))
+}
+
+// `unique(xs)` keeps the first of each set of equal items, unlike `dedup`, which only removes those next to each other
+pub(super) fn builtin_unique(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let mut seen = HashSet::new();
+    let mut kept = Vec::new();
+    for item in list_arg(&args[0], r_args)? {
+        if seen.insert(Key::structural(&item)) {
+            kept.push(item);
+        }
+    }
+    Ok(Value::from(kept))
+}
+
+fn list_fold(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 2, 2, r_args)?;
+    let items = list_recv(recv).borrow().to_vec();
+    items
+        .into_iter()
+        .try_fold(args[0].clone(), |acc, item| args[1].call(vec![acc, item], ctx, src, r_caller, r_args))
+}
+
+fn list_reverse(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    list_recv(recv).borrow_mut().modify(|items| items.reverse());
+    Ok(Value::Null)
+}
+
+// Items are compared with `==`, so types that overload it are compared as they choose
+fn list_contains(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let refs = BinaryOpRef { op: r_caller, left: r_caller, right: r_args };
+    let items = list_recv(recv).borrow().to_vec();
+    for item in items {
+        if item.eval_eq(&args[0], refs)?.eval_truth(r_caller)? {
+            return Ok(Value::Boolean(true));
+        }
+    }
+    Ok(Value::Boolean(false))
+}
+
+// Items are shown as `print` would show them, with the separator (if any) between each
+fn list_join(recv: &Value, args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 1, r_args)?;
+    let sep = match args.get(0) {
+        Some(sep) => pattern_arg(sep, r_args)?,
+        None => String::new(),
+    };
+    let items = list_recv(recv).borrow().to_vec();
+    let texts = items
+        .iter()
+        .map(|item| item.get_display_text_with(&ctx.display))
+        .collect::<ExecResult<Vec<_>>>()
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::from(texts.join(&sep)))
+}
+
+fn map_recv(recv: &Value) -> &Rc<RefCell<Map>> {
+    match recv {
+        Value::Map(m) => m,
+        _ => unreachable!(),
+    }
+}
+
+// Keys and values are listed in the order that the keys were added to the map
+fn map_keys(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(map_recv(recv).borrow().keys().cloned().collect::<Vec<_>>()))
+}
+
+fn map_values(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(map_recv(recv).borrow().values().cloned().collect::<Vec<_>>()))
+}
+
+fn map_len(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Int(map_recv(recv).borrow().len() as i64))
+}
+
+fn map_contains_key(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    Ok(Value::Boolean(map_recv(recv).borrow().contains_key(&args[0])))
+}
+
+// The value of the key that was removed, or null if there was none
+fn map_remove(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    Ok(map_recv(recv).borrow_mut().remove(&args[0]).unwrap_or(Value::Null))
+}
+
+// A new map with the items of both, taking the value of the other map for keys that are in each
+fn map_merge(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let other = match &args[0] {
+        Value::Map(other) => other.borrow().clone(),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("map"), val.get_type_name())))),
+    };
+    let mut merged = map_recv(recv).borrow().clone();
+    merged.extend(other);
+    Ok(Value::new_map(merged))
+}
+
+fn map_get(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 2, r_args)?;
+    Ok(map_recv(recv).borrow().get(&args[0]).cloned().or_else(|| args.get(1).cloned()).unwrap_or(Value::Null))
+}
+
+// Strings are indexed by character rather than by byte, so lengths and positions count characters too
+fn string_recv(recv: &Value) -> String {
+    match recv {
+        Value::String(s) => s.borrow().clone(),
+        _ => unreachable!(),
+    }
+}
+
+// What to look for in a string may be given as a string or as a single character
+fn pattern_arg(val: &Value, r_args: SrcRef) -> ExecResult<String> {
+    match val {
+        Value::Char(c) => Ok(c.to_string()),
+        val => string_arg(val, r_args),
+    }
+}
+
+fn string_len(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Int(string_recv(recv).chars().count() as i64))
+}
+
+fn string_upper(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(string_recv(recv).to_uppercase()))
+}
+
+fn string_lower(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(string_recv(recv).to_lowercase()))
+}
+
+fn string_trim(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(string_recv(recv).trim()))
+}
+
+// Without a separator, the string is split wherever there is whitespace and the empty parts are left out
+fn string_split(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 1, r_args)?;
+    let s = string_recv(recv);
+    let parts = match args.get(0) {
+        Some(sep) => {
+            let sep = pattern_arg(sep, r_args)?;
+            if sep.is_empty() {
+                return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Cannot split a string by an empty separator; use chars() to split it into characters.")))));
+            }
+            s.split(sep.as_str()).map(Value::from).collect::<Vec<_>>()
+        },
+        None => s.split_whitespace().map(Value::from).collect(),
+    };
+    Ok(Value::from(parts))
+}
+
+fn string_contains(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    Ok(Value::Boolean(string_recv(recv).contains(pattern_arg(&args[0], r_args)?.as_str())))
+}
+
+fn string_starts_with(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    Ok(Value::Boolean(string_recv(recv).starts_with(pattern_arg(&args[0], r_args)?.as_str())))
+}
+
+// Every occurrence is replaced, and the string itself is left unchanged
+fn string_replace(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 2, 2, r_args)?;
+    let from = pattern_arg(&args[0], r_args)?;
+    let to = pattern_arg(&args[1], r_args)?;
+    if from.is_empty() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Cannot replace an empty string.")))));
+    }
+    Ok(Value::from(string_recv(recv).replace(from.as_str(), &to)))
+}
+
+fn string_chars(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::from(string_recv(recv).chars().map(Value::Char).collect::<Vec<_>>()))
+}
+
+// The index of the first character of the first occurrence, or null if there is none
+fn string_find(recv: &Value, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    check_arg_num(&args, 1, 1, r_args)?;
+    let s = string_recv(recv);
+    Ok(s.find(pattern_arg(&args[0], r_args)?.as_str())
+        .map(|i| Value::Int(s[..i].chars().count() as i64))
+        .unwrap_or(Value::Null))
+}
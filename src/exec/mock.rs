@@ -0,0 +1,123 @@
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    rc::Rc,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    builtins::string_arg,
+    method::{self, Method, MethodFn},
+};
+
+/// The functions that have been replaced by `mock` or watched by `spy`, by the names they are called by, such as
+/// `fs.read` or the name a host function was registered under.
+pub(crate) type Mocks = HashMap<String, Rc<Mock>>;
+
+/// A function that has been mocked or spied on, along with every call made to it since.
+pub struct Mock {
+    name: String,
+    // The function called in place of the original, or `None` for spies, which call the original
+    replacement: Option<Value>,
+    calls: RefCell<Vec<Vec<Value>>>,
+}
+
+impl Mock {
+    /// Record a call to the function that this mocks, then call the replacement, or the original for spies.
+    pub(super) fn call<F>(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef, original: F) -> ExecResult<Value>
+        where F: FnOnce(Vec<Value>, &mut ExecCtx) -> ExecResult<Value>
+    {
+        self.calls.borrow_mut().push(args.clone());
+        match &self.replacement {
+            Some(replacement) => replacement.call(args, ctx, src, r_caller, r_args),
+            None => original(args, ctx),
+        }
+    }
+}
+
+/// The mock of the function with this name, if it has one. Builtins in namespaces are named `<namespace>.<name>`.
+pub(super) fn find(ctx: &ExecCtx, namespace: Option<&str>, name: &str) -> Option<Rc<Mock>> {
+    let mocks = ctx.mocks.as_ref().filter(|mocks| !mocks.is_empty())?;
+    match namespace {
+        Some(namespace) => mocks.get(&format!("{}.{}", namespace, name)),
+        None => mocks.get(name),
+    }.cloned()
+}
+
+/// The handle returned by `mock` and `spy`, through which tests see the calls that were made.
+#[derive(Clone)]
+pub struct MockHandle(Rc<Mock>);
+
+impl Obj for MockHandle {
+    fn get_type_name(&self) -> String {
+        String::from("mock")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<mock '{}'>", self.0.name))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<MockHandle>) = match name {
+            "calls" => return Ok(Value::from(self.0.calls.borrow().iter().cloned().map(Value::from).collect::<Vec<_>>())),
+            "count" => return Ok(Value::from(self.0.calls.borrow().len())),
+            "called_with" => ("called_with", mock_called_with),
+            "reset" => ("reset", mock_reset),
+            "restore" => ("restore", mock_restore),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+}
+
+fn mock_called_with(this: &MockHandle, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, _r_args: SrcRef) -> ExecResult<Value> {
+    Ok(Value::Boolean(this.0.calls.borrow().iter().any(|call| *call == args)))
+}
+
+fn mock_reset(this: &MockHandle, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    this.0.calls.borrow_mut().clear();
+    Ok(Value::Null)
+}
+
+// Calls made before the mock was restored can still be looked at afterwards
+fn mock_restore(this: &MockHandle, args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    if let Some(mocks) = &mut ctx.mocks {
+        if mocks.get(&this.0.name).map(|mock| Rc::ptr_eq(mock, &this.0)).unwrap_or(false) {
+            mocks.remove(&this.0.name);
+        }
+    }
+    Ok(Value::Null)
+}
+
+fn install(name: String, replacement: Option<Value>, ctx: &mut ExecCtx, r_args: SrcRef) -> ExecResult<Value> {
+    let mocks = ctx.mocks.as_mut().ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(String::from(
+        "Mocks are not enabled. Run the script with 'forge test' to use them.",
+    )))))?;
+    let mock = Rc::new(Mock { name: name.clone(), replacement, calls: RefCell::new(Vec::new()) });
+    mocks.insert(name, mock.clone());
+    Ok(Value::from(MockHandle(mock)))
+}
+
+/// Replace a builtin or host function with a function of the script's own until the test ends, recording each call.
+pub(super) fn builtin_mock(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let name = string_arg(&args[0], r_args)?;
+    let replacement = args[1].clone();
+    if replacement.get_type_name() != "function" {
+        return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("function"), replacement.get_type_name()))));
+    }
+    install(name, Some(replacement), ctx, r_args)
+}
+
+/// Record each call to a builtin or host function until the test ends, while still calling it.
+pub(super) fn builtin_spy(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let name = string_arg(&args[0], r_args)?;
+    install(name, None, ctx, r_args)
+}
@@ -1,6 +1,55 @@
 mod block_scope;
+mod builtins;
+mod check;
+mod class;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compress;
+mod config;
+mod crypto;
+mod ctx;
+mod diff;
+mod env;
+mod error_value;
+mod expect;
+mod format;
+mod fs;
+mod gc;
 mod global_scope;
+mod heap;
+mod interrupt;
+mod list;
+mod map;
+mod markup;
+mod math;
+mod memo;
+mod method;
+mod mock;
+mod module;
+mod native;
+pub(crate) mod overload;
+mod pattern;
+mod prompt;
+mod range;
+mod reflect;
+#[cfg(feature = "regex")]
+mod regex;
+mod replay;
+mod resource;
+mod rng;
+mod semver;
+#[cfg(any(feature = "msgpack", feature = "cbor"))]
+mod serial;
+#[cfg(feature = "sqlite")]
+mod sql;
+mod stats;
+mod sys;
+mod term;
+mod time;
+mod url;
 mod value;
+mod weak;
+#[cfg(feature = "net")]
+mod ws;
 
 // Reexports
 pub use self::{
@@ -8,20 +57,61 @@ pub use self::{
         Value,
         Type,
         ForgeIter,
+        PairIter,
     },
+    range::Bounds,
     global_scope::GlobalScope,
+    gc::{
+        collect as collect_cycles,
+        snapshot as heap_snapshot,
+        HeapSnapshot,
+        HeapGroup,
+        DEFAULT_THRESHOLD as DEFAULT_GC_THRESHOLD,
+    },
+    env::Env,
+    native::NativeFn,
+    error_value::ErrorValue,
+    heap::Heap,
+    weak::WeakRef,
+    interrupt::InterruptHandle,
+    fs::FileHandle,
+    resource::Resource,
+    replay::Recording,
+    rng::Rng,
+    time::{
+        Duration,
+        DateTime,
+    },
+    module::{
+        Module,
+        ModuleLoader,
+    },
+    list::{
+        List,
+        ListIter,
+    },
+    map::Map,
+    ctx::{
+        DEFAULT_MAX_CALL_DEPTH,
+        ExecCtx,
+        DisplayOptions,
+        IntFormat,
+        Permissions,
+    },
 };
 
 use std::{
     fmt,
+    mem,
     io::{self, prelude::*},
     rc::Rc,
     any::Any,
     cell::RefCell,
 };
-use hashbrown::HashMap;
 use crate::{
-    output,
+    output::{self, DiagnosticStyle, Json},
+    error::ForgeError,
+    stack,
     parser::{
         SrcRef,
         ast::{
@@ -30,10 +120,20 @@ use crate::{
             Stmt,
             Block,
             Node,
+            StringPart,
         },
     },
 };
 use block_scope::BlockScope;
+pub(crate) use module::import;
+pub(crate) use expect::Snapshots;
+pub(crate) use mock::Mocks;
+pub(crate) use replay::Replay;
+pub(crate) use diff::changed_lines;
+pub(crate) use gc::enter_site;
+pub(crate) use builtins::find_engine_builtin;
+pub(crate) use resource::Resources;
+pub(crate) use value::TailCall;
 
 #[derive(Debug)]
 pub enum ExecError {
@@ -46,8 +146,10 @@ pub enum ExecError {
     CannotIndex(SrcRef, String, String),
     CannotIndexAssign(SrcRef, String, String),
     WrongArgNum(Option<SrcRef>, usize, usize),
+    WrongArgType(String, String),
     CannotDisplay(String),
     CouldNotParse(String),
+    CannotConvert(String, &'static str),
     Io(io::Error),
     CannotDetermineTruthiness(SrcRef, String),
     UnaryOp {
@@ -59,127 +161,271 @@ pub enum ExecError {
         op: &'static str,
         left_type: String,
         right_type: String,
+        left_val: Option<String>,
+        right_val: Option<String>,
         refs: BinaryOpRef,
     },
     NoSuchItem(String),
     ItemExists(String),
+    NoSuchMember(String, String),
+    Custom(String),
+    Import(String, Box<ForgeError>),
+    ImportCycle(Vec<String>),
+    PermissionDenied(&'static str),
+    Interrupted,
+    TooDeep,
+    StackOverflow(SrcRef),
+    OutOfFuel,
+    TooManyItems(usize),
+    // Something that should not be possible happened, such as the VM finding too few values on its stack
+    Internal(&'static str),
+    ExpectationFailed {
+        message: String,
+        differences: Vec<String>,
+        r_actual: SrcRef,
+        r_expected: Option<SrcRef>,
+    },
+    InFn(Option<String>, SrcRef, Box<ExecError>),
     At(SrcRef, Box<ExecError>),
     WithSrc(Rc<String>, Box<ExecError>),
     WithPrevSrc(Rc<String>, Box<ExecError>),
 }
 
 impl ExecError {
-    pub fn fmt_nice_located(&self, f: &mut fmt::Formatter, src: Option<&str>, psrc: Option<&str>, depth: usize, r: SrcRef) -> fmt::Result {
-        writeln!(f, "[ERROR] Runtime error at {}...", r.start())?;
+    /// A short, single-line description of the error.
+    pub fn message(&self) -> String {
         match self {
-            ExecError::NotAType => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Expression is not a type.", output::Repeat(' ', (depth + 1) * 3)))
-            },
+            ExecError::NotAType => format!("Expression is not a type."),
             ExecError::InvalidIndex(ty, val) => {
                 let val = val.get_display_text().unwrap_or("<cannot display value>".to_string());
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Invalid index '{}' used to index value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), val, ty))
-            },
-            ExecError::NotIterator => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Yielded value is not an iterator.", output::Repeat(' ', (depth + 1) * 3)))
-            },
-            ExecError::NotNumeric(s) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Value of type '{}' is not numeric.", output::Repeat(' ', (depth + 1) * 3), s))
-            },
-            ExecError::NotIterable(s) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Value of type '{}' is not iterable.", output::Repeat(' ', (depth + 1) * 3), s))
+                format!("Invalid index '{}' used to index value of type '{}'.", val, ty)
             },
-            ExecError::CannotIndex(r_index, ty, ty_index) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| output::fmt_ref(f, *r_index, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot index value of type '{}' with value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), ty, ty_index))
-            },
-            ExecError::CannotIndexAssign(r_index, ty, ty_rvalue) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| output::fmt_ref(f, *r_index, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot assign index of value of type '{}' as value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), ty, ty_rvalue))
+            ExecError::NotIterator => format!("Yielded value is not an iterator."),
+            ExecError::NotNumeric(s) => format!("Value of type '{}' is not numeric.", s),
+            ExecError::NotIterable(s) => format!("Value of type '{}' is not iterable.", s),
+            ExecError::CannotIndex(_, ty, ty_index) => format!("Cannot index value of type '{}' with value of type '{}'.", ty, ty_index),
+            ExecError::CannotIndexAssign(_, ty, ty_rvalue) => format!("Cannot assign index of value of type '{}' as value of type '{}'.", ty, ty_rvalue),
+            ExecError::CannotCall(s) => format!("Cannot call value of type '{}'.", s),
+            ExecError::WrongArgNum(_, x, y) => format!("Tried to call a function with the wrong number of parameters. Expected {}, found {}.", x, y),
+            ExecError::WrongArgType(expected, found) => format!("Expected an argument of type '{}', found one of type '{}'.", expected, found),
+            ExecError::CannotDisplay(s) => format!("Cannot display value of type '{}'.", s),
+            ExecError::CouldNotParse(s) => format!("Could not parse '{}' into a value.", s),
+            ExecError::CannotConvert(ty, to) => format!("Cannot convert value of type '{}' into '{}'.", ty, to),
+            ExecError::Io(io) => format!("I/O error: {}.", io),
+            ExecError::CannotDetermineTruthiness(_, expr_type) => format!("Cannot determine the truthiness of value of type '{}'. Did you mean for this to be a bool?", expr_type),
+            ExecError::UnaryOp { op, expr_type, .. } => format!("Cannot apply unary operator '{}' to value of type '{}'.", op, expr_type),
+            ExecError::BinaryOp { op, left_type, right_type, .. } => format!("Cannot apply binary operator '{}' to values of types '{}' and '{}'.", op, left_type, right_type),
+            ExecError::NoSuchItem(item) => format!("Cannot find item '{}' within the current scope.", item),
+            ExecError::ItemExists(item) => format!("Item '{}' already exist in the current scope.", item),
+            ExecError::NoSuchMember(ty, name) => format!("Value of type '{}' has no member '{}'.", ty, name),
+            ExecError::Custom(msg) => msg.clone(),
+            ExecError::Import(path, _) => format!("Could not import module '{}'.", path),
+            ExecError::PermissionDenied(perm) => format!("This requires the '{}' permission, which the engine has not been granted.", perm),
+            ExecError::ImportCycle(cycle) => format!("Modules import each other in a cycle: {}.", cycle.join(" -> ")),
+            ExecError::Interrupted => format!("Execution was interrupted."),
+            ExecError::TooDeep => format!("Calls or expressions are nested too deeply, and would run out of stack space."),
+            ExecError::OutOfFuel => format!("The code ran out of fuel, having done as much work as the engine allows."),
+            ExecError::StackOverflow(_) => format!("Functions were called within each other more deeply than the engine allows."),
+            ExecError::TooManyItems(n) => format!("Cannot make a list of {} items, as there is not enough memory for it.", n),
+            ExecError::Internal(what) => format!("Internal error: {}. This is a bug in the engine.", what),
+            ExecError::ExpectationFailed { message, .. } => message.clone(),
+            ExecError::InFn(_, _, err) => err.message(),
+            ExecError::At(_, err) => err.message(),
+            ExecError::WithSrc(_, err) => err.message(),
+            ExecError::WithPrevSrc(_, err) => err.message(),
+        }
+    }
+
+    /// The most specific location in the source that the error refers to, if any.
+    pub fn src_ref(&self) -> Option<SrcRef> {
+        match self {
+            ExecError::CannotDetermineTruthiness(r, _) => Some(*r),
+            ExecError::UnaryOp { refs, .. } => Some(refs.op),
+            ExecError::BinaryOp { refs, .. } => Some(refs.op),
+            ExecError::ExpectationFailed { r_actual, .. } => Some(*r_actual),
+            ExecError::StackOverflow(r) => Some(*r),
+            ExecError::InFn(_, _, err) => err.src_ref(),
+            ExecError::At(r, err) => err.src_ref().or(Some(*r)),
+            ExecError::WithSrc(_, err) => err.src_ref(),
+            ExecError::WithPrevSrc(_, err) => err.src_ref(),
+            _ => None,
+        }
+    }
+
+    /// Whether the error stops the code from running altogether, having been raised by an interrupt or by running out
+    /// of fuel. `try` statements do not catch these, so that code cannot carry on regardless.
+    pub(crate) fn is_interrupt(&self) -> bool {
+        match self {
+            ExecError::Interrupted | ExecError::OutOfFuel => true,
+            ExecError::InFn(_, _, err) | ExecError::At(_, err) | ExecError::WithSrc(_, err) | ExecError::WithPrevSrc(_, err) => err.is_interrupt(),
+            _ => false,
+        }
+    }
+
+    /// A JSON object describing the error (see `ForgeError::to_json`).
+    pub(crate) fn to_json(&self) -> Json {
+        let mut trace = Vec::new();
+        let mut err = self;
+        loop {
+            match err {
+                ExecError::InFn(name, r_caller, inner) => {
+                    trace.push(Json::Object(vec![
+                        ("function", Json::from(name.clone())),
+                        ("called_at", Json::loc(r_caller.start())),
+                    ]));
+                    err = inner;
+                },
+                ExecError::At(_, inner) | ExecError::WithSrc(_, inner) | ExecError::WithPrevSrc(_, inner) => err = inner,
+                _ => break,
+            }
+        }
+        // Functions are listed innermost first, as when the error is displayed
+        trace.reverse();
+
+        let label = |message: String, r: SrcRef| Json::Object(vec![
+            ("message", Json::from(message)),
+            ("location", Json::span(r)),
+        ]);
+        let labels = match err {
+            ExecError::UnaryOp { refs, .. } => vec![label(String::from("operand"), refs.expr)],
+            ExecError::BinaryOp { left_type, right_type, refs, .. } => vec![
+                label(format!("Left operand is of type '{}'.", left_type), refs.left),
+                label(format!("Right operand is of type '{}'.", right_type), refs.right),
+            ],
+            ExecError::CannotIndex(r_index, _, _) | ExecError::CannotIndexAssign(r_index, _, _) => vec![label(String::from("index"), *r_index)],
+            ExecError::WrongArgNum(Some(r_args), _, _) => vec![label(String::from("parameters"), *r_args)],
+            ExecError::ExpectationFailed { differences, r_actual, r_expected, .. } => {
+                let mut labels = vec![label(String::from("actual value"), *r_actual)];
+                labels.extend(r_expected.map(|r_expected| label(String::from("expected value"), r_expected)));
+                labels.extend(differences.iter().map(|difference| label(difference.clone(), *r_actual)));
+                labels
             },
-            ExecError::CannotCall(s) => {
+            _ => Vec::new(),
+        };
+        let cause = match err {
+            ExecError::Import(_, err) => err.to_json_value(),
+            _ => Json::Null,
+        };
+
+        Json::Object(vec![
+            ("kind", Json::from(String::from("runtime"))),
+            ("message", Json::from(self.message())),
+            ("location", Json::from(self.src_ref().map(Json::span))),
+            ("labels", Json::Array(labels)),
+            ("trace", Json::Array(trace)),
+            ("cause", cause),
+        ])
+    }
+
+    pub fn fmt_nice_located(&self, f: &mut fmt::Formatter, src: Option<&str>, psrc: Option<&str>, depth: usize, style: DiagnosticStyle, r: SrcRef) -> fmt::Result {
+        match self {
+            ExecError::WithSrc(src, err) => return err.fmt_nice_located(f, Some(&src), psrc, depth, style, r),
+            ExecError::WithPrevSrc(psrc, err) => return err.fmt_nice_located(f, src, Some(&psrc), depth, style, r),
+            ExecError::At(r, err) => return err.fmt_nice_located(f, src, psrc, depth, style, *r),
+            // These carry their own, more precise, locations
+            ExecError::CannotDetermineTruthiness(_, _) | ExecError::UnaryOp { .. } | ExecError::BinaryOp { .. } | ExecError::ExpectationFailed { .. } | ExecError::InFn(_, _, _) => return self.fmt_nice(f, src, psrc, depth, style),
+            _ => {},
+        }
+
+        writeln!(f, "{} Runtime error at {}...", output::ErrorTag(style), r.start())?;
+        match self {
+            ExecError::CannotIndex(r_index, _, _) | ExecError::CannotIndexAssign(r_index, _, _) => {
                 Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot call value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), s))
+                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1, style))
+                    .and_then(|_| output::fmt_ref(f, *r_index, src, depth + 1, style))
             },
-            ExecError::WrongArgNum(r_args, x, y) => {
+            ExecError::WrongArgNum(r_args, _, _) => {
                 if let Some(r_args) = r_args {
-                    output::fmt_ref(f, *r_args, psrc, depth + 1)?;
+                    output::fmt_ref(f, *r_args, psrc, depth + 1, style)?;
                 }
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Tried to call a function with the wrong number of parameters. Expected {}, found {}.", output::Repeat(' ', (depth + 1) * 3), x, y))
-            },
-            ExecError::CannotDisplay(s) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot display value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), s))
-            },
-            ExecError::CouldNotParse(s) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Could not parse '{}' into a value.", output::Repeat(' ', (depth + 1) * 3), s))
+                output::fmt_ref(f, r, src, depth + 1, style)
             },
-            ExecError::Io(io) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}I/O error: {}.", output::Repeat(' ', (depth + 1) * 3), io))
-            },
-            ExecError::NoSuchItem(item) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot find item '{}' within the current scope.", output::Repeat(' ', (depth + 1) * 3), item))
-            },
-            ExecError::ItemExists(item) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Item '{}' already exist in the current scope.", output::Repeat(' ', (depth + 1) * 3), item))
+            _ => output::fmt_ref(f, r, src, depth + 1, style),
+        }?;
+        writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), self.message())?;
+        match self {
+            // Errors that occurred before the module's source could be read have nowhere to point to
+            ExecError::Import(_, err) => match &**err {
+                ForgeError::Exec(err) => writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), err.message()),
+                err => write!(f, "{}", err.display_with(style)),
             },
-            ExecError::WithSrc(src, err) => err.fmt_nice_located(f, Some(&src), psrc, depth, r),
-            ExecError::WithPrevSrc(psrc, err) => err.fmt_nice_located(f, src, Some(&psrc), depth, r),
-            ExecError::At(r, err) => err.fmt_nice_located(f, src, psrc, depth, *r),
             _ => Ok(()),
         }
     }
 
-    pub fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, psrc: Option<&str>, depth: usize) -> fmt::Result {
+    pub fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, psrc: Option<&str>, depth: usize, style: DiagnosticStyle) -> fmt::Result {
         match self {
-            ExecError::CannotDetermineTruthiness(r, expr_type) => {
+            ExecError::CannotDetermineTruthiness(r, _) => {
+                Ok(())
+                    .and_then(|_| writeln!(f, "{} Runtime error at {}...", output::ErrorTag(style), r.start()))
+                    .and_then(|_| output::fmt_ref(f, *r, src, depth + 1, style))
+                    .and_then(|_| writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), self.message()))
+            },
+            ExecError::UnaryOp { refs, .. } => {
+                Ok(())
+                    .and_then(|_| writeln!(f, "{} Runtime error at {}...", output::ErrorTag(style), refs.op.start()))
+                    .and_then(|_| output::fmt_ref(f, refs.op, src, depth + 1, style))
+                    .and_then(|_| output::fmt_ref(f, refs.expr, src, depth + 1, style))
+                    .and_then(|_| writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), self.message()))
+            },
+            ExecError::BinaryOp { left_type, right_type, left_val, right_val, refs, .. } => {
+                let fmt_operand = |f: &mut fmt::Formatter, side, r, ty, val: &Option<String>| Ok(())
+                    .and_then(|_| output::fmt_ref(f, r, src, depth + 2, style))
+                    .and_then(|_| match val {
+                        Some(val) => writeln!(f, "{}{} operand is {} of type '{}'.", output::Repeat(' ', (depth + 2) * 3), side, val, ty),
+                        None => writeln!(f, "{}{} operand is of type '{}'.", output::Repeat(' ', (depth + 2) * 3), side, ty),
+                    });
                 Ok(())
-                    .and_then(|_| writeln!(f, "[ERROR] Runtime error at {}...", r.start()))
-                    .and_then(|_| output::fmt_ref(f, *r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot determine the truthiness of value of type '{}'. Did you mean for this to be a bool?", output::Repeat(' ', (depth + 1) * 3), expr_type))
+                    .and_then(|_| writeln!(f, "{} Runtime error at {}...", output::ErrorTag(style), refs.op.start()))
+                    .and_then(|_| output::fmt_ref(f, refs.op, src, depth + 1, style))
+                    .and_then(|_| writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), self.message()))
+                    .and_then(|_| fmt_operand(f, "Left", refs.left, left_type, left_val))
+                    .and_then(|_| fmt_operand(f, "Right", refs.right, right_type, right_val))
             },
-            ExecError::UnaryOp { op, expr_type, refs } => {
+            // Each way in which the values differ is listed beneath the message
+            ExecError::ExpectationFailed { differences, r_actual, r_expected, .. } => {
                 Ok(())
-                    .and_then(|_| writeln!(f, "[ERROR] Runtime error at {}...", refs.op.start()))
-                    .and_then(|_| output::fmt_ref(f, refs.op, src, depth + 1))
-                    .and_then(|_| output::fmt_ref(f, refs.expr, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot apply unary operator '{}' to value of type '{}'.", output::Repeat(' ', (depth + 1) * 3), op, expr_type))
+                    .and_then(|_| writeln!(f, "{} Runtime error at {}...", output::ErrorTag(style), r_actual.start()))
+                    .and_then(|_| output::fmt_ref(f, *r_actual, src, depth + 1, style))
+                    .and_then(|_| match r_expected {
+                        Some(r_expected) => output::fmt_ref(f, *r_expected, src, depth + 1, style),
+                        None => Ok(()),
+                    })
+                    .and_then(|_| writeln!(f, "{}{}", output::Repeat(' ', (depth + 1) * 3), self.message()))
+                    .and_then(|_| differences
+                        .iter()
+                        .try_for_each(|difference| writeln!(f, "{}{}", output::Repeat(' ', (depth + 2) * 3), difference)))
             },
-            ExecError::BinaryOp { op, left_type, right_type, refs } => {
+            // Each function that the error passed through is listed after it, innermost first. Recursion passes through
+            // the same call many times over, which is listed once.
+            ExecError::InFn(name, r_caller, err) => {
+                let (mut err, mut src, mut psrc, mut times) = (&**err, src, psrc, 1);
+                loop {
+                    match without_src(err, src, psrc) {
+                        (ExecError::InFn(inner_name, r_inner, inner), inner_src, inner_psrc)
+                            if inner_name == name && r_inner.start() == r_caller.start() =>
+                        {
+                            err = inner;
+                            src = inner_src;
+                            psrc = inner_psrc;
+                            times += 1;
+                        },
+                        _ => break,
+                    }
+                }
+                let name = match name {
+                    Some(name) => format!("function '{}'", name),
+                    None => String::from("anonymous function"),
+                };
+                let times = if times > 1 { format!(" ({} times)", times) } else { String::new() };
                 Ok(())
-                    .and_then(|_| writeln!(f, "[ERROR] Runtime error at {}...", refs.op.start()))
-                    .and_then(|_| output::fmt_ref(f, refs.left, src, depth + 1))
-                    .and_then(|_| output::fmt_ref(f, refs.right, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Cannot apply binary operator '{}' to values of types '{}' and '{}'.", output::Repeat(' ', (depth + 1) * 3), op, left_type, right_type))
-            },
-            ExecError::At(r, err) => err.fmt_nice_located(f, src, psrc, depth, *r),
-            ExecError::WithSrc(src, err) => err.fmt_nice(f, Some(&src), psrc, depth),
-            ExecError::WithPrevSrc(psrc, err) => err.fmt_nice(f, src, Some(&psrc), depth),
+                    .and_then(|_| err.fmt_nice(f, src, psrc, depth, style))
+                    .and_then(|_| writeln!(f, "{}...in {}, called at {}{}.", output::Repeat(' ', (depth + 1) * 3), name, r_caller.start(), times))
+            },
+            ExecError::At(r, err) => err.fmt_nice_located(f, src, psrc, depth, style, *r),
+            ExecError::StackOverflow(r) => self.fmt_nice_located(f, src, psrc, depth, style, *r),
+            ExecError::WithSrc(src, err) => err.fmt_nice(f, Some(&src), psrc, depth, style),
+            ExecError::WithPrevSrc(psrc, err) => err.fmt_nice(f, src, Some(&psrc), depth, style),
             ExecError::Io(_) => Ok(()),
             ExecError::NotAType => Ok(()),
             ExecError::InvalidIndex(_, _) => Ok(()),
@@ -190,19 +436,94 @@ impl ExecError {
             ExecError::CannotIndexAssign(_, _, _) => Ok(()),
             ExecError::CannotCall(_) => Ok(()),
             ExecError::WrongArgNum(_, _, _) => Ok(()),
+            ExecError::WrongArgType(_, _) => Ok(()),
             ExecError::CannotDisplay(_) => Ok(()),
             ExecError::CouldNotParse(_) => Ok(()),
+            ExecError::CannotConvert(_, _) => Ok(()),
             ExecError::NoSuchItem(_) => Ok(()),
             ExecError::ItemExists(_) => Ok(()),
+            ExecError::NoSuchMember(_, _) => Ok(()),
+            ExecError::Custom(_) => Ok(()),
+            ExecError::Import(_, _) => Ok(()),
+            ExecError::ImportCycle(_) => Ok(()),
+            ExecError::PermissionDenied(_) => Ok(()),
+            ExecError::TooManyItems(_) => Ok(()),
+            // Interrupts arrive from outside of the code, so they have no location of their own, and running out of
+            // stack may happen before the expression that did so has been given one. Internal errors are not the
+            // fault of the code.
+            ExecError::Interrupted | ExecError::TooDeep | ExecError::OutOfFuel | ExecError::Internal(_) => writeln!(f, "{} {}", output::ErrorTag(style), self.message()),
+        }
+    }
+}
+
+// The error within any wrappers that only give it a location or the source that it refers to, along with that source
+fn without_src<'a>(mut err: &'a ExecError, mut src: Option<&'a str>, mut psrc: Option<&'a str>) -> (&'a ExecError, Option<&'a str>, Option<&'a str>) {
+    loop {
+        match err {
+            ExecError::At(_, inner) => err = inner,
+            ExecError::WithSrc(inner_src, inner) => {
+                src = Some(inner_src);
+                err = inner;
+            },
+            ExecError::WithPrevSrc(inner_psrc, inner) => {
+                psrc = Some(inner_psrc);
+                err = inner;
+            },
+            _ => return (err, src, psrc),
         }
     }
 }
 
 pub type ExecResult<T> = Result<T, ExecError>;
 
+// Keep operand previews in error messages to a single, short line
+const PREVIEW_LEN: usize = 24;
+
+pub(crate) fn truncate_preview(text: String) -> String {
+    let text = text.replace('\n', "\\n");
+    if text.chars().count() > PREVIEW_LEN {
+        format!("{}...", text.chars().take(PREVIEW_LEN).collect::<String>())
+    } else {
+        text
+    }
+}
+
+// Lists can be made with a length given by the code, which may be too great to allocate
+pub(crate) fn reserve_items(n: usize) -> ExecResult<Vec<Value>> {
+    let mut items = Vec::new();
+    items.try_reserve_exact(n).map_err(|_| ExecError::TooManyItems(n))?;
+    Ok(items)
+}
+
+pub(crate) fn parse_input(input: String) -> ExecResult<Value> {
+    input
+        .trim().parse().map(|n| Value::Int(n))
+        .or_else(|_| input.trim().parse().map(|n| Value::Float(n)))
+        .or_else(|_| input.trim().parse().map(|n| Value::Boolean(n)))
+        .or_else(|_| if input.trim() == "null" { Ok(Value::Null) } else { Err(()) })
+        .or_else(|_| input.parse().map(|n| Value::String(Rc::new(RefCell::new(n)))))
+        .map_err(|_| ExecError::CouldNotParse(input))
+}
+
 pub trait Io {
     fn input(&mut self, s: String) -> ExecResult<String>;
     fn print(&mut self, s: String) -> ExecResult<()>;
+
+    /// Read a line of input without showing what is typed, as used by `prompt.password`. By default, this is the
+    /// same as `input`.
+    fn input_hidden(&mut self, s: String) -> ExecResult<String> {
+        self.input(s)
+    }
+
+    /// Whether printed text is shown on a terminal, which the `term` namespace checks before writing escape codes.
+    fn is_terminal(&self) -> bool {
+        false
+    }
+
+    /// The width of the terminal in columns, if known.
+    fn terminal_width(&self) -> Option<usize> {
+        None
+    }
 }
 
 pub struct DefaultIo;
@@ -223,22 +544,69 @@ impl Io for DefaultIo {
         println!("{}", s);
         Ok(())
     }
+
+    #[cfg(unix)]
+    fn input_hidden(&mut self, s: String) -> ExecResult<String> {
+        // Turn off echoing for the duration of the read, if the input is a terminal
+        let mut term = unsafe { std::mem::zeroed::<libc::termios>() };
+        let is_tty = unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut term) } == 0;
+        if is_tty {
+            let mut hidden = term;
+            hidden.c_lflag &= !libc::ECHO;
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &hidden) };
+        }
+        let input = self.input(s);
+        if is_tty {
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &term) };
+            // The newline typed by the user was not echoed either
+            println!();
+        }
+        input
+    }
+
+    #[cfg(unix)]
+    fn is_terminal(&self) -> bool {
+        unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+    }
+
+    #[cfg(unix)]
+    fn terminal_width(&self) -> Option<usize> {
+        let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+        match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+            0 if size.ws_col > 0 => Some(size.ws_col as usize),
+            _ => None,
+        }
+    }
+}
+
+/// Refuses to print or read input, for hosts that run code which should have no way to talk to the user. Code that
+/// tries to do either fails with an error, as it would when using a permission that it has not been granted.
+pub struct NoIo;
+
+impl Io for NoIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Err(ExecError::PermissionDenied("io"))
+    }
+
+    fn print(&mut self, _s: String) -> ExecResult<()> {
+        Err(ExecError::PermissionDenied("io"))
+    }
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct UnaryOpRef {
-    op: SrcRef,
-    expr: SrcRef,
+    pub(crate) op: SrcRef,
+    pub(crate) expr: SrcRef,
 }
 
 #[derive(Copy, Clone, Debug)]
 pub struct BinaryOpRef {
-    op: SrcRef,
-    left: SrcRef,
-    right: SrcRef,
+    pub(crate) op: SrcRef,
+    pub(crate) left: SrcRef,
+    pub(crate) right: SrcRef,
 }
 
-pub trait Obj: 'static {
+pub trait Obj: Any {
     fn get_type_name(&self) -> String {
         format!("{:?}", self.type_id())
     }
@@ -247,14 +615,41 @@ pub trait Obj: 'static {
         Err(ExecError::CannotDisplay(self.get_type_name()))
     }
 
-    fn eval_call(&self, _params: &Node<Vec<Node<Expr>>>, _caller: &mut dyn Scope, _io: &mut dyn Io, _src: &Rc<String>, r_caller: SrcRef) -> ExecResult<Value> {
+    fn get_preview_text(&self) -> Option<String> {
+        self.get_display_text().ok().map(|text| truncate_preview(text))
+    }
+
+    fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, caller: &mut dyn Scope, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef) -> ExecResult<Value> {
+        let mut args = Vec::with_capacity(params.0.len());
+        for param in &params.0 {
+            args.push(caller.eval_expr(&param.0, ctx, src)?);
+        }
+        self.call(args, ctx, src, r_caller, params.1)
+    }
+
+    // Called with already-evaluated arguments (by the VM, or by `eval_call` above)
+    fn call(&self, _args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, r_caller: SrcRef, _r_args: SrcRef) -> ExecResult<Value> {
         Err(ExecError::At(r_caller, Box::new(ExecError::CannotCall(self.get_type_name()))))
     }
 
+    // For functions, the number of arguments that they must be called with, if that is known (`f.arity`)
+    fn arity(&self) -> Option<usize> {
+        None
+    }
+
+    // For functions, the name that they were declared with, if any (`f.name`)
+    fn name(&self) -> Option<String> {
+        None
+    }
+
     fn eval_truth(&self, r: SrcRef) -> ExecResult<bool> {
         Err(ExecError::CannotDetermineTruthiness(r, self.get_type_name()))
     }
 
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string()))))
+    }
+
     fn eval_index(&self, index: &Value, r: SrcRef) -> ExecResult<Value> {
         Err(ExecError::CannotIndex(r, self.get_type_name(), index.get_type_name()))
     }
@@ -296,6 +691,8 @@ pub trait Obj: 'static {
             op: "mul",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -305,6 +702,8 @@ pub trait Obj: 'static {
             op: "div",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -314,6 +713,8 @@ pub trait Obj: 'static {
             op: "rem",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -323,6 +724,8 @@ pub trait Obj: 'static {
             op: "add",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -332,6 +735,71 @@ pub trait Obj: 'static {
             op: "sub",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
+            refs,
+        })
+    }
+
+    fn eval_bit_not(&self, refs: UnaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::UnaryOp {
+            op: "bit_not",
+            expr_type: self.get_type_name(),
+            refs,
+        })
+    }
+
+    fn eval_shl(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::BinaryOp {
+            op: "shl",
+            left_type: self.get_type_name(),
+            right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
+            refs,
+        })
+    }
+
+    fn eval_shr(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::BinaryOp {
+            op: "shr",
+            left_type: self.get_type_name(),
+            right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
+            refs,
+        })
+    }
+
+    fn eval_bit_and(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::BinaryOp {
+            op: "bit_and",
+            left_type: self.get_type_name(),
+            right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
+            refs,
+        })
+    }
+
+    fn eval_bit_xor(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::BinaryOp {
+            op: "bit_xor",
+            left_type: self.get_type_name(),
+            right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
+            refs,
+        })
+    }
+
+    fn eval_bit_or(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        Err(ExecError::BinaryOp {
+            op: "bit_or",
+            left_type: self.get_type_name(),
+            right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -341,6 +809,8 @@ pub trait Obj: 'static {
             op: "greater",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -350,6 +820,8 @@ pub trait Obj: 'static {
             op: "greater_eq",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -359,6 +831,8 @@ pub trait Obj: 'static {
             op: "less",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -368,6 +842,8 @@ pub trait Obj: 'static {
             op: "less_eq",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -377,6 +853,8 @@ pub trait Obj: 'static {
             op: "eq",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -386,6 +864,8 @@ pub trait Obj: 'static {
             op: "not_eq",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -395,6 +875,8 @@ pub trait Obj: 'static {
             op: "and",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -404,6 +886,8 @@ pub trait Obj: 'static {
             op: "or",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -413,6 +897,8 @@ pub trait Obj: 'static {
             op: "xor",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -422,6 +908,8 @@ pub trait Obj: 'static {
             op: "range",
             left_type: self.get_type_name(),
             right_type: rhs.get_type_name(),
+            left_val: self.get_preview_text(),
+            right_val: rhs.get_preview_text(),
             refs,
         })
     }
@@ -431,6 +919,8 @@ pub trait Obj: 'static {
             op: "as",
             left_type: self.get_type_name(),
             right_type: ty.get_name(),
+            left_val: self.get_preview_text(),
+            right_val: None,
             refs,
         })
     }
@@ -442,93 +932,253 @@ pub trait Obj: 'static {
     fn assign_index(&self, index: &Value, rhs: Value, r_idx: SrcRef, r_rhs: SrcRef) -> ExecResult<()> {
         Err(ExecError::CannotIndex(r_idx, self.get_type_name(), index.get_type_name()))
     }
+
+    fn assign_member(&self, name: &str, _rhs: Value, r: SrcRef) -> ExecResult<()> {
+        Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string()))))
+    }
+
+    /// The value as a resource, if it is one, so that it can be closed by a `with` statement.
+    fn as_resource(&self) -> Option<&dyn Resource> {
+        None
+    }
 }
 
 pub trait Scope {
-    fn get_var(&self, name: &str) -> ExecResult<Value>;
-    fn take_var(&mut self, name: &str) -> Option<Value>;
-    fn declare_var(&mut self, name: String, val: Value);
-    fn assign_var(&mut self, name: &str, val: Value) -> ExecResult<()>;
-    fn list(&self);
+    fn env(&self) -> &Env;
     fn as_scope_mut(&mut self) -> &mut dyn Scope;
 
-    fn eval_type(&mut self, expr: &Expr, io: &mut dyn Io, src: &Rc<String>, r: SrcRef) -> ExecResult<Type> {
+    // Builtins are found after every variable, so declaring a variable of the same name shadows them
+    fn get_var(&self, name: &str) -> ExecResult<Value> {
+        self.env()
+            .get(name)
+            .or_else(|| builtins::find_builtin(name))
+            .ok_or(ExecError::NoSuchItem(name.to_string()))
+    }
+
+    fn take_var(&mut self, name: &str) -> Option<Value> {
+        self.env().take(name)
+    }
+
+    fn declare_var(&mut self, name: String, val: Value) {
+        self.env().declare(name, val);
+    }
+
+    fn assign_var(&mut self, name: &str, val: Value) -> ExecResult<()> {
+        if self.env().assign(name, val) {
+            Ok(())
+        } else {
+            Err(ExecError::NoSuchItem(name.to_string()))
+        }
+    }
+
+    fn list(&self) {
+        for (name, val) in self.env().vars() {
+            println!("{} = {:?}", name, val);
+        }
+    }
+
+    // Evaluate both operands of a binary operator, which maps may overload by defining a function under `name`
+    fn eval_binary(&mut self, name: &str, f: overload::BinaryFn, r: SrcRef, left: &Node<Expr>, right: &Node<Expr>, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Value> {
+        let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+        let left_val = self.eval_expr(&left.0, ctx, src)?;
+        let right_val = self.eval_expr(&right.0, ctx, src).map_err(src_map)?;
+        overload::binary(name, &left_val, &right_val, f, ctx, src, BinaryOpRef { op: r, left: left.1, right: right.1 })
+    }
+
+    // Apply a binary operator to the current value of an l-value and store the result back, evaluating any container
+    // and index only once so that `xs[f()] += 1` calls `f` a single time
+    fn eval_compound_assign(&mut self, name: &str, f: overload::BinaryFn, r: SrcRef, lvalue: &Node<LVal>, expr: &Node<Expr>, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Value> {
         let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+        let refs = BinaryOpRef { op: r, left: lvalue.1, right: expr.1 };
+        let factor = self.eval_expr(&expr.0, ctx, src)
+            .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+            .map_err(src_map)?;
 
-        match expr {
-            Expr::Ident(name) => match name.0.as_str() {
-                "num" => Ok(Type::Number),
-                "str" => Ok(Type::String),
-                "char" => Ok(Type::Char),
-                "bool" => Ok(Type::Boolean),
-                "range" => Ok(Type::Range),
-                "fn" => Ok(Type::Fn),
-                "list" => Ok(Type::List),
-                "Custom" => Ok(Type::Custom),
-                "null" => Ok(Type::Null),
-                name => Err(ExecError::NotAType)
-                    .map_err(|err| ExecError::At(r, Box::new(err)))
-                    .map_err(src_map),
+        match &lvalue.0 {
+            LVal::Local(ident) => {
+                let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
+                self.assign_var(&ident.0, overload::binary(name, &prev, &factor, f, ctx, src, refs).map_err(src_map)?)
+                    .map_err(|err| ExecError::At(ident.1, Box::new(err)))
+                    .map_err(src_map)?;
             },
+            LVal::Index(container, index) => {
+                let mut container_val = self.eval_expr(&container.0, ctx, src)
+                    .map_err(|err| ExecError::At(container.1, Box::new(err)))
+                    .map_err(src_map)?;
+                let index_val = self.eval_expr(&index.0, ctx, src)
+                    .map_err(|err| ExecError::At(index.1, Box::new(err)))
+                    .map_err(src_map)?;
+                let prev = overload::index(&container_val, &index_val, ctx, src, index.1)
+                    .map_err(|err| ExecError::At(container.1, Box::new(err)))
+                    .map_err(src_map)?;
+                let val = overload::binary(name, &prev, &factor, f, ctx, src, refs).map_err(src_map)?;
+                container_val.assign_index(&index_val, val, index.1, expr.1)
+                    .map_err(|err| ExecError::At(container.1, Box::new(err)))
+                    .map_err(src_map)?;
+            },
+            LVal::Member(container, member) => {
+                let container_val = self.eval_expr(&container.0, ctx, src)
+                    .map_err(|err| ExecError::At(container.1, Box::new(err)))
+                    .map_err(src_map)?;
+                let prev = container_val.eval_member(&member.0, member.1).map_err(src_map)?;
+                let val = overload::binary(name, &prev, &factor, f, ctx, src, refs).map_err(src_map)?;
+                container_val.assign_member(&member.0, val, member.1).map_err(src_map)?;
+            },
+        }
+        Ok(Value::Null)
+    }
+
+    fn eval_type(&mut self, expr: &Expr, ctx: &mut ExecCtx, src: &Rc<String>, r: SrcRef) -> ExecResult<Type> {
+        let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+
+        match expr {
+            Expr::Ident(name) => Type::from_name(&name.0)
+                .ok_or(ExecError::NotAType)
+                .map_err(|err| ExecError::At(r, Box::new(err)))
+                .map_err(src_map),
             _ => Err(ExecError::NotAType)
                 .map_err(|err| ExecError::At(r, Box::new(err)))
                 .map_err(src_map),
         }
     }
 
-    fn eval_expr(&mut self, expr: &Expr, io: &mut dyn Io, src: &Rc<String>) -> ExecResult<Value> {
+    // The value that a call calls, along with the value that it is a member of, if any. Produces `None` when calling a
+    // member of null through `?.`.
+    fn eval_callee(&mut self, expr: &Node<Expr>, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Option<(Value, Option<Value>)>> {
+        let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+        Ok(Some(match &expr.0 {
+            Expr::OptionalAccess(_, recv, name) => match self.eval_expr(&recv.0, ctx, src)
+                .map_err(|err| ExecError::At(recv.1, Box::new(err)))
+                .map_err(src_map)?
+            {
+                Value::Null => return Ok(None),
+                val => (val.eval_member(&name.0, name.1).map_err(src_map)?, Some(val)),
+            },
+            Expr::DotAccess(_, recv, name) => {
+                let val = self.eval_expr(&recv.0, ctx, src)
+                    .map_err(|err| ExecError::At(recv.1, Box::new(err)))
+                    .map_err(src_map)?;
+                (val.eval_member(&name.0, name.1).map_err(src_map)?, Some(val))
+            },
+            callee => (
+                self.eval_expr(callee, ctx, src)
+                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                    .map_err(src_map)?,
+                None,
+            ),
+        }))
+    }
+
+    fn eval_args(&mut self, params: &Node<Vec<Node<Expr>>>, named: &[(Node<String>, Node<Expr>)], ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<(Vec<Value>, Vec<(Node<String>, Value)>)> {
+        let mut args = Vec::with_capacity(params.0.len());
+        for param in &params.0 {
+            args.push(self.eval_expr(&param.0, ctx, src)?);
+        }
+        let mut named_args = Vec::with_capacity(named.len());
+        for (name, param) in named {
+            named_args.push((Node(name.0.clone(), name.1), self.eval_expr(&param.0, ctx, src)?));
+        }
+        Ok((args, named_args))
+    }
+
+    fn eval_call_of(&mut self, f: Value, recv: Option<Value>, r_callee: SrcRef, params: &Node<Vec<Node<Expr>>>, named: &[(Node<String>, Node<Expr>)], ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Value> {
+        // Only maps have methods that take their receiver as an argument
+        if named.is_empty() && !matches!(recv, Some(Value::Map(_))) {
+            return f.eval_call(params, self.as_scope_mut(), ctx, src, r_callee);
+        }
+        let (args, named) = self.eval_args(params, named, ctx, src)?;
+        f.call_method(recv, args, named, ctx, src, r_callee, params.1)
+    }
+
+    fn eval_expr(&mut self, expr: &Expr, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Value> {
         let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+        // Function calls pass through here too, so this also limits how deeply functions may recurse
+        if !stack::has_room() {
+            return Err(ExecError::TooDeep);
+        }
+        ctx.burn_fuel()?;
 
         match expr {
             Expr::None => Ok(Value::Null),
-            Expr::LiteralNumber(x) => Ok(Value::Number(*x)),
+            Expr::LiteralInt(x) => Ok(Value::Int(*x)),
+            Expr::LiteralFloat(x) => Ok(Value::Float(*x)),
             Expr::LiteralString(s) => Ok(Value::String(Rc::new(RefCell::new(s.to_string())))),
             Expr::LiteralChar(c) => Ok(Value::Char(*c)),
             Expr::LiteralBoolean(b) => Ok(Value::Boolean(*b)),
             Expr::LiteralNull => Ok(Value::Null),
+            Expr::InterpolatedString(parts) => {
+                let mut s = String::new();
+                for part in parts {
+                    match part {
+                        StringPart::Literal(lit) => s += lit,
+                        StringPart::Expr(expr) => s += &self.eval_expr(&expr.0, ctx, src)?
+                            .get_display_text_with(&ctx.display)
+                            .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                            .map_err(src_map)?,
+                    }
+                }
+                Ok(Value::String(Rc::new(RefCell::new(s))))
+            },
             Expr::Ident(name) =>
                 self.get_var(&name.0)
+                    .or_else(|err| builtins::find_engine_builtin(&name.0, ctx).ok_or(err))
                     .map_err(|err| ExecError::At(name.1, Box::new(err)))
                     .map_err(src_map),
-            Expr::DotAccess(_, _, _) => unimplemented!(),
-            Expr::Index(_r, expr, index) => {
-                self.eval_expr(&expr.0, io, src)
+            Expr::DotAccess(_r, expr, name) => {
+                self.eval_expr(&expr.0, ctx, src)
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))
                     .map_err(src_map)?
-                    .eval_index(
-                        &self.eval_expr(&index.0, io, src)
-                            .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                            .map_err(src_map)?,
-                        index.1,
-                    )
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                    .eval_member(&name.0, name.1)
                     .map_err(src_map)
             },
-            Expr::Call(_r, expr, params) => {
-                self.eval_expr(&expr.0, io, src)
+            Expr::OptionalAccess(_r, expr, name) => match self.eval_expr(&expr.0, ctx, src)
+                .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                .map_err(src_map)?
+            {
+                Value::Null => Ok(Value::Null),
+                val => val.eval_member(&name.0, name.1).map_err(src_map),
+            },
+            Expr::Index(_r, expr, index) => {
+                let val = self.eval_expr(&expr.0, ctx, src)
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?
-                    .eval_call(params, self.as_scope_mut(), io, src, expr.1)
+                    .map_err(src_map)?;
+                let index_val = self.eval_expr(&index.0, ctx, src)
+                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                    .map_err(src_map)?;
+                overload::index(&val, &index_val, ctx, src, index.1)
+                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                    .map_err(src_map)
+            },
+            // `x?.f()` is null, rather than calling null, when `x` is null
+            Expr::Call(_r, expr, params, named) => match self.eval_callee(expr, ctx, src)? {
+                Some((f, recv)) => self.eval_call_of(f, recv, expr.1, params, named, ctx, src),
+                // `a?.f()` is null when `a` is
+                None => Ok(Value::Null),
             },
             Expr::List(items) => {
                 let mut list_items = vec![];
                 for item in &items.0 {
                     list_items.push(
-                        self.eval_expr(&item.0, io, src)
+                        self.eval_expr(&item.0, ctx, src)
                             .map_err(|err| ExecError::At(item.1, Box::new(err)))
                             .map_err(src_map)?,
                     );
                 }
-                Ok(Value::List(Rc::new(RefCell::new(list_items))))
+                let _site = gc::enter_site(items.1);
+                Ok(Value::new_list(list_items.into()))
             },
             Expr::ListClone(item, num) => {
-                match self.eval_expr(&num.0, io, src)
+                match self.eval_expr(&num.0, ctx, src)
                     .map_err(|err| ExecError::At(num.1, Box::new(err)))
                     .map_err(src_map)?
                 {
-                    Value::Number(x) => {
-                        let mut list_items = Vec::with_capacity(x as usize);
-                        let item_val = self.eval_expr(&item.0, io, src)
+                    Value::Int(x) => {
+                        let x = x.max(0);
+                        let mut list_items = reserve_items(x as usize)
+                            .map_err(|err| ExecError::At(num.1, Box::new(err)))
+                            .map_err(src_map)?;
+                        let _site = gc::enter_site(item.1.union(&num.1));
+                        let item_val = self.eval_expr(&item.0, ctx, src)
                             .map_err(|err| ExecError::At(item.1, Box::new(err)))
                             .map_err(src_map)?;
 
@@ -540,7 +1190,7 @@ pub trait Scope {
                             );
                         }
 
-                        Ok(Value::List(Rc::new(RefCell::new(list_items))))
+                        Ok(Value::new_list(list_items.into()))
                     },
                     val => Err(ExecError::NotNumeric(val.get_type_name()))
                         .map_err(|err| ExecError::At(num.1, Box::new(err)))
@@ -548,81 +1198,109 @@ pub trait Scope {
                 }
             },
             Expr::Map(maps) => {
-                let mut hmap = HashMap::new();
+                let mut hmap = Map::new();
                 for (key, val) in &maps.0 {
                     hmap.insert(
-                        self.eval_expr(&key.0, io, src)
+                        self.eval_expr(&key.0, ctx, src)
                             .map_err(|err| ExecError::At(key.1, Box::new(err)))
                             .map_err(src_map)?,
-                        self.eval_expr(&val.0, io, src)
+                        self.eval_expr(&val.0, ctx, src)
                             .map_err(|err| ExecError::At(val.1, Box::new(err)))
                             .map_err(src_map)?,
                     );
                 }
-                Ok(Value::Map(Rc::new(RefCell::new(hmap))))
+                let _site = gc::enter_site(maps.1);
+                Ok(Value::new_map(hmap))
             },
 
             Expr::UnaryNot(r, expr) =>
-                self.eval_expr(&expr.0, io, src)?.eval_not(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
-            Expr::UnaryNeg(r, expr) =>
-                self.eval_expr(&expr.0, io, src)?.eval_neg(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
+                self.eval_expr(&expr.0, ctx, src)?.eval_not(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
+            Expr::UnaryNeg(r, expr) => {
+                let val = self.eval_expr(&expr.0, ctx, src)?;
+                overload::neg(&val, ctx, src, UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map)
+            },
+            Expr::UnaryBitNot(r, expr) =>
+                self.eval_expr(&expr.0, ctx, src)?.eval_bit_not(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
             Expr::UnaryInput(r, expr) => {
-                let text = self.eval_expr(&expr.0, io, src)
+                let text = self.eval_expr(&expr.0, ctx, src)
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))
                     .map_err(src_map)?
                     .get_display_text()
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))
                     .map_err(src_map)?;
-                let input = io.input(text)
+                let input = ctx.input(text)
                     .map_err(|err| ExecError::At(r.union(&expr.1), Box::new(err)))
                     .map_err(src_map)?;
-                input
-                    .trim().parse().map(|n| Value::Number(n))
-                    .or_else(|_| input.trim().parse().map(|n| Value::Boolean(n)))
-                    .or_else(|_| if input.trim() == "null" { Ok(Value::Null) } else { Err(()) })
-                    .or_else(|_| input.parse().map(|n| Value::String(Rc::new(RefCell::new(n)))))
-                    .map_err(|_| ExecError::At(*r, Box::new(ExecError::CouldNotParse(input))))
+                parse_input(input)
+                    .map_err(|err| ExecError::At(*r, Box::new(err)))
                     .map_err(src_map)
             },
             Expr::UnaryClone(r, expr) =>
-                self.eval_expr(&expr.0, io, src)?.eval_clone(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
+                self.eval_expr(&expr.0, ctx, src)?.eval_clone(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
             Expr::UnaryMirror(r, expr) =>
-                self.eval_expr(&expr.0, io, src)?.eval_mirror(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
+                self.eval_expr(&expr.0, ctx, src)?.eval_mirror(UnaryOpRef { op: *r, expr: expr.1 }).map_err(src_map),
 
             Expr::BinaryMul(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_mul(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__mul", Value::eval_mul, *r, left, right, ctx, src),
             Expr::BinaryDiv(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_div(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__div", Value::eval_div, *r, left, right, ctx, src),
             Expr::BinaryRem(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_rem(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__rem", Value::eval_rem, *r, left, right, ctx, src),
             Expr::BinaryAdd(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_add(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__add", Value::eval_add, *r, left, right, ctx, src),
             Expr::BinarySub(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_sub(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__sub", Value::eval_sub, *r, left, right, ctx, src),
+            Expr::BinaryShl(r, left, right) =>
+                self.eval_binary("__shl", Value::eval_shl, *r, left, right, ctx, src),
+            Expr::BinaryShr(r, left, right) =>
+                self.eval_binary("__shr", Value::eval_shr, *r, left, right, ctx, src),
+            Expr::BinaryBitAnd(r, left, right) =>
+                self.eval_binary("__bit_and", Value::eval_bit_and, *r, left, right, ctx, src),
+            Expr::BinaryBitXor(r, left, right) =>
+                self.eval_binary("__bit_xor", Value::eval_bit_xor, *r, left, right, ctx, src),
+            Expr::BinaryBitOr(r, left, right) =>
+                self.eval_binary("__bit_or", Value::eval_bit_or, *r, left, right, ctx, src),
             Expr::BinaryGreater(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_greater(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__greater", Value::eval_greater, *r, left, right, ctx, src),
             Expr::BinaryGreaterEq(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_greater_eq(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__greater_eq", Value::eval_greater_eq, *r, left, right, ctx, src),
             Expr::BinaryLess(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_less(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__less", Value::eval_less, *r, left, right, ctx, src),
             Expr::BinaryLessEq(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_less_eq(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__less_eq", Value::eval_less_eq, *r, left, right, ctx, src),
             Expr::BinaryEq(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_eq(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__eq", Value::eval_eq, *r, left, right, ctx, src),
             Expr::BinaryNotEq(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_not_eq(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
-            Expr::BinaryAnd(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_and(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
-            Expr::BinaryOr(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_or(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_binary("__not_eq", Value::eval_not_eq, *r, left, right, ctx, src),
+            // The right operand is only evaluated if the left does not decide the result, which is the deciding operand
+            Expr::BinaryAnd(_, left, right) => match self.eval_expr(&left.0, ctx, src)? {
+                val if val.is_truthy() => self.eval_expr(&right.0, ctx, src).map_err(src_map),
+                val => Ok(val),
+            },
+            Expr::BinaryOr(_, left, right) => match self.eval_expr(&left.0, ctx, src)? {
+                val if val.is_truthy() => Ok(val),
+                _ => self.eval_expr(&right.0, ctx, src).map_err(src_map),
+            },
+            Expr::BinaryCoalesce(_, left, right) => match self.eval_expr(&left.0, ctx, src)? {
+                Value::Null => self.eval_expr(&right.0, ctx, src).map_err(src_map),
+                val => Ok(val),
+            },
             Expr::BinaryXor(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_xor(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_expr(&left.0, ctx, src)?.eval_xor(&self.eval_expr(&right.0, ctx, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
             Expr::BinaryRange(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_range(&self.eval_expr(&right.0, io, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_expr(&left.0, ctx, src)?.eval_range(&self.eval_expr(&right.0, ctx, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+            Expr::BinaryIs(r, left, right) =>
+                self.eval_expr(&left.0, ctx, src)?.eval_is(&self.eval_expr(&right.0, ctx, src).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
             Expr::BinaryAs(r, left, right) =>
-                self.eval_expr(&left.0, io, src)?.eval_as(&self.eval_type(&right.0, io, src, right.1).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+                self.eval_expr(&left.0, ctx, src)?.eval_as(&self.eval_type(&right.0, ctx, src, right.1).map_err(src_map)?, BinaryOpRef { op: *r, left: left.1, right: right.1 }),
+            // Only the branch that is chosen is evaluated
+            Expr::Ternary(_, cond, a, b) => if self.eval_expr(&cond.0, ctx, src)?.eval_truth(cond.1)? {
+                self.eval_expr(&a.0, ctx, src)
+            } else {
+                self.eval_expr(&b.0, ctx, src)
+            },
             Expr::BinaryAssign(r, lvalue, rvalue) => {
-                let val = self.eval_expr(&rvalue.0, io, src)
+                let val = self.eval_expr(&rvalue.0, ctx, src)
                     .map_err(|err| ExecError::At(rvalue.1, Box::new(err)))
                     .map_err(src_map)?;
 
@@ -634,10 +1312,10 @@ pub trait Scope {
                         Ok(Value::Null)
                     },
                     LVal::Index(expr, index) => {
-                        let mut container = self.eval_expr(&expr.0, io, src)
+                        let mut container = self.eval_expr(&expr.0, ctx, src)
                             .map_err(|err| ExecError::At(expr.1, Box::new(err)))
                             .map_err(src_map)?;
-                        let index_val = self.eval_expr(&index.0, io, src)
+                        let index_val = self.eval_expr(&index.0, ctx, src)
                             .map_err(|err| ExecError::At(index.1, Box::new(err)))
                             .map_err(src_map)?;
                         container.assign_index(&index_val, val, index.1, rvalue.1)
@@ -645,156 +1323,236 @@ pub trait Scope {
                             .map_err(src_map)?;
                         Ok(Value::Null)
                     },
-                }
-            },
-            Expr::BinaryAddAssign(r, lvalue, expr) => {
-                let factor = self.eval_expr(&expr.0, io, src)
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?;
-
-                match &lvalue.0 {
-                    LVal::Local(ident) => {
-                        let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
-                        self.assign_var(&ident.0, prev.eval_add(&factor, BinaryOpRef { op: *r, left: lvalue.1, right: expr.1 }).map_err(src_map)?)
-                            .map_err(|err| ExecError::At(ident.1, Box::new(err)))
-                            .map_err(src_map)?;
-                        Ok(Value::Null)
-                    },
-                    LVal::Index(_, _) => unimplemented!(),
-                }
-            },
-            Expr::BinarySubAssign(r, lvalue, expr) => {
-                let factor = self.eval_expr(&expr.0, io, src)
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?;
-
-                match &lvalue.0 {
-                    LVal::Local(ident) => {
-                        let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
-                        self.assign_var(&ident.0, prev.eval_sub(&factor, BinaryOpRef { op: *r, left: lvalue.1, right: expr.1 }).map_err(src_map)?)
-                            .map_err(|err| ExecError::At(ident.1, Box::new(err)))
-                            .map_err(src_map)?;
-                        Ok(Value::Null)
-                    },
-                    LVal::Index(_, _) => unimplemented!(),
-                }
-            },
-            Expr::BinaryMulAssign(r, lvalue, expr) => {
-                let factor = self.eval_expr(&expr.0, io, src)
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?;
-
-                match &lvalue.0 {
-                    LVal::Local(ident) => {
-                        let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
-                        self.assign_var(&ident.0, prev.eval_mul(&factor, BinaryOpRef { op: *r, left: lvalue.1, right: expr.1 }).map_err(src_map)?)
-                            .map_err(|err| ExecError::At(ident.1, Box::new(err)))
-                            .map_err(src_map)?;
-                        Ok(Value::Null)
-                    },
-                    LVal::Index(_, _) => unimplemented!(),
-                }
-            },
-            Expr::BinaryDivAssign(r, lvalue, expr) => {
-                let factor = self.eval_expr(&expr.0, io, src)
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?;
-
-                match &lvalue.0 {
-                    LVal::Local(ident) => {
-                        let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
-                        self.assign_var(&ident.0, prev.eval_div(&factor, BinaryOpRef { op: *r, left: lvalue.1, right: expr.1 }).map_err(src_map)?)
-                            .map_err(|err| ExecError::At(ident.1, Box::new(err)))
-                            .map_err(src_map)?;
-                        Ok(Value::Null)
-                    },
-                    LVal::Index(_, _) => unimplemented!(),
-                }
-            },
-            Expr::BinaryRemAssign(r, lvalue, expr) => {
-                let factor = self.eval_expr(&expr.0, io, src)
-                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
-                    .map_err(src_map)?;
-
-                match &lvalue.0 {
-                    LVal::Local(ident) => {
-                        let prev = self.get_var(&ident.0).map_err(|err| ExecError::At(ident.1, Box::new(err))).map_err(src_map)?;
-                        self.assign_var(&ident.0, prev.eval_rem(&factor, BinaryOpRef { op: *r, left: lvalue.1, right: expr.1 }).map_err(src_map)?)
-                            .map_err(|err| ExecError::At(ident.1, Box::new(err)))
+                    LVal::Member(expr, name) => {
+                        self.eval_expr(&expr.0, ctx, src)
+                            .map_err(|err| ExecError::At(expr.1, Box::new(err)))
+                            .map_err(src_map)?
+                            .assign_member(&name.0, val, name.1)
                             .map_err(src_map)?;
                         Ok(Value::Null)
                     },
-                    LVal::Index(_, _) => unimplemented!(),
                 }
             },
+            Expr::BinaryAddAssign(r, lvalue, expr) =>
+                self.eval_compound_assign("__add", Value::eval_add, *r, lvalue, expr, ctx, src),
+            Expr::BinarySubAssign(r, lvalue, expr) =>
+                self.eval_compound_assign("__sub", Value::eval_sub, *r, lvalue, expr, ctx, src),
+            Expr::BinaryMulAssign(r, lvalue, expr) =>
+                self.eval_compound_assign("__mul", Value::eval_mul, *r, lvalue, expr, ctx, src),
+            Expr::BinaryDivAssign(r, lvalue, expr) =>
+                self.eval_compound_assign("__div", Value::eval_div, *r, lvalue, expr, ctx, src),
+            Expr::BinaryRemAssign(r, lvalue, expr) =>
+                self.eval_compound_assign("__rem", Value::eval_rem, *r, lvalue, expr, ctx, src),
             Expr::Fn(code, rc) =>
-                Ok(Value::Fn(code.clone(), rc.clone()))
+                Ok(Value::Fn(code.clone(), rc.clone(), self.env().clone()))
         }
     }
 
-    fn eval_stmt(&mut self, stmt: &Stmt, io: &mut dyn Io, src: &Rc<String>) -> ExecResult<Option<Value>> {
+    fn eval_stmt(&mut self, stmt: &Stmt, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Option<Value>> {
         match stmt {
-            Stmt::Expr(expr) => { self.eval_expr(&expr.0, io, src)?; Ok(None) },
+            Stmt::Expr(expr) => { self.eval_expr(&expr.0, ctx, src)?; Ok(None) },
             Stmt::Print(expr) => {
-                let text = self.eval_expr(&expr.0, io, src)?.get_display_text()
+                let text = self.eval_expr(&expr.0, ctx, src)?.get_display_text_with(&ctx.display)
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))?;
-                io.print(text).map(|_| None)
+                ctx.io.print(text).map(|_| None)
+                    .map_err(|err| ExecError::At(expr.1, Box::new(err)))
             },
             Stmt::If(expr, block) => {
-                if self.eval_expr(&expr.0, io, src)?.eval_truth(expr.1)? {
-                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&block.0, io, src)? {
+                if self.eval_expr(&expr.0, ctx, src)?.eval_truth(expr.1)? {
+                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&block.0, ctx, src)? {
                         return Ok(Some(val));
                     }
                 }
                 Ok(None)
             },
             Stmt::IfElse(expr, true_block, false_block) => {
-                if self.eval_expr(&expr.0, io, src)?.eval_truth(expr.1)? {
-                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&true_block.0, io, src)? {
+                if self.eval_expr(&expr.0, ctx, src)?.eval_truth(expr.1)? {
+                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&true_block.0, ctx, src)? {
+                        return Ok(Some(val));
+                    }
+                } else {
+                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&false_block.0, ctx, src)? {
+                        return Ok(Some(val));
+                    }
+                }
+                Ok(None)
+            },
+            Stmt::IfElseIf(expr, true_block, else_stmt) => {
+                if self.eval_expr(&expr.0, ctx, src)?.eval_truth(expr.1)? {
+                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&true_block.0, ctx, src)? {
                         return Ok(Some(val));
                     }
                 } else {
-                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&false_block.0, io, src)? {
+                    if let Some(val) = self.eval_stmt(&else_stmt.0, ctx, src)? {
                         return Ok(Some(val));
                     }
                 }
                 Ok(None)
             },
             Stmt::While(expr, block) => {
-                while self.eval_expr(&expr.0, io, src)?.eval_truth(expr.1)? {
-                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&block.0, io, src)? {
+                while self.eval_expr(&expr.0, ctx, src)?.eval_truth(expr.1)? {
+                    ctx.poll_interrupt(src, expr.1)?;
+                    if let Some(val) = BlockScope::new(self.as_scope_mut()).eval_block(&block.0, ctx, src)? {
                         return Ok(Some(val));
                     }
                 }
                 Ok(None)
             },
-            Stmt::For(ident, expr, block) => {
-                let iter = self.eval_expr(&expr.0, io, src)?.eval_iter(expr.1)?;
+            Stmt::For(ident, None, expr, block) => {
+                let iter = self.eval_expr(&expr.0, ctx, src)?.eval_iter(expr.1)?;
                 for item in iter {
+                    ctx.poll_interrupt(src, expr.1)?;
                     let mut scope = BlockScope::new(self.as_scope_mut());
                     scope.declare_var(ident.0.clone(), item);
-                    if let Some(val) = scope.eval_block(&block.0, io, src)? {
+                    if let Some(val) = scope.eval_block(&block.0, ctx, src)? {
                         return Ok(Some(val));
                     }
                 }
                 Ok(None)
             },
+            Stmt::For(key_ident, Some(val_ident), expr, block) => {
+                let iter = self.eval_expr(&expr.0, ctx, src)?.eval_iter_pairs(expr.1)?;
+                for (key, item) in iter {
+                    ctx.poll_interrupt(src, expr.1)?;
+                    let mut scope = BlockScope::new(self.as_scope_mut());
+                    scope.declare_var(key_ident.0.clone(), key);
+                    scope.declare_var(val_ident.0.clone(), item);
+                    if let Some(val) = scope.eval_block(&block.0, ctx, src)? {
+                        return Ok(Some(val));
+                    }
+                }
+                Ok(None)
+            },
+            // Calls within the `try` block are not the last thing that the function does, since their errors are caught
+            Stmt::TryCatch(try_block, ident, catch_block) => {
+                let outer = mem::replace(&mut ctx.tail_calls, false);
+                let res = BlockScope::new(self.as_scope_mut()).eval_block(&try_block.0, ctx, src);
+                ctx.tail_calls = outer;
+                match res {
+                    Ok(val) => Ok(val),
+                    Err(err) if err.is_interrupt() => Err(err),
+                    Err(err) => {
+                        let mut scope = BlockScope::new(self.as_scope_mut());
+                        scope.declare_var(ident.0.clone(), Value::from(ErrorValue::from_error(&err)));
+                        scope.eval_block(&catch_block.0, ctx, src)
+                    },
+                }
+            },
+            // The resource is closed however the block ends, and an error from the block is reported over one from closing
+            Stmt::With(expr, ident, block) => {
+                let val = self.eval_expr(&expr.0, ctx, src)?;
+                let obj = match &val {
+                    Value::Custom(obj) if obj.as_resource().is_some() => obj.clone(),
+                    val => return Err(ExecError::At(expr.1, Box::new(ExecError::Custom(format!(
+                        "Only resources, such as files and connections, can be used by a 'with' statement, not values of type '{}'.",
+                        val.get_type_name(),
+                    ))))),
+                };
+                let res = {
+                    let mut scope = BlockScope::new(self.as_scope_mut());
+                    scope.declare_var(ident.0.clone(), val);
+                    let outer = mem::replace(&mut ctx.tail_calls, false);
+                    let res = scope.eval_block(&block.0, ctx, src);
+                    ctx.tail_calls = outer;
+                    res
+                };
+                let closed = obj.as_resource().map(|res| res.close()).unwrap_or(Ok(()));
+                let res = res?;
+                closed.map_err(|err| ExecError::At(expr.1, Box::new(err)))?;
+                Ok(res)
+            },
+            Stmt::Match(expr, arms) => {
+                let val = self.eval_expr(&expr.0, ctx, src)?;
+                for (pattern, block) in arms {
+                    let mut bindings = Vec::new();
+                    if pattern::match_pattern(&pattern.0, &val, expr.1, &mut bindings)? {
+                        let mut scope = BlockScope::new(self.as_scope_mut());
+                        for (ident, val) in bindings {
+                            scope.declare_var(ident, val);
+                        }
+                        return scope.eval_block(&block.0, ctx, src);
+                    }
+                }
+                Ok(None)
+            },
+            Stmt::Import(path, ident) => {
+                let module = import(&path.0, ctx)
+                    .map_err(|err| ExecError::At(path.1, Box::new(err)))?;
+                self.declare_var(ident.0.clone(), module);
+                Ok(None)
+            },
             Stmt::Decl(ident, expr) => {
-                let val = self.eval_expr(&expr.0, io, src)?;
+                let val = self.eval_expr(&expr.0, ctx, src)?;
                 self.declare_var(ident.0.clone(), val);
                 Ok(None)
             },
+            // A class is declared as a map of its methods, whose `new` makes instances of it
+            Stmt::Class(ident, methods) => {
+                let mut items = Map::new();
+                let mut init = None;
+                for (name, method) in methods {
+                    let func = self.eval_expr(&method.0, ctx, src)?;
+                    let key = Value::from(name.0.clone());
+                    if items.contains_key(&key) || (name.0 == "new" && init.is_some()) {
+                        return Err(ExecError::WithSrc(src.clone(), Box::new(ExecError::At(name.1, Box::new(ExecError::Custom(format!(
+                            "The class '{}' declares more than one method named '{}'.", ident.0, name.0,
+                        )))))));
+                    }
+                    match name.0.as_str() {
+                        "new" => init = Some(func),
+                        _ => { items.insert(key, func); },
+                    }
+                }
+                let class = Rc::new(RefCell::new(items));
+                gc::track_map(&class);
+                let new = Value::from(class::Constructor::new(ident.0.clone(), &class, init));
+                class.borrow_mut().insert(Value::from(String::from("new")), new);
+                self.declare_var(ident.0.clone(), Value::Map(class));
+                Ok(None)
+            },
+            // None of the variables are declared unless the whole value has the shape of the pattern
+            Stmt::DeclPattern(pattern, expr) => {
+                let val = self.eval_expr(&expr.0, ctx, src)?;
+                let mut bindings = Vec::new();
+                pattern::destructure(&pattern.0, &val, pattern.1, &mut bindings)
+                    .map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))?;
+                for (ident, val) in bindings {
+                    self.declare_var(ident, val);
+                }
+                Ok(None)
+            },
+            // A call that a function ends with is left for the function's caller to make, once the function has returned
+            Stmt::Return(Node(Expr::Call(_, callee, params, named), r)) if ctx.tail_calls => {
+                let at = |err| ExecError::At(*r, Box::new(err));
+                let (f, recv) = match self.eval_callee(callee, ctx, src).map_err(at)? {
+                    Some(callee) => callee,
+                    None => return Ok(Some(Value::Null)),
+                };
+                match f {
+                    Value::Fn(code, f, env) => {
+                        let (args, named) = self.eval_args(params, named, ctx, src).map_err(at)?;
+                        ctx.tail_call = Some(TailCall {
+                            code, f, env, recv, args, named,
+                            src: src.clone(),
+                            r_caller: callee.1,
+                            r_args: params.1,
+                        });
+                        Ok(Some(Value::Null))
+                    },
+                    f => self.eval_call_of(f, recv, callee.1, params, named, ctx, src).map(Some).map_err(at),
+                }
+            },
             Stmt::Return(expr) => {
-                let val = self.eval_expr(&expr.0, io, src)
+                let val = self.eval_expr(&expr.0, ctx, src)
                     .map_err(|err| ExecError::At(expr.1, Box::new(err)))?;
                 Ok(Some(val))
             },
         }
     }
 
-    fn eval_block(&mut self, block: &Block, io: &mut dyn Io, src: &Rc<String>) -> ExecResult<Option<Value>> {
+    fn eval_block(&mut self, block: &Block, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Option<Value>> {
         for stmt in &block.0 {
-            if let Some(val) = self.eval_stmt(&stmt.0, io, src)? {
+            if let Some(val) = self.eval_stmt(&stmt.0, ctx, src)? {
                 return Ok(Some(val));
             }
         }
@@ -0,0 +1,153 @@
+use std::{
+    rc::Rc,
+    fs,
+    mem,
+    path::{Component, Path, PathBuf},
+};
+use hashbrown::HashMap;
+use crate::{
+    parser::{Parser, SrcRef},
+    error::ForgeError,
+};
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    GlobalScope,
+    Obj,
+    Scope,
+    Value,
+};
+
+/// The value produced by an `import` statement. The globals declared by the imported file are accessible as its
+/// members, so they cannot collide with those of the importer.
+pub struct Module {
+    name: String,
+    scope: GlobalScope,
+}
+
+impl Obj for Module {
+    fn get_type_name(&self) -> String {
+        String::from("module")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<module '{}'>", self.name))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        self.scope
+            .get_var(name)
+            .map_err(|_| ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string()))))
+    }
+}
+
+/// Resolves and caches the modules imported by an engine. Modules must be within the root directory, and may only be
+/// imported if the root was chosen by the host or the engine is allowed to read files.
+pub struct ModuleLoader {
+    root: PathBuf,
+    chosen: bool,
+    cache: HashMap<PathBuf, Value>,
+    // The modules currently being executed, innermost last
+    loading: Vec<PathBuf>,
+}
+
+impl ModuleLoader {
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        Self {
+            root: root.into(),
+            chosen: true,
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    // Paths are relative to the importing file, or to the root for code that was not itself imported. `import foo;`
+    // is shorthand for `import "foo.fg";`. Paths may not be absolute or lead up with `..`, and may not reach outside of
+    // the root through a symbolic link either.
+    fn resolve(&self, path: &str) -> ExecResult<PathBuf> {
+        let mut path = PathBuf::from(path);
+        if path.components().any(|c| match c { Component::Normal(_) | Component::CurDir => false, _ => true }) {
+            return Err(ExecError::Custom(format!(
+                "Module paths must be relative and may not contain '..', found '{}'.",
+                path.display(),
+            )));
+        }
+        if path.extension().is_none() {
+            path.set_extension("fg");
+        }
+        let path = match self.loading.last().and_then(|current| current.parent()) {
+            Some(dir) => dir.join(path),
+            None => self.root.join(path),
+        };
+
+        let root = fs::canonicalize(&self.root).map_err(ExecError::Io)?;
+        let full_path = fs::canonicalize(path).map_err(ExecError::Io)?;
+        if full_path.starts_with(root) {
+            Ok(full_path)
+        } else {
+            Err(ExecError::Custom(String::from("Modules must be within the module root.")))
+        }
+    }
+}
+
+impl Default for ModuleLoader {
+    fn default() -> Self {
+        Self {
+            chosen: false,
+            ..Self::new(".")
+        }
+    }
+}
+
+/// Load the module at the given path, executing it the first time that it is imported.
+pub(crate) fn import(path: &str, ctx: &mut ExecCtx) -> ExecResult<Value> {
+    let in_module = |err: ForgeError| ExecError::Import(path.to_string(), Box::new(err));
+
+    if !ctx.modules.chosen {
+        ctx.permissions.require_fs_read()?;
+    }
+    let full_path = ctx.modules.resolve(path).map_err(|err| in_module(err.into()))?;
+
+    if let Some(module) = ctx.modules.cache.get(&full_path) {
+        return Ok(module.clone());
+    }
+    if let Some(idx) = ctx.modules.loading.iter().position(|p| p == &full_path) {
+        let cycle = ctx.modules.loading[idx..]
+            .iter()
+            .chain(std::iter::once(&full_path))
+            .map(|p| p.display().to_string())
+            .collect();
+        return Err(ExecError::ImportCycle(cycle));
+    }
+
+    let code = fs::read_to_string(&full_path)
+        .map_err(|err| in_module(ExecError::Io(err).into()))?;
+    let stmts = Parser::new(&code)
+        .and_then(|parser| parser.parse_stmts())
+        .map_err(|err| in_module(ForgeError::InSrc(code.clone(), Box::new(err.into()))))?;
+
+    let mut scope = GlobalScope::empty();
+    let src = Rc::new(code.clone());
+    ctx.modules.loading.push(full_path.clone());
+    // A module may be imported from within a function, but its code is not part of the function
+    let outer = mem::replace(&mut ctx.tail_calls, false);
+    let res = stmts
+        .iter()
+        .try_for_each(|stmt| scope.eval_stmt(&stmt.0, ctx, &src).map(|_| ()));
+    ctx.tail_calls = outer;
+    ctx.modules.loading.pop();
+    res.map_err(|err| in_module(ForgeError::InSrc(code, Box::new(err.into()))))?;
+
+    let name = full_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let module = Value::from(Module { name, scope });
+    ctx.modules.cache.insert(full_path, module.clone());
+    Ok(module)
+}
@@ -0,0 +1,67 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    mock,
+};
+
+/// A function implemented in Rust that may be called from Forge code.
+pub struct NativeFn {
+    name: String,
+    arity: usize,
+    f: Box<dyn Fn(&[Value]) -> ExecResult<Value>>,
+}
+
+impl NativeFn {
+    pub fn new<F: Fn(&[Value]) -> ExecResult<Value> + 'static>(name: &str, arity: usize, f: F) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            f: Box::new(f),
+        }
+    }
+}
+
+impl Obj for NativeFn {
+    fn get_type_name(&self) -> String {
+        String::from("function")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<native function '{}'>", self.name))
+    }
+
+    fn arity(&self) -> Option<usize> {
+        Some(self.arity)
+    }
+
+    fn name(&self) -> Option<String> {
+        Some(self.name.clone())
+    }
+
+    fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        if let Some(mock) = mock::find(ctx, None, &self.name) {
+            return mock.call(args, ctx, src, r_caller, r_args, |args, ctx| self.call_unmocked(args, ctx, src, r_caller, r_args));
+        }
+        self.call_unmocked(args, ctx, src, r_caller, r_args)
+    }
+}
+
+impl NativeFn {
+    fn call_unmocked(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        if args.len() != self.arity {
+            Err(ExecError::At(r_args, Box::new(ExecError::WrongArgNum(
+                None, self.arity, args.len()
+            ))))
+        } else {
+            match &mut ctx.replay {
+                Some(replay) => replay.host(&self.name, || (self.f)(&args)),
+                None => (self.f)(&args),
+            }.map_err(|err| ExecError::At(r_caller.union(&r_args), Box::new(err)))
+        }.map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))
+    }
+}
@@ -0,0 +1,85 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecResult,
+    UnaryOpRef,
+    Value,
+    class,
+};
+
+/// The built-in behaviour of a binary operator, such as `Value::eval_add`.
+pub(crate) type BinaryFn = fn(&Value, &Value, BinaryOpRef) -> ExecResult<Value>;
+
+// A map overloads an operator by holding a function under its special name, or by being an instance of a class that
+// declares it as a method
+fn find(val: &Value, name: &str) -> Option<Value> {
+    let key = Value::from(name);
+    match val {
+        Value::Map(m) => m
+            .borrow()
+            .get(&key)
+            .cloned()
+            .or_else(|| class::class_of(val).and_then(|class| class.borrow().get(&key).cloned()))
+            .filter(|f| !f.is_null()),
+        _ => None,
+    }
+}
+
+// Handlers are called as methods of the operand that provided them, so that the methods of a class have `self`, but
+// are always given every operand in its original order
+fn call(handler: Value, owner: &Value, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r: SrcRef) -> ExecResult<Value> {
+    handler.call_method(Some(owner.clone()), args, Vec::new(), ctx, src, r, r)
+}
+
+// The handler for an operator of either operand, along with the operand it belongs to
+fn find_either<'a>(left: &'a Value, right: &'a Value, name: &str) -> Option<(Value, &'a Value)> {
+    find(left, name).map(|f| (f, left)).or_else(|| find(right, name).map(|f| (f, right)))
+}
+
+/// Evaluate a binary operator, calling the function a map operand defines under `name` (such as `__add`) if there is
+/// one. The left operand is checked first, and the function is called with both operands in their original order.
+pub(crate) fn binary(name: &str, left: &Value, right: &Value, f: BinaryFn, ctx: &mut ExecCtx, src: &Rc<String>, refs: BinaryOpRef) -> ExecResult<Value> {
+    let r = refs.op.union(&refs.left).union(&refs.right);
+    if let Some((handler, owner)) = find_either(left, right, name) {
+        return call(handler, owner, vec![left.clone(), right.clone()], ctx, src, r);
+    }
+    // `!=` is the opposite of `==` unless it is overloaded separately
+    if name == "__not_eq" {
+        if let Some((handler, owner)) = find_either(left, right, "__eq") {
+            let eq = call(handler, owner, vec![left.clone(), right.clone()], ctx, src, r)?;
+            return Ok(Value::Boolean(!eq.eval_truth(r)?));
+        }
+    }
+    f(left, right, refs)
+}
+
+pub(crate) fn neg(val: &Value, ctx: &mut ExecCtx, src: &Rc<String>, refs: UnaryOpRef) -> ExecResult<Value> {
+    match find(val, "__neg") {
+        Some(handler) => {
+            let r = refs.op.union(&refs.expr);
+            call(handler, val, vec![val.clone()], ctx, src, r)
+        },
+        None => val.eval_neg(refs),
+    }
+}
+
+/// Index a value. Indexing a map with a key that it does not contain calls its `__index` function, if it has one,
+/// with the map and the key. Otherwise, a map made by `defaultmap(f)` stores what `f()` produces under the key.
+pub(crate) fn index(val: &Value, index: &Value, ctx: &mut ExecCtx, src: &Rc<String>, r: SrcRef) -> ExecResult<Value> {
+    if let Value::Map(m) = val {
+        if !m.borrow().contains_key(index) {
+            if let Some(handler) = find(val, "__index") {
+                return call(handler, val, vec![val.clone(), index.clone()], ctx, src, r);
+            }
+            let default = m.borrow().default_fn().cloned();
+            if let Some(default) = default {
+                let item = default.call(Vec::new(), ctx, src, r, r)?;
+                m.borrow_mut().insert(index.clone(), item.clone());
+                return Ok(item);
+            }
+        }
+    }
+    val.eval_index(index, r)
+}
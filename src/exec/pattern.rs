@@ -0,0 +1,138 @@
+use crate::parser::{
+    SrcRef,
+    ast::{Expr, Pattern},
+};
+use super::{
+    BinaryOpRef,
+    ExecError,
+    ExecResult,
+    Value,
+};
+
+// Patterns may only contain literals, so they can be evaluated without a scope
+fn literal(expr: &Expr) -> Value {
+    match expr {
+        Expr::LiteralInt(x) => Value::Int(*x),
+        Expr::LiteralFloat(x) => Value::Float(*x),
+        Expr::LiteralString(s) => Value::from(s.as_str()),
+        Expr::LiteralChar(c) => Value::Char(*c),
+        Expr::LiteralBoolean(b) => Value::Boolean(*b),
+        _ => Value::Null,
+    }
+}
+
+/// Determine whether a value matches a pattern, collecting the variables that the pattern binds if it does.
+pub(crate) fn match_pattern(pattern: &Pattern, val: &Value, r: SrcRef, bindings: &mut Vec<(String, Value)>) -> ExecResult<bool> {
+    match pattern {
+        Pattern::Wildcard => Ok(true),
+        Pattern::Binding(ident) => {
+            bindings.push((ident.clone(), val.clone()));
+            Ok(true)
+        },
+        Pattern::Literal(expr) => val
+            .eval_eq(&literal(&expr.0), BinaryOpRef { op: expr.1, left: r, right: expr.1 })?
+            .eval_truth(expr.1),
+        // Like `..` expressions, ranges include their start but not their end
+        Pattern::Range(from, to) => Ok(match (val, literal(&from.0), literal(&to.0)) {
+            (Value::Char(c), Value::Char(from), Value::Char(to)) => from <= *c && *c < to,
+            (x, from, to) => match (x.as_number(), from.as_number(), to.as_number()) {
+                (Some(x), Some(from), Some(to)) => from <= x && x < to,
+                _ => false,
+            },
+        }),
+        Pattern::List(items, rest) => {
+            let list = match val {
+                Value::List(l) => l.borrow().clone(),
+                _ => return Ok(false),
+            };
+            if list.len() < items.len() || (rest.is_none() && list.len() != items.len()) {
+                return Ok(false);
+            }
+            for (item, pattern) in list.iter().zip(items.iter()) {
+                if !match_pattern(&pattern.0, item, pattern.1, bindings)? {
+                    return Ok(false);
+                }
+            }
+            match rest {
+                Some(rest) => {
+                    let tail = list.slice(items.len()..list.len()).unwrap();
+                    match_pattern(&rest.0, &Value::new_list(tail), rest.1, bindings)
+                },
+                None => Ok(true),
+            }
+        },
+        // Maps match if they have each of the keys, whatever other keys they have
+        Pattern::Map(entries) => {
+            let map = match val {
+                Value::Map(m) => m.borrow().clone(),
+                _ => return Ok(false),
+            };
+            for (key, pattern) in entries {
+                match map.get(&Value::from(key.0.as_str())) {
+                    Some(item) if match_pattern(&pattern.0, item, pattern.1, bindings)? => {},
+                    _ => return Ok(false),
+                }
+            }
+            Ok(true)
+        },
+        Pattern::Typed(inner, ty) => Ok(val.has_type(&ty.0) && match_pattern(&inner.0, val, inner.1, bindings)?),
+    }
+}
+
+fn items(n: usize) -> String {
+    format!("{} item{}", n, if n == 1 { "" } else { "s" })
+}
+
+/// Bind the variables of a pattern to the parts of a value, as in `var [a, b] = pair;`, failing with the way in which
+/// the value does not have the shape that the pattern expects.
+pub(crate) fn destructure(pattern: &Pattern, val: &Value, r: SrcRef, bindings: &mut Vec<(String, Value)>) -> ExecResult<()> {
+    let mismatch = |msg: String| ExecError::At(r, Box::new(ExecError::Custom(msg)));
+    match pattern {
+        Pattern::List(patterns, rest) => {
+            let list = match val {
+                Value::List(l) => l.borrow().clone(),
+                val => return Err(mismatch(format!("Cannot destructure a value of type '{}' as a list.", val.get_type_name()))),
+            };
+            match rest {
+                None if list.len() != patterns.len() => return Err(mismatch(format!(
+                    "Expected a list of {} to destructure, found one of {}.", items(patterns.len()), items(list.len()),
+                ))),
+                Some(_) if list.len() < patterns.len() => return Err(mismatch(format!(
+                    "Expected a list of at least {} to destructure, found one of {}.", items(patterns.len()), items(list.len()),
+                ))),
+                _ => {},
+            }
+            for (item, pattern) in list.iter().zip(patterns.iter()) {
+                destructure(&pattern.0, item, pattern.1, bindings)?;
+            }
+            if let Some(rest) = rest {
+                let tail = list.slice(patterns.len()..list.len()).unwrap();
+                destructure(&rest.0, &Value::new_list(tail), rest.1, bindings)?;
+            }
+            Ok(())
+        },
+        Pattern::Map(entries) => {
+            let map = match val {
+                Value::Map(m) => m.borrow().clone(),
+                val => return Err(mismatch(format!("Cannot destructure a value of type '{}' as a map.", val.get_type_name()))),
+            };
+            for (key, pattern) in entries {
+                match map.get(&Value::from(key.0.as_str())) {
+                    Some(item) => destructure(&pattern.0, item, pattern.1, bindings)?,
+                    None => return Err(ExecError::At(key.1, Box::new(ExecError::Custom(format!(
+                        "The map has no key '{}' to destructure.", key.0,
+                    ))))),
+                }
+            }
+            Ok(())
+        },
+        pattern => if match_pattern(pattern, val, r, bindings)? {
+            Ok(())
+        } else {
+            Err(mismatch(format!(
+                "The value {} does not match the pattern.",
+                val.get_preview_text().unwrap_or_else(|| format!("<{}>", val.get_type_name())),
+            )))
+        },
+    }
+}
@@ -0,0 +1,76 @@
+use std::rc::Rc;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+};
+
+// All of these read through the engine's `Io`, so hosts that provide their own input also receive these questions
+pub(super) const PROMPT: &[(&str, BuiltinFn)] = &[
+    ("confirm", prompt_confirm),
+    ("select", prompt_select),
+    ("password", prompt_password),
+];
+
+// Ask a yes or no question, returning `false` if no answer is given
+fn prompt_confirm(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let msg = string_arg(&args[0], r_args)?;
+    loop {
+        let answer = ctx.input(format!("{} [y/N] ", msg))?;
+        match answer.trim().to_lowercase().as_str() {
+            "y" | "yes" => return Ok(Value::Boolean(true)),
+            "" | "n" | "no" => return Ok(Value::Boolean(false)),
+            _ => ctx.io().print(String::from("Please answer 'y' or 'n'."))?,
+        }
+    }
+}
+
+// List the options and return the one chosen, either by its number or its text. Returns null if no answer is given.
+fn prompt_select(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let msg = string_arg(&args[0], r_args)?;
+    let options = match &args[1] {
+        Value::List(options) => options.borrow().iter().cloned().collect::<Vec<_>>(),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    };
+    if options.is_empty() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("There must be at least one option to select from.")))));
+    }
+    let texts = options
+        .iter()
+        .map(|option| option.get_display_text_with(ctx.display()))
+        .collect::<ExecResult<Vec<_>>>()
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+
+    ctx.io().print(msg)?;
+    for (i, text) in texts.iter().enumerate() {
+        ctx.io().print(format!("  {}) {}", i + 1, text))?;
+    }
+    loop {
+        let answer = ctx.input(String::from("> "))?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            return Ok(Value::Null);
+        }
+        let idx = answer
+            .parse::<usize>().ok()
+            .and_then(|n| n.checked_sub(1))
+            .filter(|idx| *idx < options.len())
+            .or_else(|| texts.iter().position(|text| text == answer));
+        match idx {
+            Some(idx) => return Ok(options[idx].clone()),
+            None => ctx.io().print(format!("Please enter a number from 1 to {}.", options.len()))?,
+        }
+    }
+}
+
+fn prompt_password(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let msg = string_arg(&args[0], r_args)?;
+    Ok(Value::from(ctx.input_hidden(format!("{} ", msg))?))
+}
@@ -0,0 +1,101 @@
+use super::Value;
+
+/// The start, end and step of a range. The step is positive, whichever way the range runs. Ranges of ints are kept as
+/// ints, so that they count exactly however large they are, and only those given a float anywhere are kept as floats.
+#[derive(Clone, Copy, Debug)]
+pub enum Bounds {
+    Int(i64, i64, i64),
+    Float(f64, f64, f64),
+}
+
+impl Bounds {
+    /// The bounds of a range between two numbers, stepping by one, or `None` if either is not a number.
+    pub fn new(start: &Value, end: &Value) -> Option<Self> {
+        Self::with_step(start, end, &Value::Int(1))
+    }
+
+    /// The bounds of a range between two numbers, stepping by a third, or `None` if any is not a number.
+    pub fn with_step(start: &Value, end: &Value, step: &Value) -> Option<Self> {
+        match (start, end, step) {
+            (Value::Int(start), Value::Int(end), Value::Int(step)) => Some(Bounds::Int(*start, *end, *step)),
+            (start, end, step) => Some(Bounds::Float(start.as_number()?, end.as_number()?, step.as_number()?)),
+        }
+    }
+
+    /// The start, end and step, as ints or floats.
+    pub fn parts(&self) -> [Value; 3] {
+        match *self {
+            Bounds::Int(start, end, step) => [Value::Int(start), Value::Int(end), Value::Int(step)],
+            Bounds::Float(start, end, step) => [Value::Float(start), Value::Float(end), Value::Float(step)],
+        }
+    }
+
+    /// The start and end of a range that steps by one, as used to slice strings and lists.
+    pub fn unit(&self) -> Option<(i64, i64)> {
+        match *self {
+            Bounds::Int(start, end, 1) => Some((start, end)),
+            Bounds::Float(start, end, 1.0) => Some((start as i64, end as i64)),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> RangeIter {
+        match *self {
+            Bounds::Int(start, end, step) => RangeIter::Int {
+                next: Some(start),
+                end,
+                step: if end < start { -step } else { step },
+            },
+            Bounds::Float(start, end, step) => RangeIter::Float {
+                start,
+                end,
+                step: if end < start { -step } else { step },
+                i: 0,
+                ints: start.fract() == 0.0 && step.fract() == 0.0,
+            },
+        }
+    }
+}
+
+/// Iterates over the numbers of a range, from its start towards its end (which is excluded) in increments of its step.
+/// Ranges whose end is below their start run downwards. Float ranges produce ints when the start and the step are
+/// integral.
+pub enum RangeIter {
+    Int {
+        next: Option<i64>,
+        end: i64,
+        step: i64,
+    },
+    Float {
+        start: f64,
+        end: f64,
+        step: f64,
+        i: u64,
+        ints: bool,
+    },
+}
+
+impl Iterator for RangeIter {
+    type Item = Value;
+
+    fn next(&mut self) -> Option<Value> {
+        match self {
+            RangeIter::Int { next, end, step } => {
+                let x = next.filter(|x| (*step > 0 && x < end) || (*step < 0 && x > end))?;
+                // A step past the largest or smallest int ends the range, since the end cannot be beyond it
+                *next = x.checked_add(*step);
+                Some(Value::Int(x))
+            },
+            RangeIter::Float { start, end, step, i, ints } => {
+                // Multiplying rather than accumulating keeps fractional steps from drifting
+                let x = *start + *i as f64 * *step;
+                if (*step > 0.0 && x < *end) || (*step < 0.0 && x > *end) {
+                    *i += 1;
+                    Some(if *ints { Value::Int(x as i64) } else { Value::Float(x) })
+                } else {
+                    None
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use std::{
+    fmt,
+    rc::Rc,
+};
+use crate::{
+    output::{self, DiagnosticStyle},
+    parser::{SrcLoc, SrcRef},
+};
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    error_value::ErrorValue,
+    method,
+};
+
+pub(super) const REFLECT: &[(&str, BuiltinFn)] = &[
+    ("source_ref", reflect_source_ref),
+];
+
+// The keys of a map describing a location, in the order that `SrcRef` stores them
+const KEYS: [&str; 4] = ["line", "col", "end_line", "end_col"];
+
+// The text between two locations, which count lines and columns from 1 and exclude the column that they end at
+fn text_in(src: &str, r: SrcRef) -> Option<String> {
+    let ((line, col), (end_line, end_col)) = (r.start().pos()?, r.limit().pos()?);
+    let lines = src.lines().collect::<Vec<_>>();
+    let mut text = String::new();
+    for n in line..=end_line {
+        let chars = lines.get(n - 1)?.chars().collect::<Vec<_>>();
+        let from = if n == line { col - 1 } else { 0 };
+        let to = if n == end_line { end_col - 1 } else { chars.len() };
+        if n > line {
+            text.push('\n');
+        }
+        text.extend(chars.get(from..to.min(chars.len()))?);
+    }
+    Some(text)
+}
+
+// `reflect.source_ref(x)` describes where a function was defined, or where a caught error happened, as a map of the
+// `line` and `col` that it starts at, the `end_line` and `end_col` that it stops before, and (for functions) the
+// `text` in between. Values with no location in the code, such as builtin functions, produce null.
+fn reflect_source_ref(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let (r, src) = match &args[0] {
+        Value::Fn(code, f, _) => ((f.0).1.union(&(f.1).1), Some(code.clone())),
+        val => match val.downcast_ref::<ErrorValue>().and_then(|err| err.src_ref()) {
+            Some(r) => (r, None),
+            None => return Ok(Value::Null),
+        },
+    };
+    let (start, limit) = match (r.start().pos(), r.limit().pos()) {
+        (Some(start), Some(limit)) => (start, limit),
+        _ => return Ok(Value::Null),
+    };
+
+    let mut map = Map::new();
+    for (key, n) in KEYS.iter().zip(&[start.0, start.1, limit.0, limit.1]) {
+        map.insert(Value::from(*key), Value::Int(*n as i64));
+    }
+    map.insert(Value::from("text"), Value::from(src.and_then(|src| text_in(&src, r))));
+    Ok(Value::from(map))
+}
+
+struct Rendered<'a>(SrcRef, &'a str);
+
+impl fmt::Display for Rendered<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        output::fmt_ref(f, self.0, Some(self.1), 0, DiagnosticStyle::Plain)
+    }
+}
+
+/// `sys.render_ref(ref)` renders a location, as produced by `reflect.source_ref`, the way that errors show where they
+/// happened: the line it is on, with carets beneath it. The location is taken to be in the code that calls it, unless
+/// the source it is in is given after it.
+pub(super) fn sys_render_ref(args: Vec<Value>, _ctx: &mut ExecCtx, src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let invalid = || ExecError::At(r_args, Box::new(ExecError::Custom(format!(
+        "Expected a map with {} to render, as produced by `reflect.source_ref`.",
+        KEYS.iter().map(|key| format!("'{}'", key)).collect::<Vec<_>>().join(", "),
+    ))));
+
+    let map = match &args[0] {
+        Value::Map(m) => m.borrow().clone(),
+        _ => return Err(invalid()),
+    };
+    let mut pos = [0; 4];
+    for (key, n) in KEYS.iter().zip(pos.iter_mut()) {
+        *n = match map.get(&Value::from(*key)) {
+            Some(Value::Int(x)) if *x > 0 => *x as usize,
+            _ => return Err(invalid()),
+        };
+    }
+    if (pos[2], pos[3]) < (pos[0], pos[1]) {
+        return Err(invalid());
+    }
+    let at = |line, col| SrcLoc::At { line, col, start_of_line: col == 1 };
+    let r = SrcRef::many(at(pos[0], pos[1]), at(pos[2], pos[3]));
+
+    let text = match args.get(1) {
+        Some(val) => string_arg(val, r_args)?,
+        None => src.to_string(),
+    };
+    Ok(Value::from(Rendered(r, &text).to_string()))
+}
@@ -0,0 +1,655 @@
+use std::rc::Rc;
+use hashbrown::HashSet;
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+};
+
+pub(super) const REGEX: &[(&str, BuiltinFn)] = &[
+    ("match", regex_match),
+    ("find_all", regex_find_all),
+    ("replace", regex_replace),
+    ("split", regex_split),
+];
+
+// Groups may not be nested more deeply than this, nor may a counted repetition repeat more than this many times
+const MAX_DEPTH: usize = 64;
+const MAX_REPEAT: usize = 1000;
+
+// The largest program that a pattern may compile to, to keep the memory needed to match it reasonable
+const MAX_INSTS: usize = 10_000;
+
+#[derive(Clone, Copy)]
+enum Assertion {
+    Start,
+    End,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Assertion {
+    fn holds(self, text: &[char], i: usize) -> bool {
+        let word_at = |i: Option<usize>| i.and_then(|i| text.get(i)).map(|c| is_word(*c)).unwrap_or(false);
+        let at_boundary = word_at(i.checked_sub(1)) != word_at(Some(i));
+        match self {
+            Assertion::Start => i == 0,
+            Assertion::End => i == text.len(),
+            Assertion::WordBoundary => at_boundary,
+            Assertion::NotWordBoundary => !at_boundary,
+        }
+    }
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// The items of a character class, such as the `a-z` and `\d` of `[a-z\d]`
+#[derive(Clone)]
+enum ClassItem {
+    Range(char, char),
+    Digit(bool),
+    Word(bool),
+    Space(bool),
+}
+
+impl ClassItem {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            ClassItem::Range(from, to) => *from <= c && c <= *to,
+            ClassItem::Digit(want) => c.is_ascii_digit() == *want,
+            ClassItem::Word(want) => is_word(c) == *want,
+            ClassItem::Space(want) => c.is_whitespace() == *want,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Class {
+    items: Vec<ClassItem>,
+    negated: bool,
+}
+
+impl Class {
+    fn of(item: ClassItem) -> Self {
+        Class { items: vec![item], negated: false }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.matches(c)) != self.negated
+    }
+}
+
+#[derive(Clone)]
+enum Node {
+    Char(char),
+    // Any character other than a newline
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Group(Box<Node>, Option<usize>), // The pattern, and the index of its capture if it has one
+    Concat(Vec<Node>),
+    Alt(Vec<Node>),
+    Repeat(Box<Node>, usize, Option<usize>, bool), // The pattern, the fewest and most repetitions, and greediness
+}
+
+// Reads a pattern such as `(\d+)-(?P<word>\w*)`
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+    depth: usize,
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn error<T>(&self, msg: &str) -> Result<T, String> {
+        Err(format!("{} at position {}", msg, self.pos))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn read_alt(&mut self) -> Result<Node, String> {
+        let mut alts = vec![self.read_concat()?];
+        while self.eat('|') {
+            alts.push(self.read_concat()?);
+        }
+        Ok(if alts.len() == 1 { alts.pop().unwrap() } else { Node::Alt(alts) })
+    }
+
+    fn read_concat(&mut self) -> Result<Node, String> {
+        let mut items = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.read_atom()?;
+            items.push(self.read_repeat(atom)?);
+        }
+        Ok(if items.len() == 1 { items.pop().unwrap() } else { Node::Concat(items) })
+    }
+
+    fn read_num(&mut self) -> Option<usize> {
+        let from = self.pos;
+        while self.peek().map(|c| c.is_ascii_digit()).unwrap_or(false) {
+            self.pos += 1;
+        }
+        self.chars[from..self.pos].iter().collect::<String>().parse().ok()
+    }
+
+    // A count such as the `2,5` of `{2,5}`, or nothing if the brace is not the start of one
+    fn read_count(&mut self) -> Option<(usize, Option<usize>)> {
+        let start = self.pos;
+        let count = self.read_num().and_then(|min| if self.eat(',') {
+            match self.peek() {
+                Some('}') => Some((min, None)),
+                _ => self.read_num().map(|max| (min, Some(max))),
+            }
+        } else {
+            Some((min, Some(min)))
+        });
+        match count {
+            Some(count) if self.eat('}') => Some(count),
+            _ => {
+                self.pos = start;
+                None
+            },
+        }
+    }
+
+    fn read_repeat(&mut self, mut atom: Node) -> Result<Node, String> {
+        loop {
+            let (min, max) = match self.peek() {
+                Some('*') => { self.pos += 1; (0, None) },
+                Some('+') => { self.pos += 1; (1, None) },
+                Some('?') => { self.pos += 1; (0, Some(1)) },
+                Some('{') => {
+                    self.pos += 1;
+                    match self.read_count() {
+                        Some(count) => count,
+                        None => {
+                            self.pos -= 1;
+                            return Ok(atom);
+                        },
+                    }
+                },
+                _ => return Ok(atom),
+            };
+            if max.map(|max| max < min).unwrap_or(false) {
+                return self.error("Repetition has a minimum greater than its maximum");
+            } else if min.max(max.unwrap_or(0)) > MAX_REPEAT {
+                return self.error(&format!("Repetition may be at most {} times", MAX_REPEAT));
+            }
+            let greedy = !self.eat('?');
+            atom = Node::Repeat(Box::new(atom), min, max, greedy);
+        }
+    }
+
+    fn read_escape(&mut self) -> Result<Result<char, ClassItem>, String> {
+        let c = match self.peek() {
+            Some(c) => c,
+            None => return self.error("Expected a character after '\\'"),
+        };
+        self.pos += 1;
+        Ok(match c {
+            'd' | 'D' => Err(ClassItem::Digit(c == 'd')),
+            'w' | 'W' => Err(ClassItem::Word(c == 'w')),
+            's' | 'S' => Err(ClassItem::Space(c == 's')),
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            c if c.is_alphanumeric() => {
+                self.pos -= 1;
+                return self.error(&format!("Unknown escape '\\{}'", c));
+            },
+            c => Ok(c),
+        })
+    }
+
+    fn read_class(&mut self) -> Result<Node, String> {
+        let negated = self.eat('^');
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let from = match self.peek() {
+                None => return self.error("Expected ']' to close the character class"),
+                // A `]` that comes first is part of the class
+                Some(']') if !first => {
+                    self.pos += 1;
+                    return Ok(Node::Class(Class { items, negated }));
+                },
+                Some('\\') => {
+                    self.pos += 1;
+                    match self.read_escape()? {
+                        Ok(c) => c,
+                        Err(item) => {
+                            items.push(item);
+                            first = false;
+                            continue;
+                        },
+                    }
+                },
+                Some(c) => { self.pos += 1; c },
+            };
+            first = false;
+            // A `-` between two characters makes a range, and is otherwise itself
+            let to = match (self.peek(), self.chars.get(self.pos + 1)) {
+                (Some('-'), Some(to)) if *to != ']' => {
+                    self.pos += 2;
+                    match to {
+                        '\\' => match self.read_escape()? {
+                            Ok(to) => to,
+                            Err(_) => return self.error("A character class cannot be the end of a range"),
+                        },
+                        to => *to,
+                    }
+                },
+                _ => from,
+            };
+            if to < from {
+                return self.error("A range must not end before it starts");
+            }
+            items.push(ClassItem::Range(from, to));
+        }
+    }
+
+    fn read_group(&mut self) -> Result<Node, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return self.error("Groups are nested too deeply");
+        }
+        let capture = if self.eat('?') {
+            if self.eat(':') {
+                None
+            } else if self.eat('<') || (self.eat('P') && self.eat('<')) {
+                let from = self.pos;
+                while self.peek().map(is_word).unwrap_or(false) {
+                    self.pos += 1;
+                }
+                let name = self.chars[from..self.pos].iter().collect::<String>();
+                if name.is_empty() || !self.eat('>') {
+                    return self.error("Expected the name of the group, followed by '>'");
+                } else if self.names.iter().any(|(other, _)| *other == name) {
+                    return self.error(&format!("There is already a group named '{}'", name));
+                }
+                self.groups += 1;
+                self.names.push((name, self.groups));
+                Some(self.groups)
+            } else {
+                return self.error("Expected ':' or a group name after '(?'");
+            }
+        } else {
+            self.groups += 1;
+            Some(self.groups)
+        };
+        let inner = self.read_alt()?;
+        if !self.eat(')') {
+            return self.error("Expected ')' to close the group");
+        }
+        self.depth -= 1;
+        Ok(Node::Group(Box::new(inner), capture))
+    }
+
+    fn read_atom(&mut self) -> Result<Node, String> {
+        let c = self.peek().unwrap();
+        self.pos += 1;
+        Ok(match c {
+            '(' => self.read_group()?,
+            '[' => self.read_class()?,
+            '.' => Node::Any,
+            '^' => Node::Assert(Assertion::Start),
+            '$' => Node::Assert(Assertion::End),
+            '\\' => match self.peek() {
+                Some('b') => { self.pos += 1; Node::Assert(Assertion::WordBoundary) },
+                Some('B') => { self.pos += 1; Node::Assert(Assertion::NotWordBoundary) },
+                _ => match self.read_escape()? {
+                    Ok(c) => Node::Char(c),
+                    Err(item) => Node::Class(Class::of(item)),
+                },
+            },
+            '*' | '+' | '?' => {
+                self.pos -= 1;
+                return self.error(&format!("There is nothing for '{}' to repeat", c));
+            },
+            c => Node::Char(c),
+        })
+    }
+}
+
+// Beyond this many states, those that have been tried are kept in a set rather than a bitmap of every state
+const MAX_BITMAP: usize = 1 << 24;
+
+enum Visited {
+    Bitmap(Vec<u64>),
+    Set(HashSet<usize>),
+}
+
+impl Visited {
+    fn new(states: usize) -> Self {
+        if states <= MAX_BITMAP {
+            Visited::Bitmap(vec![0; (states + 63) / 64])
+        } else {
+            Visited::Set(HashSet::new())
+        }
+    }
+
+    // Whether the state had not been tried before
+    fn insert(&mut self, state: usize) -> bool {
+        match self {
+            Visited::Bitmap(bits) => {
+                let (word, bit) = (state / 64, 1 << (state % 64));
+                let new = bits[word] & bit == 0;
+                bits[word] |= bit;
+                new
+            },
+            Visited::Set(set) => set.insert(state),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Assert(Assertion),
+    Split(usize, usize), // Try the first, then the second
+    Jmp(usize),
+    Save(usize),
+    Match,
+}
+
+/// A compiled regular expression. Matching backtracks, but never tries the same instruction at the same position twice,
+/// so it takes time in proportion to the length of the text and the size of the pattern at worst.
+struct Regex {
+    insts: Vec<Inst>,
+    groups: usize,
+    names: Vec<(String, usize)>,
+}
+
+// The start and end of each group, with the whole match as the first
+type Captures = Vec<Option<(usize, usize)>>;
+
+impl Regex {
+    fn new(pattern: &str) -> Result<Self, String> {
+        let chars = pattern.chars().collect::<Vec<_>>();
+        let mut parser = Parser { chars: &chars, pos: 0, depth: 0, groups: 0, names: Vec::new() };
+        let node = parser.read_alt()?;
+        if parser.pos < chars.len() {
+            return parser.error("Unmatched ')'");
+        }
+
+        let mut regex = Regex { insts: vec![Inst::Save(0)], groups: parser.groups, names: parser.names };
+        regex.compile(&node)?;
+        regex.insts.push(Inst::Save(1));
+        regex.insts.push(Inst::Match);
+        Ok(regex)
+    }
+
+    fn emit(&mut self, inst: Inst) -> Result<usize, String> {
+        if self.insts.len() >= MAX_INSTS {
+            return Err(String::from("The pattern is too large"));
+        }
+        self.insts.push(inst);
+        Ok(self.insts.len() - 1)
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(), String> {
+        match node {
+            Node::Char(c) => { self.emit(Inst::Char(*c))?; },
+            Node::Any => { self.emit(Inst::Any)?; },
+            Node::Class(class) => { self.emit(Inst::Class(class.clone()))?; },
+            Node::Assert(assertion) => { self.emit(Inst::Assert(*assertion))?; },
+            Node::Group(inner, None) => self.compile(inner)?,
+            Node::Group(inner, Some(n)) => {
+                self.emit(Inst::Save(n * 2))?;
+                self.compile(inner)?;
+                self.emit(Inst::Save(n * 2 + 1))?;
+            },
+            Node::Concat(items) => for item in items {
+                self.compile(item)?;
+            },
+            Node::Alt(alts) => {
+                let mut jumps = Vec::new();
+                for (i, alt) in alts.iter().enumerate() {
+                    if i + 1 < alts.len() {
+                        let split = self.emit(Inst::Split(0, 0))?;
+                        self.compile(alt)?;
+                        jumps.push(self.emit(Inst::Jmp(0))?);
+                        self.insts[split] = Inst::Split(split + 1, self.insts.len());
+                    } else {
+                        self.compile(alt)?;
+                    }
+                }
+                for jump in jumps {
+                    self.insts[jump] = Inst::Jmp(self.insts.len());
+                }
+            },
+            Node::Repeat(inner, min, max, greedy) => {
+                let split = |body, out| if *greedy { Inst::Split(body, out) } else { Inst::Split(out, body) };
+                for _ in 0..*min {
+                    self.compile(inner)?;
+                }
+                match max {
+                    None => {
+                        let start = self.emit(Inst::Split(0, 0))?;
+                        self.compile(inner)?;
+                        self.emit(Inst::Jmp(start))?;
+                        self.insts[start] = split(start + 1, self.insts.len());
+                    },
+                    Some(max) => {
+                        // Each optional repetition skips to the end when it does not match
+                        let mut splits = Vec::new();
+                        for _ in *min..*max {
+                            splits.push(self.emit(Inst::Split(0, 0))?);
+                            self.compile(inner)?;
+                        }
+                        for at in splits {
+                            self.insts[at] = split(at + 1, self.insts.len());
+                        }
+                    },
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Find the first match that starts at or after a position in the text.
+    fn find_at(&self, text: &[char], from: usize) -> Option<Captures> {
+        // Whether an instruction failed to lead to a match from a position does not depend on where the search started
+        let width = text.len() + 1;
+        let mut visited = Visited::new(self.insts.len() * width);
+        let mut slots = vec![None; (self.groups + 1) * 2];
+        enum Job {
+            Try(usize, usize),
+            Restore(usize, Option<usize>),
+        }
+
+        for start in from..=text.len() {
+            let mut stack = vec![Job::Try(0, start)];
+            while let Some(job) = stack.pop() {
+                let (mut pc, mut i) = match job {
+                    Job::Try(pc, i) => (pc, i),
+                    Job::Restore(slot, val) => {
+                        slots[slot] = val;
+                        continue;
+                    },
+                };
+                loop {
+                    if !visited.insert(pc * width + i) {
+                        break;
+                    }
+                    match &self.insts[pc] {
+                        Inst::Char(c) if text.get(i) == Some(c) => { pc += 1; i += 1; },
+                        Inst::Any if text.get(i).map(|c| *c != '\n').unwrap_or(false) => { pc += 1; i += 1; },
+                        Inst::Class(class) if text.get(i).map(|c| class.matches(*c)).unwrap_or(false) => { pc += 1; i += 1; },
+                        Inst::Assert(assertion) if assertion.holds(text, i) => pc += 1,
+                        Inst::Split(first, second) => {
+                            stack.push(Job::Try(*second, i));
+                            pc = *first;
+                        },
+                        Inst::Jmp(to) => pc = *to,
+                        Inst::Save(slot) => {
+                            stack.push(Job::Restore(*slot, slots[*slot]));
+                            slots[*slot] = Some(i);
+                            pc += 1;
+                        },
+                        Inst::Match => return Some(slots
+                            .chunks(2)
+                            .map(|pair| match pair {
+                                [Some(from), Some(to)] => Some((*from, *to)),
+                                _ => None,
+                            })
+                            .collect()),
+                        _ => break,
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Each match in the text, none of which overlap.
+    fn find_iter<'a>(&'a self, text: &'a [char]) -> impl Iterator<Item = Captures> + 'a {
+        let mut from = Some(0);
+        std::iter::from_fn(move || {
+            let caps = self.find_at(text, from?)?;
+            let (start, end) = caps[0].unwrap();
+            // An empty match would otherwise be found again
+            from = if end > start { Some(end) } else if end < text.len() { Some(end + 1) } else { None };
+            Some(caps)
+        })
+    }
+
+    // A map of the `text`, `start` and `end` of a match, the `groups` in it as a list and the named groups as `named`
+    fn match_value(&self, text: &[char], caps: &Captures) -> Value {
+        let group_text = |n: usize| caps[n].map(|(from, to)| text[from..to].iter().collect::<String>());
+        let (start, end) = caps[0].unwrap();
+
+        let mut map = Map::new();
+        map.insert(Value::from("text"), Value::from(group_text(0)));
+        map.insert(Value::from("start"), Value::Int(start as i64));
+        map.insert(Value::from("end"), Value::Int(end as i64));
+        map.insert(Value::from("groups"), Value::from((1..caps.len()).map(|n| Value::from(group_text(n))).collect::<Vec<_>>()));
+        map.insert(Value::from("named"), Value::from(self.names
+            .iter()
+            .map(|(name, n)| (Value::from(name.as_str()), Value::from(group_text(*n))))
+            .collect::<Map>()));
+        Value::from(map)
+    }
+}
+
+fn regex_arg(val: &Value, r_args: SrcRef) -> ExecResult<Regex> {
+    Regex::new(&string_arg(val, r_args)?).map_err(|err| ExecError::At(r_args, Box::new(ExecError::Custom(format!(
+        "Invalid regular expression: {}.", err,
+    )))))
+}
+
+// `regex.match(pattern, s)` produces the first match in `s`, or null if there is none
+fn regex_match(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let regex = regex_arg(&args[0], r_args)?;
+    let text = string_arg(&args[1], r_args)?.chars().collect::<Vec<_>>();
+    Ok(regex.find_at(&text, 0).map(|caps| regex.match_value(&text, &caps)).unwrap_or(Value::Null))
+}
+
+fn regex_find_all(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let regex = regex_arg(&args[0], r_args)?;
+    let text = string_arg(&args[1], r_args)?.chars().collect::<Vec<_>>();
+    Ok(Value::from(regex.find_iter(&text).map(|caps| regex.match_value(&text, &caps)).collect::<Vec<_>>()))
+}
+
+// Expands `$1`, `$name` and `$$` in the replacement for a match
+fn expand(regex: &Regex, replacement: &str, text: &[char], caps: &Captures, out: &mut String) {
+    let group = |n: usize| caps.get(n).copied().flatten().map(|(from, to)| &text[from..to]);
+    let mut chars = replacement.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            out.push(c);
+            continue;
+        }
+        let mut name = String::new();
+        while let Some(c) = chars.peek().copied().filter(|c| is_word(*c)) {
+            name.push(c);
+            chars.next();
+        }
+        let found = match name.parse::<usize>() {
+            _ if name.is_empty() => {
+                if chars.peek() == Some(&'$') {
+                    chars.next();
+                }
+                out.push('$');
+                continue;
+            },
+            Ok(n) => group(n),
+            Err(_) => regex.names.iter().find(|(other, _)| *other == name).and_then(|(_, n)| group(*n)),
+        };
+        out.extend(found.unwrap_or(&[]));
+    }
+}
+
+// `regex.replace(pattern, s, replacement)` replaces every match, with a string in which `$1` or `$name` stands for a
+// group, or with what a function produces when given the match
+fn regex_replace(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 3, 3, r_args)?;
+    let regex = regex_arg(&args[0], r_args)?;
+    let text = string_arg(&args[1], r_args)?.chars().collect::<Vec<_>>();
+    let replacement = &args[2];
+    if !replacement.is_function() && replacement.as_string().is_none() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string or function"), replacement.get_type_name()))));
+    }
+
+    let mut out = String::new();
+    let mut last = 0;
+    for caps in regex.find_iter(&text) {
+        let (start, end) = caps[0].unwrap();
+        out.extend(&text[last..start]);
+        match replacement.as_string() {
+            Some(replacement) => expand(&regex, &replacement, &text, &caps, &mut out),
+            None => {
+                let val = replacement.call(vec![regex.match_value(&text, &caps)], ctx, src, r_caller, r_args)?;
+                out.push_str(&val.get_display_text()?);
+            },
+        }
+        last = end;
+    }
+    out.extend(&text[last..]);
+    Ok(Value::from(out))
+}
+
+fn regex_split(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let regex = regex_arg(&args[0], r_args)?;
+    let text = string_arg(&args[1], r_args)?.chars().collect::<Vec<_>>();
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for caps in regex.find_iter(&text) {
+        let (start, end) = caps[0].unwrap();
+        // An empty match at the very start or end does not make an empty part
+        if end == 0 || start == text.len() {
+            continue;
+        }
+        parts.push(Value::from(text[last..start].iter().collect::<String>()));
+        last = end;
+    }
+    parts.push(Value::from(text[last..].iter().collect::<String>()));
+    Ok(Value::from(parts))
+}
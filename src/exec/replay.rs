@@ -0,0 +1,407 @@
+use std::{
+    fs,
+    io,
+    path::Path,
+};
+use super::{
+    ExecError,
+    ExecResult,
+    Map,
+    Rng,
+    Value,
+    range::Bounds,
+};
+
+// The start of every recording, and the version of the format that it is in
+const MAGIC: &[u8] = b"forge-replay 1\n";
+
+// Lists and maps nested more deeply than this are assumed to contain themselves
+const MAX_DEPTH: usize = 64;
+
+/// Everything that the code in an engine received from outside of it while it ran: the seed of its random number
+/// generator, the lines read by `input` and the `prompt` builtins, the times read by `datetime.now()`, and the results
+/// of the host functions that it called.
+///
+/// An engine built with `EngineBuilder::record` keeps a recording, which can be saved to a file. An engine built with
+/// `EngineBuilder::replay` takes all of those inputs from a recording instead, so the same code runs exactly as it did
+/// when it was recorded, rare failures included, without any host functions being called. Files, environment
+/// variables and the network are not recorded.
+#[derive(Clone, Debug, Default)]
+pub struct Recording {
+    events: Vec<Event>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Event {
+    Seed(u64),
+    Input(Result<String, String>),
+    Time(i128),
+    // Values are kept encoded, since the code may go on to change the values that it was given
+    Host(String, Result<Vec<u8>, String>),
+}
+
+impl Event {
+    fn describe(&self) -> String {
+        match self {
+            Event::Seed(_) => String::from("the random seed"),
+            Event::Input(_) => String::from("a line of input"),
+            Event::Time(_) => String::from("the time"),
+            Event::Host(name, _) => format!("the result of '{}'", name),
+        }
+    }
+}
+
+impl Recording {
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a Forge recording, or a damaged one"))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = MAGIC.to_vec();
+        for event in &self.events {
+            match event {
+                Event::Seed(seed) => {
+                    buf.push(b's');
+                    buf.extend_from_slice(&seed.to_le_bytes());
+                },
+                Event::Input(Ok(line)) => {
+                    buf.push(b'i');
+                    write_str(&mut buf, line);
+                },
+                Event::Input(Err(msg)) => {
+                    buf.push(b'I');
+                    write_str(&mut buf, msg);
+                },
+                Event::Time(nanos) => {
+                    buf.push(b't');
+                    buf.extend_from_slice(&nanos.to_le_bytes());
+                },
+                Event::Host(name, res) => {
+                    buf.push(b'h');
+                    write_str(&mut buf, name);
+                    match res {
+                        Ok(val) => {
+                            buf.push(b'v');
+                            write_len(&mut buf, val.len());
+                            buf.extend_from_slice(val);
+                        },
+                        Err(msg) => {
+                            buf.push(b'e');
+                            write_str(&mut buf, msg);
+                        },
+                    }
+                },
+            }
+        }
+        buf
+    }
+
+    /// Read a recording made by `to_bytes`, or produce `None` if it is damaged.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let mut reader = Reader { bytes: bytes.strip_prefix(MAGIC)?, pos: 0 };
+        let mut events = Vec::new();
+        while let Some(tag) = reader.byte() {
+            events.push(match tag {
+                b's' => Event::Seed(u64::from_le_bytes(reader.array()?)),
+                b'i' => Event::Input(Ok(reader.string()?)),
+                b'I' => Event::Input(Err(reader.string()?)),
+                b't' => Event::Time(i128::from_le_bytes(reader.array()?)),
+                b'h' => {
+                    let name = reader.string()?;
+                    match reader.byte()? {
+                        b'v' => {
+                            let len = reader.len()?;
+                            let val = reader.take(len)?.to_vec();
+                            decode(&val)?;
+                            Event::Host(name, Ok(val))
+                        },
+                        b'e' => Event::Host(name, Err(reader.string()?)),
+                        _ => return None,
+                    }
+                },
+                _ => return None,
+            });
+        }
+        // Every recording starts with the seed, and has only the one
+        match events.iter().filter(|event| matches!(event, Event::Seed(_))).count() {
+            1 if matches!(events.first(), Some(Event::Seed(_))) => Some(Self { events }),
+            _ => None,
+        }
+    }
+}
+
+/// Whether an engine is keeping a recording, or replaying one.
+pub(crate) enum Replay {
+    Record(Recording),
+    // The recording, and the number of its events that have been replayed so far
+    Replay(Recording, usize),
+}
+
+impl Replay {
+    /// Choose the seed of the engine's random number generator: the one in the recording when replaying, or else the
+    /// given one (or a random one), which is recorded.
+    pub(crate) fn seed(&mut self, seed: Option<u64>) -> u64 {
+        match self {
+            Replay::Record(recording) => {
+                let seed = seed.unwrap_or_else(|| Rng::from_entropy().next_u64());
+                recording.events.push(Event::Seed(seed));
+                seed
+            },
+            Replay::Replay(recording, pos) => match recording.events.first() {
+                Some(Event::Seed(seed)) => {
+                    *pos = 1;
+                    *seed
+                },
+                _ => unreachable!("recordings always start with their seed"),
+            },
+        }
+    }
+
+    fn next(&mut self, expected: String) -> ExecResult<&Event> {
+        match self {
+            Replay::Replay(recording, pos) => match recording.events.get(*pos) {
+                Some(event) => {
+                    *pos += 1;
+                    Ok(event)
+                },
+                None => Err(ExecError::Custom(format!(
+                    "The code asked for {} after the end of the recording, so it cannot be replayed. Has it changed \
+                    since it was recorded?",
+                    expected,
+                ))),
+            },
+            Replay::Record(_) => unreachable!("only replays have events to take"),
+        }
+    }
+
+    fn diverged(expected: String, event: &Event) -> ExecError {
+        ExecError::Custom(format!(
+            "The code asked for {} where the recording has {}, so it cannot be replayed. Has it changed since it was \
+            recorded?",
+            expected, event.describe(),
+        ))
+    }
+
+    pub(crate) fn input(&mut self, read: impl FnOnce() -> ExecResult<String>) -> ExecResult<String> {
+        match self {
+            Replay::Record(recording) => {
+                let res = read();
+                recording.events.push(Event::Input(res.as_ref().map(|line| line.clone()).map_err(|err| err.message())));
+                res
+            },
+            Replay::Replay(_, _) => match self.next(String::from("a line of input"))? {
+                Event::Input(Ok(line)) => Ok(line.clone()),
+                Event::Input(Err(msg)) => Err(ExecError::Custom(msg.clone())),
+                event => Err(Self::diverged(String::from("a line of input"), event)),
+            },
+        }
+    }
+
+    pub(crate) fn time(&mut self, now: impl FnOnce() -> i128) -> ExecResult<i128> {
+        match self {
+            Replay::Record(recording) => {
+                let nanos = now();
+                recording.events.push(Event::Time(nanos));
+                Ok(nanos)
+            },
+            Replay::Replay(_, _) => match self.next(String::from("the time"))? {
+                Event::Time(nanos) => Ok(*nanos),
+                event => Err(Self::diverged(String::from("the time"), event)),
+            },
+        }
+    }
+
+    /// Call a host function while recording, or produce the result that it had in the recording without calling it.
+    pub(crate) fn host(&mut self, name: &str, call: impl FnOnce() -> ExecResult<Value>) -> ExecResult<Value> {
+        let expected = format!("the result of '{}'", name);
+        match self {
+            Replay::Record(recording) => {
+                let res = call();
+                // Results that cannot be recorded fail only when they are replayed, so recording never changes what
+                // the code does
+                let recorded = match &res {
+                    Ok(val) => {
+                        let mut buf = Vec::new();
+                        match encode(val, &mut buf, 0) {
+                            Ok(()) => Ok(buf),
+                            Err(msg) => Err(format!("The result of '{}' could not be recorded: {}", name, msg)),
+                        }
+                    },
+                    Err(err) => Err(err.message()),
+                };
+                recording.events.push(Event::Host(name.to_string(), recorded));
+                res
+            },
+            Replay::Replay(_, _) => match self.next(expected.clone())? {
+                Event::Host(recorded, res) if recorded == name => match res {
+                    Ok(val) => Ok(decode(val).expect("recordings are checked when they are read")),
+                    Err(msg) => Err(ExecError::Custom(msg.clone())),
+                },
+                event => Err(Self::diverged(expected, event)),
+            },
+        }
+    }
+
+    pub(crate) fn recording(&self) -> Option<&Recording> {
+        match self {
+            Replay::Record(recording) => Some(recording),
+            Replay::Replay(_, _) => None,
+        }
+    }
+}
+
+fn write_len(buf: &mut Vec<u8>, len: usize) {
+    buf.extend_from_slice(&(len as u32).to_le_bytes());
+}
+
+fn write_str(buf: &mut Vec<u8>, s: &str) {
+    write_len(buf, s.len());
+    buf.extend_from_slice(s.as_bytes());
+}
+
+fn encode(val: &Value, buf: &mut Vec<u8>, depth: usize) -> Result<(), String> {
+    if depth > MAX_DEPTH {
+        return Err(String::from("it is nested too deeply. Does it contain itself?"));
+    }
+    match val {
+        Value::Null => buf.push(b'n'),
+        Value::Boolean(b) => buf.push(if *b { b't' } else { b'f' }),
+        Value::Int(x) => {
+            buf.push(b'i');
+            buf.extend_from_slice(&x.to_le_bytes());
+        },
+        Value::Float(x) => {
+            buf.push(b'd');
+            buf.extend_from_slice(&x.to_bits().to_le_bytes());
+        },
+        Value::Char(c) => {
+            buf.push(b'c');
+            buf.extend_from_slice(&(*c as u32).to_le_bytes());
+        },
+        Value::String(s) => {
+            buf.push(b's');
+            write_str(buf, &s.borrow());
+        },
+        Value::Range(Bounds::Int(start, end, step)) => {
+            buf.push(b'R');
+            for x in &[start, end, step] {
+                buf.extend_from_slice(&x.to_le_bytes());
+            }
+        },
+        Value::Range(Bounds::Float(start, end, step)) => {
+            buf.push(b'r');
+            for x in &[start, end, step] {
+                buf.extend_from_slice(&x.to_bits().to_le_bytes());
+            }
+        },
+        Value::List(l) => {
+            let l = l.borrow();
+            buf.push(b'l');
+            write_len(buf, l.len());
+            for item in l.iter() {
+                encode(item, buf, depth + 1)?;
+            }
+        },
+        Value::Map(m) => {
+            let m = m.borrow();
+            buf.push(b'm');
+            write_len(buf, m.len());
+            for (key, val) in m.iter() {
+                encode(key, buf, depth + 1)?;
+                encode(val, buf, depth + 1)?;
+            }
+        },
+        val => return Err(format!("values of type '{}' cannot be recorded.", val.get_type_name())),
+    }
+    Ok(())
+}
+
+fn decode(bytes: &[u8]) -> Option<Value> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let val = reader.value(0)?;
+    match reader.byte() {
+        None => Some(val),
+        Some(_) => None,
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(n)?)?;
+        self.pos += n;
+        Some(bytes)
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let mut array = [0; N];
+        array.copy_from_slice(self.take(N)?);
+        Some(array)
+    }
+
+    fn len(&mut self) -> Option<usize> {
+        Some(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn string(&mut self) -> Option<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    fn float(&mut self) -> Option<f64> {
+        Some(f64::from_bits(u64::from_le_bytes(self.array()?)))
+    }
+
+    fn value(&mut self, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        Some(match self.byte()? {
+            b'n' => Value::Null,
+            b't' => Value::Boolean(true),
+            b'f' => Value::Boolean(false),
+            b'i' => Value::Int(i64::from_le_bytes(self.array()?)),
+            b'd' => Value::Float(self.float()?),
+            b'c' => Value::Char(std::char::from_u32(u32::from_le_bytes(self.array()?))?),
+            b's' => Value::from(self.string()?),
+            b'R' => Value::Range(Bounds::Int(
+                i64::from_le_bytes(self.array()?),
+                i64::from_le_bytes(self.array()?),
+                i64::from_le_bytes(self.array()?),
+            )),
+            b'r' => Value::Range(Bounds::Float(self.float()?, self.float()?, self.float()?)),
+            b'l' => {
+                let len = self.len()?;
+                // Each item takes at least a byte, which keeps damaged lengths from allocating too much
+                let mut items = Vec::with_capacity(len.min(self.bytes.len() - self.pos));
+                for _ in 0..len {
+                    items.push(self.value(depth + 1)?);
+                }
+                Value::from(items)
+            },
+            b'm' => {
+                let len = self.len()?;
+                let mut items = Map::new();
+                for _ in 0..len {
+                    let key = self.value(depth + 1)?;
+                    items.insert(key, self.value(depth + 1)?);
+                }
+                Value::new_map(items)
+            },
+            _ => return None,
+        })
+    }
+}
@@ -0,0 +1,41 @@
+use std::rc::{Rc, Weak};
+use super::{
+    ExecResult,
+    Obj,
+    Value,
+};
+
+/// A value backed by something that the host holds on its behalf, such as an open file, a network connection or a
+/// database handle.
+///
+/// A resource is released as soon as it is closed: by its `close()` method, at the end of the `with` statement that
+/// opened it, or when the last reference to it goes away (including when it is freed by the cycle collector). Those
+/// opened with `ExecCtx::open_resource` that are still open when the engine is dropped are closed then, even if a
+/// reference cycle or the host is keeping them alive.
+pub trait Resource: Obj {
+    /// Release whatever the value holds. Closing a resource that has already been closed does nothing.
+    fn close(&self) -> ExecResult<()>;
+
+    fn is_closed(&self) -> bool;
+}
+
+/// The resources opened by an engine's code, held weakly so that they can still be freed as usual.
+#[derive(Default)]
+pub(crate) struct Resources(Vec<Weak<dyn Resource>>);
+
+impl Resources {
+    pub(crate) fn open<T: Resource>(&mut self, res: T) -> Value {
+        // Resources that have been freed are forgotten as new ones are opened, so that the list does not keep growing
+        self.0.retain(|res| res.strong_count() > 0);
+        let res = Rc::new(res);
+        self.0.push(Rc::downgrade(&res) as Weak<T>);
+        Value::Custom(res)
+    }
+
+    // There is nobody left to report errors to, so they are ignored
+    pub(crate) fn close_all(&mut self) {
+        for res in self.0.drain(..).filter_map(|res| res.upgrade()) {
+            res.close().ok();
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hasher},
+};
+
+/// The pseudo-random number generator used by builtins such as `uuid.v4` (xoshiro256**).
+///
+/// An engine built with a seed always produces the same sequence of values, which makes scripts that use randomness
+/// reproducible.
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: [u64; 4],
+}
+
+impl Rng {
+    pub fn from_seed(seed: u64) -> Self {
+        // Expand the seed with SplitMix64, as recommended by the authors of xoshiro
+        let mut x = seed;
+        let mut next = || {
+            x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+            let mut z = x;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+            z ^ (z >> 31)
+        };
+        Self { state: [next(), next(), next(), next()] }
+    }
+
+    /// Seed the generator from the randomness that the standard library uses to key its hash maps.
+    pub fn from_entropy() -> Self {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u64(0);
+        Self::from_seed(hasher.finish())
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let [s0, s1, s2, s3] = &mut self.state;
+        let result = s1.wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = *s1 << 17;
+        *s2 ^= *s0;
+        *s3 ^= *s1;
+        *s1 ^= *s2;
+        *s0 ^= *s3;
+        *s2 ^= t;
+        *s3 = s3.rotate_left(45);
+        result
+    }
+
+    /// Produce a float in `0.0..1.0`, using all 53 bits of its mantissa.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(8) {
+            let x = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&x[..chunk.len()]);
+        }
+    }
+
+    /// Produce a number in `0..n` without bias.
+    pub fn below(&mut self, n: u64) -> u64 {
+        // Reject values from the incomplete final span of `n` values
+        let zone = u64::max_value() - u64::max_value() % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
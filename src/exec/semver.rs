@@ -0,0 +1,278 @@
+use std::{
+    cmp::Ordering,
+    rc::Rc,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+};
+
+pub(super) const SEMVER: &[(&str, BuiltinFn)] = &[
+    ("parse", semver_parse),
+    ("compare", semver_compare),
+    ("matches", semver_matches),
+];
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Ident {
+    // Numeric identifiers always have lower precedence than alphanumeric ones
+    Num(u64),
+    Alpha(String),
+}
+
+#[derive(Clone)]
+struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: Vec<Ident>,
+    build: Option<String>,
+}
+
+impl Version {
+    fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Version { major, minor, patch, pre: Vec::new(), build: None }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let s = s.strip_prefix(|c| c == 'v' || c == 'V').unwrap_or(s);
+        let (s, build) = match s.find('+') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let (s, pre) = match s.find('-') {
+            Some(i) => (&s[..i], parse_pre(&s[i + 1..])?),
+            None => (s, Vec::new()),
+        };
+        if !build.map(|build| build.split('.').all(is_ident)).unwrap_or(true) {
+            return None;
+        }
+
+        let mut parts = s.split('.').map(parse_num);
+        match (parts.next(), parts.next(), parts.next(), parts.next()) {
+            (Some(Some(major)), Some(Some(minor)), Some(Some(patch)), None) => Some(Version {
+                pre,
+                build: build.map(|build| build.to_string()),
+                ..Version::new(major, minor, patch)
+            }),
+            _ => None,
+        }
+    }
+
+    fn core(&self) -> (u64, u64, u64) {
+        (self.major, self.minor, self.patch)
+    }
+}
+
+// Precedence ignores build metadata, and a pre-release comes before the release itself
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.core().cmp(&other.core()).then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => self.pre.cmp(&other.pre),
+        })
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Version {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version {}
+
+fn is_ident(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+// Numbers may not have leading zeroes
+fn parse_num(s: &str) -> Option<u64> {
+    if s.is_empty() || !s.chars().all(|c| c.is_ascii_digit()) || (s.len() > 1 && s.starts_with('0')) {
+        None
+    } else {
+        s.parse().ok()
+    }
+}
+
+fn parse_pre(s: &str) -> Option<Vec<Ident>> {
+    s.split('.')
+        .map(|ident| match parse_num(ident) {
+            Some(n) => Some(Ident::Num(n)),
+            None if is_ident(ident) && !ident.chars().all(|c| c.is_ascii_digit()) => Some(Ident::Alpha(ident.to_string())),
+            None => None,
+        })
+        .collect()
+}
+
+#[derive(Copy, Clone, PartialEq)]
+enum Op {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+    Tilde,
+    Caret,
+}
+
+/// A comparator such as `>=1.2`, where the minor and patch numbers may be left out or be wildcards.
+struct Comparator {
+    op: Op,
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<Ident>,
+}
+
+impl Comparator {
+    fn parse(s: &str) -> Option<Self> {
+        // A bare version is a caret requirement, unless it has a wildcard
+        let (op, s) = [(">=", Op::GreaterEq), ("<=", Op::LessEq), (">", Op::Greater), ("<", Op::Less), ("=", Op::Exact), ("~", Op::Tilde), ("^", Op::Caret)]
+            .iter()
+            .find(|(prefix, _)| s.starts_with(prefix))
+            .map(|(prefix, op)| (*op, s[prefix.len()..].trim_start()))
+            .unwrap_or((if s.split('-').next().unwrap_or("").contains(|c| c == '*' || c == 'x' || c == 'X') { Op::Exact } else { Op::Caret }, s));
+        let s = s.strip_prefix(|c| c == 'v' || c == 'V').unwrap_or(s);
+        let (s, pre) = match s.find('-') {
+            Some(i) => (&s[..i], parse_pre(&s[i + 1..])?),
+            None => (s, Vec::new()),
+        };
+
+        // Wildcards leave the rest of the version unspecified
+        let mut parts = s.split('.');
+        let mut nums = [None; 3];
+        for num in nums.iter_mut() {
+            match parts.next() {
+                Some("*") | Some("x") | Some("X") | None => break,
+                Some(part) => *num = Some(parse_num(part)?),
+            }
+        }
+        if parts.any(|part| !["*", "x", "X"].contains(&part)) || (!pre.is_empty() && nums[2].is_none()) {
+            return None;
+        }
+        let [major, minor, patch] = nums;
+        Some(Comparator { op, major, minor, patch, pre })
+    }
+
+    fn matches(&self, version: &Version) -> bool {
+        let major = match self.major {
+            Some(major) => major,
+            None => return true,
+        };
+        let lower = Version { pre: self.pre.clone(), ..Version::new(major, self.minor.unwrap_or(0), self.patch.unwrap_or(0)) };
+        // The first version beyond those that the unspecified parts allow
+        let next = match (self.minor, self.patch) {
+            (None, _) => Version::new(major + 1, 0, 0),
+            (Some(minor), None) => Version::new(major, minor + 1, 0),
+            (Some(minor), Some(patch)) => Version::new(major, minor, patch + 1),
+        };
+        match self.op {
+            Op::Exact => if self.patch.is_some() { *version == lower } else { *version >= lower && *version < next },
+            Op::Greater => if self.patch.is_some() { *version > lower } else { *version >= next },
+            Op::GreaterEq => *version >= lower,
+            Op::Less => *version < lower,
+            Op::LessEq => if self.patch.is_some() { *version <= lower } else { *version < next },
+            Op::Tilde => *version >= lower && *version < match self.minor {
+                Some(minor) => Version::new(major, minor + 1, 0),
+                None => Version::new(major + 1, 0, 0),
+            },
+            // Changes to the leftmost non-zero number are breaking
+            Op::Caret => *version >= lower && *version < match (major, self.minor, self.patch) {
+                (0, Some(0), Some(patch)) => Version::new(0, 0, patch + 1),
+                (0, Some(minor), _) => Version::new(0, minor + 1, 0),
+                (major, _, _) => Version::new(major + 1, 0, 0),
+            },
+        }
+    }
+}
+
+/// Alternatives separated by `||`, each of which is a list of comparators that must all match.
+fn parse_req(s: &str) -> Option<Vec<Vec<Comparator>>> {
+    s.split("||")
+        .map(|alt| {
+            // Operators may be separated from their versions by spaces
+            let mut comparators = Vec::new();
+            let mut op = String::new();
+            for part in alt.split(|c: char| c == ',' || c.is_whitespace()).filter(|part| !part.is_empty()) {
+                op += part;
+                if !op.chars().all(|c| "<>=~^".contains(c)) {
+                    comparators.push(Comparator::parse(&op)?);
+                    op.clear();
+                }
+            }
+            if comparators.is_empty() || !op.is_empty() {
+                None
+            } else {
+                Some(comparators)
+            }
+        })
+        .collect()
+}
+
+fn version_arg(val: &Value, r_args: SrcRef) -> ExecResult<Version> {
+    let s = string_arg(val, r_args)?;
+    Version::parse(&s)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a valid semantic version.", s)))))
+}
+
+fn semver_parse(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let version = version_arg(&args[0], r_args)?;
+    let pre = version.pre
+        .iter()
+        .map(|ident| match ident {
+            Ident::Num(n) => n.to_string(),
+            Ident::Alpha(s) => s.clone(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut parsed = Map::new();
+    parsed.insert(Value::from("major"), Value::from(version.major as f64));
+    parsed.insert(Value::from("minor"), Value::from(version.minor as f64));
+    parsed.insert(Value::from("patch"), Value::from(version.patch as f64));
+    parsed.insert(Value::from("pre"), Value::from(Some(pre.join(".")).filter(|pre| !pre.is_empty())));
+    parsed.insert(Value::from("build"), Value::from(version.build));
+    Ok(Value::from(parsed))
+}
+
+fn semver_compare(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let (a, b) = (version_arg(&args[0], r_args)?, version_arg(&args[1], r_args)?);
+    Ok(Value::from(match a.cmp(&b) {
+        Ordering::Less => -1.0,
+        Ordering::Equal => 0.0,
+        Ordering::Greater => 1.0,
+    }))
+}
+
+fn semver_matches(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let version = version_arg(&args[0], r_args)?;
+    let req = string_arg(&args[1], r_args)?;
+    let alts = parse_req(&req)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a valid version requirement.", req)))))?;
+
+    // Pre-releases only match if a comparator asks for a pre-release of the same version
+    let allows_pre = |comparators: &[Comparator]| version.pre.is_empty() || comparators
+        .iter()
+        .any(|c| !c.pre.is_empty() && (c.major, c.minor, c.patch) == (Some(version.major), Some(version.minor), Some(version.patch)));
+    Ok(Value::from(alts
+        .iter()
+        .any(|comparators| allows_pre(comparators) && comparators.iter().all(|c| c.matches(&version)))))
+}
@@ -0,0 +1,397 @@
+use std::{
+    rc::Rc,
+    str,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, bytes_arg, bytes_value},
+    method,
+};
+
+// Values that contain themselves would otherwise be encoded forever
+const MAX_DEPTH: usize = 256;
+
+#[cfg(feature = "msgpack")]
+pub(super) const MSGPACK: &[(&str, BuiltinFn)] = &[
+    ("encode", msgpack_encode),
+    ("decode", msgpack_decode),
+];
+
+#[cfg(feature = "cbor")]
+pub(super) const CBOR: &[(&str, BuiltinFn)] = &[
+    ("encode", cbor_encode),
+    ("decode", cbor_decode),
+];
+
+// Unsigned integers too large for an int are decoded as floats, losing precision rather than wrapping around
+fn uint_value(n: u64) -> Value {
+    if n <= i64::max_value() as u64 {
+        Value::Int(n as i64)
+    } else {
+        Value::Float(n as f64)
+    }
+}
+
+fn unencodable(val: &Value, format: &str) -> ExecError {
+    ExecError::Custom(format!("Values of type '{}' cannot be encoded as {}.", val.get_type_name(), format))
+}
+
+fn too_deep() -> ExecError {
+    ExecError::Custom(String::from("The value is nested too deeply to encode. Does it contain itself?"))
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    format: &'static str,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8], format: &'static str) -> Self {
+        Self { bytes, pos: 0, format }
+    }
+
+    fn invalid(&self, msg: &str) -> ExecError {
+        ExecError::Custom(format!("Invalid {} data at byte {}: {}.", self.format, self.pos, msg))
+    }
+
+    fn take(&mut self, n: usize) -> ExecResult<&'a [u8]> {
+        if self.bytes.len() - self.pos < n {
+            return Err(self.invalid("unexpected end of data"));
+        }
+        self.pos += n;
+        Ok(&self.bytes[self.pos - n..self.pos])
+    }
+
+    fn byte(&mut self) -> ExecResult<u8> {
+        self.take(1).map(|b| b[0])
+    }
+
+    // A big-endian unsigned integer of `n` bytes
+    fn uint(&mut self, n: usize) -> ExecResult<u64> {
+        Ok(self.take(n)?.iter().fold(0, |x, b| (x << 8) | *b as u64))
+    }
+
+    fn string(&mut self, len: u64) -> ExecResult<Value> {
+        let start = self.pos;
+        let bytes = self.take(len as usize)?;
+        match str::from_utf8(bytes) {
+            Ok(s) => Ok(Value::from(s)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.invalid("text is not valid UTF-8"))
+            },
+        }
+    }
+
+    fn finish(&self, val: Value) -> ExecResult<Value> {
+        if self.pos == self.bytes.len() {
+            Ok(val)
+        } else {
+            Err(self.invalid("unexpected data after the end of the value"))
+        }
+    }
+}
+
+#[cfg(feature = "msgpack")]
+fn msgpack_encode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let mut buf = Vec::new();
+    msgpack::encode(&args[0], &mut buf, 0).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(bytes_value(&buf))
+}
+
+#[cfg(feature = "msgpack")]
+fn msgpack_decode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let bytes = bytes_arg(&args[0], r_args)?;
+    let mut reader = Reader::new(&bytes, "MessagePack");
+    msgpack::decode(&mut reader, 0)
+        .and_then(|val| reader.finish(val))
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))
+}
+
+#[cfg(feature = "msgpack")]
+mod msgpack {
+    use super::*;
+
+    // Write the marker of a string, list or map with its length, using the smallest of the given widths that fits
+    fn len(buf: &mut Vec<u8>, n: usize, (fixed, max): (u8, usize), markers: [Option<u8>; 3]) {
+        match n {
+            n if n < max => buf.push(fixed | n as u8),
+            _ => if let (Some(marker), true) = (markers[0], n <= 0xff) {
+                buf.extend_from_slice(&[marker, n as u8]);
+            } else if let (Some(marker), true) = (markers[1], n <= 0xffff) {
+                buf.push(marker);
+                buf.extend_from_slice(&(n as u16).to_be_bytes());
+            } else {
+                buf.push(markers[2].unwrap());
+                buf.extend_from_slice(&(n as u32).to_be_bytes());
+            },
+        }
+    }
+
+    pub(super) fn encode(val: &Value, buf: &mut Vec<u8>, depth: usize) -> ExecResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(too_deep());
+        }
+        match val {
+            Value::Null => buf.push(0xc0),
+            Value::Boolean(b) => buf.push(if *b { 0xc3 } else { 0xc2 }),
+            Value::Int(n) => match *n {
+                n @ -32..=127 => buf.push(n as i8 as u8),
+                n if n > 0 => if n <= 0xff {
+                    buf.extend_from_slice(&[0xcc, n as u8]);
+                } else if n <= 0xffff {
+                    buf.push(0xcd);
+                    buf.extend_from_slice(&(n as u16).to_be_bytes());
+                } else if n <= 0xffff_ffff {
+                    buf.push(0xce);
+                    buf.extend_from_slice(&(n as u32).to_be_bytes());
+                } else {
+                    buf.push(0xcf);
+                    buf.extend_from_slice(&(n as u64).to_be_bytes());
+                },
+                n => if n >= i8::min_value() as i64 {
+                    buf.extend_from_slice(&[0xd0, n as i8 as u8]);
+                } else if n >= i16::min_value() as i64 {
+                    buf.push(0xd1);
+                    buf.extend_from_slice(&(n as i16).to_be_bytes());
+                } else if n >= i32::min_value() as i64 {
+                    buf.push(0xd2);
+                    buf.extend_from_slice(&(n as i32).to_be_bytes());
+                } else {
+                    buf.push(0xd3);
+                    buf.extend_from_slice(&n.to_be_bytes());
+                },
+            },
+            Value::Float(x) => {
+                buf.push(0xcb);
+                buf.extend_from_slice(&x.to_bits().to_be_bytes());
+            },
+            Value::String(_) | Value::Char(_) => {
+                let s = val.as_string().unwrap_or_else(|| val.as_char().unwrap().to_string());
+                len(buf, s.len(), (0xa0, 32), [Some(0xd9), Some(0xda), Some(0xdb)]);
+                buf.extend_from_slice(s.as_bytes());
+            },
+            Value::List(l) => {
+                let l = l.borrow();
+                len(buf, l.len(), (0x90, 16), [None, Some(0xdc), Some(0xdd)]);
+                for item in l.iter() {
+                    encode(item, buf, depth + 1)?;
+                }
+            },
+            Value::Map(m) => {
+                let m = m.borrow();
+                len(buf, m.len(), (0x80, 16), [None, Some(0xde), Some(0xdf)]);
+                for (k, v) in m.iter() {
+                    encode(k, buf, depth + 1)?;
+                    encode(v, buf, depth + 1)?;
+                }
+            },
+            val => return Err(unencodable(val, "MessagePack")),
+        }
+        Ok(())
+    }
+
+    fn array(reader: &mut Reader, len: u64, depth: usize) -> ExecResult<Value> {
+        (0..len)
+            .map(|_| decode(reader, depth + 1))
+            .collect::<ExecResult<Vec<_>>>()
+            .map(Value::from)
+    }
+
+    fn map(reader: &mut Reader, len: u64, depth: usize) -> ExecResult<Value> {
+        (0..len)
+            .map(|_| Ok((decode(reader, depth + 1)?, decode(reader, depth + 1)?)))
+            .collect::<ExecResult<Map>>()
+            .map(Value::from)
+    }
+
+    pub(super) fn decode(reader: &mut Reader, depth: usize) -> ExecResult<Value> {
+        if depth > MAX_DEPTH {
+            return Err(reader.invalid("values are nested too deeply"));
+        }
+        Ok(match reader.byte()? {
+            b @ 0x00..=0x7f => Value::Int(b as i64),
+            b @ 0x80..=0x8f => map(reader, (b & 0x0f) as u64, depth)?,
+            b @ 0x90..=0x9f => array(reader, (b & 0x0f) as u64, depth)?,
+            b @ 0xa0..=0xbf => reader.string((b & 0x1f) as u64)?,
+            0xc0 => Value::Null,
+            0xc2 => Value::Boolean(false),
+            0xc3 => Value::Boolean(true),
+            b @ 0xc4..=0xc6 => {
+                let len = reader.uint(1 << (b - 0xc4))?;
+                bytes_value(reader.take(len as usize)?)
+            },
+            0xca => Value::Float(f32::from_bits(reader.uint(4)? as u32) as f64),
+            0xcb => Value::Float(f64::from_bits(reader.uint(8)?)),
+            b @ 0xcc..=0xcf => uint_value(reader.uint(1 << (b - 0xcc))?),
+            0xd0 => Value::Int(reader.uint(1)? as u8 as i8 as i64),
+            0xd1 => Value::Int(reader.uint(2)? as u16 as i16 as i64),
+            0xd2 => Value::Int(reader.uint(4)? as u32 as i32 as i64),
+            0xd3 => Value::Int(reader.uint(8)? as i64),
+            b @ 0xd9..=0xdb => {
+                let len = reader.uint(1 << (b - 0xd9))?;
+                reader.string(len)?
+            },
+            0xdc => { let len = reader.uint(2)?; array(reader, len, depth)? },
+            0xdd => { let len = reader.uint(4)?; array(reader, len, depth)? },
+            0xde => { let len = reader.uint(2)?; map(reader, len, depth)? },
+            0xdf => { let len = reader.uint(4)?; map(reader, len, depth)? },
+            b @ 0xe0..=0xff => Value::Int(b as i8 as i64),
+            0xc7..=0xc9 | 0xd4..=0xd8 => {
+                reader.pos -= 1;
+                return Err(reader.invalid("extension types are not supported"));
+            },
+            _ => {
+                reader.pos -= 1;
+                return Err(reader.invalid("unknown type marker"));
+            },
+        })
+    }
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_encode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let mut buf = Vec::new();
+    cbor::encode(&args[0], &mut buf, 0).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(bytes_value(&buf))
+}
+
+#[cfg(feature = "cbor")]
+fn cbor_decode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let bytes = bytes_arg(&args[0], r_args)?;
+    let mut reader = Reader::new(&bytes, "CBOR");
+    cbor::decode(&mut reader, 0)
+        .and_then(|val| reader.finish(val))
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))
+}
+
+#[cfg(feature = "cbor")]
+mod cbor {
+    use super::*;
+
+    // Every item starts with its major type and an argument (a length or an integer) in as few bytes as possible
+    fn head(buf: &mut Vec<u8>, major: u8, n: u64) {
+        let major = major << 5;
+        if n < 24 {
+            buf.push(major | n as u8);
+        } else if n <= 0xff {
+            buf.extend_from_slice(&[major | 24, n as u8]);
+        } else if n <= 0xffff {
+            buf.push(major | 25);
+            buf.extend_from_slice(&(n as u16).to_be_bytes());
+        } else if n <= 0xffff_ffff {
+            buf.push(major | 26);
+            buf.extend_from_slice(&(n as u32).to_be_bytes());
+        } else {
+            buf.push(major | 27);
+            buf.extend_from_slice(&n.to_be_bytes());
+        }
+    }
+
+    pub(super) fn encode(val: &Value, buf: &mut Vec<u8>, depth: usize) -> ExecResult<()> {
+        if depth > MAX_DEPTH {
+            return Err(too_deep());
+        }
+        match val {
+            Value::Null => buf.push(0xf6),
+            Value::Boolean(b) => buf.push(if *b { 0xf5 } else { 0xf4 }),
+            Value::Int(n) if *n >= 0 => head(buf, 0, *n as u64),
+            Value::Int(n) => head(buf, 1, (-1 - *n) as u64),
+            Value::Float(x) => {
+                buf.push(0xfb);
+                buf.extend_from_slice(&x.to_bits().to_be_bytes());
+            },
+            Value::String(_) | Value::Char(_) => {
+                let s = val.as_string().unwrap_or_else(|| val.as_char().unwrap().to_string());
+                head(buf, 3, s.len() as u64);
+                buf.extend_from_slice(s.as_bytes());
+            },
+            Value::List(l) => {
+                let l = l.borrow();
+                head(buf, 4, l.len() as u64);
+                for item in l.iter() {
+                    encode(item, buf, depth + 1)?;
+                }
+            },
+            Value::Map(m) => {
+                let m = m.borrow();
+                head(buf, 5, m.len() as u64);
+                for (k, v) in m.iter() {
+                    encode(k, buf, depth + 1)?;
+                    encode(v, buf, depth + 1)?;
+                }
+            },
+            val => return Err(unencodable(val, "CBOR")),
+        }
+        Ok(())
+    }
+
+    // IEEE 754 half-precision, which CBOR encoders use for small floats
+    fn f16_to_f64(bits: u16) -> f64 {
+        let exp = (bits >> 10) & 0x1f;
+        let mant = (bits & 0x3ff) as f64;
+        let x = match exp {
+            0 => mant * 2f64.powi(-24),
+            0x1f => if mant == 0.0 { std::f64::INFINITY } else { std::f64::NAN },
+            exp => (1.0 + mant / 1024.0) * 2f64.powi(exp as i32 - 15),
+        };
+        if bits & 0x8000 != 0 { -x } else { x }
+    }
+
+    pub(super) fn decode(reader: &mut Reader, depth: usize) -> ExecResult<Value> {
+        if depth > MAX_DEPTH {
+            return Err(reader.invalid("values are nested too deeply"));
+        }
+        let start = reader.pos;
+        let initial = reader.byte()?;
+        let (major, info) = (initial >> 5, initial & 0x1f);
+        let arg = match info {
+            0..=23 => info as u64,
+            24..=27 => reader.uint(1 << (info - 24))?,
+            _ => {
+                reader.pos = start;
+                return Err(reader.invalid(if info == 31 {
+                    "indefinite-length items are not supported"
+                } else {
+                    "reserved additional information"
+                }));
+            },
+        };
+        Ok(match major {
+            0 => uint_value(arg),
+            1 => if arg <= i64::max_value() as u64 { Value::Int(-1 - arg as i64) } else { Value::Float(-1.0 - arg as f64) },
+            2 => bytes_value(reader.take(arg as usize)?),
+            3 => reader.string(arg)?,
+            4 => Value::from((0..arg)
+                .map(|_| decode(reader, depth + 1))
+                .collect::<ExecResult<Vec<_>>>()?),
+            5 => Value::from((0..arg)
+                .map(|_| Ok((decode(reader, depth + 1)?, decode(reader, depth + 1)?)))
+                .collect::<ExecResult<Map>>()?),
+            // Tags (such as dates) only describe how to interpret the item that follows, so they are ignored
+            6 => decode(reader, depth + 1)?,
+            _ => match info {
+                20 => Value::Boolean(false),
+                21 => Value::Boolean(true),
+                22 | 23 => Value::Null,
+                25 => Value::Float(f16_to_f64(arg as u16)),
+                26 => Value::Float(f32::from_bits(arg as u32) as f64),
+                27 => Value::Float(f64::from_bits(arg)),
+                _ => {
+                    reader.pos = start;
+                    return Err(reader.invalid("unsupported simple value"));
+                },
+            },
+        })
+    }
+}
@@ -0,0 +1,352 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::{c_char, c_int, c_void},
+    path::Path,
+    ptr,
+    slice,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Obj,
+    Resource,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method::{self, Method, MethodFn},
+};
+
+pub(super) const SQL: &[(&str, BuiltinFn)] = &[
+    ("open", sql_open),
+];
+
+// The parts of the SQLite C API that are used here, linked against the system library
+mod ffi {
+    use super::*;
+
+    pub enum Sqlite3 {}
+    pub enum Stmt {}
+
+    pub const OK: c_int = 0;
+    pub const ROW: c_int = 100;
+    pub const DONE: c_int = 101;
+
+    pub const OPEN_READONLY: c_int = 0x01;
+    pub const OPEN_READWRITE: c_int = 0x02;
+    pub const OPEN_CREATE: c_int = 0x04;
+
+    pub const INTEGER: c_int = 1;
+    pub const FLOAT: c_int = 2;
+    pub const TEXT: c_int = 3;
+    pub const BLOB: c_int = 4;
+
+    // Tells SQLite to copy bound text before the call returns
+    pub const TRANSIENT: isize = -1;
+
+    #[link(name = "sqlite3")]
+    extern "C" {
+        pub fn sqlite3_open_v2(filename: *const c_char, db: *mut *mut Sqlite3, flags: c_int, vfs: *const c_char) -> c_int;
+        pub fn sqlite3_close_v2(db: *mut Sqlite3) -> c_int;
+        pub fn sqlite3_errmsg(db: *mut Sqlite3) -> *const c_char;
+        pub fn sqlite3_changes(db: *mut Sqlite3) -> c_int;
+        pub fn sqlite3_prepare_v2(db: *mut Sqlite3, sql: *const c_char, len: c_int, stmt: *mut *mut Stmt, tail: *mut *const c_char) -> c_int;
+        pub fn sqlite3_finalize(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_step(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_bind_parameter_count(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_bind_parameter_name(stmt: *mut Stmt, idx: c_int) -> *const c_char;
+        pub fn sqlite3_bind_null(stmt: *mut Stmt, idx: c_int) -> c_int;
+        pub fn sqlite3_bind_int64(stmt: *mut Stmt, idx: c_int, val: i64) -> c_int;
+        pub fn sqlite3_bind_double(stmt: *mut Stmt, idx: c_int, val: f64) -> c_int;
+        pub fn sqlite3_bind_text(stmt: *mut Stmt, idx: c_int, val: *const c_char, len: c_int, destructor: isize) -> c_int;
+        pub fn sqlite3_column_count(stmt: *mut Stmt) -> c_int;
+        pub fn sqlite3_column_name(stmt: *mut Stmt, col: c_int) -> *const c_char;
+        pub fn sqlite3_column_type(stmt: *mut Stmt, col: c_int) -> c_int;
+        pub fn sqlite3_column_int64(stmt: *mut Stmt, col: c_int) -> i64;
+        pub fn sqlite3_column_double(stmt: *mut Stmt, col: c_int) -> f64;
+        pub fn sqlite3_column_text(stmt: *mut Stmt, col: c_int) -> *const u8;
+        pub fn sqlite3_column_blob(stmt: *mut Stmt, col: c_int) -> *const c_void;
+        pub fn sqlite3_column_bytes(stmt: *mut Stmt, col: c_int) -> c_int;
+    }
+}
+
+struct Connection(*mut ffi::Sqlite3);
+
+impl Connection {
+    fn error(&self) -> ExecError {
+        let msg = unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(self.0)) };
+        ExecError::Custom(format!("Database error: {}.", msg.to_string_lossy()))
+    }
+
+    fn check(&self, code: c_int) -> ExecResult<()> {
+        if code == ffi::OK {
+            Ok(())
+        } else {
+            Err(self.error())
+        }
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_close_v2(self.0); }
+    }
+}
+
+// A prepared statement, which is finalized when dropped
+struct Statement<'a> {
+    conn: &'a Connection,
+    stmt: *mut ffi::Stmt,
+}
+
+impl<'a> Statement<'a> {
+    /// Prepare the first statement in `sql`, returning it (or `None` if there is only whitespace or comments) and the
+    /// rest of the text.
+    fn prepare(conn: &'a Connection, sql: &'a [u8]) -> ExecResult<(Option<Self>, &'a [u8])> {
+        let mut stmt = ptr::null_mut();
+        let mut tail = ptr::null();
+        let code = unsafe {
+            ffi::sqlite3_prepare_v2(conn.0, sql.as_ptr() as *const c_char, sql.len() as c_int, &mut stmt, &mut tail)
+        };
+        conn.check(code)?;
+        let rest = &sql[tail as usize - sql.as_ptr() as usize..];
+        Ok((if stmt.is_null() { None } else { Some(Self { conn, stmt }) }, rest))
+    }
+
+    fn bind_value(&self, idx: c_int, val: &Value) -> ExecResult<()> {
+        let code = unsafe {
+            match val {
+                Value::Null => ffi::sqlite3_bind_null(self.stmt, idx),
+                Value::Boolean(b) => ffi::sqlite3_bind_int64(self.stmt, idx, *b as i64),
+                Value::Int(x) => ffi::sqlite3_bind_int64(self.stmt, idx, *x),
+                Value::Float(x) => ffi::sqlite3_bind_double(self.stmt, idx, *x),
+                Value::String(_) | Value::Char(_) => {
+                    let s = val.get_display_text()?;
+                    ffi::sqlite3_bind_text(self.stmt, idx, s.as_ptr() as *const c_char, s.len() as c_int, ffi::TRANSIENT)
+                },
+                val => return Err(ExecError::Custom(format!("Values of type '{}' cannot be stored in a database.", val.get_type_name()))),
+            }
+        };
+        self.conn.check(code)
+    }
+
+    /// Bind a list of values to `?` parameters by position, or a map of values to `:name` parameters by name.
+    fn bind(&self, params: &Value) -> ExecResult<()> {
+        let count = unsafe { ffi::sqlite3_bind_parameter_count(self.stmt) };
+        match params {
+            Value::Null if count == 0 => {},
+            Value::Null => return Err(ExecError::Custom(format!("The query expects {} parameters, but none were given.", count))),
+            Value::List(l) => {
+                let l = l.borrow();
+                if l.len() != count as usize {
+                    return Err(ExecError::Custom(format!("The query expects {} parameters, but {} were given.", count, l.len())));
+                }
+                for (i, val) in l.iter().enumerate() {
+                    self.bind_value(i as c_int + 1, val)?;
+                }
+            },
+            Value::Map(m) => {
+                let m = m.borrow();
+                for idx in 1..=count {
+                    let name = unsafe { ffi::sqlite3_bind_parameter_name(self.stmt, idx) };
+                    let name = if name.is_null() {
+                        return Err(ExecError::Custom(String::from("Parameters given as a map must be named, like ':name'.")));
+                    } else {
+                        unsafe { CStr::from_ptr(name) }.to_string_lossy()
+                    };
+                    // The prefix (`:`, `@` or `$`) is part of the parameter name, but not of the key
+                    match m.get(&Value::from(&name[1..])) {
+                        Some(val) => self.bind_value(idx, val)?,
+                        None => return Err(ExecError::Custom(format!("No value was given for the parameter '{}'.", name))),
+                    }
+                }
+            },
+            params => return Err(ExecError::WrongArgType(String::from("list or map"), params.get_type_name())),
+        }
+        Ok(())
+    }
+
+    fn column(&self, col: c_int) -> Value {
+        unsafe {
+            match ffi::sqlite3_column_type(self.stmt, col) {
+                ffi::INTEGER => Value::Int(ffi::sqlite3_column_int64(self.stmt, col)),
+                ffi::FLOAT => Value::Float(ffi::sqlite3_column_double(self.stmt, col)),
+                ffi::TEXT => {
+                    let text = ffi::sqlite3_column_text(self.stmt, col);
+                    let len = ffi::sqlite3_column_bytes(self.stmt, col) as usize;
+                    let bytes = if len == 0 { &[][..] } else { slice::from_raw_parts(text, len) };
+                    Value::from(String::from_utf8_lossy(bytes).into_owned())
+                },
+                // Blobs are lists of bytes, as produced by `msgpack.encode`
+                ffi::BLOB => {
+                    let blob = ffi::sqlite3_column_blob(self.stmt, col) as *const u8;
+                    let len = ffi::sqlite3_column_bytes(self.stmt, col) as usize;
+                    let bytes = if len == 0 { &[][..] } else { slice::from_raw_parts(blob, len) };
+                    Value::from(bytes.iter().map(|b| Value::Int(*b as i64)).collect::<Vec<_>>())
+                },
+                _ => Value::Null,
+            }
+        }
+    }
+
+    // Run the statement to completion, collecting each row as a map from column names to values
+    fn run(&self) -> ExecResult<Vec<Value>> {
+        let names = (0..unsafe { ffi::sqlite3_column_count(self.stmt) })
+            .map(|col| unsafe { CStr::from_ptr(ffi::sqlite3_column_name(self.stmt, col)) }.to_string_lossy().into_owned())
+            .collect::<Vec<_>>();
+        let mut rows = Vec::new();
+        loop {
+            match unsafe { ffi::sqlite3_step(self.stmt) } {
+                ffi::ROW => rows.push(Value::from(names
+                    .iter()
+                    .enumerate()
+                    .map(|(col, name)| (Value::from(name.as_str()), self.column(col as c_int)))
+                    .collect::<Map>())),
+                ffi::DONE => return Ok(rows),
+                _ => return Err(self.conn.error()),
+            }
+        }
+    }
+}
+
+impl<'a> Drop for Statement<'a> {
+    fn drop(&mut self) {
+        unsafe { ffi::sqlite3_finalize(self.stmt); }
+    }
+}
+
+/// A connection to an SQLite database, created with `sql.open(path)`.
+///
+/// Rows are returned by `query(sql, params)` as a list of maps from column names to values, and statements that
+/// change the database are run with `exec(sql, params)`. Parameters are given as a list for `?` placeholders or as a
+/// map for named placeholders like `:name`. The connection is closed with `close()`, at the end of a `with` statement,
+/// or when the last reference to it goes away.
+#[derive(Clone)]
+pub struct Database {
+    path: String,
+    conn: Rc<RefCell<Option<Connection>>>,
+}
+
+impl Database {
+    fn with_conn<R>(&self, f: impl FnOnce(&Connection) -> ExecResult<R>) -> ExecResult<R> {
+        match &*self.conn.borrow() {
+            Some(conn) => f(conn),
+            None => Err(ExecError::Custom(format!("Database '{}' has been closed.", self.path))),
+        }
+    }
+}
+
+impl Obj for Database {
+    fn get_type_name(&self) -> String {
+        String::from("database")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<database '{}'>", self.path))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<Database>) = match name {
+            "query" => ("query", db_query),
+            "exec" => ("exec", db_exec),
+            "close" => ("close", db_close),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+
+    fn as_resource(&self) -> Option<&dyn Resource> {
+        Some(self)
+    }
+}
+
+impl Resource for Database {
+    fn close(&self) -> ExecResult<()> {
+        self.conn.borrow_mut().take();
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.conn.borrow().is_none()
+    }
+}
+
+// Databases in files need permission to read them, and to write them unless opened read-only. In-memory databases need
+// no permissions.
+fn sql_open(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let path = string_arg(&args[0], r_args)?;
+    let flags = if path == ":memory:" || ctx.permissions.fs_write {
+        ffi::OPEN_READWRITE | ffi::OPEN_CREATE
+    } else {
+        ffi::OPEN_READONLY
+    };
+    let path = if path != ":memory:" {
+        ctx.permissions.require_fs_read()
+            .and_then(|_| ctx.permissions.resolve_path(Path::new(&path)))
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(|err| ExecError::At(r_args, Box::new(err)))?
+    } else {
+        path
+    };
+
+    let c_path = CString::new(path.as_str())
+        .map_err(|_| ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Database paths cannot contain null characters.")))))?;
+    let mut db = ptr::null_mut();
+    let code = unsafe { ffi::sqlite3_open_v2(c_path.as_ptr(), &mut db, flags, ptr::null()) };
+    // A connection is created even if opening fails, so that the error can be read from it
+    let conn = Connection(db);
+    conn.check(code).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+
+    Ok(ctx.open_resource(Database {
+        path,
+        conn: Rc::new(RefCell::new(Some(conn))),
+    }))
+}
+
+// Runs a single statement and returns its rows
+fn db_query(db: &Database, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let sql = string_arg(&args[0], r_args)?;
+    db.with_conn(|conn| {
+        let (stmt, rest) = Statement::prepare(conn, sql.as_bytes())?;
+        if !String::from_utf8_lossy(rest).trim().is_empty() {
+            return Err(ExecError::Custom(String::from("Only one statement may be run by 'query'. Use 'exec' to run several.")));
+        }
+        match stmt {
+            Some(stmt) => {
+                stmt.bind(args.get(1).unwrap_or(&Value::Null))?;
+                stmt.run().map(Value::from)
+            },
+            None => Ok(Value::from(Vec::<Value>::new())),
+        }
+    }).map_err(|err| ExecError::At(r_args, Box::new(err)))
+}
+
+// Runs every statement, binding the parameters to each one that has placeholders, and returns the number of rows
+// changed by the last
+fn db_exec(db: &Database, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let sql = string_arg(&args[0], r_args)?;
+    let params = args.get(1).unwrap_or(&Value::Null);
+    db.with_conn(|conn| {
+        let mut sql = sql.as_bytes();
+        while let (Some(stmt), rest) = Statement::prepare(conn, sql)? {
+            if unsafe { ffi::sqlite3_bind_parameter_count(stmt.stmt) } > 0 {
+                stmt.bind(params)?;
+            }
+            stmt.run()?;
+            sql = rest;
+        }
+        Ok(Value::Int(unsafe { ffi::sqlite3_changes(conn.0) } as i64))
+    }).map_err(|err| ExecError::At(r_args, Box::new(err)))
+}
+
+fn db_close(db: &Database, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    db.close()?;
+    Ok(Value::Null)
+}
@@ -0,0 +1,91 @@
+use std::{
+    rc::Rc,
+    cmp::Ordering,
+};
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Value,
+    builtins::{BuiltinFn, number_arg},
+    method,
+};
+
+pub(super) const STATS: &[(&str, BuiltinFn)] = &[
+    ("mean", |args, _, _, _, r_args| summary(args, r_args, mean)),
+    ("median", |args, _, _, _, r_args| summary(args, r_args, |xs| percentile(xs, 50.0))),
+    ("stddev", |args, _, _, _, r_args| summary(args, r_args, stddev)),
+    ("percentile", stats_percentile),
+    ("min_by", |args, ctx, src, r_caller, r_args| extreme_by(args, ctx, src, r_caller, r_args, Ordering::Less)),
+    ("max_by", |args, ctx, src, r_caller, r_args| extreme_by(args, ctx, src, r_caller, r_args, Ordering::Greater)),
+];
+
+fn empty(r_args: SrcRef) -> ExecError {
+    ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Expected at least one value to summarise."))))
+}
+
+// The numbers of a list, which must have at least one
+fn numbers_arg(val: &Value, r_args: SrcRef) -> ExecResult<Vec<f64>> {
+    let xs = match val {
+        Value::List(l) => l.borrow().iter().map(|x| number_arg(x, r_args)).collect::<ExecResult<Vec<_>>>()?,
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    };
+    if xs.is_empty() { Err(empty(r_args)) } else { Ok(xs) }
+}
+
+fn summary(args: Vec<Value>, r_args: SrcRef, f: fn(Vec<f64>) -> f64) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    Ok(Value::Float(f(numbers_arg(&args[0], r_args)?)))
+}
+
+fn mean(xs: Vec<f64>) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+// The standard deviation of the numbers themselves, rather than an estimate of that of what they were sampled from
+fn stddev(xs: Vec<f64>) -> f64 {
+    let mean = mean(xs.clone());
+    (xs.iter().map(|x| (x - mean).powi(2)).sum::<f64>() / xs.len() as f64).sqrt()
+}
+
+// Interpolates between the two closest numbers when the percentile falls between them, so the 50th is the median
+fn percentile(mut xs: Vec<f64>, p: f64) -> f64 {
+    xs.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let rank = p / 100.0 * (xs.len() - 1) as f64;
+    let (below, above) = (rank.floor() as usize, rank.ceil() as usize);
+    xs[below] + (xs[above] - xs[below]) * (rank - below as f64)
+}
+
+fn stats_percentile(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let xs = numbers_arg(&args[0], r_args)?;
+    let p = number_arg(&args[1], r_args)?;
+    if !(0.0..=100.0).contains(&p) {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!(
+            "Expected a percentile from 0 to 100, found {}.", p,
+        )))));
+    }
+    Ok(Value::Float(percentile(xs, p)))
+}
+
+// The item for which a function produces the least or greatest value, as `<` compares them, or the first of several
+fn extreme_by(args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef, want: Ordering) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let items = match &args[0] {
+        Value::List(l) => l.borrow().to_vec(),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list"), val.get_type_name())))),
+    };
+    let refs = BinaryOpRef { op: r_caller, left: r_args, right: r_args };
+    let mut best: Option<(Value, Value)> = None;
+    for item in items {
+        let key = args[1].call(vec![item.clone()], ctx, src, r_caller, r_args)?;
+        best = match best {
+            Some((best_key, _)) if method::natural_cmp(&key, &best_key, refs)? == want => Some((key, item)),
+            Some(best) => Some(best),
+            None => Some((key, item)),
+        };
+    }
+    best.map(|(_, item)| item).ok_or_else(|| empty(r_args))
+}
@@ -0,0 +1,49 @@
+use super::{
+    ExecCtx,
+    Map,
+    Value,
+    builtins::Builtin,
+    reflect,
+};
+
+// The optional parts of Forge that this build includes, by the name of their cargo feature
+const FEATURES: &[(&str, bool)] = &[
+    ("msgpack", cfg!(feature = "msgpack")),
+    ("cbor", cfg!(feature = "cbor")),
+    ("net", cfg!(feature = "net")),
+    ("regex", cfg!(feature = "regex")),
+    ("sqlite", cfg!(feature = "sqlite")),
+    ("gzip", cfg!(feature = "gzip")),
+    ("zstd", cfg!(feature = "zstd")),
+];
+
+/// The value of `sys`, which describes the engine that the code is running in so that it can check for what it needs
+/// before it starts. It is made afresh each time it is read, so changing it has no effect on the engine.
+pub(super) fn sys(ctx: &ExecCtx) -> Value {
+    let features = FEATURES
+        .iter()
+        .map(|(name, enabled)| (Value::from(*name), Value::from(*enabled)))
+        .collect::<Map>();
+
+    let mut permissions = Map::new();
+    permissions.insert(Value::from("fs_read"), Value::from(ctx.permissions.fs_read));
+    permissions.insert(Value::from("fs_write"), Value::from(ctx.permissions.fs_write));
+    permissions.insert(Value::from("env"), Value::from(ctx.permissions.env));
+    permissions.insert(Value::from("net"), Value::from(ctx.permissions.net));
+    permissions.insert(Value::from("clock"), Value::from(ctx.permissions.clock));
+    permissions.insert(Value::from("mocks"), Value::from(ctx.mocks.is_some()));
+
+    // Limits that the engine does not impose are null
+    let mut limits = Map::new();
+    limits.insert(Value::from("max_depth"), Value::from(ctx.max_call_depth.map(|depth| depth as i64)));
+    limits.insert(Value::from("fuel"), Value::from(ctx.fuel.map(|fuel| fuel.min(i64::MAX as u64) as i64)));
+    limits.insert(Value::from("gc_threshold"), Value::from(ctx.gc_threshold.map(|threshold| threshold as i64)));
+
+    let mut sys = Map::new();
+    sys.insert(Value::from("version"), Value::from(env!("CARGO_PKG_VERSION")));
+    sys.insert(Value::from("features"), Value::from(features));
+    sys.insert(Value::from("permissions"), Value::from(permissions));
+    sys.insert(Value::from("limits"), Value::from(limits));
+    sys.insert(Value::from("render_ref"), Value::from(Builtin::new("render_ref", Some("sys"), reflect::sys_render_ref)));
+    Value::from(sys)
+}
@@ -0,0 +1,288 @@
+use std::{
+    env,
+    rc::Rc,
+    cell::RefCell,
+};
+use crate::{
+    parser::SrcRef,
+    output::Repeat,
+};
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    Value,
+    builtins::{BuiltinFn, number_arg, string_arg},
+    method::{self, Method, MethodFn},
+};
+
+// Output that is not shown on a terminal (such as a file or a host's own `Io`) is left free of escape codes, so
+// scripts can style their output unconditionally
+pub(super) const TERM: &[(&str, BuiltinFn)] = &[
+    ("color", term_color),
+    ("bold", term_bold),
+    ("clear", term_clear),
+    ("width", term_width),
+    ("progress", term_progress),
+    ("table", term_table),
+];
+
+const COLORS: &[(&str, u8)] = &[
+    ("black", 30),
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("white", 37),
+];
+
+// Used by `term.width()` when the width of the terminal cannot be determined
+const DEFAULT_WIDTH: usize = 80;
+
+// Colors are also left out if the user has asked for them to be with the `NO_COLOR` convention
+fn use_styles(ctx: &mut ExecCtx) -> bool {
+    ctx.io().is_terminal() && env::var_os("NO_COLOR").is_none()
+}
+
+fn styled(ctx: &mut ExecCtx, code: u8, text: String) -> Value {
+    if use_styles(ctx) {
+        Value::from(format!("\x1b[{}m{}\x1b[0m", code, text))
+    } else {
+        Value::from(text)
+    }
+}
+
+fn term_color(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let name = string_arg(&args[0], r_args)?;
+    let code = COLORS
+        .iter()
+        .find(|(color, _)| *color == name)
+        .map(|(_, code)| *code)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a color. Expected one of {}.",
+            name,
+            COLORS.iter().map(|(color, _)| format!("'{}'", color)).collect::<Vec<_>>().join(", "),
+        )))))?;
+    let text = args[1].get_display_text_with(ctx.display()).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(styled(ctx, code, text))
+}
+
+fn term_bold(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let text = args[0].get_display_text_with(ctx.display()).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(styled(ctx, 1, text))
+}
+
+fn term_clear(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    if ctx.io().is_terminal() {
+        ctx.io().print(String::from("\x1b[2J\x1b[H"))?;
+    }
+    Ok(Value::Null)
+}
+
+fn term_width(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(Value::Int(ctx.io().terminal_width().unwrap_or(DEFAULT_WIDTH) as i64))
+}
+
+// Columns of a table are separated by this many spaces
+const COLUMN_GAP: usize = 2;
+
+/// Render rows as a table with aligned columns and a line beneath the headers. Rows may be lists, whose items are
+/// placed in order, or maps, whose values are placed in the column of their key. Without headers, lists are shown
+/// without a header line and maps use their keys, sorted, as headers. Numbers are aligned to the right.
+fn term_table(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    let wrong_type = |expected: &str, val: &Value| ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from(expected), val.get_type_name())));
+    let display = |val: &Value, ctx: &ExecCtx| val.get_display_text_with(ctx.display()).map_err(|err| ExecError::At(r_args, Box::new(err)));
+
+    let rows = args[0].as_list().ok_or_else(|| wrong_type("list", &args[0]))?;
+    let mut headers = match args.get(1) {
+        Some(headers) => Some(headers.as_list().ok_or_else(|| wrong_type("list", headers))?),
+        None => None,
+    };
+    if headers.is_none() && rows.iter().all(|row| row.as_map().is_some()) && !rows.is_empty() {
+        let mut keys = Vec::new();
+        for row in &rows {
+            for key in row.as_map().unwrap().keys() {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        let mut keys = keys
+            .into_iter()
+            .map(|key| Ok((display(&key, ctx)?, key)))
+            .collect::<ExecResult<Vec<_>>>()?;
+        keys.sort_by(|(a, _), (b, _)| a.cmp(b));
+        headers = Some(keys.into_iter().map(|(_, key)| key).collect());
+    }
+
+    // Each cell is its text and whether it is aligned to the right. Keys missing from map rows leave their cell empty.
+    let cell = |val: Option<&Value>, ctx: &ExecCtx| match val {
+        Some(val) => Ok((display(val, ctx)?, val.as_number().is_some())),
+        None => Ok((String::new(), false)),
+    };
+    let mut cells = Vec::new();
+    for row in &rows {
+        cells.push(if let Some(items) = row.as_list() {
+            items.iter().map(|val| cell(Some(val), ctx)).collect::<ExecResult<Vec<_>>>()?
+        } else if let Some(map) = row.as_map() {
+            headers.iter().flatten().map(|key| cell(map.get(key), ctx)).collect::<ExecResult<Vec<_>>>()?
+        } else {
+            return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("Table rows must be lists or maps, found one of type '{}'.", row.get_type_name())))));
+        });
+    }
+    let headers = match headers {
+        Some(headers) => Some(headers.iter().map(|header| display(header, ctx)).collect::<ExecResult<Vec<_>>>()?),
+        None => None,
+    };
+
+    let columns = headers.iter().map(|headers| headers.len()).chain(cells.iter().map(|row| row.len())).max().unwrap_or(0);
+    let mut widths = vec![0; columns];
+    for (i, header) in headers.iter().flatten().enumerate() {
+        widths[i] = widths[i].max(header.chars().count());
+    }
+    for row in &cells {
+        for (i, (text, _)) in row.iter().enumerate() {
+            widths[i] = widths[i].max(text.chars().count());
+        }
+    }
+
+    let fmt_line = |cells: Vec<(&str, bool)>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|((text, right), width)| if *right {
+                format!("{:>width$}", text, width = width)
+            } else {
+                format!("{:<width$}", text, width = width)
+            })
+            .collect::<Vec<_>>()
+            .join(&Repeat(' ', COLUMN_GAP).to_string());
+        line.trim_end().to_string()
+    };
+    let mut lines = Vec::new();
+    if let Some(headers) = &headers {
+        lines.push(fmt_line(headers.iter().map(|header| (header.as_str(), false)).collect()));
+        let rules = widths.iter().map(|width| Repeat('-', *width).to_string()).collect::<Vec<_>>();
+        lines.push(fmt_line(rules.iter().map(|rule| (rule.as_str(), false)).collect()));
+    }
+    for row in &cells {
+        lines.push(fmt_line(row.iter().map(|(text, right)| (text.as_str(), *right)).collect()));
+    }
+    Ok(Value::from(lines.join("\n")))
+}
+
+fn term_progress(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let total = number_arg(&args[0], r_args)?;
+    if total < 0.0 || total.fract() != 0.0 {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("The total of a progress bar must be a whole, non-negative number.")))));
+    }
+    Ok(Value::from(ProgressBar {
+        total: total as usize,
+        state: Rc::new(RefCell::new(ProgressState::default())),
+    }))
+}
+
+#[derive(Default)]
+struct ProgressState {
+    done: usize,
+    // Whether the bar has been printed, and so must be replaced by the next update
+    drawn: bool,
+    finished: bool,
+}
+
+/// A progress bar, created with `term.progress(total)`.
+///
+/// `tick()` (or `tick(n)`) advances the bar and `finish()` completes it. On a terminal, the bar is redrawn in place
+/// after each update. Elsewhere, only the final count is printed when the bar is finished, so that logs are not
+/// flooded with updates. Ticks after the bar has finished are ignored.
+#[derive(Clone)]
+pub struct ProgressBar {
+    total: usize,
+    state: Rc<RefCell<ProgressState>>,
+}
+
+// The most columns that the bar itself takes up, leaving room for the count after it
+const MAX_BAR_WIDTH: usize = 40;
+
+impl ProgressBar {
+    fn render(&self, ctx: &mut ExecCtx) -> ExecResult<()> {
+        let mut state = self.state.borrow_mut();
+        let is_terminal = ctx.io().is_terminal();
+        if !is_terminal && !state.finished {
+            return Ok(());
+        }
+
+        let percent = if self.total == 0 { 100 } else { state.done * 100 / self.total };
+        let count = format!("{}/{} ({}%)", state.done, self.total, percent);
+        let line = if is_terminal {
+            let width = ctx.io().terminal_width().unwrap_or(DEFAULT_WIDTH)
+                .saturating_sub(count.len() + 3)
+                .min(MAX_BAR_WIDTH);
+            let filled = width * percent / 100;
+            // Move up to the previous line and clear it before drawing over it
+            format!("{}[{}{}] {}", if state.drawn { "\x1b[1A\x1b[2K" } else { "" }, Repeat('#', filled), Repeat(' ', width - filled), count)
+        } else {
+            count
+        };
+        state.drawn = true;
+        ctx.io().print(line)
+    }
+}
+
+impl Obj for ProgressBar {
+    fn get_type_name(&self) -> String {
+        String::from("progress")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<progress {}/{}>", self.state.borrow().done, self.total))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<ProgressBar>) = match name {
+            "tick" => ("tick", progress_tick),
+            "finish" => ("finish", progress_finish),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+}
+
+fn progress_tick(bar: &ProgressBar, args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 1, r_args)?;
+    let n = match args.first() {
+        Some(n) => number_arg(n, r_args)?.max(0.0) as usize,
+        None => 1,
+    };
+    {
+        let mut state = bar.state.borrow_mut();
+        if state.finished {
+            return Ok(Value::Null);
+        }
+        state.done = (state.done + n).min(bar.total);
+    }
+    bar.render(ctx).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Null)
+}
+
+fn progress_finish(bar: &ProgressBar, args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    {
+        let mut state = bar.state.borrow_mut();
+        if state.finished {
+            return Ok(Value::Null);
+        }
+        state.done = bar.total;
+        state.finished = true;
+    }
+    bar.render(ctx).map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(Value::Null)
+}
@@ -0,0 +1,471 @@
+use std::{
+    rc::Rc,
+    cmp::Ordering,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use crate::parser::SrcRef;
+use super::{
+    BinaryOpRef,
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Obj,
+    UnaryOpRef,
+    Value,
+    method::{self, Method, MethodFn},
+};
+
+const NANOS_PER_SEC: i128 = 1_000_000_000;
+const SECS_PER_DAY: i64 = 86_400;
+
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+fn op_error(op: &'static str, lhs: &dyn Obj, rhs: &Value, refs: BinaryOpRef) -> ExecError {
+    ExecError::BinaryOp {
+        op,
+        left_type: lhs.get_type_name(),
+        right_type: rhs.get_type_name(),
+        left_val: lhs.get_preview_text(),
+        right_val: rhs.get_preview_text(),
+        refs,
+    }
+}
+
+fn no_such_member(obj: &dyn Obj, name: &str, r: SrcRef) -> ExecError {
+    ExecError::At(r, Box::new(ExecError::NoSuchMember(obj.get_type_name(), name.to_string())))
+}
+
+// Days since 1970-01-01 of a date in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let mp = (month as i64 + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+// The inverse of `days_from_civil`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    (if month <= 2 { yoe + era * 400 + 1 } else { yoe + era * 400 }, month, day)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Render a number of nanoseconds as a fraction of a second, without trailing zeroes (`None` if there is no fraction)
+fn fmt_frac(nanos: i128) -> Option<String> {
+    if nanos == 0 {
+        None
+    } else {
+        Some(format!("{:09}", nanos).trim_end_matches('0').to_string())
+    }
+}
+
+/// A signed span of time with nanosecond precision.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration {
+    nanos: i128,
+}
+
+impl Duration {
+    pub fn from_nanos(nanos: i128) -> Self {
+        Self { nanos }
+    }
+
+    pub fn from_secs_f64(secs: f64) -> Self {
+        Self { nanos: (secs * NANOS_PER_SEC as f64).round() as i128 }
+    }
+
+    pub fn as_nanos(&self) -> i128 {
+        self.nanos
+    }
+
+    pub fn as_secs_f64(&self) -> f64 {
+        self.nanos as f64 / NANOS_PER_SEC as f64
+    }
+}
+
+impl Obj for Duration {
+    fn get_type_name(&self) -> String {
+        String::from("duration")
+    }
+
+    // Durations are shown as their days, hours, minutes and seconds, such as `1d 2h 30.5s`
+    fn get_display_text(&self) -> ExecResult<String> {
+        let total = self.nanos.abs();
+        let secs = total / NANOS_PER_SEC;
+        let units = [(secs / SECS_PER_DAY as i128, "d"), (secs / 3600 % 24, "h"), (secs / 60 % 60, "m")];
+
+        let mut parts = units
+            .iter()
+            .filter(|(n, _)| *n != 0)
+            .map(|(n, unit)| format!("{}{}", n, unit))
+            .collect::<Vec<_>>();
+        let (s, frac) = (secs % 60, fmt_frac(total % NANOS_PER_SEC));
+        if s != 0 || frac.is_some() || parts.is_empty() {
+            parts.push(match frac {
+                Some(frac) => format!("{}.{}s", s, frac),
+                None => format!("{}s", s),
+            });
+        }
+
+        Ok(format!("{}{}", if self.nanos < 0 { "-" } else { "" }, parts.join(" ")))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let secs = self.as_secs_f64();
+        match name {
+            "days" => Ok(Value::Float(secs / SECS_PER_DAY as f64)),
+            "hours" => Ok(Value::Float(secs / 3600.0)),
+            "minutes" => Ok(Value::Float(secs / 60.0)),
+            "seconds" => Ok(Value::Float(secs)),
+            "millis" => Ok(Value::Float(secs * 1000.0)),
+            _ => Err(no_such_member(self, name, r)),
+        }
+    }
+
+    fn eval_neg(&self, _refs: UnaryOpRef) -> ExecResult<Value> {
+        Ok(Value::from(Duration::from_nanos(-self.nanos)))
+    }
+
+    fn eval_add(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        if let Some(rhs) = rhs.downcast_ref::<Duration>() {
+            Ok(Value::from(Duration::from_nanos(self.nanos + rhs.nanos)))
+        } else if let Some(rhs) = rhs.downcast_ref::<DateTime>() {
+            Ok(Value::from(rhs.shifted(self.nanos)))
+        } else {
+            Err(op_error("add", self, rhs, refs))
+        }
+    }
+
+    fn eval_sub(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match rhs.downcast_ref::<Duration>() {
+            Some(rhs) => Ok(Value::from(Duration::from_nanos(self.nanos - rhs.nanos))),
+            None => Err(op_error("sub", self, rhs, refs)),
+        }
+    }
+
+    fn eval_mul(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match rhs {
+            rhs if rhs.as_number().is_some() => Ok(Value::from(Duration::from_secs_f64(self.as_secs_f64() * rhs.as_number().unwrap()))),
+            rhs => Err(op_error("mul", self, rhs, refs)),
+        }
+    }
+
+    fn eval_div(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match rhs {
+            rhs if rhs.as_number().is_some() => Ok(Value::from(Duration::from_secs_f64(self.as_secs_f64() / rhs.as_number().unwrap()))),
+            rhs => match rhs.downcast_ref::<Duration>() {
+                Some(rhs) => Ok(Value::Float(self.nanos as f64 / rhs.nanos as f64)),
+                None => Err(op_error("div", self, rhs, refs)),
+            },
+        }
+    }
+
+    fn eval_greater(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "greater", refs, |o| o == Ordering::Greater)
+    }
+
+    fn eval_greater_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "greater_eq", refs, |o| o != Ordering::Less)
+    }
+
+    fn eval_less(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "less", refs, |o| o == Ordering::Less)
+    }
+
+    fn eval_less_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "less_eq", refs, |o| o != Ordering::Greater)
+    }
+
+    fn eval_eq(&self, rhs: &Value, _refs: BinaryOpRef) -> ExecResult<Value> {
+        Ok(Value::Boolean(rhs.downcast_ref::<Duration>() == Some(self)))
+    }
+
+    fn eval_not_eq(&self, rhs: &Value, _refs: BinaryOpRef) -> ExecResult<Value> {
+        Ok(Value::Boolean(rhs.downcast_ref::<Duration>() != Some(self)))
+    }
+}
+
+/// An instant in time, along with the UTC offset used when displaying it and accessing its calendar fields.
+#[derive(Copy, Clone, Debug)]
+pub struct DateTime {
+    // Since the Unix epoch
+    nanos: i128,
+    // In seconds east of UTC
+    offset: i32,
+}
+
+impl DateTime {
+    pub fn from_unix_nanos(nanos: i128) -> Self {
+        Self { nanos, offset: 0 }
+    }
+
+    pub fn now() -> Self {
+        let nanos = match SystemTime::now().duration_since(UNIX_EPOCH) {
+            Ok(since) => since.as_nanos() as i128,
+            Err(err) => -(err.duration().as_nanos() as i128),
+        };
+        Self::from_unix_nanos(nanos)
+    }
+
+    pub fn unix_nanos(&self) -> i128 {
+        self.nanos
+    }
+
+    /// Parse a timestamp such as `2019-06-01T12:30:00.5+01:00`, as described by RFC 3339.
+    pub fn parse_rfc3339(s: &str) -> Option<Self> {
+        let s = s.trim();
+        let num = |from: usize, to: usize| s
+            .get(from..to)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+            .and_then(|digits| digits.parse::<u32>().ok());
+        let sep = |at: usize, seps: &[u8]| s.as_bytes().get(at).filter(|b| seps.contains(b)).map(|_| ());
+
+        let year = num(0, 4)? as i64;
+        sep(4, b"-")?;
+        let month = num(5, 7).filter(|m| (1..=12).contains(m))?;
+        sep(7, b"-")?;
+        let day = num(8, 10).filter(|d| (1..=days_in_month(year, month)).contains(d))?;
+        sep(10, b"Tt ")?;
+        let hour = num(11, 13).filter(|h| *h < 24)?;
+        sep(13, b":")?;
+        let minute = num(14, 16).filter(|m| *m < 60)?;
+        sep(16, b":")?;
+        let second = num(17, 19).filter(|s| *s < 60)?;
+
+        let mut rest = s.get(19..)?;
+        let mut frac_nanos = 0;
+        if rest.starts_with('.') {
+            let digits = rest[1..].bytes().take_while(|b| b.is_ascii_digit()).count();
+            if digits == 0 {
+                return None;
+            }
+            // Precision beyond nanoseconds is discarded
+            for (i, b) in rest[1..=digits].bytes().take(9).enumerate() {
+                frac_nanos += (b - b'0') as i128 * 10i128.pow(8 - i as u32);
+            }
+            rest = &rest[1 + digits..];
+        }
+
+        let offset = match rest.as_bytes() {
+            b"Z" | b"z" => 0,
+            [sign @ b'+', ..] | [sign @ b'-', ..] if rest.len() == 6 && &rest[3..4] == ":" => {
+                let h = rest.get(1..3).filter(|h| h.bytes().all(|b| b.is_ascii_digit()))?.parse::<i32>().ok()?;
+                let m = rest.get(4..6).filter(|m| m.bytes().all(|b| b.is_ascii_digit()))?.parse::<i32>().ok()?;
+                if h >= 24 || m >= 60 {
+                    return None;
+                }
+                (h * 3600 + m * 60) * if *sign == b'-' { -1 } else { 1 }
+            },
+            _ => return None,
+        };
+
+        let local_secs = days_from_civil(year, month, day) * SECS_PER_DAY
+            + (hour * 3600 + minute * 60 + second) as i64;
+        Some(Self {
+            nanos: (local_secs - offset as i64) as i128 * NANOS_PER_SEC + frac_nanos,
+            offset,
+        })
+    }
+
+    // (year, month, day, hour, minute, second, nanosecond, weekday) in the local time of the offset
+    fn fields(&self) -> (i64, u32, u32, u32, u32, u32, u32, u32) {
+        let local = self.nanos + self.offset as i128 * NANOS_PER_SEC;
+        let secs = local.div_euclid(NANOS_PER_SEC) as i64;
+        let nanos = local.rem_euclid(NANOS_PER_SEC) as u32;
+        let (days, sod) = (secs.div_euclid(SECS_PER_DAY), secs.rem_euclid(SECS_PER_DAY) as u32);
+        let (year, month, day) = civil_from_days(days);
+        // 1970-01-01 was a Thursday
+        let weekday = (days + 3).rem_euclid(7) as u32;
+        (year, month, day, sod / 3600, sod / 60 % 60, sod % 60, nanos, weekday)
+    }
+
+    fn fmt_offset(&self, utc: &str) -> String {
+        if self.offset == 0 && !utc.is_empty() {
+            utc.to_string()
+        } else {
+            let abs = self.offset.abs();
+            format!("{}{:02}:{:02}", if self.offset < 0 { '-' } else { '+' }, abs / 3600, abs / 60 % 60)
+        }
+    }
+
+    pub fn to_rfc3339(&self) -> String {
+        let (year, month, day, hour, minute, second, nanos, _) = self.fields();
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}{}",
+            year, month, day, hour, minute, second,
+            fmt_frac(nanos as i128).map(|frac| format!(".{}", frac)).unwrap_or_default(),
+            self.fmt_offset("Z"),
+        )
+    }
+
+    /// Format the date and time according to a pattern containing `strftime`-style specifiers.
+    pub fn format(&self, pattern: &str) -> Result<String, char> {
+        let (year, month, day, hour, minute, second, nanos, weekday) = self.fields();
+        let mut s = String::new();
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            if c != '%' {
+                s.push(c);
+                continue;
+            }
+            match chars.next().unwrap_or('%') {
+                'Y' => s += &format!("{:04}", year),
+                'm' => s += &format!("{:02}", month),
+                'd' => s += &format!("{:02}", day),
+                'H' => s += &format!("{:02}", hour),
+                'M' => s += &format!("{:02}", minute),
+                'S' => s += &format!("{:02}", second),
+                'f' => s += &format!("{:03}", nanos / 1_000_000),
+                'a' => s += WEEKDAYS[weekday as usize],
+                'b' => s += MONTHS[month as usize - 1],
+                'z' => s += &self.fmt_offset(""),
+                '%' => s.push('%'),
+                c => return Err(c),
+            }
+        }
+        Ok(s)
+    }
+
+    fn shifted(&self, nanos: i128) -> Self {
+        Self { nanos: self.nanos + nanos, offset: self.offset }
+    }
+}
+
+impl Obj for DateTime {
+    fn get_type_name(&self) -> String {
+        String::from("datetime")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(self.to_rfc3339())
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (year, month, day, hour, minute, second, nanos, weekday) = self.fields();
+        let (name, f): (_, MethodFn<DateTime>) = match name {
+            "year" => return Ok(Value::Int(year as i64)),
+            "month" => return Ok(Value::Int(month as i64)),
+            "day" => return Ok(Value::Int(day as i64)),
+            "hour" => return Ok(Value::Int(hour as i64)),
+            "minute" => return Ok(Value::Int(minute as i64)),
+            "second" => return Ok(Value::Int(second as i64)),
+            "nanosecond" => return Ok(Value::Int(nanos as i64)),
+            // Monday is 1 and Sunday is 7, as in ISO 8601
+            "weekday" => return Ok(Value::Int(weekday as i64 + 1)),
+            "unix" => return Ok(Value::Float(self.nanos as f64 / NANOS_PER_SEC as f64)),
+            "offset" => return Ok(Value::from(Duration::from_nanos(self.offset as i128 * NANOS_PER_SEC))),
+            "format" => ("format", datetime_format),
+            "to_offset" => ("to_offset", datetime_to_offset),
+            _ => return Err(no_such_member(self, name, r)),
+        };
+        Ok(Value::from(Method::new(name, *self, f)))
+    }
+
+    fn eval_add(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match rhs.downcast_ref::<Duration>() {
+            Some(rhs) => Ok(Value::from(self.shifted(rhs.nanos))),
+            None => Err(op_error("add", self, rhs, refs)),
+        }
+    }
+
+    fn eval_sub(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        if let Some(rhs) = rhs.downcast_ref::<Duration>() {
+            Ok(Value::from(self.shifted(-rhs.nanos)))
+        } else if let Some(rhs) = rhs.downcast_ref::<DateTime>() {
+            Ok(Value::from(Duration::from_nanos(self.nanos - rhs.nanos)))
+        } else {
+            Err(op_error("sub", self, rhs, refs))
+        }
+    }
+
+    fn eval_greater(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "greater", refs, |o| o == Ordering::Greater)
+    }
+
+    fn eval_greater_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "greater_eq", refs, |o| o != Ordering::Less)
+    }
+
+    fn eval_less(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "less", refs, |o| o == Ordering::Less)
+    }
+
+    fn eval_less_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        cmp_by(self, rhs, "less_eq", refs, |o| o != Ordering::Greater)
+    }
+
+    // Two datetimes are equal when they refer to the same instant, even if their offsets differ
+    fn eval_eq(&self, rhs: &Value, _refs: BinaryOpRef) -> ExecResult<Value> {
+        Ok(Value::Boolean(rhs.downcast_ref::<DateTime>().map(|rhs| rhs.nanos) == Some(self.nanos)))
+    }
+
+    fn eval_not_eq(&self, rhs: &Value, _refs: BinaryOpRef) -> ExecResult<Value> {
+        Ok(Value::Boolean(rhs.downcast_ref::<DateTime>().map(|rhs| rhs.nanos) != Some(self.nanos)))
+    }
+}
+
+// Values that may be ordered against others of the same type
+trait TimeKey: Obj + Sized {
+    fn key(&self) -> i128;
+}
+
+impl TimeKey for Duration {
+    fn key(&self) -> i128 {
+        self.nanos
+    }
+}
+
+impl TimeKey for DateTime {
+    fn key(&self) -> i128 {
+        self.nanos
+    }
+}
+
+fn cmp_by<T: TimeKey>(lhs: &T, rhs: &Value, op: &'static str, refs: BinaryOpRef, f: fn(Ordering) -> bool) -> ExecResult<Value> {
+    match rhs.downcast_ref::<T>() {
+        Some(rhs) => Ok(Value::Boolean(f(lhs.key().cmp(&rhs.key())))),
+        None => Err(op_error(op, lhs, rhs, refs)),
+    }
+}
+
+fn datetime_format(dt: &DateTime, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let pattern = match &args[0] {
+        Value::String(s) => s.borrow().clone(),
+        val => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("string"), val.get_type_name())))),
+    };
+    dt.format(&pattern)
+        .map(Value::from)
+        .map_err(|c| ExecError::At(r_args, Box::new(ExecError::Custom(format!("Unknown format specifier '%{}'.", c)))))
+}
+
+fn datetime_to_offset(dt: &DateTime, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    match args[0].downcast_ref::<Duration>() {
+        Some(offset) if offset.nanos.abs() < SECS_PER_DAY as i128 * NANOS_PER_SEC => Ok(Value::from(DateTime {
+            nanos: dt.nanos,
+            offset: (offset.nanos / NANOS_PER_SEC) as i32,
+        })),
+        Some(_) => Err(ExecError::At(r_args, Box::new(ExecError::Custom(String::from("Offsets must be less than a day."))))),
+        None => Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("duration"), args[0].get_type_name())))),
+    }
+}
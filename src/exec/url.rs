@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    rc::Rc,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    Map,
+    Value,
+    builtins::{BuiltinFn, string_arg},
+    method,
+};
+
+pub(super) const URL: &[(&str, BuiltinFn)] = &[
+    ("parse", url_parse),
+    ("encode", url_encode),
+    ("decode", url_decode),
+    ("join", url_join),
+];
+
+/// A URL split into the five components of RFC 3986. Parts that are absent (rather than empty) are `None`.
+pub(super) struct Parts<'a> {
+    pub(super) scheme: Option<&'a str>,
+    pub(super) authority: Option<&'a str>,
+    pub(super) path: &'a str,
+    pub(super) query: Option<&'a str>,
+    pub(super) fragment: Option<&'a str>,
+}
+
+impl<'a> Parts<'a> {
+    pub(super) fn split(s: &'a str) -> Self {
+        let (s, fragment) = match s.find('#') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        let (s, query) = match s.find('?') {
+            Some(i) => (&s[..i], Some(&s[i + 1..])),
+            None => (s, None),
+        };
+        // A scheme is only present if the colon comes before any slash
+        let (scheme, s) = match s.find(|c| c == ':' || c == '/') {
+            Some(i) if i > 0 && s[i..].starts_with(':') => (Some(&s[..i]), &s[i + 1..]),
+            _ => (None, s),
+        };
+        let (authority, path) = if s.starts_with("//") {
+            let end = s[2..].find('/').map(|i| i + 2).unwrap_or(s.len());
+            (Some(&s[2..end]), &s[end..])
+        } else {
+            (None, s)
+        };
+        Parts { scheme, authority, path, query, fragment }
+    }
+}
+
+impl<'a> fmt::Display for Parts<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        write!(f, "{}", self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// Split a URL into a map of its components: `scheme`, `username`, `password`, `host`, `port`, `path`, `query`,
+/// `fragment`, and the decoded key-value pairs of the query as `params`. Components that are absent are null.
+fn url_parse(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let s = string_arg(&args[0], r_args)?;
+    let parts = Parts::split(&s);
+
+    let (userinfo, hostport) = match parts.authority {
+        Some(authority) => match authority.rfind('@') {
+            Some(i) => (Some(&authority[..i]), Some(&authority[i + 1..])),
+            None => (None, Some(authority)),
+        },
+        None => (None, None),
+    };
+    let (username, password) = match userinfo {
+        Some(userinfo) => match userinfo.find(':') {
+            Some(i) => (Some(&userinfo[..i]), Some(&userinfo[i + 1..])),
+            None => (Some(userinfo), None),
+        },
+        None => (None, None),
+    };
+    // The port follows the last colon, unless that colon is within the brackets of an IPv6 address
+    let (host, port) = match hostport {
+        Some(hostport) => match hostport.rfind(':').filter(|i| !hostport[*i..].contains(']')) {
+            Some(i) => (Some(&hostport[..i]), Some(&hostport[i + 1..])),
+            None => (Some(hostport), None),
+        },
+        None => (None, None),
+    };
+    let port = match port.filter(|port| !port.is_empty()) {
+        Some(port) => Some(port.parse::<u16>().map_err(|_| ExecError::At(r_args, Box::new(ExecError::Custom(format!("'{}' is not a valid port.", port)))))?),
+        None => None,
+    };
+    let decode_part = |part: Option<&str>| part.map(|part| decode(part, false)).transpose()
+        .map_err(|err| ExecError::At(r_args, Box::new(err)));
+
+    let mut params = Map::new();
+    for pair in parts.query.unwrap_or("").split('&').filter(|pair| !pair.is_empty()) {
+        let (key, val) = match pair.find('=') {
+            Some(i) => (&pair[..i], &pair[i + 1..]),
+            None => (pair, ""),
+        };
+        params.insert(
+            Value::from(decode(key, true).map_err(|err| ExecError::At(r_args, Box::new(err)))?),
+            Value::from(decode(val, true).map_err(|err| ExecError::At(r_args, Box::new(err)))?),
+        );
+    }
+
+    let mut url = Map::new();
+    url.insert(Value::from("scheme"), Value::from(parts.scheme.map(|scheme| scheme.to_lowercase())));
+    url.insert(Value::from("username"), Value::from(decode_part(username)?));
+    url.insert(Value::from("password"), Value::from(decode_part(password)?));
+    url.insert(Value::from("host"), Value::from(host.map(|host| host.to_lowercase())));
+    url.insert(Value::from("port"), Value::from(port.map(|port| port as i64)));
+    url.insert(Value::from("path"), Value::from(parts.path));
+    url.insert(Value::from("query"), Value::from(parts.query));
+    url.insert(Value::from("fragment"), Value::from(parts.fragment));
+    url.insert(Value::from("params"), Value::from(params));
+    Ok(Value::from(url))
+}
+
+// Characters that never need to be encoded
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"-_.~".contains(&b)
+}
+
+/// Percent-encode text for use as a single component of a URL, such as a query parameter or path segment.
+fn url_encode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let s = string_arg(&args[0], r_args)?;
+    Ok(Value::from(s
+        .bytes()
+        .map(|b| if is_unreserved(b) { (b as char).to_string() } else { format!("%{:02X}", b) })
+        .collect::<String>()))
+}
+
+fn url_decode(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let s = string_arg(&args[0], r_args)?;
+    Ok(Value::from(decode(&s, false).map_err(|err| ExecError::At(r_args, Box::new(err)))?))
+}
+
+// Query strings from HTML forms encode spaces as `+`
+fn decode(s: &str, plus_as_space: bool) -> ExecResult<String> {
+    let invalid = || ExecError::Custom(format!("'{}' is not validly percent-encoded.", s));
+    let mut bytes = Vec::new();
+    let mut iter = s.bytes();
+    while let Some(b) = iter.next() {
+        match b {
+            b'%' => {
+                let hex = iter.next().into_iter().chain(iter.next()).collect::<Vec<_>>();
+                let byte = std::str::from_utf8(&hex).ok()
+                    .filter(|hex| hex.len() == 2)
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    .ok_or_else(invalid)?;
+                bytes.push(byte);
+            },
+            b'+' if plus_as_space => bytes.push(b' '),
+            b => bytes.push(b),
+        }
+    }
+    String::from_utf8(bytes).map_err(|_| invalid())
+}
+
+/// Resolve a URL relative to a base URL, as a browser resolves links (RFC 3986, section 5.2).
+fn url_join(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 2, 2, r_args)?;
+    let base_str = string_arg(&args[0], r_args)?;
+    let rel_str = string_arg(&args[1], r_args)?;
+    let base = Parts::split(&base_str);
+    let rel = Parts::split(&rel_str);
+    if base.scheme.is_none() {
+        return Err(ExecError::At(r_args, Box::new(ExecError::Custom(format!("The base URL '{}' must be absolute.", base_str)))));
+    }
+
+    let path;
+    let joined = if rel.scheme.is_some() {
+        path = remove_dot_segments(rel.path);
+        Parts { path: &path, ..rel }
+    } else if rel.authority.is_some() {
+        path = remove_dot_segments(rel.path);
+        Parts { scheme: base.scheme, path: &path, ..rel }
+    } else if rel.path.is_empty() {
+        Parts {
+            query: rel.query.or(base.query),
+            fragment: rel.fragment,
+            ..base
+        }
+    } else {
+        path = if rel.path.starts_with('/') {
+            remove_dot_segments(rel.path)
+        } else {
+            // Replace the last segment of the base path
+            let merged = match (base.authority, base.path) {
+                (Some(_), "") => format!("/{}", rel.path),
+                (_, base_path) => format!("{}{}", &base_path[..base_path.rfind('/').map(|i| i + 1).unwrap_or(0)], rel.path),
+            };
+            remove_dot_segments(&merged)
+        };
+        Parts {
+            scheme: base.scheme,
+            authority: base.authority,
+            path: &path,
+            query: rel.query,
+            fragment: rel.fragment,
+        }
+    };
+    Ok(Value::from(joined.to_string()))
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    // The empty segment before the leading slash of an absolute path is never removed
+    let root = if path.starts_with('/') { 1 } else { 0 };
+    let parts = path.split('/').collect::<Vec<_>>();
+    for (i, segment) in parts.iter().enumerate() {
+        let is_last = i == parts.len() - 1;
+        match *segment {
+            "." => if is_last { segments.push("") },
+            ".." => {
+                if segments.len() > root {
+                    segments.pop();
+                }
+                if is_last {
+                    segments.push("");
+                }
+            },
+            segment => segments.push(segment),
+        }
+    }
+    let joined = segments.join("/");
+    if path.starts_with('/') && !joined.starts_with('/') {
+        format!("/{}", joined)
+    } else {
+        joined
+    }
+}
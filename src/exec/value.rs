@@ -1,12 +1,14 @@
 use std::{
     rc::Rc,
-    cmp::PartialEq,
+    cmp::{Ordering, PartialEq},
     fmt,
     ops::Range,
     cell::RefCell,
     collections::HashMap as StdHashMap,
     hash::{Hash, Hasher},
     mem,
+    any::Any,
+    convert::TryFrom,
 };
 use::hashbrown::HashMap;
 use crate::{
@@ -19,21 +21,32 @@ use crate::{
             Expr,
         },
     },
+    vm::Vm,
 };
 use super::{
+    class,
+    gc,
+    method,
+    range::Bounds,
+    List,
+    Map,
     Obj,
     UnaryOpRef,
     BinaryOpRef,
     ExecError,
     ExecResult,
     Scope,
-    GlobalScope,
-    Io,
+    Env,
+    BlockScope,
+    ExecCtx,
+    DisplayOptions,
+    truncate_preview,
 };
 
 #[derive(Debug)]
 pub enum Type {
-    Number,
+    Int,
+    Float,
     String,
     Char,
     Boolean,
@@ -45,46 +58,84 @@ pub enum Type {
 }
 
 impl Type {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "int" => Some(Type::Int),
+            // `num` predates the split between ints and floats
+            "float" | "num" => Some(Type::Float),
+            "str" => Some(Type::String),
+            "char" => Some(Type::Char),
+            "bool" => Some(Type::Boolean),
+            "range" => Some(Type::Range),
+            "fn" => Some(Type::Fn),
+            "list" => Some(Type::List),
+            "Custom" => Some(Type::Custom),
+            "null" => Some(Type::Null),
+            _ => None,
+        }
+    }
+
     pub fn get_name(&self) -> String {
         match self {
-            Type::Number => String::from("number"),
+            Type::Int => String::from("int"),
+            Type::Float => String::from("float"),
             Type::String => String::from("string"),
             Type::Char => String::from("char"),
             Type::Boolean => String::from("bool"),
             Type::Range => String::from("range"),
             Type::Fn => String::from("function"),
             Type::List => String::from("list"),
-            Type::Custom => unimplemented!(),
+            Type::Custom => String::from("custom"),
             Type::Null => String::from("null"),
         }
     }
 }
 
 pub trait ForgeIter = Iterator<Item=Value>;// + fmt::Debug;
+pub trait PairIter = Iterator<Item=(Value, Value)>;
 
 #[derive(Clone)]
 pub enum Value {
-    Number(f64),
+    Int(i64),
+    Float(f64),
     String(Rc<RefCell<String>>),
     Char(char),
     Boolean(bool),
-    Range(f64, f64),
-    Fn(Rc<String>, Rc<(Node<Args>, Node<Block>)>),
-    List(Rc<RefCell<Vec<Value>>>),
-    Map(Rc<RefCell<HashMap<Value, Value>>>),
+    Range(Bounds),
+    // A function and the environment it captured when it was created
+    Fn(Rc<String>, Rc<(Node<Args>, Node<Block>, Option<String>)>, Env),
+    List(Rc<RefCell<List>>),
+    Map(Rc<RefCell<Map>>),
     Custom(Rc<dyn Obj>),
     Null,
 }
 
+/// Drop values one at a time, moving the items out of each list and map that is dropped rather than letting them drop
+/// with it. Dropping them recursively would overflow the stack when they are nested deeply enough.
+pub(super) fn drop_items(mut pending: Vec<Value>) {
+    while let Some(val) = pending.pop() {
+        match val {
+            Value::List(l) => if let Ok(l) = Rc::try_unwrap(l) {
+                pending.append(&mut l.into_inner().take_items());
+            },
+            Value::Map(m) => if let Ok(m) = Rc::try_unwrap(m) {
+                pending.append(&mut m.into_inner().take_items());
+            },
+            _ => {},
+        }
+    }
+}
+
 impl fmt::Debug for Value {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Value::Number(x) => writeln!(f, "Number({:?})", x),
+            Value::Int(x) => writeln!(f, "Int({:?})", x),
+            Value::Float(x) => writeln!(f, "Float({:?})", x),
             Value::String(s) => writeln!(f, "String({:?})", s),
             Value::Char(c) => writeln!(f, "Char({:?})", c),
             Value::Boolean(b) => writeln!(f, "Boolean({:?})", b),
-            Value::Range(x, y) => writeln!(f, "Range({:?}, {:?})", x, y),
-            Value::Fn(s, func) => writeln!(f, "Fn({:?}, {:?})", s, func),
+            Value::Range(bounds) => writeln!(f, "Range({:?})", bounds),
+            Value::Fn(s, func, env) => writeln!(f, "Fn({:?}, {:?}, {:?})", s, func, env),
             Value::List(l) => writeln!(f, "List({:?})", l.borrow()),
             Value::Map(m) => writeln!(f, "Map({:?})", m.borrow()),
             Value::Custom(c) => writeln!(f, "Custom({:?})", &c as *const _),
@@ -94,35 +145,35 @@ impl fmt::Debug for Value {
 }
 
 impl<V: Into<Value>, F: Fn() -> V + 'static> Obj for F {
-    fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, _caller: &mut dyn Scope, _io: &mut dyn Io, src: &Rc<String>, _r_caller: SrcRef) -> ExecResult<Value> {
-        if params.0.len() != 0 {
-            Err(ExecError::At(params.1, Box::new(ExecError::WrongArgNum(
-                None, 0, params.0.len()
+    fn call(&self, args: Vec<Value>, _ctx: &mut ExecCtx, src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        if args.len() != 0 {
+            Err(ExecError::At(r_args, Box::new(ExecError::WrongArgNum(
+                None, 0, args.len()
             )))).map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))
         } else {
-            Ok(self.call(()).into())
+            Ok((self)().into())
         }
     }
 }
 
 /* TODO: Fix this
 macro_rules! expand_args {
-    ($params:expr, $caller:expr, $io:expr, $src:expr, 1) => ($caller.eval_expr(&$params.0[0].0, $io, $src)?);
-    ($params:expr, $caller:expr, $io:expr, $src:expr, $n:expr, $left:tt) => (
-        expand_args!($params, $caller, $io, $src, $left), $caller.eval_expr(&$params.0[$n].0, $io, $src)?
+    ($params:expr, $caller:expr, $ctx:expr, $src:expr, 1) => ($caller.eval_expr(&$params.0[0].0, $ctx, $src)?);
+    ($params:expr, $caller:expr, $ctx:expr, $src:expr, $n:expr, $left:tt) => (
+        expand_args!($params, $caller, $ctx, $src, $left), $caller.eval_expr(&$params.0[$n].0, $ctx, $src)?
     );
 }
 
 macro_rules! impl_obj_for_fn {
     ($n:expr, $x:expr) => (
         impl<V: Into<Value> + 'static> Obj for fn(Value) -> V {
-            fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, caller: &mut dyn Scope, io: &mut dyn Io, src: &Rc<String>, _r_caller: SrcRef) -> ExecResult<Value> {
+            fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, caller: &mut dyn Scope, ctx: &mut ExecCtx, src: &Rc<String>, _r_caller: SrcRef) -> ExecResult<Value> {
                 if params.0.len() != $n {
                     Err(ExecError::At(params.1, Box::new(ExecError::WrongArgNum(
                         None, 0, params.0.len()
                     )))).map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)))
                 } else {
-                    Ok(self(expand_args!(params, caller, io, src, $n, $x)).into())
+                    Ok(self(expand_args!(params, caller, ctx, src, $n, $x)).into())
                 }
             }
         }
@@ -132,15 +183,32 @@ impl_obj_for_fn!(1);
 impl_obj_for_fn!(1, 2);
 */
 
+/// A call to a function written in Forge that another function ended with, as in `return g(x);`, which is left for the
+/// caller of that function to make (see `Value::call_method`).
+pub(crate) struct TailCall {
+    pub(crate) code: Rc<String>,
+    pub(crate) f: Rc<(Node<Args>, Node<Block>, Option<String>)>,
+    pub(crate) env: Env,
+    pub(crate) recv: Option<Value>,
+    pub(crate) args: Vec<Value>,
+    pub(crate) named: Vec<(Node<String>, Value)>,
+    // Where the call was made, which is within the code of the function that ended with it
+    pub(crate) src: Rc<String>,
+    pub(crate) r_caller: SrcRef,
+    pub(crate) r_args: SrcRef,
+}
+
 impl PartialEq for Value {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
-            (Value::Number(x), Value::Number(y)) => x.eq(y),
+            (Value::Int(x), Value::Int(y)) => x.eq(y),
+            (Value::Float(x), Value::Float(y)) => x.eq(y),
+            (Value::Int(x), Value::Float(y)) | (Value::Float(y), Value::Int(x)) => exact_int(*y) == Some(*x),
             (Value::String(x), Value::String(y)) => x.eq(y),
             (Value::Char(x), Value::Char(y)) => x.eq(y),
             (Value::Boolean(x), Value::Boolean(y)) => x.eq(y),
-            (Value::Range(x0, x1), Value::Range(y0, y1)) => (x0, x1).eq(&(y0, y1)),
-            (Value::Fn(_, x), Value::Fn(_, y)) => Rc::ptr_eq(&x, &y),
+            (Value::Range(x), Value::Range(y)) => x.parts() == y.parts(),
+            (Value::Fn(_, x, x_env), Value::Fn(_, y, y_env)) => Rc::ptr_eq(&x, &y) && x_env.ptr_eq(y_env),
             (Value::List(x), Value::List(y)) => Rc::ptr_eq(&x, &y),
             (Value::Map(x), Value::Map(y)) => Rc::ptr_eq(&x, &y),
             (Value::Null, Value::Null) => true,
@@ -153,26 +221,44 @@ impl Eq for Value {}
 
 impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        mem::discriminant(self).hash(state);
+        // Ints and floats that are equal must hash the same, so they share a discriminant
+        match self {
+            Value::Float(_) => mem::discriminant(&Value::Int(0)).hash(state),
+            _ => mem::discriminant(self).hash(state),
+        }
         match self {
-            Value::Number(x) => x.to_bits().hash(state),
+            Value::Int(x) => x.hash(state),
+            Value::Float(x) => match exact_int(*x) {
+                Some(x) => x.hash(state),
+                None => x.to_bits().hash(state),
+            },
             Value::String(x) => x.borrow().as_str().hash(state),
             Value::Char(x) => x.hash(state),
             Value::Boolean(x) => x.hash(state),
-            Value::Range(a, b) => {
-                a.to_bits().hash(state);
-                b.to_bits().hash(state);
-            },
-            Value::Fn(_, x) => Rc::into_raw(x.clone()).hash(state),
-            Value::List(x) => Rc::into_raw(x.clone()).hash(state),
-            Value::Map(x) => Rc::into_raw(x.clone()).hash(state),
-            Value::Custom(x) => Rc::into_raw(x.clone()).hash(state),
+            Value::Range(bounds) => bounds.parts().hash(state),
+            Value::Fn(_, x, _) => (Rc::as_ptr(x) as *const ()).hash(state),
+            Value::List(x) => (Rc::as_ptr(x) as *const ()).hash(state),
+            Value::Map(x) => (Rc::as_ptr(x) as *const ()).hash(state),
+            Value::Custom(x) => (Rc::as_ptr(x) as *const ()).hash(state),
             Value::Null => {},
         }
     }
 }
 
 impl Value {
+    // Lists and maps may form reference cycles, so they are always created here to be tracked by the cycle collector
+    pub(crate) fn new_list(list: List) -> Self {
+        let l = Rc::new(RefCell::new(list));
+        gc::track_list(&l);
+        Value::List(l)
+    }
+
+    pub(crate) fn new_map(map: Map) -> Self {
+        let m = Rc::new(RefCell::new(map));
+        gc::track_map(&m);
+        Value::Map(m)
+    }
+
     pub fn as_custom(self) -> Option<Rc<dyn Obj>> {
         match self {
             Value::Custom(rc) => Some(rc.clone()),
@@ -180,35 +266,252 @@ impl Value {
         }
     }
 
+    /// Whether the value is a function of any kind, including builtins and the methods of values.
+    pub fn is_function(&self) -> bool {
+        match self {
+            Value::Fn(..) => true,
+            Value::Custom(c) => c.get_type_name() == "function",
+            _ => false,
+        }
+    }
+
+    /// For functions, the number of parameters that must be given when calling them, leaving out those with defaults
+    /// and any that collect extra arguments. Builtins that take a varying number of arguments produce `None`.
+    pub fn arity(&self) -> Option<usize> {
+        match self {
+            Value::Fn(_, f, _) => Some((f.0).0.0.iter().filter(|param| param.default.is_none()).count()),
+            Value::Custom(c) => c.arity(),
+            _ => None,
+        }
+    }
+
+    /// For functions, the name that they were declared with. Anonymous functions produce `None`.
+    pub fn fn_name(&self) -> Option<String> {
+        match self {
+            Value::Fn(_, f, _) => f.2.clone(),
+            Value::Custom(c) => c.name(),
+            _ => None,
+        }
+    }
+
+    pub fn is_null(&self) -> bool {
+        match self {
+            Value::Null => true,
+            _ => false,
+        }
+    }
+
+    /// The value of an int or a float, as a float.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Value::Int(x) => Some(*x as f64),
+            Value::Float(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Value::Int(x) => Some(*x),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_char(&self) -> Option<char> {
+        match self {
+            Value::Char(c) => Some(*c),
+            _ => None,
+        }
+    }
+
+    /// Copy the contents of a string value. Strings are mutable and shared, so they cannot be borrowed directly.
+    pub fn as_string(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(s.borrow().clone()),
+            _ => None,
+        }
+    }
+
+    /// Copy the items of a list value. The items themselves are still shared with the list.
+    pub fn as_list(&self) -> Option<Vec<Value>> {
+        match self {
+            Value::List(l) => Some(l.borrow().to_vec()),
+            _ => None,
+        }
+    }
+
+    /// Copy the entries of a map value. The keys and values themselves are still shared with the map.
+    pub fn as_map(&self) -> Option<StdHashMap<Value, Value>> {
+        match self {
+            Value::Map(m) => Some(m.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect()),
+            _ => None,
+        }
+    }
+
+    /// Borrow the Rust value behind a custom value, if it is of the given type.
+    pub fn downcast_ref<T: Obj>(&self) -> Option<&T> {
+        match self {
+            Value::Custom(c) => (&**c as &dyn Any).downcast_ref(),
+            _ => None,
+        }
+    }
+
     #[inline(always)]
-    pub fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, caller: &mut dyn Scope, io: &mut dyn Io, src: &Rc<String>, r_caller: SrcRef) -> ExecResult<Value> {
+    pub fn eval_call(&self, params: &Node<Vec<Node<Expr>>>, caller: &mut dyn Scope, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef) -> ExecResult<Value> {
         match self {
-            Value::Fn(code, f) => if ((f.0).0).0.len() != params.0.len() {
-                return Err(ExecError::WithPrevSrc(code.clone(), Box::new(ExecError::At(params.1, Box::new(ExecError::WrongArgNum(
-                    Some((f.0).1), ((f.0).0).0.len(), params.0.len()
-                )))))).map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)));
-            } else {
-                // TODO: Properly scope functions
-                let mut scope = GlobalScope::empty();
-                for (arg, param) in ((f.0).0).0.iter().zip(&params.0) {
-                    scope.declare_var(arg.0.clone(), caller.eval_expr(&param.0, io, src)?);
+            Value::Fn(_, _, _) => {
+                let mut args = Vec::with_capacity(params.0.len());
+                for param in &params.0 {
+                    args.push(caller.eval_expr(&param.0, ctx, src)?);
+                }
+                self.call(args, ctx, src, r_caller, params.1)
+            },
+            Value::Custom(custom) => custom.eval_call(params, caller, ctx, src, r_caller),
+            _ => Err(ExecError::At(r_caller, Box::new(ExecError::CannotCall(self.get_type_name())))),
+        }
+    }
+
+    pub fn call(&self, args: Vec<Value>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        self.call_named(args, Vec::new(), ctx, src, r_caller, r_args)
+    }
+
+    /// Call the value with arguments given by name as well as by position, as in `draw(1, color: "red")`. Only
+    /// functions written in Forge have parameter names to match against, so other values cannot be given names.
+    pub fn call_named(&self, args: Vec<Value>, named: Vec<(Node<String>, Value)>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        self.call_method(None, args, named, ctx, src, r_caller, r_args)
+    }
+
+    /// Call the value, which was found as a member of the receiver, as in `counter.add(1)`. Functions written in Forge
+    /// that are stored in maps are methods of those maps, and refer to them as `self`.
+    pub fn call_method(&self, recv: Option<Value>, args: Vec<Value>, named: Vec<(Node<String>, Value)>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let error = |r, msg| ExecError::WithSrc(src.clone(), Box::new(ExecError::At(r, Box::new(ExecError::Custom(msg)))));
+        match self {
+            Value::Fn(code, f, env) => {
+                let mut res = Self::call_fn(code, f, env, recv, args, named, ctx, src, r_caller, r_args);
+                // A function that ends by calling another, as in `return g(x);`, leaves that call to be made here once
+                // it has returned, so that recursion through such calls does not use up the stack. Errors from them
+                // pass through this function and the last of them, but not those in between.
+                while res.is_ok() {
+                    let call = match ctx.tail_call.take() {
+                        Some(call) => call,
+                        None => break,
+                    };
+                    let TailCall { code: tail_code, f: tail_f, env: tail_env, recv, args, named, src: tail_src, r_caller: r_tail, r_args } = call;
+                    res = Self::call_fn(&tail_code, &tail_f, &tail_env, recv, args, named, ctx, &tail_src, r_tail, r_args)
+                        .map_err(|err| ExecError::InFn(f.2.clone(), r_caller, Box::new(ExecError::WithSrc(
+                            code.clone(),
+                            Box::new(ExecError::WithSrc(tail_src.clone(), Box::new(err))),
+                        ))));
                 }
-                Ok(scope.eval_block(&(f.1).0, io, &code)?.unwrap_or(Value::Null))
+                res
+            },
+            _ if !named.is_empty() => Err(error(named[0].0.1, format!(
+                "Arguments cannot be given by name to a value of type '{}'.", self.get_type_name(),
+            ))),
+            Value::Custom(custom) => {
+                let _site = gc::enter_site(r_caller);
+                custom.call(args, ctx, src, r_caller, r_args)
             },
-            Value::Custom(custom) => custom.eval_call(params, caller, io, src, r_caller),
             _ => Err(ExecError::At(r_caller, Box::new(ExecError::CannotCall(self.get_type_name())))),
         }
     }
 
+    fn call_fn(code: &Rc<String>, f: &Rc<(Node<Args>, Node<Block>, Option<String>)>, env: &Env, recv: Option<Value>, args: Vec<Value>, named: Vec<(Node<String>, Value)>, ctx: &mut ExecCtx, src: &Rc<String>, r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+        let error = |r, msg| ExecError::WithSrc(src.clone(), Box::new(ExecError::At(r, Box::new(ExecError::Custom(msg)))));
+        let params = &(f.0).0;
+        let (min, max) = params.arity();
+        if (named.is_empty() && args.len() < min) || max.map(|max| args.len() > max).unwrap_or(false) {
+            return Err(ExecError::WithPrevSrc(code.clone(), Box::new(ExecError::At(r_args, Box::new(ExecError::WrongArgNum(
+                Some((f.0).1), if args.len() < min { min } else { params.0.len() }, args.len()
+            )))))).map_err(|err| ExecError::WithSrc(src.clone(), Box::new(err)));
+        }
+
+        // Each parameter is given its argument and where that argument was given, for errors to point to
+        let mut args = args.into_iter();
+        let mut slots = params.0.iter().map(|_| args.next().map(|val| (val, r_args))).collect::<Vec<_>>();
+        for (name, val) in named {
+            match params.0.iter().position(|param| param.name.0 == name.0) {
+                Some(i) if slots[i].is_some() => return Err(error(name.1, format!(
+                    "The parameter '{}' was given more than one argument.", name.0,
+                ))),
+                Some(i) => slots[i] = Some((val, name.1)),
+                None => return Err(error(name.1, format!("The function has no parameter named '{}'.", name.0))),
+            }
+        }
+        if let Some(param) = params.0.iter().zip(&slots).find(|(param, slot)| slot.is_none() && param.default.is_none()).map(|(param, _)| param) {
+            return Err(error(r_args, format!("No argument was given for the parameter '{}'.", param.name.0)));
+        }
+        for (param, slot) in params.0.iter().zip(&slots) {
+            match (&param.ty, slot) {
+                (Some(ty), Some((val, r))) if !val.has_type(&ty.0) => return Err(error(*r, format!(
+                    "Expected an argument of type '{}' for parameter '{}', found one of type '{}'.",
+                    ty.0, param.name.0, val.get_type_name(),
+                ))),
+                _ => {},
+            }
+        }
+
+        ctx.poll_interrupt(src, r_caller)?;
+        // The frames of the call, and whatever its code allocates without a location of its own, were made by the call
+        let _site = gc::enter_site(r_caller);
+        if ctx.max_call_depth.map(|max| ctx.call_depth >= max).unwrap_or(false) {
+            return Err(ExecError::WithSrc(src.clone(), Box::new(ExecError::StackOverflow(r_caller))));
+        }
+        let in_fn = |err| ExecError::InFn(f.2.clone(), r_caller, Box::new(ExecError::WithSrc(code.clone(), Box::new(err))));
+        let mut scope = BlockScope::within(env);
+        if let Some(recv @ Value::Map(_)) = recv {
+            scope.declare_var(String::from("self"), recv);
+        }
+        // Defaults are evaluated in the function's scope, so they may refer to the parameters before them
+        for (param, slot) in params.0.iter().zip(slots) {
+            let val = match (slot, &param.default) {
+                (Some((val, _)), _) => val,
+                (None, Some(default)) => scope.eval_expr(&default.0, ctx, &code).map_err(in_fn)?,
+                (None, None) => Value::Null,
+            };
+            scope.declare_var(param.name.0.clone(), val);
+        }
+        if let Some(rest) = &params.1 {
+            scope.declare_var(rest.0.clone(), Value::from(args.collect::<Vec<_>>()));
+        }
+        let outer = mem::replace(&mut ctx.tail_calls, true);
+        ctx.call_depth += 1;
+        let res = match ctx.compiled_fns.as_mut().and_then(|fns| fns.get(f)) {
+            Some(chunk) => Vm::new(&mut scope).run(&chunk, ctx, &code),
+            None => scope.eval_block(&(f.1).0, ctx, &code),
+        };
+        ctx.call_depth -= 1;
+        ctx.tail_calls = outer;
+        Ok(res.map_err(in_fn)?.unwrap_or(Value::Null))
+    }
+
+    /// Whether this value may be passed for a parameter with the given type annotation. `num` accepts both ints and
+    /// floats, and the names that `as` understands may be used alongside the names of the types themselves.
+    pub fn has_type(&self, ty: &str) -> bool {
+        match (ty, self) {
+            ("num", Value::Int(_)) | ("num", Value::Float(_)) => true,
+            ("str", Value::String(_)) | ("fn", Value::Fn(..)) => true,
+            (ty, val) => val.get_type_name() == ty,
+        }
+    }
+
     #[inline(always)]
     pub fn get_type_name(&self) -> String {
         match self {
-            Value::Number(_) => String::from("number"),
+            Value::Int(_) => String::from("int"),
+            Value::Float(_) => String::from("float"),
             Value::String(_) => String::from("string"),
             Value::Char(_) => String::from("char"),
             Value::Boolean(_) => String::from("bool"),
-            Value::Range(_, _) => String::from("range"),
-            Value::Fn(_, _) => String::from("function"),
+            Value::Range(_) => String::from("range"),
+            Value::Fn(_, _, _) => String::from("function"),
             Value::List(_) => String::from("list"),
             Value::Map(_) => String::from("map"),
             Value::Custom(c) => c.get_type_name(),
@@ -218,41 +521,95 @@ impl Value {
 
     #[inline(always)]
     pub fn get_display_text(&self) -> ExecResult<String> {
-        Ok(match self {
-            Value::Number(x) => format!("{}", x),
+        self.get_display_text_with(&DisplayOptions::default())
+    }
+
+    pub fn get_display_text_with(&self, opts: &DisplayOptions) -> ExecResult<String> {
+        self.get_display_text_nested(opts, &mut Vec::new())
+    }
+
+    // `parents` holds the addresses of the containers currently being displayed, so that self-referential values can
+    // be displayed without recursing forever.
+    fn get_display_text_nested(&self, opts: &DisplayOptions, parents: &mut Vec<*const ()>) -> ExecResult<String> {
+        let elided = |len: usize| opts.max_list_len.map(|max| len > max).unwrap_or(false);
+        let addr = match self {
+            Value::List(l) => Some(l.as_ptr() as *const ()),
+            Value::Map(m) => Some(m.as_ptr() as *const ()),
+            _ => None,
+        };
+        if let Some(addr) = addr {
+            if parents.contains(&addr) {
+                return Ok(String::from("<cycle>"));
+            } else if opts.max_depth.map(|max| parents.len() >= max).unwrap_or(false) {
+                return Ok(String::from("[...]"));
+            }
+            parents.push(addr);
+        }
+
+        let text = match self {
+            Value::Int(x) => opts.fmt_int(*x),
+            Value::Float(x) => opts.fmt_float(*x),
             Value::String(s) => s.borrow().clone(),
             Value::Char(c) => format!("{}", c),
             Value::Boolean(b) => format!("{}", b),
-            Value::Range(x, y) => format!("{}..{}", x, y),
-            Value::Fn(_, _) => String::from("<function>"),
+            Value::Range(Bounds::Int(x, y, 1)) => format!("{}..{}", opts.fmt_int(*x), opts.fmt_int(*y)),
+            Value::Range(Bounds::Int(x, y, step)) => format!("{}..{}..{}", opts.fmt_int(*x), opts.fmt_int(*y), opts.fmt_int(*step)),
+            Value::Range(Bounds::Float(x, y, step)) if *step == 1.0 => format!("{}..{}", opts.fmt_number(*x), opts.fmt_number(*y)),
+            Value::Range(Bounds::Float(x, y, step)) => format!("{}..{}..{}", opts.fmt_number(*x), opts.fmt_number(*y), opts.fmt_number(*step)),
+            Value::Fn(_, f, _) => match &f.2 {
+                Some(name) => format!("<function '{}'>", name),
+                None => String::from("<function>"),
+            },
             Value::List(l) => {
+                let l = l.borrow();
                 let mut s = String::from("[");
-                if let Some(i) = l.borrow().get(0) {
-                    s += &i.get_display_text()?;
+                for (i, item) in l.iter().take(opts.max_list_len.unwrap_or(l.len())).enumerate() {
+                    if i != 0 {
+                        s += ", ";
+                    }
+                    s += &item.get_display_text_nested(opts, parents)?;
                 }
-                for item in l.borrow().get(1..).unwrap_or(&[]) {
-                    s += ", ";
-                    s += &item.get_display_text()?;
+                if elided(l.len()) {
+                    s += if opts.max_list_len == Some(0) { "..." } else { ", ..." };
                 }
                 s.push(']');
                 s
             },
             Value::Map(m) => {
+                let m = m.borrow();
                 let mut s = String::from("[");
-                for (i, (key, val)) in m.borrow().iter().enumerate() {
+                for (i, (key, val)) in m.iter().take(opts.max_list_len.unwrap_or(m.len())).enumerate() {
                     if i != 0 {
                         s += ", ";
                     }
-                    s += &key.get_display_text()?;
+                    s += &key.get_display_text_nested(opts, parents)?;
                     s += ": ";
-                    s += &val.get_display_text()?;
+                    s += &val.get_display_text_nested(opts, parents)?;
+                }
+                if elided(m.len()) {
+                    s += if opts.max_list_len == Some(0) { "..." } else { ", ..." };
                 }
                 s.push(']');
                 s
             },
             Value::Custom(c) => c.get_display_text()?,
             Value::Null => String::from("<null>"),
-        })
+        };
+
+        if addr.is_some() {
+            parents.pop();
+        }
+        Ok(text)
+    }
+
+    /// Produce a short, printable form of this value for use in error messages.
+    pub fn get_preview_text(&self) -> Option<String> {
+        match self {
+            Value::String(s) => Some(format!("\"{}\"", truncate_preview(s.borrow().clone()))),
+            Value::Char(c) => Some(format!("'{}'", c)),
+            Value::Custom(c) => c.get_preview_text(),
+            val => val.get_display_text().ok().map(truncate_preview),
+        }
     }
 
     #[inline(always)]
@@ -264,31 +621,68 @@ impl Value {
         }
     }
 
+    // `and` and `or` decide on their left operand, for which only `false` and `null` count as false, so that
+    // `name or "anonymous"` works. Custom objects without a truthiness of their own count as true.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Boolean(b) => *b,
+            Value::Custom(c) => c.eval_truth(SrcRef::empty()).unwrap_or(true),
+            _ => true,
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        match self {
+            Value::Custom(c) if !self.is_function() => c.eval_member(name, r),
+            // Maps double as records, so `m.name` reads the same item as `m["name"]`. Instances of classes find the members
+            // that they lack in their class, and then the methods of all maps, such as `keys`.
+            Value::Map(m) => {
+                let key = Value::from(name.to_string());
+                if let Some(val) = m.borrow().get(&key) {
+                    return Ok(val.clone());
+                }
+                Ok(class::class_of(self)
+                    .and_then(|class| class.borrow().get(&key).cloned())
+                    .or_else(|| method::find_method(self, name))
+                    .unwrap_or(Value::Null))
+            },
+            // Any function can say what it expects, so that scripts can work with functions that they know nothing about
+            val if val.is_function() && name == "arity" => Ok(Value::from(val.arity().map(|arity| arity as i64))),
+            val if val.is_function() && name == "name" => Ok(Value::from(val.fn_name())),
+            val => method::find_method(val, name)
+                .ok_or_else(|| ExecError::At(r, Box::new(ExecError::NoSuchMember(val.get_type_name(), name.to_string())))),
+        }
+    }
+
     #[inline(always)]
     pub fn eval_index(&self, index: &Value, r: SrcRef) -> ExecResult<Value> {
         match (self, index) {
-            (Value::String(s), Value::Number(i)) => Ok(s
-                .borrow()
-                .char_indices()
-                .nth(*i as usize)
-                .map(|(_, c)| Value::Char(c))
-                .unwrap_or(Value::Null)
-            ),
-            (Value::String(s), Value::Range(a, b)) => Ok(Value::String(Rc::new(RefCell::new(s
-                .borrow()
-                .chars()
-                .skip(*a as usize)
-                .take(*b as usize - *a as usize)
-                .collect()
-            )))),
-            (Value::List(l), Value::Number(i)) => Ok(l.borrow().get(*i as usize).cloned().unwrap_or(Value::Null)),
-            (Value::List(l), Value::Range(x, y)) => Ok({
-                if let Some(slice) = l.borrow().get(*x as usize..*y as usize) {
-                    Value::List(Rc::new(RefCell::new(slice.iter().map(|v| v.clone()).collect())))
-                } else {
-                    Value::Null
-                }
-            }),
+            (Value::String(s), Value::Int(i)) => {
+                let s = s.borrow();
+                Ok(resolve_index(*i, s.chars().count())
+                    .and_then(|i| s.chars().nth(i))
+                    .map(Value::Char)
+                    .unwrap_or(Value::Null))
+            },
+            (Value::String(s), Value::Range(bounds)) if bounds.unit().is_some() => {
+                let s = s.borrow();
+                Ok(resolve_range(*bounds, s.chars().count())
+                    .map(|range| Value::from(s.chars().skip(range.start).take(range.len()).collect::<String>()))
+                    .unwrap_or(Value::Null))
+            },
+            (Value::List(l), Value::Int(i)) => {
+                let l = l.borrow();
+                Ok(resolve_index(*i, l.len()).and_then(|i| l.get(i).cloned()).unwrap_or(Value::Null))
+            },
+            (Value::List(l), Value::Range(bounds)) if bounds.unit().is_some() => {
+                let l = l.borrow();
+                Ok(resolve_range(*bounds, l.len())
+                    .and_then(|range| l.slice(range))
+                    .map(|slice| Value::new_list(slice))
+                    .unwrap_or(Value::Null))
+            },
             (Value::Map(m), index) => Ok(m.borrow().get(index).cloned().unwrap_or(Value::Null)),
             (Value::Custom(c), index) => c.eval_index(index, r),
             (this, index) => Err(ExecError::CannotIndex(r, this.get_type_name(), index.get_type_name())),
@@ -311,7 +705,8 @@ impl Value {
     #[inline(always)]
     pub fn eval_neg(&self, refs: UnaryOpRef) -> ExecResult<Value> {
         match self {
-            Value::Number(x) => Ok(Value::Number(-x)),
+            Value::Int(x) => x.checked_neg().map(Value::Int).ok_or_else(|| overflow(refs.op)),
+            Value::Float(x) => Ok(Value::Float(-x)),
             Value::Custom(c) => c.eval_neg(refs),
             _ => Err(ExecError::UnaryOp {
                 op: "not",
@@ -324,14 +719,15 @@ impl Value {
     #[inline(always)]
     pub fn eval_clone(&self, refs: UnaryOpRef) -> ExecResult<Value> {
         match self {
-            Value::Number(x) => Ok(Value::Number(*x)),
+            Value::Int(x) => Ok(Value::Int(*x)),
+            Value::Float(x) => Ok(Value::Float(*x)),
             Value::String(s) => Ok(Value::String(s.clone())),
             Value::Char(c) => Ok(Value::Char(*c)),
             Value::Boolean(b) => Ok(Value::Boolean(*b)),
-            Value::Range(x, y) => Ok(Value::Range(*x, *y)),
-            Value::Fn(s, f) => Ok(Value::Fn(s.clone(), f.clone())),
-            Value::List(l) => Ok(Value::List(Rc::new(l.as_ref().clone()))),
-            Value::Map(m) => Ok(Value::Map(Rc::new(m.as_ref().clone()))),
+            Value::Range(bounds) => Ok(Value::Range(*bounds)),
+            Value::Fn(s, f, env) => Ok(Value::Fn(s.clone(), f.clone(), env.clone())),
+            Value::List(l) => Ok(Value::new_list(l.borrow().clone())),
+            Value::Map(m) => Ok(Value::new_map(m.borrow().clone())),
             Value::Custom(c) => c.eval_clone(refs),
             Value::Null => Ok(Value::Null),
         }
@@ -340,16 +736,17 @@ impl Value {
     #[inline(always)]
     pub fn eval_mirror(&self, refs: UnaryOpRef) -> ExecResult<Value> {
         match self {
-            Value::Number(x) => Ok(Value::Number(*x)),
+            Value::Int(x) => Ok(Value::Int(*x)),
+            Value::Float(x) => Ok(Value::Float(*x)),
             Value::String(s) => Ok(Value::String(s.clone())),
             Value::Char(c) => Ok(Value::Char(*c)),
             Value::Boolean(b) => Ok(Value::Boolean(*b)),
-            Value::Range(x, y) => Ok(Value::Range(*x, *y)),
-            Value::Fn(s, f) => Ok(Value::Fn(s.clone(), f.clone())),
-            Value::List(l) => Ok(Value::List(Rc::new(RefCell::new(l.borrow().iter().map(|i| i.eval_mirror(refs)).collect::<Result<_, _>>()?)))),
-            Value::Map(m) => Ok(Value::Map(Rc::new(RefCell::new(m.borrow().iter().map(|(k, v)| {
+            Value::Range(bounds) => Ok(Value::Range(*bounds)),
+            Value::Fn(s, f, env) => Ok(Value::Fn(s.clone(), f.clone(), env.clone())),
+            Value::List(l) => Ok(Value::new_list(l.borrow().iter().map(|i| i.eval_mirror(refs)).collect::<Result<Vec<_>, _>>()?.into())),
+            Value::Map(m) => Ok(Value::new_map(m.borrow().iter().map(|(k, v)| {
                 Ok((k.eval_mirror(refs)?, v.eval_mirror(refs)?))
-            }).collect::<Result<_, _>>()?)))),
+            }).collect::<Result<_, _>>()?)),
             Value::Custom(c) => c.eval_mirror(refs),
             Value::Null => Ok(Value::Null),
         }
@@ -358,12 +755,15 @@ impl Value {
     #[inline(always)]
     pub fn eval_mul(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(*x * *y)),
+            (Value::Int(x), Value::Int(y)) => x.checked_mul(*y).map(Value::Int).ok_or_else(|| overflow(refs.op)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Float(float(x) * float(y))),
             (Value::Custom(c), rhs) => c.eval_mul(rhs, refs),
             (this, rhs) => Err(ExecError::BinaryOp {
                 op: "mul",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -372,12 +772,15 @@ impl Value {
     #[inline(always)]
     pub fn eval_div(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(*x / *y)),
+            // Dividing always produces a float, so that `1 / 2` is `0.5`
+            (x, y) if is_number(x) && is_number(y) => Ok(Value::Float(float(x) / float(y))),
             (Value::Custom(c), rhs) => c.eval_div(rhs, refs),
             (this, rhs) => Err(ExecError::BinaryOp {
                 op: "div",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -386,12 +789,19 @@ impl Value {
     #[inline(always)]
     pub fn eval_rem(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(*x % *y)),
+            (Value::Int(_), Value::Int(0)) => Err(ExecError::At(refs.right, Box::new(ExecError::Custom(String::from(
+                "Cannot take the remainder of division by zero. Use floats to get NaN instead.",
+            ))))),
+            // Only `i64::MIN % -1` overflows, and its remainder is exactly 0
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Int(x.wrapping_rem(*y))),
+            (x, y) if is_mixed(x, y) => Ok(Value::Float(float(x) % float(y))),
             (Value::Custom(c), rhs) => c.eval_rem(rhs, refs),
             (this, rhs) => Err(ExecError::BinaryOp {
                 op: "rem",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -400,21 +810,23 @@ impl Value {
     #[inline(always)]
     pub fn eval_add(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(x.clone() + y)),
+            (Value::Int(x), Value::Int(y)) => x.checked_add(*y).map(Value::Int).ok_or_else(|| overflow(refs.op)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Float(float(x) + float(y))),
             (Value::String(x), Value::String(y)) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &y.borrow())))),
             (Value::String(x), Value::Char(y)) => Ok(Value::String(Rc::new(RefCell::new(format!("{}{}", x.borrow(), y))))),
-            (Value::String(x), Value::Number(y)) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &format!("{}", y))))),
+            (Value::String(x), Value::Int(y)) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &format!("{}", y))))),
+            (Value::String(x), Value::Float(y)) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &format!("{}", y))))),
             (Value::String(x), Value::Boolean(y)) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &format!("{}", y))))),
             (Value::String(x), Value::Null) => Ok(Value::String(Rc::new(RefCell::new(x.borrow().clone() + &"null")))),
             (Value::List(x), Value::List(y)) => {
-                let mut v = x.borrow().clone();
-                v.append(&mut y.borrow().clone());
-                Ok(Value::List(Rc::new(RefCell::new(v))))
+                let mut v = x.borrow().to_vec();
+                v.extend_from_slice(&y.borrow());
+                Ok(Value::new_list(v.into()))
             },
             (Value::List(x), rhs) => {
-                let mut v = x.borrow().clone();
+                let mut v = x.borrow().to_vec();
                 v.push(rhs.clone());
-                Ok(Value::List(Rc::new(RefCell::new(v))))
+                Ok(Value::new_list(v.into()))
             },
             (Value::Map(m), Value::List(l)) => if l.borrow().len() == 2 {
                 let mut m = m.borrow().clone();
@@ -422,12 +834,14 @@ impl Value {
                     l.borrow().get(0).unwrap().clone(),
                     l.borrow().get(1).unwrap().clone(),
                 );
-                Ok(Value::Map(Rc::new(RefCell::new(m))))
+                Ok(Value::new_map(m))
             } else {
                 Err(ExecError::BinaryOp {
                     op: "insert",
                     left_type: self.get_type_name(),
                     right_type: format!("{} (length = {})", rhs.get_type_name(), l.borrow().len()),
+                    left_val: self.get_preview_text(),
+                    right_val: rhs.get_preview_text(),
                     refs,
                 })
             },
@@ -436,6 +850,8 @@ impl Value {
                 op: "add",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -444,17 +860,115 @@ impl Value {
     #[inline(always)]
     pub fn eval_sub(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Number(*x - *y)),
+            (Value::Int(x), Value::Int(y)) => x.checked_sub(*y).map(Value::Int).ok_or_else(|| overflow(refs.op)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Float(float(x) - float(y))),
             (Value::Map(m), rhs) => {
                 let mut m = m.borrow().clone();
                 let _ = m.remove(rhs);
-                Ok(Value::Map(Rc::new(RefCell::new(m))))
+                Ok(Value::new_map(m))
             },
             (Value::Custom(c), rhs) => c.eval_sub(rhs, refs),
             (this, rhs) => Err(ExecError::BinaryOp {
                 op: "sub",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
+                refs,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_bit_not(&self, refs: UnaryOpRef) -> ExecResult<Value> {
+        match self {
+            x if is_number(x) => Ok(Value::Int(!to_int(x))),
+            Value::Custom(c) => c.eval_bit_not(refs),
+            _ => Err(ExecError::UnaryOp {
+                op: "bit_not",
+                expr_type: self.get_type_name(),
+                refs,
+            })
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_shl(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match (self, rhs) {
+            (x, y) if is_number(x) && is_number(y) => shift(to_int(x), to_int(y), i64::checked_shl, refs.right).map(Value::Int),
+            (Value::Custom(c), rhs) => c.eval_shl(rhs, refs),
+            (this, rhs) => Err(ExecError::BinaryOp {
+                op: "shl",
+                left_type: this.get_type_name(),
+                right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
+                refs,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_shr(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match (self, rhs) {
+            (x, y) if is_number(x) && is_number(y) => shift(to_int(x), to_int(y), i64::checked_shr, refs.right).map(Value::Int),
+            // Functions are composed, so that `f >> g` applies `f` and then `g`
+            (f, g) if f.is_function() && g.is_function() => Ok(Value::from(method::Composed::new(f.clone(), g.clone()))),
+            (Value::Custom(c), rhs) => c.eval_shr(rhs, refs),
+            (this, rhs) => Err(ExecError::BinaryOp {
+                op: "shr",
+                left_type: this.get_type_name(),
+                right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
+                refs,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_bit_and(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match (self, rhs) {
+            (x, y) if is_number(x) && is_number(y) => Ok(Value::Int(to_int(x) & to_int(y))),
+            (Value::Custom(c), rhs) => c.eval_bit_and(rhs, refs),
+            (this, rhs) => Err(ExecError::BinaryOp {
+                op: "bit_and",
+                left_type: this.get_type_name(),
+                right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
+                refs,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_bit_xor(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match (self, rhs) {
+            (x, y) if is_number(x) && is_number(y) => Ok(Value::Int(to_int(x) ^ to_int(y))),
+            (Value::Custom(c), rhs) => c.eval_bit_xor(rhs, refs),
+            (this, rhs) => Err(ExecError::BinaryOp {
+                op: "bit_xor",
+                left_type: this.get_type_name(),
+                right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
+                refs,
+            }),
+        }
+    }
+
+    #[inline(always)]
+    pub fn eval_bit_or(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match (self, rhs) {
+            (x, y) if is_number(x) && is_number(y) => Ok(Value::Int(to_int(x) | to_int(y))),
+            (Value::Custom(c), rhs) => c.eval_bit_or(rhs, refs),
+            (this, rhs) => Err(ExecError::BinaryOp {
+                op: "bit_or",
+                left_type: this.get_type_name(),
+                right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -463,7 +977,8 @@ impl Value {
     #[inline(always)]
     pub fn eval_greater(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x > *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x > *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y) == Some(Ordering::Greater))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x > *y)),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x > *y)),
             (Value::Custom(c), rhs) => c.eval_greater(rhs, refs),
@@ -471,6 +986,8 @@ impl Value {
                 op: "greater",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -479,7 +996,8 @@ impl Value {
     #[inline(always)]
     pub fn eval_greater_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x >= *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x >= *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y).map_or(false, Ordering::is_ge))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x >= *y)),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x >= *y)),
             (Value::Custom(c), rhs) => c.eval_greater_eq(rhs, refs),
@@ -487,6 +1005,8 @@ impl Value {
                 op: "greater_eq",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -495,7 +1015,8 @@ impl Value {
     #[inline(always)]
     pub fn eval_less(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x < *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x < *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y) == Some(Ordering::Less))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x < *y)),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x < *y)),
             (Value::Custom(c), rhs) => c.eval_less(rhs, refs),
@@ -503,6 +1024,8 @@ impl Value {
                 op: "less",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -511,7 +1034,8 @@ impl Value {
     #[inline(always)]
     pub fn eval_less_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x <= *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x <= *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y).map_or(false, Ordering::is_le))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x <= *y)),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x <= *y)),
             (Value::Custom(c), rhs) => c.eval_less_eq(rhs, refs),
@@ -519,6 +1043,8 @@ impl Value {
                 op: "less_eq",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -527,18 +1053,19 @@ impl Value {
     #[inline(always)]
     pub fn eval_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x == *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x == *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y) == Some(Ordering::Equal))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x.borrow() == *y.borrow())),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x == *y)),
             (Value::Boolean(x), Value::Boolean(y)) => Ok(Value::Boolean(*x == *y)),
-            (Value::Fn(_, x), Value::Fn(_, y)) => Ok(Value::Boolean(Rc::ptr_eq(&x, &y))),
+            (Value::Fn(..), Value::Fn(..)) => Ok(Value::Boolean(self == rhs)),
             (Value::List(x), Value::List(y)) => Ok(Value::Boolean(
                 x.borrow().len() == y.borrow().len() &&
                 x.borrow().iter().zip(y.borrow().iter()).all(|(x, y)| x.eq(y))
             )),
             (Value::Map(x), Value::Map(y)) => Ok(Value::Boolean(
                 x.borrow().len() == y.borrow().len() &&
-                x.borrow().iter().zip(y.borrow().iter()).all(|((xk, xv), (yk, yv))| xk.eq(yk) && yv.eq(yv))
+                x.borrow().iter().all(|(k, xv)| y.borrow().get(k).map_or(false, |yv| xv.eq(yv)))
             )),
             (Value::Custom(c), rhs) => c.eval_eq(rhs, refs),
             (Value::Null, Value::Null) => Ok(Value::Boolean(true)),
@@ -549,18 +1076,19 @@ impl Value {
     #[inline(always)]
     pub fn eval_not_eq(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Boolean(*x != *y)),
+            (Value::Int(x), Value::Int(y)) => Ok(Value::Boolean(*x != *y)),
+            (x, y) if is_mixed(x, y) => Ok(Value::Boolean(num_cmp(x, y) != Some(Ordering::Equal))),
             (Value::String(x), Value::String(y)) => Ok(Value::Boolean(*x != *y)),
             (Value::Char(x), Value::Char(y)) => Ok(Value::Boolean(*x != *y)),
             (Value::Boolean(x), Value::Boolean(y)) => Ok(Value::Boolean(*x != *y)),
-            (Value::Fn(_, x), Value::Fn(_, y)) => Ok(Value::Boolean(!Rc::ptr_eq(&x, &y))),
+            (Value::Fn(..), Value::Fn(..)) => Ok(Value::Boolean(self != rhs)),
             (Value::List(x), Value::List(y)) => Ok(Value::Boolean(
                 x.borrow().len() != y.borrow().len() ||
                 !x.borrow().iter().zip(y.borrow().iter()).all(|(x, y)| x.eq(y))
             )),
             (Value::Map(x), Value::Map(y)) => Ok(Value::Boolean(
                 x.borrow().len() != y.borrow().len() ||
-                !x.borrow().iter().zip(y.borrow().iter()).all(|((xk, xv), (yk, yv))| xk.eq(yk) && yv.eq(yv))
+                !x.borrow().iter().all(|(k, xv)| y.borrow().get(k).map_or(false, |yv| xv.eq(yv)))
             )),
             (Value::Custom(c), rhs) => c.eval_not_eq(rhs, refs),
             (Value::Null, Value::Null) => Ok(Value::Boolean(false)),
@@ -577,6 +1105,8 @@ impl Value {
                 op: "and",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -591,6 +1121,8 @@ impl Value {
                 op: "or",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -605,20 +1137,44 @@ impl Value {
                 op: "xor",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
     }
 
+    /// Whether this is an instance of the class on the right, as in `p is Point`. Values that are not instances of any
+    /// class are instances of none.
+    pub fn eval_is(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
+        match rhs {
+            Value::Map(class) => Ok(Value::Boolean(class::class_of(self).map(|c| Rc::ptr_eq(&c, class)).unwrap_or(false))),
+            rhs => Err(ExecError::At(refs.right, Box::new(ExecError::Custom(format!(
+                "The right side of 'is' must be a class, not a value of type '{}'.",
+                rhs.get_type_name(),
+            ))))),
+        }
+    }
+
     #[inline(always)]
     pub fn eval_range(&self, rhs: &Value, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, rhs) {
-            (Value::Number(x), Value::Number(y)) => Ok(Value::Range(*x, *y)),
+            (x, y) if is_number(x) && is_number(y) => Ok(Value::Range(Bounds::new(x, y).unwrap())),
+            // `0..10..2` steps by 2
+            (Value::Range(bounds), step) if is_number(step) && float(step) > 0.0 && float(step).is_finite() => {
+                let [x, y, _] = bounds.parts();
+                Ok(Value::Range(Bounds::with_step(&x, &y, step).unwrap()))
+            },
+            (Value::Range(_), step) if is_number(step) => Err(ExecError::At(refs.right, Box::new(ExecError::Custom(String::from(
+                "The step of a range must be a positive number. Ranges whose end is below their start run downwards.",
+            ))))),
             (Value::Custom(c), rhs) => c.eval_range(rhs, refs),
             (this, rhs) => Err(ExecError::BinaryOp {
                 op: "range",
                 left_type: this.get_type_name(),
                 right_type: rhs.get_type_name(),
+                left_val: this.get_preview_text(),
+                right_val: rhs.get_preview_text(),
                 refs,
             }),
         }
@@ -627,15 +1183,26 @@ impl Value {
     #[inline(always)]
     pub fn eval_as(&self, ty: &Type, refs: BinaryOpRef) -> ExecResult<Value> {
         match (self, ty) {
-            (Value::Number(x), Type::Char) => Ok(Value::Char(*x as u64 as u8 as char)),
-            (Value::Number(s), Type::String) => Ok(Value::String(Rc::new(RefCell::new(format!("{}", s))))),
-            (Value::Char(c), Type::Number) => Ok(Value::Number(*c as u8 as f64)),
+            (Value::Int(x), Type::Int) => Ok(Value::Int(*x)),
+            (Value::Int(x), Type::Float) => Ok(Value::Float(*x as f64)),
+            (Value::Float(x), Type::Float) => Ok(Value::Float(*x)),
+            // Floats are truncated towards zero, and those that no int can hold cannot be converted
+            (Value::Float(x), Type::Int) if x.is_finite() && x.trunc() >= -9.223372036854776e18 && x.trunc() < 9.223372036854776e18 => Ok(Value::Int(x.trunc() as i64)),
+            (Value::Float(x), Type::Int) => Err(ExecError::Custom(format!("The float {} is too large to convert into an int.", x))),
+            (Value::Int(x), Type::Char) => Ok(Value::Char(*x as u64 as u8 as char)),
+            (Value::Float(x), Type::Char) => Ok(Value::Char(*x as u64 as u8 as char)),
+            (Value::Int(x), Type::String) => Ok(Value::String(Rc::new(RefCell::new(format!("{}", x))))),
+            (Value::Float(x), Type::String) => Ok(Value::String(Rc::new(RefCell::new(format!("{}", x))))),
+            (Value::Char(c), Type::Int) => Ok(Value::Int(*c as u8 as i64)),
+            (Value::Char(c), Type::Float) => Ok(Value::Float(*c as u8 as f64)),
             (Value::Char(c), Type::String) => Ok(Value::String(Rc::new(RefCell::new(format!("{}", c))))),
             (Value::Boolean(b), Type::String) => Ok(Value::String(Rc::new(RefCell::new(format!("{}", b))))),
             _ => Err(ExecError::BinaryOp {
                 op: "as",
                 left_type: self.get_type_name(),
                 right_type: ty.get_name(),
+                left_val: self.get_preview_text(),
+                right_val: None,
                 refs,
             }),
         }.map_err(|err| ExecError::At(refs.op, Box::new(err)))
@@ -644,60 +1211,69 @@ impl Value {
     #[inline(always)]
     pub fn eval_iter(&self, r: SrcRef) -> ExecResult<Box<ForgeIter>> {
         match self {
-            Value::Range(x, y) => Ok(Box::new((*x as i64..*y as i64).map(|v| Value::Number(v as f64)))),
+            Value::Range(bounds) => Ok(Box::new(bounds.iter())),
             Value::String(s) => Ok(Box::new(s.borrow().chars().collect::<Vec<_>>().into_iter().map(|c| Value::Char(c)))),
-            Value::List(l) => Ok(Box::new(l.borrow().clone().into_iter())),
+            Value::List(l) => Ok(Box::new(l.borrow().iter_owned())),
             Value::Custom(c) => c.eval_iter(r),
             _ => Err(ExecError::At(r, Box::new(ExecError::NotIterable(self.get_type_name())))),
         }
     }
 
+    /// Iterate over the keys and values of a map, or the items of any other iterable value along with their indices.
+    pub fn eval_iter_pairs(&self, r: SrcRef) -> ExecResult<Box<dyn PairIter>> {
+        match self {
+            // The entries are copied so that the map can be changed while it is iterated over
+            Value::Map(m) => Ok(Box::new(m.borrow().iter().map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>().into_iter())),
+            _ => Ok(Box::new(self.eval_iter(r)?.enumerate().map(|(i, item)| (Value::Int(i as i64), item)))),
+        }
+    }
+
     #[inline(always)]
     pub fn assign_index(&mut self, index: &Value, rhs: Value, r_idx: SrcRef, r_rhs: SrcRef) -> ExecResult<()> {
-        let byte_indices = |s: &str, (a, b)| Ok((
+        let invalid = || ExecError::At(r_idx, Box::new(ExecError::InvalidIndex(self.get_type_name(), index.clone())));
+        let byte_indices = |s: &str, range: Range<usize>| Ok((
             s
                 .char_indices()
-                .nth(a)
-                .ok_or_else(|| ExecError::At(r_idx, Box::new(ExecError::InvalidIndex(self.get_type_name(), index.clone()))))?.0,
+                .nth(range.start)
+                .ok_or_else(invalid)?.0,
             s
                 .char_indices()
                 .chain(Some((s.len(), '_')))
-                .nth(b as usize)
-                .ok_or_else(|| ExecError::At(r_idx, Box::new(ExecError::InvalidIndex(self.get_type_name(), index.clone()))))?.0,
+                .nth(range.end)
+                .ok_or_else(invalid)?.0,
         ));
         match (&self, index, &rhs) {
-            (Value::String(s), Value::Number(i), Value::Char(new_c)) => {
+            (Value::String(s), Value::Int(i), Value::Char(new_c)) => {
                 let mut s = s.borrow_mut();
-                let byte_idxs = byte_indices(&s, (*i as usize, *i as usize + 1))?;
+                let i = resolve_index(*i, s.chars().count()).ok_or_else(invalid)?;
+                let byte_idxs = byte_indices(&s, i..i + 1)?;
                 Ok(s.replace_range(byte_idxs.0..byte_idxs.1, &new_c.to_string()))
             },
-            (Value::String(_), Value::Number(_), rhs) => Err(ExecError::CannotIndexAssign(r_rhs, self.get_type_name(), rhs.get_type_name())),
-            (Value::String(s), Value::Range(a, b), Value::String(new_s)) => {
+            (Value::String(_), Value::Int(_), rhs) => Err(ExecError::CannotIndexAssign(r_rhs, self.get_type_name(), rhs.get_type_name())),
+            (Value::String(s), Value::Range(bounds), Value::String(new_s)) if bounds.unit().is_some() => {
                 let mut s = s.borrow_mut();
-                let byte_idxs = byte_indices(&s, (*a as usize, *b as usize))?;
+                let range = resolve_range(*bounds, s.chars().count()).ok_or_else(invalid)?;
+                let byte_idxs = byte_indices(&s, range)?;
                 Ok(s.replace_range(byte_idxs.0..byte_idxs.1, &new_s.borrow()))
             },
-            (Value::String(_), Value::Range(_, _), rhs) => Err(ExecError::CannotIndexAssign(r_rhs, self.get_type_name(), rhs.get_type_name())),
-            (Value::List(l), Value::Number(i), _) => {
+            (Value::String(_), Value::Range(_), rhs) => Err(ExecError::CannotIndexAssign(r_rhs, self.get_type_name(), rhs.get_type_name())),
+            (Value::List(l), Value::Int(i), _) => {
+                let i = resolve_index(*i, l.borrow().len()).ok_or_else(invalid)?;
                 l
                     .borrow_mut()
-                    .get_mut(*i as usize)
-                    .map(|v| *v = rhs)
-                    .ok_or_else(|| ExecError::At(r_idx, Box::new(ExecError::InvalidIndex(self.get_type_name(), index.clone()))))
+                    .set(i, rhs)
+                    .ok_or_else(invalid)
             },
-            (Value::List(l), Value::Range(a, b), Value::List(extra_l)) => {
-                let extra_list = extra_l.borrow().clone();
-                if *a as usize >= 0 && *b as usize <= l.borrow().len() {
-                    let new_list = Value::List(Rc::new(RefCell::new(l
-                        .borrow_mut()
-                        .splice(*a as usize..*b as usize, extra_list)
-                        .collect()
-                    )));
-                    *self = new_list;
-                    Ok(())
-                } else {
-                    Err(ExecError::At(r_idx, Box::new(ExecError::InvalidIndex(self.get_type_name(), index.clone()))))
-                }
+            (Value::List(l), Value::Range(bounds), Value::List(extra_l)) if bounds.unit().is_some() => {
+                let extra_list = extra_l.borrow().to_vec();
+                let range = resolve_range(*bounds, l.borrow().len()).ok_or_else(invalid)?;
+                let new_list = Value::new_list(l
+                    .borrow_mut()
+                    .splice(range, extra_list)
+                    .into()
+                );
+                *self = new_list;
+                Ok(())
             },
             (Value::Map(m), index, rhs) => {
                 m.borrow_mut().insert(index.clone(), rhs.clone());
@@ -706,12 +1282,118 @@ impl Value {
             (this, index, _) => Err(ExecError::CannotIndex(r_idx, this.get_type_name(), index.get_type_name())),
         }
     }
+
+    pub fn assign_member(&self, name: &str, rhs: Value, r: SrcRef) -> ExecResult<()> {
+        match self {
+            Value::Map(m) => {
+                m.borrow_mut().insert(Value::from(name.to_string()), rhs);
+                Ok(())
+            },
+            Value::Custom(c) => c.assign_member(name, rhs, r),
+            val => Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(val.get_type_name(), name.to_string())))),
+        }
+    }
+}
+
+// Negative indices count back from the end, so `-1` is the last item
+pub(super) fn resolve_index(i: i64, len: usize) -> Option<usize> {
+    let i = if i < 0 { (len as i64).checked_add(i)? } else { i };
+    if i >= 0 {
+        Some(i as usize)
+    } else {
+        None
+    }
+}
+
+fn is_number(val: &Value) -> bool {
+    match val {
+        Value::Int(_) | Value::Float(_) => true,
+        _ => false,
+    }
+}
+
+// Arithmetic on an int and a float promotes the int to a float
+fn is_mixed(x: &Value, y: &Value) -> bool {
+    match (x, y) {
+        (Value::Int(_), Value::Int(_)) => false,
+        (x, y) => is_number(x) && is_number(y),
+    }
+}
+
+fn float(val: &Value) -> f64 {
+    val.as_number().unwrap_or(std::f64::NAN)
+}
+
+// Compares numbers exactly, even ints too large to be converted to floats without rounding, so that comparisons agree
+// with how map keys are compared. NaN compares with nothing.
+fn num_cmp(x: &Value, y: &Value) -> Option<Ordering> {
+    match (x, y) {
+        (Value::Int(x), Value::Float(y)) => int_float_cmp(*x, *y),
+        (Value::Float(x), Value::Int(y)) => int_float_cmp(*y, *x).map(Ordering::reverse),
+        (x, y) => float(x).partial_cmp(&float(y)),
+    }
+}
+
+fn int_float_cmp(x: i64, y: f64) -> Option<Ordering> {
+    if y.is_nan() {
+        None
+    } else if y >= 9.223372036854776e18 {
+        Some(Ordering::Less)
+    } else if y < -9.223372036854776e18 {
+        Some(Ordering::Greater)
+    } else {
+        // The integral parts are compared exactly, and the fractional part breaks ties
+        let whole = y.trunc();
+        Some(x.cmp(&(whole as i64)).then_with(|| 0.0.partial_cmp(&(y - whole)).unwrap_or(Ordering::Equal)))
+    }
+}
+
+// The int equal to a float, if there is one
+fn exact_int(x: f64) -> Option<i64> {
+    if x.fract() == 0.0 && x >= -9.223372036854776e18 && x < 9.223372036854776e18 {
+        Some(x as i64)
+    } else {
+        None
+    }
+}
+
+pub(super) fn overflow(r: SrcRef) -> ExecError {
+    ExecError::At(r, Box::new(ExecError::Custom(String::from(
+        "The result is too large for an int. Use floats for numbers this large.",
+    ))))
+}
+
+// Bitwise operators work on ints. Floats are truncated to their integral part first, and clamped to the range of an int.
+fn to_int(val: &Value) -> i64 {
+    match val {
+        Value::Int(x) => *x,
+        val => float(val).trunc() as i64,
+    }
+}
+
+// Ints may be shifted by 0 to 63 places. Shifting by more or by a negative amount is an error, like other overflows.
+fn shift(x: i64, by: i64, f: fn(i64, u32) -> Option<i64>, r: SrcRef) -> ExecResult<i64> {
+    u32::try_from(by).ok().and_then(|by| f(x, by)).ok_or_else(|| ExecError::At(r, Box::new(ExecError::Custom(format!(
+        "Cannot shift an int by {} places. Shifts must be from 0 to 63 places.", by,
+    )))))
+}
+
+// The items that a range selects, or `None` if it does not fit within `len` items
+fn resolve_range(bounds: Bounds, len: usize) -> Option<Range<usize>> {
+    let (a, b) = bounds.unit()?;
+    let (a, b) = (resolve_index(a, len)?, resolve_index(b, len)?);
+    if a <= b && b <= len {
+        Some(a..b)
+    } else {
+        None
+    }
 }
 
 impl PartialEq<f64> for Value {
     fn eq(&self, other: &f64) -> bool {
         match self {
-            Value::Number(x) => x.eq(other),
+            Value::Int(x) => (*x as f64).eq(other),
+            Value::Float(x) => x.eq(other),
             _ => false,
         }
     }
@@ -720,7 +1402,8 @@ impl PartialEq<f64> for Value {
 impl PartialEq<i64> for Value {
     fn eq(&self, other: &i64) -> bool {
         match self {
-            Value::Number(x) => x.eq(&(*other as f64)),
+            Value::Int(x) => x.eq(other),
+            Value::Float(x) => x.eq(&(*other as f64)),
             _ => false,
         }
     }
@@ -764,13 +1447,13 @@ impl From<()> for Value {
 
 impl From<f64> for Value {
     fn from(other: f64) -> Self {
-        Value::Number(other)
+        Value::Float(other)
     }
 }
 
 impl From<i64> for Value {
     fn from(other: i64) -> Self {
-        Value::Number(other as f64)
+        Value::Int(other)
     }
 }
 
@@ -794,18 +1477,120 @@ impl From<String> for Value {
 
 impl From<Range<i64>> for Value {
     fn from(other: Range<i64>) -> Self {
-        Value::Range(other.start as f64, other.end as f64)
+        Value::Range(Bounds::Int(other.start, other.end, 1))
     }
 }
 
 impl<T: Into<Value>> From<Vec<T>> for Value {
     fn from(other: Vec<T>) -> Self {
-        Value::List(Rc::new(RefCell::new(other.into_iter().map(|i| i.into()).collect())))
+        Value::new_list(other.into_iter().map(|i| i.into()).collect())
     }
 }
 
 impl<K: Into<Value> + Eq + Hash, V: Into<Value>> From<StdHashMap<K, V>> for Value {
     fn from(other: StdHashMap<K, V>) -> Self {
-        Value::Map(Rc::new(RefCell::new(other.into_iter().map(|(k, v)| (k.into(), v.into())).collect())))
+        Value::new_map(other.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+impl From<i32> for Value {
+    fn from(other: i32) -> Self {
+        Value::Int(other as i64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(other: usize) -> Self {
+        Value::Int(other as i64)
+    }
+}
+
+impl From<char> for Value {
+    fn from(other: char) -> Self {
+        Value::Char(other)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(other: Option<T>) -> Self {
+        other.map(|x| x.into()).unwrap_or(Value::Null)
+    }
+}
+
+impl From<Map> for Value {
+    fn from(map: Map) -> Self {
+        Value::new_map(map)
+    }
+}
+
+// Hash maps have no order, so the maps made from them iterate in an arbitrary one
+impl<K: Into<Value> + Eq + Hash, V: Into<Value>> From<HashMap<K, V>> for Value {
+    fn from(other: HashMap<K, V>) -> Self {
+        Value::new_map(other.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
+    }
+}
+
+fn cannot_convert(val: &Value, to: &'static str) -> ExecError {
+    ExecError::CannotConvert(val.get_type_name(), to)
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_number().ok_or_else(|| cannot_convert(&val, "f64"))
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_int().ok_or_else(|| cannot_convert(&val, "i64"))
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_bool().ok_or_else(|| cannot_convert(&val, "bool"))
+    }
+}
+
+impl TryFrom<Value> for char {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_char().ok_or_else(|| cannot_convert(&val, "char"))
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_string().ok_or_else(|| cannot_convert(&val, "String"))
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_list().ok_or_else(|| cannot_convert(&val, "Vec<Value>"))
+    }
+}
+
+// Only maps with string keys may be converted, since any other key would be lost
+impl TryFrom<Value> for StdHashMap<String, Value> {
+    type Error = ExecError;
+
+    fn try_from(val: Value) -> ExecResult<Self> {
+        val.as_map()
+            .ok_or_else(|| cannot_convert(&val, "HashMap<String, Value>"))?
+            .into_iter()
+            .map(|(k, v)| Ok((String::try_from(k)?, v)))
+            .collect()
     }
 }
@@ -0,0 +1,93 @@
+use std::{
+    rc::{Rc, Weak},
+    cell::RefCell,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    List,
+    Map,
+    Obj,
+    Value,
+    method::{self, Method, MethodFn},
+};
+
+#[derive(Clone)]
+enum Target {
+    String(Weak<RefCell<String>>),
+    List(Weak<RefCell<List>>),
+    Map(Weak<RefCell<Map>>),
+    Custom(Weak<dyn Obj>),
+}
+
+/// A reference to a value that does not keep it alive, created with `weak(value)`.
+///
+/// `get()` produces the value, or null once nothing else refers to it and it has been freed. Caches and observers can
+/// hold weak references to the values they track without keeping them alive or forming reference cycles with them.
+/// Only values that are shared by reference (strings, lists, maps and objects) can be referenced weakly.
+#[derive(Clone)]
+pub struct WeakRef {
+    type_name: String,
+    target: Target,
+}
+
+impl WeakRef {
+    pub fn new(val: &Value) -> Option<Self> {
+        let target = match val {
+            Value::String(s) => Target::String(Rc::downgrade(s)),
+            Value::List(l) => Target::List(Rc::downgrade(l)),
+            Value::Map(m) => Target::Map(Rc::downgrade(m)),
+            Value::Custom(c) => Target::Custom(Rc::downgrade(c)),
+            _ => return None,
+        };
+        Some(Self { type_name: val.get_type_name(), target })
+    }
+
+    /// The value, if it has not been freed.
+    pub fn get(&self) -> Option<Value> {
+        match &self.target {
+            Target::String(s) => s.upgrade().map(Value::String),
+            Target::List(l) => l.upgrade().map(Value::List),
+            Target::Map(m) => m.upgrade().map(Value::Map),
+            Target::Custom(c) => c.upgrade().map(Value::Custom),
+        }
+    }
+}
+
+impl Obj for WeakRef {
+    fn get_type_name(&self) -> String {
+        String::from("weak")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        match self.get() {
+            Some(_) => Ok(format!("<weak {}>", self.type_name)),
+            None => Ok(format!("<weak {}, freed>", self.type_name)),
+        }
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<WeakRef>) = match name {
+            "get" => ("get", weak_get),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+}
+
+pub(super) fn builtin_weak(args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    WeakRef::new(&args[0])
+        .map(Value::from)
+        .ok_or_else(|| ExecError::At(r_args, Box::new(ExecError::Custom(format!(
+            "Only strings, lists, maps and objects can be referenced weakly, not values of type '{}'.",
+            args[0].get_type_name(),
+        )))))
+}
+
+fn weak_get(weak: &WeakRef, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    Ok(weak.get().unwrap_or(Value::Null))
+}
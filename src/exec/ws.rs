@@ -0,0 +1,386 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time,
+};
+use crate::parser::SrcRef;
+use super::{
+    ExecCtx,
+    ExecError,
+    ExecResult,
+    ForgeIter,
+    Obj,
+    Resource,
+    Rng,
+    Value,
+    builtins::{BuiltinFn, number_arg, string_arg},
+    method::{self, Method, MethodFn},
+    url::Parts,
+};
+
+pub(super) const WS: &[(&str, BuiltinFn)] = &[
+    ("connect", ws_connect),
+];
+
+// Appended to the client's key to produce the key that the server must answer with (RFC 6455, section 1.3)
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_BINARY: u8 = 0x2;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xa;
+
+struct Conn {
+    reader: BufReader<TcpStream>,
+    writer: TcpStream,
+    // Frames sent by clients are masked with unpredictable keys
+    rng: Rng,
+    closed: bool,
+}
+
+impl Conn {
+    fn close(&mut self) {
+        if !self.closed {
+            // 1000 is a normal closure
+            self.write_frame(OP_CLOSE, &1000u16.to_be_bytes()).ok();
+            self.closed = true;
+            self.writer.shutdown(std::net::Shutdown::Both).ok();
+        }
+    }
+
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xffff => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            },
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            },
+        }
+        let mut mask = [0; 4];
+        self.rng.fill_bytes(&mut mask);
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        self.writer.write_all(&frame)
+    }
+
+    // Returns whether this is the final frame of its message, the opcode and the payload
+    fn read_frame(&mut self) -> io::Result<(bool, u8, Vec<u8>)> {
+        let mut head = [0; 2];
+        self.reader.read_exact(&mut head)?;
+        let len = match head[1] & 0x7f {
+            126 => {
+                let mut len = [0; 2];
+                self.reader.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            },
+            127 => {
+                let mut len = [0; 8];
+                self.reader.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            },
+            len => len as u64,
+        };
+        let mut mask = [0; 4];
+        if head[1] & 0x80 != 0 {
+            self.reader.read_exact(&mut mask)?;
+        }
+        let mut payload = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+        Ok((head[0] & 0x80 != 0, head[0] & 0x0f, payload))
+    }
+
+    /// Wait for the next text or binary message, answering pings along the way. Returns `None` if the connection is
+    /// closed, or if no message starts arriving within the timeout.
+    fn recv(&mut self, timeout: Option<time::Duration>) -> io::Result<Option<Value>> {
+        if self.closed {
+            return Ok(None);
+        }
+        self.reader.get_ref().set_read_timeout(timeout)?;
+        let waited = self.reader.fill_buf().map(|_| ());
+        self.reader.get_ref().set_read_timeout(None)?;
+        match waited {
+            Err(ref err) if err.kind() == io::ErrorKind::WouldBlock || err.kind() == io::ErrorKind::TimedOut => return Ok(None),
+            res => res?,
+        }
+
+        let mut message: Option<(u8, Vec<u8>)> = None;
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                OP_PING => self.write_frame(OP_PONG, &payload)?,
+                OP_PONG => {},
+                OP_CLOSE => {
+                    // The closing handshake is completed by echoing the status code
+                    self.write_frame(OP_CLOSE, &payload[..payload.len().min(2)]).ok();
+                    self.closed = true;
+                    return Ok(None);
+                },
+                OP_TEXT | OP_BINARY | OP_CONTINUATION => {
+                    match (&mut message, opcode) {
+                        (None, OP_CONTINUATION) | (Some(_), OP_TEXT) | (Some(_), OP_BINARY) =>
+                            return Err(io::Error::new(io::ErrorKind::InvalidData, "the server sent fragments out of order")),
+                        (Some((_, data)), _) => data.extend_from_slice(&payload),
+                        (None, _) => message = Some((opcode, payload)),
+                    }
+                    if fin {
+                        let (opcode, data) = message.take().unwrap();
+                        return Ok(Some(if opcode == OP_TEXT {
+                            Value::from(String::from_utf8(data)
+                                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "the server sent text that is not valid UTF-8"))?)
+                        } else {
+                            Value::from(data.into_iter().map(|b| Value::Int(b as i64)).collect::<Vec<_>>())
+                        }));
+                    }
+                },
+                _ => return Err(io::Error::new(io::ErrorKind::InvalidData, "the server sent an unknown frame")),
+            }
+        }
+    }
+}
+
+// The server is told that the connection is closing, rather than finding it cut off
+impl Drop for Conn {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// A WebSocket connection, created with `ws.connect(url)`.
+///
+/// Messages are sent with `send(msg)`, where strings are sent as text and lists of bytes as binary data, and received
+/// with `recv()` or by iterating over the connection with a `for` loop. `recv(secs)` gives up and returns null if no
+/// message arrives in time. Receiving returns null once the server closes the connection, and `close()` closes it from
+/// this end, as does the end of a `with` statement or the last reference to it going away.
+#[derive(Clone)]
+pub struct WebSocket {
+    url: String,
+    conn: Rc<RefCell<Conn>>,
+}
+
+impl WebSocket {
+    fn closed_err(&self) -> ExecError {
+        ExecError::Custom(format!("The connection to '{}' has been closed.", self.url))
+    }
+}
+
+impl Obj for WebSocket {
+    fn get_type_name(&self) -> String {
+        String::from("websocket")
+    }
+
+    fn get_display_text(&self) -> ExecResult<String> {
+        Ok(format!("<websocket '{}'>", self.url))
+    }
+
+    fn eval_member(&self, name: &str, r: SrcRef) -> ExecResult<Value> {
+        let (name, f): (_, MethodFn<WebSocket>) = match name {
+            "send" => ("send", ws_send),
+            "recv" => ("recv", ws_recv),
+            "close" => ("close", ws_close),
+            _ => return Err(ExecError::At(r, Box::new(ExecError::NoSuchMember(self.get_type_name(), name.to_string())))),
+        };
+        Ok(Value::from(Method::new(name, self.clone(), f)))
+    }
+
+    // Iteration yields messages until the connection is closed, and stops early if it fails
+    fn eval_iter(&self, _r: SrcRef) -> ExecResult<Box<dyn ForgeIter>> {
+        let conn = self.conn.clone();
+        Ok(Box::new(std::iter::from_fn(move || conn.borrow_mut().recv(None).ok().and_then(|msg| msg))))
+    }
+
+    fn as_resource(&self) -> Option<&dyn Resource> {
+        Some(self)
+    }
+}
+
+impl Resource for WebSocket {
+    fn close(&self) -> ExecResult<()> {
+        self.conn.borrow_mut().close();
+        Ok(())
+    }
+
+    fn is_closed(&self) -> bool {
+        self.conn.borrow().closed
+    }
+}
+
+// `ws.connect(url, headers)` opens a connection to a `ws://` URL, sending any extra headers with the handshake
+fn ws_connect(args: Vec<Value>, ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 2, r_args)?;
+    ctx.permissions.require_net().map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    let url = string_arg(&args[0], r_args)?;
+    let headers = match args.get(1) {
+        None | Some(Value::Null) => Vec::new(),
+        Some(Value::Map(m)) => m
+            .borrow()
+            .iter()
+            .map(|(k, v)| Ok(format!("{}: {}\r\n", k.get_display_text()?, v.get_display_text()?)))
+            .collect::<ExecResult<Vec<_>>>()
+            .map_err(|err| ExecError::At(r_args, Box::new(err)))?,
+        Some(val) => return Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("map"), val.get_type_name())))),
+    };
+    let conn = connect(&url, &headers, Rng::from_seed(ctx.rng().next_u64()))
+        .map_err(|err| ExecError::At(r_args, Box::new(err)))?;
+    Ok(ctx.open_resource(WebSocket { url, conn: Rc::new(RefCell::new(conn)) }))
+}
+
+fn connect(url: &str, headers: &[String], mut rng: Rng) -> ExecResult<Conn> {
+    let parts = Parts::split(url);
+    let authority = match (parts.scheme.map(|s| s.to_lowercase()).as_ref().map(|s| s.as_str()), parts.authority) {
+        (Some("ws"), Some(authority)) if !authority.is_empty() => authority,
+        (Some("wss"), _) => return Err(ExecError::Custom(String::from("Secure WebSocket connections ('wss://') are not yet supported."))),
+        _ => return Err(ExecError::Custom(format!("'{}' is not a WebSocket URL. Expected one like 'ws://host/path'.", url))),
+    };
+    let host = &authority[authority.rfind('@').map(|i| i + 1).unwrap_or(0)..];
+    let addr = if host.rfind(':').filter(|i| !host[*i..].contains(']')).is_some() {
+        host.to_string()
+    } else {
+        format!("{}:80", host)
+    };
+    let resource = match parts.query {
+        Some(query) => format!("{}?{}", if parts.path.is_empty() { "/" } else { parts.path }, query),
+        None => String::from(if parts.path.is_empty() { "/" } else { parts.path }),
+    };
+
+    let mut nonce = [0; 16];
+    rng.fill_bytes(&mut nonce);
+    let key = base64(&nonce);
+    let stream = TcpStream::connect(&addr).map_err(ExecError::Io)?;
+    let mut writer = stream.try_clone().map_err(ExecError::Io)?;
+    write!(
+        writer,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n{}\r\n",
+        resource, host, key, headers.concat(),
+    ).map_err(ExecError::Io)?;
+
+    let mut reader = BufReader::new(stream);
+    let mut status = String::new();
+    reader.read_line(&mut status).map_err(ExecError::Io)?;
+    let mut accept = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).map_err(ExecError::Io)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some(i) = line.find(':') {
+            if line[..i].trim().eq_ignore_ascii_case("sec-websocket-accept") {
+                accept = Some(line[i + 1..].trim().to_string());
+            }
+        }
+    }
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(ExecError::Custom(format!("The server at '{}' refused the WebSocket connection: {}", url, status.trim())));
+    }
+    if accept.as_ref().map(|s| s.as_str()) != Some(base64(&sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes())).as_str()) {
+        return Err(ExecError::Custom(format!("The server at '{}' answered the WebSocket handshake incorrectly.", url)));
+    }
+
+    Ok(Conn { reader, writer, rng, closed: false })
+}
+
+fn ws_send(ws: &WebSocket, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 1, 1, r_args)?;
+    let (opcode, payload) = match &args[0] {
+        Value::List(l) => (OP_BINARY, l
+            .borrow()
+            .iter()
+            .map(|b| match b {
+                Value::Int(x) if *x >= 0 && *x <= 255 => Ok(*x as u8),
+                _ => Err(ExecError::At(r_args, Box::new(ExecError::WrongArgType(String::from("list of bytes"), b.get_type_name())))),
+            })
+            .collect::<ExecResult<Vec<_>>>()?),
+        msg => (OP_TEXT, msg.get_display_text().map_err(|err| ExecError::At(r_args, Box::new(err)))?.into_bytes()),
+    };
+    let mut conn = ws.conn.borrow_mut();
+    if conn.closed {
+        return Err(ExecError::At(r_args, Box::new(ws.closed_err())));
+    }
+    conn.write_frame(opcode, &payload).map_err(|err| ExecError::At(r_args, Box::new(ExecError::Io(err))))?;
+    Ok(Value::Null)
+}
+
+fn ws_recv(ws: &WebSocket, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 1, r_args)?;
+    let timeout = match args.get(0) {
+        Some(secs) => Some(time::Duration::from_secs_f64(number_arg(secs, r_args)?.max(0.001))),
+        None => None,
+    };
+    let msg = ws.conn.borrow_mut().recv(timeout).map_err(|err| ExecError::At(r_args, Box::new(ExecError::Io(err))))?;
+    Ok(msg.unwrap_or(Value::Null))
+}
+
+// Closing a connection more than once does nothing
+fn ws_close(ws: &WebSocket, args: Vec<Value>, _ctx: &mut ExecCtx, _src: &Rc<String>, _r_caller: SrcRef, r_args: SrcRef) -> ExecResult<Value> {
+    method::check_arg_num(&args, 0, 0, r_args)?;
+    ws.close()?;
+    Ok(Value::Null)
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, b)| n | (*b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            s.push(if i <= chunk.len() { ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char } else { '=' });
+        }
+    }
+    s
+}
+
+// Only used for the handshake, which the protocol defines in terms of SHA-1
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x6745_2301, 0xefcd_ab89, 0x98ba_dcfe, 0x1032_5476, 0xc3d2_e1f0];
+    let mut msg = data.to_vec();
+    msg.push(0x80);
+    while msg.len() % 64 != 56 {
+        msg.push(0);
+    }
+    msg.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+    for block in msg.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..80 {
+            w[i] = if i < 16 {
+                u32::from_be_bytes([block[i * 4], block[i * 4 + 1], block[i * 4 + 2], block[i * 4 + 3]])
+            } else {
+                (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1)
+            };
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = a.rotate_left(5).wrapping_add(f).wrapping_add(e).wrapping_add(k).wrapping_add(*w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+        for (h, x) in h.iter_mut().zip(&[a, b, c, d, e]) {
+            *h = h.wrapping_add(*x);
+        }
+    }
+    let mut out = [0; 20];
+    for (i, h) in h.iter().enumerate() {
+        out[i * 4..i * 4 + 4].copy_from_slice(&h.to_be_bytes());
+    }
+    out
+}
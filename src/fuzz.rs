@@ -0,0 +1,449 @@
+//! Entry points for fuzzing the lexer, parser and both execution modes, for use by cargo-fuzz targets (see `fuzz/`).
+//!
+//! Raw bytes mostly exercise the lexer and parser, since few random inputs parse. `Program::from_bytes` instead uses
+//! the bytes to choose between the kinds of statement and expression that the parser produces, so that every input is
+//! a program that parses and runs, reaching deep into the evaluator.
+
+use std::{
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Duration,
+};
+use crate::{
+    Engine,
+    ExecMode,
+    ExecResult,
+    Io,
+    parser,
+};
+
+// Programs are interrupted after this long, since they may loop forever
+const TIMEOUT: Duration = Duration::from_millis(250);
+// Once interrupted, programs are interrupted again this often, since `try`/`catch` and `on_interrupt` may carry on
+const REINTERRUPT: Duration = Duration::from_millis(1);
+
+// Generated programs are kept small enough to run quickly
+const MAX_STMTS: usize = 8;
+const MAX_BLOCK_DEPTH: usize = 3;
+const MAX_EXPR_DEPTH: usize = 4;
+
+/// Discards output and answers every prompt with an empty line.
+struct NullIo;
+
+impl Io for NullIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(String::new())
+    }
+
+    fn print(&mut self, _s: String) -> ExecResult<()> {
+        Ok(())
+    }
+}
+
+/// Lex and parse the bytes as both statements and an expression. Errors are expected; only panics are bugs.
+pub fn parse_bytes(data: &[u8]) {
+    let code = String::from_utf8_lossy(data);
+    if let Ok(parser) = parser::Parser::new(&code) {
+        let _ = parser.parse_stmts();
+    }
+    if let Ok(parser) = parser::Parser::new(&code) {
+        let _ = parser.parse_expr();
+    }
+}
+
+/// Run the code with both the interpreter and the bytecode VM, without any permissions and with output discarded.
+///
+/// Code that runs for too long is interrupted, repeatedly, so that handlers registered with `on_interrupt` cannot keep
+/// it running. Code from raw bytes may still run forever by catching the interrupts in a loop, which fuzzers report as
+/// a timeout, or overflow the stack by recursing without end; generated programs do neither.
+pub fn exec_str(code: &str) {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_io(NullIo).with_exec_mode(mode).with_seed(0).finish();
+        let interrupt = engine.interrupt_handle();
+        let (done, finished) = mpsc::channel::<()>();
+        let watchdog = thread::spawn(move || {
+            if let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(TIMEOUT) {
+                interrupt.interrupt();
+                while let Err(RecvTimeoutError::Timeout) = finished.recv_timeout(REINTERRUPT) {
+                    interrupt.interrupt();
+                }
+            }
+        });
+        let _ = engine.exec(code);
+        drop(done);
+        let _ = watchdog.join();
+    }
+}
+
+/// Lex, parse and run the bytes as a script.
+pub fn exec_bytes(data: &[u8]) {
+    exec_str(&String::from_utf8_lossy(data));
+}
+
+/// The choices that generate a program, read from fuzzer input. Zeroes are read once the input runs out, so that every
+/// input produces a complete program and short inputs produce simple ones.
+struct Choices<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Choices<'a> {
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((b, rest)) => {
+                self.data = rest;
+                *b
+            },
+            None => 0,
+        }
+    }
+
+    // A number from 0 to `n - 1`
+    fn below(&mut self, n: usize) -> usize {
+        self.byte() as usize % n.max(1)
+    }
+
+    fn pick<'b, T>(&mut self, items: &'b [T]) -> &'b T {
+        &items[self.below(items.len())]
+    }
+}
+
+const BINARY_OPS: &[&str] = &[
+    "+", "-", "*", "/", "%", "<<", ">>", "&", "|", "^", "==", "!=", "<", "<=", ">", ">=", "and", "or", "xor", "??", "..",
+];
+const UNARY_OPS: &[&str] = &["-", "!", "~", "clone ", "mirror "];
+const TYPES: &[&str] = &["int", "float", "num", "string", "char", "bool"];
+const NAMES: &[&str] = &["a", "b", "name", "len", "x"];
+const EDGE_INTS: &[&str] = &["0", "-1", "9223372036854775807", "-9223372036854775807", "0x7f", "0b101"];
+const STRINGS: &[&str] = &["", "a", "forge", "é中", "{", "\\n"];
+
+/// A program generated from fuzzer input, which always parses.
+pub struct Program {
+    src: String,
+}
+
+impl Program {
+    pub fn from_bytes(data: &[u8]) -> Self {
+        let mut gen = Generator {
+            choices: Choices { data },
+            src: String::new(),
+            vars: Vec::new(),
+            fns: Vec::new(),
+            next_id: 0,
+        };
+        let n = 1 + gen.choices.below(MAX_STMTS);
+        for _ in 0..n {
+            gen.stmt(0);
+        }
+        Self { src: gen.src }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.src
+    }
+
+    /// Run the program with both the interpreter and the bytecode VM (see `exec_str`).
+    pub fn exec(&self) {
+        exec_str(&self.src);
+    }
+}
+
+struct Generator<'a> {
+    choices: Choices<'a>,
+    src: String,
+    // Variables in scope, which may be read and assigned
+    vars: Vec<String>,
+    // Functions in scope and their number of parameters. Functions may only call those declared before them, so that
+    // programs never recurse.
+    fns: Vec<(String, usize)>,
+    next_id: usize,
+}
+
+impl<'a> Generator<'a> {
+    fn fresh(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{}{}", prefix, self.next_id)
+    }
+
+    fn push(&mut self, s: &str) {
+        self.src.push_str(s);
+    }
+
+    fn block(&mut self, depth: usize) {
+        self.block_with("", depth);
+    }
+
+    // Variables declared in a block go out of scope at its end
+    fn block_with(&mut self, first: &str, depth: usize) {
+        let (vars, fns) = (self.vars.len(), self.fns.len());
+        self.push(" {\n");
+        self.push(first);
+        let n = self.choices.below(MAX_STMTS / 2 + 1);
+        for _ in 0..n {
+            self.stmt(depth + 1);
+        }
+        self.push("}\n");
+        self.vars.truncate(vars);
+        self.fns.truncate(fns);
+    }
+
+    fn stmt(&mut self, depth: usize) {
+        let kinds = if depth >= MAX_BLOCK_DEPTH { 4 } else { 11 };
+        match self.choices.below(kinds) {
+            0 => {
+                let name = self.fresh("v");
+                self.push(&format!("var {} = ", name));
+                self.expr(0);
+                self.push(";\n");
+                self.vars.push(name);
+            },
+            1 => {
+                self.push("print ");
+                self.expr(0);
+                self.push(";\n");
+            },
+            2 if !self.vars.is_empty() => {
+                let name = self.choices.pick(&self.vars).clone();
+                let op = *self.choices.pick(&["=", "+=", "-=", "*=", "/=", "%="]);
+                self.push(&format!("{} {} ", name, op));
+                self.expr(0);
+                self.push(";\n");
+            },
+            2 | 3 => {
+                self.expr(0);
+                self.push(";\n");
+            },
+            4 => {
+                self.push("if ");
+                self.expr(0);
+                self.block(depth);
+                if self.choices.below(2) == 0 {
+                    self.push("else");
+                    self.block(depth);
+                }
+            },
+            // Loop counters are never assigned by the body, so loops end
+            5 => {
+                let counter = self.fresh("i");
+                let limit = self.choices.below(8);
+                self.push(&format!("var {} = 0;\nwhile {} < {}", counter, counter, limit));
+                self.block_with(&format!("{} += 1;\n", counter), depth);
+            },
+            6 => {
+                let name = self.fresh("v");
+                self.push(&format!("for {} in ", name));
+                self.expr(0);
+                self.vars.push(name);
+                self.block(depth);
+                self.vars.pop();
+            },
+            7 => {
+                let name = self.fresh("f");
+                let arity = self.choices.below(3);
+                let params = (0..arity).map(|_| self.fresh("p")).collect::<Vec<_>>();
+                self.push(&format!("fn {}({})", name, params.join(", ")));
+                let outer = std::mem::replace(&mut self.vars, params);
+                self.block(depth);
+                self.vars = outer;
+                self.fns.push((name, arity));
+            },
+            8 => {
+                let name = self.fresh("e");
+                self.push("try");
+                self.block(depth);
+                self.push(&format!("catch {}", name));
+                self.vars.push(name);
+                self.block(depth);
+                self.vars.pop();
+            },
+            9 => {
+                self.push("match ");
+                self.expr(0);
+                self.push(" {\n");
+                let n = self.choices.below(3);
+                for _ in 0..n {
+                    let (vars, fns) = (self.vars.len(), self.fns.len());
+                    self.pattern();
+                    self.push(" =>");
+                    self.block(depth);
+                    self.vars.truncate(vars);
+                    self.fns.truncate(fns);
+                }
+                self.push("}\n");
+            },
+            _ => {
+                let (a, b) = (self.fresh("v"), self.fresh("v"));
+                self.push(&format!("var [{}, ...{}] = ", a, b));
+                self.expr(0);
+                self.push(";\n");
+                self.vars.push(a);
+                self.vars.push(b);
+            },
+        }
+    }
+
+    fn pattern(&mut self) {
+        match self.choices.below(5) {
+            0 => self.push("_"),
+            1 => {
+                let name = self.fresh("v");
+                self.push(&name);
+                self.vars.push(name);
+            },
+            2 => self.literal(),
+            3 => {
+                let a = self.fresh("v");
+                self.push(&format!("[{}, ...]", a));
+                self.vars.push(a);
+            },
+            _ => {
+                let key = *self.choices.pick(NAMES);
+                self.push(&format!("{{{}}}", key));
+                self.vars.push(key.to_string());
+            },
+        }
+    }
+
+    fn literal(&mut self) {
+        match self.choices.below(7) {
+            0 => {
+                let x = self.choices.byte() as i8;
+                self.push(&format!("{}", x));
+            },
+            1 => {
+                let x = *self.choices.pick(EDGE_INTS);
+                self.push(x);
+            },
+            2 => {
+                let x = self.choices.byte() as i8;
+                self.push(&format!("{}.5", x));
+            },
+            3 => {
+                let s = *self.choices.pick(STRINGS);
+                self.push(&format!("\"{}\"", s.replace('{', "\\{")));
+            },
+            4 => {
+                let c = *self.choices.pick(&['a', 'z', '0', 'é']);
+                self.push(&format!("'{}'", c));
+            },
+            5 => {
+                let b = *self.choices.pick(&["true", "false"]);
+                self.push(b);
+            },
+            _ => self.push("null"),
+        }
+    }
+
+    fn expr(&mut self, depth: usize) {
+        let kinds = if depth >= MAX_EXPR_DEPTH { 2 } else { 16 };
+        match self.choices.below(kinds) {
+            0 => self.literal(),
+            1 if !self.vars.is_empty() => {
+                let name = self.choices.pick(&self.vars).clone();
+                self.push(&name);
+            },
+            1 => self.literal(),
+            2 | 3 => {
+                self.push("(");
+                self.expr(depth + 1);
+                let op = *self.choices.pick(BINARY_OPS);
+                self.push(&format!(" {} ", op));
+                self.expr(depth + 1);
+                self.push(")");
+            },
+            4 => {
+                let op = *self.choices.pick(UNARY_OPS);
+                self.push(&format!("({}(", op));
+                self.expr(depth + 1);
+                self.push("))");
+            },
+            5 => {
+                self.push("(");
+                self.expr(depth + 1);
+                self.push(" ? ");
+                self.expr(depth + 1);
+                self.push(" : ");
+                self.expr(depth + 1);
+                self.push(")");
+            },
+            6 => {
+                self.push("[");
+                let n = self.choices.below(4);
+                for i in 0..n {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.expr(depth + 1);
+                }
+                self.push("]");
+            },
+            7 => {
+                self.push("[");
+                let n = 1 + self.choices.below(3);
+                for i in 0..n {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    let key = *self.choices.pick(NAMES);
+                    self.push(&format!("\"{}\": ", key));
+                    self.expr(depth + 1);
+                }
+                self.push("]");
+            },
+            8 => {
+                self.push("(");
+                self.expr(depth + 1);
+                self.push(")[");
+                self.expr(depth + 1);
+                self.push("]");
+            },
+            9 => {
+                self.push("(");
+                self.expr(depth + 1);
+                let access = *self.choices.pick(&[".", "?."]);
+                let name = *self.choices.pick(NAMES);
+                self.push(&format!("){}{}", access, name));
+            },
+            10 => {
+                self.push("(");
+                self.expr(depth + 1);
+                let ty = *self.choices.pick(TYPES);
+                self.push(&format!(" as {})", ty));
+            },
+            // Calls are sometimes given the wrong number of arguments
+            11 if !self.fns.is_empty() => {
+                let (name, arity) = self.choices.pick(&self.fns).clone();
+                let n = if self.choices.below(8) == 0 { self.choices.below(4) } else { arity };
+                self.push(&format!("{}(", name));
+                for i in 0..n {
+                    if i > 0 {
+                        self.push(", ");
+                    }
+                    self.expr(depth + 1);
+                }
+                self.push(")");
+            },
+            11 | 12 => {
+                let param = self.fresh("p");
+                self.push(&format!("(|{}| {{ return ", param));
+                self.vars.push(param);
+                self.expr(depth + 1);
+                self.vars.pop();
+                self.push("; })(");
+                self.expr(depth + 1);
+                self.push(")");
+            },
+            13 => {
+                self.push("\"{");
+                self.expr(depth + 1);
+                self.push("}\"");
+            },
+            _ => {
+                self.push("(");
+                self.expr(depth + 1);
+                self.push(" ?? ");
+                self.expr(depth + 1);
+                self.push(")");
+            },
+        }
+    }
+}
@@ -1,14 +1,19 @@
 #![feature(
     bind_by_move_pattern_guards,
     try_from,
-    fn_traits,
     trait_alias,
 )]
 
 mod parser;
 mod exec;
+mod compile;
+mod vm;
 mod error;
 mod output;
+mod stack;
+pub mod conformance;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 
 // Reexports
 pub use exec::{
@@ -16,25 +21,68 @@ pub use exec::{
     ExecResult,
     Io,
     DefaultIo,
+    NoIo,
+    ExecCtx,
+    DisplayOptions,
+    IntFormat,
+    Permissions,
     Value,
+    Bounds,
+    List,
+    ListIter,
+    Map,
     Scope,
     Obj,
+    Resource,
+    NativeFn,
+    ErrorValue,
+    Heap,
+    WeakRef,
+    InterruptHandle,
+    FileHandle,
+    Duration,
+    DateTime,
+    Module,
+    ModuleLoader,
+    Rng,
     GlobalScope,
+    Recording,
+    HeapSnapshot,
+    HeapGroup,
 };
 pub use error::{
     ForgeResult,
     ForgeError,
+    Diagnostic,
 };
+pub use output::DiagnosticStyle;
+pub use vm::ExecMode;
 
 use std::{
-    ops::DerefMut,
     rc::Rc,
+    path::PathBuf,
+    panic::{self, AssertUnwindSafe},
 };
-use parser::ParseError;
+use parser::{
+    ParseError,
+    ast::{Node, Expr, Stmt},
+};
+use compile::{Chunk, FnCache};
 
 pub struct EngineBuilder {
     io: Box<dyn Io>,
+    display: DisplayOptions,
     global_scope: GlobalScope,
+    exec_mode: ExecMode,
+    module_root: Option<PathBuf>,
+    seed: Option<u64>,
+    permissions: Permissions,
+    gc_threshold: Option<usize>,
+    max_call_depth: Option<usize>,
+    replay: Option<exec::Replay>,
+    snapshot_dir: Option<PathBuf>,
+    update_snapshots: bool,
+    allow_mocks: bool,
 }
 
 impl EngineBuilder {
@@ -43,29 +91,216 @@ impl EngineBuilder {
         self
     }
 
+    pub fn with_display(mut self, display: DisplayOptions) -> Self {
+        self.display = display;
+        self
+    }
+
     pub fn with_global<T: Into<Value>>(mut self, name: &str, val: T) -> Self {
         self.global_scope.declare_var(name.to_string(), val.into());
         self
     }
 
-    pub fn finish(self) -> Engine {
+    pub fn with_fn<F: Fn(&[Value]) -> ExecResult<Value> + 'static>(self, name: &str, arity: usize, f: F) -> Self {
+        self.with_global(name, NativeFn::new(name, arity, f))
+    }
+
+    pub fn with_exec_mode(mut self, mode: ExecMode) -> Self {
+        self.exec_mode = mode;
+        self
+    }
+
+    /// Set the directory that modules imported by top-level code are resolved relative to, and allow code to import
+    /// the modules within it. Without one, modules are imported from the working directory, and only if the engine is
+    /// allowed to read files (see `EngineBuilder::allow_fs`).
+    pub fn with_module_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.module_root = Some(root.into());
+        self
+    }
+
+    /// Seed the random number generator used by builtins such as `rand`, `uuid.v4` and `random.token`, so that every run
+    /// produces the same values (deterministic mode).
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_permissions(mut self, permissions: Permissions) -> Self {
+        self.permissions = permissions;
+        self
+    }
+
+    /// Allow scripts to read and write the filesystem.
+    pub fn allow_fs(mut self) -> Self {
+        self.permissions.fs_read = true;
+        self.permissions.fs_write = true;
+        self
+    }
+
+    /// Confine the files that scripts may use to those within a directory, which relative paths are then resolved
+    /// against. This does not grant access to the filesystem by itself (see `EngineBuilder::allow_fs`).
+    pub fn with_fs_root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.permissions.fs_root = Some(root.into());
+        self
+    }
+
+    /// Allow scripts to read and change environment variables and the working directory of the process.
+    pub fn allow_env(mut self) -> Self {
+        self.permissions.env = true;
+        self
+    }
+
+    /// Allow scripts to connect to other machines over the network.
+    pub fn allow_net(mut self) -> Self {
+        self.permissions.net = true;
+        self
+    }
+
+    /// Allow scripts to read the host's clock, such as with `time.now()`.
+    pub fn allow_clock(mut self) -> Self {
+        self.permissions.clock = true;
+        self
+    }
+
+    /// Collect reference cycles automatically once this many lists, maps and variable frames have been allocated since
+    /// the last collection, or never if `None`. Cycles may still be collected manually with `Engine::gc`.
+    pub fn with_gc_threshold(mut self, threshold: Option<usize>) -> Self {
+        self.gc_threshold = threshold;
+        self
+    }
+
+    /// Allow at most this many function calls to be underway at once, or leave only the stack to limit them if `None`.
+    /// Calls beyond the limit fail with `ExecError::StackOverflow`, which scripts may catch. Unless the stack is
+    /// much larger than usual, calls run out of it well before the default limit (see `DEFAULT_MAX_CALL_DEPTH`).
+    pub fn with_max_call_depth(mut self, depth: Option<usize>) -> Self {
+        self.max_call_depth = depth;
+        self
+    }
+
+    /// Record everything that the code receives from outside of the engine, so that it can be replayed exactly (see
+    /// `Recording`). The recording so far is available from `Engine::recording`.
+    pub fn record(mut self) -> Self {
+        self.replay = Some(exec::Replay::Record(Recording::default()));
+        self
+    }
+
+    /// Take everything that the code receives from outside of the engine from a recording, rather than from the host,
+    /// the user or the clock, so that the code runs just as it did when it was recorded.
+    pub fn replay(mut self, recording: Recording) -> Self {
+        self.replay = Some(exec::Replay::Replay(recording, 0));
+        self
+    }
+
+    /// Keep the snapshots that `expect_snapshot` compares values against in this directory. Without one, calling
+    /// `expect_snapshot` is an error.
+    pub fn with_snapshot_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.snapshot_dir = Some(dir.into());
+        self
+    }
+
+    /// Have `expect_snapshot` record the values it is given as the new snapshots, rather than failing when they differ.
+    pub fn update_snapshots(mut self) -> Self {
+        self.update_snapshots = true;
+        self
+    }
+
+    /// Allow scripts to replace builtins and host functions with `mock`, and to record the calls made to them with
+    /// `spy`, so that code that depends on the outside world can be tested offline.
+    pub fn allow_mocks(mut self) -> Self {
+        self.allow_mocks = true;
+        self
+    }
+
+    pub fn finish(mut self) -> Engine {
+        let mut ctx = ExecCtx::new(self.io, self.display);
+        ctx.modules = match self.module_root {
+            Some(root) => ModuleLoader::new(root),
+            None => ModuleLoader::default(),
+        };
+        let seed = match &mut self.replay {
+            Some(replay) => Some(replay.seed(self.seed)),
+            None => self.seed,
+        };
+        if let Some(seed) = seed {
+            ctx.rng = Rng::from_seed(seed);
+        }
+        ctx.replay = self.replay;
+        ctx.permissions = self.permissions;
+        ctx.gc_threshold = self.gc_threshold;
+        ctx.max_call_depth = self.max_call_depth;
+        if self.exec_mode == ExecMode::Bytecode {
+            ctx.compiled_fns = Some(FnCache::default());
+        }
+        let update = self.update_snapshots;
+        ctx.snapshots = self.snapshot_dir.map(|dir| exec::Snapshots { dir, update });
+        if self.allow_mocks {
+            ctx.mocks = Some(exec::Mocks::new());
+        }
         Engine {
-            io: self.io,
+            ctx,
             global_scope: self.global_scope,
+            exec_mode: self.exec_mode,
         }
     }
 }
 
 pub struct Engine {
-    io: Box<dyn Io>,
+    ctx: ExecCtx,
     global_scope: GlobalScope,
+    exec_mode: ExecMode,
 }
 
 impl Engine {
     pub fn build() -> EngineBuilder {
         EngineBuilder {
             io: Box::new(DefaultIo),
+            display: DisplayOptions::default(),
             global_scope: GlobalScope::empty(),
+            exec_mode: ExecMode::default(),
+            module_root: None,
+            seed: None,
+            permissions: Permissions::default(),
+            gc_threshold: Some(exec::DEFAULT_GC_THRESHOLD),
+            max_call_depth: Some(exec::DEFAULT_MAX_CALL_DEPTH),
+            replay: None,
+            snapshot_dir: None,
+            update_snapshots: false,
+            allow_mocks: false,
+        }
+    }
+
+    // Returns `None` when the interpreter should be used instead
+    fn compile_stmts(&self, stmts: &[Node<Stmt>]) -> Option<Chunk> {
+        match self.exec_mode {
+            ExecMode::Interpret => None,
+            ExecMode::Bytecode => compile::compile_stmts(stmts).ok(),
+        }
+    }
+
+    fn compile_expr(&self, expr: &Expr) -> Option<Chunk> {
+        match self.exec_mode {
+            ExecMode::Interpret => None,
+            ExecMode::Bytecode => compile::compile_expr(expr).ok(),
+        }
+    }
+
+    fn run_stmts(&mut self, stmts: &[Node<Stmt>], src: &Rc<String>) -> ExecResult<()> {
+        match self.compile_stmts(stmts) {
+            Some(chunk) => vm::Vm::new(&mut self.global_scope).run(&chunk, &mut self.ctx, src).map(|_| ()),
+            None => {
+                for stmt in stmts {
+                    // stmt.0.print_debug(0); // TODO: Remove this
+                    self.global_scope.eval_stmt(&stmt.0, &mut self.ctx, src)?;
+                }
+                Ok(())
+            },
+        }
+    }
+
+    fn run_expr(&mut self, expr: &Expr, src: &Rc<String>) -> ExecResult<Value> {
+        match self.compile_expr(expr) {
+            Some(chunk) => vm::Vm::new(&mut self.global_scope).run(&chunk, &mut self.ctx, src).map(|val| val.unwrap_or(Value::Null)),
+            None => self.global_scope.eval_expr(expr, &mut self.ctx, src),
         }
     }
 
@@ -78,7 +313,7 @@ impl Engine {
             //expr.print_debug(0);
 
             Ok(
-                self.global_scope.eval_expr(&expr, self.io.deref_mut(), &Rc::new(expr_str.to_string()))
+                self.run_expr(&expr, &Rc::new(expr_str.to_string()))
                     .map_err(|err| ForgeError::InSrc(expr_str.to_string(), Box::new(err.into())))?
             )
         };
@@ -91,41 +326,166 @@ impl Engine {
             let stmts = parser::Parser::new(module).map_err(map_src)?.parse_stmts()
                 .map_err(|err| ForgeError::InSrc(module.to_string(), Box::new(err.into())))?;
 
-            for stmt in &stmts {
-                // stmt.0.print_debug(0); // TODO: Remove this
-                self.global_scope.eval_stmt(&stmt.0, self.io.deref_mut(), &Rc::new(module.to_string()))
-                    .map_err(|err| ForgeError::InSrc(module.to_string(), Box::new(err.into())))?;
-            }
+            self.run_stmts(&stmts, &Rc::new(module.to_string()))
+                .map_err(|err| ForgeError::InSrc(module.to_string(), Box::new(err.into())))?;
 
             Ok(())
         };
         exec_fn()
     }
 
+    /// Run a line entered at an interactive prompt, producing its value if it is an expression.
+    ///
+    /// If the input is incomplete (see `ForgeError::is_incomplete`), the caller should append the next line to it and
+    /// try again.
     pub fn prompt(&mut self, input: &str) -> ForgeResult<Option<Value>> {
         let map_src = |err: ParseError| ForgeError::InSrc(input.to_string(), Box::new(err.into()));
         match parser::Parser::new(input).map_err(map_src)?.parse_stmts() {
             Ok(stmts) => {
-                for stmt in &stmts {
-                    self.global_scope.eval_stmt(&stmt.0, self.io.deref_mut(), &Rc::new(input.to_string()))?;
-                }
+                self.run_stmts(&stmts, &Rc::new(input.to_string()))?;
                 Ok(None)
             },
-            Err(stmts_err) => Ok(Some(self.global_scope.eval_expr(
-                &parser::Parser::new(input).map_err(|err| err.max(stmts_err)).map_err(map_src)?.parse_expr().map_err(map_src)?,
-                self.io.deref_mut(),
-                &Rc::new(input.to_string()),
-            ).map_err(|err| ForgeError::InSrc(input.to_string(), Box::new(err.into())))?)),
+            Err(stmts_err) => {
+                let expr = match parser::Parser::new(input).map_err(|err| err.max(stmts_err.clone())).map_err(map_src)?.parse_expr() {
+                    Ok(expr) => expr,
+                    // The input may be the start of a statement that continues on the next line
+                    Err(_) if stmts_err.is_incomplete() => return Err(map_src(stmts_err)),
+                    Err(expr_err) => return Err(map_src(expr_err)),
+                };
+                Ok(Some(self.run_expr(&expr, &Rc::new(input.to_string()))
+                    .map_err(|err| ForgeError::InSrc(input.to_string(), Box::new(err.into())))?))
+            },
         }
     }
 
+    /// Like `exec`, but guaranteed not to panic, whatever the code given to it.
+    ///
+    /// Errors in the code, including code that nests or recurses too deeply or makes lists too large to allocate, are
+    /// always reported as errors rather than panics, whichever method runs the code. Lists and maps are freed without
+    /// recursion, so nesting them deeply cannot overflow the stack either. These methods additionally catch
+    /// any panic from a bug within Forge itself and produce it as `ForgeError::Internal`, so that embedders running
+    /// untrusted code can rely on never being unwound into. Panics from functions registered by the embedder are
+    /// caught in the same way. The panic hook still runs, so the panic is reported as usual unless it is replaced.
+    ///
+    /// The engine may be left part-way through running the code after an internal error, so it should be dropped
+    /// rather than used again. This guarantee relies on panics unwinding, as they do unless `panic = "abort"` is set.
+    pub fn safe_exec(&mut self, module: &str) -> ForgeResult<()> {
+        catch_panic(|| self.exec(module))
+    }
+
+    /// Like `eval`, but guaranteed not to panic (see `Engine::safe_exec`).
+    pub fn safe_eval(&mut self, expr_str: &str) -> ForgeResult<Value> {
+        catch_panic(|| self.eval(expr_str))
+    }
+
+    /// Like `prompt`, but guaranteed not to panic (see `Engine::safe_exec`).
+    pub fn safe_prompt(&mut self, input: &str) -> ForgeResult<Option<Value>> {
+        catch_panic(|| self.prompt(input))
+    }
+
+    /// Make a Rust function callable from Forge code under the given name.
+    pub fn register_fn<F: Fn(&[Value]) -> ExecResult<Value> + 'static>(&mut self, name: &str, arity: usize, f: F) {
+        self.global_scope.declare_var(name.to_string(), NativeFn::new(name, arity, f).into());
+    }
+
     pub fn global_scope(&self) -> &GlobalScope {
         &self.global_scope
     }
 
+    /// Free the lists, maps and functions that are unreachable but kept alive by reference cycles, such as a closure
+    /// stored in a map that it refers to. Returns the number of containers freed.
+    ///
+    /// Values that the host still holds are never freed, along with anything they refer to.
+    pub fn gc(&mut self) -> usize {
+        exec::collect_cycles()
+    }
+
+    /// Count the lists, maps and scopes that are alive, and roughly how much memory they use, by where they were
+    /// allocated, to find what is using up memory. Like `gc`, this covers every engine on the current thread.
+    pub fn heap_snapshot(&self) -> HeapSnapshot {
+        exec::heap_snapshot()
+    }
+
+    /// A handle that can be used to interrupt the code running in this engine, even from another thread.
+    pub fn interrupt_handle(&self) -> InterruptHandle {
+        self.ctx.interrupt.clone()
+    }
+
+    pub fn display_options(&self) -> &DisplayOptions {
+        self.ctx.display()
+    }
+
+    pub fn display_options_mut(&mut self) -> &mut DisplayOptions {
+        &mut self.ctx.display
+    }
+
+    /// Allow the code run from now on to evaluate this many more expressions (or bytecode instructions, when running
+    /// bytecode) in total, after which it fails with `ExecError::OutOfFuel`. `None` removes the limit. Fuel that is
+    /// left over carries on to the code run next, so it must be set again once it has run out.
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        self.ctx.fuel = fuel;
+    }
+
+    /// What the code has received from outside of the engine so far, if it is being recorded (see
+    /// `EngineBuilder::record`).
+    pub fn recording(&self) -> Option<&Recording> {
+        self.ctx.replay.as_ref().and_then(|replay| replay.recording())
+    }
+
+    /// How much fuel is left, if it is limited (see `Engine::set_fuel`).
+    pub fn fuel(&self) -> Option<u64> {
+        self.ctx.fuel
+    }
+
+    /// Reseed the random number generator (see `EngineBuilder::with_seed`), so that the code run from now on produces
+    /// the same values each time, such as when replaying a game from its start.
+    pub fn seed_rng(&mut self, seed: u64) {
+        self.ctx.rng = Rng::from_seed(seed);
+    }
+
+    /// Change how many function calls may be underway at once (see `EngineBuilder::with_max_call_depth`).
+    pub fn set_max_call_depth(&mut self, depth: Option<usize>) {
+        self.ctx.max_call_depth = depth;
+    }
+
+    /// Produce the text for a value in the same way that `print` would.
+    pub fn display(&self, val: &Value) -> Option<String> {
+        val.get_display_text_with(self.ctx.display()).ok()
+    }
+
+    /// Get the value of a global variable, leaving it in place.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.global_scope.get_var(name).ok()
+    }
+
+    /// Declare a global variable, replacing any existing variable of the same name.
+    pub fn set<T: Into<Value>>(&mut self, name: &str, val: T) {
+        self.global_scope.declare_var(name.to_string(), val.into());
+    }
+
     pub fn take(&mut self, name: &str) -> Option<Value> {
         self.global_scope.take_var(name)
     }
+
+    /// The names of the global variables, in no particular order. Builtins are not included.
+    pub fn global_names(&self) -> Vec<String> {
+        self.global_scope
+            .env()
+            .vars()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+}
+
+// Run the function, producing any panic within it as an internal error instead
+fn catch_panic<T>(f: impl FnOnce() -> ForgeResult<T>) -> ForgeResult<T> {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        let msg = payload.downcast_ref::<&str>().map(|msg| msg.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| String::from("unknown panic"));
+        Err(ForgeError::Internal(msg))
+    })
 }
 
 impl Default for Engine {
@@ -0,0 +1,97 @@
+use std::fmt;
+use crate::parser::{SrcLoc, SrcRef};
+
+/// A minimal JSON document, written out compactly by its `Display` implementation.
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(usize),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    /// A position in the source as `{"line": _, "col": _}`, or null for positions without a line and column (such
+    /// as the end of input).
+    pub fn loc(loc: SrcLoc) -> Self {
+        match loc.pos() {
+            Some((line, col)) => Json::Object(vec![
+                ("line", Json::Int(line)),
+                ("col", Json::Int(col)),
+            ]),
+            None => Json::Null,
+        }
+    }
+
+    /// A range of the source as `{"start": _, "end": _}`, where the end is exclusive.
+    pub fn span(r: SrcRef) -> Self {
+        match r {
+            SrcRef::Range { start, limit } => Json::Object(vec![
+                ("start", Json::loc(start)),
+                ("end", Json::loc(limit)),
+            ]),
+            SrcRef::Empty => Json::Null,
+        }
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(val: Option<T>) -> Self {
+        val.map(|val| val.into()).unwrap_or(Json::Null)
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::Str(s)
+    }
+}
+
+fn fmt_str(f: &mut fmt::Formatter, s: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(x) => write!(f, "{}", x),
+            Json::Str(s) => fmt_str(f, s),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            },
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (name, val)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    fmt_str(f, name)?;
+                    write!(f, ":{}", val)?;
+                }
+                write!(f, "}}")
+            },
+        }
+    }
+}
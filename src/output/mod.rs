@@ -1,6 +1,10 @@
+mod json;
+
 use std::fmt;
 use crate::parser::SrcRef;
 
+pub use self::json::Json;
+
 pub struct Repeat(pub char, pub usize);
 
 impl std::fmt::Display for Repeat {
@@ -12,7 +16,64 @@ impl std::fmt::Display for Repeat {
     }
 }
 
-pub fn fmt_ref(f: &mut fmt::Formatter, r: SrcRef, src: Option<&str>, depth: usize) -> fmt::Result {
+/// How errors are rendered when displayed with `ForgeError::display_with`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DiagnosticStyle {
+    /// The line containing each error location, with carets beneath it. This is what `Display` produces.
+    Plain,
+    /// A window of the surrounding lines, with line numbers in the gutter.
+    Rich,
+    /// The same as `Rich`, with ANSI colors for terminals that support them.
+    Colored,
+}
+
+impl Default for DiagnosticStyle {
+    fn default() -> Self {
+        DiagnosticStyle::Plain
+    }
+}
+
+impl DiagnosticStyle {
+    fn paint(self, color: &'static str) -> Paint {
+        Paint(if self == DiagnosticStyle::Colored { color } else { "" })
+    }
+}
+
+// ANSI escape sequences used by `DiagnosticStyle::Colored`
+const RED_BOLD: &str = "\x1b[1;31m";
+const BLUE: &str = "\x1b[34m";
+const RESET: &str = "\x1b[0m";
+
+// Writes an escape sequence only when colors are enabled
+struct Paint(&'static str);
+
+impl fmt::Display for Paint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// The label that begins each error message.
+pub struct ErrorTag(pub DiagnosticStyle);
+
+impl fmt::Display for ErrorTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[ERROR]{}", self.0.paint(RED_BOLD), self.0.paint(RESET))
+    }
+}
+
+// The number of lines shown either side of an error location by the rich styles
+const CONTEXT_LINES: usize = 2;
+
+pub fn fmt_ref(f: &mut fmt::Formatter, r: SrcRef, src: Option<&str>, depth: usize, style: DiagnosticStyle) -> fmt::Result {
+    match (style, src, r.start().pos()) {
+        (DiagnosticStyle::Rich, Some(src), Some((line, col))) |
+        (DiagnosticStyle::Colored, Some(src), Some((line, col))) => fmt_ref_rich(f, r, src, line, col, depth, style),
+        _ => fmt_ref_plain(f, r, src, depth),
+    }
+}
+
+fn fmt_ref_plain(f: &mut fmt::Formatter, r: SrcRef, src: Option<&str>, depth: usize) -> fmt::Result {
     let pos_str = r.start().pos().map(|p| format!("{:>4}", p.0)).unwrap_or(String::new());
     if let (Some(src), Some((line, col))) = (src, r.start().pos()) {
         let line_str = src.lines().nth(line.saturating_sub(1)).unwrap_or("<none>").replace('\t', " ");
@@ -32,3 +93,29 @@ pub fn fmt_ref(f: &mut fmt::Formatter, r: SrcRef, src: Option<&str>, depth: usiz
         writeln!(f, "{}", r)
     }
 }
+
+fn fmt_ref_rich(f: &mut fmt::Formatter, r: SrcRef, src: &str, line: usize, col: usize, depth: usize, style: DiagnosticStyle) -> fmt::Result {
+    let lines = src.lines().collect::<Vec<_>>();
+    let first = line.saturating_sub(CONTEXT_LINES).max(1);
+    let last = (line + CONTEXT_LINES).min(lines.len()).max(line);
+    let width = last.to_string().len().max(4);
+    let indent = Repeat(' ', depth * 3);
+
+    for n in first..=last {
+        let line_str = lines.get(n - 1).copied().unwrap_or("").replace('\t', " ");
+        writeln!(f, "{}{}{:>width$} |{} {}", indent, style.paint(BLUE), n, style.paint(RESET), line_str, width = width)?;
+        if n == line {
+            writeln!(f, "{}{}{} |{}{}{}{}{}",
+                indent,
+                style.paint(BLUE),
+                Repeat(' ', width),
+                style.paint(RESET),
+                Repeat(' ', col),
+                style.paint(RED_BOLD),
+                Repeat('^', r.length_in(src).unwrap_or(1)),
+                style.paint(RESET),
+            )?;
+        }
+    }
+    Ok(())
+}
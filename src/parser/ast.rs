@@ -11,22 +11,26 @@ pub struct Node<T>(pub T, pub SrcRef);
 #[derive(Debug)]
 pub enum Expr {
     None,
-    LiteralNumber(f64),
+    LiteralInt(i64),
+    LiteralFloat(f64),
     LiteralString(String),
     LiteralChar(char),
     LiteralBoolean(bool),
     LiteralNull,
+    InterpolatedString(Vec<StringPart>),
     Ident(Node<String>),
     List(Node<Vec<Node<Expr>>>),
     ListClone(Box<Node<Expr>>, Box<Node<Expr>>),
     Map(Node<Vec<(Node<Expr>, Node<Expr>)>>),
 
-    Call(SrcRef, Box<Node<Expr>>, Node<Vec<Node<Expr>>>),
+    Call(SrcRef, Box<Node<Expr>>, Node<Vec<Node<Expr>>>, Vec<(Node<String>, Node<Expr>)>), // Positional, then named arguments
     DotAccess(SrcRef, Box<Node<Expr>>, Node<String>),
+    OptionalAccess(SrcRef, Box<Node<Expr>>, Node<String>), // `x?.name`, which is null when `x` is
     Index(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
 
     UnaryNot(SrcRef, Box<Node<Expr>>),
     UnaryNeg(SrcRef, Box<Node<Expr>>),
+    UnaryBitNot(SrcRef, Box<Node<Expr>>),
     UnaryInput(SrcRef, Box<Node<Expr>>),
     UnaryClone(SrcRef, Box<Node<Expr>>),
     UnaryMirror(SrcRef, Box<Node<Expr>>),
@@ -36,6 +40,11 @@ pub enum Expr {
     BinaryRem(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryAdd(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinarySub(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryShl(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryShr(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryBitAnd(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryBitXor(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryBitOr(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryGreater(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryGreaterEq(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryLess(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
@@ -44,9 +53,12 @@ pub enum Expr {
     BinaryNotEq(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryAnd(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryOr(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryCoalesce(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>), // `a ?? b`, which is `b` only when `a` is null
     BinaryXor(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryRange(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
     BinaryAs(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>),
+    BinaryIs(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>), // `instance is Class`
+    Ternary(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>, Box<Node<Expr>>), // Condition, then, else
 
     BinaryAssign(SrcRef, Node<LVal>, Box<Node<Expr>>),
     BinaryAddAssign(SrcRef, Node<LVal>, Box<Node<Expr>>),
@@ -55,17 +67,46 @@ pub enum Expr {
     BinaryDivAssign(SrcRef, Node<LVal>, Box<Node<Expr>>),
     BinaryRemAssign(SrcRef, Node<LVal>, Box<Node<Expr>>),
 
-    Fn(Rc<String>, Rc<(Node<Args>, Node<Block>)>),
+    Fn(Rc<String>, Rc<(Node<Args>, Node<Block>, Option<String>)>),
+}
+
+#[derive(Debug)]
+pub enum StringPart {
+    Literal(String),
+    Expr(Node<Expr>),
 }
 
 #[derive(Debug)]
 pub enum LVal {
     Local(Node<String>),
     Index(Box<Node<Expr>>, Box<Node<Expr>>),
+    Member(Box<Node<Expr>>, Node<String>),
+}
+
+#[derive(Debug)]
+pub enum Pattern {
+    Wildcard,
+    Binding(String),
+    Literal(Node<Expr>),
+    Range(Node<Expr>, Node<Expr>),
+    List(Vec<Node<Pattern>>, Option<Box<Node<Pattern>>>), // Items, rest
+    Map(Vec<(Node<String>, Node<Pattern>)>), // Keys, and the patterns their values must match
+    Typed(Box<Node<Pattern>>, Node<String>), // A binding or wildcard, and the type the value must have
+}
+
+/// A parameter of a function, with an optional type annotation such as the `int` of `|x: int|` and an optional
+/// default such as the `10` of `|x = 10|`, which is evaluated each time the function is called without it.
+#[derive(Debug)]
+pub struct Param {
+    pub name: Node<String>,
+    pub ty: Option<Node<String>>,
+    pub default: Option<Node<Expr>>,
 }
 
+/// The parameters of a function, followed by the name that any extra arguments are collected into as a list, such as
+/// the `rest` of `|a, ...rest|`. Parameters with defaults come after those without.
 #[derive(Debug)]
-pub struct Args(pub Vec<Node<String>>);
+pub struct Args(pub Vec<Param>, pub Option<Node<String>>);
 
 #[derive(Debug)]
 pub struct Block(pub Vec<Node<Stmt>>);
@@ -76,9 +117,16 @@ pub enum Stmt {
     Print(Node<Expr>),
     If(Node<Expr>, Node<Block>),
     IfElse(Node<Expr>, Node<Block>, Node<Block>),
+    IfElseIf(Node<Expr>, Node<Block>, Box<Node<Stmt>>),
     While(Node<Expr>, Node<Block>),
-    For(Node<String>, Node<Expr>, Node<Block>),
+    For(Node<String>, Option<Node<String>>, Node<Expr>, Node<Block>), // With two identifiers, the first is bound to the key
+    TryCatch(Node<Block>, Node<String>, Node<Block>),
+    With(Node<Expr>, Node<String>, Node<Block>), // `with expr as name { ... }`, closing the resource afterwards
+    Match(Node<Expr>, Vec<(Node<Pattern>, Node<Block>)>),
     Decl(Node<String>, Node<Expr>),
+    Class(Node<String>, Vec<(Node<String>, Node<Expr>)>), // The name of the class and its methods, which are functions
+    DeclPattern(Node<Pattern>, Node<Expr>), // `var [a, b] = pair;` or `var {x, y} = point;`
+    Import(Node<String>, Node<String>), // Path, name bound to the module
     Return(Node<Expr>),
 }
 
@@ -100,6 +148,7 @@ impl Node<Expr> {
         match self {
             Node(Expr::Ident(ident), r) => Ok(Node(LVal::Local(ident), r)),
             Node(Expr::Index(_, expr, index), r) => Ok(Node(LVal::Index(expr, index), r)),
+            Node(Expr::DotAccess(_, expr, name), r) => Ok(Node(LVal::Member(expr, name), r)),
             Node(_, _) => Err(ParseError::At(r, Box::new(ParseError::NotAnLValue))),
         }
     }
@@ -109,11 +158,21 @@ impl Expr {
     pub fn print_debug(&self, depth: usize) {
         match self {
             Expr::None => println!("{}None expression", Spaces(depth)),
-            Expr::LiteralNumber(x) => println!("{}Number literal '{}'", Spaces(depth), x),
+            Expr::LiteralInt(x) => println!("{}Int literal '{}'", Spaces(depth), x),
+            Expr::LiteralFloat(x) => println!("{}Float literal '{:?}'", Spaces(depth), x),
             Expr::LiteralString(s) => println!("{}String literal '{}'", Spaces(depth), s),
             Expr::LiteralChar(c) => println!("{}Character literal '{}'", Spaces(depth), c),
             Expr::LiteralBoolean(b) => println!("{}Boolean literal '{}'", Spaces(depth), b),
             Expr::LiteralNull => println!("{}Null literal", Spaces(depth)),
+            Expr::InterpolatedString(parts) => {
+                println!("{}Interpolated string", Spaces(depth));
+                for part in parts {
+                    match part {
+                        StringPart::Literal(s) => println!("{}String literal '{}'", Spaces(depth + 1), s),
+                        StringPart::Expr(expr) => expr.0.print_debug(depth + 1),
+                    }
+                }
+            },
             Expr::Ident(s) => println!("{}Identifier '{}'", Spaces(depth), s.0),
             Expr::List(items) => {
                 println!("{}List", Spaces(depth));
@@ -138,18 +197,26 @@ impl Expr {
                     val.0.print_debug(depth + 2);
                 }
             },
-            Expr::Call(_, expr, params) => {
+            Expr::Call(_, expr, params, named) => {
                 println!("{}Call", Spaces(depth));
                 expr.0.print_debug(depth + 1);
                 for param in &params.0 {
                     println!("{}Parameter", Spaces(depth + 1));
                     param.0.print_debug(depth + 1);
                 }
+                for (name, param) in named {
+                    println!("{}Parameter '{}'", Spaces(depth + 1), name.0);
+                    param.0.print_debug(depth + 1);
+                }
             },
             Expr::DotAccess(_, expr, s) => {
                 println!("{}Dot access '{}'", Spaces(depth), s.0);
                 expr.0.print_debug(depth + 1);
             },
+            Expr::OptionalAccess(_, expr, s) => {
+                println!("{}Optional access '{}'", Spaces(depth), s.0);
+                expr.0.print_debug(depth + 1);
+            },
             Expr::Index(_, expr, index) => {
                 println!("{}Index access", Spaces(depth));
                 expr.0.print_debug(depth + 1);
@@ -163,6 +230,10 @@ impl Expr {
                 println!("{}Unary neg", Spaces(depth));
                 expr.0.print_debug(depth + 1);
             },
+            Expr::UnaryBitNot(_, expr) => {
+                println!("{}Unary bit not", Spaces(depth));
+                expr.0.print_debug(depth + 1);
+            },
             Expr::UnaryInput(_, expr) => {
                 println!("{}Unary input", Spaces(depth));
                 expr.0.print_debug(depth + 1);
@@ -200,6 +271,31 @@ impl Expr {
                 left.0.print_debug(depth + 1);
                 right.0.print_debug(depth + 1);
             },
+            Expr::BinaryShl(_, left, right) => {
+                println!("{}Binary shl", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
+            Expr::BinaryShr(_, left, right) => {
+                println!("{}Binary shr", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
+            Expr::BinaryBitAnd(_, left, right) => {
+                println!("{}Binary bit and", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
+            Expr::BinaryBitXor(_, left, right) => {
+                println!("{}Binary bit xor", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
+            Expr::BinaryBitOr(_, left, right) => {
+                println!("{}Binary bit or", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
             Expr::BinaryGreater(_, left, right) => {
                 println!("{}Binary greater", Spaces(depth));
                 left.0.print_debug(depth + 1);
@@ -240,6 +336,11 @@ impl Expr {
                 left.0.print_debug(depth + 1);
                 right.0.print_debug(depth + 1);
             },
+            Expr::BinaryCoalesce(_, left, right) => {
+                println!("{}Binary coalesce", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
             Expr::BinaryXor(_, left, right) => {
                 println!("{}Binary xor", Spaces(depth));
                 left.0.print_debug(depth + 1);
@@ -255,6 +356,17 @@ impl Expr {
                 left.0.print_debug(depth + 1);
                 right.0.print_debug(depth + 1);
             },
+            Expr::BinaryIs(_, left, right) => {
+                println!("{}Binary is", Spaces(depth));
+                left.0.print_debug(depth + 1);
+                right.0.print_debug(depth + 1);
+            },
+            Expr::Ternary(_, cond, a, b) => {
+                println!("{}Ternary", Spaces(depth));
+                cond.0.print_debug(depth + 1);
+                a.0.print_debug(depth + 1);
+                b.0.print_debug(depth + 1);
+            },
             Expr::BinaryAssign(_, target, expr) => {
                 println!("{}Binary assign", Spaces(depth));
                 target.0.print_debug(depth + 1);
@@ -286,7 +398,10 @@ impl Expr {
                 expr.0.print_debug(depth + 1);
             },
             Expr::Fn(_, rc) => {
-                println!("{}Function", Spaces(depth));
+                match &rc.2 {
+                    Some(name) => println!("{}Function '{}'", Spaces(depth), name),
+                    None => println!("{}Function", Spaces(depth)),
+                }
                 (rc.0).0.print_debug(depth + 1);
                 (rc.1).0.print_debug(depth + 1);
             },
@@ -303,6 +418,49 @@ impl LVal {
                 expr.0.print_debug(depth + 1);
                 index.0.print_debug(depth + 1);
             },
+            LVal::Member(expr, name) => {
+                println!("{}Member l-value '{}'", Spaces(depth), name.0);
+                expr.0.print_debug(depth + 1);
+            },
+        }
+    }
+}
+
+impl Pattern {
+    pub fn print_debug(&self, depth: usize) {
+        match self {
+            Pattern::Wildcard => println!("{}Wildcard pattern", Spaces(depth)),
+            Pattern::Binding(ident) => println!("{}Binding pattern '{}'", Spaces(depth), ident),
+            Pattern::Literal(expr) => {
+                println!("{}Literal pattern", Spaces(depth));
+                expr.0.print_debug(depth + 1);
+            },
+            Pattern::Range(from, to) => {
+                println!("{}Range pattern", Spaces(depth));
+                from.0.print_debug(depth + 1);
+                to.0.print_debug(depth + 1);
+            },
+            Pattern::List(items, rest) => {
+                println!("{}List pattern", Spaces(depth));
+                for item in items {
+                    item.0.print_debug(depth + 1);
+                }
+                if let Some(rest) = rest {
+                    println!("{}Rest", Spaces(depth + 1));
+                    rest.0.print_debug(depth + 2);
+                }
+            },
+            Pattern::Map(entries) => {
+                println!("{}Map pattern", Spaces(depth));
+                for (key, pattern) in entries {
+                    println!("{}Key '{}'", Spaces(depth + 1), key.0);
+                    pattern.0.print_debug(depth + 2);
+                }
+            },
+            Pattern::Typed(inner, ty) => {
+                println!("{}Typed pattern '{}'", Spaces(depth), ty.0);
+                inner.0.print_debug(depth + 1);
+            },
         }
     }
 }
@@ -329,20 +487,63 @@ impl Stmt {
                 true_block.0.print_debug(depth + 1);
                 false_block.0.print_debug(depth + 1);
             },
+            Stmt::IfElseIf(expr, true_block, else_stmt) => {
+                println!("{}If-else-if statement", Spaces(depth));
+                expr.0.print_debug(depth + 1);
+                true_block.0.print_debug(depth + 1);
+                else_stmt.0.print_debug(depth + 1);
+            },
             Stmt::While(expr, block) => {
                 println!("{}While statement", Spaces(depth));
                 expr.0.print_debug(depth + 1);
                 block.0.print_debug(depth + 1);
             },
-            Stmt::For(ident, expr, block) => {
-                println!("{}For statement '{}'", Spaces(depth), ident.0);
+            Stmt::For(ident, val_ident, expr, block) => {
+                match val_ident {
+                    Some(val_ident) => println!("{}For statement '{}', '{}'", Spaces(depth), ident.0, val_ident.0),
+                    None => println!("{}For statement '{}'", Spaces(depth), ident.0),
+                }
+                expr.0.print_debug(depth + 1);
+                block.0.print_debug(depth + 1);
+            },
+            Stmt::TryCatch(try_block, ident, catch_block) => {
+                println!("{}Try statement", Spaces(depth));
+                try_block.0.print_debug(depth + 1);
+                println!("{}Catch '{}'", Spaces(depth), ident.0);
+                catch_block.0.print_debug(depth + 1);
+            },
+            Stmt::With(expr, ident, block) => {
+                println!("{}With statement as '{}'", Spaces(depth), ident.0);
                 expr.0.print_debug(depth + 1);
                 block.0.print_debug(depth + 1);
             },
+            Stmt::Match(expr, arms) => {
+                println!("{}Match statement", Spaces(depth));
+                expr.0.print_debug(depth + 1);
+                for (pattern, block) in arms {
+                    pattern.0.print_debug(depth + 1);
+                    block.0.print_debug(depth + 2);
+                }
+            },
+            Stmt::Import(path, ident) => {
+                println!("{}Import statement '{}' as '{}'", Spaces(depth), path.0, ident.0);
+            },
             Stmt::Decl(ident, expr) => {
                 println!("{}Declaration statement '{}'", Spaces(depth), ident.0);
                 expr.0.print_debug(depth + 1);
             },
+            Stmt::Class(ident, methods) => {
+                println!("{}Class declaration statement '{}'", Spaces(depth), ident.0);
+                for (name, method) in methods {
+                    println!("{}Method '{}'", Spaces(depth + 1), name.0);
+                    method.0.print_debug(depth + 2);
+                }
+            },
+            Stmt::DeclPattern(pattern, expr) => {
+                println!("{}Destructuring declaration statement", Spaces(depth));
+                pattern.0.print_debug(depth + 1);
+                expr.0.print_debug(depth + 1);
+            },
             Stmt::Return(expr) => {
                 println!("{}Return statement", Spaces(depth));
                 expr.0.print_debug(depth + 1);
@@ -361,10 +562,27 @@ impl Block {
 }
 
 impl Args {
+    /// The fewest and most arguments that the function may be called with, the most being `None` if it has a rest
+    /// parameter.
+    pub fn arity(&self) -> (usize, Option<usize>) {
+        let required = self.0.iter().take_while(|param| param.default.is_none()).count();
+        (required, if self.1.is_some() { None } else { Some(self.0.len()) })
+    }
+
     pub fn print_debug(&self, depth: usize) {
         println!("{}Args", Spaces(depth));
-        for arg in &self.0 {
-            println!("{}Argument '{}'", Spaces(depth + 2), arg.0);
+        for param in &self.0 {
+            match &param.ty {
+                Some(ty) => println!("{}Argument '{}' of type '{}'", Spaces(depth + 2), param.name.0, ty.0),
+                None => println!("{}Argument '{}'", Spaces(depth + 2), param.name.0),
+            }
+            if let Some(default) = &param.default {
+                println!("{}Default", Spaces(depth + 4));
+                default.0.print_debug(depth + 6);
+            }
+        }
+        if let Some(rest) = &self.1 {
+            println!("{}Rest argument '{}'", Spaces(depth + 2), rest.0);
         }
     }
 }
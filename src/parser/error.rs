@@ -2,9 +2,10 @@ use std::fmt;
 use super::{
     Item,
     Lexeme,
+    SrcLoc,
     SrcRef,
 };
-use crate::output;
+use crate::output::{self, DiagnosticStyle, Json};
 
 #[derive(Clone, Debug)]
 pub enum ParseError {
@@ -12,12 +13,23 @@ pub enum ParseError {
     NotAnLValue,
     UnexpectedChar(char),
     CharTooLong,
+    InvalidEscape(char),
+    UnclosedComment,
+    CommentAfterOperand,
+    InvalidUnicodeEscape,
+    InvalidDigit(char, &'static str), // Digit, kind of literal
+    MissingDigits(&'static str),
+    IntTooLarge(&'static str),
     ExpectedDelimiter(char),
     Expected(Item, Item), // Expected, found
     ReservedKeyword(String),
+    PositionalAfterNamed,
+    TooDeep,
     WhileParsing(String, Box<ParseError>),
     At(SrcRef, Box<ParseError>),
     Many(Vec<ParseError>),
+    // The input ended part-way through a construct, so more input may complete it
+    Incomplete(Box<ParseError>),
 }
 
 impl ParseError {
@@ -55,67 +67,131 @@ impl ParseError {
         }
     }
 
+    /// Whether parsing failed only because the input ended part-way through a construct, such as a block that was
+    /// opened but never closed. Interactive prompts use this to ask for more input rather than report the error.
+    pub fn is_incomplete(&self) -> bool {
+        match self {
+            ParseError::Incomplete(_) => true,
+            _ => false,
+        }
+    }
+
+    // Only the first of many errors is considered, since those after it may be artifacts of recovering from it
+    pub(crate) fn hit_eof(&self) -> bool {
+        match self {
+            // Comments that are still open at the end of the input point to where they began
+            ParseError::At(_, err) if match &**err { ParseError::UnclosedComment => true, _ => false } => true,
+            ParseError::At(r, err) => r.start() == SrcLoc::End && match &**err {
+                ParseError::Expected(_, Item::Lexeme(Lexeme::Eof)) => true,
+                ParseError::ExpectedDelimiter(_) => true,
+                _ => false,
+            },
+            ParseError::WhileParsing(_, err) => err.hit_eof(),
+            ParseError::Many(errs) => errs.first().map(|err| err.hit_eof()).unwrap_or(false),
+            ParseError::Incomplete(_) => true,
+            _ => false,
+        }
+    }
+
+    pub(crate) fn classify(self) -> Self {
+        if self.hit_eof() && !self.is_incomplete() {
+            ParseError::Incomplete(Box::new(self))
+        } else {
+            self
+        }
+    }
+
     pub fn while_parsing(self, element: &str) -> Self {
         ParseError::WhileParsing(element.to_string(), Box::new(self))
     }
 
-    pub fn fmt_nice_located(&self, f: &mut fmt::Formatter, src: Option<&str>, depth: usize, r: SrcRef, while_parsing: Vec<String>) -> fmt::Result {
+    /// A description of an error that is not a wrapper around another, given the location that it occurred at.
+    fn describe(&self, r: SrcRef) -> Option<String> {
+        match self {
+            ParseError::NotAnLValue => Some(format!("This is not an l-value and cannot be assigned to.")),
+            ParseError::UnexpectedChar(c) => Some(format!("Unexpected character '{}' in code.", c)),
+            ParseError::CharTooLong => Some(format!("Character literal may only be 1 character long.")),
+            ParseError::UnclosedComment => Some(format!("Block comment is never closed. Are you missing a '*/'?")),
+            ParseError::CommentAfterOperand => Some(format!("'//' starts a comment, not floor division. Use 'math.floor(a / b)' to divide, or end the statement before the comment.")),
+            ParseError::InvalidEscape(c) => Some(format!("Invalid escape sequence '\\{}'. Use '\\\\' for a literal backslash.", c)),
+            ParseError::InvalidUnicodeEscape => Some(format!("Invalid unicode escape. Expected '\\u{{...}}' containing 1 to 6 hexadecimal digits of a valid code point.")),
+            ParseError::InvalidDigit(c, kind) => Some(format!("Invalid digit '{}' in {} literal.", c, kind)),
+            ParseError::MissingDigits(kind) => Some(format!("The {} literal has no digits.", kind)),
+            // Only decimal literals can be floats
+            ParseError::IntTooLarge(kind) => Some(format!("The {} literal is too large for an int.{}", kind,
+                if *kind == "decimal" { " Add '.0' to make it a float." } else { "" },
+            )),
+            ParseError::ExpectedDelimiter(c) => Some(format!("Expected token delimiter. Are you missing a '{}'?", c)),
+            ParseError::Expected(expected, found) => Some(format!("Expected {}, found {}.{}", expected, found,
+                if r.start().start_of_line() && *expected == Item::Lexeme(Lexeme::Semicolon) {
+                    " Did you forget to add a semicolon on the previous line?"
+                } else {
+                    ""
+                },
+            )),
+            ParseError::ReservedKeyword(keyword) => Some(format!("Use of keyword '{}' is not permitted because it is reserved for future use.", keyword)),
+            ParseError::PositionalAfterNamed => Some(format!("Arguments given by position must come before those given by name.")),
+            ParseError::TooDeep => Some(format!("The code is nested too deeply to be parsed.")),
+            _ => None,
+        }
+    }
+
+    pub fn fmt_nice_located(&self, f: &mut fmt::Formatter, src: Option<&str>, depth: usize, style: DiagnosticStyle, r: SrcRef, while_parsing: Vec<String>) -> fmt::Result {
         let indent = output::Repeat(' ', (depth + 1) * 3);
-        writeln!(f, "[ERROR] Parsing error at {}...", r.start())?;
+        writeln!(f, "{} Parsing error at {}...", output::ErrorTag(style), r.start())?;
         for elem in &while_parsing {
             writeln!(f, "{}...while parsing {}...", indent, elem)?;
         }
-        match self {
-            ParseError::NotAnLValue => {
+        match self.describe(r) {
+            Some(msg) => {
                 Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}This is not an l-value and cannot be assigned to.", indent))
+                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1, style))
+                    .and_then(|_| writeln!(f, "{}{}", indent, msg))
             },
-            ParseError::UnexpectedChar(c) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Unexpected character '{}' in code.", indent, c))
-            },
-            ParseError::CharTooLong => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Character literal may only be 1 character long.", indent))
-            },
-            ParseError::ExpectedDelimiter(c) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Expected token delimiter. Are you missing a '{}'?", indent, c))
-            },
-            ParseError::Expected(expected, found) => {
-                Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Expected {}, found {}.{}", indent, expected, found,
-                        if r.start().start_of_line() && *expected == Item::Lexeme(Lexeme::Semicolon) {
-                            " Did you forget to add a semicolon on the previous line?"
-                        } else {
-                            ""
-                        },
-                    ))
-            },
-            ParseError::ReservedKeyword(keyword) => {
+            None => Ok(()),
+        }
+    }
+
+    pub fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, depth: usize, style: DiagnosticStyle, mut while_parsing: Vec<String>) -> fmt::Result {
+        match self {
+            ParseError::WhileParsing(element, err) => {
+                while_parsing.push(element.clone());
                 Ok(())
-                    .and_then(|_| output::fmt_ref(f, r, src, depth + 1))
-                    .and_then(|_| writeln!(f, "{}Use of keyword '{}' is not permitted because it is reserved for future use.", indent, keyword))
+                    .and_then(|_| err.fmt_nice(f, src, depth, style, while_parsing))
             },
+            ParseError::At(r, err) => err.fmt_nice_located(f, src, depth, style, *r, while_parsing),
+            ParseError::Many(errs) => errs.iter().try_for_each(|err| err.fmt_nice(f, src, depth, style, while_parsing.clone())),
+            ParseError::Incomplete(err) => err.fmt_nice(f, src, depth, style, while_parsing),
             _ => Ok(()),
         }
     }
 
-    pub fn fmt_nice(&self, f: &mut fmt::Formatter, src: Option<&str>, depth: usize, mut while_parsing: Vec<String>) -> fmt::Result {
+    /// Append a JSON object describing each error to `errors` (see `ForgeError::to_json`).
+    pub(crate) fn to_json(&self, r: SrcRef, while_parsing: &mut Vec<String>, incomplete: bool, errors: &mut Vec<Json>) {
         match self {
             ParseError::WhileParsing(element, err) => {
                 while_parsing.push(element.clone());
-                Ok(())
-                    .and_then(|_| err.fmt_nice(f, src, depth, while_parsing))
+                err.to_json(r, while_parsing, incomplete, errors);
+                while_parsing.pop();
+            },
+            ParseError::At(r, err) => err.to_json(*r, while_parsing, incomplete, errors),
+            ParseError::Many(errs) => errs.iter().for_each(|err| err.to_json(r, while_parsing, incomplete, errors)),
+            ParseError::Incomplete(err) => err.to_json(r, while_parsing, true, errors),
+            err => {
+                let (expected, found) = match err {
+                    ParseError::Expected(expected, found) => (Some(expected.to_string()), Some(found.to_string())),
+                    _ => (None, None),
+                };
+                errors.push(Json::Object(vec![
+                    ("kind", Json::from(String::from("parse"))),
+                    ("message", Json::from(err.describe(r))),
+                    ("location", Json::span(r)),
+                    ("expected", Json::from(expected)),
+                    ("found", Json::from(found)),
+                    ("while_parsing", Json::Array(while_parsing.iter().cloned().map(Json::from).collect())),
+                    ("incomplete", Json::Bool(incomplete)),
+                ]));
             },
-            ParseError::At(r, err) => err.fmt_nice_located(f, src, depth, *r, while_parsing),
-            ParseError::Many(errs) => errs.iter().try_for_each(|err| err.fmt_nice(f, src, depth, while_parsing.clone())),
-            _ => Ok(()),
         }
     }
 }
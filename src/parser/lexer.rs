@@ -15,9 +15,17 @@ pub enum Lexeme {
     Comma,
     Dot,
     DotDot,
+    Ellipsis,
     Colon,
+    Question,
+    QuestionQuestion,
+    QuestionDot,
     Semicolon,
     Pipe,
+    Amp,
+    Caret,
+    Tilde,
+    FatArrow,
 
     // Multi-character lexemes
     Bang,    BangEq,
@@ -29,12 +37,19 @@ pub enum Lexeme {
     Star,    StarEq,
     Slash,   SlashEq,
     Percent, PercentEq,
+    Shl,     Shr,
 
     // Literals
     Ident(String),
     String(String),
+    // Fragments of an interpolated string: `"a {x} b {y} c"` is lexed as `InterpStart("a ")`, the tokens of `x`,
+    // `InterpMid(" b ")`, the tokens of `y` and finally `InterpEnd(" c")`
+    InterpStart(String),
+    InterpMid(String),
+    InterpEnd(String),
     Char(char),
-    Number(f64),
+    Int(i64),
+    Float(f64),
     True, False,
     Null,
 
@@ -43,13 +58,19 @@ pub enum Lexeme {
     If, Else,
     Break, Return,
     For, While,
+    Try, Catch,
+    With,
+    Import,
+    Match,
     Fn,
+    Class,
     This,
     Var,
     Print, Input,
     Clone,
     Mirror,
     As,
+    Is,
 
     // Misc
     Reserved,
@@ -68,9 +89,17 @@ impl fmt::Display for Lexeme {
             Lexeme::Comma => write!(f, ","),
             Lexeme::Dot => write!(f, "."),
             Lexeme::DotDot => write!(f, ".."),
+            Lexeme::Ellipsis => write!(f, "..."),
             Lexeme::Colon => write!(f, ":"),
+            Lexeme::Question => write!(f, "?"),
+            Lexeme::QuestionQuestion => write!(f, "??"),
+            Lexeme::QuestionDot => write!(f, "?."),
             Lexeme::Semicolon => write!(f, ";"),
             Lexeme::Pipe => write!(f, "|"),
+            Lexeme::Amp => write!(f, "&"),
+            Lexeme::Caret => write!(f, "^"),
+            Lexeme::Tilde => write!(f, "~"),
+            Lexeme::FatArrow => write!(f, "=>"),
 
             Lexeme::Bang =>      write!(f, "!"),
             Lexeme::BangEq =>    write!(f, "!="),
@@ -90,11 +119,17 @@ impl fmt::Display for Lexeme {
             Lexeme::SlashEq =>   write!(f, "/="),
             Lexeme::Percent =>   write!(f, "%"),
             Lexeme::PercentEq => write!(f, "%="),
+            Lexeme::Shl =>       write!(f, "<<"),
+            Lexeme::Shr =>       write!(f, ">>"),
 
             Lexeme::Ident(s) => write!(f, "{}", s),
             Lexeme::String(s) => write!(f, "\"{}\"", s),
+            Lexeme::InterpStart(s) => write!(f, "\"{}{{", s),
+            Lexeme::InterpMid(s) => write!(f, "}}{}{{", s),
+            Lexeme::InterpEnd(s) => write!(f, "}}{}\"", s),
             Lexeme::Char(c) => write!(f, "\"{}\"", c),
-            Lexeme::Number(x) => write!(f, "{}", x),
+            Lexeme::Int(x) => write!(f, "{}", x),
+            Lexeme::Float(x) => write!(f, "{:?}", x),
             Lexeme::True => write!(f, "true"),
             Lexeme::False => write!(f, "false"),
             Lexeme::Null => write!(f, "null"),
@@ -109,7 +144,13 @@ impl fmt::Display for Lexeme {
             Lexeme::Return => write!(f, "return"),
             Lexeme::For => write!(f, "for"),
             Lexeme::While => write!(f, "while"),
+            Lexeme::Try => write!(f, "try"),
+            Lexeme::Catch => write!(f, "catch"),
+            Lexeme::With => write!(f, "with"),
+            Lexeme::Import => write!(f, "import"),
+            Lexeme::Match => write!(f, "match"),
             Lexeme::Fn => write!(f, "fn"),
+            Lexeme::Class => write!(f, "class"),
             Lexeme::This => write!(f, "this"),
             Lexeme::Var => write!(f, "var"),
             Lexeme::Print => write!(f, "print"),
@@ -117,6 +158,7 @@ impl fmt::Display for Lexeme {
             Lexeme::Clone => write!(f, "clone"),
             Lexeme::Mirror => write!(f, "mirror"),
             Lexeme::As => write!(f, "as"),
+            Lexeme::Is => write!(f, "is"),
 
             Lexeme::Reserved => write!(f, "<reserved>"),
             Lexeme::Eof => write!(f, "EOF"),
@@ -124,16 +166,29 @@ impl fmt::Display for Lexeme {
     }
 }
 
-const RESERVED_KEYWORDS: [&'static str; 34] = [
-    "self",   "Self",     "extern", "move",     "async",
-    "mut",      "enum",   "continue", "string", "yield",
-    "bool",     "const",  "mut",      "loop",   "pub",
-    "priv",     "ref",    "match",    "use",    "where",
-    "do",       "clone",  "type",     "class",  "base",
-    "super",    "struct", "trait",    "impl",   "of",
-    "with",     "when",   "then",     "await",
+// `bool` and `string` are not reserved, since they are the names of types that parameters may be annotated with, and
+// nor is `self`, which is the variable through which methods refer to the maps they belong to
+const RESERVED_KEYWORDS: [&'static str; 28] = [
+    "Self",     "extern", "move",     "async",
+    "mut",      "enum",   "continue", "yield",
+    "const",  "mut",      "loop",   "pub",
+    "priv",     "ref",    "use",      "where",  "do",
+    "clone",    "type",   "base",     "super",
+    "struct",   "trait",  "impl",     "of",
+    "when",     "then",   "await",
 ];
 
+impl Lexeme {
+    /// Whether an operand can end with this lexeme, such that a binary operator could follow it.
+    fn is_operand_end(&self) -> bool {
+        matches!(self,
+            Lexeme::Ident(_) | Lexeme::String(_) | Lexeme::InterpEnd(_) | Lexeme::Char(_)
+            | Lexeme::Int(_) | Lexeme::Float(_) | Lexeme::True | Lexeme::False | Lexeme::Null | Lexeme::This
+            | Lexeme::RParen | Lexeme::RBrack
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Token(pub Lexeme, pub SrcRef);
 
@@ -147,6 +202,8 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
     enum State {
         Default,
         Comment,
+        // The number of block comments that have been opened but not yet closed
+        BlockComment(usize),
         String(char, bool),
         Number,
         Ident,
@@ -155,6 +212,12 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
     let mut strbuf = String::new();
     let mut seen_dot = false;
     let mut start_loc = SrcLoc::start();
+    // For each interpolation we're inside, the number of unclosed braces opened within it
+    let mut interps: Vec<usize> = vec![];
+    // Whether the string being lexed continues on from an interpolation
+    let mut resumed = false;
+    // The location of the backslash that began the escape sequence being lexed
+    let mut escape_loc = SrcLoc::start();
 
     loop {
         let c = chars.clone().next().unwrap_or('\0');
@@ -165,15 +228,51 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                 ' ' | '\r' | '\t' | '\n' => was_whitespace = true,
                 '(' => tokens.push(Token(Lexeme::LParen, SrcRef::single(loc))),
                 ')' => tokens.push(Token(Lexeme::RParen, SrcRef::single(loc))),
-                '{' => tokens.push(Token(Lexeme::LBrace, SrcRef::single(loc))),
-                '}' => tokens.push(Token(Lexeme::RBrace, SrcRef::single(loc))),
+                '{' => {
+                    if let Some(depth) = interps.last_mut() {
+                        *depth += 1;
+                    }
+                    tokens.push(Token(Lexeme::LBrace, SrcRef::single(loc)));
+                },
+                '}' => match interps.last_mut() {
+                    Some(0) => {
+                        interps.pop();
+                        strbuf.clear();
+                        start_loc = loc;
+                        resumed = true;
+                        state = State::String('"' /*"*/, false);
+                    },
+                    depth => {
+                        if let Some(depth) = depth {
+                            *depth -= 1;
+                        }
+                        tokens.push(Token(Lexeme::RBrace, SrcRef::single(loc)));
+                    },
+                },
                 '[' => tokens.push(Token(Lexeme::LBrack, SrcRef::single(loc))),
                 ']' => tokens.push(Token(Lexeme::RBrack, SrcRef::single(loc))),
                 ',' => tokens.push(Token(Lexeme::Comma, SrcRef::single(loc))),
                 '|' => tokens.push(Token(Lexeme::Pipe, SrcRef::single(loc))),
+                '&' => tokens.push(Token(Lexeme::Amp, SrcRef::single(loc))),
+                '^' => tokens.push(Token(Lexeme::Caret, SrcRef::single(loc))),
+                '~' => tokens.push(Token(Lexeme::Tilde, SrcRef::single(loc))),
                 ':' => tokens.push(Token(Lexeme::Colon, SrcRef::single(loc))),
+                '?' => match chars.clone().nth(1) {
+                    Some('?') => {
+                        tokens.push(Token(Lexeme::QuestionQuestion, SrcRef::double(loc)));
+                        incr = 2;
+                    },
+                    Some('.') => {
+                        tokens.push(Token(Lexeme::QuestionDot, SrcRef::double(loc)));
+                        incr = 2;
+                    },
+                    _ => tokens.push(Token(Lexeme::Question, SrcRef::single(loc))),
+                },
                 ';' => tokens.push(Token(Lexeme::Semicolon, SrcRef::single(loc))),
-                '.' => if chars.clone().nth(1) == Some('.') {
+                '.' => if chars.clone().nth(1) == Some('.') && chars.clone().nth(2) == Some('.') {
+                    tokens.push(Token(Lexeme::Ellipsis, SrcRef::many(loc, loc.next_col(true).next_col(true).next_col(true))));
+                    incr = 3;
+                } else if chars.clone().nth(1) == Some('.') {
                     tokens.push(Token(Lexeme::DotDot, SrcRef::double(loc)));
                     incr = 2;
                 } else {
@@ -188,18 +287,27 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                 '=' => if chars.clone().nth(1) == Some('=') {
                     tokens.push(Token(Lexeme::Eq, SrcRef::double(loc)));
                     incr = 2;
+                } else if chars.clone().nth(1) == Some('>') {
+                    tokens.push(Token(Lexeme::FatArrow, SrcRef::double(loc)));
+                    incr = 2;
                 } else {
                     tokens.push(Token(Lexeme::Assign, SrcRef::single(loc)));
                 },
                 '>' => if chars.clone().nth(1) == Some('=') {
                     tokens.push(Token(Lexeme::GreaterEq, SrcRef::double(loc)));
                     incr = 2;
+                } else if chars.clone().nth(1) == Some('>') {
+                    tokens.push(Token(Lexeme::Shr, SrcRef::double(loc)));
+                    incr = 2;
                 } else {
                     tokens.push(Token(Lexeme::Greater, SrcRef::single(loc)));
                 },
                 '<' => if chars.clone().nth(1) == Some('=') {
                     tokens.push(Token(Lexeme::LessEq, SrcRef::double(loc)));
                     incr = 2;
+                } else if chars.clone().nth(1) == Some('<') {
+                    tokens.push(Token(Lexeme::Shl, SrcRef::double(loc)));
+                    incr = 2;
                 } else {
                     tokens.push(Token(Lexeme::Less, SrcRef::single(loc)));
                 },
@@ -224,6 +332,22 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                 '/' => if chars.clone().nth(1) == Some('=') {
                     tokens.push(Token(Lexeme::SlashEq, SrcRef::double(loc)));
                     incr = 2;
+                } else if chars.clone().nth(1) == Some('/') {
+                    // `a // b` is floor division in other languages, so a comment straight after an operand on the
+                    // same line is more likely a mistake than a remark
+                    if tokens.last().is_some_and(|Token(lexeme, r)| lexeme.is_operand_end()
+                        && r.limit().pos().map(|(line, _)| line) == loc.pos().map(|(line, _)| line))
+                    {
+                        errors.push(ParseError::At(
+                            SrcRef::double(loc),
+                            Box::new(ParseError::CommentAfterOperand),
+                        ));
+                    }
+                    state = State::Comment;
+                } else if chars.clone().nth(1) == Some('*') {
+                    start_loc = loc;
+                    state = State::BlockComment(1);
+                    incr = 2;
                 } else {
                     tokens.push(Token(Lexeme::Slash, SrcRef::single(loc)));
                 },
@@ -237,6 +361,7 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                 '"' => /*"*/ {
                     strbuf.clear();
                     start_loc = loc;
+                    resumed = false;
                     state = State::String('\"' /*"*/, false);
                 },
                 '\'' => {
@@ -251,7 +376,15 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                     state = State::Number;
                     incr = 0;
                 },
-                '\0' => break,
+                '\0' => {
+                    if interps.len() > 0 {
+                        errors.push(ParseError::At(
+                            SrcRef::end(),
+                            Box::new(ParseError::ExpectedDelimiter('}')),
+                        ));
+                    }
+                    break;
+                },
                 c => if c.is_alphanumeric() || c == '_' {
                     strbuf.clear();
                     start_loc = loc;
@@ -268,13 +401,66 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                 '\n' | '\0' => state = State::Default,
                 _ => {},
             },
+            // Block comments nest, so that code containing them can itself be commented out
+            State::BlockComment(depth) => {
+                was_whitespace = true;
+                match (c, chars.clone().nth(1)) {
+                    ('/', Some('*')) => {
+                        state = State::BlockComment(depth + 1);
+                        incr = 2;
+                    },
+                    ('*', Some('/')) => {
+                        state = if depth == 1 { State::Default } else { State::BlockComment(depth - 1) };
+                        incr = 2;
+                    },
+                    ('\0', _) => {
+                        errors.push(ParseError::At(
+                            SrcRef::double(start_loc),
+                            Box::new(ParseError::UnclosedComment),
+                        ));
+                        break;
+                    },
+                    _ => {},
+                }
+            },
             State::String(delim, escaped) => match c {
-
                 '\\' if !escaped => {
+                    escape_loc = loc;
                     state = State::String(delim, true);
                 },
-                'n' if escaped => {
-                    strbuf.push('\n');
+                'u' if escaped => {
+                    // `\u{...}` gives a character by its code point, in 1 to 6 hexadecimal digits
+                    let rest = chars.clone().skip(1).take(8).collect::<String>();
+                    let c = Some(&rest)
+                        .filter(|rest| rest.starts_with('{'))
+                        .and_then(|rest| rest.find('}'))
+                        .filter(|end| (2..=7).contains(end))
+                        .and_then(|end| u32::from_str_radix(&rest[1..end], 16).ok().map(|x| (x, end)))
+                        .and_then(|(x, end)| std::char::from_u32(x).map(|c| (c, end)));
+                    match c {
+                        Some((c, end)) => {
+                            strbuf.push(c);
+                            incr = end + 2;
+                        },
+                        None => errors.push(ParseError::At(
+                            SrcRef::many(escape_loc, loc.next_col(true)),
+                            Box::new(ParseError::InvalidUnicodeEscape),
+                        )),
+                    }
+                    state = State::String(delim, false);
+                },
+                c if escaped && c != '\0' => {
+                    match c {
+                        'n' => strbuf.push('\n'),
+                        't' => strbuf.push('\t'),
+                        'r' => strbuf.push('\r'),
+                        '0' => strbuf.push('\0'),
+                        '\\' | '"' /*"*/ | '\'' | '{' | '}' => strbuf.push(c),
+                        c => errors.push(ParseError::At(
+                            SrcRef::many(escape_loc, loc.next_col(true)),
+                            Box::new(ParseError::InvalidEscape(c)),
+                        )),
+                    }
                     state = State::String(delim, false);
                 },
                 '\0' => {
@@ -284,9 +470,16 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                     ));
                     break;
                 },
+                // `{}` and `{:...}` cannot begin an expression, so they are left as they are for `format`
+                '{' if delim == '"' /*"*/ && !escaped && !matches!(chars.clone().nth(1), Some('}') | Some(':')) => {
+                    let lexeme = if resumed { Lexeme::InterpMid(strbuf.clone()) } else { Lexeme::InterpStart(strbuf.clone()) };
+                    tokens.push(Token(lexeme, SrcRef::many(start_loc, loc.next_col(true))));
+                    interps.push(0);
+                    state = State::Default;
+                },
                 c if c == delim && !escaped => {
                     match delim {
-                        '\'' => if strbuf.len() == 1 {
+                        '\'' => if strbuf.chars().count() == 1 {
                             tokens.push(Token(Lexeme::Char(strbuf.char_indices().next().unwrap().1), SrcRef::many(start_loc, loc.next_col(true))));
                         } else {
                             errors.push(ParseError::At(
@@ -294,8 +487,11 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                                 Box::new(ParseError::CharTooLong),
                             ));
                         },
-                        '"' /*"*/ => tokens.push(Token(Lexeme::String(strbuf.clone()), SrcRef::many(start_loc, loc.next_col(true)))),
-                        _ => unimplemented!(),
+                        // Strings are the only other literals with delimiters
+                        _ => {
+                            let lexeme = if resumed { Lexeme::InterpEnd(strbuf.clone()) } else { Lexeme::String(strbuf.clone()) };
+                            tokens.push(Token(lexeme, SrcRef::many(start_loc, loc.next_col(true))));
+                        },
                     }
                     state = State::Default;
                 },
@@ -304,21 +500,19 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                     state = State::String(delim, false);
                 },
             },
-            State::Number => match c {
-                '0' ... '9' => strbuf.push(c),
-                '.' => if !seen_dot && chars.clone().nth(1).map(|c| c.is_ascii_digit()).unwrap_or(false) {
-                    strbuf.push(c);
-                    seen_dot = true;
-                } else {
-                    tokens.push(Token(Lexeme::Number(strbuf.parse().unwrap()), SrcRef::many(start_loc, loc)));
-                    state = State::Default;
-                    incr = 0;
-                },
-                _ => {
-                    tokens.push(Token(Lexeme::Number(strbuf.parse().unwrap()), SrcRef::many(start_loc, loc)));
-                    state = State::Default;
-                    incr = 0;
-                },
+            // Letters are included so that malformed literals, such as `0b102` or `12ab`, are reported as a whole
+            State::Number => if c.is_alphanumeric() || c == '_' {
+                strbuf.push(c);
+            } else if c == '.' && !seen_dot && chars.clone().nth(1).map(|c| c.is_ascii_digit()).unwrap_or(false) {
+                strbuf.push(c);
+                seen_dot = true;
+            } else {
+                match parse_number(&strbuf, start_loc) {
+                    Ok(lexeme) => tokens.push(Token(lexeme, SrcRef::many(start_loc, loc))),
+                    Err(err) => errors.push(err),
+                }
+                state = State::Default;
+                incr = 0;
             },
             State::Ident => if c.is_alphanumeric() || c == '_' {
                 strbuf.push(c);
@@ -334,7 +528,13 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                     "return" => Lexeme::Return,
                     "for" => Lexeme::For,
                     "while" => Lexeme::While,
+                    "try" => Lexeme::Try,
+                    "catch" => Lexeme::Catch,
+                    "with" => Lexeme::With,
+                    "import" => Lexeme::Import,
+                    "match" => Lexeme::Match,
                     "fn" => Lexeme::Fn,
+                    "class" => Lexeme::Class,
                     "this" => Lexeme::This,
                     "var" => Lexeme::Var,
                     "var" => Lexeme::Var,
@@ -344,6 +544,7 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
                     "clone" => Lexeme::Clone,
                     "mirror" => Lexeme::Mirror,
                     "as" => Lexeme::As,
+                    "is" => Lexeme::Is,
                     "true" => Lexeme::True,
                     "false" => Lexeme::False,
                     "null" => Lexeme::Null,
@@ -379,3 +580,55 @@ pub fn lex(code: &str) -> ParseResult<Vec<Token>> {
         Err(ParseError::Many(errors))
     }
 }
+
+/// Parse a numeric literal, which may be hexadecimal (`0xFF`), octal (`0o755`) or binary (`0b1010`), and may contain
+/// `_` separators after its first digit. Literals with a decimal point are floats, and all others are ints. Errors point
+/// at the first invalid character, given the location of the literal's first character.
+fn parse_number(s: &str, start: SrcLoc) -> ParseResult<Lexeme> {
+    let (radix, name, prefix_len) = match s.get(0..2) {
+        Some("0x") => (16, "hexadecimal", 2),
+        Some("0o") => (8, "octal", 2),
+        Some("0b") => (2, "binary", 2),
+        _ => (10, "decimal", 0),
+    };
+
+    // `None` once the value no longer fits in an int
+    let mut val = Some(0i64);
+    let mut digits = String::new();
+    let mut loc = start;
+    for (i, c) in s.chars().enumerate() {
+        if i >= prefix_len {
+            match c {
+                '_' => {},
+                '.' if radix == 10 => digits.push(c),
+                c => match c.to_digit(radix) {
+                    Some(digit) => {
+                        val = val
+                            .and_then(|val| val.checked_mul(radix as i64))
+                            .and_then(|val| val.checked_add(digit as i64));
+                        digits.push(c);
+                    },
+                    None => return Err(ParseError::At(
+                        SrcRef::single(loc),
+                        Box::new(ParseError::InvalidDigit(c, name)),
+                    )),
+                },
+            }
+        }
+        loc = loc.next_col(true);
+    }
+
+    if digits.is_empty() {
+        Err(ParseError::At(
+            SrcRef::many(start, loc),
+            Box::new(ParseError::MissingDigits(name)),
+        ))
+    } else if digits.contains('.') {
+        Ok(Lexeme::Float(digits.parse().unwrap()))
+    } else {
+        val.map(Lexeme::Int).ok_or_else(|| ParseError::At(
+            SrcRef::many(start, loc),
+            Box::new(ParseError::IntTooLarge(name)),
+        ))
+    }
+}
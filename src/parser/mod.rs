@@ -42,7 +42,7 @@ pub struct Parser {
 impl Parser {
     pub fn new(code: &str) -> ParseResult<Self> {
         Ok(Self {
-            tokens: lex(code)?,
+            tokens: lex(code).map_err(ParseError::classify)?,
             code: Rc::new(code.to_string()),
         })
     }
@@ -65,6 +65,17 @@ impl Parser {
         }
         */
 
-        ParseCtx::new(self.tokens.iter(), self.code.clone()).read_stmts_full()
+        let (stmts, mut errors) = self.parse_stmts_recovering();
+        match errors.len() {
+            0 => Ok(stmts),
+            1 => Err(errors.remove(0).classify()),
+            _ => Err(ParseError::Many(errors).classify()),
+        }
+    }
+
+    /// Parse as many statements as possible, skipping those that fail to parse and returning their errors alongside
+    /// the statements that did parse.
+    pub fn parse_stmts_recovering(&self) -> (Vec<Node<Stmt>>, Vec<ParseError>) {
+        ParseCtx::new(self.tokens.iter(), self.code.clone()).read_stmts_recovering()
     }
 }
@@ -1,8 +1,11 @@
 use std::{
     slice,
     fmt,
+    mem,
     rc::Rc,
+    cell::Cell,
 };
+use crate::stack;
 use super::{
     ParseError,
     ParseResult,
@@ -12,10 +15,12 @@ use super::{
     ast::{
         Node,
         Expr,
-        LVal,
         Stmt,
         Block,
         Args,
+        Param,
+        StringPart,
+        Pattern,
     },
 };
 
@@ -27,6 +32,8 @@ pub enum Item {
     Stmt,
     Assignment,
     LVal,
+    Module,
+    Pattern,
     End,
 }
 
@@ -42,6 +49,8 @@ impl fmt::Display for Item {
             Item::Stmt => write!(f, "statement"),
             Item::Assignment => write!(f, "assignment"),
             Item::LVal => write!(f, "l-value"),
+            Item::Module => write!(f, "module path"),
+            Item::Pattern => write!(f, "pattern"),
             Item::End => write!(f, "end of input"),
         }
     }
@@ -54,10 +63,100 @@ fn expected(expected: Item, found: Item, src_ref: SrcRef) -> ParseError {
     )
 }
 
+type UnaryMaker = fn(SrcRef, Box<Node<Expr>>) -> Expr;
+type BinaryMaker = fn(SrcRef, Box<Node<Expr>>, Box<Node<Expr>>) -> Expr;
+
+enum Level {
+    // Prefix operators. If nested, the operand may itself begin with an operator of the same level.
+    Prefix(&'static [(Lexeme, UnaryMaker)], bool),
+    // Left-associative infix operators
+    Infix(&'static [(Lexeme, BinaryMaker)]),
+}
+
+// Operator precedence, from loosest to tightest binding. Operands of the last level are calls, accesses or primaries.
+const PRECEDENCE: &[Level] = &[
+    Level::Infix(&[
+        (Lexeme::QuestionQuestion, Expr::BinaryCoalesce),
+    ]),
+    Level::Infix(&[
+        (Lexeme::And, Expr::BinaryAnd),
+        (Lexeme::Or, Expr::BinaryOr),
+        (Lexeme::Xor, Expr::BinaryXor),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Eq, Expr::BinaryEq),
+        (Lexeme::BangEq, Expr::BinaryNotEq),
+        (Lexeme::Is, Expr::BinaryIs),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Greater, Expr::BinaryGreater),
+        (Lexeme::GreaterEq, Expr::BinaryGreaterEq),
+        (Lexeme::Less, Expr::BinaryLess),
+        (Lexeme::LessEq, Expr::BinaryLessEq),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Pipe, Expr::BinaryBitOr),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Caret, Expr::BinaryBitXor),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Amp, Expr::BinaryBitAnd),
+    ]),
+    Level::Prefix(&[
+        (Lexeme::Input, Expr::UnaryInput),
+        (Lexeme::Clone, Expr::UnaryClone),
+        (Lexeme::Mirror, Expr::UnaryMirror),
+    ], true),
+    Level::Infix(&[
+        (Lexeme::DotDot, Expr::BinaryRange),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Shl, Expr::BinaryShl),
+        (Lexeme::Shr, Expr::BinaryShr),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Plus, Expr::BinaryAdd),
+        (Lexeme::Minus, Expr::BinarySub),
+    ]),
+    Level::Infix(&[
+        (Lexeme::Star, Expr::BinaryMul),
+        (Lexeme::Slash, Expr::BinaryDiv),
+        (Lexeme::Percent, Expr::BinaryRem),
+    ]),
+    Level::Prefix(&[
+        (Lexeme::Bang, Expr::UnaryNot),
+        (Lexeme::Minus, Expr::UnaryNeg),
+        (Lexeme::Tilde, Expr::UnaryBitNot),
+    ], false),
+    Level::Infix(&[
+        (Lexeme::As, Expr::BinaryAs),
+    ]),
+];
+
+// The level at which parameter defaults are read, just tighter than `|`, which would otherwise be taken as the end of
+// the parameters of a closure
+fn default_level() -> usize {
+    PRECEDENCE
+        .iter()
+        .position(|level| match level {
+            Level::Infix(ops) => ops.iter().any(|(op, _)| *op == Lexeme::Pipe),
+            Level::Prefix(_, _) => false,
+        })
+        .map(|pipe| pipe + 1)
+        .expect("`|` has a level of precedence")
+}
+
 #[derive(Clone)]
 pub struct ParseCtx<'a> {
     tokens: slice::Iter<'a, Token>,
     code: Rc<String>,
+    // Where the code became too deeply nested to parse, if it has. This is shared with the copies made to try each
+    // alternative, so that they all give up at once rather than each going as deep as the stack allows.
+    too_deep: Rc<Cell<Option<SrcRef>>>,
+    // The statements that failed to parse and were skipped. Each copy has its own, so that those found while trying
+    // an alternative that is not taken are forgotten with it.
+    errors: Vec<ParseError>,
 }
 
 impl<'a> ParseCtx<'a> {
@@ -65,6 +164,8 @@ impl<'a> ParseCtx<'a> {
         Self {
             tokens,
             code,
+            too_deep: Rc::new(Cell::new(None)),
+            errors: Vec::new(),
         }
     }
 
@@ -85,6 +186,17 @@ impl<'a> ParseCtx<'a> {
         self.tokens.clone().next().unwrap_or(&Token(Lexeme::Eof, SrcRef::end())).clone()
     }
 
+    // Nested code is parsed recursively, so code nested deeply enough would otherwise overflow the stack
+    fn check_depth(&self) -> ParseResult<()> {
+        if self.too_deep.get().is_none() && !stack::has_room() {
+            self.too_deep.set(Some(self.peek().1));
+        }
+        match self.too_deep.get() {
+            Some(r) => Err(ParseError::At(r, Box::new(ParseError::TooDeep))),
+            None => Ok(()),
+        }
+    }
+
     fn read_ident(&mut self) -> ParseResult<Node<String>> {
         match self.peek() {
             Token(Lexeme::Ident(s), r) => {
@@ -97,7 +209,8 @@ impl<'a> ParseCtx<'a> {
 
     fn read_primary(&mut self) -> ParseResult<(Node<Expr>, Option<ParseError>)> {
         let expr = match self.peek() {
-            Token(Lexeme::Number(x), r) => Node(Expr::LiteralNumber(x), r),
+            Token(Lexeme::Int(x), r) => Node(Expr::LiteralInt(x), r),
+            Token(Lexeme::Float(x), r) => Node(Expr::LiteralFloat(x), r),
             Token(Lexeme::String(s), r) => Node(Expr::LiteralString(s), r),
             Token(Lexeme::Char(c), r) => Node(Expr::LiteralChar(c), r),
             Token(Lexeme::True, r) => Node(Expr::LiteralBoolean(true), r),
@@ -116,6 +229,12 @@ impl<'a> ParseCtx<'a> {
                 *self = this;
                 return Ok((fn_expr, Some(err)));
             },
+            Token(Lexeme::InterpStart(_), _r) => {
+                let mut this = self.clone();
+                let (string_expr, err) = this.read_interpolated_string()?;
+                *self = this;
+                return Ok((string_expr, Some(err)));
+            },
             Token(Lexeme::LBrack, _r) => {
                 // Try reading list first
                 let mut this = self.clone();
@@ -156,10 +275,11 @@ impl<'a> ParseCtx<'a> {
         loop {
             let mut this = self.clone();
             match this.read_member() {
-                Ok((dot_r, Node(ident, r), err)) => {
+                Ok((dot_r, optional, Node(ident, r), err)) => {
                     *self = this;
                     let r_union = expr.1.union(&r).union(&dot_r);
-                    expr = Node(Expr::DotAccess(dot_r, Box::new(expr), Node(ident, r)), r_union);
+                    let make = if optional { Expr::OptionalAccess } else { Expr::DotAccess };
+                    expr = Node(make(dot_r, Box::new(expr), Node(ident, r)), r_union);
                     max_err = err.max(max_err);
                     continue;
                 },
@@ -188,246 +308,111 @@ impl<'a> ParseCtx<'a> {
         loop {
             let mut this = self.clone();
             match this.read_params() {
-                Ok((Node(params, params_r), err)) => {
+                Ok((Node(params, params_r), named, err)) => {
                     *self = this;
                     let r_union = params
                         .iter()
+                        .chain(named.iter().map(|(_, param)| param))
                         .fold(SrcRef::empty(), |r, p| p.1.union(&r));
-                    expr = Node(Expr::Call(params_r, Box::new(expr), Node(params, params_r)), r_union);
+                    expr = Node(Expr::Call(params_r, Box::new(expr), Node(params, params_r), named), r_union);
                     max_err = err.max(max_err);
+                    continue;
                 },
-                Err(err) => return Ok((expr, err.max(max_err))),
+                Err(err) => max_err = err.max(max_err),
             };
-        }
-    }
 
-    fn read_as(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_call()?;
-
-        loop {
-            match self.peek() {
-                Token(Lexeme::As, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_call()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryAs(r, Box::new(expr), Box::new(operand)), r_union);
+            // The result of a call may itself be accessed, as in `expect(x).to_equal(y)`
+            let mut this = self.clone();
+            match this.read_member() {
+                Ok((dot_r, optional, Node(ident, r), err)) => {
+                    *self = this;
+                    let r_union = expr.1.union(&r).union(&dot_r);
+                    let make = if optional { Expr::OptionalAccess } else { Expr::DotAccess };
+                    expr = Node(make(dot_r, Box::new(expr), Node(ident, r)), r_union);
                     max_err = err.max(max_err);
+                    continue;
                 },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
-        }
-    }
-
-    fn read_unary(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        Ok(match self.peek() {
-            Token(Lexeme::Bang, r) => {
-                self.advance();
-                let (operand, err) = self.read_as()?;
-                let r_union = r.union(&operand.1);
-                (Node(Expr::UnaryNot(r, Box::new(operand)), r_union), err)
-            },
-            Token(Lexeme::Minus, r) => {
-                self.advance();
-                let (operand, err) = self.read_as()?;
-                let r_union = r.union(&operand.1);
-                (Node(Expr::UnaryNeg(r, Box::new(operand)), r_union), err)
-            },
-            _ => self.read_as()?,
-        })
-    }
-
-    fn read_multiplication(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_unary()?;
+                Err(err) => max_err = err.max(max_err),
+            }
 
-        loop {
-            match self.peek() {
-                Token(Lexeme::Star, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryMul(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Slash, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryDiv(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Percent, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryRem(r, Box::new(expr), Box::new(operand)), r_union);
+            let mut this = self.clone();
+            match this.read_index() {
+                Ok((dot_r, index_expr, err)) => {
+                    *self = this;
+                    let r_union = expr.1.union(&index_expr.1).union(&dot_r);
+                    expr = Node(Expr::Index(dot_r, Box::new(expr), Box::new(index_expr)), r_union);
                     max_err = err.max(max_err);
+                    continue;
                 },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
-        }
-    }
-
-    fn read_addition(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_multiplication()?;
+                Err(err) => max_err = err.max(max_err),
+            }
 
-        loop {
-            match self.peek() {
-                Token(Lexeme::Plus, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_multiplication()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryAdd(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Minus, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_multiplication()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinarySub(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
+            return Ok((expr, max_err));
         }
     }
 
-    fn read_high_binary(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_addition()?;
-
-        loop {
-            match self.peek() {
-                Token(Lexeme::DotDot, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_addition()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryRange(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
+    fn read_operators(&mut self, level: usize) -> ParseResult<(Node<Expr>, ParseError)> {
+        self.check_depth()?;
+        match PRECEDENCE.get(level) {
+            None => self.read_call(),
+            Some(Level::Prefix(ops, nested)) => match self.peek() {
+                Token(l, r) => match ops.iter().find(|(op, _)| *op == l) {
+                    Some((_, make)) => {
+                        self.advance();
+                        let (operand, err) = self.read_operators(if *nested { level } else { level + 1 })?;
+                        let r_union = r.union(&operand.1);
+                        Ok((Node(make(r, Box::new(operand)), r_union), err))
+                    },
+                    None => self.read_operators(level + 1),
                 },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
-        }
-    }
-
-    fn read_mid_unary(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        Ok(match self.peek() {
-            Token(Lexeme::Input, r) => {
-                self.advance();
-                let (operand, err) = self.read_mid_unary()?;
-                let r_union = r.union(&operand.1);
-                (Node(Expr::UnaryInput(r, Box::new(operand)), r_union), err)
             },
-            Token(Lexeme::Clone, r) => {
-                self.advance();
-                let (operand, err) = self.read_mid_unary()?;
-                let r_union = r.union(&operand.1);
-                (Node(Expr::UnaryClone(r, Box::new(operand)), r_union), err)
+            Some(Level::Infix(ops)) => {
+                let (mut expr, mut max_err) = self.read_operators(level + 1)?;
+
+                loop {
+                    let Token(l, r) = self.peek();
+                    match ops.iter().find(|(op, _)| *op == l) {
+                        Some((_, make)) => {
+                            self.advance();
+                            let (operand, err) = self.read_operators(level + 1)?;
+                            let r_union = r.union(&expr.1).union(&operand.1);
+                            expr = Node(make(r, Box::new(expr), Box::new(operand)), r_union);
+                            max_err = err.max(max_err);
+                        },
+                        None => return Ok((expr, max_err)),
+                    }
+                }
             },
-            Token(Lexeme::Mirror, r) => {
-                self.advance();
-                let (operand, err) = self.read_mid_unary()?;
-                let r_union = r.union(&operand.1);
-                (Node(Expr::UnaryMirror(r, Box::new(operand)), r_union), err)
-            },
-            _ => self.read_high_binary()?,
-        })
-    }
-
-    fn read_comparison(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_mid_unary()?;
-
-        loop {
-            match self.peek() {
-                Token(Lexeme::Greater, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_mid_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryGreater(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::GreaterEq, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_mid_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryGreaterEq(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Less, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_mid_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryLess(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::LessEq, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_mid_unary()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryLessEq(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
         }
     }
 
-    fn read_equivalence(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_comparison()?;
+    // `cond ? a : b` binds more loosely than any operator but more tightly than assignment, and nests to the right
+    fn read_conditional(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
+        const ELEMENT: &'static str = "conditional expression";
 
-        loop {
-            match self.peek() {
-                Token(Lexeme::Eq, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_comparison()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryEq(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::BangEq, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_comparison()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryNotEq(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
-        }
-    }
+        let (cond, max_err) = self.read_operators(0)?;
 
-    fn read_logical(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
-        let (mut expr, mut max_err) = self.read_equivalence()?;
+        let r = match self.peek() {
+            Token(Lexeme::Question, r) => { self.advance(); r },
+            _ => return Ok((cond, max_err)),
+        };
 
-        loop {
-            match self.peek() {
-                Token(Lexeme::And, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_equivalence()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryAnd(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Or, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_equivalence()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryOr(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(Lexeme::Xor, r) => {
-                    self.advance();
-                    let (operand, err) = self.read_equivalence()?;
-                    let r_union = r.union(&expr.1).union(&operand.1);
-                    expr = Node(Expr::BinaryXor(r, Box::new(expr), Box::new(operand)), r_union);
-                    max_err = err.max(max_err);
-                },
-                Token(_l, _r) => return Ok((expr, max_err)),
-            };
+        let (then, err) = self.read_conditional().map_err(|err| err.max(max_err.clone()).while_parsing(ELEMENT))?;
+        let max_err = err.max(max_err);
+
+        match self.peek() {
+            Token(Lexeme::Colon, _) => self.advance(),
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Colon), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
         }
+
+        let (other, err) = self.read_conditional().map_err(|err| err.max(max_err.clone()).while_parsing(ELEMENT))?;
+        let r_union = cond.1.union(&r).union(&other.1);
+        Ok((Node(Expr::Ternary(r, Box::new(cond), Box::new(then), Box::new(other)), r_union), err.max(max_err)))
     }
 
     fn read_assignment(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
         let mut this = self.clone();
-        let (Node(expr, expr_r), max_err) = match this.read_logical() {
+        let (Node(expr, expr_r), max_err) = match this.read_conditional() {
             Ok((expr, err)) => {
                 *self = this;
                 (expr, err)
@@ -439,42 +424,42 @@ impl<'a> ParseCtx<'a> {
             Token(Lexeme::Assign, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinaryAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
             Token(Lexeme::PlusEq, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinaryAddAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
             Token(Lexeme::MinusEq, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinarySubAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
             Token(Lexeme::StarEq, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinaryMulAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
             Token(Lexeme::SlashEq, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinaryDivAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
             Token(Lexeme::PercentEq, r) => {
                 self.advance();
                 let lvalue = Node(expr, expr_r).into_lvalue(r.union(&expr_r)).map_err(|err| err.max(max_err.clone()))?;
-                let (operand, err) = self.read_logical()?;
+                let (operand, err) = self.read_conditional()?;
                 let r_union = r.union(&expr_r).union(&operand.1);
                 Ok((Node(Expr::BinaryRemAssign(r, lvalue, Box::new(operand)), r_union), err.max(max_err)))
             },
@@ -482,40 +467,6 @@ impl<'a> ParseCtx<'a> {
         }
     }
 
-    fn read_lvalue(&mut self) -> ParseResult<(Node<LVal>, ParseError)> {
-        const ELEMENT: &'static str = "lvalue";
-
-        let mut this = self.clone();
-        let max_err = match this.read_index() {
-            Ok((r, index, err)) => {
-                *self = this;
-                unimplemented!();
-                //return Ok((index, err))
-            },
-            Err(err) => err,
-        };
-
-        let mut this = self.clone();
-        let max_err = match this.read_member() {
-            Ok((r, member, err)) => {
-                *self = this;
-                unimplemented!();
-                //return Ok((member, err))
-            },
-            Err(err) => err.max(max_err),
-        };
-
-        let max_err = match self.read_ident() {
-            Ok(ident) => {
-                return Ok((Node(LVal::Local(Node(ident.0, ident.1)), ident.1), ParseError::Phoney))
-            },
-            Err(err) => err.while_parsing(ELEMENT).max(max_err),
-        };
-
-        let next = self.peek();
-        Err(expected(Item::LVal, Item::Lexeme(next.0), next.1).max(max_err))
-    }
-
     fn read_expr(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
         const ELEMENT: &'static str = "expression";
 
@@ -525,7 +476,7 @@ impl<'a> ParseCtx<'a> {
                 *self = this;
                 Ok((expr, err))
             },
-            Err(err) => self.read_logical().map_err(|err| err.while_parsing(ELEMENT)).map_err(|e| e.max(err)),
+            Err(err) => self.read_conditional().map_err(|err| err.while_parsing(ELEMENT)).map_err(|e| e.max(err)),
         }
     }
 
@@ -547,12 +498,49 @@ impl<'a> ParseCtx<'a> {
         }
     }
 
-    fn read_member(&mut self) -> ParseResult<(SrcRef, Node<String>, ParseError)> {
-        let dot_r = match self.peek() {
-            Token(Lexeme::Dot, r) => { self.advance(); r},
+    fn read_interpolated_string(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
+        let (mut parts, r_start) = match self.peek() {
+            Token(Lexeme::InterpStart(s), r) => { self.advance(); (vec![StringPart::Literal(s)], r) },
+            Token(l, r) => return Err(expected(Item::Primary, Item::Lexeme(l), r)),
+        };
+
+        let mut max_err = ParseError::phoney();
+        loop {
+            let (expr, err) = self.read_expr()?;
+            parts.push(StringPart::Expr(expr));
+            max_err = err.max(max_err);
+
+            match self.peek() {
+                Token(Lexeme::InterpMid(s), _r) => {
+                    self.advance();
+                    parts.push(StringPart::Literal(s));
+                },
+                Token(Lexeme::InterpEnd(s), r) => {
+                    self.advance();
+                    parts.push(StringPart::Literal(s));
+                    return Ok((Node(Expr::InterpolatedString(parts), r_start.union(&r)), max_err));
+                },
+                Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RBrace), Item::Lexeme(l), r).max(max_err)),
+            }
+        }
+    }
+
+    // `.name`, or `?.name` for an optional access
+    fn read_member(&mut self) -> ParseResult<(SrcRef, bool, Node<String>, ParseError)> {
+        let (dot_r, optional) = match self.peek() {
+            Token(Lexeme::Dot, r) => { self.advance(); (r, false) },
+            Token(Lexeme::QuestionDot, r) => { self.advance(); (r, true) },
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Dot), Item::Lexeme(l), r))
         };
-        Ok((dot_r, self.read_ident()?, ParseError::Phoney))
+        // `match` may name a member, as in `regex.match`, since a keyword cannot follow a `.`
+        let member = match self.peek() {
+            Token(Lexeme::Match, r) => {
+                self.advance();
+                Node(String::from("match"), r)
+            },
+            _ => self.read_ident()?,
+        };
+        Ok((dot_r, optional, member, ParseError::Phoney))
     }
 
     fn read_index(&mut self) -> ParseResult<(SrcRef, Node<Expr>, ParseError)> {
@@ -591,7 +579,7 @@ impl<'a> ParseCtx<'a> {
         let (block, max_err) = self.read_block().map_err(|err| err.max(max_err).while_parsing(ELEMENT))?;
 
         let r_union = args.1.union(&r_start).union(&r_middle).union(&block.1);
-        Ok((Node(Expr::Fn(self.code.clone(), Rc::new((Node(args.0, args.1.union(&r_start).union(&r_middle)), block))), r_union), max_err.while_parsing(ELEMENT)))
+        Ok((Node(Expr::Fn(self.code.clone(), Rc::new((Node(args.0, args.1.union(&r_start).union(&r_middle)), block, None))), r_union), max_err.while_parsing(ELEMENT)))
     }
 
     fn read_list_expr(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
@@ -676,19 +664,70 @@ impl<'a> ParseCtx<'a> {
         Ok((Node(params, r_total), max_err))
     }
 
-    fn read_params(&mut self) -> ParseResult<(Node<Vec<Node<Expr>>>, ParseError)> {
+    // Arguments may be given by name, as in `draw(x: 10, y: 20)`, after those given by position
+    fn read_params(&mut self) -> ParseResult<(Node<Vec<Node<Expr>>>, Vec<(Node<String>, Node<Expr>)>, ParseError)> {
         let r_start = match self.peek() {
             Token(Lexeme::LParen, r) => { self.advance(); r },
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::LParen), Item::Lexeme(l), r)),
         };
 
-        let (params, max_err) = self.read_paramlist()?;
+        let mut params = vec![];
+        let mut named = vec![];
+        let mut r_total = r_start;
+        let mut max_err = ParseError::Phoney;
+
+        loop {
+            let mut this = self.clone();
+            let name = match this.peek() {
+                Token(Lexeme::Ident(name), r) => {
+                    this.advance();
+                    match this.peek() {
+                        Token(Lexeme::Colon, _) => {
+                            this.advance();
+                            *self = this;
+                            Some(Node(name, r))
+                        },
+                        _ => None,
+                    }
+                },
+                _ => None,
+            };
+
+            let mut this = self.clone();
+            match this.read_expr() {
+                Ok((expr, err)) => {
+                    *self = this;
+                    r_total = r_total.union(&expr.1);
+                    max_err = err.max(max_err);
+                    match name {
+                        Some(name) => named.push((name, expr)),
+                        None if !named.is_empty() => return Err(ParseError::At(expr.1, Box::new(ParseError::PositionalAfterNamed))),
+                        None => params.push(expr),
+                    }
+                },
+                Err(err) if name.is_some() => return Err(err.max(max_err)),
+                Err(err) => {
+                    max_err = err.max(max_err);
+                    break;
+                },
+            }
+
+            match self.peek() {
+                Token(Lexeme::Comma, r) => {
+                    self.advance();
+                    r_total = r_total.union(&r);
+                },
+                Token(l, r) => {
+                    max_err = expected(Item::Lexeme(Lexeme::Comma), Item::Lexeme(l), r).max(max_err);
+                    break;
+                },
+            }
+        }
 
         match self.peek() {
             Token(Lexeme::RParen, r) => {
                 self.advance();
-                let r_union = params.1.union(&r_start).union(&r);
-                Ok((Node(params.0, r_union), max_err))
+                Ok((Node(params, r_total.union(&r)), named, max_err))
             },
             Token(l, r) => Err(expected(Item::Lexeme(Lexeme::RParen), Item::Lexeme(l), r).max(max_err)),
         }
@@ -809,6 +848,26 @@ impl<'a> ParseCtx<'a> {
         }
     }
 
+    // Values separated by commas, as in `return a, b;`, are put together in a list, which `var a, b = f();` takes apart
+    // again
+    fn read_values(&mut self) -> ParseResult<(Node<Expr>, ParseError)> {
+        let (expr, mut max_err) = self.read_expr()?;
+        match self.peek() {
+            Token(Lexeme::Comma, _) => {
+                let mut items = vec![expr];
+                while let Token(Lexeme::Comma, _) = self.peek() {
+                    self.advance();
+                    let (item, err) = self.read_expr()?;
+                    max_err = err.max(max_err);
+                    items.push(item);
+                }
+                let r_items = items[0].1.union(&items[items.len() - 1].1);
+                Ok((Node(Expr::List(Node(items, r_items)), r_items), max_err))
+            },
+            _ => Ok((expr, max_err)),
+        }
+    }
+
     fn read_return_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
         const ELEMENT: &'static str = "return statement";
 
@@ -817,7 +876,7 @@ impl<'a> ParseCtx<'a> {
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Return), Item::Lexeme(l), r).while_parsing(ELEMENT)),
         };
 
-        let (expr, max_err) = self.read_expr().map_err(|err| err.while_parsing(ELEMENT))?;
+        let (expr, max_err) = self.read_values().map_err(|err| err.while_parsing(ELEMENT))?;
 
         match self.peek() {
             Token(Lexeme::Semicolon, r) => {
@@ -854,6 +913,17 @@ impl<'a> ParseCtx<'a> {
             },
         };
 
+        // `else if` chains parse the trailing if statement directly rather than requiring a nested block
+        if let Token(Lexeme::If, _) = self.peek() {
+            return match self.read_if_else_stmt() {
+                Ok((else_stmt, err)) => {
+                    let r_union = expr.1.union(&r_start).union(&r_else).union(&else_stmt.1);
+                    Ok((Node(Stmt::IfElseIf(expr, true_block, Box::new(else_stmt)), r_union), err.max(max_err).while_parsing(ELEMENT)))
+                },
+                Err(err) => Err(err.max(max_err).while_parsing(ELEMENT)),
+            };
+        }
+
         match self.read_block() {
             Ok((block, err)) => {
                 let r_union = expr.1.union(&r_start).union(&r_else).union(&block.1);
@@ -895,6 +965,18 @@ impl<'a> ParseCtx<'a> {
             Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
         };
 
+        // `for k, v in x` binds both the key and the value
+        let val_ident = match self.peek() {
+            Token(Lexeme::Comma, _) => {
+                self.advance();
+                match self.peek() {
+                    Token(Lexeme::Ident(s), r) => { self.advance(); Some(Node(s.clone(), r)) },
+                    Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                }
+            },
+            _ => None,
+        };
+
         let r_middle = match self.peek() {
             Token(Lexeme::In, r) => { self.advance(); r },
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::In), Item::Lexeme(l), r).while_parsing(ELEMENT)),
@@ -905,12 +987,397 @@ impl<'a> ParseCtx<'a> {
         match self.read_block() {
             Ok((block, err)) => {
                 let r_union = expr.1.union(&r_start).union(&r_ident).union(&r_middle).union(&block.1);
-                Ok((Node(Stmt::For(Node(ident, r_ident), expr, block), r_union), err.max(max_err).while_parsing(ELEMENT)))
+                Ok((Node(Stmt::For(Node(ident, r_ident), val_ident, expr, block), r_union), err.max(max_err).while_parsing(ELEMENT)))
             }
             Err(err) => Err(err.max(max_err).while_parsing(ELEMENT)),
         }
     }
 
+    fn read_try_catch_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        const ELEMENT: &'static str = "try statement";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::Try, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Try), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (try_block, max_err) = self.read_block().map_err(|err| err.while_parsing(ELEMENT))?;
+
+        let r_middle = match self.peek() {
+            Token(Lexeme::Catch, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Catch), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        };
+
+        let (ident, r_ident) = match self.peek() {
+            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+            Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        };
+
+        match self.read_block() {
+            Ok((catch_block, err)) => {
+                let r_union = r_start.union(&try_block.1).union(&r_middle).union(&r_ident).union(&catch_block.1);
+                Ok((Node(Stmt::TryCatch(try_block, Node(ident, r_ident), catch_block), r_union), err.max(max_err).while_parsing(ELEMENT)))
+            }
+            Err(err) => Err(err.max(max_err).while_parsing(ELEMENT)),
+        }
+    }
+
+    fn read_with_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        const ELEMENT: &'static str = "with statement";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::With, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::With), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        // `as` is also the cast operator, so the resource is read only up to the first `as` that is followed by a name
+        // and then the block, outside of any brackets
+        let tokens = self.tokens.as_slice();
+        let mut depth = 0usize;
+        let end = (0..tokens.len()).find(|&i| {
+            match &tokens[i].0 {
+                Lexeme::LParen | Lexeme::LBrack | Lexeme::LBrace => depth += 1,
+                Lexeme::RParen | Lexeme::RBrack | Lexeme::RBrace => depth = depth.saturating_sub(1),
+                _ => {},
+            }
+            depth == 0 && match tokens[i..] {
+                [Token(Lexeme::As, _), Token(Lexeme::Ident(_), _), Token(Lexeme::LBrace, _), ..] => true,
+                _ => false,
+            }
+        });
+        let (expr, max_err) = match end {
+            Some(end) => {
+                let mut this = Self { tokens: tokens[..end].iter(), ..self.clone() };
+                let (expr, max_err) = this.read_expr().map_err(|err| err.while_parsing(ELEMENT))?;
+                let Token(l, r) = this.peek();
+                if l != Lexeme::Eof {
+                    return Err(expected(Item::Lexeme(Lexeme::As), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT));
+                }
+                self.tokens = tokens[end..].iter();
+                (expr, max_err)
+            },
+            None => self.read_expr().map_err(|err| err.while_parsing(ELEMENT))?,
+        };
+
+        let r_middle = match self.peek() {
+            Token(Lexeme::As, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::As), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        };
+
+        let (ident, r_ident) = match self.peek() {
+            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+            Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        };
+
+        match self.read_block() {
+            Ok((block, err)) => {
+                let r_union = r_start.union(&expr.1).union(&r_middle).union(&r_ident).union(&block.1);
+                Ok((Node(Stmt::With(expr, Node(ident, r_ident), block), r_union), err.max(max_err).while_parsing(ELEMENT)))
+            }
+            Err(err) => Err(err.max(max_err).while_parsing(ELEMENT)),
+        }
+    }
+
+    fn read_pattern_literal(&mut self) -> ParseResult<Node<Expr>> {
+        let (neg, r_neg) = match self.peek() {
+            Token(Lexeme::Minus, r) => { self.advance(); (true, Some(r)) },
+            _ => (false, None),
+        };
+
+        let (expr, r) = match self.peek() {
+            Token(Lexeme::Int(x), r) => (Expr::LiteralInt(if neg { -x } else { x }), r),
+            Token(Lexeme::Float(x), r) => (Expr::LiteralFloat(if neg { -x } else { x }), r),
+            Token(_, _) if neg => {
+                let next = self.peek();
+                return Err(expected(Item::Lexeme(Lexeme::Int(0)), Item::Lexeme(next.0), next.1));
+            },
+            Token(Lexeme::String(s), r) => (Expr::LiteralString(s), r),
+            Token(Lexeme::Char(c), r) => (Expr::LiteralChar(c), r),
+            Token(Lexeme::True, r) => (Expr::LiteralBoolean(true), r),
+            Token(Lexeme::False, r) => (Expr::LiteralBoolean(false), r),
+            Token(Lexeme::Null, r) => (Expr::LiteralNull, r),
+            Token(l, r) => return Err(expected(Item::Pattern, Item::Lexeme(l), r)),
+        };
+        self.advance();
+        Ok(Node(expr, r_neg.map(|r_neg| r_neg.union(&r)).unwrap_or(r)))
+    }
+
+    fn read_pattern(&mut self) -> ParseResult<Node<Pattern>> {
+        const ELEMENT: &'static str = "pattern";
+        self.check_depth()?;
+
+        match self.peek() {
+            Token(Lexeme::Ident(ident), r) => {
+                self.advance();
+                let pattern = Node(if ident == "_" { Pattern::Wildcard } else { Pattern::Binding(ident) }, r);
+                // A type after the name, as in `n: num`, must be had by the value for it to match
+                match self.peek() {
+                    Token(Lexeme::Colon, _) => {
+                        self.advance();
+                        // `fn` and `null` are keywords, but also the names of types
+                        let ty = match self.peek() {
+                            Token(Lexeme::Ident(ty), r) => Node(ty, r),
+                            Token(Lexeme::Fn, r) => Node(String::from("fn"), r),
+                            Token(Lexeme::Null, r) => Node(String::from("null"), r),
+                            Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                        };
+                        self.advance();
+                        let r_total = r.union(&ty.1);
+                        Ok(Node(Pattern::Typed(Box::new(pattern), ty), r_total))
+                    },
+                    _ => Ok(pattern),
+                }
+            },
+            Token(Lexeme::LBrack, r_start) => {
+                self.advance();
+                let mut items = vec![];
+                let mut rest = None;
+                loop {
+                    match self.peek() {
+                        Token(Lexeme::RBrack, r_end) => {
+                            self.advance();
+                            return Ok(Node(Pattern::List(items, rest), r_start.union(&r_end)));
+                        },
+                        // A rest pattern, such as `...` or `...tail`, must come last
+                        Token(Lexeme::Ellipsis, r) if rest.is_none() => {
+                            self.advance();
+                            rest = Some(Box::new(match self.peek() {
+                                Token(Lexeme::Ident(ident), r_ident) if ident != "_" => {
+                                    self.advance();
+                                    Node(Pattern::Binding(ident), r.union(&r_ident))
+                                },
+                                Token(Lexeme::Ident(_), r_ident) => {
+                                    self.advance();
+                                    Node(Pattern::Wildcard, r.union(&r_ident))
+                                },
+                                _ => Node(Pattern::Wildcard, r),
+                            }));
+                            match self.peek() {
+                                Token(Lexeme::RBrack, _) => {},
+                                Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RBrack), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                            }
+                        },
+                        _ => {
+                            items.push(self.read_pattern().map_err(|err| err.while_parsing(ELEMENT))?);
+                            match self.peek() {
+                                Token(Lexeme::Comma, _) => self.advance(),
+                                Token(Lexeme::RBrack, _) => {},
+                                Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RBrack), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                            }
+                        },
+                    }
+                }
+            },
+            // Each key is bound to a variable of the same name, as in `{x, y}`, unless given a pattern, as in `{x: [a, b]}`
+            Token(Lexeme::LBrace, r_start) => {
+                self.advance();
+                let mut entries = vec![];
+                loop {
+                    match self.peek() {
+                        Token(Lexeme::RBrace, r_end) => {
+                            self.advance();
+                            return Ok(Node(Pattern::Map(entries), r_start.union(&r_end)));
+                        },
+                        _ => {
+                            let key = self.read_ident().map_err(|err| err.while_parsing(ELEMENT))?;
+                            let pattern = match self.peek() {
+                                Token(Lexeme::Colon, _) => {
+                                    self.advance();
+                                    self.read_pattern().map_err(|err| err.while_parsing(ELEMENT))?
+                                },
+                                _ => Node(Pattern::Binding(key.0.clone()), key.1),
+                            };
+                            entries.push((key, pattern));
+                            match self.peek() {
+                                Token(Lexeme::Comma, _) => self.advance(),
+                                Token(Lexeme::RBrace, _) => {},
+                                Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RBrace), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                            }
+                        },
+                    }
+                }
+            },
+            _ => {
+                let from = self.read_pattern_literal().map_err(|err| err.while_parsing(ELEMENT))?;
+                match self.peek() {
+                    Token(Lexeme::DotDot, _) => {
+                        self.advance();
+                        let to = self.read_pattern_literal().map_err(|err| err.while_parsing(ELEMENT))?;
+                        let r_union = from.1.union(&to.1);
+                        Ok(Node(Pattern::Range(from, to), r_union))
+                    },
+                    _ => {
+                        let r = from.1;
+                        Ok(Node(Pattern::Literal(from), r))
+                    },
+                }
+            },
+        }
+    }
+
+    fn read_match_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        const ELEMENT: &'static str = "match statement";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::Match, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Match), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (expr, mut max_err) = self.read_expr().map_err(|err| err.while_parsing(ELEMENT))?;
+
+        match self.peek() {
+            Token(Lexeme::LBrace, _) => self.advance(),
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::LBrace), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        }
+
+        // Arms are separated by optional commas
+        let mut arms = vec![];
+        let r_end = loop {
+            match self.peek() {
+                Token(Lexeme::RBrace, r) => { self.advance(); break r },
+                Token(Lexeme::Comma, _) if !arms.is_empty() => self.advance(),
+                _ => {
+                    let pattern = self.read_pattern().map_err(|err| err.max(max_err.clone()).while_parsing(ELEMENT))?;
+                    match self.peek() {
+                        Token(Lexeme::FatArrow, _) => self.advance(),
+                        Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::FatArrow), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+                    }
+                    let (block, err) = self.read_block().map_err(|err| err.max(max_err.clone()).while_parsing(ELEMENT))?;
+                    max_err = err.max(max_err);
+                    arms.push((pattern, block));
+                },
+            }
+        };
+
+        let r_union = r_start.union(&expr.1).union(&r_end);
+        Ok((Node(Stmt::Match(expr, arms), r_union), max_err.while_parsing(ELEMENT)))
+    }
+
+    fn read_import_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        const ELEMENT: &'static str = "import statement";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::Import, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Import), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        // `import "path/to/foo.fg";` and `import foo;` both bind the module to `foo` unless another name is given
+        let (path, r_path) = match self.peek() {
+            Token(Lexeme::String(s), r) => { self.advance(); (s.clone(), r) },
+            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+            Token(l, r) => return Err(expected(Item::Module, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (ident, r_ident) = match self.peek() {
+            Token(Lexeme::As, _) => {
+                self.advance();
+                match self.peek() {
+                    Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+                    Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+                }
+            },
+            _ => {
+                let stem = std::path::Path::new(&path)
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| path.clone());
+                (stem, r_path)
+            },
+        };
+
+        match self.peek() {
+            Token(Lexeme::Semicolon, r) => {
+                self.advance();
+                let r_union = r_start.union(&r_path).union(&r_ident).union(&r);
+                Ok((Node(Stmt::Import(Node(path, r_path), Node(ident, r_ident)), r_union), ParseError::phoney()))
+            },
+            Token(l, r) => Err(expected(Item::Lexeme(Lexeme::Semicolon), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        }
+    }
+
+    fn read_fn_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        let (ident, func, max_err) = self.read_fn_decl(false)?;
+        let r_fn = func.1;
+        Ok((Node(Stmt::Decl(ident, func), r_fn), max_err))
+    }
+
+    // A function declaration is a variable declaration whose value is a function that knows its own name. A method may
+    // name `self` as its first parameter, which is left out since methods are given `self` whenever they are called.
+    fn read_fn_decl(&mut self, is_method: bool) -> ParseResult<(Node<String>, Node<Expr>, ParseError)> {
+        const ELEMENT: &'static str = "function declaration";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::Fn, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Fn), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (ident, r_ident) = match self.peek() {
+            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+            Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let r_lparen = match self.peek() {
+            Token(Lexeme::LParen, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::LParen), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (args, max_err) = self.read_args().map_err(|err| err.while_parsing(ELEMENT))?;
+
+        let r_rparen = match self.peek() {
+            Token(Lexeme::RParen, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RParen), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+        };
+
+        let (block, max_err) = self.read_block().map_err(|err| err.max(max_err).while_parsing(ELEMENT))?;
+
+        let r_args = args.1.union(&r_lparen).union(&r_rparen);
+        let r_fn = r_start.union(&r_args).union(&block.1);
+        let Node(mut args, _) = args;
+        if is_method && matches!(args.0.first(), Some(Param { name, ty: None, default: None }) if name.0 == "self") {
+            args.0.remove(0);
+        }
+        let func = Expr::Fn(self.code.clone(), Rc::new((Node(args, r_args), block, Some(ident.clone()))));
+        Ok((Node(ident, r_ident), Node(func, r_fn), max_err.while_parsing(ELEMENT)))
+    }
+
+    fn read_class_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        const ELEMENT: &'static str = "class declaration";
+
+        let r_start = match self.peek() {
+            Token(Lexeme::Class, r) => { self.advance(); r },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Class), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        let (ident, r_ident) = match self.peek() {
+            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+            Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        };
+
+        match self.peek() {
+            Token(Lexeme::LBrace, _) => { self.advance(); },
+            Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::LBrace), Item::Lexeme(l), r).while_parsing(ELEMENT)),
+        }
+
+        // The body of a class holds nothing but its methods
+        let mut methods = vec![];
+        let mut max_err = ParseError::phoney();
+        loop {
+            match self.peek() {
+                Token(Lexeme::RBrace, r) => {
+                    self.advance();
+                    let r_union = r_start.union(&r_ident).union(&r);
+                    return Ok((Node(Stmt::Class(Node(ident, r_ident), methods), r_union), max_err.while_parsing(ELEMENT)));
+                },
+                Token(Lexeme::Fn, _) => {
+                    let (name, func, err) = self.read_fn_decl(true).map_err(|err| err.max(max_err.clone()).while_parsing(ELEMENT))?;
+                    max_err = err.max(max_err);
+                    methods.push((name, func));
+                },
+                Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::RBrace), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
+            }
+        }
+    }
+
     fn read_decl_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
         const ELEMENT: &'static str = "variable declaration";
 
@@ -919,29 +1386,59 @@ impl<'a> ParseCtx<'a> {
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Var), Item::Lexeme(l), r).while_parsing(ELEMENT)),
         };
 
-        let (ident, r_ident) = match self.peek() {
-            Token(Lexeme::Ident(s), r) => { self.advance(); (s.clone(), r) },
+        // A list or map pattern declares each of the variables that it binds
+        let target = match self.peek() {
+            Token(Lexeme::Ident(s), r) => { self.advance(); Node(Pattern::Binding(s), r) },
+            Token(Lexeme::LBrack, _) | Token(Lexeme::LBrace, _) => self.read_pattern().map_err(|err| err.while_parsing(ELEMENT))?,
             Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).while_parsing(ELEMENT)),
         };
 
+        // Several targets, as in `var a, b = f();`, take apart a list in the same way as `var [a, b] = f();`
+        let several = match self.peek() {
+            Token(Lexeme::Comma, _) => true,
+            _ => false,
+        };
+        let target = match self.peek() {
+            Token(Lexeme::Comma, _) => {
+                let first = match target {
+                    Node(Pattern::Binding(ident), r) if ident == "_" => Node(Pattern::Wildcard, r),
+                    target => target,
+                };
+                let mut items = vec![first];
+                while let Token(Lexeme::Comma, _) = self.peek() {
+                    self.advance();
+                    items.push(self.read_pattern().map_err(|err| err.while_parsing(ELEMENT))?);
+                }
+                let r_items = items[0].1.union(&items[items.len() - 1].1);
+                Node(Pattern::List(items, None), r_items)
+            },
+            _ => target,
+        };
+
         let r_assign = match self.peek() {
             Token(Lexeme::Assign, r) => { self.advance(); r },
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::Assign), Item::Lexeme(l), r).while_parsing(ELEMENT)),
         };
 
-        let (expr, max_err) = self.read_expr().map_err(|err| err.while_parsing(ELEMENT))?;
+        let (expr, max_err) = if several { self.read_values() } else { self.read_expr() }
+            .map_err(|err| err.while_parsing(ELEMENT))?;
 
         match self.peek() {
             Token(Lexeme::Semicolon, r) => {
                 self.advance();
-                let r_union = expr.1.union(&r_start).union(&r_ident).union(&r_assign).union(&r);
-                Ok((Node(Stmt::Decl(Node(ident, r_ident), expr), r_union), max_err))
+                let r_union = expr.1.union(&r_start).union(&target.1).union(&r_assign).union(&r);
+                let stmt = match target {
+                    Node(Pattern::Binding(ident), r_ident) => Stmt::Decl(Node(ident, r_ident), expr),
+                    pattern => Stmt::DeclPattern(pattern, expr),
+                };
+                Ok((Node(stmt, r_union), max_err))
             },
             Token(l, r) => Err(expected(Item::Lexeme(Lexeme::Semicolon), Item::Lexeme(l), r).max(max_err).while_parsing(ELEMENT)),
         }
     }
 
     fn read_stmt(&mut self) -> ParseResult<(Node<Stmt>, ParseError)> {
+        self.check_depth()?;
         let mut this = self.clone();
         let max_err = match this.read_expr_stmt() {
             Ok((stmt, err)) => {
@@ -987,6 +1484,60 @@ impl<'a> ParseCtx<'a> {
             Err(err) => err.max(max_err),
         };
 
+        let mut this = self.clone();
+        let max_err = match this.read_try_catch_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
+        let mut this = self.clone();
+        let max_err = match this.read_with_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
+        let mut this = self.clone();
+        let max_err = match this.read_match_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
+        let mut this = self.clone();
+        let max_err = match this.read_import_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
+        let mut this = self.clone();
+        let max_err = match this.read_class_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
+        let mut this = self.clone();
+        let max_err = match this.read_fn_stmt() {
+            Ok((stmt, err)) => {
+                *self = this;
+                return Ok((stmt, err.max(max_err)))
+            },
+            Err(err) => err.max(max_err),
+        };
+
         let mut this = self.clone();
         let max_err = match this.read_decl_stmt() {
             Ok((stmt, err)) => {
@@ -1009,7 +1560,9 @@ impl<'a> ParseCtx<'a> {
         Err(expected(Item::Stmt, Item::Lexeme(next.0), next.1).max(max_err))
     }
 
-    fn read_stmts(&mut self) -> ParseResult<(Vec<Node<Stmt>>, ParseError)> {
+    // Statements up to the end of the block or input, which `end` describes. Statements that fail to parse are
+    // skipped, and their errors kept, so that those after them are still read.
+    fn read_stmts(&mut self, end: Item) -> (Vec<Node<Stmt>>, ParseError) {
         let mut stmts = vec![];
 
         let mut max_err = ParseError::phoney();
@@ -1023,7 +1576,16 @@ impl<'a> ParseCtx<'a> {
                     stmts.push(stmt);
                     max_err = err.max(max_err);
                 },
-                Err(err) => return Ok((stmts, err.max(max_err))),
+                Err(err) => match self.peek() {
+                    // Whoever reads the statements checks that they end properly
+                    Token(Lexeme::RBrace, _) | Token(Lexeme::Eof, _) => return (stmts, err.max(max_err)),
+                    Token(l, r) => {
+                        let err = expected(end.clone(), Item::Lexeme(l), r).max(err.max(max_err));
+                        self.errors.push(err);
+                        max_err = ParseError::phoney();
+                        self.synchronize();
+                    },
+                },
             }
         }
     }
@@ -1034,7 +1596,7 @@ impl<'a> ParseCtx<'a> {
             Token(l, r) => return Err(expected(Item::Lexeme(Lexeme::LBrace), Item::Lexeme(l), r)),
         };
 
-        let (stmts, max_err) = self.read_stmts()?;
+        let (stmts, max_err) = self.read_stmts(Item::Lexeme(Lexeme::RBrace));
 
         match self.peek() {
             Token(Lexeme::RBrace, r) => {
@@ -1051,7 +1613,8 @@ impl<'a> ParseCtx<'a> {
     }
 
     fn read_args(&mut self) -> ParseResult<(Node<Args>, ParseError)> {
-        let mut args = vec![];
+        let mut args: Vec<Param> = vec![];
+        let mut rest = None;
         let mut r_total = SrcRef::empty();
         let mut max_err = ParseError::Phoney;
 
@@ -1060,7 +1623,15 @@ impl<'a> ParseCtx<'a> {
                 Token(Lexeme::Ident(s), r) => {
                     self.advance();
                     r_total = r_total.union(&r);
-                    args.push(Node(s.clone(), r));
+                    args.push(Param { name: Node(s.clone(), r), ty: None, default: None });
+                },
+                // The rest parameter, such as `...rest`, must come last
+                Token(Lexeme::Ellipsis, r) => {
+                    self.advance();
+                    let name = self.read_ident()?;
+                    r_total = r_total.union(&r).union(&name.1);
+                    rest = Some(name);
+                    break;
                 },
                 Token(l, r) => {
                     max_err = expected(Item::Ident, Item::Lexeme(l), r).max(max_err);
@@ -1068,6 +1639,40 @@ impl<'a> ParseCtx<'a> {
                 },
             }
 
+            if let Token(Lexeme::Colon, _) = self.peek() {
+                self.advance();
+                // `fn` and `null` are keywords, but also the names of types
+                let ty = match self.peek() {
+                    Token(Lexeme::Ident(ty), r) => Node(ty, r),
+                    Token(Lexeme::Fn, r) => Node(String::from("fn"), r),
+                    Token(Lexeme::Null, r) => Node(String::from("null"), r),
+                    Token(l, r) => return Err(expected(Item::Ident, Item::Lexeme(l), r).max(max_err)),
+                };
+                self.advance();
+                r_total = r_total.union(&ty.1);
+                if let Some(arg) = args.last_mut() {
+                    arg.ty = Some(ty);
+                }
+            }
+
+            // Defaults are read without `|`, which would otherwise be taken as the end of a closure's parameters
+            match self.peek() {
+                Token(Lexeme::Assign, _) => {
+                    self.advance();
+                    let (default, err) = self.read_operators(default_level())?;
+                    max_err = err.max(max_err);
+                    r_total = r_total.union(&default.1);
+                    if let Some(arg) = args.last_mut() {
+                        arg.default = Some(default);
+                    }
+                },
+                // Once one parameter has a default, all of those after it need one too
+                Token(l, r) if args.len() > 1 && args[args.len() - 2].default.is_some() => {
+                    return Err(expected(Item::Lexeme(Lexeme::Assign), Item::Lexeme(l), r).max(max_err));
+                },
+                _ => {},
+            }
+
             match self.peek() {
                 Token(Lexeme::Comma, r) => {
                     self.advance();
@@ -1080,34 +1685,61 @@ impl<'a> ParseCtx<'a> {
             }
         }
 
-        Ok((Node(Args(args), r_total), max_err))
+        Ok((Node(Args(args, rest), r_total), max_err))
     }
 
     pub fn read_expr_full(&mut self) -> ParseResult<Expr> {
-        let (expr, max_err) = match self.read_expr() {
-            Ok((expr, max_err)) => (expr, max_err),
-            Err(err) => return match self.peek() {
-                Token(Lexeme::Eof, _) => Ok(Expr::None),
-                _ => Err(err),
-            },
-        };
+        if let Token(Lexeme::Eof, _) = self.peek() {
+            return Ok(Expr::None);
+        }
+        let (expr, max_err) = self.read_expr().map_err(ParseError::classify)?;
         match self.peek() {
             Token(Lexeme::Eof, _) => Ok(expr.0),
-            Token(l, r) => Err(expected(Item::End, Item::Lexeme(l), r).max(max_err)),
+            Token(l, r) => Err(expected(Item::End, Item::Lexeme(l), r).max(max_err).classify()),
         }
     }
 
-    pub fn read_stmts_full(&mut self) -> ParseResult<Vec<Node<Stmt>>> {
-        let (stmts, max_err) = match self.read_stmts() {
-            Ok((stmts, max_err)) => (stmts, max_err),
-            Err(err) => return match self.peek() {
-                Token(Lexeme::Eof, _) => Ok(vec![]),
-                _ => Err(err),
-            },
-        };
-        match self.peek() {
-            Token(Lexeme::Eof, _) => Ok(stmts),
-            Token(l, r) => Err(expected(Item::End, Item::Lexeme(l), r).max(max_err)),
+    // Skip past the statement that failed to parse: up to and including the next `;`, or the `}` closing a block that
+    // was opened along the way, but not the `}` closing the block that the statement is in
+    fn synchronize(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.peek() {
+                Token(Lexeme::Eof, _) => return,
+                Token(Lexeme::Semicolon, _) if depth == 0 => return self.advance(),
+                Token(Lexeme::LBrace, _) => depth += 1,
+                Token(Lexeme::RBrace, _) if depth == 0 => return,
+                Token(Lexeme::RBrace, _) if depth == 1 => {
+                    self.advance();
+                    // Blocks used as part of an expression (such as functions) are still followed by a semicolon
+                    if let Token(Lexeme::Semicolon, _) = self.peek() {
+                        self.advance();
+                    }
+                    return;
+                },
+                Token(Lexeme::RBrace, _) => depth -= 1,
+                _ => {},
+            }
+            self.advance();
+        }
+    }
+
+    /// Read every statement of the input, along with the errors of those that could not be parsed. Statements that
+    /// fail to parse, including those inside blocks and functions, are left out of the statements read.
+    pub fn read_stmts_recovering(&mut self) -> (Vec<Node<Stmt>>, Vec<ParseError>) {
+        let mut stmts = vec![];
+
+        loop {
+            let (mut new_stmts, max_err) = self.read_stmts(Item::End);
+            stmts.append(&mut new_stmts);
+            match self.peek() {
+                Token(Lexeme::Eof, _) => return (stmts, mem::take(&mut self.errors)),
+                // A `}` without a block to close
+                Token(l, r) => {
+                    self.errors.push(expected(Item::End, Item::Lexeme(l), r).max(max_err));
+                    self.advance();
+                },
+            }
         }
     }
 }
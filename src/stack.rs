@@ -0,0 +1,47 @@
+//! Guards against running out of stack space, which aborts the process rather than unwinding.
+//!
+//! The parser and the interpreter are both recursive, so deeply nested code or deep recursion in a script would
+//! otherwise overflow the host's stack. Each recursive step checks how much of the current thread's stack is left, and
+//! fails with an error once less than `RED_ZONE` remains.
+
+use std::cell::Cell;
+
+// Enough for the deepest recursion between two checks, which is a call from one function to another in a debug build
+const RED_ZONE: usize = 256 * 1024;
+
+thread_local! {
+    // The lowest address of the current thread's stack, found on first use
+    static STACK_LOW: Cell<Option<Option<usize>>> = Cell::new(None);
+}
+
+#[cfg(target_os = "linux")]
+fn find_stack_low() -> Option<usize> {
+    unsafe {
+        let mut attr = std::mem::zeroed::<libc::pthread_attr_t>();
+        if libc::pthread_getattr_np(libc::pthread_self(), &mut attr) != 0 {
+            return None;
+        }
+        let mut addr = std::ptr::null_mut();
+        let mut size = 0;
+        let found = libc::pthread_attr_getstack(&attr, &mut addr, &mut size) == 0;
+        libc::pthread_attr_destroy(&mut attr);
+        if found { Some(addr as usize) } else { None }
+    }
+}
+
+// Elsewhere, the size of the stack is not known and so it goes unchecked
+#[cfg(not(target_os = "linux"))]
+fn find_stack_low() -> Option<usize> {
+    None
+}
+
+/// Whether there is enough of the stack left to go deeper.
+pub(crate) fn has_room() -> bool {
+    let low = STACK_LOW.with(|low| low.get().unwrap_or_else(|| {
+        let found = find_stack_low();
+        low.set(Some(found));
+        found
+    }));
+    let here = &low as *const _ as usize;
+    low.map(|low| here.saturating_sub(low) > RED_ZONE).unwrap_or(true)
+}
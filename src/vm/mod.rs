@@ -0,0 +1,440 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+};
+use crate::{
+    compile::{
+        Chunk,
+        Instr,
+        UnaryOp,
+        BinaryOp,
+    },
+    exec::{
+        self,
+        ExecError,
+        ExecResult,
+        ForgeIter,
+        PairIter,
+        ExecCtx,
+        Env,
+        ErrorValue,
+        Scope,
+        TailCall,
+        UnaryOpRef,
+        Value,
+        overload,
+    },
+    parser::{SrcRef, ast::Node},
+    stack,
+};
+
+/// Selects the strategy that an `Engine` uses to run code.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ExecMode {
+    /// Walk the AST directly.
+    Interpret,
+    /// Lower the AST into bytecode and run it on the VM, falling back to the interpreter for code that cannot yet be
+    /// compiled.
+    Bytecode,
+}
+
+impl Default for ExecMode {
+    fn default() -> Self {
+        ExecMode::Interpret
+    }
+}
+
+// A loop iterator, yielding either single values or key-value pairs
+enum Iter {
+    Values(Box<dyn ForgeIter>),
+    Pairs(Box<dyn PairIter>),
+}
+
+// The state to restore when an error is raised within a `try` block
+struct Handler {
+    target: usize,
+    stack_len: usize,
+    env: Env,
+    iters_len: usize,
+}
+
+pub struct Vm<'a> {
+    scope: &'a mut dyn Scope,
+    // The innermost block scope, whose outermost parent is that of `scope`
+    env: Env,
+    stack: Vec<Value>,
+    // The variables that the compiler gave slots to
+    locals: Vec<Value>,
+    iters: Vec<Iter>,
+    handlers: Vec<Handler>,
+}
+
+impl<'a> Vm<'a> {
+    pub fn new(scope: &'a mut dyn Scope) -> Self {
+        Self {
+            env: scope.env().clone(),
+            scope,
+            stack: Vec::new(),
+            locals: Vec::new(),
+            iters: Vec::new(),
+            handlers: Vec::new(),
+        }
+    }
+
+    // The compiler never leaves too few values on the stack, so running out means that it has a bug
+    fn pop(&mut self) -> ExecResult<Value> {
+        self.stack.pop().ok_or(ExecError::Internal("the VM stack underflowed"))
+    }
+
+    fn top(&self) -> ExecResult<&Value> {
+        self.stack.last().ok_or(ExecError::Internal("the VM stack underflowed"))
+    }
+
+    fn pop_many(&mut self, n: usize) -> ExecResult<Vec<Value>> {
+        let at = self.stack.len().checked_sub(n).ok_or(ExecError::Internal("the VM stack underflowed"))?;
+        Ok(self.stack.split_off(at))
+    }
+
+    fn local(&mut self, slot: usize) -> ExecResult<&mut Value> {
+        self.locals.get_mut(slot).ok_or(ExecError::Internal("a VM local slot was out of range"))
+    }
+
+    fn get_var(&self, name: &str) -> ExecResult<Value> {
+        match self.env.get(name) {
+            Some(val) => Ok(val),
+            None => self.scope.get_var(name),
+        }
+    }
+
+    fn assign_var(&mut self, name: &str, val: Value) -> ExecResult<()> {
+        if self.env.assign(name, val) {
+            Ok(())
+        } else {
+            Err(ExecError::NoSuchItem(name.to_string()))
+        }
+    }
+
+    /// Run a chunk to completion, yielding the value left on top of the stack (if any).
+    pub fn run(&mut self, chunk: &Chunk, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<Option<Value>> {
+        // Functions run on the VM call each other through here, so this limits how deeply they may recurse
+        if !stack::has_room() {
+            return Err(ExecError::TooDeep);
+        }
+        self.locals.resize(chunk.locals, Value::Null);
+        let mut pc = 0;
+        while let Some(instr) = chunk.code.get(pc) {
+            let at = pc;
+            pc += 1;
+            if let Err(err) = ctx.burn_fuel().and_then(|_| self.step(instr, &mut pc, ctx, src)) {
+                let err = match chunk.location_of(at) {
+                    Some(r) => ExecError::WithSrc(src.clone(), Box::new(ExecError::At(r, Box::new(err)))),
+                    None => err,
+                };
+                // Unwind to the innermost enclosing `try` block, if any, and enter its `catch` block
+                let handler = match self.handlers.pop() {
+                    Some(handler) if !err.is_interrupt() => handler,
+                    _ => return Err(err),
+                };
+                self.stack.truncate(handler.stack_len);
+                self.env = handler.env;
+                self.iters.truncate(handler.iters_len);
+                self.stack.push(Value::from(ErrorValue::from_error(&err)));
+                pc = handler.target;
+            }
+        }
+
+        Ok(self.stack.pop())
+    }
+
+    fn step(&mut self, instr: &Instr, pc: &mut usize, ctx: &mut ExecCtx, src: &Rc<String>) -> ExecResult<()> {
+        let src_map = |err| ExecError::WithSrc(src.clone(), Box::new(err));
+        let at = |r: SrcRef| move |err| ExecError::At(r, Box::new(err));
+
+        match instr {
+            Instr::Const(val) => self.stack.push(val.clone()),
+            Instr::String(s) => self.stack.push(Value::String(Rc::new(RefCell::new(s.clone())))),
+            Instr::Pop => { self.pop()?; },
+            Instr::Dup(n) => {
+                let top = self.pop_many(*n)?;
+                self.stack.extend(top.iter().cloned());
+                self.stack.extend(top);
+            },
+            Instr::Rot(n) => {
+                let mut top = self.pop_many(*n)?;
+                top.rotate_right(1);
+                self.stack.extend(top);
+            },
+
+            Instr::Load(name, r) => {
+                let val = self.get_var(name)
+                    .or_else(|err| exec::find_engine_builtin(name, ctx).ok_or(err))
+                    .map_err(at(*r))
+                    .map_err(src_map)?;
+                self.stack.push(val);
+            },
+            Instr::Store(name, r) => {
+                let val = self.pop()?;
+                self.assign_var(name, val).map_err(at(*r)).map_err(src_map)?;
+            },
+            Instr::Declare(name) => {
+                let val = self.pop()?;
+                self.env.declare(name.clone(), val);
+            },
+            Instr::LoadLocal(slot) => {
+                let val = self.local(*slot)?.clone();
+                self.stack.push(val);
+            },
+            Instr::StoreLocal(slot) => {
+                let val = self.pop()?;
+                *self.local(*slot)? = val;
+            },
+            Instr::StoreIndex(r_expr, r_index, r_rvalue) => {
+                let index = self.pop()?;
+                let mut container = self.pop()?;
+                let val = self.pop()?;
+                container.assign_index(&index, val, *r_index, *r_rvalue).map_err(at(*r_expr)).map_err(src_map)?;
+            },
+            Instr::StoreMember(name, r_expr, r_name) => {
+                let container = self.pop()?;
+                let val = self.pop()?;
+                container.assign_member(name, val, *r_name).map_err(at(*r_expr)).map_err(src_map)?;
+            },
+
+            Instr::Unary(op, refs) => {
+                let val = self.pop()?;
+                let res = match op {
+                    UnaryOp::Not => val.eval_not(*refs),
+                    UnaryOp::Neg => overload::neg(&val, ctx, src, *refs),
+                    UnaryOp::BitNot => val.eval_bit_not(*refs),
+                    UnaryOp::Clone => val.eval_clone(*refs),
+                    UnaryOp::Mirror => val.eval_mirror(*refs),
+                }.map_err(src_map)?;
+                self.stack.push(res);
+            },
+            Instr::Binary(op, refs) => {
+                let right = self.pop()?;
+                let left = self.pop()?;
+                let res = match op {
+                    BinaryOp::Mul => overload::binary("__mul", &left, &right, Value::eval_mul, ctx, src, *refs),
+                    BinaryOp::Div => overload::binary("__div", &left, &right, Value::eval_div, ctx, src, *refs),
+                    BinaryOp::Rem => overload::binary("__rem", &left, &right, Value::eval_rem, ctx, src, *refs),
+                    BinaryOp::Add => overload::binary("__add", &left, &right, Value::eval_add, ctx, src, *refs),
+                    BinaryOp::Sub => overload::binary("__sub", &left, &right, Value::eval_sub, ctx, src, *refs),
+                    BinaryOp::Shl => overload::binary("__shl", &left, &right, Value::eval_shl, ctx, src, *refs),
+                    BinaryOp::Shr => overload::binary("__shr", &left, &right, Value::eval_shr, ctx, src, *refs),
+                    BinaryOp::BitAnd => overload::binary("__bit_and", &left, &right, Value::eval_bit_and, ctx, src, *refs),
+                    BinaryOp::BitXor => overload::binary("__bit_xor", &left, &right, Value::eval_bit_xor, ctx, src, *refs),
+                    BinaryOp::BitOr => overload::binary("__bit_or", &left, &right, Value::eval_bit_or, ctx, src, *refs),
+                    BinaryOp::Greater => overload::binary("__greater", &left, &right, Value::eval_greater, ctx, src, *refs),
+                    BinaryOp::GreaterEq => overload::binary("__greater_eq", &left, &right, Value::eval_greater_eq, ctx, src, *refs),
+                    BinaryOp::Less => overload::binary("__less", &left, &right, Value::eval_less, ctx, src, *refs),
+                    BinaryOp::LessEq => overload::binary("__less_eq", &left, &right, Value::eval_less_eq, ctx, src, *refs),
+                    BinaryOp::Eq => overload::binary("__eq", &left, &right, Value::eval_eq, ctx, src, *refs),
+                    BinaryOp::NotEq => overload::binary("__not_eq", &left, &right, Value::eval_not_eq, ctx, src, *refs),
+                    BinaryOp::Xor => left.eval_xor(&right, *refs),
+                    BinaryOp::Range => left.eval_range(&right, *refs),
+                    BinaryOp::Is => left.eval_is(&right, *refs),
+                }.map_err(src_map)?;
+                self.stack.push(res);
+            },
+            Instr::As(ty, refs) => {
+                let val = self.pop()?;
+                let res = val.eval_as(ty, *refs).map_err(src_map)?;
+                self.stack.push(res);
+            },
+            Instr::Index(r_expr, r_index) => {
+                let index = self.pop()?;
+                let val = self.pop()?;
+                let res = overload::index(&val, &index, ctx, src, *r_index).map_err(at(*r_expr)).map_err(src_map)?;
+                self.stack.push(res);
+            },
+            Instr::Member(name, r) => {
+                let val = self.pop()?.eval_member(name, *r).map_err(src_map)?;
+                self.stack.push(val);
+            },
+            Instr::Input(r, r_expr) => {
+                let text = self.pop()?.get_display_text().map_err(at(*r_expr)).map_err(src_map)?;
+                let input = ctx.input(text).map_err(at(r.union(r_expr))).map_err(src_map)?;
+                let val = exec::parse_input(input).map_err(at(*r)).map_err(src_map)?;
+                self.stack.push(val);
+            },
+
+            Instr::Import(path, r) => {
+                let module = exec::import(path, ctx).map_err(at(*r))?;
+                self.stack.push(module);
+            },
+
+            Instr::Display(r) => {
+                let text = self.pop()?.get_display_text_with(&ctx.display).map_err(at(*r)).map_err(src_map)?;
+                self.stack.push(Value::String(Rc::new(RefCell::new(text))));
+            },
+            Instr::Concat(n) => {
+                let mut s = String::new();
+                for part in self.pop_many(*n)? {
+                    if let Value::String(part) = part {
+                        s += &part.borrow();
+                    }
+                }
+                self.stack.push(Value::String(Rc::new(RefCell::new(s))));
+            },
+            Instr::MakeList(n, r) => {
+                let items = self.pop_many(*n)?;
+                let _site = exec::enter_site(*r);
+                self.stack.push(Value::new_list(items.into()));
+            },
+            Instr::MakeListClone(r_item, r_num) => {
+                let num = self.pop()?;
+                let item = self.pop()?;
+                let n = match num {
+                    Value::Int(x) => x.max(0) as usize,
+                    val => return Err(ExecError::NotNumeric(val.get_type_name())).map_err(at(*r_num)).map_err(src_map),
+                };
+                let mut items = exec::reserve_items(n).map_err(at(*r_num)).map_err(src_map)?;
+                let _site = exec::enter_site(r_item.union(r_num));
+                for _ in 0..n {
+                    items.push(item
+                        .eval_clone(UnaryOpRef { op: r_item.union(r_num), expr: *r_item })
+                        .map_err(at(*r_item))
+                        .map_err(src_map)?);
+                }
+                self.stack.push(Value::new_list(items.into()));
+            },
+            Instr::MakeMap(n, r) => {
+                let mut items = self.pop_many(*n * 2)?.into_iter();
+                let mut map = exec::Map::new();
+                while let (Some(key), Some(val)) = (items.next(), items.next()) {
+                    map.insert(key, val);
+                }
+                let _site = exec::enter_site(*r);
+                self.stack.push(Value::new_map(map));
+            },
+            Instr::MakeFn(code, f) => self.stack.push(Value::Fn(code.clone(), f.clone(), self.env.clone())),
+            Instr::Call(n, r_callee, r_args) => {
+                let args = self.pop_many(*n)?;
+                let callee = self.pop()?;
+                let res = callee.call(args, ctx, src, *r_callee, *r_args)?;
+                self.stack.push(res);
+            },
+            Instr::CallNamed(n, names, r_callee, r_args) => {
+                let named = self.pop_many(names.len())?;
+                let args = self.pop_many(*n)?;
+                let callee = self.pop()?;
+                let named = names.iter().map(|(name, r)| Node(name.clone(), *r)).zip(named).collect();
+                let res = callee.call_named(args, named, ctx, src, *r_callee, *r_args)?;
+                self.stack.push(res);
+            },
+            Instr::CallMethod(n, names, r_callee, r_args) => {
+                let named = self.pop_many(names.len())?;
+                let args = self.pop_many(*n)?;
+                let callee = self.pop()?;
+                let recv = self.pop()?;
+                let named = names.iter().map(|(name, r)| Node(name.clone(), *r)).zip(named).collect();
+                let res = callee.call_method(Some(recv), args, named, ctx, src, *r_callee, *r_args)?;
+                self.stack.push(res);
+            },
+            // Calls to functions written in Forge are left to the caller of the function being run, which returns null
+            // in their place
+            Instr::TailCall(n, names, is_method, r_callee, r_args) => {
+                let named = self.pop_many(names.len())?;
+                let args = self.pop_many(*n)?;
+                let callee = self.pop()?;
+                let recv = if *is_method { Some(self.pop()?) } else { None };
+                let named = names.iter().map(|(name, r)| Node(name.clone(), *r)).zip(named).collect();
+                let res = match callee {
+                    Value::Fn(code, f, env) => {
+                        ctx.tail_call = Some(TailCall {
+                            code, f, env, recv, args, named,
+                            src: src.clone(),
+                            r_caller: *r_callee,
+                            r_args: *r_args,
+                        });
+                        Value::Null
+                    },
+                    callee => callee.call_method(recv, args, named, ctx, src, *r_callee, *r_args)?,
+                };
+                self.stack.push(res);
+            },
+
+            Instr::PushScope => self.env = self.env.child(),
+            Instr::PopScope => self.env = self.env.parent().cloned().ok_or(ExecError::Internal("the VM left more scopes than it entered"))?,
+            // Loops end by jumping back to their start, so this is where long-running code notices interrupts
+            Instr::Jump(target) => {
+                ctx.poll_interrupt(src, SrcRef::empty())?;
+                *pc = *target;
+            },
+            Instr::JumpIfFalse(target, r) => if !self.pop()?.eval_truth(*r)? {
+                *pc = *target;
+            },
+            Instr::JumpIfFalsy(target) => if self.top()?.is_truthy() {
+                self.pop()?;
+            } else {
+                *pc = *target;
+            },
+            Instr::JumpIfTruthy(target) => if self.top()?.is_truthy() {
+                *pc = *target;
+            } else {
+                self.pop()?;
+            },
+            Instr::JumpIfNull(target) => if *self.top()? == Value::Null {
+                *pc = *target;
+            },
+            Instr::JumpIfNotNull(target) => if *self.top()? == Value::Null {
+                self.pop()?;
+            } else {
+                *pc = *target;
+            },
+            Instr::IterStart(r) => {
+                let iter = self.pop()?.eval_iter(*r)?;
+                self.iters.push(Iter::Values(iter));
+            },
+            Instr::IterStartPairs(r) => {
+                let iter = self.pop()?.eval_iter_pairs(*r)?;
+                self.iters.push(Iter::Pairs(iter));
+            },
+            Instr::IterNext(target) => match self.iters.last_mut() {
+                Some(Iter::Values(iter)) => match iter.next() {
+                    Some(val) => self.stack.push(val),
+                    None => {
+                        self.iters.pop();
+                        *pc = *target;
+                    },
+                },
+                Some(Iter::Pairs(iter)) => match iter.next() {
+                    Some((key, val)) => {
+                        self.stack.push(key);
+                        self.stack.push(val);
+                    },
+                    None => {
+                        self.iters.pop();
+                        *pc = *target;
+                    },
+                },
+                None => *pc = *target,
+            },
+
+            Instr::Print(r) => {
+                let text = self.pop()?.get_display_text_with(&ctx.display).map_err(at(*r)).map_err(src_map)?;
+                ctx.io.print(text).map_err(at(*r)).map_err(src_map)?;
+            },
+            Instr::TryStart(target) => self.handlers.push(Handler {
+                target: *target,
+                stack_len: self.stack.len(),
+                env: self.env.clone(),
+                iters_len: self.iters.len(),
+            }),
+            Instr::TryEnd => { self.handlers.pop(); },
+
+            Instr::Return(target) => {
+                let val = self.pop()?;
+                self.env = self.scope.env().clone();
+                self.iters.clear();
+                self.handlers.clear();
+                self.stack.clear();
+                self.stack.push(val);
+                *pc = *target;
+            },
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(any(feature = "gzip", feature = "zstd"))]
+
+use forge::Engine;
+
+// "hello hello hello" as bytes
+const HELLO: &str = "[104, 101, 108, 108, 111, 32, 104, 101, 108, 108, 111, 32, 104, 101, 108, 108, 111]";
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip() {
+    let mut engine = Engine::default();
+
+    // Written by another implementation
+    assert!(engine.eval(&format!(
+        "compress.gunzip([31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 203, 72, 205, 201, 201, 87, 200, 64, 144, 0, 128, 136, 249, 229, 17, 0, 0, 0]) == {}",
+        HELLO,
+    )).unwrap() == true);
+
+    engine.exec(r#"
+        var text = "";
+        for i in 0..1000 {
+            text += "line {i}\n";
+        }
+        var count = |xs| {
+            var n = 0;
+            for x in xs {
+                n += 1;
+            }
+            return n;
+        };
+        var packed = compress.gzip(text);
+        var fast = compress.gzip(text, 1);
+        var size = count(packed);
+        var unpacked_len = count(compress.gunzip(packed));
+        var fast_len = count(compress.gunzip(fast));
+    "#).unwrap();
+    assert!(engine.eval("packed[0] == 31 and packed[1] == 139").unwrap() == true);
+    assert!(engine.eval("size < 3000").unwrap() == true);
+    assert!(engine.eval("unpacked_len").unwrap() == 8890.0);
+    assert!(engine.eval("fast_len").unwrap() == 8890.0);
+    assert!(engine.eval(&format!("compress.gunzip(compress.gzip({})) == {}", HELLO, HELLO)).unwrap() == true);
+
+    assert!(engine.eval("compress.gunzip([1, 2, 3])").is_err());
+    assert!(engine.eval("compress.gunzip(packed[0..10])").is_err());
+    assert!(engine.eval("compress.gzip(text, 10)").is_err());
+}
+
+#[cfg(feature = "zstd")]
+#[test]
+fn zstd() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(&format!(
+        "compress.unzstd([40, 181, 47, 253, 4, 88, 101, 0, 0, 48, 104, 101, 108, 108, 111, 32, 1, 0, 49, 74, 17, 162, 237, 30, 12]) == {}",
+        HELLO,
+    )).unwrap() == true);
+    assert!(engine.eval(&format!("compress.unzstd(compress.zstd({}, 19)) == {}", HELLO, HELLO)).unwrap() == true);
+    // Concatenated frames decompress to the concatenation of their contents
+    assert!(engine.eval("compress.unzstd(compress.zstd(\"ab\") + compress.zstd(\"c\")) == [97, 98, 99]").unwrap() == true);
+
+    assert!(engine.eval("compress.unzstd([])").is_err());
+    assert!(engine.eval("compress.unzstd([40, 181, 47, 253, 4])").is_err());
+    assert!(engine.eval("compress.zstd(\"x\", 0)").is_err());
+}
@@ -0,0 +1,75 @@
+use forge::Engine;
+
+const DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/config");
+
+fn engine() -> Engine {
+    Engine::build()
+        .with_global("dir", DIR)
+        .allow_fs()
+        .allow_env()
+        .finish()
+}
+
+#[test]
+fn load_toml() {
+    let mut engine = engine();
+
+    engine.exec(r#"
+        var all = config.load(dir + "/settings.toml");
+        var typed = config.load(dir + "/settings.toml", [
+            "name": "string",
+            "workers": "number",
+            "tags": "list",
+            "debug": "bool",
+            "db": ["host": "string", "port": "number"],
+            "timeout": "number?",
+        ]);
+        var ratio = all["ratio"];
+        var host = all["db"]["host"];
+        var tags = typed["tags"];
+        var port = typed["db"]["port"];
+        var timeout = typed["timeout"];
+    "#).unwrap();
+    assert!(engine.take("ratio").unwrap() == 0.5);
+    assert!(engine.take("host").unwrap() == "localhost");
+    assert!(engine.take("tags").unwrap().get_display_text().unwrap() == "[a, b]");
+    // The port is written as a string, but the schema asks for a number
+    assert!(engine.take("port").unwrap() == 5432.0);
+    assert!(engine.take("timeout").unwrap().is_null());
+}
+
+#[test]
+fn load_errors() {
+    let mut engine = engine();
+
+    let err = engine.exec(r#"config.load(dir + "/settings.toml", ["user": "string", "db": ["password": "string"]]);"#).unwrap_err().to_string();
+    assert!(err.contains("settings.toml' is missing the settings 'db.password', 'user'."));
+
+    let err = engine.exec(r#"config.load(dir + "/settings.toml", ["name": "number"]);"#).unwrap_err().to_string();
+    assert!(err.contains("The setting 'name' on line 2 of"));
+    assert!(err.contains("should be of type 'number', but is demo app of type 'string'."));
+
+    let err = engine.exec(r#"config.load(dir + "/broken.toml");"#).unwrap_err().to_string();
+    assert!(err.contains("Could not parse line 2 of"));
+
+    assert!(engine.exec(r#"config.load(dir + "/settings.toml", ["name": "text"]);"#).is_err());
+    assert!(Engine::default().exec(r#"config.load("settings.toml");"#).unwrap_err().to_string().contains("'fs_read' permission"));
+}
+
+#[test]
+fn load_env() {
+    let mut engine = engine();
+    std::env::set_var("FORGE_TEST_CFG_C", "from the environment");
+
+    engine.exec(r#"
+        var vars = config.load_env(dir + "/.env");
+        var typed = config.load(dir + "/.env", ["FORGE_TEST_CFG_PORT": "number"]);
+        var port = typed["FORGE_TEST_CFG_PORT"];
+    "#).unwrap();
+    assert_eq!(std::env::var("FORGE_TEST_CFG_A").unwrap(), "hello");
+    assert_eq!(std::env::var("FORGE_TEST_CFG_B").unwrap(), "two\nlines");
+    // Variables that are already set take precedence over the file
+    assert_eq!(std::env::var("FORGE_TEST_CFG_C").unwrap(), "from the environment");
+    assert!(engine.take("port").unwrap() == 8080.0);
+    assert!(engine.eval(r#"vars["FORGE_TEST_CFG_C"]"#).unwrap() == "plain value");
+}
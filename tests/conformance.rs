@@ -0,0 +1,43 @@
+use forge::{ExecMode, conformance::{Case, Problem, find_cases}};
+
+#[test]
+fn grammar_corpus() {
+    let cases = find_cases("tests/grammar").unwrap();
+    assert!(!cases.is_empty());
+
+    let failures = cases
+        .iter()
+        .map(|path| Case::load(path).unwrap())
+        .flat_map(|case| case.run().into_iter().map(move |mismatch| format!("{}: {}", case.path().display(), mismatch)))
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn mismatches() {
+    let case = Case::parse("output.fg", String::from(r#"
+        print 1;    # expect: 1
+        print 3;    # expect: 2
+    "#));
+    let mismatches = case.run();
+    assert_eq!(mismatches.len(), 2);
+    assert_eq!(mismatches[0].mode, ExecMode::Interpret);
+    match &mismatches[1].problem {
+        Problem::Output(diff) => assert_eq!(diff, &["-2", "+3"]),
+        problem => panic!("{:?}", problem),
+    }
+    let text = mismatches[0].to_string();
+    assert!(text.contains("the output differs (-expected +actual):\n-2\n+3"), "{}", text);
+
+    let case = Case::parse("error.fg", String::from("print nothing; # expect-error: something"));
+    match &case.run()[0].problem {
+        Problem::WrongError(expected, found) => assert!(expected == "something" && found.contains("nothing"), "{}", found),
+        problem => panic!("{:?}", problem),
+    }
+
+    let case = Case::parse("no_error.fg", String::from("var x = 1; # expect-error: anything"));
+    assert!(case.run()[0].to_string().contains("did not fail with the expected error: anything"));
+
+    let case = Case::parse("unexpected.fg", String::from("print missing;"));
+    assert!(case.run()[1].to_string().contains("When run as bytecode, the case failed unexpectedly"));
+}
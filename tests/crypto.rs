@@ -0,0 +1,51 @@
+use forge::Engine;
+
+// RFC 8032, section 7.1, test 1
+const RFC_PUBLIC: &str = "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a";
+const RFC_SIG: &str = "e5564300c360ac729086e2cc806e828a84877f1eb8e5d974d873e065224901555fb8821590a33bacc61e39701cf9b46bd25bf5f0595bbe24655141438e7a100b";
+
+// Made with the private key 00 01 02 ... 1f, signing a timestamp followed by a body as some webhook providers do
+const PUBLIC: &str = "03a107bff3ce10be1d70dd18e74bc09967e4d6309ba50d5f1ddc8664125531b8";
+const SIG: &str = "22a8327298877415973ce8d3e6474d003ad5b4f0df7f9d09b905cbe719a04fcd43c811d3e5ec27736b6a207989642a5bb36917d1058328f052a1ff69ed2ccf0d";
+
+#[test]
+fn hashes() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(r#"crypto.sha256("")"#).unwrap() == "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert!(engine.eval(&format!(r#"crypto.sha256("{}")"#, "a".repeat(200))).unwrap() == "c2a908d98f5df987ade41b5fce213067efbcc21ef2240212a41e54b5e7c28ae5");
+    assert!(engine.eval(r#"crypto.hmac_sha256("secret", "hello")"#).unwrap() == "88aab3ede8d3adf94d26ab90d3bafd4a2083070c3bcce9c014ee04a443847c0b");
+    // Keys longer than a block are hashed
+    assert!(engine.eval(&format!(r#"crypto.hmac_sha256("{}", [104, 101, 108, 108, 111])"#, "k".repeat(100))).unwrap() == "3fd578d761a4f6703009e92d2e8a0aea22ab9ab50d4ad250dc95d8c369dec3f5");
+    assert!(engine.eval("crypto.sha256(5)").is_err());
+    assert!(engine.eval("crypto.sha256([256])").is_err());
+}
+
+#[test]
+fn ed25519() {
+    let mut engine = Engine::default();
+
+    engine.exec(&format!(r#"
+        var valid = crypto.verify_ed25519("{}", "{}", "");
+        var webhook = crypto.verify_ed25519("{}", "{}", "1700000000 type=ping");
+        var tampered = crypto.verify_ed25519("{}", "{}", "1700000001 type=ping");
+        var wrong_key = crypto.verify_ed25519("{}", "{}", "");
+    "#, RFC_PUBLIC, RFC_SIG, PUBLIC, SIG, PUBLIC, SIG, PUBLIC, RFC_SIG)).unwrap();
+
+    assert!(engine.take("valid").unwrap() == true);
+    assert!(engine.take("webhook").unwrap() == true);
+    assert!(engine.take("tampered").unwrap() == false);
+    assert!(engine.take("wrong_key").unwrap() == false);
+    assert!(engine.eval(&format!(r#"crypto.verify_ed25519("{}", "abcd", "")"#, RFC_PUBLIC)).is_err());
+    assert!(engine.eval(&format!(r#"crypto.verify_ed25519("{}", "{}", "")"#, "zz".repeat(32), RFC_SIG)).is_err());
+}
+
+#[test]
+fn constant_time_equal() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(r#"crypto.equal("sha256=abc", "sha256=abc")"#).unwrap() == true);
+    assert!(engine.eval(r#"crypto.equal("sha256=abc", "sha256=abd")"#).unwrap() == false);
+    assert!(engine.eval(r#"crypto.equal("abc", "abcd")"#).unwrap() == false);
+    assert!(engine.eval(r#"crypto.equal([1, 2], [1, 2])"#).unwrap() == true);
+}
@@ -0,0 +1,64 @@
+use forge::Engine;
+
+#[test]
+fn lines() {
+    let mut engine = Engine::default();
+    let old = (1..=20).map(|i| format!("line {}", i)).collect::<Vec<_>>().join("\n") + "\n";
+    let new = old.replace("line 2\n", "line two\n").replace("line 4\n", "").replace("line 18\n", "line 18\nline 18.5\n");
+    engine.set("old", old.as_str());
+    engine.set("new", new.as_str());
+
+    engine.exec(r#"
+        var hunks = diff.lines(old, new);
+        var count = 0;
+        for hunk in hunks {
+            count += 1;
+        }
+        var first = hunks[0];
+        var second = hunks[1];
+        var tight = diff.lines(old, new, 0);
+        var same = diff.lines(old, old);
+    "#).unwrap();
+    assert!(engine.take("count").unwrap() == 2.0);
+    assert!(engine.eval("first.old_start").unwrap() == 1.0);
+    assert!(engine.eval("first.old_count").unwrap() == 7.0);
+    assert!(engine.eval("first.new_start").unwrap() == 1.0);
+    assert!(engine.eval("first.new_count").unwrap() == 6.0);
+    assert!(engine.eval("first.lines[0]").unwrap() == " line 1");
+    assert!(engine.eval("first.lines[1]").unwrap() == "-line 2");
+    assert!(engine.eval("first.lines[2]").unwrap() == "+line two");
+    assert!(engine.eval("first.lines[4]").unwrap() == "-line 4");
+    assert!(engine.eval("second.old_start").unwrap() == 16.0);
+    assert!(engine.eval("second.new_start").unwrap() == 15.0);
+    assert!(engine.eval("second.lines[3]").unwrap() == "+line 18.5");
+    assert!(engine.eval("tight[1].old_start").unwrap() == 4.0);
+    assert!(engine.eval("tight[1].old_count").unwrap() == 1.0);
+    assert!(engine.eval("tight[1].new_count").unwrap() == 0.0);
+    assert!(engine.eval("same[0]").unwrap().is_null());
+
+    assert!(engine.exec("diff.lines(old, new, -1);").is_err());
+}
+
+#[test]
+fn apply() {
+    let mut engine = Engine::default();
+    let old = (1..=30).map(|i| format!("{}", i * i)).collect::<Vec<_>>().join("\n");
+    let new = old.replacen("16", "sixteen", 1).replace("\n900", "") + "\nend";
+    engine.set("old", old.as_str());
+    engine.set("new", new.as_str());
+
+    engine.exec(r#"
+        var patched = diff.apply(old, diff.lines(old, new));
+        var from_empty = diff.apply("", diff.lines("", new));
+        var to_empty = diff.apply(old, diff.lines(old, ""));
+        var unchanged = diff.apply(old, []);
+    "#).unwrap();
+    assert!(engine.take("patched").unwrap() == new.as_str());
+    assert!(engine.take("from_empty").unwrap() == new.as_str());
+    assert!(engine.take("to_empty").unwrap() == "");
+    assert!(engine.take("unchanged").unwrap() == old.as_str());
+
+    assert!(engine.exec(r#"diff.apply("a\nb", diff.lines("a\nc", "a\nd"));"#).unwrap_err().to_string().contains("Hunk 1 does not match line 2."));
+    assert!(engine.exec(r#"diff.apply("a", [["old_start": 5, "lines": []]]);"#).unwrap_err().to_string().contains("Hunk 1 does not apply at line 5."));
+    assert!(engine.exec(r#"diff.apply("a", [5]);"#).unwrap_err().to_string().contains("Hunk 1 is not a hunk"));
+}
@@ -0,0 +1,80 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+};
+use forge::{Engine, DisplayOptions, IntFormat, ExecResult, Io};
+
+struct CaptureIo(Rc<RefCell<Vec<String>>>);
+
+impl Io for CaptureIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(String::new())
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.0.borrow_mut().push(s);
+        Ok(())
+    }
+}
+
+#[test]
+fn print_options() {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::build()
+        .with_io(CaptureIo(output.clone()))
+        .with_display(DisplayOptions {
+            float_precision: Some(2),
+            int_format: IntFormat::Grouped,
+            max_list_len: Some(3),
+            ..DisplayOptions::default()
+        })
+        .finish();
+
+    engine.exec(r#"
+        print 1 / 3;
+        print 1234567;
+        print -1000;
+        print [1, 2, 3, 4, 5];
+        print [1, 2];
+    "#).unwrap();
+
+    assert_eq!(*output.borrow(), vec!["0.33", "1,234,567", "-1,000", "[1, 2, 3, ...]", "[1, 2]"]);
+
+    engine.display_options_mut().int_format = IntFormat::Hex;
+    let val = engine.eval("255").unwrap();
+    assert_eq!(engine.display(&val).unwrap(), "0xff");
+}
+
+#[test]
+fn default_display() {
+    let engine = Engine::default();
+
+    assert_eq!(engine.display(&2.5.into()).unwrap(), "2.5");
+    assert_eq!(engine.display(&1000.into()).unwrap(), "1000");
+    // Floats keep their decimal point, so they can be told apart from ints
+    assert_eq!(engine.display(&1000.0.into()).unwrap(), "1000.0");
+}
+
+#[test]
+fn cyclic_values() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var l = [1, 2];
+        l[1] = l;
+        var m = ["a": 1];
+        m["self"] = [m, l];
+        var deep = [[[[1]]]];
+    "#).unwrap();
+
+    let l = engine.take("l").unwrap();
+    assert_eq!(engine.display(&l).unwrap(), "[1, <cycle>]");
+    assert_eq!(format!("{}", l), "[1, <cycle>]");
+
+    let m = engine.take("m").unwrap();
+    assert!(engine.display(&m).unwrap().contains("self: [<cycle>, [1, <cycle>]]"));
+
+    engine.display_options_mut().max_depth = Some(2);
+    let deep = engine.take("deep").unwrap();
+    assert_eq!(engine.display(&deep).unwrap(), "[[[...]]]");
+}
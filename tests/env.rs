@@ -0,0 +1,38 @@
+use forge::Engine;
+
+#[test]
+fn variables_and_cwd() {
+    let mut engine = Engine::build().allow_env().finish();
+    let original = std::env::current_dir().unwrap();
+    let modules = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/modules");
+
+    engine.exec(r#"
+        env.set("FORGE_TEST_VAR", "hello");
+        var value = env.get("FORGE_TEST_VAR");
+        env.set("FORGE_TEST_VAR", null);
+        var removed = env.get("FORGE_TEST_VAR");
+        var start = env.cwd();
+    "#).unwrap();
+    assert!(engine.take("value").unwrap() == "hello");
+    assert!(engine.take("removed").unwrap().is_null());
+    assert!(engine.take("start").unwrap() == original.to_string_lossy().as_ref());
+    assert!(engine.exec("env.set(\"BAD=NAME\", 1);").is_err());
+
+    engine.set("dir", modules);
+    engine.exec("env.set_cwd(dir);").unwrap();
+    assert_eq!(std::env::current_dir().unwrap(), std::path::Path::new(modules).canonicalize().unwrap());
+    assert!(engine.exec("env.set_cwd(\"no/such/dir\");").is_err());
+
+    std::env::set_current_dir(original).unwrap();
+}
+
+#[test]
+fn permission_required() {
+    let mut engine = Engine::build().allow_fs().finish();
+
+    let err = engine.exec("env.cwd();").unwrap_err().to_string();
+    assert!(err.contains("'env' permission"));
+    assert!(engine.exec("env.get(\"PATH\");").is_err());
+    assert!(engine.exec("env.set(\"FORGE_DENIED\", 1);").is_err());
+    assert!(std::env::var("FORGE_DENIED").is_err());
+}
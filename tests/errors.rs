@@ -0,0 +1,239 @@
+use forge::{Engine, ExecMode, ForgeError, DiagnosticStyle, NoIo};
+
+#[test]
+fn binary_op_operands() {
+    let mut engine = Engine::default();
+
+    let msg = format!("{}", engine.eval(r#""hello" + [1, 2] * 3"#).unwrap_err());
+    assert!(msg.contains("Cannot apply binary operator 'mul' to values of types 'list' and 'int'."));
+    assert!(msg.contains("Left operand is [1, 2] of type 'list'."));
+    assert!(msg.contains("Right operand is 3 of type 'int'."));
+
+    let msg = format!("{}", engine.eval(r#""a very long string that will not fit" - 1"#).unwrap_err());
+    assert!(msg.contains("Left operand is \"a very long string that ...\" of type 'string'."));
+}
+
+#[test]
+fn multiple_parse_errors() {
+    let mut engine = Engine::default();
+
+    let msg = format!("{}", engine.exec(r#"
+        var x = 5 +;
+        if x > 3 {
+            print x
+        }
+        var y = ;
+        print "fine";
+    "#).unwrap_err());
+    assert_eq!(msg.matches("[ERROR] Parsing error").count(), 3);
+
+    let msg = format!("{}", engine.exec(r#"
+        var f = |x| { return x; };
+        var y = f(1;
+        var z = 2;
+    "#).unwrap_err());
+    assert_eq!(msg.matches("[ERROR] Parsing error").count(), 1);
+
+    // Statements inside blocks and functions are skipped without leaving the block
+    let msg = format!("{}", engine.exec(r#"
+        fn f(x) {
+            var a = ;
+            if x {
+                var b = 1 +;
+                print b;
+            }
+            return x;
+        }
+        var c = ;
+        }
+        print f(1);
+    "#).unwrap_err());
+    assert_eq!(msg.matches("[ERROR] Parsing error").count(), 4, "{}", msg);
+    assert!(engine.exec("fn g() {\n    var a = ").unwrap_err().is_incomplete());
+}
+
+#[test]
+fn function_call_stack() {
+    let mut engine = Engine::default();
+
+    let msg = format!("{}", engine.exec(r#"
+        fn fail(x) {
+            return x + [];
+        }
+        fn apply(f, x) {
+            return f(x);
+        }
+        apply(fail, 1);
+    "#).unwrap_err());
+    assert!(msg.contains("Runtime error at 3:22"));
+    let inner = msg.find("...in function 'fail', called at 6:20.").unwrap();
+    let outer = msg.find("...in function 'apply', called at 8:9.").unwrap();
+    assert!(inner < outer);
+
+    let msg = format!("{}", engine.exec("var f = |x| { return -[x]; };\nf(1);").unwrap_err());
+    assert!(msg.contains("...in anonymous function, called at 2:1."));
+}
+
+#[test]
+fn incomplete_input() {
+    let mut engine = Engine::default();
+
+    for code in &["while x < 10 {", "var x = ", "var xs = [1, 2", "print \"abc", "if x { } else", "fn f(a, b", "var x = /* a comment"] {
+        assert!(engine.exec(code).unwrap_err().is_incomplete(), "{:?} should be incomplete", code);
+        assert!(engine.prompt(code).unwrap_err().is_incomplete(), "{:?} should be incomplete", code);
+    }
+    for code in &["var x = ;", "1 + ) {", "var x = 1; x + [];"] {
+        assert!(!engine.exec(code).unwrap_err().is_incomplete(), "{:?} should not be incomplete", code);
+    }
+
+    let msg = format!("{}", engine.exec("while true {").unwrap_err());
+    assert!(msg.contains("Parsing error at end of input"));
+    assert!(engine.prompt("var y = [\n1,\n2];").is_ok());
+}
+
+#[test]
+fn prompt_continuation() {
+    let mut engine = Engine::default();
+
+    assert!(engine.prompt("var x = 2;").unwrap().is_none());
+    assert!(engine.prompt("x").unwrap().unwrap() == 2.0);
+    assert!(engine.prompt("x +").unwrap_err().is_incomplete());
+    assert!(engine.prompt("x +\n1").unwrap().unwrap() == 3.0);
+    assert!(engine.prompt("[x,\n x]").unwrap().unwrap().to_string() == "[2, 2]");
+    assert!(!engine.prompt("y").unwrap_err().is_incomplete());
+}
+
+#[test]
+fn diagnostic_styles() {
+    let mut engine = Engine::default();
+
+    let err = engine.exec("var a = 1;\nvar b = 2;\nvar c = a + [];\nvar d = 4;\nvar e = 5;\nvar f = 6;").unwrap_err();
+
+    let plain = format!("{}", err);
+    assert_eq!(plain, format!("{}", err.display_with(DiagnosticStyle::Plain)));
+    assert!(!plain.contains("var b"));
+
+    // Surrounding lines are shown with their line numbers
+    let rich = format!("{}", err.display_with(DiagnosticStyle::Rich));
+    assert!(rich.contains("   1 | var a = 1;"));
+    assert!(rich.contains("   3 | var c = a + [];"));
+    assert!(rich.contains("   5 | var e = 5;"));
+    assert!(!rich.contains("var f"));
+    assert!(!rich.contains('\x1b'));
+
+    let colored = format!("{}", err.display_with(DiagnosticStyle::Colored));
+    assert!(colored.contains("\x1b[1;31m[ERROR]\x1b[0m"));
+    assert!(colored.contains("var c = a + [];"));
+}
+
+#[test]
+fn json_diagnostics() {
+    let mut engine = Engine::default();
+
+    let json = engine.exec("var x = 5 +;\nprint \"fine\";").unwrap_err().to_json();
+    assert!(json.starts_with(r#"{"errors":[{"kind":"parse","#));
+    assert!(json.contains(r#""location":{"start":{"line":1,"col":12},"end":{"line":1,"col":13}}"#));
+    assert!(json.contains(r#""expected":"primary expression","found":"';'""#));
+    assert!(json.contains(r#""while_parsing":["variable declaration"]"#));
+    assert!(json.contains(r#""incomplete":false"#));
+
+    let json = engine.exec(r#"
+        fn fail(x) {
+            return x + "a \"quoted\" string";
+        }
+        fail(1);
+    "#).unwrap_err().to_json();
+    assert!(json.contains(r#""kind":"runtime""#));
+    assert!(json.contains(r#""message":"Cannot apply binary operator 'add' to values of types 'int' and 'string'.""#));
+    assert!(json.contains(r#""trace":[{"function":"fail","called_at":{"line":5,"col":9}}]"#));
+    assert!(json.contains(r#"{"message":"Right operand is of type 'string'.","location":{"start":{"line":3,"col":24},"end":{"line":3,"col":45}}}"#));
+}
+
+#[test]
+fn no_panics() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        let msg = format!("{}", engine.exec("var f = |n| { return 1 + f(n + 1); }; f(0);").unwrap_err());
+        assert!(msg.contains("nested too deeply, and would run out of stack space"), "{}", msg);
+        assert!(msg.contains("times)"), "{}", msg);
+
+        let code = format!("print {}1{};", "(".repeat(100000), ")".repeat(100000));
+        let msg = format!("{}", engine.exec(&code).unwrap_err());
+        assert!(msg.contains("The code is nested too deeply to be parsed."), "{}", msg);
+
+        let msg = format!("{}", engine.exec("print [0; 9223372036854775807];").unwrap_err());
+        assert!(msg.contains("Cannot make a list of 9223372036854775807 items"), "{}", msg);
+
+        // Lists and maps nested deeply enough to overflow the stack if dropped recursively are freed one at a time
+        engine.safe_exec("var l = []; for i in 0..200000 { l = [l]; } l = null;").unwrap();
+        engine.safe_exec(r#"var m = ["a": 1]; for i in 0..200000 { m = ["a": m, "b": [m]]; } m = null;"#).unwrap();
+    }
+
+    let mut engine = Engine::build().with_fn("explode", 0, |_| panic!("boom")).finish();
+    let err = engine.safe_exec("explode();").unwrap_err();
+    match &err {
+        ForgeError::Internal(msg) => assert_eq!(msg, "boom"),
+        err => panic!("{}", err),
+    }
+    assert!(err.to_string().contains("Forge failed unexpectedly: boom."));
+    assert!(err.to_json().contains(r#""kind":"internal""#), "{}", err.to_json());
+    assert_eq!(Engine::default().safe_eval("1 + 2").unwrap(), forge::Value::Int(3));
+}
+
+#[test]
+fn call_depth_limit() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).with_max_call_depth(Some(4)).finish();
+        engine.exec(r#"
+            fn depth(n) {
+                if n == 0 { return 0; }
+                return 1 + depth(n - 1);
+            }
+            fn count(n, total) {
+                if n == 0 { return total; }
+                return count(n - 1, total + 1);
+            }
+        "#).unwrap();
+
+        assert!(engine.eval("depth(3)").unwrap() == 3);
+        // Tail calls leave the call that made them, so they do not count towards the limit
+        assert!(engine.eval("count(100, 0)").unwrap() == 100);
+
+        let msg = format!("{}", engine.eval("depth(4)").unwrap_err());
+        assert!(msg.contains("Functions were called within each other more deeply than the engine allows."), "{}", msg);
+        assert!(msg.contains("Runtime error at 4:24"), "{}", msg);
+        assert!(msg.contains("...in function 'depth', called at 4:28 (3 times)."), "{}", msg);
+
+        engine.exec(r#"
+            var caught = null;
+            try {
+                depth(20);
+            } catch e {
+                caught = e.message;
+            }
+        "#).unwrap();
+        assert!(engine.take("caught").unwrap() == "Functions were called within each other more deeply than the engine allows.");
+        assert!(engine.eval("depth(2)").unwrap() == 2);
+
+        engine.set_max_call_depth(Some(5));
+        assert!(engine.eval("depth(4)").unwrap() == 4);
+    }
+}
+
+#[test]
+fn no_io() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).with_io(NoIo).finish();
+
+        let msg = format!("{}", engine.exec("var x = 1;\nprint x + 1;").unwrap_err());
+        assert!(msg.contains("This requires the 'io' permission, which the engine has not been granted."), "{}", msg);
+        assert!(msg.contains("Runtime error at 2:7"), "{}", msg);
+
+        let msg = format!("{}", engine.eval(r#"input "Name? ""#).unwrap_err());
+        assert!(msg.contains("This requires the 'io' permission"), "{}", msg);
+
+        engine.exec(r#"var caught = false; try { print "hi"; } catch e { caught = true; }"#).unwrap();
+        assert!(engine.eval("caught").unwrap() == true);
+    }
+}
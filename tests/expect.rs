@@ -0,0 +1,167 @@
+use forge::{Engine, DiagnosticStyle, ExecMode};
+
+const MODES: [ExecMode; 2] = [ExecMode::Interpret, ExecMode::Bytecode];
+
+#[test]
+fn passing() {
+    for &mode in &MODES {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            expect(1 + 1).to_equal(2);
+            expect([1, [2, 3], ["a": "b"]]).to_equal([1, [2, 3], ["a": "b"]]);
+            expect([1, 2]).not_to_equal([1, 2, 3]);
+            expect([1, [2]]).to_contain([2]);
+            expect(["k": 1]).to_contain("k");
+            expect("forge").to_contain("org");
+            expect(true).to_be_truthy();
+            expect(null).to_be_falsy();
+        "#).unwrap();
+    }
+}
+
+#[test]
+fn structural_diff() {
+    for &mode in &MODES {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        let err = engine.exec(r#"var xs = [1, 2, ["a": 1, "b": [3]]];
+expect(xs).to_equal([1, 3, ["a": 1, "b": [4, 5]], "extra"]);"#).unwrap_err();
+        let text = format!("{}", err.display_with(DiagnosticStyle::Plain));
+        assert!(text.contains("Expected the values to be equal."), "{}", text);
+        assert!(text.contains("At [1]: expected 3, found 2."), "{}", text);
+        assert!(text.contains("At [2].b[0]: expected 4, found 3."), "{}", text);
+        assert!(text.contains("At [2].b[1]: expected 5, but the list ends."), "{}", text);
+        assert!(text.contains("At [3]: expected \"extra\", but the list ends."), "{}", text);
+        // Both the actual and the expected values are pointed out
+        assert!(text.contains("|       ^^^^"), "{}", text);
+        assert!(text.contains("|                    ^^^^^^^^^^"), "{}", text);
+
+        let json = err.to_json().to_string();
+        assert!(json.contains(r#""message":"actual value""#), "{}", json);
+        assert!(json.contains(r#""message":"expected value""#), "{}", json);
+        assert!(json.contains("At [1]: expected 3, found 2."), "{}", json);
+    }
+}
+
+#[test]
+fn failures() {
+    let mut engine = Engine::default();
+
+    let message = |engine: &mut Engine, code: &str| engine.exec(code).unwrap_err().to_string();
+    assert!(message(&mut engine, r#"expect(["x": 1]).to_equal(["x": 1, "y z": 2]);"#).contains(r#"At ["y z"]: expected 2, but the key is missing."#));
+    assert!(message(&mut engine, "expect([1]).to_equal([1, 2]);").contains("At [1]: expected 2, but the list ends."));
+    assert!(message(&mut engine, "expect(3).to_equal(\"3\");").contains("expected \"3\", found 3."));
+    assert!(message(&mut engine, "expect(1).not_to_equal(1);").contains("Expected the values to differ, but both are 1."));
+    assert!(message(&mut engine, "expect([1, 2]).to_contain(3);").contains("Expected [1, 2] to contain 3."));
+    assert!(message(&mut engine, "expect(0).to_be_falsy();").contains("Expected a falsy value, found 0."));
+    assert!(message(&mut engine, "expect(null).to_be_truthy();").contains("Expected a truthy value, found <null>."));
+    assert!(message(&mut engine, "expect(0..20).to_equal(20..40);").contains("expected 20..40, found 0..20."));
+
+    // Long lists of differences are cut short
+    let text = message(&mut engine, "expect([]).to_equal([1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12]);");
+    assert!(text.contains("...and 2 more."), "{}", text);
+}
+
+#[test]
+fn snapshots() {
+    let dir = std::env::temp_dir().join(format!("forge-snapshots-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let message = |engine: &mut Engine, code: &str| engine.exec(code).unwrap_err().to_string();
+    let value = r#"["b": [1, 2], "a": "x"]"#;
+
+    // Snapshots are only recorded when asked to
+    let mut engine = Engine::build().with_snapshot_dir(&dir).finish();
+    let text = message(&mut engine, &format!(r#"expect_snapshot("point", {});"#, value));
+    assert!(text.contains("There is no snapshot named 'point' yet."), "{}", text);
+    assert!(text.contains(r#"+    "a": "x","#), "{}", text);
+
+    let mut engine = Engine::build().with_snapshot_dir(&dir).update_snapshots().finish();
+    engine.exec(&format!(r#"expect_snapshot("point", {});"#, value)).unwrap();
+    assert_eq!(
+        std::fs::read_to_string(dir.join("point.snap")).unwrap(),
+        "[\n    \"a\": \"x\",\n    \"b\": [\n        1,\n        2,\n    ],\n]\n",
+    );
+
+    let mut engine = Engine::build().with_snapshot_dir(&dir).finish();
+    engine.exec(&format!(r#"expect_snapshot("point", {});"#, value)).unwrap();
+    let text = message(&mut engine, r#"expect_snapshot("point", ["b": [1, 3], "a": "x"]);"#);
+    assert!(text.contains("The value does not match the snapshot 'point'."), "{}", text);
+    assert!(text.contains("-        2,") && text.contains("+        3,"), "{}", text);
+
+    assert!(message(&mut engine, r#"expect_snapshot("../escape", 1);"#).contains("'../escape' is not a valid snapshot name."));
+    assert!(message(&mut Engine::default(), r#"expect_snapshot("point", 1);"#).contains("Snapshots are not enabled."));
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn properties() {
+    for &mode in &MODES {
+        let mut engine = Engine::build().with_exec_mode(mode).with_seed(7).finish();
+        let message = |engine: &mut Engine, code: &str| engine.exec(code).unwrap_err().to_string();
+
+        engine.exec(r#"
+            check(|a: int, b: int| { return (a ^ b) ^ b == a; });
+            check(|x: num, s: str, c: char, b: bool| { return "{s}{c}" != null; }, 50);
+            check(|xs: list| { expect(xs + [1]).to_contain(1); });
+        "#).unwrap();
+
+        // Failing inputs are shrunk to the simplest that still fail
+        let text = message(&mut engine, "check(|x: int| { return x < 50; }, 200);");
+        assert!(text.contains("The property does not hold, found after"), "{}", text);
+        assert!(text.contains("x = 50"), "{}", text);
+        assert!(text.contains("The property returned false."), "{}", text);
+
+        let text = message(&mut engine, "check(|s: str| { var n = 0; for c in s { n += 1; } return n < 3; });");
+        assert!(text.contains(r#"s = "aaa""#), "{}", text);
+
+        let text = message(&mut engine, "check(|x: int, y: int| { expect(x).to_equal(y); });");
+        assert!(text.contains("x = 1") && text.contains("y = 0"), "{}", text);
+        assert!(text.contains("The property failed: Expected the values to be equal."), "{}", text);
+
+        assert!(message(&mut engine, "check(|x| { return true; });").contains("Parameter 'x' needs a type, such as 'x: int'"));
+        assert!(message(&mut engine, "check(|m: map| { return true; });").contains("Cannot generate values of type 'map' for parameter 'm'."));
+        assert!(message(&mut engine, "check(|x: int| { return true; }, 0);").contains("The number of runs must be a positive int, not 0."));
+    }
+}
+
+#[test]
+fn mocks() {
+    for &mode in &MODES {
+        let mut engine = Engine::build()
+            .with_exec_mode(mode)
+            .with_fn("fetch", 1, |_| panic!("the host function should be mocked"))
+            .allow_mocks()
+            .finish();
+
+        engine.exec(r#"
+            fn status(url) { return fetch(url) == "ok" ? "up" : "down"; }
+            var fetch_mock = mock("fetch", |url| { return url == "a" ? "ok" : "error"; });
+            expect(status("a")).to_equal("up");
+            expect(status("b")).to_equal("down");
+            expect(fetch_mock.count).to_equal(2);
+            expect(fetch_mock.calls).to_equal([["a"], ["b"]]);
+            expect(fetch_mock.called_with("b")).to_be_truthy();
+            expect(fetch_mock.called_with("c")).to_be_falsy();
+
+            # Builtins in namespaces are mocked by their full names
+            mock("env.get", |name| { return "mocked " + name; });
+            expect(env.get("HOME")).to_equal("mocked HOME");
+
+            # Spies record calls but still call the original
+            var token = spy("random.token");
+            var a = random.token(8);
+            expect(token.calls).to_equal([[8]]);
+            token.reset();
+            expect(token.count).to_equal(0);
+            token.restore();
+            random.token(8);
+            expect(token.count).to_equal(0);
+        "#).unwrap();
+    }
+
+    let message = |engine: &mut Engine, code: &str| engine.exec(code).unwrap_err().to_string();
+    let mut engine = Engine::build().allow_mocks().finish();
+    assert!(message(&mut engine, r#"mock("fetch", 1);"#).contains("Expected an argument of type 'function', found one of type 'int'."));
+    assert!(message(&mut Engine::default(), r#"mock("env.get", |x| { return x; });"#).contains("Mocks are not enabled."));
+}
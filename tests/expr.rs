@@ -1,4 +1,4 @@
-use forge::{Engine, Value};
+use forge::{Bounds, Engine, ExecMode, Value};
 
 #[test]
 fn literals() {
@@ -13,12 +13,70 @@ fn literals() {
     assert!(engine.eval(r#"true"#).unwrap() != false);
 }
 
+#[test]
+fn numeric_literals() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(r#"0xFF"#).unwrap() == 255.0);
+    assert!(engine.eval(r#"0b1010"#).unwrap() == 10.0);
+    assert!(engine.eval(r#"0o755"#).unwrap() == 493.0);
+    assert!(engine.eval(r#"1_000_000"#).unwrap() == 1000000.0);
+    assert!(engine.eval(r#"0xff_ff + 1_0.2_5"#).unwrap() == 65545.25);
+
+    let msg = engine.eval(r#"0b102"#).unwrap_err().to_string();
+    assert!(msg.contains("Invalid digit '2' in binary literal."));
+    assert!(msg.contains("Parsing error at 1:5"));
+    assert!(engine.eval(r#"0x"#).unwrap_err().to_string().contains("The hexadecimal literal has no digits."));
+    assert!(engine.eval(r#"12ab"#).unwrap_err().to_string().contains("Invalid digit 'a' in decimal literal."));
+}
+
+#[test]
+fn escape_sequences() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(r#""a\tb\nc""#).unwrap() == "a\tb\nc");
+    assert!(engine.eval(r#""\\ \" \{x\}""#).unwrap() == "\\ \" {x}");
+    assert!(engine.eval(r#""\u{1F600}\u{e9}""#).unwrap() == "\u{1F600}\u{e9}");
+    assert!(engine.eval(r#"'\u{41}'"#).unwrap() == Value::Char('A'));
+    assert!(engine.eval(r#"'\''"#).unwrap() == Value::Char('\''));
+
+    let msg = engine.eval(r#""bad \q escape""#).unwrap_err().to_string();
+    assert!(msg.contains("Parsing error at 1:6"));
+    assert!(msg.contains("Invalid escape sequence '\\q'."));
+    let msg = engine.eval(r#""\u{110000}""#).unwrap_err().to_string();
+    assert!(msg.contains("Parsing error at 1:2"));
+    assert!(msg.contains("Invalid unicode escape."));
+    assert!(engine.eval(r#""\u{}""#).is_err());
+    assert!(engine.eval(r#""\u41""#).is_err());
+}
+
 #[test]
 fn comments() {
     let mut engine = Engine::default();
 
     assert!(engine.eval(r#"# A comment"#).unwrap() == Value::Null);
     assert!(engine.eval(r#"147.5 # Another # comment!"#).unwrap() == 147.5);
+    assert!(engine.eval(r#"// A C-style comment"#).unwrap() == Value::Null);
+    assert!(engine.eval("8\n// 2").unwrap() == 8.0);
+    assert!(engine.exec("var x = 8; // 2").is_ok());
+    assert!(engine.exec("if true { x = 9; } // 2").is_ok());
+    assert!(engine.eval("x").unwrap() == 9.0);
+    assert!(engine.eval(r#"8 / /* divided by */ 2"#).unwrap() == 4.0);
+    assert!(engine.eval("1 /* outer /* nested */\n still a comment */ + 2").unwrap() == 3.0);
+
+    // Locations after comments are unaffected by them
+    let msg = engine.eval("/* one\n two */ /* three */ oops").unwrap_err().to_string();
+    assert!(msg.contains("Runtime error at 2:21"));
+
+    // A line comment straight after an operand is more likely an attempt at floor division
+    for code in &["8 // 2", "x// 2", "f() // 2", "xs[0] // 2", "\"8\" // 2"] {
+        let msg = engine.eval(code).unwrap_err().to_string();
+        assert!(msg.contains("'//' starts a comment, not floor division."));
+    }
+
+    let msg = engine.eval("1 /* outer /* nested */").unwrap_err().to_string();
+    assert!(msg.contains("Parsing error at 1:3"));
+    assert!(msg.contains("Block comment is never closed."));
 }
 
 #[test]
@@ -106,3 +164,379 @@ fn logical_operations() {
     assert!(engine.eval(r#"!false"#).unwrap() == true);
     assert!(engine.eval(r#"!true"#).unwrap() == false);
 }
+
+#[test]
+fn operator_precedence() {
+    let mut engine = Engine::default();
+
+    // Left associativity
+    assert!(engine.eval(r#"10 - 4 - 3"#).unwrap() == 3.0);
+    assert!(engine.eval(r#"64 / 4 / 2"#).unwrap() == 8.0);
+    assert!(engine.eval(r#"17 % 5 * 2"#).unwrap() == 4.0);
+
+    // Unary operators bind tighter than multiplication, but `as` binds tighter still
+    assert!(engine.eval(r#"-2 * 3 + 10"#).unwrap() == 4.0);
+    assert!(engine.eval(r#"!true == false"#).unwrap() == true);
+    assert!(engine.eval(r#"-'A' as num + 1"#).unwrap() == -64.0);
+    assert!(engine.eval(r#"'A' as num + 1"#).unwrap() == 66.0);
+
+    // Ranges sit between addition and comparison
+    assert!(engine.eval(r#""abcdefgh"[1 + 1..2 * 3] == "cdef""#).unwrap() == true);
+
+    // `clone` and `mirror` bind looser than ranges and nest
+    assert!(engine.eval(r#"mirror mirror [1, 2] + [3] == [1, 2, 3]"#).unwrap() == true);
+
+    // Comparison binds tighter than equivalence, which binds tighter than logic
+    assert!(engine.eval(r#"1 < 2 == 3 > 4 xor true"#).unwrap() == true);
+    assert!(engine.eval(r#"false and true or true"#).unwrap() == true);
+    assert!(engine.eval(r#"false and (true or true)"#).unwrap() == false);
+
+    // Calls and indexing bind tightest of all
+    assert!(engine.eval(r#"-[1, 2, 3][1] * 2"#).unwrap() == -4.0);
+    assert!(engine.eval(r#"|x| { return x * 2; }(3) + 1"#).unwrap() == 7.0);
+}
+
+#[test]
+fn list_slices() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var l = [1, 2, 3, 4, 5];
+        var s = l[1..4];
+        var t = s[1..3];
+        s[0] = 20;
+        l[3] = 40;
+        var total = 0;
+        for x in t {
+            total += x;
+        }
+    "#).unwrap();
+
+    assert!(engine.eval(r#"l == [1, 2, 3, 40, 5]"#).unwrap() == true);
+    assert!(engine.eval(r#"s == [20, 3, 4]"#).unwrap() == true);
+    assert!(engine.eval(r#"t == [3, 4]"#).unwrap() == true);
+    assert!(engine.eval(r#"total"#).unwrap() == 7.0);
+    assert!(engine.eval(r#"l[2..6]"#).unwrap() == Value::Null);
+    assert!(engine.eval(r#"t + [5] == [3, 4, 5]"#).unwrap() == true);
+}
+
+#[test]
+fn negative_indices() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var l = [1, 2, 3, 4, 5];
+        var last = l[-1];
+        var tail = l[-3..-1];
+        var s = "forge lang";
+        var ext = s[-4..-1];
+        var first_char = s[-10];
+        l[-2] = 40;
+        l[-5..-4] = [10, 11];
+        s[-4..-2] = "LA";
+    "#).unwrap();
+
+    assert!(engine.eval("last").unwrap() == 5.0);
+    assert!(engine.eval("tail == [3, 4]").unwrap() == true);
+    assert!(engine.eval("ext").unwrap() == "lan");
+    assert!(engine.eval("first_char == 'f'").unwrap() == true);
+    assert!(engine.eval("l == [10, 11, 2, 3, 40, 5]").unwrap() == true);
+    assert!(engine.eval("s").unwrap() == "forge LAng");
+
+    // Indices before the start, and ranges that end before they start, select nothing
+    assert!(engine.eval("l[-7]").unwrap() == Value::Null);
+    assert!(engine.eval("l[-1..-3]").unwrap() == Value::Null);
+    assert!(engine.eval(r#""abc"[2..1]"#).unwrap() == Value::Null);
+    assert!(engine.eval(r#""abc"[1..5]"#).unwrap() == Value::Null);
+    assert!(engine.exec("l[-9] = 0;").is_err());
+}
+
+#[test]
+fn range_steps() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var collect = |r| {
+                var xs = [];
+                for x in r {
+                    xs += x;
+                }
+                return xs;
+            };
+            var evens = collect(0..10..2);
+            var down = collect(5..0);
+            var down_by = collect(10..0..3);
+            var quarters = collect(0..1..0.25);
+            var empty = collect(3..3);
+            var huge = collect(9007199254740993..9007199254740996);
+            var last = collect(9223372036854775805..9223372036854775807..3);
+            var first = collect(-9223372036854775806..-9223372036854775807 - 1);
+        "#).unwrap();
+
+        assert!(engine.eval("evens == [0, 2, 4, 6, 8]").unwrap() == true);
+        assert!(engine.eval("down == [5, 4, 3, 2, 1]").unwrap() == true);
+        assert!(engine.eval("down_by == [10, 7, 4, 1]").unwrap() == true);
+        assert!(engine.eval("quarters == [0, 0.25, 0.5, 0.75]").unwrap() == true);
+        assert!(engine.eval("empty == []").unwrap() == true);
+
+        // Ranges of ints count exactly, even beyond where floats can tell ints apart, and stop at the limits of ints
+        assert_eq!(engine.eval("huge").unwrap().to_string(), "[9007199254740993, 9007199254740994, 9007199254740995]");
+        assert_eq!(engine.eval("last").unwrap().to_string(), "[9223372036854775805]");
+        assert_eq!(engine.eval("first").unwrap().to_string(), "[-9223372036854775806, -9223372036854775807]");
+
+        // Steps are added like a third bound, so they may be sums too
+        assert!(engine.eval(r#""{1 + 1..10..1 + 3}""#).unwrap() == "2..10..4");
+        assert!(engine.eval(r#""{0..6..1}""#).unwrap() == "0..6");
+        assert!(engine.eval("0..10..0").is_err());
+        assert!(engine.eval("0..10..-1").is_err());
+        assert!(engine.eval("[1, 2, 3][0..2..2]").is_err());
+    }
+}
+
+#[test]
+fn string_interpolation() {
+    let mut engine = Engine::build()
+        .with_global("name", "Bob".to_string())
+        .with_global("age", 41)
+        .finish();
+
+    assert!(engine.eval(r#""hello {name}, you are {age + 1}""#).unwrap() == "hello Bob, you are 42");
+    assert!(engine.eval(r#""{1}{2}""#).unwrap() == "12");
+    assert!(engine.eval(r#""list: {[1, 2][0..1]}, map: {["a": true]}""#).unwrap() == "list: [1], map: [a: true]");
+    assert!(engine.eval(r#""outer {"inner {name}!"} done""#).unwrap() == "outer inner Bob! done");
+    assert!(engine.eval(r#""fn: {|x| { return x; }(7)}""#).unwrap() == "fn: 7");
+    assert!(engine.eval(r#""escaped \{name}""#).unwrap() == "escaped {name}");
+    assert!(engine.eval(r#""unclosed {name""#).is_err());
+}
+
+#[test]
+fn ternary() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var calls = 0;
+            var count = |x| { calls += 1; return x; };
+            var sign = |x| { return x < 0 ? "negative" : x == 0 ? "zero" : "positive"; };
+            var a = sign(-3);
+            var b = sign(0);
+            var c = sign(8);
+            var picked = true ? count(1) : count(2);
+            var total = 1 + 2 > 2 ? 10 * 2 : 0;
+            var m = ["size": 3 > 2 ? "big" : "small"];
+            var nested = false ? 1 : true ? 2 : 3;
+            var chosen = 0;
+            chosen = 5 > 3 and 2 > 1 ? "yes" : "no";
+        "#).unwrap();
+
+        assert!(engine.take("a").unwrap() == "negative");
+        assert!(engine.take("b").unwrap() == "zero");
+        assert!(engine.take("c").unwrap() == "positive");
+        assert!(engine.take("picked").unwrap() == 1.0);
+        assert!(engine.take("calls").unwrap() == 1.0);
+        assert!(engine.take("total").unwrap() == 20.0);
+        assert!(engine.eval("m.size").unwrap() == "big");
+        assert!(engine.take("nested").unwrap() == 2.0);
+        assert!(engine.take("chosen").unwrap() == "yes");
+        assert!(engine.eval("1 ? 2 : 3").is_err());
+        assert!(engine.exec("var x = true ? 1;").is_err());
+    }
+}
+
+#[test]
+fn short_circuit() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var calls = 0;
+            var touch = |x| { calls += 1; return x; };
+            var a = false and touch(true);
+            var b = true or touch(false);
+            var c = true and touch(5);
+            var d = null or touch("fallback");
+
+            var config = ["name": "forge"];
+            var name = config.name or "unnamed";
+            var port = config.port or 8080;
+            var first = null or false or 0;
+            var guarded = config.missing and config.missing.inner;
+            var empty = "" and "kept";
+        "#).unwrap();
+
+        assert!(engine.take("a").unwrap() == false);
+        assert!(engine.take("b").unwrap() == true);
+        assert!(engine.take("c").unwrap() == 5.0);
+        assert!(engine.take("d").unwrap() == "fallback");
+        assert!(engine.take("calls").unwrap() == 2.0);
+        assert!(engine.take("name").unwrap() == "forge");
+        assert!(engine.take("port").unwrap() == 8080.0);
+        assert!(engine.take("first").unwrap() == 0.0);
+        assert!(engine.take("guarded").unwrap().is_null());
+        assert!(engine.take("empty").unwrap() == "kept");
+        assert!(engine.eval("true xor 1").is_err());
+    }
+}
+
+#[test]
+fn null_coalescing() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var calls = 0;
+            var touch = |x| { calls += 1; return x; };
+            var a = null ?? touch(1);
+            var b = 0 ?? touch(2);
+            var c = false ?? touch(3);
+            var d = null ?? null ?? "last";
+
+            var user = ["name": "ada", "address": null, "greet": || { return "hi"; }];
+            var nobody = null;
+            var name = user?.name;
+            var street = user.address?.street;
+            var missing = nobody?.name ?? "anonymous";
+            var greeting = user?.greet();
+            var skipped = nobody?.greet(touch(4));
+        "#).unwrap();
+
+        assert!(engine.take("a").unwrap() == 1);
+        // Only null falls back, unlike `or`
+        assert!(engine.take("b").unwrap() == 0);
+        assert!(engine.take("c").unwrap() == false);
+        assert!(engine.take("d").unwrap() == "last");
+        assert!(engine.take("name").unwrap() == "ada");
+        assert!(engine.take("street").unwrap().is_null());
+        assert!(engine.take("missing").unwrap() == "anonymous");
+        assert!(engine.take("greeting").unwrap() == "hi");
+        assert!(engine.take("skipped").unwrap().is_null());
+        assert!(engine.take("calls").unwrap() == 1);
+
+        // `??` binds more loosely than any other operator, and `?.` only guards the access it is used in
+        assert!(engine.eval("null ?? 1 + 2").unwrap() == 3);
+        assert!(engine.eval("null or false ?? 4").unwrap() == false);
+        assert!(engine.eval("true ? null ?? 1 : 2").unwrap() == 1);
+        assert!(engine.eval("nobody?.address.street").is_err());
+        assert!(engine.exec("nobody?.name = 1;").is_err());
+    }
+}
+
+#[test]
+fn call_results() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            fn pair() {
+                return [["name": "a"], 2];
+            }
+            var make = || { return |x| { return x * 2; }; };
+        "#).unwrap();
+        assert!(engine.eval("pair()[1]").unwrap() == 2.0);
+        assert!(engine.eval("pair()[0].name").unwrap() == "a");
+        assert!(engine.eval("make()(4)").unwrap() == 8.0);
+    }
+}
+
+#[test]
+fn bitwise() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        assert!(engine.eval("6 & 3").unwrap() == 2.0);
+        assert!(engine.eval("6 | 3").unwrap() == 7.0);
+        assert!(engine.eval("6 ^ 3").unwrap() == 5.0);
+        assert!(engine.eval("~5").unwrap() == -6.0);
+        assert!(engine.eval("1 << 10").unwrap() == 1024.0);
+        assert!(engine.eval("-16 >> 2").unwrap() == -4.0);
+        assert!(engine.eval("1 << 63").unwrap() == i64::MIN);
+        assert!(engine.eval("-1 >> 63").unwrap() == -1);
+        assert!(engine.eval("1 << -1").unwrap_err().to_string().contains("Cannot shift an int by -1 places"));
+        assert!(engine.eval("1 << 64").is_err());
+        assert!(engine.eval("1 >> 64").is_err());
+        assert!(engine.eval("-1 >> 100").is_err());
+        // Only the integral part takes part
+        assert!(engine.eval("7.9 & 5.2").unwrap() == 5.0);
+        assert!(engine.eval("-7.9 | 0").unwrap() == -7.0);
+
+        // Precedence follows Rust's, except that comparisons bind more loosely
+        assert!(engine.eval("1 << 2 + 1").unwrap() == 8.0);
+        assert!(engine.eval("1 | 6 & 3").unwrap() == 3.0);
+        assert!(engine.eval("1 | 2 ^ 3").unwrap() == 1.0);
+        assert!(engine.eval("1 | 2 == 3").unwrap() == true);
+        assert!(engine.eval("0..1 << 2").unwrap() == Value::Range(Bounds::Int(0, 4, 1)));
+
+        // Closures still begin with a pipe
+        engine.exec(r#"
+            var set = |x| { return x | 1; };
+            var flags = ["bits": 4, "__bit_or": |a, b| { return a.bits | b; }];
+        "#).unwrap();
+        assert!(engine.eval("set(4)").unwrap() == 5.0);
+        assert!(engine.eval("flags | 2").unwrap() == 6.0);
+
+        assert!(engine.eval(r#""a" & 1"#).unwrap_err().to_string().contains("Cannot apply binary operator 'bit_and' to values of types 'string' and 'int'."));
+        assert!(engine.eval("~true").unwrap_err().to_string().contains("Cannot apply unary operator 'bit_not' to value of type 'bool'."));
+    }
+}
+
+#[test]
+fn ints_and_floats() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        assert!(engine.eval("3").unwrap() == Value::Int(3));
+        assert!(engine.eval("3.0").unwrap() == Value::Float(3.0));
+        assert!(engine.eval("1_000 * 3").unwrap().as_int() == Some(3000));
+        // Ints are exact where floats would round
+        assert!(engine.eval("9007199254740993 + 2").unwrap().as_int() == Some(9007199254740995));
+        assert!(engine.eval("-7 % 3").unwrap().as_int() == Some(-1));
+
+        // Mixing the two promotes the int, and dividing always produces a float
+        assert!(engine.eval("1 + 0.5").unwrap() == Value::Float(1.5));
+        assert!(engine.eval("6 / 3").unwrap() == Value::Float(2.0));
+        assert!(engine.eval("7.5 % 2").unwrap() == Value::Float(1.5));
+        assert!(engine.eval("2 == 2.0").unwrap() == true);
+        assert!(engine.eval("2 < 2.5").unwrap() == true);
+        assert!(engine.eval("[1: \"a\"][1.0]").unwrap() == "a");
+
+        assert!(engine.eval("7.9 as int").unwrap() == Value::Int(7));
+        assert!(engine.eval("-7.9 as int").unwrap() == Value::Int(-7));
+        assert!(engine.eval("3 as float").unwrap() == Value::Float(3.0));
+        assert!(engine.eval("3 as num").unwrap() == Value::Float(3.0));
+        assert!(engine.eval("'a' as int").unwrap() == Value::Int(97));
+
+        // Ranges count in ints unless they start or step by a fraction
+        engine.exec("var xs = []; var ys = []; for x in 0..3 { xs = xs + x; } for y in 0..1..0.5 { ys = ys + y; }").unwrap();
+        assert!(engine.eval("xs").unwrap().as_list().unwrap().iter().all(|x| x.as_int().is_some()));
+        assert!(engine.eval("ys").unwrap().as_list().unwrap() == vec![Value::Float(0.0), Value::Float(0.5)]);
+
+        let display = |engine: &mut Engine, code: &str| {
+            let val = engine.eval(code).unwrap();
+            engine.display(&val).unwrap()
+        };
+        assert_eq!(display(&mut engine, "10 / 4"), "2.5");
+        assert_eq!(display(&mut engine, "10 / 5"), "2.0");
+        assert_eq!(display(&mut engine, "10 * 5"), "50");
+
+        assert!(engine.eval("9223372036854775807 + 1").unwrap_err().to_string().contains("The result is too large for an int."));
+        assert!(engine.eval("-(-9223372036854775807 - 1)").unwrap_err().to_string().contains("The result is too large for an int."));
+        assert!(engine.eval("9223372036854775807 * 2").unwrap_err().to_string().contains("The result is too large for an int."));
+        assert!(engine.eval("(-9223372036854775807 - 1) % -1").unwrap() == 0);
+        assert!(engine.eval("5 % 0").unwrap_err().to_string().contains("Cannot take the remainder of division by zero."));
+        assert!(engine.eval("(0.0 / 0.0) as int").unwrap_err().to_string().contains("too large to convert into an int"));
+        assert!(engine.eval("99999999999999999999").unwrap_err().to_string().contains("The decimal literal is too large for an int. Add '.0' to make it a float."));
+        let msg = engine.eval("0xFFFFFFFFFFFFFFFF").unwrap_err().to_string();
+        assert!(msg.contains("The hexadecimal literal is too large for an int.") && !msg.contains("'.0'"), "{}", msg);
+
+        // Ints and floats are compared exactly, as map keys are, rather than by rounding the int to a float
+        assert!(engine.eval("9007199254740993 == 9007199254740992.0").unwrap() == false);
+        assert!(engine.eval("9007199254740993 != 9007199254740992.0").unwrap() == true);
+        assert!(engine.eval("9007199254740993 > 9007199254740992.0").unwrap() == true);
+        assert!(engine.eval("9007199254740992.0 < 9007199254740993").unwrap() == true);
+        assert!(engine.eval("[9007199254740992.0: 1].contains_key(9007199254740993)").unwrap() == false);
+        assert!(engine.eval("2 == 2.0 and 2 <= 2.0 and 2 < 2.5 and -3 > -3.5 and 9223372036854775807 < 9223372036854775808.0").unwrap() == true);
+        assert!(engine.eval("1 == 0.0 / 0.0 or 1 < 0.0 / 0.0").unwrap() == false);
+        assert!(engine.eval("99999999999999999999.0").unwrap() == 1e20);
+    }
+}
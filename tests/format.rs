@@ -0,0 +1,35 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn format() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec("var x = 3; var y = 2.0 / 3.0;").unwrap();
+        assert!(engine.eval(r#"format("x = {}, y = {:.2}", x, y)"#).unwrap() == "x = 3, y = 0.67");
+
+        // `{}` and `{:...}` are left alone by interpolation, while other braces still interpolate
+        assert!(engine.eval(r#""{} {:x} {x}""#).unwrap() == "{} {:x} 3");
+
+        assert!(engine.eval(r#"format("[{:>6}] [{:<4}] [{:^7}] [{:*^5}]", 42, "ab", "mid", 1)"#).unwrap()
+            == "[    42] [ab  ] [  mid  ] [**1**]");
+        assert!(engine.eval(r#"format("{:+} {:08.3} {:05} {:.1}", 5, -3.14159, -42, 2)"#).unwrap() == "+5 -003.142 -0042 2.0");
+        assert!(engine.eval(r#"format("{:x} {:X} {:o} {:b} {:.2e}", 255, 255, 8, 5, 1234.5)"#).unwrap() == "ff FF 10 101 1.23e3");
+        assert!(engine.eval(r#"format("\{1} \{0} \{1}", "a", "b")"#).unwrap() == "b a b");
+        assert!(engine.eval(r#"format("\{\{{}}}", 1)"#).unwrap() == "{1}");
+        assert!(engine.eval(r#"format("{:.3} {}", "abcdef", [1, "two"])"#).unwrap() == "abc [1, two]");
+
+        assert!(engine.eval(r#"format("{} {}", 1)"#).is_err());
+        assert!(engine.eval(r#"format("{}", 1, 2)"#).is_err());
+        assert!(engine.eval(r#"format("{:q}", 1)"#).is_err());
+        assert!(engine.eval(r#"format("{:x}", 1.5)"#).is_err());
+        assert!(engine.eval(r#"format("}", 1)"#).is_err());
+        assert!(engine.eval("format()").is_err());
+
+        // Widths and precisions are limited, rather than exhausting memory
+        assert!(engine.eval(r#"format("{:.999999999}", 1.5)"#).unwrap_err().to_string().contains("at most 65535"));
+        assert!(engine.eval(r#"format("{:99999999999}", 1)"#).unwrap_err().to_string().contains("at most 65535"));
+        assert!(engine.eval(r#"format("{:.99999999999999999999999}", 1)"#).is_err());
+        assert_eq!(engine.eval(r#"format("{:65535}", 1)"#).unwrap().as_string().unwrap().len(), 65535);
+    }
+}
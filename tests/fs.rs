@@ -0,0 +1,229 @@
+use std::{cell::RefCell, rc::Rc};
+use forge::{Engine, ExecMode, FileHandle, Permissions, Resource, Value};
+
+const TREE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/modules");
+
+fn engine(mode: ExecMode) -> Engine {
+    Engine::build()
+        .with_exec_mode(mode)
+        .with_global("tree", TREE)
+        .allow_fs()
+        .finish()
+}
+
+#[test]
+fn glob() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = engine(*mode);
+
+        engine.exec(r#"
+            var all = fs.glob(tree + "/**/*.fg");
+            var top = fs.glob(tree + "/cycle_?.fg");
+            var nested = fs.glob(tree + "/*/shapes.fg");
+            var none = fs.glob(tree + "/*.txt");
+        "#).unwrap();
+
+        let strip = |name: &str| engine
+            .get(name)
+            .unwrap()
+            .as_list()
+            .unwrap()
+            .iter()
+            .map(|path| path.as_string().unwrap().replacen(TREE, "", 1))
+            .collect::<Vec<_>>();
+        assert_eq!(strip("all"), ["/broken.fg", "/cycle_a.fg", "/cycle_b.fg", "/math.fg", "/util/consts.fg", "/util/shapes.fg"]);
+        assert_eq!(strip("top"), ["/cycle_a.fg", "/cycle_b.fg"]);
+        assert_eq!(strip("nested"), ["/util/shapes.fg"]);
+        assert!(strip("none").is_empty());
+    }
+}
+
+#[test]
+fn walk() {
+    let visited = Rc::new(RefCell::new(Vec::new()));
+    let record = visited.clone();
+    let mut engine = Engine::build()
+        .with_global("tree", TREE)
+        .with_fn("visit", 1, move |args| {
+            let path = args[0].as_string().unwrap().replacen(TREE, "", 1);
+            record.borrow_mut().push(path.clone());
+            Ok(Value::from(path != "/util"))
+        })
+        .allow_fs()
+        .finish();
+
+    engine.exec(r#"
+        fs.walk(tree, visit);
+        var names = fs.list_dir(tree + "/util");
+    "#).unwrap();
+
+    assert_eq!(*visited.borrow(), ["/broken.fg", "/cycle_a.fg", "/cycle_b.fg", "/math.fg", "/util"]);
+    assert_eq!(engine.eval("names").unwrap().to_string(), "[consts.fg, shapes.fg]");
+
+    visited.borrow_mut().clear();
+    engine.exec("fs.walk(tree + \"/util\", visit);").unwrap();
+    assert_eq!(*visited.borrow(), ["/util/consts.fg", "/util/shapes.fg"]);
+}
+
+#[test]
+fn permission_required() {
+    let mut engine = Engine::build().with_global("tree", TREE).finish();
+
+    let err = engine.exec("fs.glob(tree + \"/*.fg\");").unwrap_err().to_string();
+    assert!(err.contains("fs_read"));
+    assert!(engine.exec("fs.walk(tree, |p| { return true; });").is_err());
+    assert!(engine.exec("fs.list_dir(tree);").is_err());
+}
+
+#[test]
+fn file_handles() {
+    let path = std::env::temp_dir().join(format!("forge-file-handles-{}.txt", std::process::id()));
+    let mut engine = Engine::build()
+        .with_global("path", path.to_string_lossy().into_owned())
+        .allow_fs()
+        .finish();
+
+    engine.exec(r#"
+        var out = fs.open(path, "w");
+        out.write("first\n");
+        out.write(2);
+        out.write("\n");
+        out.close();
+        out.close();
+
+        out = fs.open(path, "a");
+        out.write("third\n");
+        out.close();
+
+        var reader = fs.open(path, "r");
+        var first = reader.read_line();
+        var rest = [];
+        for line in reader {
+            rest += line;
+        }
+        var after = reader.read_line();
+    "#).unwrap();
+
+    assert!(engine.take("first").unwrap() == "first");
+    assert_eq!(engine.eval("rest").unwrap().to_string(), "[2, third]");
+    assert!(engine.take("after").unwrap().is_null());
+    assert!(engine.exec("reader.write(\"x\");").is_err());
+    assert!(engine.exec("reader.close(); reader.read_line();").is_err());
+    assert!(engine.exec("fs.open(path, \"rw\");").is_err());
+    assert!(engine.exec("fs.open(path + \".missing\", \"r\");").is_err());
+
+    let mut read_only = Engine::build()
+        .with_global("path", path.to_string_lossy().into_owned())
+        .with_permissions(Permissions { fs_read: true, ..Permissions::default() })
+        .finish();
+    assert!(read_only.exec("var f = fs.open(path, \"r\"); f.close();").is_ok());
+    let err = read_only.exec("fs.open(path, \"w\");").unwrap_err().to_string();
+    assert!(err.contains("fs_write"));
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn resources() {
+    let path = std::env::temp_dir().join(format!("forge-resources-{}.txt", std::process::id()));
+    let read = || std::fs::read_to_string(&path).unwrap();
+    let mut engine = Engine::build()
+        .with_global("path", path.to_string_lossy().into_owned())
+        .allow_fs()
+        .finish();
+
+    engine.exec(r#"
+        var kept = null;
+        with fs.open(path, "w") as f {
+            f.write("written");
+            kept = f;
+        }
+        fn first_line() {
+            with fs.open(path, "r") as f {
+                kept = f;
+                return f.read_line();
+            }
+        }
+        var line = first_line();
+    "#).unwrap();
+    assert!(engine.take("line").unwrap() == "written");
+    assert!(engine.get("kept").unwrap().downcast_ref::<FileHandle>().unwrap().is_closed());
+
+    // Resources are closed even when the block fails
+    let err = engine.exec(r#"with fs.open(path, "a") as f { kept = f; f.write("!"); f.nothing(); }"#).unwrap_err();
+    assert!(err.to_string().contains("no member 'nothing'"), "{}", err);
+    assert!(engine.get("kept").unwrap().downcast_ref::<FileHandle>().unwrap().is_closed());
+    assert_eq!(read(), "written!");
+
+    let err = engine.exec("with [1, 2] as xs { }").unwrap_err().to_string();
+    assert!(err.contains("Only resources, such as files and connections, can be used by a 'with' statement, not values of type 'list'."), "{}", err);
+
+    // A file kept alive by a cycle is still closed, and its writes flushed, when the engine is dropped
+    engine.exec(r#"
+        var holder = ["file": fs.open(path, "w")];
+        holder.itself = holder;
+        holder.file.write("flushed");
+    "#).unwrap();
+    assert_eq!(read(), "");
+    drop(engine);
+    assert_eq!(read(), "flushed");
+
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn whole_files() {
+    let dir = std::env::temp_dir().join(format!("forge-whole-files-{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("inner")).unwrap();
+    let dir_str = dir.to_string_lossy().into_owned();
+
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build()
+            .with_exec_mode(*mode)
+            .with_global("dir", dir_str.clone())
+            .allow_fs()
+            .finish();
+
+        engine.exec(r#"
+            var path = dir + "/notes.txt";
+            var before = fs.exists(path);
+            fs.write(path, "line one\nline two\n");
+            var text = fs.read(path);
+            fs.write(path, [1, 2]);
+        "#).unwrap();
+        assert!(engine.take("before").unwrap() == false);
+        assert!(engine.take("text").unwrap() == "line one\nline two\n");
+        assert!(engine.eval("fs.read(path)").unwrap() == "[1, 2]");
+        assert!(engine.eval("fs.exists(path)").unwrap() == true);
+        assert_eq!(engine.eval("fs.list_dir(dir)").unwrap().to_string(), "[inner, notes.txt]");
+        assert!(engine.eval(r#"fs.read(dir + "/missing.txt")"#).is_err());
+        std::fs::remove_file(dir.join("notes.txt")).unwrap();
+    }
+
+    // Confined to the directory, paths are relative to it and cannot lead out of it
+    let mut engine = Engine::build().with_fs_root(&dir).allow_fs().finish();
+    engine.exec(r#"fs.write("inner/data.txt", "kept");"#).unwrap();
+    assert_eq!(std::fs::read_to_string(dir.join("inner/data.txt")).unwrap(), "kept");
+    assert!(engine.eval(r#"fs.read("inner/../inner/data.txt")"#).unwrap() == "kept");
+    assert!(engine.eval(&format!(r#"fs.exists("{}/inner/data.txt")"#, dir_str)).unwrap() == true);
+    assert_eq!(engine.eval(r#"fs.glob("**/*.txt")"#).unwrap().to_string(), "[inner/data.txt]");
+    for code in &[r#"fs.read("../outside.txt")"#, r#"fs.write("/tmp/outside.txt", 1)"#, r#"fs.list_dir("..")"#, r#"fs.open("../x", "w")"#] {
+        let err = engine.eval(code).unwrap_err().to_string();
+        assert!(err.contains("outside of the directory"), "{}", err);
+    }
+    assert!(engine.eval(r#"fs.exists("..")"#).unwrap() == false);
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("escape")).unwrap();
+        assert!(engine.eval(r#"fs.list_dir("escape")"#).is_err());
+    }
+
+    // Without the permission, nothing can be read or written
+    let mut engine = Engine::build().with_global("dir", dir_str).finish();
+    assert!(engine.eval(r#"fs.read(dir + "/inner/data.txt")"#).unwrap_err().to_string().contains("fs_read"));
+    assert!(engine.eval(r#"fs.write(dir + "/x.txt", 1)"#).unwrap_err().to_string().contains("fs_write"));
+    assert!(engine.eval("fs.exists(dir)").is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}
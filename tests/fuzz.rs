@@ -0,0 +1,40 @@
+#![cfg(feature = "fuzz")]
+
+use forge::{Engine, fuzz};
+
+// Deterministic bytes standing in for fuzzer input
+fn inputs(n: usize) -> Vec<Vec<u8>> {
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    (0..n)
+        .map(|i| (0..i % 200).map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        }).collect())
+        .collect()
+}
+
+#[test]
+fn raw_bytes() {
+    for input in inputs(300) {
+        fuzz::parse_bytes(&input);
+    }
+    fuzz::parse_bytes(b"var x = [1, 2; \"unterminated {");
+    fuzz::exec_bytes(b"var n = 0; while true { n += 1; }");
+}
+
+#[test]
+fn generated_programs() {
+    // Deeply nested expressions take more stack to parse than test threads have in debug builds
+    let runner = std::thread::Builder::new().stack_size(8 << 20).spawn(|| {
+        for input in inputs(300) {
+            let program = fuzz::Program::from_bytes(&input);
+            if let Err(err) = Engine::default().exec(&format!("if false {{\n{}}}", program.source())) {
+                assert!(!err.to_string().contains("Parsing error"), "generated program does not parse:\n{}\n{}", program.source(), err);
+            }
+            program.exec();
+        }
+    }).unwrap();
+    runner.join().unwrap();
+}
@@ -0,0 +1,116 @@
+use forge::{Engine, ExecMode, Value};
+
+#[test]
+fn collect_cycles() {
+    let mut engine = Engine::build().with_gc_threshold(None).finish();
+
+    engine.exec(r#"
+        var make = || {
+            var obj = ["count": 0];
+            obj["incr"] = || { obj["count"] = obj["count"] + 1; };
+            return obj;
+        };
+        var kept = make();
+        make();
+        var l = [1];
+        l[0] = l;
+        l = null;
+    "#).unwrap();
+
+    assert!(engine.gc() > 0);
+    assert_eq!(engine.gc(), 0);
+
+    // Values that are still reachable survive collection
+    engine.exec(r#"
+        var incr = kept["incr"];
+        incr();
+        incr();
+        var count = kept["count"];
+    "#).unwrap();
+    assert_eq!(engine.gc(), 0);
+    assert!(engine.take("count").unwrap() == 2.0);
+}
+
+#[test]
+fn host_values_are_roots() {
+    let mut engine = Engine::build().with_gc_threshold(None).finish();
+
+    let obj = engine.eval(r#"
+        (|| {
+            var obj = ["name": "cycle"];
+            obj["self"] = obj;
+            return obj;
+        })()
+    "#).unwrap();
+    engine.gc();
+    assert!(engine.display(&obj).unwrap().contains("name: cycle"));
+}
+
+#[test]
+fn automatic_collection() {
+    let mut engine = Engine::build().with_gc_threshold(Some(100)).finish();
+
+    engine.exec(r#"
+        for i in 0..1000 {
+            var l = [i];
+            l[0] = l;
+        }
+    "#).unwrap();
+
+    // Most of the cycles were collected while the loop ran
+    assert!(engine.gc() < 200);
+}
+
+#[test]
+fn weak_references() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).with_gc_threshold(None).finish();
+
+        engine.exec(r#"
+            var kept = ["name": "kept"];
+            var cache = ["kept": weak(kept), "dropped": weak(["name": "dropped"])];
+            var cycle = [1];
+            cycle[0] = cycle;
+            var in_cycle = weak(cycle);
+            cycle = null;
+        "#).unwrap();
+
+        assert!(engine.eval(r#"cache.kept.get().name"#).unwrap() == "kept");
+        assert!(engine.eval(r#"cache.kept.get() == kept"#).unwrap() == true);
+        assert!(engine.eval(r#"cache.dropped.get()"#).unwrap() == Value::Null);
+        assert_eq!(engine.eval(r#""{cache.dropped}""#).unwrap().to_string(), "<weak map, freed>");
+
+        // A weak reference does not keep a cycle alive once it has been collected
+        assert!(engine.eval("in_cycle.get()").unwrap() != Value::Null);
+        engine.gc();
+        assert!(engine.eval("in_cycle.get()").unwrap() == Value::Null);
+
+        let err = engine.eval("weak(3)").unwrap_err().to_string();
+        assert!(err.contains("Only strings, lists, maps and objects can be referenced weakly, not values of type 'int'."), "{}", err);
+    }
+}
+
+#[test]
+fn heap_snapshot() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+        engine.exec(r#"
+            var rows = [null; 50];
+            for i in 0..50 {
+                rows[i] = ["id": i, "name": "row {i}"];
+            }
+        "#).unwrap();
+
+        let snapshot = engine.heap_snapshot();
+        let rows = snapshot.groups.iter().find(|group| group.site == Some((4, 28))).unwrap();
+        assert_eq!(rows.type_name, "map");
+        assert_eq!(rows.count, 50);
+        assert!(rows.bytes > 50 * "row 0".len());
+        assert_eq!(snapshot.groups[0].site, Some((4, 28)));
+        assert!(snapshot.groups.iter().any(|group| group.type_name == "list" && group.site == Some((2, 25)) && group.count == 1));
+        assert!(snapshot.to_string().contains("allocated at"));
+
+        engine.exec("rows = null;").unwrap();
+        assert!(engine.heap_snapshot().groups.iter().all(|group| group.site != Some((4, 28))));
+    }
+}
@@ -0,0 +1,111 @@
+use std::{thread, time::Duration};
+use forge::{Engine, ExecMode, Value};
+
+fn engine(mode: ExecMode) -> Engine {
+    let mut engine = Engine::build().with_exec_mode(mode).finish();
+    let handle = engine.interrupt_handle();
+    engine.register_fn("interrupt", 0, move |_| {
+        handle.interrupt();
+        Ok(Value::Null)
+    });
+    engine
+}
+
+#[test]
+fn interrupt_raises_error() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = engine(*mode);
+
+        let msg = engine.exec("var n = 0; while true { n += 1; if n == 3 { interrupt(); } }").unwrap_err().to_string();
+        assert!(msg.contains("Execution was interrupted."));
+        assert!(engine.take("n").unwrap() == 3.0);
+
+        // Interrupts stop the code, rather than being caught
+        let msg = engine.exec(r#"
+            var caught = null;
+            try {
+                for i in 0..1000 {
+                    interrupt();
+                }
+            } catch e {
+                caught = e.message;
+            }
+        "#).unwrap_err().to_string();
+        assert!(msg.contains("Execution was interrupted."));
+        assert!(engine.take("caught").unwrap() == Value::Null);
+    }
+}
+
+#[test]
+fn interrupt_handler() {
+    let mut engine = engine(ExecMode::Interpret);
+
+    engine.exec(r#"
+        var running = true;
+        var interrupts = 0;
+        on_interrupt(|| {
+            running = false;
+            interrupts += 1;
+        });
+        var n = 0;
+        while running {
+            n += 1;
+            if n == 10 {
+                interrupt();
+            }
+        }
+    "#).unwrap();
+    assert!(engine.take("interrupts").unwrap() == 1.0);
+    assert!(engine.eval("n < 20").unwrap() == true);
+
+    // Interrupting the handler itself stops it
+    engine.exec("on_interrupt(|| { while true { interrupt(); } });").unwrap();
+    assert!(engine.exec("while true { interrupt(); }").is_err());
+
+    engine.exec("on_interrupt(null);").unwrap();
+    assert!(engine.exec("while true { interrupt(); }").is_err());
+}
+
+#[test]
+fn interrupt_from_another_thread() {
+    let mut engine = Engine::default();
+    let handle = engine.interrupt_handle();
+
+    let interrupter = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(50));
+        handle.interrupt();
+    });
+    assert!(engine.exec("var f = |x| { return x + 1; }; var i = 0; while true { i = f(i); }").is_err());
+    interrupter.join().unwrap();
+    assert!(!engine.interrupt_handle().is_pending());
+}
+
+#[test]
+fn fuel() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+        engine.exec("var caught = null;").unwrap();
+
+        engine.set_fuel(Some(1000));
+        let msg = engine.exec("while true {}").unwrap_err().to_string();
+        assert!(msg.contains("The code ran out of fuel"), "{}", msg);
+        assert_eq!(engine.fuel(), Some(0));
+
+        // Code that has run out of fuel cannot carry on by catching the error
+        engine.set_fuel(Some(1000));
+        assert!(engine.exec("try { for i in 0..1000000 {} } catch e { caught = e.message; }").is_err());
+        engine.set_fuel(None);
+        assert!(engine.take("caught").unwrap() == Value::Null);
+
+        // Not even by a `catch` block that does nothing, which has no work to use fuel on
+        engine.set_fuel(Some(1000));
+        let msg = engine.exec("try { while true {} } catch e {}").unwrap_err().to_string();
+        assert!(msg.contains("The code ran out of fuel"), "{}", msg);
+        engine.set_fuel(None);
+
+        engine.set_fuel(Some(10000));
+        engine.exec("var total = 0; for i in 0..10 { total += i; }").unwrap();
+        assert!(engine.take("total").unwrap() == 45);
+        assert!(engine.fuel().unwrap() < 10000);
+    }
+}
@@ -0,0 +1,96 @@
+use forge::Engine;
+
+#[test]
+fn xml_parse() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var doc = xml.parse("<?xml version='1.0'?>
+            <!-- Build settings -->
+            <project name='forge' version='0.1'>
+                <dependency id='hashbrown'/>
+                <dependency id='libc'>&lt;unix only&gt; &#x263A;</dependency>
+                <notes><![CDATA[a < b & c]]></notes>
+            </project>");
+        var tag = doc.tag;
+        var name = doc.attrs.name;
+        var first = doc.children[0].attrs["id"];
+        var second = doc.children[1].text;
+        var notes = doc.children[2].children[0];
+        var empty = doc.children[0].children;
+    "#).unwrap();
+    assert!(engine.take("tag").unwrap() == "project");
+    assert!(engine.take("name").unwrap() == "forge");
+    assert!(engine.take("first").unwrap() == "hashbrown");
+    assert!(engine.take("second").unwrap() == "<unix only> \u{263A}");
+    assert!(engine.take("notes").unwrap() == "a < b & c");
+    assert!(engine.eval("empty[0]").unwrap().is_null());
+
+    assert!(engine.exec(r#"xml.parse("<a><b></a>");"#).unwrap_err().to_string().contains("expected '</b>', found '</a>'"));
+    assert!(engine.exec(r#"xml.parse("<a>
+        <b>");"#).unwrap_err().to_string().contains("Could not parse line 2 of XML: '<b>' is never closed."));
+    assert!(engine.exec(r#"xml.parse("<a x=1/>");"#).is_err());
+    assert!(engine.exec(r#"xml.parse("<a/><b/>");"#).unwrap_err().to_string().contains("only one root element"));
+}
+
+#[test]
+fn html_parse_and_select() {
+    let mut engine = Engine::default();
+
+    engine.exec(r##"
+        var doc = html.parse("<!DOCTYPE html>
+            <HTML><head><title>Tom &amp; Jerry</title>
+            <script>if (a < b) document.write('</p>');</script></head>
+            <body>
+                <ul id=menu>
+                    <li class='item active'><a href='/home'>Home</a>
+                    <li class=item><a href='https://example.com/about'>About</a>
+                    <li class=item disabled><a href=/contact>Contact</a>
+                </ul>
+                <p>First<br>line
+                <p>Second</span> paragraph
+            </body>");
+
+        var titles = html.select(doc, "title");
+        var title = titles[0].text;
+        var scripts = html.select(doc, "script");
+        var script = scripts[0].text;
+
+        var hrefs = "";
+        for a in html.select(doc, "#menu > li.item a") {
+            hrefs += a.attrs.href + " ";
+        }
+        var active = html.select(doc, "li.active.item a");
+        active = active[0].text;
+        var external = html.select(doc, "a[href^=https]");
+        external = external[0].text;
+        var disabled = html.select(doc, "li[disabled] a, title");
+        disabled = disabled[1].text;
+
+        var paragraphs = 0;
+        for p in html.select(doc, "body p") {
+            paragraphs += 1;
+        }
+        var second = html.select(doc, "p");
+        second = second[1].text;
+        var none = html.select(doc, "ul > a");
+        none = none[0];
+        var lists = html.select(doc, "ul");
+        var inner = html.select(lists[0], "li");
+        inner = inner[2].text;
+    "##).unwrap();
+    assert!(engine.take("title").unwrap() == "Tom & Jerry");
+    assert!(engine.take("script").unwrap() == "if (a < b) document.write('</p>');");
+    assert!(engine.take("hrefs").unwrap() == "/home https://example.com/about /contact ");
+    assert!(engine.take("active").unwrap() == "Home");
+    assert!(engine.take("external").unwrap() == "About");
+    assert!(engine.take("disabled").unwrap() == "Contact");
+    assert!(engine.take("paragraphs").unwrap() == 2.0);
+    assert!(engine.take("second").unwrap() == "Second paragraph\n            ");
+    assert!(engine.take("none").unwrap().is_null());
+    assert!(engine.take("inner").unwrap() == "Contact");
+
+    assert!(engine.exec(r#"html.select(html.parse("<p>"), "p >");"#).unwrap_err().to_string().contains("'p >' is not a valid selector."));
+    assert!(engine.exec(r#"html.select(html.parse("<p>"), "p:first-child");"#).is_err());
+    assert!(engine.exec(r#"html.select(5, "p");"#).is_err());
+}
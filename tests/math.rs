@@ -0,0 +1,40 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn math() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        assert!(engine.eval("math.sqrt(16)").unwrap() == 4.0);
+        assert!(engine.eval("math.pi").unwrap() == std::f64::consts::PI);
+        assert!(engine.eval("math.e").unwrap() == std::f64::consts::E);
+        assert!(engine.eval("math.sin(0)").unwrap() == 0.0);
+        assert!(engine.eval("math.cos(math.pi)").unwrap() == -1.0);
+        assert!(engine.eval("math.exp(0)").unwrap() == 1.0);
+        assert!(engine.eval("math.log(math.e)").unwrap() == 1.0);
+        assert!(engine.eval("math.log(8, 2)").unwrap() == 3.0);
+
+        // Ints stay ints where they can, and rounding produces one
+        assert!(engine.eval("math.abs(-3)").unwrap() == 3);
+        assert!(engine.eval("math.abs(-2.5)").unwrap() == 2.5);
+        assert!(engine.eval("math.floor(2.7)").unwrap() == 2);
+        assert!(engine.eval("math.ceil(2.1)").unwrap() == 3);
+        assert!(engine.eval("math.round(-2.5)").unwrap() == -3);
+        assert!(engine.eval("math.pow(2, 10)").unwrap() == 1024);
+        assert!(engine.eval("math.pow(2, -1)").unwrap() == 0.5);
+        assert!(engine.eval("math.pow(4, 0.5)").unwrap() == 2.0);
+        assert!(engine.eval("math.pow(2, 64)").is_err());
+        assert!(engine.eval("math.abs(-9223372036854775807 - 1)").is_err());
+
+        // `min` and `max` take several values, or a list of them
+        assert!(engine.eval("math.min(3, 1.5, 2)").unwrap() == 1.5);
+        assert!(engine.eval("math.max([3, 7, 2])").unwrap() == 7);
+        assert!(engine.eval(r#"math.max("apple", "pear")"#).unwrap() == "pear");
+        assert!(engine.eval("math.min([])").is_err());
+        assert!(engine.eval(r#"math.min(1, "one")"#).is_err());
+
+        assert!(engine.eval(r#"math.sqrt("four")"#).is_err());
+        assert!(engine.eval("math.sqrt()").is_err());
+        assert!(engine.eval("math.tau").is_err());
+    }
+}
@@ -0,0 +1,471 @@
+use forge::{Engine, ExecMode, Value};
+
+#[test]
+fn list_sort() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var xs = [5, 3, 9, 1, 3];
+        xs.sort();
+        var desc = [5, 3, 9, 1, 3];
+        desc.sort(|a, b| { return b - a; });
+        var pairs = [[2, "a"], [1, "b"], [2, "c"], [1, "d"]];
+        pairs.sort(|a, b| { return a[0] < b[0]; });
+        var order = "";
+        for p in pairs {
+            order += p[1];
+        }
+    "#).unwrap();
+
+    assert!(engine.eval(r#"xs == [1, 3, 3, 5, 9]"#).unwrap() == true);
+    assert!(engine.eval(r#"desc == [9, 5, 3, 3, 1]"#).unwrap() == true);
+    assert!(engine.take("order").unwrap() == "bdac");
+
+    assert!(engine.exec(r#"[1, "a"].sort();"#).is_err());
+    assert!(engine.exec(r#"[1, 2].sort(|a, b| { return "x"; });"#).is_err());
+}
+
+#[test]
+fn list_search_and_dedup() {
+    let mut engine = Engine::default();
+
+    assert!(engine.eval(r#"[1, 3, 5, 7, 9].binary_search(7)"#).unwrap() == 3.0);
+    assert!(engine.eval(r#"[1, 3, 5, 7, 9].binary_search(4)"#).unwrap() == Value::Null);
+    assert!(engine.eval(r#"[].binary_search(4)"#).unwrap() == Value::Null);
+
+    engine.exec(r#"
+        var xs = [1, 1, 2, 3, 3, 3, 1];
+        xs.dedup();
+        var view = xs[0..2];
+        var f = xs.sort;
+    "#).unwrap();
+    assert!(engine.eval(r#"xs == [1, 2, 3, 1]"#).unwrap() == true);
+    assert!(engine.eval(r#"f() == null and xs == [1, 1, 2, 3] and view == [1, 2]"#).unwrap() == true);
+
+    engine.exec("var ls = [[1], [1], [2], [1]]; ls.dedup();").unwrap();
+    assert_eq!(engine.eval("ls").unwrap().to_string(), "[[1], [2], [1]]");
+
+    assert!(engine.eval(r#"[1].nonexistent"#).is_err());
+    assert!(engine.eval(r#"[1].dedup(1)"#).is_err());
+}
+
+#[test]
+fn list_methods() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var xs = [1, 2, 3];
+            xs.push(4);
+            var last = xs.pop();
+            xs.insert(0, 0);
+            xs.insert(-1, 9);
+            xs.insert(xs.len(), 10);
+            var removed = xs.remove(-2);
+
+            var words = ["pear", "fig", "banana"];
+            words.sort_by(|w| { return w.len(); });
+            var rev = [1, 2, 3];
+            rev.reverse();
+        "#).unwrap();
+
+        assert!(engine.eval("last").unwrap() == 4);
+        assert!(engine.eval("removed").unwrap() == 3);
+        assert!(engine.eval("xs == [0, 1, 2, 9, 10]").unwrap() == true);
+        assert!(engine.eval(r#"words == ["fig", "pear", "banana"]"#).unwrap() == true);
+        assert!(engine.eval("rev == [3, 2, 1]").unwrap() == true);
+        assert!(engine.eval("[].pop()").unwrap() == Value::Null);
+
+        assert!(engine.eval("[1, 2, 3].map(|x| { return x * 10; }) == [10, 20, 30]").unwrap() == true);
+        assert!(engine.eval("[1, 2, 3, 4].filter(|x| { return x % 2 == 0; }) == [2, 4]").unwrap() == true);
+        assert!(engine.eval("[1, 2, 3, 4].fold(0, |acc, x| { return acc + x; })").unwrap() == 10);
+        assert!(engine.eval(r#"[1, 2, 3].map(|x| { return x * x; }).filter(|x| { return x > 1; }).join(", ")"#).unwrap() == "4, 9");
+        assert!(engine.eval(r#"[[1], "a"].contains([1]) and ![[1], "a"].contains("b")"#).unwrap() == true);
+        assert!(engine.eval(r#"["a", 'b', 3].join()"#).unwrap() == "ab3");
+
+        // Views share their items with the list they were taken from until either is changed
+        engine.exec("var all = [1, 2, 3]; var view = all[0..2]; view.push(9);").unwrap();
+        assert!(engine.eval("all == [1, 2, 3] and view == [1, 2, 9]").unwrap() == true);
+
+        assert!(engine.eval("[1, 2].insert(3, 0)").is_err());
+        assert!(engine.eval("[1, 2].remove(2)").is_err());
+        assert!(engine.eval(r#"[1, 2].remove("0")"#).is_err());
+        assert!(engine.eval("[1, 2].filter(|x| { return x; })").is_err());
+        assert!(engine.eval(r#"[1, "a"].sort_by(|x| { return x; })"#).is_err());
+        assert!(engine.eval("[1, 2].map()").is_err());
+    }
+}
+
+#[test]
+fn heap() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var h = heap();
+        for x in [5, 1, 4, 2, 3] {
+            h.push(x);
+        }
+        var first = h.peek();
+        var len = h.len();
+        var order = [];
+        while h {
+            order += [h.pop_min()];
+        }
+        var empty = h.pop_min();
+    "#).unwrap();
+
+    assert!(engine.take("first").unwrap() == 1.0);
+    assert!(engine.take("len").unwrap() == 5.0);
+    assert!(engine.take("empty").unwrap() == Value::Null);
+    assert_eq!(engine.eval("order").unwrap().to_string(), "[1, 2, 3, 4, 5]");
+}
+
+#[test]
+fn heap_with_key() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var tasks = heap(|task| { return task[1]; });
+        tasks.push(["write", 2]);
+        tasks.push(["plan", 1]);
+        tasks.push(["test", 2]);
+        tasks.push(["ship", 3]);
+        var order = [];
+        while tasks {
+            var task = tasks.pop_min();
+            order += [task[0]];
+        }
+    "#).unwrap();
+
+    // Items with equal keys keep the order they were pushed in
+    assert_eq!(engine.eval("order").unwrap().to_string(), "[plan, write, test, ship]");
+    assert!(engine.exec("var h = heap(); h.push(1); h.push([]);").is_err());
+}
+
+#[test]
+fn string_methods() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        assert!(engine.eval(r#""  Hello, World  ".trim()"#).unwrap() == "Hello, World");
+        assert!(engine.eval(r#""Hello".upper() + "Hello".lower()"#).unwrap() == "HELLOhello");
+        assert!(engine.eval(r#""a,b,,c".split(",") == ["a", "b", "", "c"]"#).unwrap() == true);
+        assert!(engine.eval(r#""  two   words ".split() == ["two", "words"]"#).unwrap() == true);
+        assert!(engine.eval(r#""haystack".contains("st") and !"haystack".contains("ts")"#).unwrap() == true);
+        assert!(engine.eval(r#""haystack".starts_with('h') and !"haystack".starts_with("st")"#).unwrap() == true);
+        assert!(engine.eval(r#""a-b-c".replace("-", "+")"#).unwrap() == "a+b+c");
+        assert!(engine.eval(r#""haystack".find("st")"#).unwrap() == 3);
+        assert!(engine.eval(r#""haystack".find("needle")"#).unwrap() == Value::Null);
+
+        // The string itself is not changed, and a method can be taken and called later
+        engine.exec(r#"var s = "abc"; var up = s.upper; var t = s.replace("b", "x");"#).unwrap();
+        assert!(engine.eval(r#"s + up() + t"#).unwrap() == "abcABCaxc");
+
+        assert!(engine.eval(r#""a,b".split("")"#).is_err());
+        assert!(engine.eval(r#""abc".contains(1)"#).is_err());
+        assert!(engine.eval(r#""abc".len(1)"#).is_err());
+        assert!(engine.eval(r#""abc".nonexistent"#).is_err());
+    }
+}
+
+#[test]
+fn string_methods_unicode() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        // Lengths and positions count characters, as indexing does, rather than bytes
+        assert!(engine.eval(r#""naïve café".len()"#).unwrap() == 10);
+        assert!(engine.eval(r#""naïve café".find("café")"#).unwrap() == 6);
+        assert!(engine.eval(r#""naïve café"["naïve café".find('é')] == 'é'"#).unwrap() == true);
+        assert!(engine.eval(r#""日本語".chars() == ['日', '本', '語']"#).unwrap() == true);
+        assert!(engine.eval(r#""日本語".chars()[1] == "日本語"[1]"#).unwrap() == true);
+        assert!(engine.eval(r#""straße".upper()"#).unwrap() == "STRASSE");
+        assert!(engine.eval(r#""ÀÉÎ".lower()"#).unwrap() == "àéî");
+        assert!(engine.eval(r#""\u{2003}wide spaces\u{3000}".trim()"#).unwrap() == "wide spaces");
+        assert!(engine.eval(r#""😀 and 😀".replace('😀', "🙂")"#).unwrap() == "🙂 and 🙂");
+        assert!(engine.eval(r#""α→β→γ".split("→") == ["α", "β", "γ"]"#).unwrap() == true);
+    }
+}
+
+#[test]
+fn partial_application() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            fn add(a, b, c) { return a + b + c; }
+            var add_1 = add.bind(1);
+            var add_3 = add.bind(1, 2);
+            var add_6 = partial(add_3, 3);
+            var tens = [1, 2, 3].map(partial(add, 10, 0));
+            var join = partial(["a", "b"].join);
+        "#).unwrap();
+
+        assert!(engine.eval("add_1(2, 3)").unwrap() == 6);
+        assert!(engine.eval("add_3(10)").unwrap() == 13);
+        assert!(engine.eval("add_6()").unwrap() == 6);
+        assert!(engine.eval("tens == [11, 12, 13]").unwrap() == true);
+        assert!(engine.eval(r#"join("-")"#).unwrap() == "a-b");
+        assert_eq!(engine.eval("add_3").unwrap().to_string(), "<bound function 'add'>");
+
+        let msg = engine.eval("add_3(1, 2)").unwrap_err().to_string();
+        assert!(msg.contains("Expected 3, found 4."), "{}", msg);
+        assert!(engine.eval("partial(5, 1)").unwrap_err().to_string().contains("Cannot call value of type 'int'."));
+        assert!(engine.eval("partial()").is_err());
+    }
+}
+
+#[test]
+fn map_methods() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var m = ["b": 2, "a": 1];
+            m["c"] = 3;
+            var removed = m.remove("a");
+            var missing = m.remove("z");
+            var merged = m.merge(["b": 20, "d": 4]);
+            var ages = ["len": 7];
+        "#).unwrap();
+
+        assert!(engine.eval(r#"m.keys() == ["b", "c"] and m.values() == [2, 3]"#).unwrap() == true);
+        assert!(engine.eval("m.len()").unwrap() == 2);
+        assert!(engine.eval(r#"m.contains_key("b") and !m.contains_key("a")"#).unwrap() == true);
+        assert!(engine.eval("removed").unwrap() == 1);
+        assert!(engine.eval("missing").unwrap() == Value::Null);
+        assert!(engine.eval(r#"merged.keys() == ["b", "c", "d"] and merged.b == 20 and m.b == 2"#).unwrap() == true);
+        assert!(engine.eval(r#"m.get("c", 0) + m.get("z", 10)"#).unwrap() == 13);
+        assert!(engine.eval(r#"m.get("z")"#).unwrap() == Value::Null);
+        // The keys of a map come before its methods
+        assert!(engine.eval("ages.len").unwrap() == 7);
+
+        assert!(engine.eval("m.merge(1)").is_err());
+        assert!(engine.eval("m.get()").is_err());
+    }
+}
+
+#[test]
+fn map_insertion_order() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var m = ["zebra": 5];
+            for word in ["apple", "mango", "kiwi", "banana"] {
+                m[word] = word.len();
+            }
+            m["apple"] = 0;
+            m.remove("mango");
+            m["mango"] = 1;
+            var order = "";
+            for k, v in m {
+                order += "{k}={v} ";
+            }
+        "#).unwrap();
+
+        // Assigning to a key that is present leaves it in place, while removing it and adding it again moves it last
+        assert!(engine.take("order").unwrap() == "zebra=5 apple=0 kiwi=4 banana=6 mango=1 ");
+        assert_eq!(engine.eval("m").unwrap().to_string(), "[zebra: 5, apple: 0, kiwi: 4, banana: 6, mango: 1]");
+        assert!(engine.eval(r#"m == ["mango": 1, "banana": 6, "kiwi": 4, "apple": 0, "zebra": 5]"#).unwrap() == true);
+        assert!(engine.eval(r#"m != ["mango": 1, "banana": 6, "kiwi": 4, "apple": 0, "zebra": 0]"#).unwrap() == true);
+    }
+}
+
+#[test]
+fn sorted_and_default_maps() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var s = sortedmap();
+            for word in ["pear", "apple", "fig", "kiwi"] {
+                s[word] = word.len();
+            }
+            s.remove("fig");
+            s["banana"] = 6;
+            var copy = sortedmap([3: "c", 1: "a", 2.5: "b", 'x': "d"]);
+
+            var counts = defaultmap(|| { return 0; });
+            var groups = defaultmap(|| { return []; });
+            for word in ["one", "two", "three", "two", "one", "two"] {
+                counts[word] += 1;
+                groups[word.len()].push(word);
+            }
+        "#).unwrap();
+
+        assert_eq!(engine.eval("s").unwrap().to_string(), "[apple: 5, banana: 6, kiwi: 4, pear: 4]");
+        assert_eq!(engine.eval("s.keys()").unwrap().to_string(), "[apple, banana, kiwi, pear]");
+        assert_eq!(engine.eval("copy").unwrap().to_string(), "[1: a, 2.5: b, 3: c, x: d]");
+        // Copies keep their order
+        engine.exec(r#"var t = clone s; t["avocado"] = 0;"#).unwrap();
+        assert_eq!(engine.eval("t.keys()").unwrap().to_string(), "[apple, avocado, banana, kiwi, pear]");
+
+        assert_eq!(engine.eval("counts").unwrap().to_string(), "[one: 2, two: 3, three: 1]");
+        assert_eq!(engine.eval("groups").unwrap().to_string(), "[3: [one, two, two, one, two], 5: [three]]");
+        // Reading a missing key stores the default, while `get` and `contains_key` leave the map as it was
+        assert!(engine.eval(r#"counts.get("four")"#).unwrap().is_null());
+        assert!(engine.eval(r#"counts.contains_key("four")"#).unwrap() == false);
+        assert!(engine.eval(r#"counts["four"]"#).unwrap() == 0);
+        assert!(engine.eval(r#"counts.contains_key("four")"#).unwrap() == true);
+
+        assert!(engine.eval("defaultmap(0)").is_err());
+        assert!(engine.eval("sortedmap([1, 2])").is_err());
+    }
+}
+
+#[test]
+fn composition() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            fn double(x) { return x * 2; }
+            fn inc(x) { return x + 1; }
+            var double_then_inc = double >> inc;
+            var shout = compose(|s| { return s.trim(); }, |s| { return s.upper(); }, |s| { return s + "!"; });
+            var add = |a, b| { return a + b; };
+        "#).unwrap();
+
+        assert!(engine.eval("double_then_inc(5)").unwrap() == 11);
+        assert!(engine.eval("(inc >> double)(5)").unwrap() == 12);
+        assert!(engine.eval("(add >> double >> inc)(1, 2)").unwrap() == 7);
+        assert!(engine.eval("[1, 2, 3].map(double >> inc >> partial(add, 10)) == [13, 15, 17]").unwrap() == true);
+        assert!(engine.eval(r#"shout("  hey ")"#).unwrap() == "HEY!");
+        assert!(engine.eval("compose(double)(4)").unwrap() == 8);
+        assert!(engine.eval("8 >> 2").unwrap() == 2);
+        assert_eq!(engine.eval("double >> inc").unwrap().to_string(), "<function 'double'> >> <function 'inc'>");
+
+        assert!(engine.eval("double >> 1").is_err());
+        assert!(engine.eval("compose(double, 1)").unwrap_err().to_string().contains("Cannot call value of type 'int'."));
+    }
+}
+
+#[test]
+fn memoization() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var calls = 0;
+            var square = memo(|x| { calls += 1; return x * x; });
+            var fib = null;
+            fib = memo(|n| {
+                if n < 2 { return n; }
+                return fib(n - 1) + fib(n - 2);
+            });
+            var recent = memo(|x| { calls += 1; return x; }, 2);
+        "#).unwrap();
+
+        assert!(engine.eval("square(4)").unwrap() == 16);
+        assert!(engine.eval("square(4)").unwrap() == 16);
+        assert!(engine.eval("square(4.0)").unwrap() == 16);
+        assert!(engine.eval("calls").unwrap() == 1);
+
+        // Arguments are compared by what they contain, as it was when the call was made
+        engine.exec(r#"
+            calls = 0;
+            var total = memo(|xs| { calls += 1; return xs.fold(0, |a, b| { return a + b; }); });
+            var xs = [1, 2, 3];
+            total(xs);
+            total([1, 2, 3]);
+            xs.push(4);
+        "#).unwrap();
+        assert!(engine.eval("total(xs)").unwrap() == 10);
+        assert!(engine.eval("calls").unwrap() == 2);
+        engine.exec(r#"
+            calls = 0;
+            var size = memo(|m| { calls += 1; return m.len(); });
+            size(["a": 1, "b": 2]);
+            size(["b": 2, "a": 1]);
+        "#).unwrap();
+        assert!(engine.eval("calls").unwrap() == 1);
+
+        // Recursive calls go through the cache too, so each of these only calls the function once
+        engine.exec("for n in 0..90 { fib(n); }").unwrap();
+        assert!(engine.eval("fib(90)").unwrap() == 2880067194370816120i64);
+
+        // Only the most recent results are kept once the limit is reached
+        engine.exec("calls = 0; recent(1); recent(2); recent(3); recent(3); recent(1);").unwrap();
+        assert!(engine.eval("calls").unwrap() == 4);
+
+        assert_eq!(engine.eval("square").unwrap().to_string(), "<memo function>");
+        assert!(engine.eval("memo(1)").unwrap_err().to_string().contains("Cannot call value of type 'int'."));
+        assert!(engine.eval("memo(square, 0)").is_err());
+    }
+}
+
+#[test]
+fn function_introspection() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+        engine.register_fn("twice", 1, |args| Ok(Value::Int(args[0].as_int().unwrap() * 2)));
+
+        engine.exec(r#"
+            fn greet(name, greeting = "Hello", ...rest) { return "{greeting}, {name}!"; }
+            fn add(a, b, c) { return a + b + c; }
+            var routes = ["add": add, "twice": twice];
+            fn dispatch(name, args) {
+                var f = routes[name];
+                if f.arity != args.len() { return "{f.name} takes {f.arity} arguments"; }
+                return f.apply(args);
+            }
+        "#).unwrap();
+
+        assert!(engine.eval("add.arity").unwrap() == 3);
+        assert!(engine.eval("greet.arity").unwrap() == 1);
+        assert!(engine.eval("|| { return 1; }.arity").unwrap() == 0);
+        assert!(engine.eval("add.bind(1).arity").unwrap() == 2);
+        assert!(engine.eval("(add >> twice).arity").unwrap() == 3);
+        assert!(engine.eval("memo(add).arity").unwrap() == 3);
+        assert!(engine.eval("twice.arity").unwrap() == 1);
+        assert!(engine.eval("partial.arity").unwrap().is_null());
+
+        assert!(engine.eval("add.name").unwrap() == "add");
+        assert!(engine.eval("add.bind(1).name").unwrap() == "add");
+        assert!(engine.eval("twice.name").unwrap() == "twice");
+        assert!(engine.eval("partial.name").unwrap() == "partial");
+        assert!(engine.eval("[1].push.name").unwrap() == "push");
+        assert!(engine.eval("|| { return 1; }.name").unwrap().is_null());
+
+        assert!(engine.eval("add.apply([1, 2, 3])").unwrap() == 6);
+        assert!(engine.eval(r#"greet.apply(["Ada"])"#).unwrap() == "Hello, Ada!");
+        assert!(engine.eval("partial(add, 1).apply([2, 3])").unwrap() == 6);
+        assert!(engine.eval(r#"dispatch("add", [1, 2, 3])"#).unwrap() == 6);
+        assert!(engine.eval(r#"dispatch("twice", [21])"#).unwrap() == 42);
+        assert!(engine.eval(r#"dispatch("add", [1])"#).unwrap() == "add takes 3 arguments");
+
+        assert!(engine.eval("add.apply(1, 2, 3)").is_err());
+        assert!(engine.eval("add.apply([1, 2])").unwrap_err().to_string().contains("Expected 3, found 2."));
+        assert!(engine.eval("[1].arity").is_err());
+    }
+}
+
+#[test]
+fn grouping() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var words = ["pear", "fig", "apple", "kiwi", "plum", "fig"];
+            var by_len = group_by(words, |w| { return w.len(); });
+            var counts = count_by(words, |w| { return w; });
+        "#).unwrap();
+
+        assert_eq!(engine.eval("by_len").unwrap().to_string(), "[4: [pear, kiwi, plum], 3: [fig, fig], 5: [apple]]");
+        assert_eq!(engine.eval("counts").unwrap().to_string(), "[pear: 1, fig: 2, apple: 1, kiwi: 1, plum: 1]");
+        assert_eq!(engine.eval("unique(words)").unwrap().to_string(), "[pear, fig, apple, kiwi, plum]");
+        // Lists and maps are the same if what they contain is, even when they are separate values
+        engine.exec("var l = [2];").unwrap();
+        assert_eq!(engine.eval("unique([1, l, 1.0, l, [2], 'c', 'c'])").unwrap().to_string(), "[1, [2], c]");
+        assert!(engine.eval("unique([[1], [1]]).len()").unwrap() == 1.0);
+        assert!(engine.eval(r#"unique([["a": 1, "b": 2], ["b": 2, "a": 1]]).len()"#).unwrap() == 1.0);
+        assert_eq!(engine.eval("count_by([1, 2, 3, 4], |x| { return [x % 2]; })").unwrap().to_string(), "[[1]: 2, [0]: 2]");
+        assert_eq!(engine.eval("group_by([1, 2, 3], |x| { return [x > 1]; })").unwrap().to_string(), "[[false]: [1], [true]: [2, 3]]");
+        assert_eq!(engine.eval("group_by([], |x| { return x; })").unwrap().to_string(), "[]");
+
+        assert!(engine.eval("unique(5)").is_err());
+        assert!(engine.eval("count_by([1], 5)").is_err());
+        assert!(engine.eval(r#"group_by([1, "a"], |x| { return x.len(); })"#).is_err());
+    }
+}
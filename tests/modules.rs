@@ -0,0 +1,68 @@
+use forge::{Engine, ExecMode};
+
+fn engine(mode: ExecMode) -> Engine {
+    Engine::build()
+        .with_module_root(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/modules"))
+        .with_exec_mode(mode)
+        .finish()
+}
+
+#[test]
+fn import_namespaces() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = engine(*mode);
+
+        engine.exec(r#"
+            var square = "not the module's square";
+            import math;
+            import "util/shapes.fg" as shapes;
+            var nine = math.square(3);
+            var area = shapes.area;
+        "#).unwrap();
+
+        assert!(engine.take("square").unwrap() == "not the module's square");
+        assert!(engine.take("nine").unwrap() == 9.0);
+        assert!(engine.take("area").unwrap() == 3.14159 * 4.0);
+    }
+}
+
+#[test]
+fn import_caching() {
+    let mut engine = engine(ExecMode::Interpret);
+
+    engine.exec(r#"
+        import math;
+        import "math.fg" as again;
+        math.items.sort();
+        var items = again.items;
+    "#).unwrap();
+
+    assert_eq!(engine.eval("items").unwrap().to_string(), "[1, 2, 3]");
+}
+
+#[test]
+fn import_errors() {
+    let mut engine = engine(ExecMode::Interpret);
+
+    assert!(engine.exec("import missing;").is_err());
+    assert!(engine.exec("import broken;").is_err());
+
+    let err = engine.exec("import cycle_a;").unwrap_err().to_string();
+    assert!(err.contains("cycle"));
+    assert!(engine.exec("try { import cycle_a; } catch e { var caught = e; }").is_ok());
+}
+
+#[test]
+fn import_confinement() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = engine(*mode);
+
+        assert!(engine.exec(r#"import "../modules.rs" as m;"#).unwrap_err().to_string().contains(".."));
+        assert!(engine.exec(r#"import "util/../math.fg" as m;"#).is_err());
+        assert!(engine.exec(r#"import "/etc/hostname" as m;"#).is_err());
+
+        // Without a module root of its own, an engine may only import modules if it may read files
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+        assert!(engine.exec("import math;").unwrap_err().to_string().contains("fs_read"));
+    }
+}
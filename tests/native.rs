@@ -0,0 +1,32 @@
+use forge::{Engine, ExecError, Value};
+
+#[test]
+fn register_fn() {
+    let mut engine = Engine::build()
+        .with_fn("double", 1, |args| match &args[0] {
+            Value::Int(x) => Ok(Value::Int(x * 2)),
+            val => Err(ExecError::NotNumeric(val.get_type_name())),
+        })
+        .finish();
+    engine.register_fn("join", 2, |args| Ok(format!("{}-{}", args[0], args[1]).into()));
+
+    assert!(engine.eval(r#"double(21)"#).unwrap() == 42.0);
+    assert!(engine.eval(r#"join("a", double(2))"#).unwrap() == "a-4");
+
+    engine.exec(r#"var f = double; var x = f(f(1));"#).unwrap();
+    assert!(engine.take("x").unwrap() == 4.0);
+}
+
+#[test]
+fn native_fn_errors() {
+    let mut engine = Engine::default();
+    engine.register_fn("fail", 0, |_| Err(ExecError::Custom("Something went wrong".to_string())));
+    engine.register_fn("id", 1, |args| Ok(args[0].clone()));
+
+    let msg = format!("{}", engine.eval(r#"id(1, 2)"#).unwrap_err());
+    assert!(msg.contains("Expected 1, found 2."));
+
+    let msg = format!("{}", engine.exec("var x = 1;\nfail();").unwrap_err());
+    assert!(msg.contains("at 2:1"));
+    assert!(msg.contains("Something went wrong"));
+}
@@ -0,0 +1,89 @@
+use forge::{Engine, ExecMode};
+
+const VECTOR: &str = r#"
+    var vec = |x, y| {
+        var v = ["x": x, "y": y];
+        v["__add"] = |a, b| { return vec(a["x"] + b["x"], a["y"] + b["y"]); };
+        v["__mul"] = |a, b| { return vec(a["x"] * b, a["y"] * b); };
+        v["__eq"] = |a, b| { return a["x"] == b["x"] and a["y"] == b["y"]; };
+        v["__less"] = |a, b| { return a["x"] * a["x"] + a["y"] * a["y"] < b["x"] * b["x"] + b["y"] * b["y"]; };
+        v["__neg"] = |a| { return vec(-a["x"], -a["y"]); };
+        return v;
+    };
+"#;
+
+#[test]
+fn operators() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(VECTOR).unwrap();
+        engine.exec(r#"
+            var sum = vec(1, 2) + vec(3, 4);
+            var sum_x = sum["x"];
+            var scaled = vec(1, 2) * 3;
+            var scaled_y = scaled["y"];
+            var neg = -vec(1, 2);
+            var neg_x = neg["x"];
+            var total = vec(0, 0);
+            total += vec(2, 2);
+            var total_y = total["y"];
+            var eq = vec(1, 2) == vec(1, 2);
+            var not_eq = vec(1, 2) != vec(1, 2);
+            var less = vec(1, 1) < vec(2, 2);
+        "#).unwrap();
+
+        assert!(engine.take("sum_x").unwrap() == 4.0);
+        assert!(engine.take("scaled_y").unwrap() == 6.0);
+        assert!(engine.take("neg_x").unwrap() == -1.0);
+        assert!(engine.take("total_y").unwrap() == 2.0);
+        assert!(engine.take("eq").unwrap() == true);
+        assert!(engine.take("not_eq").unwrap() == false);
+        assert!(engine.take("less").unwrap() == true);
+
+        // Maps without the special members keep their usual behaviour
+        assert!(engine.eval(r#"(["a": 1] + ["b", 2])["b"]"#).unwrap() == 2.0);
+        assert!(engine.eval(r#"["a": 1] < ["a": 1]"#).is_err());
+    }
+}
+
+#[test]
+fn class_operators() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            class V {
+                fn new(x) { self.x = x; }
+                fn __add(a, b) { return V.new(a.x + b.x); }
+                fn __mul(a, b) { return V.new(self.x * (a is V and b or a)); }
+                fn __neg(a) { return V.new(-a.x); }
+            }
+            var sum = V.new(1) + V.new(2);
+            var scaled = V.new(3) * 2;
+            var neg = -V.new(4);
+        "#).unwrap();
+
+        assert!(engine.eval("sum.x").unwrap() == 3);
+        assert!(engine.eval("sum is V").unwrap() == true);
+        assert!(engine.eval("scaled.x").unwrap() == 6);
+        assert!(engine.eval("neg.x").unwrap() == -4);
+        // The handler of the right operand is used when the left has none, and `self` is the operand it belongs to
+        assert!(engine.eval("(2 * V.new(5)).x").unwrap() == 10);
+        assert!(engine.eval("V.new(1) < V.new(2)").is_err());
+    }
+}
+
+#[test]
+fn index_fallback() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var counts = ["apples": 3, "__index": |m, k| { return 0; }];
+        var apples = counts["apples"];
+        var pears = counts["pears"];
+    "#).unwrap();
+
+    assert!(engine.take("apples").unwrap() == 3.0);
+    assert!(engine.take("pears").unwrap() == 0.0);
+}
@@ -0,0 +1,79 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+    collections::VecDeque,
+};
+use forge::{Engine, ExecResult, Io};
+
+// Answers each question with the next of a fixed list of lines, recording everything shown to the user
+struct ScriptedIo {
+    answers: VecDeque<&'static str>,
+    shown: Rc<RefCell<Vec<String>>>,
+}
+
+impl Io for ScriptedIo {
+    fn input(&mut self, s: String) -> ExecResult<String> {
+        self.shown.borrow_mut().push(s);
+        Ok(self.answers.pop_front().unwrap_or("").to_string())
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.shown.borrow_mut().push(s);
+        Ok(())
+    }
+
+    fn input_hidden(&mut self, s: String) -> ExecResult<String> {
+        self.shown.borrow_mut().push(format!("(hidden) {}", s));
+        self.input(String::new())
+    }
+}
+
+fn engine_with_answers(answers: &[&'static str]) -> (Engine, Rc<RefCell<Vec<String>>>) {
+    let shown = Rc::new(RefCell::new(Vec::new()));
+    let engine = Engine::build()
+        .with_io(ScriptedIo { answers: answers.iter().copied().collect(), shown: shown.clone() })
+        .finish();
+    (engine, shown)
+}
+
+#[test]
+fn confirm() {
+    let (mut engine, shown) = engine_with_answers(&["maybe", "Y", "no", ""]);
+    engine.exec(r#"
+        var a = prompt.confirm("Continue?");
+        var b = prompt.confirm("Really?");
+        var c = prompt.confirm("Sure?");
+    "#).unwrap();
+    assert!(engine.take("a").unwrap() == true);
+    assert!(engine.take("b").unwrap() == false);
+    assert!(engine.take("c").unwrap() == false);
+    assert_eq!(shown.borrow()[0], "Continue? [y/N] ");
+    assert_eq!(shown.borrow()[1], "Please answer 'y' or 'n'.");
+}
+
+#[test]
+fn select() {
+    let (mut engine, shown) = engine_with_answers(&["4", "2", "blue", ""]);
+    engine.exec(r#"
+        var colors = ["red", "green", "blue"];
+        var a = prompt.select("Pick a color:", colors);
+        var b = prompt.select("Pick another:", colors);
+        var c = prompt.select("And another:", colors);
+    "#).unwrap();
+    assert!(engine.take("a").unwrap() == "green");
+    assert!(engine.take("b").unwrap() == "blue");
+    assert!(engine.take("c").unwrap().is_null());
+    assert_eq!(&shown.borrow()[0..4], &["Pick a color:", "  1) red", "  2) green", "  3) blue"]);
+    assert!(shown.borrow().contains(&String::from("Please enter a number from 1 to 3.")));
+
+    assert!(engine.exec("prompt.select(\"Pick:\", []);").is_err());
+    assert!(engine.exec("prompt.select(\"Pick:\", \"red\");").is_err());
+}
+
+#[test]
+fn password() {
+    let (mut engine, shown) = engine_with_answers(&["hunter2"]);
+    engine.exec(r#"var secret = prompt.password("Password:");"#).unwrap();
+    assert!(engine.take("secret").unwrap() == "hunter2");
+    assert_eq!(shown.borrow()[0], "(hidden) Password: ");
+}
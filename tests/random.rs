@@ -0,0 +1,89 @@
+use forge::{Engine, ExecMode};
+
+fn ids(engine: &mut Engine) -> Vec<String> {
+    engine.exec(r#"
+        var ids = [uuid.v4(), random.token(12), random.token(20, "alphanumeric"), random.token(0)];
+    "#).unwrap();
+    engine.get("ids").unwrap().as_list().unwrap().iter().map(|id| id.as_string().unwrap()).collect()
+}
+
+#[test]
+fn random_ids() {
+    let ids = ids(&mut Engine::default());
+
+    let uuid = ids[0].as_bytes();
+    assert_eq!(uuid.len(), 36);
+    assert!([8, 13, 18, 23].iter().all(|i| uuid[*i] == b'-'));
+    assert_eq!(uuid[14], b'4');
+    assert!(b"89ab".contains(&uuid[19]));
+
+    assert_eq!(ids[1].len(), 12);
+    assert!(ids[1].bytes().all(|b| b"0123456789abcdef".contains(&b)));
+    assert_eq!(ids[2].len(), 20);
+    assert!(ids[2].bytes().all(|b| b.is_ascii_alphanumeric()));
+    assert_eq!(ids[3], "");
+
+    let mut engine = Engine::default();
+    assert!(engine.eval("uuid.v4() != uuid.v4()").unwrap() == true);
+    assert!(engine.eval("random.token(-1)").is_err());
+    assert!(engine.eval("random.token(4, \"base64\")").is_err());
+}
+
+#[test]
+fn deterministic_mode() {
+    let a = ids(&mut Engine::build().with_seed(42).finish());
+    let b = ids(&mut Engine::build().with_seed(42).finish());
+    let c = ids(&mut Engine::build().with_seed(43).finish());
+
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+}
+
+fn rolls(engine: &mut Engine) -> Vec<String> {
+    engine.exec(r#"
+        var deck = [1, 2, 3, 4, 5, 6, 7, 8];
+        shuffle(deck);
+        var rolls = [rand(), rand_range(1, 6), rand_range(-1.5, 1.5), deck];
+    "#).unwrap();
+    engine.get("rolls").unwrap().as_list().unwrap().iter().map(|roll| roll.to_string()).collect()
+}
+
+#[test]
+fn random_numbers() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var valid = true;
+            var faces = [];
+            for i in 0..500 {
+                var x = rand();
+                var face = rand_range(1, 6);
+                var y = rand_range(2, 2.5);
+                valid = valid and x >= 0 and x < 1 and y >= 2 and y <= 2.5;
+                if !faces.contains(face) { faces.push(face); }
+            }
+            var deck = [1, 2, 3, 4, 5];
+            shuffle(deck);
+            faces.sort();
+            deck.sort();
+        "#).unwrap();
+        assert!(engine.eval("valid").unwrap() == true);
+        assert!(engine.eval("faces == [1, 2, 3, 4, 5, 6]").unwrap() == true);
+        assert!(engine.eval("deck == [1, 2, 3, 4, 5]").unwrap() == true);
+        assert!(engine.eval("rand_range(3, 3)").unwrap() == 3);
+        assert!(engine.eval("rand_range(-9223372036854775807 - 1, 9223372036854775807)").is_ok());
+
+        assert!(engine.eval("rand_range(6, 1)").is_err());
+        assert!(engine.eval("rand(1)").is_err());
+        assert!(engine.eval(r#"shuffle("abc")"#).is_err());
+    }
+
+    // The same seed gives the same numbers, whether it is given when the engine is built or afterwards
+    let a = rolls(&mut Engine::build().with_seed(7).finish());
+    let mut engine = Engine::default();
+    engine.seed_rng(7);
+    assert_eq!(a, rolls(&mut engine));
+    engine.seed_rng(8);
+    assert_ne!(a, rolls(&mut engine));
+}
@@ -0,0 +1,41 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn source_refs() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+var double = |x| { return x * 2; };
+var r = reflect.source_ref(double);
+var e = null;
+try {
+    var y = 1 + "one";
+} catch err {
+    e = reflect.source_ref(err);
+}
+        "#).unwrap();
+
+        assert!(engine.eval("r.line").unwrap() == 2);
+        assert!(engine.eval("r.col").unwrap() == 14);
+        assert!(engine.eval("r.end_line").unwrap() == 2);
+        assert!(engine.eval("r.end_col").unwrap() == 35);
+        assert!(engine.eval("r.text").unwrap() == "|x| { return x * 2; }");
+        assert!(engine.eval("e.line").unwrap() == 6);
+        assert!(engine.eval("reflect.source_ref(math.sqrt)").unwrap().is_null());
+        assert!(engine.eval("reflect.source_ref(5)").unwrap().is_null());
+
+        // Locations render as they do in errors, against the code that renders them unless given the source
+        assert_eq!(
+            engine.eval(r#"sys.render_ref(["line": 1, "col": 5, "end_line": 1, "end_col": 8])"#).unwrap().to_string(),
+            "   1| sys.render_ref([\"line\": 1, \"col\": 5, \"end_line\": 1, \"end_col\": 8])\n    |     ^^^\n",
+        );
+        assert_eq!(
+            engine.eval(r#"sys.render_ref(r, "\nabcdefghijklmnopqrstuvwxyz0123456789")"#).unwrap().to_string(),
+            "   2| abcdefghijklmnopqrstuvwxyz0123456789\n    |              ^^^^^^^^^^^^^^^^^^^^^\n",
+        );
+        assert!(engine.eval(r#"sys.render_ref(["line": 1])"#).is_err());
+        assert!(engine.eval(r#"sys.render_ref(["line": 2, "col": 5, "end_line": 1, "end_col": 8])"#).is_err());
+        assert!(engine.eval("sys.render_ref(5)").is_err());
+    }
+}
@@ -0,0 +1,51 @@
+#![cfg(feature = "regex")]
+
+use forge::{Engine, ExecMode};
+
+#[test]
+fn regex() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            var m = regex.match("(\\d+)-(?P<word>\\w*)(x)?", "ab 12-cd ef");
+        "#).unwrap();
+        assert!(engine.eval("m.text").unwrap() == "12-cd");
+        assert!(engine.eval("m.start").unwrap() == 3);
+        assert!(engine.eval("m.end").unwrap() == 8);
+        assert_eq!(engine.eval("m.groups").unwrap().to_string(), "[12, cd, <null>]");
+        assert!(engine.eval("m.named.word").unwrap() == "cd");
+        // The keys of the match are always in the same order
+        assert_eq!(engine.eval("m.keys()").unwrap().to_string(), "[text, start, end, groups, named]");
+        assert!(engine.eval(r#"regex.match("^\\d+$", "12a")"#).unwrap().is_null());
+
+        // Positions count characters, as `find` does
+        assert!(engine.eval(r#"regex.match("b", "ébb").start"#).unwrap() == 1);
+
+        assert_eq!(
+            engine.eval(r#"regex.find_all("\\b\\w", "one two  three").map(|m| { return m.text; })"#).unwrap().to_string(),
+            "[o, t, t]",
+        );
+        assert_eq!(engine.eval(r#"regex.find_all("a*", "baa").len()"#).unwrap(), 3);
+        assert!(engine.eval(r#"regex.replace("(\\w+)@(?<host>\\w+)", "joe@home, ann@work", "$host:$1 $$")"#).unwrap()
+            == "home:joe $, work:ann $");
+        assert!(engine.eval(r#"regex.replace("\\d+", "a1b22c", |m| { return m.text.len(); })"#).unwrap() == "a1b2c");
+        assert_eq!(engine.eval(r#"regex.split(",\\s*", "a, b,c,,  d")"#).unwrap().to_string(), "[a, b, c, , d]");
+
+        // Greediness, counted repetition, classes and alternation
+        assert!(engine.eval(r#"regex.match("<.+?>", "<a><b>").text"#).unwrap() == "<a>");
+        assert!(engine.eval(r#"regex.match("x\{2,3}", "xxxx").text"#).unwrap() == "xxx");
+        assert!(engine.eval(r#"regex.match("[^\\d\\s-]+", "12 -ab3").text"#).unwrap() == "ab");
+        assert!(engine.eval(r#"regex.match("(a|ab)(c|bcd)", "abcd").groups[1]"#).unwrap() == "bcd");
+
+        // Patterns that would take exponential time to backtrack through do not
+        assert!(engine.eval(r#"regex.match("^(a+)+$", "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaab")"#).unwrap().is_null());
+
+        assert!(engine.eval(r#"regex.match("(", "x")"#).is_err());
+        assert!(engine.eval(r#"regex.match("a**", "x")"#).is_ok());
+        assert!(engine.eval(r#"regex.match("*", "x")"#).is_err());
+        assert!(engine.eval(r#"regex.match("[z-a]", "x")"#).is_err());
+        assert!(engine.eval(r#"regex.match("\\q", "x")"#).is_err());
+        assert!(engine.eval(r#"regex.replace("x", "x", 5)"#).is_err());
+    }
+}
@@ -0,0 +1,80 @@
+use std::{
+    cell::Cell,
+    collections::VecDeque,
+    rc::Rc,
+};
+use forge::{Engine, EngineBuilder, ExecError, ExecMode, ExecResult, Io, Recording, Value};
+
+// Answers each question with the next of a fixed list of lines
+struct ScriptedIo(VecDeque<&'static str>);
+
+impl Io for ScriptedIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(self.0.pop_front().unwrap_or("").to_string())
+    }
+
+    fn print(&mut self, _s: String) -> ExecResult<()> {
+        Ok(())
+    }
+}
+
+const CODE: &str = r#"
+    var name = input "Name? ";
+    var token = random.token(16);
+    var now = "{datetime.now()}";
+    var first = next_id();
+    var second = next_id();
+    var failed = null;
+    try {
+        fail();
+    } catch e {
+        failed = e.message;
+    }
+    var results = [name, token, now, first, second, failed];
+"#;
+
+fn results(engine: &mut Engine) -> Vec<String> {
+    engine.exec(CODE).unwrap();
+    engine.get("results").unwrap().as_list().unwrap().iter().map(|val| engine.display(val).unwrap()).collect()
+}
+
+fn builder(mode: ExecMode, answers: &[&'static str], calls: Rc<Cell<i64>>) -> EngineBuilder {
+    Engine::build()
+        .with_exec_mode(mode)
+        .allow_clock()
+        .with_io(ScriptedIo(answers.iter().copied().collect()))
+        .with_fn("next_id", 0, move |_| {
+            calls.set(calls.get() + 1);
+            Ok(Value::from(vec![Value::Int(calls.get()), Value::from("id")]))
+        })
+        .with_fn("fail", 0, |_| Err(ExecError::Custom(String::from("The host gave up."))))
+}
+
+#[test]
+fn record_and_replay() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let calls = Rc::new(Cell::new(0));
+        let mut engine = builder(mode, &["Ada"], calls.clone()).record().finish();
+        let recorded = results(&mut engine);
+        assert_eq!(recorded[0], "Ada");
+        assert_eq!(recorded[3], "[1, id]");
+        assert_eq!(recorded[5], "The host gave up.");
+        assert_eq!(calls.get(), 2);
+
+        let recording = Recording::from_bytes(&engine.recording().unwrap().to_bytes()).unwrap();
+        let calls = Rc::new(Cell::new(100));
+        let mut engine = builder(mode, &["Grace"], calls.clone()).replay(recording.clone()).finish();
+        assert_eq!(results(&mut engine), recorded);
+        // Host functions are not called when replaying, and their results are the recorded ones
+        assert_eq!(calls.get(), 100);
+        assert!(engine.recording().is_none());
+
+        // Code that asks for something other than what was recorded cannot be replayed
+        let mut engine = builder(mode, &[], calls.clone()).replay(recording).finish();
+        let msg = engine.exec("var x = next_id();").unwrap_err().to_string();
+        assert!(msg.contains("The code asked for the result of 'next_id' where the recording has a line of input"), "{}", msg);
+    }
+
+    assert!(Recording::from_bytes(b"not a recording").is_none());
+    assert!(Recording::from_bytes(b"forge-replay 1\n").is_none());
+}
@@ -0,0 +1,81 @@
+use forge::Engine;
+
+#[test]
+fn parse() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var v = semver.parse("v1.20.3-rc.1+build.5");
+        var plain = semver.parse("0.1.0");
+    "#).unwrap();
+    assert!(engine.eval("v.major").unwrap() == 1.0);
+    assert!(engine.eval("v.minor").unwrap() == 20.0);
+    assert!(engine.eval("v.patch").unwrap() == 3.0);
+    assert!(engine.eval("v.pre").unwrap() == "rc.1");
+    assert!(engine.eval("v.build").unwrap() == "build.5");
+    assert!(engine.eval("plain.pre").unwrap().is_null());
+    assert!(engine.eval("plain.build").unwrap().is_null());
+
+    for invalid in &["1.2", "1.2.3.4", "01.2.3", "1.2.3-", "1.2.3-01", "1.2.3+", "a.b.c", ""] {
+        assert!(engine.exec(&format!(r#"semver.parse("{}");"#, invalid)).unwrap_err().to_string().contains("is not a valid semantic version."));
+    }
+}
+
+#[test]
+fn compare() {
+    let mut engine = Engine::default();
+
+    // In increasing order of precedence, as given by the specification
+    let ordered = [
+        "1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11",
+        "1.0.0-rc.1", "1.0.0", "1.0.1", "1.2.0", "1.10.0", "2.0.0",
+    ];
+    for (i, a) in ordered.iter().enumerate() {
+        for (j, b) in ordered.iter().enumerate() {
+            let expected = if i < j { -1.0 } else if i > j { 1.0 } else { 0.0 };
+            assert!(engine.eval(&format!(r#"semver.compare("{}", "{}")"#, a, b)).unwrap() == expected, "{} vs {}", a, b);
+        }
+    }
+    assert!(engine.eval(r#"semver.compare("1.0.0+a", "1.0.0+b")"#).unwrap() == 0.0);
+}
+
+#[test]
+fn matches() {
+    let mut engine = Engine::default();
+    let mut check = |version: &str, req: &str, expected: bool| {
+        assert!(engine.eval(&format!(r#"semver.matches("{}", "{}")"#, version, req)).unwrap() == expected, "{} {}", version, req);
+    };
+
+    check("1.2.3", "^1.2", true);
+    check("1.9.0", "^1.2", true);
+    check("2.0.0", "^1.2", false);
+    check("1.1.0", "^1.2", false);
+    check("0.2.5", "^0.2.3", true);
+    check("0.3.0", "^0.2.3", false);
+    check("0.0.4", "^0.0.3", false);
+    check("1.5.0", "1.2", true);
+    check("1.2.9", "~1.2.3", true);
+    check("1.3.0", "~1.2.3", false);
+    check("1.2.3", "=1.2.3", true);
+    check("1.2.4", "=1.2.3", false);
+    check("1.2.4", "=1.2", true);
+    check("1.3.0", ">1.2", true);
+    check("1.2.9", ">1.2", false);
+    check("1.2.9", "<=1.2", true);
+    check("1.3.0", "<=1.2", false);
+    check("1.4.0", ">= 1.2, < 1.5", true);
+    check("1.5.0", ">=1.2 <1.5", false);
+    check("3.1.0", "^1 || ^3", true);
+    check("2.1.0", "^1 || ^3", false);
+    check("1.2.7", "1.2.*", true);
+    check("1.3.0", "1.2.x", false);
+    check("9.9.9", "*", true);
+    check("2.0.0-rc.1", "<2.0.0", false);
+    check("2.0.0-rc.2", ">=2.0.0-rc.1", true);
+    check("2.0.0-rc.1", ">=2.0.0-beta", true);
+    check("2.0.0", ">=2.0.0-rc.1", true);
+
+    for invalid in &["", ">=", "^1.x.3", "1.2-rc", "a.b", "1 ||"] {
+        assert!(engine.exec(&format!(r#"semver.matches("1.0.0", "{}");"#, invalid)).unwrap_err().to_string().contains("is not a valid version requirement."), "{}", invalid);
+    }
+}
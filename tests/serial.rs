@@ -0,0 +1,72 @@
+use forge::Engine;
+
+#[test]
+fn msgpack() {
+    let mut engine = Engine::default();
+
+    let encoded = engine.eval(r#"msgpack.encode(["a": 1])"#).unwrap();
+    assert_eq!(engine.display(&encoded).unwrap(), "[129, 161, 97, 1]");
+    let encoded = engine.eval(r#"msgpack.encode([-1, 300, 'x', null, false])"#).unwrap();
+    assert_eq!(engine.display(&encoded).unwrap(), "[149, 255, 205, 1, 44, 161, 120, 192, 194]");
+    // Instances of classes are encoded as their items alone
+    engine.exec("class P { fn new() { self.a = 1; } }").unwrap();
+    let encoded = engine.eval("msgpack.encode(P.new())").unwrap();
+    assert_eq!(engine.display(&encoded).unwrap(), "[129, 161, 97, 1]");
+
+    engine.exec(r#"
+        var value = ["name": "forge", "tags": ["fast", "small"], "ratio": 0.25, "big": 5000000000, "neg": -40000];
+        var decoded = msgpack.decode(msgpack.encode(value));
+        var name = decoded["name"];
+        var tag = decoded["tags"][1];
+        var ratio = decoded["ratio"];
+        var big = decoded["big"];
+        var neg = decoded["neg"];
+    "#).unwrap();
+    assert!(engine.take("name").unwrap() == "forge");
+    assert!(engine.take("tag").unwrap() == "small");
+    assert!(engine.take("ratio").unwrap() == 0.25);
+    assert!(engine.take("big").unwrap() == 5000000000.0);
+    assert!(engine.take("neg").unwrap() == -40000.0);
+
+    // Binary data decodes to a list of bytes
+    let decoded = engine.eval("msgpack.decode([196, 2, 7, 8])").unwrap();
+    assert_eq!(engine.display(&decoded).unwrap(), "[7, 8]");
+
+    let err = engine.eval("msgpack.decode([146, 1])").unwrap_err().to_string();
+    assert!(err.contains("Invalid MessagePack data at byte 2: unexpected end of data."), "{}", err);
+    let err = engine.eval("msgpack.decode([1, 2])").unwrap_err().to_string();
+    assert!(err.contains("unexpected data after the end of the value"), "{}", err);
+    let err = engine.eval("msgpack.decode([1, 256])").unwrap_err().to_string();
+    assert!(err.contains("list of bytes"), "{}", err);
+    let err = engine.exec("var f = || { return 1; }; msgpack.encode([f]);").unwrap_err().to_string();
+    assert!(err.contains("Values of type 'function' cannot be encoded as MessagePack."), "{}", err);
+}
+
+#[test]
+fn cbor() {
+    let mut engine = Engine::default();
+
+    let encoded = engine.eval("cbor.encode([1, [2, 3], -500, true])").unwrap();
+    assert_eq!(engine.display(&encoded).unwrap(), "[132, 1, 130, 2, 3, 57, 1, 243, 245]");
+
+    engine.exec(r#"
+        var value = ["text": "héllo", "items": [1.5, -2, null], "nested": ["deep": [true]]];
+        var decoded = cbor.decode(cbor.encode(value));
+        var text = decoded["text"];
+        var items = decoded["items"];
+        var deep = decoded["nested"]["deep"][0];
+    "#).unwrap();
+    assert!(engine.take("text").unwrap() == "héllo");
+    let items = engine.take("items").unwrap();
+    assert_eq!(engine.display(&items).unwrap(), "[1.5, -2, <null>]");
+    assert!(engine.take("deep").unwrap() == true);
+
+    // Half-precision floats and tags are understood
+    assert!(engine.eval("cbor.decode([249, 60, 0])").unwrap() == 1.0);
+    assert!(engine.eval("cbor.decode([193, 26, 0, 0, 0, 10])").unwrap() == 10.0);
+
+    let err = engine.eval("cbor.decode([159, 1, 255])").unwrap_err().to_string();
+    assert!(err.contains("Invalid CBOR data at byte 0: indefinite-length items are not supported."), "{}", err);
+    let err = engine.exec("var l = [1]; l[0] = l; cbor.encode(l);").unwrap_err().to_string();
+    assert!(err.contains("nested too deeply"), "{}", err);
+}
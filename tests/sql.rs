@@ -0,0 +1,73 @@
+#![cfg(feature = "sqlite")]
+
+use forge::Engine;
+
+#[test]
+fn query_and_exec() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var db = sql.open(":memory:");
+        db.exec("create table fruit (name text, price real, stock integer); create index by_name on fruit (name);");
+        var inserted = db.exec("insert into fruit values (?, ?, ?), ('pear', 0.75, null)", ["apple", 0.5, 12]);
+        var rows = db.query("select * from fruit where price < :max order by name", ["max": 1]);
+        var first = rows[0];
+        var apple = first["name"];
+        var stock = first["stock"];
+        var missing = rows[1]["stock"];
+        var none = db.query("select * from fruit where name = ?", ["kiwi"]);
+    "#).unwrap();
+
+    assert_eq!(engine.take("inserted").unwrap().as_int(), Some(2));
+    assert!(engine.take("apple").unwrap() == "apple");
+    assert_eq!(engine.take("stock").unwrap().as_int(), Some(12));
+    assert_eq!(engine.eval("first[\"price\"]").unwrap().to_string(), "0.5");
+    assert_eq!(engine.eval("db.query(\"select ? as x\", [0.0])[0][\"x\"]").unwrap().as_int(), None);
+    assert!(engine.take("missing").unwrap().is_null());
+    assert_eq!(engine.eval("rows").map(|rows| rows.as_list().unwrap().len()).unwrap(), 2);
+    assert_eq!(engine.take("none").unwrap().as_list().unwrap().len(), 0);
+
+    let err = engine.eval(r#"db.query("select * from fruits")"#).unwrap_err().to_string();
+    assert!(err.contains("Database error: no such table: fruits."), "{}", err);
+    let err = engine.eval(r#"db.query("select ?", [1, 2])"#).unwrap_err().to_string();
+    assert!(err.contains("The query expects 1 parameters, but 2 were given."), "{}", err);
+    let err = engine.eval(r#"db.query("select 1; select 2")"#).unwrap_err().to_string();
+    assert!(err.contains("Only one statement may be run by 'query'."), "{}", err);
+
+    engine.exec("db.close();").unwrap();
+    assert!(engine.eval(r#"db.query("select 1")"#).is_err());
+}
+
+#[test]
+fn permissions() {
+    let path = std::env::temp_dir().join(format!("forge_sql_test_{}.db", std::process::id()));
+    let path = path.to_string_lossy().into_owned();
+
+    let mut engine = Engine::default();
+    engine.set("path", path.as_str());
+    assert!(engine.exec("sql.open(path);").unwrap_err().to_string().contains("fs_read"));
+
+    let mut engine = Engine::build().allow_fs().finish();
+    engine.set("path", path.as_str());
+    engine.exec(r#"
+        var db = sql.open(path);
+        db.exec("create table t (x integer); insert into t values (1);");
+        db.close();
+    "#).unwrap();
+
+    // Without permission to write, the database is opened read-only
+    let mut engine = Engine::build()
+        .with_permissions(forge::Permissions { fs_read: true, ..Default::default() })
+        .finish();
+    engine.set("path", path.as_str());
+    engine.exec(r#"
+        var db = sql.open(path);
+        var rows = db.query("select x from t");
+        var x = rows[0]["x"];
+    "#).unwrap();
+    assert!(engine.take("x").unwrap() == 1.0);
+    let err = engine.exec(r#"db.exec("insert into t values (2)");"#).unwrap_err().to_string();
+    assert!(err.contains("readonly"), "{}", err);
+
+    std::fs::remove_file(&path).unwrap();
+}
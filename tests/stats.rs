@@ -0,0 +1,33 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn stats() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec("var times = [12, 7, 3.5, 9, 8.5];").unwrap();
+        assert!(engine.eval("stats.mean(times)").unwrap() == 8.0);
+        assert!(engine.eval("stats.median(times)").unwrap() == 8.5);
+        assert!(engine.eval("stats.median([4, 1, 3, 2])").unwrap() == 2.5);
+        assert!(engine.eval("stats.stddev([2, 4, 4, 4, 5, 5, 7, 9])").unwrap() == 2.0);
+        assert!(engine.eval("stats.stddev([5])").unwrap() == 0.0);
+
+        // Percentiles interpolate between the numbers either side of them
+        assert!(engine.eval("stats.percentile(times, 0)").unwrap() == 3.5);
+        assert!(engine.eval("stats.percentile(times, 100)").unwrap() == 12.0);
+        assert!(engine.eval("stats.percentile([10, 20, 30, 40], 90)").unwrap() == 37.0);
+
+        // Ties go to the first
+        engine.exec(r#"var people = [["name": "Ada", "age": 36], ["name": "Alan", "age": 41], ["name": "Grace", "age": 36]];"#).unwrap();
+        assert!(engine.eval(r#"stats.min_by(people, |p| { return p.age; }).name"#).unwrap() == "Ada");
+        assert!(engine.eval(r#"stats.max_by(people, |p| { return p.age; }).name"#).unwrap() == "Alan");
+        assert!(engine.eval(r#"stats.max_by(["kiwi", "fig", "banana"], |s| { return s.len(); })"#).unwrap() == "banana");
+
+        assert!(engine.eval("stats.mean([])").is_err());
+        assert!(engine.eval(r#"stats.mean([1, "two"])"#).is_err());
+        assert!(engine.eval("stats.mean(5)").is_err());
+        assert!(engine.eval("stats.percentile(times, 101)").is_err());
+        assert!(engine.eval("stats.min_by([], |x| { return x; })").is_err());
+        assert!(engine.eval(r#"stats.min_by([1, "a"], |x| { return x; })"#).is_err());
+    }
+}
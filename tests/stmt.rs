@@ -0,0 +1,636 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn else_if_chains() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var classify = |x| {
+            if x < 0 {
+                return "negative";
+            } else if x == 0 {
+                return "zero";
+            } else if x < 10 {
+                return "small";
+            } else {
+                return "large";
+            }
+        };
+        var a = classify(-3);
+        var b = classify(0);
+        var c = classify(4);
+        var d = classify(42);
+    "#).unwrap();
+
+    assert!(engine.take("a").unwrap() == "negative");
+    assert!(engine.take("b").unwrap() == "zero");
+    assert!(engine.take("c").unwrap() == "small");
+    assert!(engine.take("d").unwrap() == "large");
+}
+
+#[test]
+fn else_if_without_else() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var x = 5;
+        var r = 0;
+        if x == 1 { r = 1; } else if x == 2 { r = 2; }
+    "#).unwrap();
+
+    assert!(engine.take("r").unwrap() == 0.0);
+}
+
+#[test]
+fn try_catch() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var caught = null;
+        var line = null;
+        try {
+            var x = 1;
+            x = x + [];
+            caught = "unreachable";
+        } catch e {
+            caught = e.message;
+            line = e.line;
+        }
+
+        var untouched = "ok";
+        try {
+            untouched = "still ok";
+        } catch e {
+            untouched = "caught";
+        }
+
+        var nested = "";
+        try {
+            try {
+                undefined_var;
+            } catch e {
+                nested = nested + "inner ";
+                null();
+            }
+        } catch e {
+            nested = nested + "outer";
+        }
+    "#).unwrap();
+
+    assert!(engine.take("caught").unwrap() != "unreachable");
+    assert!(engine.take("line").unwrap() == 6.0);
+    assert!(engine.take("untouched").unwrap() == "still ok");
+    assert!(engine.take("nested").unwrap() == "inner outer");
+}
+
+#[test]
+fn try_catch_return() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var safe_div = |a, b| {
+            try {
+                if b == 0 { null(); }
+                return a / b;
+            } catch e {
+                return "failed";
+            }
+        };
+        var a = safe_div(6, 3);
+        var b = safe_div(1, 0);
+    "#).unwrap();
+
+    assert!(engine.take("a").unwrap() == 2.0);
+    assert!(engine.take("b").unwrap() == "failed");
+}
+
+#[test]
+fn match_patterns() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var describe = |x| {
+            match x {
+                0 => { return "zero"; }
+                -1 => { return "minus one"; }
+                1..10 => { return "small"; }
+                'a'..'z' => { return "lowercase"; }
+                "hello" => { return "greeting"; }
+                true => { return "yes"; },
+                null => { return "nothing"; },
+                [] => { return "empty"; },
+                [a, b] => { return "pair of {a} and {b}"; },
+                [first, 0, ...rest] => { return "{first} then zero then {rest}"; },
+                [_, ...] => { return "some list"; },
+                _ => { return "something else"; },
+            }
+        };
+        var results = [
+            describe(0), describe(-1), describe(5), describe(10), describe('q'), describe("hello"),
+            describe(true), describe(null), describe([]), describe([1, 2]), describe([7, 0, 8, 9]),
+            describe([1, 2, 3]), describe(false),
+        ];
+    "#).unwrap();
+
+    assert_eq!(
+        engine.eval("results").unwrap().to_string(),
+        "[zero, minus one, small, something else, lowercase, greeting, yes, nothing, empty, \
+        pair of 1 and 2, 7 then zero then [8, 9], some list, something else]",
+    );
+}
+
+#[test]
+fn destructuring() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var [a, b, c] = [1, 2, 3];
+        var [head, ...tail] = ["x", "y", "z"];
+        var {x, y} = ["x": 4, "y": 5, "z": 6];
+        var {name, tags: [first_tag, ...]} = ["name": "forge", "tags": ["lang", "rust"]];
+        var [_, {id}] = [null, ["id": 7]];
+        var [] = [];
+
+        var shape = |s| {
+            match s {
+                {kind: "circle", r} => { return "circle of {r}"; },
+                {w, h} => { return "{w} by {h}"; },
+                _ => { return "unknown"; },
+            }
+        };
+        var shapes = [shape(["kind": "circle", "r": 2]), shape(["w": 1, "h": 3]), shape(["w": 1]), shape(5)];
+    "#).unwrap();
+
+    assert!(engine.take("a").unwrap() == 1);
+    assert!(engine.take("c").unwrap() == 3);
+    assert!(engine.take("head").unwrap() == "x");
+    assert_eq!(engine.take("tail").unwrap().to_string(), "[y, z]");
+    assert!(engine.take("x").unwrap() == 4);
+    assert!(engine.take("y").unwrap() == 5);
+    assert!(engine.take("z").is_none());
+    assert!(engine.take("name").unwrap() == "forge");
+    assert!(engine.take("first_tag").unwrap() == "lang");
+    assert!(engine.take("id").unwrap() == 7);
+    assert_eq!(engine.eval("shapes").unwrap().to_string(), "[circle of 2, 1 by 3, unknown, unknown]");
+
+    let message = |engine: &mut Engine, code: &str| engine.exec(code).unwrap_err().to_string();
+    assert!(message(&mut engine, "var [p, q] = [1, 2, 3];").contains("Expected a list of 2 items to destructure, found one of 3 items."));
+    assert!(message(&mut engine, "var [p, q, ...r] = [1];").contains("Expected a list of at least 2 items to destructure, found one of 1 item."));
+    assert!(message(&mut engine, "var [p] = 5;").contains("Cannot destructure a value of type 'int' as a list."));
+    assert!(message(&mut engine, r#"var {p, q} = ["p": 1];"#).contains("The map has no key 'q' to destructure."));
+    assert!(message(&mut engine, "var [0, p] = [1, 2];").contains("The value 1 does not match the pattern."));
+    // The rest is spelled like a rest parameter
+    assert!(engine.exec("var [p, ..r] = [1, 2];").is_err());
+    // Nothing is declared when the value does not fit
+    assert!(engine.get("p").is_none());
+}
+
+#[test]
+fn multiple_return_values() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+
+        engine.exec(r#"
+            fn sorted(a, b) {
+                if a > b {
+                    return b, a;
+                }
+                return a, b;
+            }
+            var lo, hi = sorted(9, 2);
+            var _, {name}, [first, ...] = sorted(1, 1), ["name": "forge"], [3, 4];
+            var both = sorted(5, 7);
+        "#).unwrap();
+
+        assert!(engine.take("lo").unwrap() == 2);
+        assert!(engine.take("hi").unwrap() == 9);
+        assert!(engine.take("name").unwrap() == "forge");
+        assert!(engine.take("first").unwrap() == 3);
+        // The values are returned together as a list
+        assert_eq!(engine.take("both").unwrap().to_string(), "[5, 7]");
+
+        let msg = engine.exec("var x, y = sorted;").unwrap_err().to_string();
+        assert!(msg.contains("Cannot destructure a value of type 'function' as a list."), "{}", msg);
+        let msg = engine.exec("var x, y, z = sorted(1, 1);").unwrap_err().to_string();
+        assert!(msg.contains("Expected a list of 3 items to destructure, found one of 2 items."), "{}", msg);
+        assert!(engine.exec("var x, = sorted(1, 1);").is_err());
+        assert!(engine.exec("fn f() { return 1, ; }").is_err());
+    }
+}
+
+#[test]
+fn match_without_matching_arm() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var x = "unchanged";
+        match 42 {
+            0 => { x = "zero"; }
+        }
+        var bound = null;
+        match [1, [2, 3]] {
+            [a, [b, c]] => { bound = a + b + c; }
+        }
+    "#).unwrap();
+
+    assert!(engine.take("x").unwrap() == "unchanged");
+    assert!(engine.take("bound").unwrap() == 6.0);
+    assert!(engine.exec("match 1 { 1 => { } 2 }").is_err());
+    assert!(engine.exec("match 1 { x y => { } }").is_err());
+}
+
+#[test]
+fn fn_declaration() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        fn add(a, b) {
+            return a + b;
+        }
+        fn nothing() { }
+        var sum = add(2, 3);
+        var empty = nothing();
+        var name = "{add}";
+    "#).unwrap();
+
+    assert!(engine.take("sum").unwrap() == 5.0);
+    assert!(engine.take("empty").unwrap().is_null());
+    assert!(engine.take("name").unwrap() == "<function 'add'>");
+    assert!(engine.exec("fn (x) { return x; }").is_err());
+}
+
+#[test]
+fn parameter_types() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            fn scale(x: num, by: int) {
+                return x * by;
+            }
+            var greet = |name: str, loud: bool| { return loud ? name + "!" : name; };
+            var call = |f: fn, x| { return f(x); };
+        "#).unwrap();
+
+        assert!(engine.eval("scale(1.5, 2)").unwrap() == 3.0);
+        assert!(engine.eval("scale(3, 2)").unwrap() == 6);
+        assert!(engine.eval(r#"greet("forge", true)"#).unwrap() == "forge!");
+        assert!(engine.eval("call(|x: int| { return x + 1; }, 1)").unwrap() == 2);
+
+        let msg = engine.eval("scale(1, 2.5)").unwrap_err().to_string();
+        assert!(msg.contains("Expected an argument of type 'int' for parameter 'by', found one of type 'float'."), "{}", msg);
+        assert!(engine.eval(r#"call(|x: int| { return x; }, "a")"#).unwrap_err().to_string().contains("found one of type 'string'"));
+        assert!(engine.exec("fn broken(x:) { }").is_err());
+    }
+}
+
+#[test]
+fn default_and_rest_parameters() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var calls = 0;
+            fn next() { calls += 1; return calls; }
+            fn pad(s, width = 4, fill = width - 3) {
+                return [s, width, fill];
+            }
+            var stamp = |x, at = next()| { return [x, at]; };
+            var gather = |first, ...rest| { return [first, rest]; };
+            fn sum(a: int, b = 10, ...rest) {
+                var total = a + b;
+                for x in rest { total += x; }
+                return total;
+            }
+        "#).unwrap();
+
+        assert!(engine.eval(r#"pad("a") == ["a", 4, 1]"#).unwrap() == true);
+        assert!(engine.eval(r#"pad("a", 8) == ["a", 8, 5]"#).unwrap() == true);
+        assert!(engine.eval(r#"pad("a", 8, 0) == ["a", 8, 0]"#).unwrap() == true);
+        // Defaults are evaluated anew on each call
+        assert!(engine.eval("stamp(1) == [1, 1] and stamp(1) == [1, 2] and stamp(1, 0) == [1, 0]").unwrap() == true);
+        assert!(engine.eval("gather(1)[0] == 1 and gather(1)[1] == []").unwrap() == true);
+        assert!(engine.eval("gather(1, 2, 3)[1] == [2, 3]").unwrap() == true);
+        assert!(engine.eval("sum(1)").unwrap() == 11);
+        assert!(engine.eval("sum(1, 2, 3, 4)").unwrap() == 10);
+
+        let msg = engine.eval("pad()").unwrap_err().to_string();
+        assert!(msg.contains("Expected 1, found 0."), "{}", msg);
+        let msg = engine.eval("pad(1, 2, 3, 4)").unwrap_err().to_string();
+        assert!(msg.contains("Expected 3, found 4."), "{}", msg);
+        assert!(engine.eval("gather()").unwrap_err().to_string().contains("Expected 1, found 0."));
+        assert!(engine.exec("fn broken(a = 1, b) { }").is_err());
+        assert!(engine.exec("fn broken(...rest, a) { }").is_err());
+    }
+}
+
+#[test]
+fn named_arguments() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            fn draw(x, y, color = "black", width: int = 1) {
+                return "{x},{y} {color} {width}";
+            }
+            var span = |from, to, ...rest| { return to - from; };
+        "#).unwrap();
+
+        assert!(engine.eval(r#"draw(x: 10, y: 20, color: "red")"#).unwrap() == "10,20 red 1");
+        assert!(engine.eval("draw(y: 2, x: 1)").unwrap() == "1,2 black 1");
+        assert!(engine.eval("draw(1, 2, width: 3)").unwrap() == "1,2 black 3");
+        assert!(engine.eval("span(to: 5, from: 1)").unwrap() == 4);
+
+        let message = |engine: &mut Engine, code: &str| engine.eval(code).unwrap_err().to_string();
+        let text = message(&mut engine, "draw(1, 2, colour: 3)");
+        assert!(text.contains("The function has no parameter named 'colour'."), "{}", text);
+        // Errors point to the name that caused them
+        assert!(text.contains("|            ^^^^^^"), "{}", text);
+        assert!(message(&mut engine, "draw(1, 2, x: 3)").contains("The parameter 'x' was given more than one argument."));
+        assert!(message(&mut engine, "draw(1, color: 3)").contains("No argument was given for the parameter 'y'."));
+        assert!(message(&mut engine, r#"draw(1, 2, width: "3")"#).contains("Expected an argument of type 'int' for parameter 'width'"));
+        assert!(message(&mut engine, "span(1, 2, rest: 3)").contains("no parameter named 'rest'"));
+        assert!(message(&mut engine, "expect(value: 1)").contains("Arguments cannot be given by name to a value of type 'function'."));
+        assert!(message(&mut engine, "draw(x: 1, 2)").contains("Arguments given by position must come before those given by name."));
+    }
+}
+
+#[test]
+fn methods() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var counter = ["count": 0];
+            counter.add = |n = 1| {
+                self.count += n;
+                return self;
+            };
+            fn len2() { return self.x * self.x + self.y * self.y; }
+            var point = ["x": 3, "y": 4, "len2": len2];
+            var other = ["x": 1, "y": 2, "len2": point.len2];
+            var missing = null;
+        "#).unwrap();
+
+        assert!(engine.eval("counter.add(2).add(n: 3).add()").unwrap() == engine.get("counter").unwrap());
+        assert!(engine.eval("counter.count").unwrap() == 6);
+        assert!(engine.eval("counter.add(by: 1)").is_err());
+        assert!(engine.eval("point.len2()").unwrap() == 25);
+        assert!(engine.eval("other.len2()").unwrap() == 5);
+        assert!(engine.eval(r#"point["len2"]()"#).is_err());
+        assert!(engine.eval("missing?.len2()").unwrap() == forge::Value::Null);
+        // Methods of other values are unaffected
+        assert!(engine.eval("[1, 2, 3].binary_search(2)").unwrap() == 1);
+
+        let text = engine.eval("len2()").unwrap_err().to_string();
+        assert!(text.contains("Cannot find item 'self' within the current scope."), "{}", text);
+    }
+}
+
+#[test]
+fn classes() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            class Point {
+                fn new(x, y = 0) {
+                    self.x = x;
+                    self.y = y;
+                    return 42;
+                }
+
+                fn len2(self) {
+                    return self.x * self.x + self.y * self.y;
+                }
+
+                fn plus(other) {
+                    return Point.new(self.x + other.x, self.y + other.y);
+                }
+            }
+            class Marker {}
+            var p = Point.new(3, 4);
+            var q = p.plus(Point.new(1));
+        "#).unwrap();
+
+        assert!(engine.eval("p.len2()").unwrap() == 25);
+        assert!(engine.eval("q.x").unwrap() == 4);
+        assert!(engine.eval("q.y").unwrap() == 4);
+        assert!(engine.eval("p is Point").unwrap() == true);
+        assert!(engine.eval("p is Marker").unwrap() == false);
+        assert!(engine.eval("Marker.new() is Marker").unwrap() == true);
+        assert!(engine.eval(r#"["x": 1] is Point"#).unwrap() == false);
+        assert!(engine.eval("3 is Point").unwrap() == false);
+        // Instances are maps, whose own items come before the methods of their class
+        engine.exec("p.len2 = || { return -1; };").unwrap();
+        assert!(engine.eval("p.len2()").unwrap() == -1);
+        assert!(engine.eval("q.len2()").unwrap() == 32);
+        // The class is not one of the items of its instances
+        assert_eq!(engine.eval("q.keys()").unwrap().to_string(), "[x, y]");
+        assert!(engine.eval("q.len()").unwrap() == 2);
+        assert!(engine.eval(r#"q == ["x": 4, "y": 4]"#).unwrap() == true);
+
+        assert!(engine.eval("Marker.new(1)").is_err());
+        assert!(engine.eval("Point.new()").is_err());
+        assert!(engine.eval("p is 3").is_err());
+        let text = engine.exec("class Twice { fn f() {} fn f() {} }").unwrap_err().to_string();
+        assert!(text.contains("The class 'Twice' declares more than one method named 'f'."), "{}", text);
+    }
+}
+
+#[test]
+fn tail_calls() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            fn count(n, total = 0) {
+                if n == 0 {
+                    return total;
+                }
+                return count(n - 1, total: total + 1);
+            }
+            fn is_even(n) { if n == 0 { return true; } return is_odd(n - 1); }
+            fn is_odd(n) { if n == 0 { return false; } return is_even(n - 1); }
+            var walker = ["steps": 0];
+            walker.walk = |n| {
+                if n == 0 {
+                    return self.steps;
+                }
+                self.steps += 1;
+                return self.walk(n - 1);
+            };
+            fn find(x) { return [1, 2, 3].binary_search(x); }
+            fn maybe(m) { return m?.walk(1); }
+            fn fails() { return 1 + null; }
+            fn caught() {
+                try {
+                    return fails();
+                } catch err {
+                    return "caught";
+                }
+            }
+            fn first() { return second(); }
+            fn second() { return fails(); }
+        "#).unwrap();
+
+        // Recursion through tail calls does not use up the stack
+        assert!(engine.eval("count(10000)").unwrap() == 10000);
+        assert!(engine.eval("is_even(10001)").unwrap() == false);
+        assert!(engine.eval("walker.walk(10000)").unwrap() == 10000);
+        assert!(engine.eval("find(2)").unwrap() == 1);
+        assert!(engine.eval("maybe(null)").unwrap() == forge::Value::Null);
+        // A call within `try` is not the last thing that the function does
+        assert!(engine.eval("caught()").unwrap() == "caught");
+
+        let text = engine.eval("first()").unwrap_err().to_string();
+        assert!(text.contains("...in function 'fails'"), "{}", text);
+        assert!(text.contains("...in function 'first'"), "{}", text);
+    }
+}
+
+#[test]
+fn closures() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        fn counter() {
+            var n = 0;
+            return |by| {
+                n += by;
+                return n;
+            };
+        }
+        var a = counter();
+        var b = counter();
+        a(1);
+        var from_a = a(2);
+        var from_b = b(10);
+
+        fn fact(n) {
+            if n <= 1 {
+                return 1;
+            }
+            return n * fact(n - 1);
+        }
+        var fact_5 = fact(5);
+
+        var offset = 1;
+        var shift = |x| { return x + offset; };
+        offset = 100;
+        var shifted = shift(1);
+    "#).unwrap();
+
+    assert!(engine.take("from_a").unwrap() == 3.0);
+    assert!(engine.take("from_b").unwrap() == 10.0);
+    assert!(engine.take("fact_5").unwrap() == 120.0);
+    assert!(engine.take("shifted").unwrap() == 101.0);
+    assert!(engine.eval("a == a").unwrap() == true);
+    assert!(engine.eval("a == b").unwrap() == false);
+}
+
+#[test]
+fn for_key_value() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var prices = ["apple": 3, "pear": 5];
+            var total = 0;
+            var names = "";
+            for name, price in prices {
+                total += price;
+                prices[name] = price * 2;
+            }
+            var doubled = prices["pear"];
+
+            var weighted = 0;
+            for i, x in [10, 20, 30] {
+                weighted += i * x;
+            }
+
+            var last = null;
+            for i, c in "abc" {
+                last = i;
+            }
+        "#).unwrap();
+
+        assert!(engine.take("total").unwrap() == 8.0);
+        assert!(engine.take("doubled").unwrap() == 10.0);
+        assert!(engine.take("weighted").unwrap() == 80.0);
+        assert!(engine.take("last").unwrap() == 2.0);
+        assert!(engine.exec("for k, v in 5 { }").is_err());
+        assert!(engine.exec("for k, in [1] { }").is_err());
+    }
+}
+
+#[test]
+fn element_assignment() {
+    for mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+
+        engine.exec(r#"
+            var xs = [1, 2, 3];
+            xs[0] += 10;
+            xs[-1] *= 4;
+            xs[1] -= 5;
+
+            var calls = 0;
+            var next = || { calls += 1; return 2; };
+            xs[next()] %= 5;
+
+            var point = ["x": 1];
+            point.x += 2;
+            point.y = 7;
+            point["y"] /= 2;
+
+            var grid = [[1, 2], [3, 4]];
+            grid[1][0] += 30;
+        "#).unwrap();
+
+        assert!(engine.eval("xs[0]").unwrap() == 11.0);
+        assert!(engine.eval("xs[1]").unwrap() == -3.0);
+        assert!(engine.eval("xs[2]").unwrap() == 2.0);
+        assert!(engine.take("calls").unwrap() == 1.0);
+        assert!(engine.eval("point.x").unwrap() == 3.0);
+        assert!(engine.eval("point.y").unwrap() == 3.5);
+        assert!(engine.eval("point.z").unwrap() == forge::Value::Null);
+        assert!(engine.eval("grid[1][0]").unwrap() == 33.0);
+        assert!(engine.exec("var n = 5; n.x = 1;").is_err());
+        assert!(engine.exec("[1, 2] += 1;").is_err());
+    }
+}
+
+#[test]
+fn match_types() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var describe = |x| {
+            match x {
+                n: int => { return "int {n}"; }
+                n: num => { return "number {n}"; }
+                s: str => { return "string {s}"; }
+                [first: str, ...] => { return "list starting with {first}"; }
+                l: list => { return "list of {l.len()}"; }
+                {name: n: str} => { return "named {n}"; }
+                _: fn => { return "function"; }
+                _: null => { return "nothing"; }
+                _ => { return "something else"; }
+            }
+        };
+        var results = [
+            describe(3), describe(2.5), describe("hi"), describe(["a", 1]), describe([1, 2]),
+            describe(["name": "Ada"]), describe(|| {}), describe(null), describe(true),
+        ];
+        var [a: int, b] = [1, "two"];
+    "#).unwrap();
+
+    assert_eq!(
+        engine.eval("results").unwrap().to_string(),
+        "[int 3, number 2.5, string hi, list starting with a, list of 2, named Ada, function, nothing, something else]",
+    );
+    assert!(engine.eval("a").unwrap() == 1);
+    assert!(engine.exec(r#"var [c: int] = ["three"];"#).is_err());
+    assert!(engine.exec("match 1 { n: => { } }").is_err());
+}
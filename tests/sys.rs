@@ -0,0 +1,32 @@
+use forge::{Engine, ExecMode};
+
+#[test]
+fn sys() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).allow_net().with_max_call_depth(Some(50)).finish();
+
+        assert!(engine.eval("sys.version").unwrap() == env!("CARGO_PKG_VERSION"));
+        assert_eq!(engine.eval("sys.keys()").unwrap().to_string(), "[version, features, permissions, limits, render_ref]");
+        assert_eq!(engine.eval("sys.limits.keys()").unwrap().to_string(), "[max_depth, fuel, gc_threshold]");
+        assert_eq!(engine.eval("sys.permissions.keys()").unwrap().to_string(), "[fs_read, fs_write, env, net, clock, mocks]");
+        assert!(engine.eval("sys.limits.max_depth").unwrap() == 50);
+        assert!(engine.eval("sys.limits.fuel").unwrap().is_null());
+        assert!(engine.eval("sys.permissions.net").unwrap() == true);
+        assert!(engine.eval("sys.permissions.fs_write").unwrap() == false);
+        #[cfg(feature = "msgpack")]
+        assert!(engine.eval("sys.features.msgpack").unwrap() == true);
+        #[cfg(not(feature = "sqlite"))]
+        assert!(engine.eval("sys.features.sqlite").unwrap() == false);
+
+        // It reflects the engine as it is when it is read
+        engine.set_fuel(Some(1_000));
+        assert!(engine.eval("sys.limits.fuel > 900").unwrap() == true);
+        engine.set_fuel(None);
+
+        // Changing it changes nothing, and declaring a variable of the same name shadows it
+        engine.exec("sys.limits.max_depth = 1;").unwrap();
+        assert!(engine.eval("sys.limits.max_depth").unwrap() == 50);
+        engine.exec(r#"var sys = "mine";"#).unwrap();
+        assert!(engine.eval("sys").unwrap() == "mine");
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+};
+use forge::{Engine, ExecResult, Io};
+
+struct TermIo {
+    terminal: bool,
+    output: Rc<RefCell<Vec<String>>>,
+}
+
+impl Io for TermIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(String::new())
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.output.borrow_mut().push(s);
+        Ok(())
+    }
+
+    fn is_terminal(&self) -> bool {
+        self.terminal
+    }
+
+    fn terminal_width(&self) -> Option<usize> {
+        if self.terminal { Some(120) } else { None }
+    }
+}
+
+fn engine(terminal: bool) -> (Engine, Rc<RefCell<Vec<String>>>) {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let engine = Engine::build()
+        .with_io(TermIo { terminal, output: output.clone() })
+        .finish();
+    (engine, output)
+}
+
+#[test]
+fn styles_on_terminal() {
+    let (mut engine, output) = engine(true);
+    engine.exec(r#"
+        var warning = term.color("red", "careful");
+        var title = term.bold(42);
+        var width = term.width();
+        term.clear();
+    "#).unwrap();
+    assert!(engine.take("warning").unwrap() == "\x1b[31mcareful\x1b[0m");
+    assert!(engine.take("title").unwrap() == "\x1b[1m42\x1b[0m");
+    assert!(engine.take("width").unwrap() == 120.0);
+    assert_eq!(output.borrow().len(), 1);
+
+    let err = engine.exec(r#"term.color("mauve", "x");"#).unwrap_err().to_string();
+    assert!(err.contains("'mauve' is not a color."));
+}
+
+#[test]
+fn no_op_without_terminal() {
+    let (mut engine, output) = engine(false);
+    engine.exec(r#"
+        var warning = term.color("red", "careful");
+        var title = term.bold("Report");
+        var width = term.width();
+        term.clear();
+    "#).unwrap();
+    assert!(engine.take("warning").unwrap() == "careful");
+    assert!(engine.take("title").unwrap() == "Report");
+    assert!(engine.take("width").unwrap() == 80.0);
+    assert!(output.borrow().is_empty());
+}
+
+#[test]
+fn progress_on_terminal() {
+    let (mut engine, output) = engine(true);
+    engine.exec(r#"
+        var bar = term.progress(4);
+        bar.tick();
+        bar.tick(2);
+        bar.finish();
+        bar.tick();
+    "#).unwrap();
+    let output = output.borrow();
+    assert_eq!(output.len(), 3);
+    assert!(output[0].starts_with("[##########"));
+    assert!(output[0].ends_with("] 1/4 (25%)"));
+    assert!(output[1].starts_with("\x1b[1A\x1b[2K["));
+    assert!(output[1].ends_with("] 3/4 (75%)"));
+    assert!(output[2].ends_with("] 4/4 (100%)"));
+    assert!(!output[2].contains("  "));
+}
+
+#[test]
+fn progress_without_terminal() {
+    let (mut engine, output) = engine(false);
+    engine.exec(r#"
+        var bar = term.progress(10);
+        for i in 0..10 {
+            bar.tick();
+        }
+        var shown = "{bar}";
+        bar.finish();
+        bar.finish();
+    "#).unwrap();
+    assert!(engine.take("shown").unwrap() == "<progress 10/10>");
+    assert_eq!(*output.borrow(), vec![String::from("10/10 (100%)")]);
+    assert!(engine.exec("term.progress(-1);").is_err());
+}
+
+#[test]
+fn tables() {
+    let (mut engine, _) = engine(false);
+
+    let table = engine.eval(r#"term.table([["alice", 30], ["bob", 4]], ["name", "age"])"#).unwrap();
+    assert!(table == "name   age\n-----  ---\nalice   30\nbob      4");
+
+    let table = engine.eval(r#"term.table([["name": "carol", "score": 9.5], ["name": "dan", "score": 10, "extra": true]])"#).unwrap();
+    assert!(table == "extra  name   score\n-----  -----  -----\n       carol    9.5\ntrue   dan       10");
+
+    let table = engine.eval(r#"term.table([[1, 2, 3], ["x"]])"#).unwrap();
+    assert!(table == "1  2  3\nx");
+
+    assert!(engine.eval(r#"term.table([1, 2])"#).is_err());
+    assert!(engine.eval(r#"term.table("rows")"#).is_err());
+}
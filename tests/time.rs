@@ -0,0 +1,88 @@
+use forge::{Engine, DateTime, Duration, ExecMode};
+
+#[test]
+fn datetime_parsing() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var t = datetime.parse("2019-02-28T23:30:05.25+01:00");
+        var fields = [t.year, t.month, t.day, t.hour, t.minute, t.second, t.weekday];
+        var text = "{t}";
+        var utc = "{t.to_offset(duration.hours(0))}";
+        var formatted = t.format("%a %d %b %Y, %H:%M:%S.%f %z");
+    "#).unwrap();
+
+    assert_eq!(engine.eval("fields").unwrap().to_string(), "[2019, 2, 28, 23, 30, 5, 4]");
+    assert!(engine.take("text").unwrap() == "2019-02-28T23:30:05.25+01:00");
+    assert!(engine.take("utc").unwrap() == "2019-02-28T22:30:05.25Z");
+    assert!(engine.take("formatted").unwrap() == "Thu 28 Feb 2019, 23:30:05.250 +01:00");
+
+    for bad in &["2019-02-29T00:00:00Z", "2019-01-01 00:00:00", "2019-1-01T00:00:00Z", "2019-01-01T24:00:00Z"] {
+        assert!(engine.eval(&format!("datetime.parse(\"{}\")", bad)).is_err());
+    }
+
+    let t = engine.eval(r#"datetime.from_unix(86400 * 365)"#).unwrap();
+    assert_eq!(t.downcast_ref::<DateTime>().unwrap().to_rfc3339(), "1971-01-01T00:00:00Z");
+}
+
+#[test]
+fn time_arithmetic() {
+    let mut engine = Engine::default();
+
+    engine.exec(r#"
+        var start = datetime.parse("2020-02-28T12:00:00Z");
+        var end = start + duration.days(1) + duration.hours(13);
+        var elapsed = end - start;
+        var later = end > start;
+        var same = datetime.parse("2020-03-01T02:00:00+01:00") == end;
+        var half = elapsed / 2;
+        var ratio = elapsed / duration.minutes(30);
+    "#).unwrap();
+
+    assert_eq!(engine.eval("end").unwrap().to_string(), "2020-03-01T01:00:00Z");
+    assert_eq!(engine.eval("elapsed").unwrap().to_string(), "1d 13h");
+    assert_eq!(engine.eval("half").unwrap().to_string(), "18h 30m");
+    assert_eq!(engine.eval("duration.millis(-1500)").unwrap().to_string(), "-1.5s");
+    // Ints are not rounded through floats, however large
+    assert_eq!(engine.eval("duration.days(100000000000000)").unwrap().to_string(), "100000000000000d");
+    assert_eq!(engine.eval("duration.seconds(9007199254740993)").unwrap().to_string(), engine.eval("duration.seconds(9007199254740992) + duration.seconds(1)").unwrap().to_string());
+    assert_eq!(engine.eval("duration.hours(1.5)").unwrap().to_string(), "1h 30m");
+    assert!(engine.take("later").unwrap() == true);
+    assert!(engine.take("same").unwrap() == true);
+    assert!(engine.take("ratio").unwrap() == 74.0);
+    assert!(engine.eval("elapsed.hours").unwrap() == 37.0);
+    assert_eq!(engine.eval("elapsed").unwrap().downcast_ref::<Duration>().unwrap().as_secs_f64(), 37.0 * 3600.0);
+
+    assert!(engine.eval("start + 1").is_err());
+    assert!(engine.eval("start < duration.days(1)").is_err());
+}
+
+#[test]
+fn clock() {
+    for &mode in &[ExecMode::Interpret, ExecMode::Bytecode] {
+        let mut engine = Engine::build().with_exec_mode(mode).allow_clock().finish();
+
+        engine.exec(r#"
+            var start = time.elapsed();
+            var total = 0;
+            for i in 0..1000 { total += i; }
+            var taken = time.elapsed() - start;
+            var year = time.format("%Y");
+        "#).unwrap();
+        assert!(engine.eval("taken >= duration.seconds(0)").unwrap() == true);
+        assert!(engine.eval("taken.seconds < 60").unwrap() == true);
+        assert!(engine.eval("time.unix() > 1500000000").unwrap() == true);
+        assert!(engine.eval("time.now().year >= 2020").unwrap() == true);
+        assert!(engine.eval(r#"year == "{time.now().year}""#).unwrap() == true);
+        assert!(engine.eval("sys.permissions.clock").unwrap() == true);
+        assert!(engine.eval(r#"time.format("%Q")"#).is_err());
+
+        // Without the permission, code cannot tell when or for how long it is running
+        let mut engine = Engine::build().with_exec_mode(mode).finish();
+        for code in &["time.now()", "time.unix()", "time.elapsed()", r#"time.format("%Y")"#, "datetime.now()"] {
+            let msg = engine.eval(code).unwrap_err().to_string();
+            assert!(msg.contains("clock"), "{}", msg);
+        }
+        assert!(engine.eval("sys.permissions.clock").unwrap() == false);
+    }
+}
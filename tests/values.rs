@@ -0,0 +1,46 @@
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+};
+use forge::{Engine, Value};
+
+#[test]
+fn host_values() {
+    let mut engine = Engine::default();
+
+    let mut config = HashMap::new();
+    config.insert("scale", Value::from(2.5));
+    config.insert("label", Value::from("total"));
+    engine.set("config", config);
+    engine.set("items", vec![1i64, 2, 3]);
+    engine.set("missing", None::<f64>);
+
+    engine.exec(r#"
+        var sum = 0;
+        for x in items {
+            sum += x * config["scale"];
+        }
+        var result = [config["label"]: sum, "empty": missing == null];
+    "#).unwrap();
+
+    let result = HashMap::<String, Value>::try_from(engine.get("result").unwrap()).unwrap();
+    assert_eq!(f64::try_from(result["total"].clone()).unwrap(), 15.0);
+    assert_eq!(result["empty"].as_bool(), Some(true));
+
+    let items = Vec::<Value>::try_from(engine.get("items").unwrap()).unwrap();
+    assert_eq!(items.iter().filter_map(Value::as_number).collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+    assert!(engine.get("missing").unwrap().is_null());
+    assert!(engine.get("undeclared").is_none());
+}
+
+#[test]
+fn failed_conversions() {
+    let mut engine = Engine::default();
+
+    assert!(f64::try_from(engine.eval("\"5\"").unwrap()).is_err());
+    assert!(String::try_from(engine.eval("5").unwrap()).is_err());
+    assert_eq!(String::try_from(engine.eval("\"5\"").unwrap()).unwrap(), "5");
+    // Map keys must be strings
+    assert!(HashMap::<String, Value>::try_from(engine.eval("[1: 2]").unwrap()).is_err());
+    assert_eq!(engine.eval("'x'").unwrap().as_char(), Some('x'));
+}
@@ -0,0 +1,265 @@
+use std::{
+    rc::Rc,
+    cell::RefCell,
+};
+use forge::{Engine, ExecMode, ExecResult, Io};
+
+struct CaptureIo(Rc<RefCell<Vec<String>>>);
+
+impl Io for CaptureIo {
+    fn input(&mut self, _s: String) -> ExecResult<String> {
+        Ok(String::from("7"))
+    }
+
+    fn print(&mut self, s: String) -> ExecResult<()> {
+        self.0.borrow_mut().push(s);
+        Ok(())
+    }
+}
+
+fn run(mode: ExecMode, code: &str) -> Vec<String> {
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let mut engine = Engine::build()
+        .with_io(CaptureIo(output.clone()))
+        .with_exec_mode(mode)
+        .finish();
+    engine.exec(code).unwrap();
+    let lines = output.borrow().clone();
+    lines
+}
+
+fn same_output(code: &str) {
+    let interpreted = run(ExecMode::Interpret, code);
+    let compiled = run(ExecMode::Bytecode, code);
+    assert_eq!(interpreted, compiled);
+    assert!(interpreted.len() > 0);
+}
+
+#[test]
+fn loops_and_scopes() {
+    same_output(r#"
+        var total = 0;
+        var i = 0;
+        while i < 100 {
+            var sq = i * i;
+            total += sq;
+            i += 1;
+        }
+        print total;
+
+        for x in 1..4 {
+            for y in [10, 20] {
+                print x * y;
+            }
+        }
+    "#);
+}
+
+#[test]
+fn branches_and_collections() {
+    same_output(r#"
+        var xs = [0; 5];
+        for i in 0..5 {
+            if i % 2 == 0 {
+                xs[i] = "even";
+            } else if i == 3 {
+                xs[i] = "three";
+            } else {
+                xs[i] = i as str;
+            }
+        }
+        print xs;
+        var m = ["a": 1];
+        m["b"] = 2;
+        print m["a"] + m["b"];
+        print "Hello"[1..3] + '!';
+        print (input "? ") + 1;
+    "#);
+}
+
+#[test]
+fn functions_and_returns() {
+    same_output(r#"
+        var square = |x| { return x * x; };
+        var add = |a, b| { return a + b; };
+        print add(square(3), 1);
+        for i in 0..10 {
+            if i == 2 {
+                return null;
+            }
+            print i;
+        }
+        print "after";
+    "#);
+}
+
+#[test]
+fn expression_eval() {
+    let mut engine = Engine::build()
+        .with_exec_mode(ExecMode::Bytecode)
+        .finish();
+
+    assert!(engine.eval(r#"5 * 2 + 1"#).unwrap() == 11.0);
+    assert!(engine.eval(r#"!(4 == 6) and true"#).unwrap() == true);
+    assert!(engine.eval(r#""foo" + 1"#).unwrap() == "foo1");
+}
+
+#[test]
+fn interpolation() {
+    same_output(r#"
+        var xs = [1, 2, 3];
+        for x in xs {
+            print "{x} squared is {x * x}";
+        }
+        print "nested {"{xs}"}";
+    "#);
+}
+
+#[test]
+fn try_catch() {
+    same_output(r#"
+        for i in 0..4 {
+            try {
+                for x in [1, 2] {
+                    if i == x { print i + []; }
+                }
+                print "ok {i}";
+            } catch e {
+                print "caught {i} at line {e.line}";
+            }
+        }
+        try { print missing; } catch err { print err.col; }
+    "#);
+}
+
+#[test]
+fn closures() {
+    same_output(r#"
+        var total = 0;
+        var adders = [];
+        for x in 1..4 {
+            var add = |y| { total += x * y; };
+            adders += add;
+        }
+        for add in adders {
+            add(10);
+        }
+        print total;
+
+        fn make() {
+            var items = [];
+            return |item| { items += item; return items; };
+        }
+        var push = make();
+        push(1);
+        print push(2);
+    "#);
+}
+
+#[test]
+fn element_and_member_assignment() {
+    same_output(r#"
+        var counts = ["apple": 0];
+        for word in ["apple", "pear", "apple"] {
+            if counts[word] == null {
+                counts[word] = 0;
+            }
+            counts[word] += 1;
+        }
+        print counts["apple"];
+        print counts.pear;
+
+        var calls = 0;
+        var pick = || { calls += 1; return -1; };
+        var xs = [1, 2, 3];
+        xs[pick()] *= 10;
+        print xs;
+        print calls;
+
+        var player = ["name": "Ada", "score": 10];
+        player.score -= 4;
+        player.name += "!";
+        player.level = 2;
+        player.level %= 2;
+        print player.score;
+        print player.name;
+        print player.level;
+    "#);
+}
+
+#[test]
+fn function_bodies() {
+    same_output(r#"
+        fn fib(n) {
+            var a = 0;
+            var b = 1;
+            for i in 0..n {
+                var next = a + b;
+                a = b;
+                b = next;
+            }
+            return a;
+        }
+        print fib(30);
+
+        fn shadow(x) {
+            x += 1;
+            if x > 1 {
+                var x = "inner";
+                print x;
+            }
+            return x;
+        }
+        print shadow(5);
+
+        fn counter(start = 0, step = |n| { return n + 1; }) {
+            var n = start;
+            return || { n = step(n); return n; };
+        }
+        var next = counter(10);
+        next();
+        print next();
+
+        fn risky(x) {
+            try {
+                return x + [];
+            } catch e {
+                return "caught at line {e.line}";
+            }
+        }
+        print risky(1);
+
+        fn count(n, total = 0) {
+            if n == 0 { return total; }
+            return count(n - 1, total + n);
+        }
+        print count(100000);
+
+        fn fails(x) {
+            var y = x * 2;
+            return y + missing;
+        }
+        try { fails(1); } catch e { print "{e.line}:{e.col}"; }
+    "#);
+}
+
+// Errors are described the same way, with the same locations, however the code that raised them was run
+fn same_error(code: &str, fuel: Option<u64>) {
+    let errors = [ExecMode::Interpret, ExecMode::Bytecode].iter().map(|mode| {
+        let mut engine = Engine::build().with_exec_mode(*mode).finish();
+        engine.set_fuel(fuel);
+        engine.exec(code).unwrap_err().to_string()
+    }).collect::<Vec<_>>();
+    assert_eq!(errors[0], errors[1], "{}", code);
+}
+
+#[test]
+fn errors_in_functions() {
+    same_error("fn f() { try { while true {} } catch e {} return 1; } f();", Some(1000));
+    same_error("fn f() { print 1 + []; } f();", None);
+    same_error("fn f() { var x = [1, missing]; } f();", None);
+    same_error("fn f() { print \"{(1).x}\"; } f();", None);
+    same_error("fn f() { var xs = []; xs[3] += 1; } f();", None);
+    same_error("fn f() { var m = 1; m.x = [5()]; } f();", None);
+    same_error("fn f(a) { return a; } fn g() { f(1, 2); return 1; } g();", None);
+}
@@ -0,0 +1,102 @@
+#![cfg(feature = "net")]
+
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+use forge::Engine;
+
+// The engine is seeded, so the handshake key is always the same. The answer is
+// base64(sha1(key + "258EAFA5-E914-47DA-95CA-C5AB0DC85B11")).
+const KEY: &str = "paiijJVYTCHcGUGevmqnhA==";
+const ACCEPT: &str = "pAMwsuLeAiTXZm3tCuFb4+p6xmI=";
+
+fn read_frame(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+    let mut head = [0; 2];
+    stream.read_exact(&mut head).unwrap();
+    assert!(head[1] & 0x80 != 0, "client frames must be masked");
+    let len = (head[1] & 0x7f) as usize;
+    let mut mask = [0; 4];
+    stream.read_exact(&mut mask).unwrap();
+    let mut payload = vec![0; len];
+    stream.read_exact(&mut payload).unwrap();
+    payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+    (head[0] & 0x0f, payload)
+}
+
+fn write_frame(stream: &mut TcpStream, fin: bool, opcode: u8, payload: &[u8]) {
+    stream.write_all(&[if fin { 0x80 } else { 0 } | opcode, payload.len() as u8]).unwrap();
+    stream.write_all(payload).unwrap();
+}
+
+// Answers the handshake, then echoes one message back in two fragments with a ping between them, and closes
+fn serve(listener: TcpListener) {
+    let (mut stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        if line.trim().is_empty() {
+            break;
+        }
+        if line.starts_with("Sec-WebSocket-Key:") {
+            key = Some(line[18..].trim().to_string());
+        }
+    }
+    assert_eq!(key.as_ref().map(|k| k.as_str()), Some(KEY));
+    write!(stream, "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", ACCEPT).unwrap();
+
+    let (opcode, msg) = read_frame(&mut stream);
+    assert_eq!(opcode, 1);
+    let (first, rest) = msg.split_at(msg.len() / 2);
+    write_frame(&mut stream, false, 1, first);
+    write_frame(&mut stream, true, 9, b"ping");
+    write_frame(&mut stream, true, 0, rest);
+    assert_eq!(read_frame(&mut stream), (0xa, b"ping".to_vec()));
+
+    let (opcode, bytes) = read_frame(&mut stream);
+    assert_eq!(opcode, 2);
+    write_frame(&mut stream, true, 2, &bytes);
+
+    write_frame(&mut stream, true, 8, &1000u16.to_be_bytes());
+    assert_eq!(read_frame(&mut stream).0, 8);
+}
+
+#[test]
+fn echo() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = thread::spawn(move || serve(listener));
+
+    let mut engine = Engine::build().with_seed(7).allow_net().finish();
+    engine.set("url", format!("ws://127.0.0.1:{}/echo", port));
+    engine.exec(r#"
+        var sock = ws.connect(url);
+        sock.send("hello, websocket");
+        var text = sock.recv();
+        sock.send([1, 2, 255]);
+        var bytes = sock.recv();
+        var closed = sock.recv();
+    "#).unwrap();
+    server.join().unwrap();
+
+    assert!(engine.take("text").unwrap() == "hello, websocket");
+    let bytes = engine.take("bytes").unwrap();
+    assert_eq!(engine.display(&bytes).unwrap(), "[1, 2, 255]");
+    assert!(engine.take("closed").unwrap().is_null());
+    assert!(engine.exec(r#"sock.send("too late");"#).unwrap_err().to_string().contains("has been closed"));
+}
+
+#[test]
+fn errors() {
+    let mut engine = Engine::default();
+    assert!(engine.exec(r#"ws.connect("ws://127.0.0.1:1");"#).unwrap_err().to_string().contains("'net' permission"));
+
+    let mut engine = Engine::build().allow_net().finish();
+    let err = engine.exec(r#"ws.connect("http://example.com");"#).unwrap_err().to_string();
+    assert!(err.contains("is not a WebSocket URL"), "{}", err);
+    let err = engine.exec(r#"ws.connect("wss://example.com");"#).unwrap_err().to_string();
+    assert!(err.contains("not yet supported"), "{}", err);
+}